@@ -0,0 +1,143 @@
+// Counts heap allocations, rather than time, per strategy evaluation on
+// both the slice path and the incremental path. The thresholds are low
+// enough that every call returns a signal.
+
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use hft_trading_bot::fixtures::{order_book, price_history};
+use hft_trading_bot::indicators::IndicatorHub;
+use hft_trading_bot::symbols::Symbol;
+use hft_trading_bot::{MeanReversionStrategy, MomentumStrategy, TradingStrategy};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const HISTORY: usize = 1000;
+const SYMBOL: &str = "SOL/USDT";
+
+// The system allocator, counting every allocation it makes
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// Allocations made between start and end. The extra count for each sample
+// keeps criterion's statistics defined when the code under test makes none.
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> u64 {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, started: u64) -> u64 {
+        ALLOCATIONS.load(Ordering::Relaxed) - started + 1
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        a + b
+    }
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn to_f64(&self, value: &u64) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationFormatter
+    }
+}
+
+struct AllocationFormatter;
+
+impl ValueFormatter for AllocationFormatter {
+    fn scale_values(&self, _typical: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical: f64,
+        _throughput: &Throughput,
+        _values: &mut [f64],
+    ) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+fn bench_allocations(c: &mut Criterion<Allocations>) {
+    let prices = price_history(SYMBOL, HISTORY, 1);
+    let book = order_book(SYMBOL, prices[HISTORY - 1].price, 10, 1);
+    let strategies: [(&str, Box<dyn TradingStrategy>); 2] = [
+        (
+            "momentum_200",
+            Box::new(MomentumStrategy::with_params(200, 0.0, 0.0, 100.0).unwrap()),
+        ),
+        (
+            "mean_reversion_200",
+            Box::new(MeanReversionStrategy::new(200, 0.0)),
+        ),
+    ];
+    let mut hub = IndicatorHub::new();
+    hub.register_window(200);
+    let symbol = Symbol::intern(SYMBOL);
+    for price in &prices {
+        hub.update(&symbol, price.price, price.volume);
+    }
+    let indicators = hub.get(symbol.id()).unwrap();
+
+    let mut group = c.benchmark_group("allocations_per_signal");
+    for (name, strategy) in &strategies {
+        group.bench_function(format!("{}/analyze", name), |b| {
+            b.iter(|| strategy.analyze(black_box(&prices), black_box(&book)))
+        });
+        group.bench_function(format!("{}/analyze_incremental", name), |b| {
+            b.iter(|| {
+                strategy.analyze_incremental(
+                    black_box(&prices),
+                    black_box(indicators),
+                    black_box(&book),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn allocations() -> Criterion<Allocations> {
+    Criterion::default().with_measurement(Allocations)
+}
+
+criterion_group! {
+    name = benches;
+    config = allocations();
+    targets = bench_allocations
+}
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+// Compares recomputing a rolling mean from the price slice on every tick (the
+// original strategy path) with reading it from an incrementally-updated window,
+// and times the push path every tick takes through the indicator hub.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use hft_trading_bot::fixtures::price_series;
+use hft_trading_bot::indicators::{IndicatorHub, RollingWindow};
+use hft_trading_bot::symbols::Symbol;
+
+const WINDOW: usize = 200;
+const HISTORY: usize = 1000;
+
+fn bench_rolling_mean(c: &mut Criterion) {
+    let history = price_series(HISTORY, 1);
+    let ticks = price_series(HISTORY * 2, 2);
+
+    let mut group = c.benchmark_group("rolling_mean_200");
+
+    group.bench_function("slice_recompute", |b| {
+        b.iter(|| {
+            // What analyze does per tick: collect the window, then sum it
+            let recent: Vec<f64> = black_box(&history)
+                .iter()
+                .rev()
+                .take(WINDOW)
+                .copied()
+                .collect();
+            black_box(recent.iter().sum::<f64>() / recent.len() as f64)
+        })
+    });
+
+    group.bench_function("incremental", |b| {
+        let mut window = RollingWindow::new(WINDOW);
+        for &p in &history {
+            window.push(p);
+        }
+        let mut i = 0;
+        b.iter(|| {
+            window.push(ticks[i]);
+            i = (i + 1) % ticks.len();
+            black_box(window.mean())
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_push(c: &mut Criterion) {
+    let ticks = price_series(HISTORY * 2, 3);
+    let mut group = c.benchmark_group("push");
+
+    group.bench_function("rolling_window_200", |b| {
+        let mut window = RollingWindow::new(WINDOW);
+        let mut i = 0;
+        b.iter(|| {
+            window.push(black_box(ticks[i]));
+            i = (i + 1) % ticks.len();
+        })
+    });
+
+    // What record_price does per tick with the default strategies' windows
+    group.bench_function("indicator_hub", |b| {
+        let mut hub = IndicatorHub::new();
+        for window in [10, 20, WINDOW] {
+            hub.register_window(window);
+        }
+        hub.register_ema(20);
+        let sol = Symbol::intern("SOL/USDT");
+        let mut i = 0;
+        b.iter(|| {
+            hub.update(&sol, black_box(ticks[i]), 1_000.0);
+            i = (i + 1) % ticks.len();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_rolling_mean, bench_push);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+// Times the OrderBook helpers strategies and the risk manager call on
+// every evaluation, on a 20-level book.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use hft_trading_bot::OrderBook;
+use hft_trading_bot::fixtures::order_book;
+
+const LEVELS: usize = 20;
+
+fn bench_helpers(c: &mut Criterion) {
+    let book = order_book("SOL/USDT", 100.0, LEVELS, 1);
+    let mut group = c.benchmark_group("orderbook");
+
+    group.bench_function("mid_and_spread_bps", |b| {
+        b.iter(|| (black_box(&book).mid_price(), black_box(&book).spread_bps()))
+    });
+    group.bench_function("imbalance_5", |b| {
+        b.iter(|| black_box(&book).imbalance(black_box(5)))
+    });
+    group.bench_function("total_depth_all", |b| {
+        b.iter(|| black_box(&book).total_depth(black_box(LEVELS)))
+    });
+    group.bench_function("microprice", |b| b.iter(|| black_box(&book).microprice()));
+
+    // Levels arrive unordered from some venues, so new() sorts them
+    let mut shuffled = book.clone();
+    shuffled.bids.reverse();
+    shuffled.asks.reverse();
+    group.bench_function("new_unsorted", |b| {
+        b.iter(|| {
+            OrderBook::new(
+                shuffled.symbol.clone(),
+                shuffled.bids.clone(),
+                shuffled.asks.clone(),
+                shuffled.timestamp,
+            )
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_helpers);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+// Times RiskManager::validate_order with positions open in many symbols, so
+// the exposure checks have a full map to walk. The risk manager's locks
+// don't need a runtime, so a plain executor drives it.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use futures::executor::block_on;
+use hft_trading_bot::fixtures::{market_order, order_book, positions, symbol};
+use hft_trading_bot::{OrderSide, Price, RiskManager, RiskParams};
+use std::collections::HashMap;
+
+fn bench_validate_order(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate_order");
+    for open in [10, 100, 1000] {
+        let risk = RiskManager::new(RiskParams {
+            max_gross_exposure: f64::MAX,
+            max_net_exposure: f64::MAX,
+            ..RiskParams::default()
+        });
+        let held = positions(open, 1);
+        block_on(async {
+            for position in &held {
+                risk.marks().record_price(&Price {
+                    symbol: position.symbol.to_string(),
+                    price: position.avg_price,
+                    timestamp: 0,
+                    volume: 0.0,
+                });
+            }
+            let cash = HashMap::from([("USDT".to_string(), 1_000_000.0)]);
+            risk.restore(held, cash, 0.0, 0).await;
+        });
+        let traded = symbol(0);
+        let book = order_book(&traded, 100.0, 10, 1);
+        let order = market_order(&traded, OrderSide::Buy, 1.0);
+
+        group.bench_function(format!("{}_positions", open), |b| {
+            b.iter(|| {
+                block_on(risk.validate_order(black_box(&order), 100.0, black_box(&book)))
+                    .expect("the fixture order is within limits")
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_validate_order);
+criterion_main!(benches);
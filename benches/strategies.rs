@@ -0,0 +1,42 @@
+// Times a full analyze pass of each built-in strategy over a 1000-price
+// history, the slice path evaluate_symbol falls back to.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use hft_trading_bot::fixtures::{order_book, price_history};
+use hft_trading_bot::{MeanReversionStrategy, MomentumStrategy, TradingStrategy};
+
+const HISTORY: usize = 1000;
+
+fn bench_analyze(c: &mut Criterion) {
+    let prices = price_history("SOL/USDT", HISTORY, 1);
+    let book = order_book("SOL/USDT", prices[HISTORY - 1].price, 10, 1);
+    let strategies: [(&str, Box<dyn TradingStrategy>); 4] = [
+        (
+            "momentum_10",
+            Box::new(MomentumStrategy::new(10, 0.02).unwrap()),
+        ),
+        (
+            "momentum_200",
+            Box::new(MomentumStrategy::new(200, 0.02).unwrap()),
+        ),
+        (
+            "mean_reversion_20",
+            Box::new(MeanReversionStrategy::new(20, 0.03)),
+        ),
+        (
+            "mean_reversion_200",
+            Box::new(MeanReversionStrategy::new(200, 0.03)),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("analyze_1000");
+    for (name, strategy) in &strategies {
+        group.bench_function(*name, |b| {
+            b.iter(|| strategy.analyze(black_box(&prices), black_box(&book)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_analyze);
+criterion_main!(benches);
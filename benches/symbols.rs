@@ -0,0 +1,70 @@
+// Per-symbol lookups as an evaluation pass over 20 symbols makes them:
+// the price history keyed by name, as it used to be, against the same
+// history in a SymbolMap keyed by interned id. Also times handing a
+// symbol's key on by clone.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use hft_trading_bot::Price;
+use hft_trading_bot::fixtures::{price_history, symbol};
+use hft_trading_bot::symbols::{Symbol, SymbolMap};
+use std::collections::HashMap;
+
+const SYMBOLS: usize = 20;
+const HISTORY: usize = 100;
+
+fn bench_lookup(c: &mut Criterion) {
+    let names: Vec<String> = (0..SYMBOLS).map(symbol).collect();
+    let interned: Vec<_> = names.iter().map(|name| Symbol::intern(name)).collect();
+
+    let mut by_name: HashMap<String, Vec<Price>> = HashMap::new();
+    let mut by_id: SymbolMap<Vec<Price>> = SymbolMap::new();
+    for (i, (name, symbol)) in names.iter().zip(&interned).enumerate() {
+        let history = price_history(name, HISTORY, i as u64);
+        by_name.insert(name.clone(), history.clone());
+        by_id.insert(symbol.id(), history);
+    }
+
+    let mut group = c.benchmark_group("symbol_lookup_20");
+    group.bench_function("hashmap_string", |b| {
+        b.iter(|| {
+            names
+                .iter()
+                .filter_map(|name| by_name.get(black_box(name.as_str())))
+                .map(Vec::len)
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("symbol_map", |b| {
+        b.iter(|| {
+            interned
+                .iter()
+                .filter_map(|symbol| by_id.get(black_box(symbol.id())))
+                .map(Vec::len)
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("symbol_clone_20");
+    group.bench_function("string", |b| {
+        b.iter(|| names.iter().for_each(|name| drop(black_box(name.clone()))))
+    });
+    group.bench_function("symbol", |b| {
+        b.iter(|| {
+            interned.iter().for_each(|symbol| {
+                black_box(*symbol);
+            })
+        })
+    });
+    group.bench_function("symbol_id", |b| {
+        b.iter(|| {
+            interned.iter().for_each(|symbol| {
+                black_box(symbol.id());
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup);
+criterion_main!(benches);
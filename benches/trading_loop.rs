@@ -0,0 +1,68 @@
+// Times evaluation passes of the trading loop over 1, 20 and 100 symbols: a
+// replay of a tick and a book per symbol per second through the default
+// strategies, each pass reading the book the polling task cached. The
+// virtual clock follows the data, so only the passes themselves take time.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use hft_trading_bot::backtest::Backtester;
+use hft_trading_bot::config::BotConfig;
+use hft_trading_bot::fixtures::{order_book, price_history, symbol};
+use hft_trading_bot::recording::{MarketEvent, RecordedEvent};
+
+const SYMBOL_COUNTS: [usize; 3] = [1, 20, 100];
+const SECONDS: usize = 100;
+
+fn replay_events(symbols: usize) -> Vec<RecordedEvent> {
+    let histories: Vec<_> = (0..symbols)
+        .map(|i| price_history(&symbol(i), SECONDS, i as u64))
+        .collect();
+    let mut events = Vec::with_capacity(2 * symbols * SECONDS);
+    for second in 0..SECONDS {
+        for (i, history) in histories.iter().enumerate() {
+            let price = &history[second];
+            let received_at_ms = price.timestamp * 1_000;
+            let mut book = order_book(
+                &price.symbol,
+                price.price,
+                10,
+                (i * SECONDS + second) as u64,
+            );
+            book.timestamp = price.timestamp;
+            events.push(RecordedEvent {
+                received_at_ms,
+                event: MarketEvent::Price(price.clone()),
+            });
+            events.push(RecordedEvent {
+                received_at_ms,
+                event: MarketEvent::Book(book),
+            });
+        }
+    }
+    events
+}
+
+fn bench_evaluation_passes(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("trading_loop");
+    group.sample_size(10);
+    for symbols in SYMBOL_COUNTS {
+        let config = BotConfig {
+            symbols: (0..symbols).map(symbol).collect(),
+            ..BotConfig::default()
+        };
+        let backtester = Backtester::new(&config);
+        let events = replay_events(symbols);
+        group.throughput(Throughput::Elements((symbols * SECONDS) as u64));
+        group.bench_function(format!("{symbols}_symbols"), |b| {
+            b.iter(|| runtime.block_on(backtester.replay(&events)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_evaluation_passes);
+criterion_main!(benches);
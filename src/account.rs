@@ -0,0 +1,187 @@
+use crate::fees::FeeModel;
+use crate::margin::MarginConfig;
+use crate::symbols::assets;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Starting balances and trading terms for the account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountConfig {
+    // Quote asset for symbols that don't name one (no "BASE/QUOTE" form)
+    pub quote_asset: String,
+    // Cash per asset before any trading (paper mode)
+    pub balances: HashMap<String, f64>,
+    // Buying power multiplier on equity; 1.0 means cash only
+    pub leverage: f64,
+    // Commission schedule applied to fills
+    pub fees: FeeModel,
+    // Hold longs and shorts in a symbol as separate legs instead of one
+    // net position
+    pub hedge_mode: bool,
+    // Leverage and margin on perpetual futures
+    pub margin: MarginConfig,
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            quote_asset: "USDT".to_string(),
+            balances: HashMap::from([("USDT".to_string(), 10_000.0)]),
+            leverage: 1.0,
+            fees: FeeModel::default(),
+            hedge_mode: false,
+            margin: MarginConfig::default(),
+        }
+    }
+}
+
+impl AccountConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.leverage < 1.0 {
+            return Err("Account leverage must be at least 1.0".to_string());
+        }
+        self.fees.validate()?;
+        self.margin.validate()?;
+        if let Some((asset, _)) = self.balances.iter().find(|(_, b)| **b < 0.0) {
+            return Err(format!("Starting balance for {} is negative", asset));
+        }
+        Ok(())
+    }
+}
+
+// Cash balances per quote asset. Fills move cash; positions themselves are
+// tracked by the risk manager. Balances in different quote assets are
+// converted into the reporting currency when summing equity (see
+// currency.rs).
+// Perpetual futures are margined rather than paid for: only their realized
+// PnL, fees and funding move cash.
+#[derive(Debug, Clone)]
+pub struct Account {
+    quote_asset: String,
+    balances: HashMap<String, f64>,
+    leverage: f64,
+    fees: FeeModel,
+    fees_paid: f64,
+    margin: MarginConfig,
+    // Symbols traded as perpetual futures
+    perps: HashSet<String>,
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Self::new(&AccountConfig::default())
+    }
+}
+
+impl Account {
+    pub fn new(config: &AccountConfig) -> Self {
+        Self {
+            quote_asset: config.quote_asset.clone(),
+            balances: config.balances.clone(),
+            leverage: config.leverage,
+            fees: config.fees.clone(),
+            fees_paid: 0.0,
+            margin: config.margin.clone(),
+            perps: HashSet::new(),
+        }
+    }
+
+    // Trades these symbols as perpetual futures
+    pub fn with_perps(mut self, symbols: impl IntoIterator<Item = String>) -> Self {
+        self.perps.extend(symbols);
+        self
+    }
+
+    pub fn is_perp(&self, symbol: &str) -> bool {
+        self.perps.contains(symbol)
+    }
+
+    pub fn has_perps(&self) -> bool {
+        !self.perps.is_empty()
+    }
+
+    pub fn margin(&self) -> &MarginConfig {
+        &self.margin
+    }
+
+    // The asset a symbol is priced and settled in, e.g. USDT for SOL/USDT
+    pub fn quote_for<'a>(&'a self, symbol: &'a str) -> &'a str {
+        assets(symbol).map_or(self.quote_asset.as_str(), |(_, quote)| quote)
+    }
+
+    pub fn balance(&self, asset: &str) -> f64 {
+        self.balances.get(asset).copied().unwrap_or(0.0)
+    }
+
+    pub fn balances(&self) -> &HashMap<String, f64> {
+        &self.balances
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.balances.values().sum()
+    }
+
+    pub fn leverage(&self) -> f64 {
+        self.leverage
+    }
+
+    pub fn fees(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    pub fn fees_paid(&self) -> f64 {
+        self.fees_paid
+    }
+
+    // Settles a signed fill in the symbol's quote asset: buys debit cash,
+    // sells credit it, and the fee is always debited
+    pub fn apply_fill(&mut self, symbol: &str, quantity: f64, price: f64, fee: f64) {
+        let quote = self.quote_for(symbol).to_string();
+        *self.balances.entry(quote).or_insert(0.0) -= quantity * price + fee;
+        self.fees_paid += fee;
+    }
+
+    // Settles a perp fill: the PnL it realized, already net of its fee,
+    // is all that moves cash
+    pub fn apply_perp_fill(&mut self, symbol: &str, realized: f64, fee: f64) {
+        let quote = self.quote_for(symbol).to_string();
+        *self.balances.entry(quote).or_insert(0.0) += realized;
+        self.fees_paid += fee;
+    }
+
+    // Credits a funding payment received (debits one paid)
+    pub fn apply_funding(&mut self, symbol: &str, amount: f64) {
+        let quote = self.quote_for(symbol).to_string();
+        *self.balances.entry(quote).or_insert(0.0) += amount;
+    }
+
+    // Puts back balances saved before a restart
+    pub fn restore(&mut self, balances: HashMap<String, f64>) {
+        self.balances = balances;
+    }
+
+    // Takes the venue's balances of the assets held as cash; other assets
+    // are positions, which fills keep track of
+    pub fn update_balances(&mut self, balances: &HashMap<String, f64>) {
+        for (asset, balance) in balances {
+            if let Some(local) = self.balances.get_mut(asset) {
+                *local = *balance;
+            }
+        }
+    }
+
+    // Replaces local balances with the exchange's view of them
+    pub fn reconcile(&mut self, balances: HashMap<String, f64>) {
+        for (asset, balance) in &balances {
+            let local = self.balance(asset);
+            if (local - balance).abs() > 1e-9 {
+                println!(
+                    "Balance drift on {}: local {:.4}, exchange {:.4}",
+                    asset, local, balance
+                );
+            }
+        }
+        self.balances = balances;
+    }
+}
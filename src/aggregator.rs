@@ -0,0 +1,200 @@
+use crate::{OrderSide, TradingSignal};
+use serde::{Deserialize, Serialize};
+
+// How signals from several strategies on the same symbol are combined
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum AggregationPolicy {
+    // The first strategy (in registration order) to signal decides
+    #[default]
+    FirstWins,
+    // The side with strictly more signals wins; ties produce no trade
+    MajorityVote,
+    // Sum of signed confidences; trade only if |net| exceeds the threshold
+    ConfidenceWeightedNet { threshold: f64 },
+}
+
+// A signal tagged with the strategy that produced it
+#[derive(Debug, Clone)]
+pub struct CandidateSignal {
+    pub strategy: String,
+    pub signal: TradingSignal,
+}
+
+// The single net signal for a symbol after aggregation
+#[derive(Debug, Clone)]
+pub struct AggregatedSignal {
+    pub signal: TradingSignal,
+    pub sources: Vec<String>, // Strategies that voted for the chosen side
+}
+
+pub struct SignalAggregator {
+    policy: AggregationPolicy,
+}
+
+impl SignalAggregator {
+    pub fn new(policy: AggregationPolicy) -> Self {
+        Self { policy }
+    }
+
+    pub fn policy(&self) -> &AggregationPolicy {
+        &self.policy
+    }
+
+    // Combine all signals generated for one symbol in one evaluation pass
+    pub fn aggregate(&self, candidates: &[CandidateSignal]) -> Option<AggregatedSignal> {
+        if candidates.is_empty() {
+            return None;
+        }
+
+        match self.policy {
+            AggregationPolicy::FirstWins => {
+                let first = &candidates[0];
+                Some(AggregatedSignal {
+                    signal: first.signal.clone(),
+                    sources: vec![first.strategy.clone()],
+                })
+            }
+            AggregationPolicy::MajorityVote => {
+                let buys = candidates
+                    .iter()
+                    .filter(|c| c.signal.action == OrderSide::Buy)
+                    .count();
+                let sells = candidates.len() - buys;
+
+                let side = match buys.cmp(&sells) {
+                    std::cmp::Ordering::Greater => OrderSide::Buy,
+                    std::cmp::Ordering::Less => OrderSide::Sell,
+                    std::cmp::Ordering::Equal => return None,
+                };
+
+                Self::strongest_on_side(candidates, side, None)
+            }
+            AggregationPolicy::ConfidenceWeightedNet { threshold } => {
+                let net: f64 = candidates
+                    .iter()
+                    .map(|c| match c.signal.action {
+                        OrderSide::Buy => c.signal.confidence,
+                        OrderSide::Sell => -c.signal.confidence,
+                    })
+                    .sum();
+
+                if net.abs() <= threshold {
+                    return None;
+                }
+
+                let side = if net > 0.0 {
+                    OrderSide::Buy
+                } else {
+                    OrderSide::Sell
+                };
+
+                Self::strongest_on_side(candidates, side, Some(net.abs().min(1.0)))
+            }
+        }
+    }
+
+    // Use the highest-confidence signal on the winning side as the template
+    // for the order, crediting every strategy that agreed with it
+    fn strongest_on_side(
+        candidates: &[CandidateSignal],
+        side: OrderSide,
+        confidence: Option<f64>,
+    ) -> Option<AggregatedSignal> {
+        let winners: Vec<&CandidateSignal> = candidates
+            .iter()
+            .filter(|c| c.signal.action == side)
+            .collect();
+
+        let strongest = winners
+            .iter()
+            .max_by(|a, b| a.signal.confidence.total_cmp(&b.signal.confidence))?;
+
+        let mut signal = strongest.signal.clone();
+        if let Some(confidence) = confidence {
+            signal.confidence = confidence;
+        }
+
+        Some(AggregatedSignal {
+            signal,
+            sources: winners.iter().map(|c| c.strategy.clone()).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimeInForce;
+
+    fn candidate(strategy: &str, action: OrderSide, confidence: f64) -> CandidateSignal {
+        CandidateSignal {
+            strategy: strategy.to_string(),
+            signal: TradingSignal {
+                symbol: "SOL/USDT".into(),
+                action,
+                confidence,
+                target_price: 100.0,
+                quantity: 1.0,
+                time_in_force: TimeInForce::Gtc,
+                limit_price: None,
+            },
+        }
+    }
+
+    #[test]
+    fn conflicting_signals_net_to_no_trade() {
+        let aggregator =
+            SignalAggregator::new(AggregationPolicy::ConfidenceWeightedNet { threshold: 0.2 });
+        let candidates = [
+            candidate("momentum", OrderSide::Buy, 0.7),
+            candidate("mean_reversion", OrderSide::Sell, 0.6),
+        ];
+        assert!(aggregator.aggregate(&candidates).is_none());
+    }
+
+    #[test]
+    fn net_above_threshold_trades_the_stronger_side() {
+        let aggregator =
+            SignalAggregator::new(AggregationPolicy::ConfidenceWeightedNet { threshold: 0.2 });
+        let candidates = [
+            candidate("momentum", OrderSide::Buy, 0.9),
+            candidate("mean_reversion", OrderSide::Sell, 0.3),
+        ];
+        let aggregated = aggregator.aggregate(&candidates).unwrap();
+        assert_eq!(aggregated.signal.action, OrderSide::Buy);
+        assert!((aggregated.signal.confidence - 0.6).abs() < 1e-9);
+        assert_eq!(aggregated.sources, ["momentum"]);
+    }
+
+    #[test]
+    fn majority_vote_ties_produce_no_trade() {
+        let aggregator = SignalAggregator::new(AggregationPolicy::MajorityVote);
+        let tied = [
+            candidate("a", OrderSide::Buy, 0.9),
+            candidate("b", OrderSide::Sell, 0.1),
+        ];
+        assert!(aggregator.aggregate(&tied).is_none());
+
+        let majority = [
+            candidate("a", OrderSide::Sell, 0.2),
+            candidate("b", OrderSide::Sell, 0.5),
+            candidate("c", OrderSide::Buy, 0.9),
+        ];
+        let aggregated = aggregator.aggregate(&majority).unwrap();
+        assert_eq!(aggregated.signal.action, OrderSide::Sell);
+        assert_eq!(aggregated.sources, ["a", "b"]);
+    }
+
+    #[test]
+    fn first_wins_takes_the_first_signal() {
+        let aggregator = SignalAggregator::new(AggregationPolicy::FirstWins);
+        let candidates = [
+            candidate("a", OrderSide::Sell, 0.1),
+            candidate("b", OrderSide::Buy, 0.9),
+        ];
+        let aggregated = aggregator.aggregate(&candidates).unwrap();
+        assert_eq!(aggregated.signal.action, OrderSide::Sell);
+        assert_eq!(aggregated.sources, ["a"]);
+    }
+}
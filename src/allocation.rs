@@ -0,0 +1,298 @@
+use crate::attribution::StrategyStats;
+use crate::{Order, OrderSide};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+
+// Per-strategy caps on deployed notional, on top of the global risk limits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AllocationConfig {
+    // Notional each strategy may have deployed, in the quote asset, by
+    // strategy name; a strategy without one is only held to the global
+    // limits
+    pub budgets: BTreeMap<String, f64>,
+    // What happens to an order past its strategy's budget
+    pub exceed: BudgetPolicy,
+}
+
+impl AllocationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (strategy, budget) in &self.budgets {
+            if !(budget.is_finite() && *budget > 0.0) {
+                return Err(format!(
+                    "Allocation budget for {} must be positive",
+                    strategy
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPolicy {
+    // Reject the whole order
+    #[default]
+    Reject,
+    // Cut it down to what's left of the budget
+    Shrink,
+}
+
+// The budget a strategy's order would have gone past
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    pub strategy: String,
+    // Deployed and reserved before the order
+    pub used: f64,
+    // What the order would have added
+    pub requested: f64,
+    pub budget: f64,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} budget exceeded: {:.2} in use + {:.2} > {:.2}",
+            self.strategy, self.used, self.requested, self.budget
+        )
+    }
+}
+
+/// One budgeted strategy's use of its budget
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetUsage {
+    pub budget: f64,
+    // Open lots attributed to the strategy, at cost
+    pub deployed: f64,
+    // Working orders' notional still to fill
+    pub reserved: f64,
+    // (deployed + reserved) / budget
+    pub utilization: f64,
+}
+
+// One strategy's share of a working order, not yet filled
+#[derive(Debug, Clone)]
+struct Share {
+    strategy: String,
+    // What of it closes the strategy's own lots; fills take this first
+    closing: f64,
+    // What adds to them, and is reserved
+    opening: f64,
+}
+
+impl Share {
+    fn fill(&mut self, quantity: f64) {
+        let closing = quantity.min(self.closing);
+        self.closing -= closing;
+        self.opening = (self.opening - (quantity - closing)).max(0.0);
+    }
+}
+
+// Notional held for a working order
+#[derive(Debug, Clone)]
+struct Reservation {
+    price: f64,
+    shares: Vec<Share>,
+    // Sent, so once the order stops working the reservation goes
+    submitted: bool,
+}
+
+/// Keeps each strategy within its budget. What a strategy has deployed is
+/// its open lots at cost, as attributed by StrategyStats, so it comes back
+/// as those lots close, whoever's fill closes them. An accepted order
+/// reserves the notional it would add until it fills or stops working;
+/// the part of it that closes the strategy's own lots needs no budget.
+/// Orders for several strategies ("A+B") are shared equally among them,
+/// as their fills are.
+#[derive(Debug, Default)]
+pub struct StrategyAllocator {
+    config: AllocationConfig,
+    // By order id
+    reservations: Mutex<HashMap<String, Reservation>>,
+}
+
+impl StrategyAllocator {
+    pub fn new(config: AllocationConfig) -> Self {
+        Self {
+            config,
+            reservations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &AllocationConfig {
+        &self.config
+    }
+
+    /// Checks the order against its strategies' budgets at `price` and, if
+    /// it fits, reserves what it adds. Under BudgetPolicy::Shrink an order
+    /// past a budget is cut down to fit, and the budget it went past is
+    /// returned. `replacing` is a working order it takes the place of,
+    /// whose reservation no longer counts; `is_open` says whether a sent
+    /// order is still working.
+    pub fn reserve(
+        &self,
+        order: &mut Order,
+        price: f64,
+        replacing: Option<&str>,
+        stats: &StrategyStats,
+        is_open: impl Fn(&str) -> bool,
+    ) -> Result<Option<BudgetExceeded>, BudgetExceeded> {
+        let Some(names) = order.strategy.as_deref() else {
+            return Ok(None);
+        };
+        let strategies: Vec<&str> = names.split('+').collect();
+        if !strategies
+            .iter()
+            .any(|s| self.config.budgets.contains_key(*s))
+        {
+            return Ok(None);
+        }
+        let mut reservations = self.reservations.lock().unwrap();
+        reservations.retain(|id, reservation| !reservation.submitted || is_open(id));
+
+        let key = order.position_key();
+        // What of each strategy's share would close its own lots
+        let closing: Vec<f64> = strategies
+            .iter()
+            .map(|strategy| {
+                let held = stats.held(strategy, &key);
+                match order.side {
+                    OrderSide::Buy => (-held).max(0.0),
+                    OrderSide::Sell => held.max(0.0),
+                }
+            })
+            .collect();
+        let count = strategies.len() as f64;
+        let deployed = stats.deployed();
+        let mut quantity = order.quantity;
+        let mut shrunk = None;
+        for (strategy, &closing) in strategies.iter().zip(&closing) {
+            let Some(&budget) = self.config.budgets.get(*strategy) else {
+                continue;
+            };
+            let requested = (quantity / count - closing).max(0.0) * price;
+            let used = deployed.get(*strategy).copied().unwrap_or(0.0)
+                + reserved(&reservations, strategy, replacing);
+            if used + requested <= budget + 1e-9 {
+                continue;
+            }
+            let exceeded = BudgetExceeded {
+                strategy: strategy.to_string(),
+                used,
+                requested,
+                budget,
+            };
+            if self.config.exceed == BudgetPolicy::Reject || price <= 0.0 {
+                return Err(exceeded);
+            }
+            // Whatever of it closes lots, and what's left of the budget
+            let fits = (closing + (budget - used).max(0.0) / price) * count;
+            if fits <= f64::EPSILON {
+                return Err(exceeded);
+            }
+            quantity = fits;
+            shrunk = Some(exceeded);
+        }
+        order.quantity = quantity;
+        let shares = strategies
+            .iter()
+            .zip(closing)
+            .map(|(strategy, closing)| {
+                let share = quantity / count;
+                Share {
+                    strategy: strategy.to_string(),
+                    closing: closing.min(share),
+                    opening: (share - closing).max(0.0),
+                }
+            })
+            .collect();
+        reservations.insert(
+            order.id.clone(),
+            Reservation {
+                price,
+                shares,
+                submitted: false,
+            },
+        );
+        Ok(shrunk)
+    }
+
+    // The order reserved under `order_id` went out as `sent_id`; it's
+    // released once that stops working
+    pub fn submitted(&self, order_id: &str, sent_id: &str) {
+        let mut reservations = self.reservations.lock().unwrap();
+        if let Some(mut reservation) = reservations.remove(order_id) {
+            reservation.submitted = true;
+            reservations.insert(sent_id.to_string(), reservation);
+        }
+    }
+
+    // Drops an order's reservation, e.g. when it couldn't be sent
+    pub fn release(&self, order_id: &str) {
+        self.reservations.lock().unwrap().remove(order_id);
+    }
+
+    // A fill moves its quantity from the order's reservation to the
+    // strategy's deployed lots
+    pub fn on_fill(&self, order_id: &str, quantity: f64) {
+        let mut reservations = self.reservations.lock().unwrap();
+        let Some(reservation) = reservations.get_mut(order_id) else {
+            return;
+        };
+        let count = reservation.shares.len() as f64;
+        for share in &mut reservation.shares {
+            share.fill(quantity / count);
+        }
+        if reservation
+            .shares
+            .iter()
+            .all(|share| share.closing + share.opening <= f64::EPSILON)
+        {
+            reservations.remove(order_id);
+        }
+    }
+
+    /// Every budgeted strategy's use of its budget
+    pub fn usage(&self, stats: &StrategyStats) -> BTreeMap<String, BudgetUsage> {
+        let reservations = self.reservations.lock().unwrap();
+        let deployed = stats.deployed();
+        self.config
+            .budgets
+            .iter()
+            .map(|(strategy, &budget)| {
+                let deployed = deployed.get(strategy).copied().unwrap_or(0.0);
+                let reserved = reserved(&reservations, strategy, None);
+                let usage = BudgetUsage {
+                    budget,
+                    deployed,
+                    reserved,
+                    utilization: (deployed + reserved) / budget,
+                };
+                (strategy.clone(), usage)
+            })
+            .collect()
+    }
+}
+
+// The strategy's share of what working orders have reserved, leaving out
+// `except`
+fn reserved(
+    reservations: &HashMap<String, Reservation>,
+    strategy: &str,
+    except: Option<&str>,
+) -> f64 {
+    reservations
+        .iter()
+        .filter(|(id, _)| except != Some(id.as_str()))
+        .flat_map(|(_, r)| {
+            r.shares
+                .iter()
+                .filter(|share| share.strategy == strategy)
+                .map(|share| share.opening * r.price)
+        })
+        .fold(0.0, |total, notional| total + notional)
+}
@@ -0,0 +1,440 @@
+use crate::TradingBot;
+use crate::events::BotEvent;
+use crate::external::{ExternalSignal, SignalRejection};
+use crate::metrics::Metrics;
+use crate::shadow::StrategyMode;
+use crate::symbols::assets;
+use crate::venue::ExecutionMode;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{HeaderMap, StatusCode, Uri, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+// Overrides `auth_token` so the secret can stay out of the config file
+pub const AUTH_TOKEN_ENV: &str = "API_AUTH_TOKEN";
+pub const SIGNAL_TOKEN_ENV: &str = "API_SIGNAL_TOKEN";
+
+// Embedded HTTP status and control API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    pub bind: String,
+    // Requests must carry `Authorization: Bearer <token>` when set
+    pub auth_token: Option<String>,
+    // Shared with whatever posts to /signals; auth_token when unset. One
+    // of the two must be set for /signals to take anything.
+    pub signal_token: Option<String>,
+    // How long a posted signal without an expiry stays good
+    pub signal_ttl_secs: u64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:8080".to_string(),
+            auth_token: None,
+            signal_token: None,
+            signal_ttl_secs: 60,
+        }
+    }
+}
+
+impl ApiConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.bind.parse::<SocketAddr>().is_err() {
+            return Err(format!("Invalid API bind address: {}", self.bind));
+        }
+        if self.signal_ttl_secs == 0 {
+            return Err("API signal_ttl_secs must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    // The bearer token, from the environment if set there
+    pub fn token(&self) -> Option<String> {
+        std::env::var(AUTH_TOKEN_ENV)
+            .ok()
+            .or_else(|| self.auth_token.clone())
+            .filter(|token| !token.is_empty())
+    }
+
+    // What /signals authenticates against, from the environment if set
+    // there
+    pub fn signal_token(&self) -> Option<String> {
+        std::env::var(SIGNAL_TOKEN_ENV)
+            .ok()
+            .or_else(|| self.signal_token.clone())
+            .filter(|token| !token.is_empty())
+            .or_else(|| self.token())
+    }
+}
+
+#[derive(Clone)]
+struct ApiState {
+    bot: TradingBot,
+    token: Option<String>,
+    signal_token: Option<String>,
+    signal_ttl_ms: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddSymbol {
+    symbol: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct UnsubscribeParams {
+    // Cancel the symbol's orders and close its position too
+    flatten: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct EventParams {
+    // At most one price tick per symbol per this many milliseconds
+    price_interval_ms: u64,
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(serde_json::json!({ "error": message.into() }))).into_response()
+}
+
+/// The API's routes over a running bot:
+///
+/// - `GET /status`: the execution mode, primary venue, whether trading is
+///   halted, which symbols the volatility breaker has paused, every
+///   position's protective levels, the symbols' correlation matrix and
+///   each budgeted strategy's use of its budget
+/// - `GET /positions`, `GET /orders/open`, `GET /pnl`, `GET /strategies`,
+///   `GET /symbols`
+/// - `GET /report`: the day's PnL report so far, as the daily report writes
+///   it in JSON
+/// - `GET /equity`: the sampled equity curve, with Sharpe, Sortino and
+///   drawdown over it
+/// - `POST /strategies/{name}/enable` and `/disable`
+/// - `POST /kill-switch` and `POST /resume`
+/// - `POST /symbols` with `{"symbol": "ADA/USDT"}`, and
+///   `DELETE /symbols/ADA/USDT` (`?flatten=true` to close out the symbol)
+/// - `GET /events`, a WebSocket stream of the bot's events as JSON
+///   messages; `?price_interval_ms=N` throttles price ticks per symbol
+/// - `POST /signals` with an ExternalSignal, traded under the bot's risk
+///   controls. It authenticates with the signal token instead, which
+///   webhook senders that can't set headers put in the body as `token`.
+///
+/// Every route answers JSON; errors are `{"error": "..."}`. Browsers can't
+/// set headers on a WebSocket, so the token may also be passed as
+/// `?access_token=`.
+pub fn router(bot: TradingBot, config: &ApiConfig) -> Router {
+    let state = ApiState {
+        bot,
+        token: config.token(),
+        signal_token: config.signal_token(),
+        signal_ttl_ms: config.signal_ttl_secs * 1_000,
+    };
+    Router::new()
+        .route("/status", get(status))
+        .route("/positions", get(positions))
+        .route("/orders/open", get(open_orders))
+        .route("/pnl", get(pnl))
+        .route("/report", get(report))
+        .route("/equity", get(equity))
+        .route("/strategies", get(strategies))
+        .route("/strategies/{name}/{action}", post(set_strategy))
+        .route("/kill-switch", post(kill_switch))
+        .route("/resume", post(resume))
+        .route("/symbols", get(symbols).post(subscribe))
+        .route("/symbols/{*symbol}", delete(unsubscribe))
+        .route("/events", get(events))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .route("/signals", post(signals))
+        .with_state(state)
+}
+
+/// Serves the API on `bind` in a background task and returns the address
+/// actually bound (useful with port 0)
+pub async fn serve(bot: TradingBot, config: &ApiConfig) -> Result<SocketAddr, String> {
+    let listener = tokio::net::TcpListener::bind(&config.bind)
+        .await
+        .map_err(|e| format!("Failed to bind API {}: {}", config.bind, e))?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to bind API {}: {}", config.bind, e))?;
+    let app = router(bot, config);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            println!("API server stopped: {}", e);
+        }
+    });
+    Ok(addr)
+}
+
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    if let Some(token) = &state.token {
+        let presented = presented_token(request.headers(), request.uri());
+        if !presented.is_some_and(|presented| constant_time_eq(presented, token)) {
+            return error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+        }
+    }
+    next.run(request).await
+}
+
+// The bearer token, or the access_token query parameter
+fn presented_token<'a>(headers: &'a HeaderMap, uri: &'a Uri) -> Option<&'a str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| {
+            uri.query().and_then(|query| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("access_token="))
+            })
+        })
+}
+
+// Compares without an early exit, so timing doesn't reveal the token
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+async fn status(State(state): State<ApiState>) -> Response {
+    let bot = &state.bot;
+    Json(serde_json::json!({
+        "mode": bot.execution_mode(),
+        "live": bot.execution_mode() == ExecutionMode::Live,
+        "primary_venue": bot.primary_venue(),
+        "venues": bot.venues().map(|venue| venue.id()).collect::<Vec<_>>(),
+        "halted": bot.is_halted(),
+        "breaker_tripped": bot.tripped_symbols(),
+        "protection": bot.protective_levels().await,
+        "correlations": bot.correlations(),
+        "strategy_budgets": bot.strategy_budgets(),
+    }))
+    .into_response()
+}
+
+async fn positions(State(state): State<ApiState>) -> Response {
+    Json(state.bot.positions().await).into_response()
+}
+
+async fn open_orders(State(state): State<ApiState>) -> Response {
+    Json(state.bot.open_orders().await).into_response()
+}
+
+async fn pnl(State(state): State<ApiState>) -> Response {
+    Json(state.bot.pnl().await).into_response()
+}
+
+async fn report(State(state): State<ApiState>) -> Response {
+    Json(state.bot.daily_report().await).into_response()
+}
+
+async fn equity(State(state): State<ApiState>) -> Response {
+    Json(state.bot.equity_curve()).into_response()
+}
+
+async fn strategies(State(state): State<ApiState>) -> Response {
+    Json(state.bot.list_strategies().await).into_response()
+}
+
+async fn set_strategy(
+    State(state): State<ApiState>,
+    Path((name, action)): Path<(String, String)>,
+) -> Response {
+    let found = match action.as_str() {
+        "enable" => state.bot.set_strategy_enabled(&name, true).await,
+        "disable" => state.bot.set_strategy_enabled(&name, false).await,
+        "live" => state.bot.set_strategy_mode(&name, StrategyMode::Live).await,
+        "shadow" => {
+            state
+                .bot
+                .set_strategy_mode(&name, StrategyMode::Shadow)
+                .await
+        }
+        _ => return error(StatusCode::NOT_FOUND, format!("unknown action {}", action)),
+    };
+    if !found {
+        return error(StatusCode::NOT_FOUND, format!("no strategy named {}", name));
+    }
+    match state
+        .bot
+        .list_strategies()
+        .await
+        .into_iter()
+        .find(|s| s.name == name)
+    {
+        Some(info) => Json(info).into_response(),
+        None => error(StatusCode::NOT_FOUND, format!("no strategy named {}", name)),
+    }
+}
+
+async fn kill_switch(State(state): State<ApiState>) -> Response {
+    Json(state.bot.kill_switch().await).into_response()
+}
+
+async fn resume(State(state): State<ApiState>) -> Response {
+    state.bot.resume().await;
+    Json(serde_json::json!({ "halted": state.bot.is_halted() })).into_response()
+}
+
+async fn symbols(State(state): State<ApiState>) -> Response {
+    Json(state.bot.symbols().await).into_response()
+}
+
+async fn subscribe(State(state): State<ApiState>, Json(body): Json<AddSymbol>) -> Response {
+    if assets(&body.symbol).is_none() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            format!("symbol must look like BASE/QUOTE, got {}", body.symbol),
+        );
+    }
+    match state.bot.subscribe(&body.symbol).await {
+        Ok(()) => (StatusCode::CREATED, Json(state.bot.symbols().await)).into_response(),
+        Err(e) => error(StatusCode::CONFLICT, e),
+    }
+}
+
+async fn unsubscribe(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    Query(params): Query<UnsubscribeParams>,
+) -> Response {
+    match state.bot.unsubscribe(&symbol, params.flatten).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => error(StatusCode::NOT_FOUND, e),
+    }
+}
+
+// Parsed before it is authenticated, since the token may be in it; an
+// unauthenticated sender only ever hears 401
+async fn signals(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    uri: Uri,
+    body: String,
+) -> Response {
+    let Some(token) = &state.signal_token else {
+        return error(
+            StatusCode::FORBIDDEN,
+            "signals are refused until signal_token or auth_token is set",
+        );
+    };
+    let parsed = serde_json::from_str::<ExternalSignal>(&body);
+    let presented = presented_token(&headers, &uri).or_else(|| {
+        parsed
+            .as_ref()
+            .ok()
+            .and_then(|signal| signal.token.as_deref())
+    });
+    if !presented.is_some_and(|presented| constant_time_eq(presented, token)) {
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid signal token");
+    }
+    let signal = match parsed {
+        Ok(signal) => signal,
+        Err(e) => return error(StatusCode::BAD_REQUEST, format!("malformed signal: {}", e)),
+    };
+    match state
+        .bot
+        .submit_external_signal(signal, state.signal_ttl_ms)
+        .await
+    {
+        Ok(pending) => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({
+                "strategy": pending.strategy,
+                "symbol": pending.signal.symbol.as_ref(),
+                "side": pending.signal.action,
+                "quantity": pending.signal.quantity,
+                "expires_at_ms": pending.expires_at_ms,
+            })),
+        )
+            .into_response(),
+        Err(rejection @ SignalRejection::Malformed(_)) => {
+            error(StatusCode::BAD_REQUEST, rejection.to_string())
+        }
+        Err(rejection) => error(StatusCode::UNPROCESSABLE_ENTITY, rejection.to_string()),
+    }
+}
+
+async fn events(
+    State(state): State<ApiState>,
+    Query(params): Query<EventParams>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    // Subscribed before the upgrade so nothing in between is missed
+    let events = state.bot.subscribe_events();
+    let metrics = state.bot.metrics();
+    upgrade.on_upgrade(move |socket| {
+        stream_events(
+            socket,
+            events,
+            metrics,
+            Duration::from_millis(params.price_interval_ms),
+        )
+    })
+}
+
+// Forwards events to one client until it disconnects. The client only
+// ever holds up its own receiver: once it is EVENT_CAPACITY events behind,
+// the oldest are dropped and it is sent a `dropped` event with the count.
+async fn stream_events(
+    mut socket: WebSocket,
+    mut events: broadcast::Receiver<BotEvent>,
+    metrics: Arc<Metrics>,
+    price_interval: Duration,
+) {
+    let mut last_price: HashMap<String, Instant> = HashMap::new();
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                // Nothing is read from clients
+                Some(Ok(_)) => continue,
+            },
+        };
+        let event = match event {
+            Ok(BotEvent::Price(price)) if !price_interval.is_zero() => {
+                let now = Instant::now();
+                if last_price
+                    .get(&price.symbol)
+                    .is_some_and(|last| now.duration_since(*last) < price_interval)
+                {
+                    continue;
+                }
+                last_price.insert(price.symbol.clone(), now);
+                BotEvent::Price(price)
+            }
+            Ok(event) => event,
+            Err(RecvError::Lagged(count)) => {
+                metrics.events_dropped.inc_by(count);
+                BotEvent::Dropped { count }
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let Ok(json) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            return;
+        }
+    }
+}
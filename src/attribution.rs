@@ -0,0 +1,318 @@
+use crate::performance::UNATTRIBUTED;
+use crate::{Fill, Position, PositionKey};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+// Per-strategy PnL reporting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttributionConfig {
+    // Seconds between per-strategy summaries in the log; 0 turns them off
+    pub log_interval_secs: u64,
+}
+
+impl Default for AttributionConfig {
+    fn default() -> Self {
+        Self {
+            log_interval_secs: 60,
+        }
+    }
+}
+
+/// One strategy's attributed results. Realized PnL is credited to the
+/// strategy that opened the position, whoever's fill closed it; fees are
+/// charged to the strategy whose order paid them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StrategyPnl {
+    pub realized_pnl: f64,
+    // Open lots marked at the symbol's latest price
+    pub unrealized_pnl: f64,
+    pub fees: f64,
+    // realized + unrealized - fees
+    pub net_pnl: f64,
+    pub fills: usize,
+    // Closing fills against the strategy's lots; each is won or lost on
+    // the realized PnL it gave the strategy
+    pub trades: usize,
+    pub winning_trades: usize,
+    pub hit_rate: Option<f64>,
+}
+
+impl fmt::Display for StrategyPnl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "net {:.2} (realized {:.2}, unrealized {:.2}, fees {:.2}), {} trades",
+            self.net_pnl, self.realized_pnl, self.unrealized_pnl, self.fees, self.trades
+        )?;
+        if let Some(hit_rate) = self.hit_rate {
+            write!(f, ", {:.0}% won", hit_rate * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+// Part of a position, still open, and the strategy that opened it
+#[derive(Debug, Clone)]
+struct Lot {
+    strategy: String,
+    // Signed: positive long, negative short
+    quantity: f64,
+    price: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Totals {
+    realized_pnl: f64,
+    fees: f64,
+    fills: usize,
+    trades: usize,
+    winning_trades: usize,
+}
+
+/// Splits the netted positions back out by strategy. Each position is kept
+/// as a FIFO queue of lots tagged with the strategy that opened them; a
+/// fill first closes the oldest opposite lots, crediting their openers
+/// with the PnL, and whatever is left opens a lot of its own. Fills of
+/// aggregated orders ("A+B") are shared equally among their strategies,
+/// and fills with none (stops, the kill switch) count as UNATTRIBUTED.
+#[derive(Debug, Default)]
+pub struct StrategyStats {
+    lots: HashMap<PositionKey, VecDeque<Lot>>,
+    totals: BTreeMap<String, Totals>,
+}
+
+// Realized PnL one fill gave a strategy by closing its lots
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedTrade {
+    pub strategy: String,
+    pub pnl: f64,
+}
+
+impl StrategyStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Books a fill, returning the trades it closed, one per strategy
+    /// whose lots it closed
+    pub fn apply_fill(&mut self, fill: &Fill) -> Vec<ClosedTrade> {
+        let strategies: Vec<&str> = match fill.strategy.as_deref() {
+            Some(names) => names.split('+').collect(),
+            None => vec![UNATTRIBUTED],
+        };
+        let share = fill.quantity / strategies.len() as f64;
+        let sign = fill.signed_quantity().signum();
+        let lots = self
+            .lots
+            .entry((fill.venue.clone(), fill.symbol, fill.position_side))
+            .or_default();
+
+        let mut closed: BTreeMap<String, f64> = BTreeMap::new();
+        for strategy in &strategies {
+            let totals = self.totals.entry(strategy.to_string()).or_default();
+            totals.fills += 1;
+            totals.fees += fill.fee / strategies.len() as f64;
+
+            let mut remaining = share;
+            while remaining > f64::EPSILON {
+                let Some(lot) = lots.front_mut().filter(|lot| lot.quantity.signum() != sign) else {
+                    break;
+                };
+                let closing = remaining.min(lot.quantity.abs());
+                *closed.entry(lot.strategy.clone()).or_default() +=
+                    closing * (fill.price - lot.price) * lot.quantity.signum();
+                lot.quantity -= closing * lot.quantity.signum();
+                remaining -= closing;
+                if lot.quantity.abs() < f64::EPSILON {
+                    lots.pop_front();
+                }
+            }
+            if remaining > f64::EPSILON {
+                lots.push_back(Lot {
+                    strategy: strategy.to_string(),
+                    quantity: remaining * sign,
+                    price: fill.price,
+                });
+            }
+        }
+
+        closed
+            .into_iter()
+            .map(|(strategy, pnl)| {
+                let totals = self.totals.entry(strategy.clone()).or_default();
+                totals.realized_pnl += pnl;
+                totals.trades += 1;
+                if pnl > 0.0 {
+                    totals.winning_trades += 1;
+                }
+                ClosedTrade { strategy, pnl }
+            })
+            .collect()
+    }
+
+    // A position restored from a snapshot, whose openers aren't known
+    pub fn restore_position(&mut self, position: &Position) {
+        if position.quantity.abs() < f64::EPSILON {
+            return;
+        }
+        self.lots.insert(
+            position.key(),
+            VecDeque::from([Lot {
+                strategy: UNATTRIBUTED.to_string(),
+                quantity: position.quantity,
+                price: position.avg_price,
+            }]),
+        );
+    }
+
+    /// Every strategy's results so far, with open lots marked by `mark`
+    /// (a symbol's latest price; lots without one are marked at cost)
+    pub fn snapshot(&self, mark: impl Fn(&str) -> Option<f64>) -> BTreeMap<String, StrategyPnl> {
+        let mut report: BTreeMap<String, StrategyPnl> = self
+            .totals
+            .iter()
+            .map(|(strategy, totals)| {
+                let pnl = StrategyPnl {
+                    realized_pnl: totals.realized_pnl,
+                    fees: totals.fees,
+                    fills: totals.fills,
+                    trades: totals.trades,
+                    winning_trades: totals.winning_trades,
+                    hit_rate: (totals.trades > 0)
+                        .then(|| totals.winning_trades as f64 / totals.trades as f64),
+                    ..StrategyPnl::default()
+                };
+                (strategy.clone(), pnl)
+            })
+            .collect();
+        for ((_, symbol, _), lots) in &self.lots {
+            let mark = mark(symbol.as_str());
+            for lot in lots {
+                let pnl = report.entry(lot.strategy.clone()).or_default();
+                pnl.unrealized_pnl += lot.quantity * (mark.unwrap_or(lot.price) - lot.price);
+            }
+        }
+        for pnl in report.values_mut() {
+            pnl.net_pnl = pnl.realized_pnl + pnl.unrealized_pnl - pnl.fees;
+        }
+        report
+    }
+
+    // Each strategy's open lots at what they cost
+    pub fn deployed(&self) -> BTreeMap<String, f64> {
+        let mut deployed = BTreeMap::new();
+        for lot in self.lots.values().flatten() {
+            *deployed.entry(lot.strategy.clone()).or_default() += lot.quantity.abs() * lot.price;
+        }
+        deployed
+    }
+
+    // The strategy's open lots on the position, signed
+    pub fn held(&self, strategy: &str, key: &PositionKey) -> f64 {
+        self.lots
+            .get(key)
+            .into_iter()
+            .flatten()
+            .filter(|lot| lot.strategy == strategy)
+            .map(|lot| lot.quantity)
+            .sum()
+    }
+
+    // Symbols with open lots, for marking a snapshot
+    pub fn open_symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self
+            .lots
+            .iter()
+            .filter(|(_, lots)| !lots.is_empty())
+            .map(|((_, symbol, _), _)| symbol.to_string())
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::fill;
+    use crate::symbols::SymbolId;
+    use crate::venue::VenueId;
+    use crate::{OrderSide, PositionSide};
+
+    fn by(strategy: &str, side: OrderSide, quantity: f64, price: f64) -> Fill {
+        Fill {
+            strategy: Some(strategy.to_string()),
+            fee: 0.1,
+            ..fill("SOL/USDT", side, quantity, price)
+        }
+    }
+
+    #[test]
+    fn interleaved_trades_are_credited_to_the_opener_first_in_first_out() {
+        let mut stats = StrategyStats::new();
+        let trades = [
+            by("a", OrderSide::Buy, 1.0, 100.0),
+            by("b", OrderSide::Buy, 1.0, 102.0),
+            // Closes a's lot, the oldest: a wins 5
+            by("a", OrderSide::Sell, 1.0, 105.0),
+            // Closes b's: b loses 4
+            by("b", OrderSide::Sell, 1.0, 98.0),
+            by("b", OrderSide::Buy, 2.0, 100.0),
+            // a's sell closes half of b's lot, so b wins 3
+            by("a", OrderSide::Sell, 1.0, 103.0),
+        ];
+        let closed: Vec<ClosedTrade> = trades
+            .iter()
+            .flat_map(|fill| stats.apply_fill(fill))
+            .collect();
+        let pnls: Vec<(&str, f64)> = closed
+            .iter()
+            .map(|trade| (trade.strategy.as_str(), trade.pnl))
+            .collect();
+        assert_eq!(pnls, [("a", 5.0), ("b", -4.0), ("b", 3.0)]);
+
+        let report = stats.snapshot(|_| Some(101.0));
+        let a = &report["a"];
+        assert_eq!((a.realized_pnl, a.unrealized_pnl), (5.0, 0.0));
+        assert_eq!((a.trades, a.winning_trades, a.fills), (1, 1, 3));
+        assert!((a.net_pnl - 4.7).abs() < 1e-9);
+        let b = &report["b"];
+        assert_eq!((b.realized_pnl, b.unrealized_pnl), (-1.0, 1.0));
+        assert_eq!((b.trades, b.hit_rate), (2, Some(0.5)));
+        assert!((b.net_pnl + 0.3).abs() < 1e-9);
+        let key = (
+            VenueId::paper(),
+            SymbolId::intern("SOL/USDT"),
+            PositionSide::Both,
+        );
+        assert_eq!(stats.held("b", &key), 1.0);
+        assert_eq!(stats.held("a", &key), 0.0);
+    }
+
+    #[test]
+    fn an_aggregated_fill_is_shared_and_a_stop_is_unattributed() {
+        let mut stats = StrategyStats::new();
+        stats.apply_fill(&by("a+b", OrderSide::Buy, 2.0, 100.0));
+        let stop = fill("SOL/USDT", OrderSide::Sell, 2.0, 95.0);
+        let closed = stats.apply_fill(&stop);
+        assert_eq!(
+            closed,
+            [
+                ClosedTrade {
+                    strategy: "a".to_string(),
+                    pnl: -5.0
+                },
+                ClosedTrade {
+                    strategy: "b".to_string(),
+                    pnl: -5.0
+                },
+            ]
+        );
+        let report = stats.snapshot(|_| None);
+        assert_eq!(report[UNATTRIBUTED].fills, 1);
+        assert!((report["a"].fees - 0.05).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,422 @@
+use crate::clock::VirtualClock;
+use crate::config::BotConfig;
+use crate::events::BotEvent;
+use crate::performance::PerformanceBreakdown;
+use crate::rate_limit::{ExhaustedPolicy, RateLimitConfig};
+use crate::recording::{MarketEvent, RecordedEvent};
+use crate::schedule::Due;
+use crate::symbols::Symbol;
+use crate::venue::ExecutionMode;
+use crate::{Fill, OrderBook, Position, Price, TradingBot};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+
+// How the backtester builds a book for rows that don't carry one: levels
+// stepped out from the row's price, each holding a fixed quantity. Orders
+// bigger than a level walk further out, which is the slippage model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BacktestConfig {
+    // Full bid/ask spread around the row's price
+    pub spread_bps: f64,
+    // Distance between successive levels on each side
+    pub level_step_bps: f64,
+    pub level_quantity: f64,
+    pub levels: usize,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            spread_bps: 2.0,
+            level_step_bps: 1.0,
+            level_quantity: 1_000.0,
+            levels: 10,
+        }
+    }
+}
+
+impl BacktestConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.spread_bps < 0.0 || self.level_step_bps < 0.0 {
+            return Err("Backtest spread_bps and level_step_bps must be non-negative".to_string());
+        }
+        if self.level_quantity <= 0.0 || self.levels == 0 {
+            return Err(
+                "Backtest books need at least one level with positive quantity".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn synthetic_book(&self, price: &Price) -> OrderBook {
+        let half_spread = price.price * self.spread_bps / 2.0 / 10_000.0;
+        let step = price.price * self.level_step_bps / 10_000.0;
+        let (bids, asks) = (0..self.levels)
+            .map(|i| {
+                let offset = half_spread + i as f64 * step;
+                (
+                    (price.price - offset, self.level_quantity),
+                    (price.price + offset, self.level_quantity),
+                )
+            })
+            .unzip();
+        OrderBook::new(price.symbol.clone(), bids, asks, price.timestamp)
+    }
+}
+
+// One row of historical data: a tick (or a candle's close) and, if the data
+// has them, the book levels at that time
+#[derive(Debug, Clone)]
+pub struct MarketRecord {
+    pub price: Price,
+    pub book: Option<OrderBook>,
+}
+
+/// Loads a CSV of ticks or candles. The header names the columns:
+/// `timestamp`, `price` (or `close` for candles) and `volume` are required,
+/// `symbol` is optional (rows default to `default_symbol`), and book levels
+/// may be given as `bid_1`, `bid_qty_1`, `ask_1`, `ask_qty_1`, `bid_2`, ...
+/// Rows come back in timestamp order.
+pub fn load_csv(path: &str, default_symbol: &str) -> Result<Vec<MarketRecord>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read market data {}: {}", path, e))?;
+    parse_csv(&contents, default_symbol)
+}
+
+pub fn parse_csv(contents: &str, default_symbol: &str) -> Result<Vec<MarketRecord>, String> {
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or("Market data is empty")?;
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    let column = |name: &str| columns.iter().position(|c| c.eq_ignore_ascii_case(name));
+
+    let timestamp_col = column("timestamp").ok_or("Market data has no timestamp column")?;
+    let price_col = column("price")
+        .or_else(|| column("close"))
+        .ok_or("Market data has no price or close column")?;
+    let volume_col = column("volume").ok_or("Market data has no volume column")?;
+    let symbol_col = column("symbol");
+    let mut book_cols = Vec::new();
+    for level in 1.. {
+        let cols = (
+            column(&format!("bid_{}", level)),
+            column(&format!("bid_qty_{}", level)),
+            column(&format!("ask_{}", level)),
+            column(&format!("ask_qty_{}", level)),
+        );
+        let (Some(bid), Some(bid_qty), Some(ask), Some(ask_qty)) = cols else {
+            break;
+        };
+        book_cols.push((bid, bid_qty, ask, ask_qty));
+    }
+
+    let mut records = Vec::new();
+    for (index, line) in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let number = |col: usize| -> Result<f64, String> {
+            fields
+                .get(col)
+                .and_then(|f| f.parse::<f64>().ok())
+                .ok_or_else(|| format!("Line {}: bad {}", index + 1, columns[col]))
+        };
+
+        let price = Price {
+            symbol: symbol_col
+                .and_then(|col| fields.get(col))
+                .map_or(default_symbol, |s| s)
+                .to_string(),
+            price: number(price_col)?,
+            timestamp: number(timestamp_col)? as u64,
+            volume: number(volume_col)?,
+        };
+
+        let mut bids = Vec::new();
+        let mut asks = Vec::new();
+        for &(bid, bid_qty, ask, ask_qty) in &book_cols {
+            // Levels may be left blank on rows with a thinner book
+            if let (Ok(price), Ok(quantity)) = (number(bid), number(bid_qty)) {
+                bids.push((price, quantity));
+            }
+            if let (Ok(price), Ok(quantity)) = (number(ask), number(ask_qty)) {
+                asks.push((price, quantity));
+            }
+        }
+        let book = (!bids.is_empty() || !asks.is_empty())
+            .then(|| OrderBook::new(price.symbol.clone(), bids, asks, price.timestamp));
+
+        records.push(MarketRecord { price, book });
+    }
+
+    records.sort_by_key(|r| r.price.timestamp);
+    Ok(records)
+}
+
+// What a backtest did
+#[derive(Debug, Clone)]
+pub struct BacktestResult {
+    pub ticks: usize,
+    pub fills: Vec<Fill>,
+    // Sorted by symbol
+    pub positions: Vec<Position>,
+    pub starting_equity: f64,
+    // Open positions marked at their last price
+    pub final_equity: f64,
+    pub realized_pnl: f64,
+    pub fees_paid: f64,
+    // (row timestamp, equity) after each row
+    pub equity_curve: Vec<(u64, f64)>,
+    pub performance: PerformanceBreakdown,
+    // Every BotEvent::Signal the strategies raised, in order
+    pub signals: Vec<BotEvent>,
+}
+
+impl std::fmt::Display for BacktestResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Ticks replayed:  {}", self.ticks)?;
+        writeln!(f, "Fills:           {}", self.fills.len())?;
+        writeln!(f, "Starting equity: {:.2}", self.starting_equity)?;
+        writeln!(f, "Final equity:    {:.2}", self.final_equity)?;
+        writeln!(f, "Realized PnL:    {:.2}", self.realized_pnl)?;
+        writeln!(f, "Fees paid:       {:.2}", self.fees_paid)?;
+        let overall = &self.performance.overall;
+        writeln!(f, "Total return:    {:.2}%", overall.total_return * 100.0)?;
+        writeln!(f, "Sharpe:          {}", format_ratio(overall.sharpe))?;
+        writeln!(f, "Sortino:         {}", format_ratio(overall.sortino))?;
+        writeln!(
+            f,
+            "Max drawdown:    {:.2}% ({}s)",
+            overall.max_drawdown * 100.0,
+            overall.max_drawdown_duration_secs
+        )?;
+        writeln!(
+            f,
+            "Trades:          {} (hit rate {})",
+            overall.trades,
+            overall
+                .hit_rate
+                .map_or("n/a".to_string(), |h| format!("{:.1}%", h * 100.0))
+        )?;
+        writeln!(
+            f,
+            "Profit factor:   {}",
+            format_ratio(overall.profit_factor)
+        )?;
+        writeln!(f, "Turnover:        {:.2}x", overall.turnover)?;
+        for position in &self.positions {
+            writeln!(
+                f,
+                "Position {}: {:.4} @ {:.4} (realized {:.2})",
+                position.symbol, position.quantity, position.avg_price, position.realized_pnl
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn format_ratio(ratio: Option<f64>) -> String {
+    ratio.map_or("n/a".to_string(), |r| format!("{:.2}", r))
+}
+
+/// Replays historical data through a bot built from the given config. The
+/// production strategies, aggregator, risk manager and paper executor all
+/// run unchanged; only the data source differs. Each row marks its symbol,
+/// installs its book (or a synthetic one) and runs one evaluation pass on
+/// it, and fills are booked before the next row.
+pub struct Backtester {
+    config: BotConfig,
+}
+
+impl Backtester {
+    pub fn new(config: &BotConfig) -> Self {
+        let mut config = config.clone();
+        // Nothing to pace against a simulated venue
+        config.rate_limits = RateLimitConfig {
+            orders_per_second: 1e9,
+            order_burst: usize::MAX,
+            cancels_per_second: 1e9,
+            cancel_burst: usize::MAX,
+            on_exhausted: ExhaustedPolicy::Reject,
+        };
+        // Simulated trades stay on paper and out of the live journal and
+        // state
+        config.mode = ExecutionMode::Paper;
+        config.journal.enabled = false;
+        config.state.enabled = false;
+        Self { config }
+    }
+
+    pub async fn run(&self, records: &[MarketRecord]) -> BacktestResult {
+        let start_ms = records.first().map_or(0, |r| r.price.timestamp * 1_000);
+        let mut session = Session::start(&self.config, start_ms).await;
+        for record in records {
+            let symbol = Symbol::intern(&record.price.symbol);
+            session.clock.advance_to(record.price.timestamp * 1_000);
+            session
+                .bot
+                .record_price(&symbol, record.price.clone())
+                .await;
+            let book = record
+                .book
+                .clone()
+                .unwrap_or_else(|| self.config.backtest.synthetic_book(&record.price));
+            session.evaluate(&symbol, book).await;
+            session.settle(record.price.timestamp).await;
+        }
+        session.finish(records.len()).await
+    }
+
+    /// Replays a market data recording event by event. A recorded book is
+    /// one evaluation pass of the live loop, so evaluating on each book
+    /// (after the prices received before it) reproduces the live run's
+    /// signal sequence. Fills are stamped with the event's receive time.
+    pub async fn replay(&self, events: &[RecordedEvent]) -> BacktestResult {
+        let start_ms = events.first().map_or(0, |e| e.received_at_ms);
+        let mut session = Session::start(&self.config, start_ms).await;
+        let mut ticks = 0;
+        // Symbols with a price since their last evaluation. With no
+        // evaluation interval the live loop records a book on every poll
+        // but evaluates only on a new price, so a book repeated after the
+        // prices stop is not another pass. On a timer every book is one.
+        let on_data = self.config.schedule.evaluation_interval().is_none();
+        let mut priced = HashSet::new();
+        for event in events {
+            session.clock.advance_to(event.received_at_ms);
+            match &event.event {
+                MarketEvent::Price(price) => {
+                    ticks += 1;
+                    let symbol = Symbol::intern(&price.symbol);
+                    session.bot.record_price(&symbol, price.clone()).await;
+                    priced.insert(symbol.id());
+                }
+                MarketEvent::Book(book) => {
+                    let symbol = Symbol::intern(&book.symbol);
+                    if priced.remove(&symbol.id()) || !on_data {
+                        session.evaluate(&symbol, book.clone()).await;
+                    } else {
+                        session.bot.record_book(&symbol, book.clone()).await;
+                        session
+                            .bot
+                            .order_executor
+                            .match_resting_orders(symbol.name())
+                            .await;
+                    }
+                }
+            }
+            session.settle(event.received_at_ms / 1_000).await;
+        }
+        session.finish(ticks).await
+    }
+}
+
+// One simulated run: a fresh bot on a virtual clock that follows the data,
+// and what it has done so far
+struct Session {
+    bot: TradingBot,
+    clock: Arc<VirtualClock>,
+    fill_stream: mpsc::UnboundedReceiver<Fill>,
+    events: broadcast::Receiver<BotEvent>,
+    starting_equity: f64,
+    fills: Vec<Fill>,
+    equity_curve: Vec<(u64, f64)>,
+    signals: Vec<BotEvent>,
+}
+
+impl Session {
+    async fn start(config: &BotConfig, start_ms: u64) -> Self {
+        let clock = Arc::new(VirtualClock::new(start_ms));
+        let bot = TradingBot::from_config_with_clock(config, clock.clone());
+        let fill_stream = bot
+            .order_executor
+            .take_fills()
+            .expect("a fresh executor still has its fill stream");
+        let events = bot.subscribe_events();
+        let starting_equity = bot.risk_manager.equity().await;
+        Self {
+            bot,
+            clock,
+            fill_stream,
+            events,
+            starting_equity,
+            fills: Vec::new(),
+            equity_curve: Vec::new(),
+            signals: Vec::new(),
+        }
+    }
+
+    // One evaluation pass on the symbol against `book`
+    async fn evaluate(&self, interned: &Symbol, book: OrderBook) {
+        let bot = &self.bot;
+        let symbol = interned.name();
+        bot.settle_funding().await;
+        bot.check_basis().await;
+        bot.check_drawdown().await;
+        bot.check_exits().await;
+        let strategies = bot.strategies.read().await;
+        let warmed_up = bot
+            .price_history
+            .read()
+            .await
+            .get(interned.id())
+            .is_some_and(|prices| prices.len() >= 10);
+        bot.record_book(interned, book.clone()).await;
+        if bot.check_freshness(symbol).await
+            && bot.check_session(symbol).await
+            && bot.check_breaker(symbol).await
+            && !bot.is_halted()
+            && warmed_up
+        {
+            bot.evaluate_symbol(interned, book, &strategies, Due::ALL)
+                .await;
+        } else {
+            // Resting orders still see the market while warming up, halted,
+            // outside the trading session or paused by the breaker
+            bot.order_executor.match_resting_orders(symbol).await;
+        }
+    }
+
+    // Books the fills so far and samples equity
+    async fn settle(&mut self, timestamp: u64) {
+        // Booking a fill can place protective orders, which can fill too
+        // Stamped with the data's time rather than the wall clock
+        while let Ok(mut fill) = self.fill_stream.try_recv() {
+            fill.timestamp = timestamp;
+            self.bot.on_fill(&fill).await;
+            self.fills.push(fill);
+        }
+        self.equity_curve
+            .push((timestamp, self.bot.risk_manager.equity().await));
+        loop {
+            match self.events.try_recv() {
+                Ok(event @ BotEvent::Signal { .. }) => self.signals.push(event),
+                Ok(_) | Err(broadcast::error::TryRecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+    }
+
+    async fn finish(self, ticks: usize) -> BacktestResult {
+        let mut positions = self.bot.risk_manager.positions().await;
+        positions.sort_by_key(|a| a.symbol);
+        BacktestResult {
+            ticks,
+            starting_equity: self.starting_equity,
+            final_equity: self.bot.risk_manager.equity().await,
+            realized_pnl: positions.iter().map(|p| p.realized_pnl).sum(),
+            fees_paid: self.fills.iter().map(|f| f.fee).sum(),
+            performance: PerformanceBreakdown::compute(
+                self.starting_equity,
+                &self.equity_curve,
+                &self.fills,
+            ),
+            equity_curve: self.equity_curve,
+            fills: self.fills,
+            positions,
+            signals: self.signals,
+        }
+    }
+}
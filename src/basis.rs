@@ -0,0 +1,134 @@
+use crate::venue::VenueId;
+use serde::{Deserialize, Serialize};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 86_400.0;
+
+// Delta-neutral spot/perp basis trade: long spot, short the perp while the
+// perp trades rich, collecting its funding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BasisConfig {
+    pub enabled: bool,
+    pub spot_symbol: String,
+    // Must be a perp in [symbol_meta]
+    pub perp_symbol: String,
+    // Where each leg is sent; the primary venue when unset
+    pub spot_venue: Option<VenueId>,
+    pub perp_venue: Option<VenueId>,
+    // Notional of each leg, in the quote asset
+    pub target_notional: f64,
+    // Annualized basis to open at, and to unwind at or below
+    pub entry_basis: f64,
+    pub exit_basis: f64,
+}
+
+impl Default for BasisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            spot_symbol: String::new(),
+            perp_symbol: String::new(),
+            spot_venue: None,
+            perp_venue: None,
+            target_notional: 1_000.0,
+            entry_basis: 0.10,
+            exit_basis: 0.02,
+        }
+    }
+}
+
+impl BasisConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.spot_symbol.is_empty() || self.perp_symbol.is_empty() {
+            return Err("Basis needs a spot_symbol and a perp_symbol".to_string());
+        }
+        if self.spot_symbol == self.perp_symbol {
+            return Err("Basis spot_symbol and perp_symbol must differ".to_string());
+        }
+        if self.target_notional <= 0.0 {
+            return Err("Basis target_notional must be positive".to_string());
+        }
+        if self.exit_basis >= self.entry_basis {
+            return Err("Basis exit_basis must be below entry_basis".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Perp over spot as a fraction of spot, annualized by the funding
+/// intervals in a year: the perp's premium is what funding pulls in each
+/// interval.
+pub fn annualized_basis(spot: f64, perp: f64, funding_interval_secs: u64) -> Option<f64> {
+    if spot <= 0.0 || !spot.is_finite() || !perp.is_finite() || funding_interval_secs == 0 {
+        return None;
+    }
+    Some((perp - spot) / spot * SECONDS_PER_YEAR / funding_interval_secs as f64)
+}
+
+// What the basis trade wants done
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BasisAction {
+    // Buy this much spot and sell as much of the perp
+    Enter { quantity: f64, basis: f64 },
+    // Sell the spot leg and buy back the perp leg
+    Exit { quantity: f64, basis: f64 },
+}
+
+/// Opens the pair once the annualized basis reaches entry_basis and
+/// unwinds it once the basis is back to exit_basis. The position is only
+/// counted as open or closed once both legs have gone out.
+#[derive(Debug, Clone)]
+pub struct BasisStrategy {
+    config: BasisConfig,
+    // Quantity held on each leg
+    open: Option<f64>,
+}
+
+impl BasisStrategy {
+    pub const NAME: &'static str = "BasisStrategy";
+
+    pub fn new(config: BasisConfig) -> Self {
+        Self { config, open: None }
+    }
+
+    pub fn config(&self) -> &BasisConfig {
+        &self.config
+    }
+
+    // Quantity held on each leg, if the pair is open
+    pub fn open_quantity(&self) -> Option<f64> {
+        self.open
+    }
+
+    pub fn evaluate(
+        &self,
+        spot: f64,
+        perp: f64,
+        funding_interval_secs: u64,
+    ) -> Option<BasisAction> {
+        let basis = annualized_basis(spot, perp, funding_interval_secs)?;
+        match self.open {
+            None if basis >= self.config.entry_basis => Some(BasisAction::Enter {
+                quantity: self.config.target_notional / spot,
+                basis,
+            }),
+            Some(quantity) if basis <= self.config.exit_basis => {
+                Some(BasisAction::Exit { quantity, basis })
+            }
+            _ => None,
+        }
+    }
+
+    // Both legs of an entry went out with this quantity each
+    pub fn opened(&mut self, quantity: f64) {
+        self.open = Some(quantity);
+    }
+
+    // Both legs of the exit went out
+    pub fn closed(&mut self) {
+        self.open = None;
+    }
+}
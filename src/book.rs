@@ -0,0 +1,551 @@
+use crate::metrics::Metrics;
+use crate::{MarketData, OrderBook, Price};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+// Updates held per symbol while waiting out a gap; the oldest go first
+const MAX_PENDING: usize = 1024;
+
+// Levels per side a Kraken checksum covers
+const CHECKSUM_DEPTH: usize = 10;
+
+// Full depth for a symbol as of `sequence`, the last update it includes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookSnapshot {
+    pub symbol: String,
+    pub sequence: u64,
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+    pub timestamp: u64,
+}
+
+// One diff: each level's new quantity, zero removing the level. Sequence
+// numbers increase by one per update.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookUpdate {
+    pub symbol: String,
+    pub sequence: u64,
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+    pub timestamp: u64,
+    // CRC32 the venue sent of its book after this update (Kraken's);
+    // checked for symbols with a checksum precision set
+    #[serde(default)]
+    pub checksum: Option<u32>,
+}
+
+// What became of an update or snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookOutcome {
+    // The book is valid and up to date
+    Applied,
+    // Already covered by the book (a duplicate or late arrival); dropped
+    Stale,
+    // An update was skipped: the book is invalid until the missing updates
+    // arrive or a snapshot is applied. Reported once per gap; the caller
+    // should request a snapshot.
+    Gap { expected: u64, received: u64 },
+    // Held until a snapshot (or the rest of a gap) arrives
+    Pending,
+    // The book no longer matched the venue's checksum after an update. It
+    // is invalid until a snapshot is applied; the caller should resubscribe.
+    Corrupt { expected: u32, computed: u32 },
+}
+
+/// Kraken's book checksum: the CRC32 of the top ten asks (best first) then
+/// the top ten bids (best first), each level's price and quantity written
+/// at the pair's precision with the decimal point and leading zeros
+/// dropped, all run together. Levels are best first on both sides.
+pub fn kraken_checksum(
+    bids: &[(f64, f64)],
+    asks: &[(f64, f64)],
+    price_precision: usize,
+    qty_precision: usize,
+) -> u32 {
+    let digits = |value: f64, precision: usize| {
+        format!("{:.*}", precision, value)
+            .replace('.', "")
+            .trim_start_matches('0')
+            .to_string()
+    };
+    let mut hasher = crc32fast::Hasher::new();
+    for &(price, quantity) in asks
+        .iter()
+        .take(CHECKSUM_DEPTH)
+        .chain(bids.iter().take(CHECKSUM_DEPTH))
+    {
+        hasher.update(digits(price, price_precision).as_bytes());
+        hasher.update(digits(quantity, qty_precision).as_bytes());
+    }
+    hasher.finalize()
+}
+
+// Total order over prices, for keying levels
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PriceKey(pub f64);
+
+impl PartialEq for PriceKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PriceKey {}
+
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+fn set_levels(side: &mut BTreeMap<PriceKey, f64>, levels: &[(f64, f64)]) {
+    for &(price, quantity) in levels {
+        if quantity > 0.0 {
+            side.insert(PriceKey(price), quantity);
+        } else {
+            side.remove(&PriceKey(price));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SymbolBook {
+    bids: BTreeMap<PriceKey, f64>,
+    asks: BTreeMap<PriceKey, f64>,
+    // Last update applied; None until the first snapshot
+    sequence: Option<u64>,
+    timestamp: u64,
+    // Updates past a gap, by sequence
+    pending: BTreeMap<u64, BookUpdate>,
+}
+
+impl SymbolBook {
+    fn is_valid(&self) -> bool {
+        self.sequence.is_some() && self.pending.is_empty()
+    }
+
+    fn hold(&mut self, update: BookUpdate) {
+        self.pending.insert(update.sequence, update);
+        if self.pending.len() > MAX_PENDING {
+            self.pending.pop_first();
+        }
+    }
+
+    // Applies held updates for as long as they follow on, checking each
+    // one's checksum given the precision to write levels at. On a mismatch
+    // the book is dropped and (expected, computed) returned.
+    fn catch_up(&mut self, precision: Option<(usize, usize)>) -> Option<(u32, u32)> {
+        let mut sequence = self.sequence?;
+        while let Some(update) = self.pending.remove(&(sequence + 1)) {
+            set_levels(&mut self.bids, &update.bids);
+            set_levels(&mut self.asks, &update.asks);
+            self.timestamp = update.timestamp;
+            sequence = update.sequence;
+            if let (Some(expected), Some((price_precision, qty_precision))) =
+                (update.checksum, precision)
+            {
+                let book = self.view("", CHECKSUM_DEPTH);
+                let computed =
+                    kraken_checksum(&book.bids, &book.asks, price_precision, qty_precision);
+                if computed != expected {
+                    *self = SymbolBook::default();
+                    return Some((expected, computed));
+                }
+            }
+        }
+        self.sequence = Some(sequence);
+        None
+    }
+
+    fn view(&self, symbol: &str, depth: usize) -> OrderBook {
+        let book = OrderBook {
+            symbol: symbol.to_string(),
+            bids: self
+                .bids
+                .iter()
+                .rev()
+                .take(depth)
+                .map(|(price, quantity)| (price.0, *quantity))
+                .collect(),
+            asks: self
+                .asks
+                .iter()
+                .take(depth)
+                .map(|(price, quantity)| (price.0, *quantity))
+                .collect(),
+            timestamp: self.timestamp,
+        };
+        book.debug_validate();
+        book
+    }
+}
+
+/// Per-symbol L2 books maintained from a snapshot plus sequenced diffs.
+/// A gap in the sequence marks the book invalid, and book() returns None
+/// for it, until the missing updates turn up or a snapshot replaces it;
+/// updates after the gap are held meanwhile and applied on top. Symbols
+/// with a checksum precision have each update's checksum verified, and a
+/// book that fails it is dropped until the next snapshot.
+#[derive(Debug, Clone, Default)]
+pub struct OrderBookManager {
+    books: HashMap<String, SymbolBook>,
+    // (price, quantity) decimals checksums are computed at, by symbol
+    checksum_precision: HashMap<String, (usize, usize)>,
+    corruptions: Option<prometheus::IntCounterVec>,
+}
+
+impl OrderBookManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Counts books failing their checksum as hft_book_checksum_failures_total
+    pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+        self.corruptions = Some(metrics.book_checksum_failures.clone());
+        self
+    }
+
+    // Verifies the symbol's updates against their checksums, writing
+    // levels at the pair's price and quantity precision
+    pub fn set_checksum_precision(
+        &mut self,
+        symbol: &str,
+        price_precision: usize,
+        qty_precision: usize,
+    ) {
+        self.checksum_precision
+            .insert(symbol.to_string(), (price_precision, qty_precision));
+    }
+
+    pub fn apply_snapshot(&mut self, snapshot: BookSnapshot) -> BookOutcome {
+        let precision = self.checksum_precision.get(&snapshot.symbol).copied();
+        let book = self.books.entry(snapshot.symbol.clone()).or_default();
+        if book.is_valid() && book.sequence.is_some_and(|s| s >= snapshot.sequence) {
+            return BookOutcome::Stale;
+        }
+        book.bids.clear();
+        book.asks.clear();
+        set_levels(&mut book.bids, &snapshot.bids);
+        set_levels(&mut book.asks, &snapshot.asks);
+        book.sequence = Some(snapshot.sequence);
+        book.timestamp = snapshot.timestamp;
+        book.pending
+            .retain(|&sequence, _| sequence > snapshot.sequence);
+        if let Some((expected, computed)) = book.catch_up(precision) {
+            return self.corrupt(&snapshot.symbol, expected, computed);
+        }
+        match book.pending.first_key_value() {
+            None => BookOutcome::Applied,
+            Some((&received, _)) => BookOutcome::Gap {
+                expected: snapshot.sequence + 1,
+                received,
+            },
+        }
+    }
+
+    pub fn apply_update(&mut self, update: BookUpdate) -> BookOutcome {
+        let precision = self.checksum_precision.get(&update.symbol).copied();
+        let symbol = update.symbol.clone();
+        let book = self.books.entry(update.symbol.clone()).or_default();
+        let Some(sequence) = book.sequence else {
+            book.hold(update);
+            return BookOutcome::Pending;
+        };
+        if update.sequence <= sequence {
+            return BookOutcome::Stale;
+        }
+        let was_valid = book.is_valid();
+        let received = update.sequence;
+        book.hold(update);
+        if let Some((expected, computed)) = book.catch_up(precision) {
+            return self.corrupt(&symbol, expected, computed);
+        }
+        if book.is_valid() {
+            BookOutcome::Applied
+        } else if was_valid {
+            BookOutcome::Gap {
+                expected: sequence + 1,
+                received,
+            }
+        } else {
+            BookOutcome::Pending
+        }
+    }
+
+    fn corrupt(&mut self, symbol: &str, expected: u32, computed: u32) -> BookOutcome {
+        println!(
+            "{} book failed its checksum ({} expected, {} computed); dropped until a snapshot",
+            symbol, expected, computed
+        );
+        if let Some(counter) = &self.corruptions {
+            counter.with_label_values(&[symbol]).inc();
+        }
+        BookOutcome::Corrupt { expected, computed }
+    }
+
+    // The top `depth` levels a side, or None while the book is invalid
+    pub fn book(&self, symbol: &str, depth: usize) -> Option<OrderBook> {
+        self.books
+            .get(symbol)
+            .filter(|book| book.is_valid())
+            .map(|book| book.view(symbol, depth))
+    }
+
+    pub fn is_valid(&self, symbol: &str) -> bool {
+        self.books.get(symbol).is_some_and(SymbolBook::is_valid)
+    }
+
+    // Last update applied to the symbol's book
+    pub fn sequence(&self, symbol: &str) -> Option<u64> {
+        self.books.get(symbol).and_then(|book| book.sequence)
+    }
+
+    // Marks the symbol's book invalid until a snapshot replaces it, as when
+    // the depth stream dropped and updates were missed
+    pub fn invalidate(&mut self, symbol: &str) {
+        if let Some(book) = self.books.get_mut(symbol) {
+            *book = SymbolBook::default();
+        }
+    }
+
+    pub fn invalidate_all(&mut self) {
+        for book in self.books.values_mut() {
+            *book = SymbolBook::default();
+        }
+    }
+
+    // Symbols waiting on a snapshot (or the rest of a gap)
+    pub fn invalid_symbols(&self) -> Vec<String> {
+        self.books
+            .iter()
+            .filter(|(_, book)| !book.is_valid())
+            .map(|(symbol, _)| symbol.clone())
+            .collect()
+    }
+}
+
+/// Serves books from an OrderBookManager, which a venue's depth stream
+/// keeps current, and prices from another feed
+pub struct ManagedBookFeed {
+    prices: Arc<dyn MarketData>,
+    books: Arc<Mutex<OrderBookManager>>,
+    depth: usize,
+}
+
+impl ManagedBookFeed {
+    pub fn new(
+        prices: Arc<dyn MarketData>,
+        books: Arc<Mutex<OrderBookManager>>,
+        depth: usize,
+    ) -> Self {
+        Self {
+            prices,
+            books,
+            depth,
+        }
+    }
+}
+
+impl MarketData for ManagedBookFeed {
+    fn get_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Price>> {
+        self.prices.get_price(symbol)
+    }
+
+    fn get_orderbook<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<OrderBook>> {
+        let book = self.books.lock().unwrap().book(symbol, self.depth);
+        Box::pin(async move { book })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(sequence: u64, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> BookSnapshot {
+        BookSnapshot {
+            symbol: "SOL/USDT".to_string(),
+            sequence,
+            bids: bids.to_vec(),
+            asks: asks.to_vec(),
+            timestamp: sequence,
+        }
+    }
+
+    fn update(sequence: u64, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> BookUpdate {
+        BookUpdate {
+            symbol: "SOL/USDT".to_string(),
+            sequence,
+            bids: bids.to_vec(),
+            asks: asks.to_vec(),
+            timestamp: sequence,
+            checksum: None,
+        }
+    }
+
+    type Levels = Vec<(f64, f64)>;
+
+    // (bids, asks) best first
+    fn levels(books: &OrderBookManager) -> (Levels, Levels) {
+        let book = books.book("SOL/USDT", 10).expect("the book is valid");
+        (book.bids, book.asks)
+    }
+
+    #[test]
+    fn updates_arriving_out_of_order_are_held_then_applied() {
+        let mut books = OrderBookManager::new();
+        // Updates racing ahead of the snapshot wait for it
+        assert_eq!(
+            books.apply_update(update(11, &[(99.0, 2.0)], &[])),
+            BookOutcome::Pending
+        );
+        assert!(books.book("SOL/USDT", 10).is_none());
+        let outcome = books.apply_snapshot(snapshot(10, &[(99.0, 1.0)], &[(101.0, 1.0)]));
+        assert_eq!(outcome, BookOutcome::Applied);
+        assert_eq!(books.sequence("SOL/USDT"), Some(11));
+
+        // 13 before 12: held, then both go on in order
+        let outcome = books.apply_update(update(13, &[(98.0, 4.0)], &[]));
+        assert_eq!(
+            outcome,
+            BookOutcome::Gap {
+                expected: 12,
+                received: 13
+            }
+        );
+        assert_eq!(
+            books.apply_update(update(12, &[(98.0, 3.0)], &[(102.0, 1.0)])),
+            BookOutcome::Applied
+        );
+        assert_eq!(books.sequence("SOL/USDT"), Some(13));
+        assert_eq!(
+            levels(&books),
+            (
+                vec![(99.0, 2.0), (98.0, 4.0)],
+                vec![(101.0, 1.0), (102.0, 1.0)]
+            )
+        );
+        // A late duplicate changes nothing
+        assert_eq!(
+            books.apply_update(update(12, &[(98.0, 3.0)], &[])),
+            BookOutcome::Stale
+        );
+        assert_eq!(levels(&books).0, vec![(99.0, 2.0), (98.0, 4.0)]);
+    }
+
+    #[test]
+    fn a_gap_invalidates_the_book_until_a_snapshot() {
+        let mut books = OrderBookManager::new();
+        books.apply_snapshot(snapshot(1, &[(99.0, 1.0)], &[(101.0, 1.0)]));
+        assert_eq!(
+            books.apply_update(update(2, &[(99.5, 1.0)], &[])),
+            BookOutcome::Applied
+        );
+
+        // 3 never comes: the gap is reported once and the book withheld
+        assert_eq!(
+            books.apply_update(update(4, &[(99.0, 5.0)], &[])),
+            BookOutcome::Gap {
+                expected: 3,
+                received: 4
+            }
+        );
+        assert_eq!(
+            books.apply_update(update(5, &[], &[(100.5, 1.0)])),
+            BookOutcome::Pending
+        );
+        assert!(!books.is_valid("SOL/USDT"));
+        assert!(books.book("SOL/USDT", 10).is_none());
+        assert_eq!(books.invalid_symbols(), vec!["SOL/USDT".to_string()]);
+
+        // The snapshot covers up to 4; 5 is applied on top of it
+        let outcome = books.apply_snapshot(snapshot(4, &[(99.0, 7.0)], &[(101.0, 2.0)]));
+        assert_eq!(outcome, BookOutcome::Applied);
+        assert_eq!(books.sequence("SOL/USDT"), Some(5));
+        assert_eq!(
+            levels(&books),
+            (vec![(99.0, 7.0)], vec![(100.5, 1.0), (101.0, 2.0)])
+        );
+        assert!(books.invalid_symbols().is_empty());
+    }
+
+    #[test]
+    fn a_zero_quantity_removes_the_level() {
+        let mut books = OrderBookManager::new();
+        books.apply_snapshot(snapshot(
+            1,
+            &[(99.0, 1.0), (98.0, 2.0)],
+            &[(101.0, 1.0), (102.0, 2.0)],
+        ));
+        books.apply_update(update(2, &[(99.0, 0.0)], &[(102.0, 0.0), (103.0, 1.0)]));
+        assert_eq!(
+            levels(&books),
+            (vec![(98.0, 2.0)], vec![(101.0, 1.0), (103.0, 1.0)])
+        );
+        // Removing a level that isn't there is harmless
+        assert_eq!(
+            books.apply_update(update(3, &[(97.0, 0.0)], &[])),
+            BookOutcome::Applied
+        );
+        assert_eq!(levels(&books).0, vec![(98.0, 2.0)]);
+    }
+
+    // The book in Kraken's checksum guide, 0.000005 at every level
+    fn kraken_example() -> (Levels, Levels) {
+        let level = |price: f64| (price, 0.000005);
+        let bids = [
+            0.05, 0.04995, 0.0499, 0.0498, 0.04975, 0.0497, 0.04965, 0.0496, 0.04955, 0.0495,
+        ];
+        let asks = [
+            0.05005, 0.0501, 0.05015, 0.0502, 0.05025, 0.0503, 0.05035, 0.0504, 0.05045, 0.0505,
+        ];
+        (
+            bids.into_iter().map(level).collect(),
+            asks.into_iter().map(level).collect(),
+        )
+    }
+
+    #[test]
+    fn the_checksum_matches_krakens_documented_example() {
+        let (bids, asks) = kraken_example();
+        assert_eq!(kraken_checksum(&bids, &asks, 5, 8), 974_947_235);
+    }
+
+    #[test]
+    fn updates_are_checked_against_the_venues_checksum() {
+        let mut books = OrderBookManager::new();
+        books.set_checksum_precision("SOL/USDT", 5, 8);
+        let (bids, asks) = kraken_example();
+        assert_eq!(
+            books.apply_snapshot(snapshot(1, &bids, &asks)),
+            BookOutcome::Applied
+        );
+
+        // A new bid pushes 0.0495 out of the ten levels checked
+        let matching = BookUpdate {
+            checksum: Some(2_726_735_196),
+            ..update(2, &[(0.04985, 0.000005)], &[])
+        };
+        assert_eq!(books.apply_update(matching), BookOutcome::Applied);
+        let wrong = BookUpdate {
+            checksum: Some(974_947_235),
+            ..update(3, &[(0.05, 0.00001)], &[])
+        };
+        assert!(matches!(
+            books.apply_update(wrong),
+            BookOutcome::Corrupt {
+                expected: 974_947_235,
+                ..
+            }
+        ));
+        assert!(!books.is_valid("SOL/USDT"));
+    }
+}
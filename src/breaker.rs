@@ -0,0 +1,206 @@
+use crate::indicators::RealizedVolatility;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+// Pauses a symbol after an abnormal price move
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BreakerConfig {
+    pub enabled: bool,
+    // The move is measured from the oldest price within this many seconds
+    pub horizon_secs: u64,
+    // Trip on a move at least this large, as a fraction (0.05 = 5%)
+    pub max_move_pct: Option<f64>,
+    // Trip on a move this many standard deviations of the trailing tick
+    // returns, scaled to the ticks in the horizon
+    pub max_z_score: Option<f64>,
+    // Tick returns in the trailing volatility the z-score is against
+    pub volatility_window: usize,
+    // How long new entries stay paused once tripped
+    pub cooldown_secs: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            horizon_secs: 10,
+            max_move_pct: Some(0.05),
+            max_z_score: Some(8.0),
+            volatility_window: 100,
+            cooldown_secs: 60,
+        }
+    }
+}
+
+impl BreakerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.max_move_pct.is_none() && self.max_z_score.is_none() {
+            return Err("Breaker needs max_move_pct or max_z_score".to_string());
+        }
+        if self
+            .max_move_pct
+            .is_some_and(|pct| pct.is_nan() || pct <= 0.0)
+        {
+            return Err("Breaker max_move_pct must be positive".to_string());
+        }
+        if self.max_z_score.is_some_and(|z| z.is_nan() || z <= 0.0) {
+            return Err("Breaker max_z_score must be positive".to_string());
+        }
+        if self.horizon_secs == 0 || self.cooldown_secs == 0 {
+            return Err("Breaker horizon_secs and cooldown_secs must be positive".to_string());
+        }
+        if self.volatility_window < 2 {
+            return Err("Breaker volatility_window must be at least 2 returns".to_string());
+        }
+        Ok(())
+    }
+}
+
+// A symbol's breaker tripping
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trip {
+    // Signed fraction over the horizon
+    pub move_pct: f64,
+    // None while the trailing volatility is warming up
+    pub z_score: Option<f64>,
+    pub until_ms: u64,
+}
+
+// A tripped symbol, as the status API shows it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrippedSymbol {
+    pub symbol: String,
+    pub remaining_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+struct SymbolBreaker {
+    // (received at ms, price) within the horizon, oldest first
+    recent: VecDeque<(u64, f64)>,
+    volatility: RealizedVolatility,
+    tripped_until_ms: Option<u64>,
+}
+
+/// Watches each symbol's short-horizon move against its trailing
+/// volatility. A tripped symbol opens no new positions until its cooldown
+/// runs out; its protective orders keep working.
+#[derive(Debug, Clone, Default)]
+pub struct VolatilityBreaker {
+    config: BreakerConfig,
+    symbols: HashMap<String, SymbolBreaker>,
+}
+
+impl VolatilityBreaker {
+    pub fn new(config: &BreakerConfig) -> Self {
+        Self {
+            config: config.clone(),
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Takes a price and returns the trip if it makes the symbol's move
+    /// abnormal. A symbol already tripped has its cooldown extended
+    /// instead.
+    pub fn on_price(&mut self, symbol: &str, price: f64, now_ms: u64) -> Option<Trip> {
+        if !(self.config.enabled && price.is_finite() && price > 0.0) {
+            return None;
+        }
+        let config = &self.config;
+        let breaker = self
+            .symbols
+            .entry(symbol.to_string())
+            .or_insert_with(|| SymbolBreaker {
+                recent: VecDeque::new(),
+                volatility: RealizedVolatility::new(config.volatility_window, None),
+                tripped_until_ms: None,
+            });
+        let horizon_ms = config.horizon_secs * 1000;
+        while breaker
+            .recent
+            .front()
+            .is_some_and(|&(at_ms, _)| at_ms + horizon_ms < now_ms)
+        {
+            breaker.recent.pop_front();
+        }
+
+        // Judged against the volatility before this tick, so a jump can't
+        // dampen its own z-score
+        let trip = breaker.recent.front().and_then(|&(_, reference)| {
+            let move_pct = price / reference - 1.0;
+            let returns = breaker.recent.len() as f64;
+            let z_score = breaker
+                .volatility
+                .value()
+                .filter(|&std_dev| std_dev > 0.0)
+                .map(|std_dev| (price / reference).ln().abs() / (std_dev * returns.sqrt()));
+            let abnormal = config.max_move_pct.is_some_and(|max| move_pct.abs() >= max)
+                || config
+                    .max_z_score
+                    .zip(z_score)
+                    .is_some_and(|(max, z)| z >= max);
+            abnormal.then_some(Trip {
+                move_pct,
+                z_score,
+                until_ms: now_ms + config.cooldown_secs * 1000,
+            })
+        });
+        breaker.recent.push_back((now_ms, price));
+        breaker.volatility.update(price);
+
+        let trip = trip?;
+        let already_tripped = breaker
+            .tripped_until_ms
+            .is_some_and(|until_ms| until_ms > now_ms);
+        breaker.tripped_until_ms = Some(trip.until_ms);
+        (!already_tripped).then_some(trip)
+    }
+
+    pub fn is_tripped(&self, symbol: &str, now_ms: u64) -> bool {
+        self.symbols
+            .get(symbol)
+            .and_then(|breaker| breaker.tripped_until_ms)
+            .is_some_and(|until_ms| until_ms > now_ms)
+    }
+
+    // Clears the symbol's trip once its cooldown is over; true if it did
+    pub fn take_resumed(&mut self, symbol: &str, now_ms: u64) -> bool {
+        let Some(breaker) = self.symbols.get_mut(symbol) else {
+            return false;
+        };
+        match breaker.tripped_until_ms {
+            Some(until_ms) if until_ms <= now_ms => {
+                breaker.tripped_until_ms = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn tripped(&self, now_ms: u64) -> Vec<TrippedSymbol> {
+        let mut tripped: Vec<TrippedSymbol> = self
+            .symbols
+            .iter()
+            .filter_map(|(symbol, breaker)| {
+                let until_ms = breaker.tripped_until_ms.filter(|&until| until > now_ms)?;
+                Some(TrippedSymbol {
+                    symbol: symbol.clone(),
+                    remaining_ms: until_ms - now_ms,
+                })
+            })
+            .collect();
+        tripped.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        tripped
+    }
+
+    pub fn remove(&mut self, symbol: &str) {
+        self.symbols.remove(symbol);
+    }
+}
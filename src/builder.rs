@@ -0,0 +1,414 @@
+use crate::account::Account;
+use crate::aggregator::SignalAggregator;
+use crate::allocation::StrategyAllocator;
+use crate::attribution::StrategyStats;
+use crate::basis::BasisStrategy;
+use crate::breaker::VolatilityBreaker;
+use crate::candles::CandleAggregator;
+use crate::clock::{Clock, SystemClock};
+use crate::config::BotConfig;
+use crate::correlation::CorrelationTracker;
+use crate::currency::CurrencyConverter;
+use crate::equity::EquityCurve;
+use crate::events::EVENT_CAPACITY;
+use crate::external::ExternalSignals;
+use crate::funding::FundingRates;
+use crate::indicators::IndicatorHub;
+use crate::journal::{Journal, JournalStore};
+use crate::latency::LatencyRecorder;
+use crate::marks::MarkPriceCache;
+use crate::metrics::Metrics;
+use crate::monitor::PositionMonitor;
+use crate::staleness::FreshnessTracker;
+use crate::streaks::StreakTracker;
+use crate::symbols::{SymbolMap, SymbolRegistry, assets};
+use crate::tick_filter::TickFilter;
+use crate::venue::{DryRunExchange, Exchange, ExecutionMode, PaperExchange, VenueId};
+use crate::{
+    BookCache, MarketData, OrderExecutor, RiskManager, RiskParams, StrategyEntry, TradingBot,
+    TradingStrategy,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use tokio::sync::{Mutex, Notify, RwLock, Semaphore, broadcast};
+
+/// Assembles a TradingBot. Starts from a BotConfig, the defaults unless one
+/// is given; components handed in directly take the place of the ones the
+/// config would build.
+pub struct TradingBotBuilder {
+    config: BotConfig,
+    // Used instead of config.strategies when any are given
+    strategies: Vec<StrategyEntry>,
+    feed: Option<Arc<dyn MarketData>>,
+    executor: Option<OrderExecutor>,
+    // Used instead of the config's venues of the same id
+    venues: Vec<Arc<dyn Exchange>>,
+    // Live mode sends orders to venues only once this is set
+    live_confirmed: bool,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for TradingBotBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TradingBotBuilder {
+    pub fn new() -> Self {
+        Self::from_config(&BotConfig::default())
+    }
+
+    pub fn from_config(config: &BotConfig) -> Self {
+        Self {
+            config: config.clone(),
+            strategies: Vec::new(),
+            feed: None,
+            executor: None,
+            venues: Vec::new(),
+            live_confirmed: false,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_symbols(mut self, symbols: Vec<String>) -> Self {
+        self.config.symbols = symbols;
+        self
+    }
+
+    // Adds a strategy evaluated on every symbol. The first one added
+    // replaces the config's strategies.
+    pub fn with_strategy(self, strategy: Box<dyn TradingStrategy>) -> Self {
+        self.with_strategy_entry(StrategyEntry::new(strategy))
+    }
+
+    // Adds a strategy with its symbols or trigger already set
+    pub fn with_strategy_entry(mut self, entry: StrategyEntry) -> Self {
+        self.strategies.push(entry);
+        self
+    }
+
+    pub fn with_risk_params(mut self, params: RiskParams) -> Self {
+        self.config.risk = params;
+        self
+    }
+
+    // Where prices and books come from, instead of the simulator
+    pub fn with_feed(mut self, feed: impl MarketData + 'static) -> Self {
+        self.feed = Some(Arc::new(feed));
+        self
+    }
+
+    // Submits the bot's orders, instead of a paper executor built from
+    // the config. Its settings are kept; only its book cache is replaced
+    // with the bot's.
+    pub fn with_executor(mut self, executor: OrderExecutor) -> Self {
+        self.executor = Some(executor);
+        self
+    }
+
+    // A venue to trade on, already connected. Orders go to it if it is
+    // the config's primary venue.
+    pub fn with_venue(mut self, venue: Arc<dyn Exchange>) -> Self {
+        self.venues.push(venue);
+        self
+    }
+
+    // Acknowledges that a live-mode config sends real orders. Without it a
+    // live config is refused by build() and dry-run by assembly.
+    pub fn confirm_live(mut self) -> Self {
+        self.live_confirmed = true;
+        self
+    }
+
+    // Timestamps, trading days and loop pacing
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // The bot, once the configuration checks out: at least one symbol and
+    // one strategy, and every config section valid
+    pub fn build(self) -> Result<TradingBot, String> {
+        if self.config.symbols.is_empty() {
+            return Err("A bot needs at least one symbol".to_string());
+        }
+        if self.strategies.is_empty() && self.config.strategies.is_empty() {
+            return Err("A bot needs at least one strategy".to_string());
+        }
+        // A primary venue handed in needn't be in [venues] too, so the rest
+        // of the config is checked as if it traded on paper
+        let mut checked = self.config.clone();
+        if self
+            .venues
+            .iter()
+            .any(|v| *v.id() == checked.venues.primary)
+        {
+            checked.venues.primary = VenueId::paper();
+            checked.mode = ExecutionMode::Paper;
+        }
+        checked.validate()?;
+        if self.config.mode == ExecutionMode::Live && !self.live_confirmed {
+            return Err("Live mode sends real orders and must be confirmed".to_string());
+        }
+        Ok(self.assemble())
+    }
+
+    // The bot, without checking the configuration
+    pub(crate) fn assemble(self) -> TradingBot {
+        let config = self.config;
+        let clock = self.clock;
+        let mut mode = config.mode;
+        if mode == ExecutionMode::Live && !self.live_confirmed {
+            println!("Live mode wasn't confirmed; dry-running instead");
+            mode = ExecutionMode::DryRun;
+        }
+        let strategies: Vec<StrategyEntry> = if self.strategies.is_empty() {
+            config
+                .strategies
+                .iter()
+                .map(|sc| {
+                    let entry = StrategyEntry::new(sc.kind.build())
+                        .with_trigger(sc.trigger)
+                        .with_mode(sc.mode)
+                        .with_max_holding_secs(sc.max_holding_secs);
+                    match &sc.symbols {
+                        Some(symbols) => entry.with_symbols(symbols.clone()),
+                        None => entry,
+                    }
+                })
+                .collect()
+        } else {
+            self.strategies
+        };
+
+        for entry in &strategies {
+            match &entry.symbols {
+                Some(symbols) => println!(
+                    "Strategy {} assigned to symbols: {:?}",
+                    entry.strategy.name(),
+                    symbols
+                ),
+                None => println!("Strategy {} assigned to all symbols", entry.strategy.name()),
+            }
+        }
+
+        let mut indicators = IndicatorHub::new();
+        indicators.register_volatility(
+            config.volatility.window,
+            config.volatility.annualization_periods,
+        );
+        for entry in &strategies {
+            for window in entry.strategy.indicator_windows() {
+                indicators.register_window(window);
+            }
+            for spec in entry.strategy.ofi_specs() {
+                indicators.register_ofi(spec);
+            }
+        }
+        let mut candles = CandleAggregator::new(&config.candles);
+        for entry in &strategies {
+            for timeframe in entry.strategy.candle_timeframes() {
+                candles.register_timeframe(timeframe);
+            }
+        }
+        candles.register_atr(
+            config.volatility.atr_timeframe,
+            config.volatility.atr_period,
+        );
+
+        let order_books: BookCache = Arc::new(RwLock::new(HashMap::new()));
+
+        let journal = if config.journal.enabled {
+            match Journal::open(&config.journal) {
+                Ok(journal) => Some(journal),
+                Err(e) => {
+                    println!("Trade journal disabled: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        // An injected executor keeps its own settings but fills against
+        // the bot's books
+        let mut order_executor = match self.executor {
+            Some(executor) => executor.with_order_books(Arc::clone(&order_books)),
+            None => OrderExecutor::with_rate_limits(&config.rate_limits)
+                .with_retry(config.retry.clone())
+                .with_order_books(Arc::clone(&order_books))
+                .with_paper_config(config.paper.clone())
+                .with_fee_model(config.account.fees.clone())
+                .with_symbol_meta(SymbolRegistry::new(config.symbol_meta.clone()))
+                .with_clock(clock.clone()),
+        };
+        if let Some(journal) = &journal {
+            order_executor = order_executor.with_journal(journal.clone());
+        }
+        // Streaks and cooldowns carry over a restart through the journal
+        let mut streaks = StreakTracker::new(config.streaks.clone());
+        if config.streaks.enabled && journal.is_some() {
+            match JournalStore::open(&config.journal.path).and_then(|store| store.streaks()) {
+                Ok(restored) => streaks.restore(restored),
+                Err(e) => println!("Couldn't restore strategy streaks: {}", e),
+            }
+        }
+        // Quotes and balances with no way into the reporting currency are
+        // taken 1:1, and flagged as such in the reports
+        let account = Account::new(&config.account).with_perps(config.perp_symbols());
+        let currency = CurrencyConverter::new(&config.currency).with_symbols(config.symbols.iter());
+        let held = config
+            .symbols
+            .iter()
+            .filter_map(|symbol| assets(symbol).map(|(_, quote)| quote))
+            .chain(account.balances().keys().map(String::as_str));
+        for asset in currency.missing(held) {
+            println!(
+                "No rate from {} to {}; taken 1:1 until one turns up",
+                asset,
+                currency.reporting()
+            );
+        }
+        let risk_manager = RiskManager::with_symbol_params(
+            config.risk.clone(),
+            config
+                .resolved_symbol_risk()
+                .expect("symbol risk overrides are checked by BotConfig::validate"),
+        )
+        .with_account(account)
+        .with_clock(clock.clone())
+        .with_session(&config.session)
+        .with_marks(Arc::new(MarkPriceCache::new(config.marks.source)))
+        .with_currency(Arc::new(currency))
+        .with_correlations(Arc::new(CorrelationTracker::new(
+            config.correlation.clone(),
+        )))
+        .with_open_orders(order_executor.open_order_tracker());
+        // The day's PnL, and with it the daily loss limit, carries over a
+        // restart through the journal too
+        if journal.is_some() {
+            let session = risk_manager.session();
+            match JournalStore::open(&config.journal.path)
+                .and_then(|store| store.session(session.session()))
+            {
+                Ok(Some(stats)) => {
+                    session.restore(stats);
+                }
+                Ok(None) => {}
+                Err(e) => println!("Couldn't restore the trading session: {}", e),
+            }
+        }
+        // Reduce-only orders are capped at the positions as booked
+        let order_executor = Arc::new(order_executor.with_positions(risk_manager.position_book()));
+
+        let mut venues: BTreeMap<VenueId, Arc<dyn Exchange>> = BTreeMap::new();
+        match config.venues.build(clock.clone()) {
+            Ok(built) => venues.extend(built.into_iter().map(|v| (v.id().clone(), v))),
+            Err(e) => println!("Venues disabled: {}", e),
+        }
+        venues.extend(self.venues.into_iter().map(|v| (v.id().clone(), v)));
+        let mut primary = match mode {
+            ExecutionMode::Paper => VenueId::paper(),
+            ExecutionMode::DryRun | ExecutionMode::Live => config.venues.primary.clone(),
+        };
+        if !venues.contains_key(&primary) && primary != VenueId::paper() {
+            println!("Primary venue {} unavailable; trading on paper", primary);
+            primary = VenueId::paper();
+            mode = ExecutionMode::Paper;
+        }
+        // Outside live mode no venue is sent an order, even to flatten a
+        // position recovered from a live run
+        if mode != ExecutionMode::Live {
+            for venue in venues.values_mut() {
+                *venue = Arc::new(DryRunExchange::new(venue.clone(), order_executor.clone()));
+            }
+        }
+        // The primary venue's prices, unless a feed was given
+        let market_feed = match (self.feed, venues.get(&primary)) {
+            (Some(feed), _) => feed,
+            (None, Some(venue)) => venue.market_data(),
+            (None, None) => TradingBot::market_feed(&config, clock.clone()),
+        };
+        venues.insert(
+            VenueId::paper(),
+            Arc::new(PaperExchange::new(
+                market_feed.clone(),
+                order_executor.clone(),
+                clock.clone(),
+            )),
+        );
+
+        // Funding times already past at startup aren't settled again
+        let funding_time = config.funding.funding_time(clock.now_secs());
+
+        TradingBot {
+            strategies: Arc::new(RwLock::new(strategies)),
+            aggregator: Arc::new(SignalAggregator::new(config.aggregation.clone())),
+            risk_manager: Arc::new(risk_manager),
+            order_executor,
+            market_feed,
+            order_books,
+            price_history: Arc::new(RwLock::new(SymbolMap::new())),
+            price_history_config: config.price_history.clone(),
+            indicators: Arc::new(RwLock::new(indicators)),
+            candles: Arc::new(RwLock::new(candles)),
+            atr_timeframe: config.volatility.atr_timeframe,
+            staleness: config.staleness.clone(),
+            freshness: Arc::new(Mutex::new(FreshnessTracker::new())),
+            schedule: config.schedule.clone(),
+            calendar: Arc::new(
+                config
+                    .calendar
+                    .calendar()
+                    .expect("the calendar is checked by BotConfig::validate"),
+            ),
+            sessions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            breaker: Arc::new(std::sync::Mutex::new(VolatilityBreaker::new(
+                &config.breaker,
+            ))),
+            tick_filter: Arc::new(std::sync::Mutex::new(TickFilter::new(&config.tick_filter))),
+            supervision: config.supervision.clone(),
+            data_ready: Arc::new(std::sync::Mutex::new(SymbolMap::new())),
+            evaluation_slots: Arc::new(Semaphore::new(config.schedule.max_concurrent_evaluations)),
+            price_received: Arc::new(std::sync::Mutex::new(SymbolMap::new())),
+            latency: Arc::new(LatencyRecorder::new()),
+            is_running: Arc::new(Mutex::new(false)),
+            halted: Arc::new(AtomicBool::new(false)),
+            loss_limit_day: Arc::new(AtomicU64::new(0)),
+            venues: Arc::new(venues),
+            venue: primary,
+            mode,
+            protective_orders: Arc::new(Mutex::new(HashMap::new())),
+            clock,
+            journal,
+            state: config.state.clone(),
+            metrics: Arc::new(Metrics::new()),
+            symbols: Arc::new(Mutex::new(BTreeMap::new())),
+            external_signals: Arc::new(ExternalSignals::default()),
+            strategy_stats: Arc::new(std::sync::Mutex::new(StrategyStats::new())),
+            allocator: Arc::new(StrategyAllocator::new(config.allocation.clone())),
+            equity_curve: Arc::new(EquityCurve::new(config.equity.clone())),
+            shadow_stats: Arc::new(std::sync::Mutex::new(BTreeMap::new())),
+            attribution: config.attribution.clone(),
+            marks: config.marks.clone(),
+            holding: config.holding.clone(),
+            monitor: Arc::new(std::sync::Mutex::new(PositionMonitor::new())),
+            monitor_ready: Arc::new(Notify::new()),
+            reconcile: config.reconcile.clone(),
+            adopted_orders: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            report: config.report.clone(),
+            order_guard: config.order_guard.clone(),
+            hedge_mode: config.account.hedge_mode,
+            depth_sizing: config.depth_sizing.clone(),
+            basis: Arc::new(std::sync::Mutex::new(BasisStrategy::new(
+                config.basis.clone(),
+            ))),
+            funding: config.funding.clone(),
+            funding_rates: Arc::new(FundingRates::new(config.funding.rates.clone())),
+            funding_time: Arc::new(AtomicU64::new(funding_time)),
+            streaks: Arc::new(std::sync::Mutex::new(streaks)),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        }
+    }
+}
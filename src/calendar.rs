@@ -0,0 +1,275 @@
+use crate::history::parse_timestamp_ms;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const MS_PER_MINUTE: u64 = 60_000;
+const MINUTES_PER_DAY: u64 = 1_440;
+const MS_PER_DAY: u64 = MINUTES_PER_DAY * MS_PER_MINUTE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Weekday {
+    // Monday first
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// The UTC weekday (Monday = 0) of a time; 1970-01-01 was a Thursday
+fn weekday_index(now_ms: u64) -> usize {
+    ((now_ms / MS_PER_DAY + 3) % 7) as usize
+}
+
+/// A weekly recurring window the bot trades in, in UTC. A window ending
+/// before it starts runs past midnight into the next day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradingWindow {
+    // Days the window starts on; empty means every day
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    // "HH:MM"; end can be "24:00"
+    pub start: String,
+    pub end: String,
+    // Symbols the window covers; None = all
+    #[serde(default)]
+    pub symbols: Option<Vec<String>>,
+}
+
+/// A one-off period with no trading, such as a scheduled announcement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Blackout {
+    // RFC 3339 UTC ("2026-11-04T18:45:00Z")
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    // Symbols halted; None = all
+    #[serde(default)]
+    pub symbols: Option<Vec<String>>,
+}
+
+// When symbols may open new positions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    pub enabled: bool,
+    // A symbol covered by any window trades only inside one; a symbol no
+    // window covers trades around the clock
+    pub windows: Vec<TradingWindow>,
+    pub blackouts: Vec<Blackout>,
+    // Cancel a symbol's working orders (not its stops) when it closes
+    pub cancel_orders: bool,
+    // Flatten positions this many minutes before a blackout, halting new
+    // entries from then on; None leaves them open through it
+    pub flatten_before_blackout_mins: Option<u64>,
+}
+
+impl CalendarConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        self.calendar().map(|_| ())
+    }
+
+    // The calendar with its times parsed
+    pub fn calendar(&self) -> Result<TradingCalendar, String> {
+        let windows = self
+            .windows
+            .iter()
+            .map(|window| {
+                let start = parse_time_of_day(&window.start)?;
+                let end = parse_time_of_day(&window.end)?;
+                if start == MINUTES_PER_DAY {
+                    return Err(format!("Calendar window can't start at {}", window.start));
+                }
+                if start == end {
+                    return Err(format!(
+                        "Calendar window {}-{} is empty; use 00:00-24:00 for a whole day",
+                        window.start, window.end
+                    ));
+                }
+                let mut days = [window.days.is_empty(); 7];
+                for day in &window.days {
+                    days[day.index()] = true;
+                }
+                Ok(Window {
+                    days,
+                    start,
+                    end,
+                    symbols: window.symbols.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let blackouts = self
+            .blackouts
+            .iter()
+            .map(|blackout| {
+                let start_ms = parse_timestamp_ms(&blackout.start)?;
+                let end_ms = parse_timestamp_ms(&blackout.end)?;
+                if end_ms <= start_ms {
+                    return Err(format!(
+                        "Calendar blackout {} must end after it starts",
+                        blackout.start
+                    ));
+                }
+                Ok(Period {
+                    start_ms,
+                    end_ms,
+                    reason: blackout.reason.clone(),
+                    symbols: blackout.symbols.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(TradingCalendar {
+            enabled: self.enabled,
+            windows,
+            blackouts,
+            cancel_orders: self.cancel_orders,
+            flatten_lead_ms: self
+                .flatten_before_blackout_mins
+                .map(|mins| mins * MS_PER_MINUTE),
+        })
+    }
+}
+
+// Minutes past midnight of "HH:MM", up to 24:00
+fn parse_time_of_day(value: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid time of day {} (expected HH:MM)", value);
+    let (hours, minutes) = value.split_once(':').ok_or_else(invalid)?;
+    let (Ok(hours), Ok(minutes)) = (hours.parse::<u64>(), minutes.parse::<u64>()) else {
+        return Err(invalid());
+    };
+    if minutes > 59 || hours * 60 + minutes > MINUTES_PER_DAY {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+fn covers(symbols: &Option<Vec<String>>, symbol: &str) -> bool {
+    symbols
+        .as_ref()
+        .is_none_or(|symbols| symbols.iter().any(|s| s == symbol))
+}
+
+#[derive(Debug, Clone)]
+struct Window {
+    days: [bool; 7],
+    // Minutes past midnight
+    start: u64,
+    end: u64,
+    symbols: Option<Vec<String>>,
+}
+
+impl Window {
+    fn contains(&self, now_ms: u64) -> bool {
+        let minute = now_ms % MS_PER_DAY / MS_PER_MINUTE;
+        let day = weekday_index(now_ms);
+        if self.start < self.end {
+            return self.days[day] && (self.start..self.end).contains(&minute);
+        }
+        // Overnight: the evening of a listed day, or the morning after one
+        (self.days[day] && minute >= self.start) || (self.days[(day + 6) % 7] && minute < self.end)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Period {
+    start_ms: u64,
+    end_ms: u64,
+    reason: Option<String>,
+    symbols: Option<Vec<String>>,
+}
+
+/// Whether a symbol may open new positions right now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionState {
+    Open,
+    // Outside every trading window covering the symbol
+    OutsideHours,
+    // In a blackout, or close enough to one that positions are flattened
+    Blackout {
+        reason: Option<String>,
+        flatten: bool,
+    },
+}
+
+impl SessionState {
+    pub fn is_open(&self) -> bool {
+        *self == SessionState::Open
+    }
+}
+
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionState::Open => f.write_str("open"),
+            SessionState::OutsideHours => f.write_str("outside trading hours"),
+            SessionState::Blackout {
+                reason: Some(reason),
+                ..
+            } => write!(f, "blackout: {}", reason),
+            SessionState::Blackout { reason: None, .. } => f.write_str("blackout"),
+        }
+    }
+}
+
+/// The trading windows and blackouts, parsed. A closed symbol opens no new
+/// positions, but its protective stops keep working.
+#[derive(Debug, Clone, Default)]
+pub struct TradingCalendar {
+    enabled: bool,
+    windows: Vec<Window>,
+    blackouts: Vec<Period>,
+    cancel_orders: bool,
+    flatten_lead_ms: Option<u64>,
+}
+
+impl TradingCalendar {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Whether closing cancels the symbol's working orders
+    pub fn cancels_orders(&self) -> bool {
+        self.cancel_orders
+    }
+
+    pub fn state(&self, symbol: &str, now_ms: u64) -> SessionState {
+        if !self.enabled {
+            return SessionState::Open;
+        }
+        let lead_ms = self.flatten_lead_ms.unwrap_or(0);
+        if let Some(blackout) = self.blackouts.iter().find(|b| {
+            covers(&b.symbols, symbol)
+                && (b.start_ms.saturating_sub(lead_ms)..b.end_ms).contains(&now_ms)
+        }) {
+            return SessionState::Blackout {
+                reason: blackout.reason.clone(),
+                flatten: self.flatten_lead_ms.is_some(),
+            };
+        }
+        let mut windows = self
+            .windows
+            .iter()
+            .filter(|w| covers(&w.symbols, symbol))
+            .peekable();
+        if windows.peek().is_none() {
+            return SessionState::Open;
+        }
+        if windows.any(|w| w.contains(now_ms)) {
+            SessionState::Open
+        } else {
+            SessionState::OutsideHours
+        }
+    }
+}
@@ -0,0 +1,512 @@
+use crate::Price;
+use crate::indicators::Atr;
+use crate::symbols::{Symbol, SymbolId, SymbolMap};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// Bar lengths candles are built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Timeframe {
+    #[serde(rename = "1s")]
+    S1,
+    #[serde(rename = "5s")]
+    S5,
+    #[serde(rename = "1m")]
+    M1,
+    #[serde(rename = "5m")]
+    M5,
+}
+
+impl Timeframe {
+    pub const ALL: [Timeframe; 4] = [Timeframe::S1, Timeframe::S5, Timeframe::M1, Timeframe::M5];
+
+    pub fn secs(&self) -> u64 {
+        match self {
+            Timeframe::S1 => 1,
+            Timeframe::S5 => 5,
+            Timeframe::M1 => 60,
+            Timeframe::M5 => 300,
+        }
+    }
+
+    // Start of the bar containing `timestamp` (seconds)
+    pub fn open_time(&self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.secs()
+    }
+}
+
+impl std::fmt::Display for Timeframe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Timeframe::S1 => "1s",
+            Timeframe::S5 => "5s",
+            Timeframe::M1 => "1m",
+            Timeframe::M5 => "5m",
+        };
+        f.write_str(label)
+    }
+}
+
+// Candles kept per symbol, on top of those strategies ask for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CandleConfig {
+    pub timeframes: Vec<Timeframe>,
+    // Closed candles kept per symbol and timeframe
+    pub max_candles: usize,
+}
+
+impl Default for CandleConfig {
+    fn default() -> Self {
+        Self {
+            timeframes: Timeframe::ALL.to_vec(),
+            max_candles: 500,
+        }
+    }
+}
+
+impl CandleConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_candles == 0 {
+            return Err("Candle max_candles must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+// One OHLCV bar; open_time in seconds since the Unix epoch
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub open_time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    // Zero for a bar no tick arrived in, which is flat at the previous close
+    pub ticks: u64,
+}
+
+impl Candle {
+    fn new(open_time: u64, price: f64, volume: f64) -> Self {
+        Self {
+            open_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume,
+            ticks: 1,
+        }
+    }
+
+    fn empty(open_time: u64, close: f64) -> Self {
+        Self {
+            volume: 0.0,
+            ticks: 0,
+            ..Self::new(open_time, close, 0.0)
+        }
+    }
+
+    fn add(&mut self, price: f64, volume: f64) {
+        if self.ticks == 0 {
+            *self = Self::new(self.open_time, price, volume);
+            return;
+        }
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += volume;
+        self.ticks += 1;
+    }
+}
+
+// One symbol's candles of one timeframe: the closed ones, oldest first,
+// and the one still forming
+#[derive(Debug, Clone)]
+struct CandleSeries {
+    timeframe: Timeframe,
+    capacity: usize,
+    closed: VecDeque<Candle>,
+    current: Option<Candle>,
+    // Updated as each bar closes
+    atr: Option<Atr>,
+}
+
+impl CandleSeries {
+    fn new(timeframe: Timeframe, capacity: usize, atr_period: Option<usize>) -> Self {
+        Self {
+            timeframe,
+            capacity,
+            closed: VecDeque::new(),
+            current: None,
+            atr: atr_period.map(Atr::new),
+        }
+    }
+
+    fn update(&mut self, price: &Price) {
+        let open_time = self.timeframe.open_time(price.timestamp);
+        match &self.current {
+            None => self.current = Some(Candle::new(open_time, price.price, price.volume)),
+            // Late ticks for a bar that has already closed are dropped
+            Some(current) if open_time < current.open_time => {}
+            Some(_) => {
+                self.advance(price.timestamp);
+                if let Some(current) = &mut self.current {
+                    current.add(price.price, price.volume);
+                }
+            }
+        }
+    }
+
+    // Closes the forming bar once `now` is past its end. Bars with no ticks
+    // in between are flat at the last close with zero volume, and so is the
+    // bar opened for `now` until a tick arrives.
+    fn advance(&mut self, now: u64) {
+        let Some(current) = self.current else {
+            return;
+        };
+        let open_time = self.timeframe.open_time(now);
+        if open_time <= current.open_time {
+            return;
+        }
+        let step = self.timeframe.secs();
+        self.push(current);
+        // Only the newest `capacity` empty bars would survive anyway
+        let empty = (open_time - current.open_time) / step - 1;
+        let skipped = empty.saturating_sub(self.capacity as u64);
+        let mut bar = current.open_time + (skipped + 1) * step;
+        while bar < open_time {
+            self.push(Candle::empty(bar, current.close));
+            bar += step;
+        }
+        self.current = Some(Candle::empty(open_time, current.close));
+    }
+
+    fn push(&mut self, candle: Candle) {
+        if let Some(atr) = &mut self.atr {
+            atr.update(candle.high, candle.low, candle.close);
+        }
+        if self.closed.len() == self.capacity {
+            self.closed.pop_front();
+        }
+        self.closed.push_back(candle);
+    }
+
+    fn last(&self, n: usize) -> Vec<Candle> {
+        let skip = self.closed.len().saturating_sub(n);
+        self.closed.iter().skip(skip).copied().collect()
+    }
+}
+
+// Every timeframe's candles for a single symbol
+#[derive(Debug, Clone, Default)]
+pub struct SymbolCandles {
+    series: BTreeMap<Timeframe, CandleSeries>,
+}
+
+impl SymbolCandles {
+    // The newest `n` closed candles, oldest first; empty if the timeframe
+    // isn't tracked
+    pub fn candles(&self, timeframe: Timeframe, n: usize) -> Vec<Candle> {
+        self.series
+            .get(&timeframe)
+            .map_or_else(Vec::new, |series| series.last(n))
+    }
+
+    // Closed candles that opened after `last`, oldest first; with no `last`,
+    // the newest `seed`. Lets a strategy keep its own state one bar at a
+    // time without rereading bars it has seen.
+    pub fn closed_since(
+        &self,
+        timeframe: Timeframe,
+        last: Option<u64>,
+        seed: usize,
+    ) -> Vec<Candle> {
+        let Some(series) = self.series.get(&timeframe) else {
+            return Vec::new();
+        };
+        let Some(last) = last else {
+            return series.last(seed);
+        };
+        let new = series
+            .closed
+            .iter()
+            .rev()
+            .take_while(|candle| candle.open_time > last)
+            .count();
+        series.last(new)
+    }
+
+    // ATR over the timeframe's closed bars, if one is tracked and has
+    // warmed up. Empty bars count, as zero-range bars at the last close.
+    pub fn atr(&self, timeframe: Timeframe) -> Option<f64> {
+        self.series
+            .get(&timeframe)
+            .and_then(|series| series.atr.as_ref())
+            .and_then(Atr::value)
+    }
+
+    // The candle still forming
+    pub fn current(&self, timeframe: Timeframe) -> Option<&Candle> {
+        self.series
+            .get(&timeframe)
+            .and_then(|series| series.current.as_ref())
+    }
+
+    fn update(&mut self, price: &Price) {
+        for series in self.series.values_mut() {
+            series.update(price);
+        }
+    }
+
+    fn advance(&mut self, now: u64) {
+        for series in self.series.values_mut() {
+            series.advance(now);
+        }
+    }
+}
+
+/// Builds OHLCV candles per symbol from price ticks, for each registered
+/// timeframe. Bars close on their time boundary rather than on the next
+/// tick: advance() closes every bar whose interval has passed, so a quiet
+/// symbol still gets its (flat, zero-volume) bars.
+#[derive(Debug, Clone)]
+pub struct CandleAggregator {
+    timeframes: Vec<Timeframe>,
+    capacity: usize,
+    // ATR period per timeframe it is tracked on
+    atr_periods: HashMap<Timeframe, usize>,
+    symbols: SymbolMap<SymbolCandles>,
+}
+
+impl CandleAggregator {
+    pub fn new(config: &CandleConfig) -> Self {
+        let mut aggregator = Self {
+            timeframes: Vec::new(),
+            capacity: config.max_candles,
+            atr_periods: HashMap::new(),
+            symbols: SymbolMap::new(),
+        };
+        for &timeframe in &config.timeframes {
+            aggregator.register_timeframe(timeframe);
+        }
+        aggregator
+    }
+
+    // Timeframes registered after data has arrived start with the next tick
+    pub fn register_timeframe(&mut self, timeframe: Timeframe) {
+        if self.timeframes.contains(&timeframe) {
+            return;
+        }
+        self.timeframes.push(timeframe);
+        let atr_period = self.atr_periods.get(&timeframe).copied();
+        for candles in self.symbols.values_mut() {
+            candles.series.insert(
+                timeframe,
+                CandleSeries::new(timeframe, self.capacity, atr_period),
+            );
+        }
+    }
+
+    // Tracks an ATR of `period` bars on the timeframe for every symbol,
+    // registering the timeframe if need be. The ATR starts warming up with
+    // the next bar that closes.
+    pub fn register_atr(&mut self, timeframe: Timeframe, period: usize) {
+        self.register_timeframe(timeframe);
+        self.atr_periods.insert(timeframe, period);
+        for candles in self.symbols.values_mut() {
+            if let Some(series) = candles.series.get_mut(&timeframe) {
+                series.atr = Some(Atr::new(period));
+            }
+        }
+    }
+
+    pub fn update(&mut self, symbol: &Symbol, price: &Price) {
+        if self.symbols.get(symbol.id()).is_none() {
+            let candles = self.new_symbol_candles();
+            self.symbols.insert(symbol.id(), candles);
+        }
+        self.symbols.get_mut(symbol.id()).unwrap().update(price);
+    }
+
+    // Closes every symbol's bars that ended by `now` (seconds)
+    pub fn advance(&mut self, now: u64) {
+        for candles in self.symbols.values_mut() {
+            candles.advance(now);
+        }
+    }
+
+    pub fn get(&self, id: SymbolId) -> Option<&SymbolCandles> {
+        self.symbols.get(id)
+    }
+
+    pub fn remove(&mut self, id: SymbolId) {
+        self.symbols.remove(id);
+    }
+
+    pub fn candles(&self, id: SymbolId, timeframe: Timeframe, n: usize) -> Vec<Candle> {
+        self.get(id)
+            .map_or_else(Vec::new, |candles| candles.candles(timeframe, n))
+    }
+
+    fn new_symbol_candles(&self) -> SymbolCandles {
+        SymbolCandles {
+            series: self
+                .timeframes
+                .iter()
+                .map(|&timeframe| (timeframe, self.new_series(timeframe)))
+                .collect(),
+        }
+    }
+
+    fn new_series(&self, timeframe: Timeframe) -> CandleSeries {
+        CandleSeries::new(
+            timeframe,
+            self.capacity,
+            self.atr_periods.get(&timeframe).copied(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbols::Symbol;
+
+    // 2024-01-01T00:00:00Z, on every timeframe's boundary
+    const T: u64 = 1_704_067_200;
+
+    fn aggregator(timeframes: &[Timeframe], max_candles: usize) -> CandleAggregator {
+        CandleAggregator::new(&CandleConfig {
+            timeframes: timeframes.to_vec(),
+            max_candles,
+        })
+    }
+
+    // SOL/USDT is the first symbol interned, ETH/USDT the second
+    fn symbols() -> (Symbol, Symbol) {
+        (Symbol::intern("SOL/USDT"), Symbol::intern("ETH/USDT"))
+    }
+
+    fn sol() -> SymbolId {
+        symbols().0.id()
+    }
+
+    fn tick(candles: &mut CandleAggregator, timestamp: u64, price: f64, volume: f64) {
+        candles.update(
+            &symbols().0,
+            &Price {
+                symbol: "SOL/USDT".to_string(),
+                price,
+                timestamp,
+                volume,
+            },
+        );
+    }
+
+    fn bar(open_time: u64, ohlc: [f64; 4], volume: f64, ticks: u64) -> Candle {
+        let [open, high, low, close] = ohlc;
+        Candle {
+            open_time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            ticks,
+        }
+    }
+
+    // SOL/USDT's forming 5s bar
+    fn forming(candles: &CandleAggregator) -> Option<Candle> {
+        candles.get(sol())?.current(Timeframe::S5).copied()
+    }
+
+    #[test]
+    fn ticks_across_boundaries_and_a_gap() {
+        let mut candles = aggregator(&[Timeframe::S5, Timeframe::M1], 100);
+        tick(&mut candles, T + 1, 10.0, 1.0);
+        tick(&mut candles, T + 3, 12.0, 2.0);
+        tick(&mut candles, T + 4, 9.0, 1.0);
+        tick(&mut candles, T + 5, 11.0, 3.0);
+        tick(&mut candles, T + 7, 13.0, 1.0);
+        // Nothing from T+10 to T+20
+        tick(&mut candles, T + 21, 14.0, 2.0);
+
+        assert_eq!(
+            candles.candles(sol(), Timeframe::S5, 10),
+            [
+                bar(T, [10.0, 12.0, 9.0, 9.0], 4.0, 3),
+                bar(T + 5, [11.0, 13.0, 11.0, 13.0], 4.0, 2),
+                bar(T + 10, [13.0; 4], 0.0, 0),
+                bar(T + 15, [13.0; 4], 0.0, 0),
+            ]
+        );
+        let symbol = candles.get(sol()).unwrap();
+        assert_eq!(
+            symbol.current(Timeframe::S5),
+            Some(&bar(T + 20, [14.0; 4], 2.0, 1))
+        );
+        // The minute is still forming
+        assert!(candles.candles(sol(), Timeframe::M1, 10).is_empty());
+        assert_eq!(
+            symbol.current(Timeframe::M1),
+            Some(&bar(T, [10.0, 14.0, 9.0, 14.0], 10.0, 6))
+        );
+        assert!(candles.candles(sol(), Timeframe::S1, 10).is_empty());
+        assert!(
+            candles
+                .candles(symbols().1.id(), Timeframe::S5, 10)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn quiet_intervals_close_on_the_clock() {
+        let mut candles = aggregator(&[Timeframe::S5, Timeframe::M1], 100);
+        tick(&mut candles, T + 2, 10.0, 1.0);
+        tick(&mut candles, T + 8, 11.0, 1.0);
+        candles.advance(T + 62);
+
+        let minute = candles.candles(sol(), Timeframe::M1, 10);
+        assert_eq!(minute, [bar(T, [10.0, 11.0, 10.0, 11.0], 2.0, 2)]);
+        let five = candles.candles(sol(), Timeframe::S5, 100);
+        // T, T+5 and ten empty bars to T+55
+        assert_eq!(five.len(), 12);
+        assert_eq!(five[1], bar(T + 5, [11.0; 4], 1.0, 1));
+        for (i, candle) in five[2..].iter().enumerate() {
+            assert_eq!(*candle, bar(T + 10 + 5 * i as u64, [11.0; 4], 0.0, 0));
+        }
+        // A tick in the forming empty bar opens it at its own price
+        tick(&mut candles, T + 63, 12.0, 4.0);
+        let current = Some(bar(T + 60, [12.0; 4], 4.0, 1));
+        assert_eq!(forming(&candles), current);
+        // A late tick for a closed bar changes nothing
+        tick(&mut candles, T + 30, 99.0, 1.0);
+        assert_eq!(candles.candles(sol(), Timeframe::S5, 100), five);
+        assert_eq!(forming(&candles), current);
+        assert_eq!(
+            candles
+                .get(sol())
+                .unwrap()
+                .closed_since(Timeframe::S5, Some(T + 45), 0),
+            five[10..]
+        );
+    }
+
+    #[test]
+    fn a_long_gap_keeps_only_the_newest_bars() {
+        let mut candles = aggregator(&[Timeframe::S1], 3);
+        tick(&mut candles, T, 10.0, 1.0);
+        candles.advance(T + 1_000);
+        assert_eq!(
+            candles.candles(sol(), Timeframe::S1, 10),
+            [
+                bar(T + 997, [10.0; 4], 0.0, 0),
+                bar(T + 998, [10.0; 4], 0.0, 0),
+                bar(T + 999, [10.0; 4], 0.0, 0),
+            ]
+        );
+    }
+}
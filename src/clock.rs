@@ -0,0 +1,69 @@
+use futures::future::BoxFuture;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// The bot's source of time. Live runs use the system clock; simulations and
+// backtests use a virtual one so runs are repeatable and never wait.
+pub trait Clock: Send + Sync {
+    // Milliseconds since the Unix epoch
+    fn now_ms(&self) -> u64;
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    fn now_secs(&self) -> u64 {
+        self.now_ms() / 1_000
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A clock that only moves when told to. Sleeping advances it by the
+/// duration at once (yielding to other tasks), so simulated waits take no
+/// real time; drivers move it to each event's time with `advance_to`.
+#[derive(Debug, Default)]
+pub struct VirtualClock {
+    now_ms: AtomicU64,
+}
+
+impl VirtualClock {
+    pub fn new(start_ms: u64) -> Self {
+        Self {
+            now_ms: AtomicU64::new(start_ms),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now_ms
+            .fetch_add(duration.as_millis() as u64, Ordering::AcqRel);
+    }
+
+    // Never moves backwards
+    pub fn advance_to(&self, ms: u64) {
+        self.now_ms.fetch_max(ms, Ordering::AcqRel);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::Acquire)
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.advance(duration);
+        Box::pin(tokio::task::yield_now())
+    }
+}
@@ -0,0 +1,596 @@
+use crate::account::AccountConfig;
+use crate::aggregator::AggregationPolicy;
+use crate::allocation::AllocationConfig;
+use crate::api::ApiConfig;
+use crate::attribution::AttributionConfig;
+use crate::backtest::BacktestConfig;
+use crate::basis::BasisConfig;
+use crate::breaker::BreakerConfig;
+use crate::calendar::CalendarConfig;
+use crate::candles::{CandleConfig, Timeframe};
+use crate::correlation::CorrelationConfig;
+use crate::currency::CurrencyConfig;
+use crate::depth_sizing::DepthSizingConfig;
+use crate::donchian::{DonchianBreakoutStrategy, DonchianExit};
+use crate::equity::EquityConfig;
+use crate::funding::FundingConfig;
+use crate::holding::HoldingConfig;
+use crate::indicators::VolatilityConfig;
+use crate::journal::JournalConfig;
+use crate::macd::MacdStrategy;
+use crate::marks::MarkConfig;
+use crate::metrics::MetricsConfig;
+use crate::ml::MlStrategy;
+use crate::notify::NotifyConfig;
+use crate::ofi::OfiStrategy;
+use crate::optimize::OptimizeConfig;
+use crate::order_guard::OrderGuardConfig;
+use crate::paper::PaperConfig;
+use crate::price_buffer::PriceHistoryConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::reconcile::ReconcileConfig;
+use crate::recording::RecordingConfig;
+use crate::report::ReportConfig;
+use crate::retry::RetryConfig;
+use crate::scalper::{ScalperParams, SpreadScalperStrategy};
+use crate::schedule::{EvaluationTrigger, ScheduleConfig};
+use crate::session::SessionConfig;
+use crate::shadow::StrategyMode;
+use crate::simulation::SimulationConfig;
+use crate::staleness::StalenessConfig;
+use crate::state::StateConfig;
+use crate::streaks::StreakConfig;
+use crate::supervisor::SupervisionConfig;
+use crate::symbols::{InstrumentType, SymbolMeta};
+use crate::tick_filter::TickFilterConfig;
+use crate::venue::{ExecutionMode, VenueConfig, VenueId, VenuesConfig};
+use crate::{MeanReversionStrategy, MomentumStrategy, RiskParams, TradingStrategy};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Top-level bot configuration, loaded from a TOML file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    // Where orders go: paper, dry_run (the primary venue's data, paper
+    // fills) or live
+    pub mode: ExecutionMode,
+    pub symbols: Vec<String>,
+    pub strategies: Vec<StrategyConfig>,
+    pub aggregation: AggregationPolicy,
+    pub risk: RiskParams,
+    // Per-symbol risk overrides; only the fields that differ from `risk`
+    pub symbol_risk: HashMap<String, toml::Table>,
+    pub rate_limits: RateLimitConfig,
+    pub retry: RetryConfig,
+    pub account: AccountConfig,
+    pub paper: PaperConfig,
+    // Tick/lot sizes and minimums per symbol that orders are rounded to
+    pub symbol_meta: HashMap<String, SymbolMeta>,
+    pub backtest: BacktestConfig,
+    pub optimize: OptimizeConfig,
+    pub recording: RecordingConfig,
+    pub simulation: SimulationConfig,
+    pub journal: JournalConfig,
+    pub state: StateConfig,
+    pub metrics: MetricsConfig,
+    pub api: ApiConfig,
+    pub notify: NotifyConfig,
+    pub candles: CandleConfig,
+    pub staleness: StalenessConfig,
+    pub schedule: ScheduleConfig,
+    pub supervision: SupervisionConfig,
+    pub venues: VenuesConfig,
+    pub attribution: AttributionConfig,
+    pub allocation: AllocationConfig,
+    pub calendar: CalendarConfig,
+    pub holding: HoldingConfig,
+    pub volatility: VolatilityConfig,
+    pub breaker: BreakerConfig,
+    pub tick_filter: TickFilterConfig,
+    pub price_history: PriceHistoryConfig,
+    pub marks: MarkConfig,
+    pub correlation: CorrelationConfig,
+    // The currency risk limits, PnL and reports are converted into
+    pub currency: CurrencyConfig,
+    pub reconcile: ReconcileConfig,
+    // When the trading day that daily limits and reports cover rolls over
+    pub session: SessionConfig,
+    pub report: ReportConfig,
+    pub equity: EquityConfig,
+    pub streaks: StreakConfig,
+    pub order_guard: OrderGuardConfig,
+    pub depth_sizing: DepthSizingConfig,
+    pub basis: BasisConfig,
+    pub funding: FundingConfig,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            mode: ExecutionMode::default(),
+            symbols: vec![
+                "SOL/USDT".to_string(),
+                "BTC/USDT".to_string(),
+                "ETH/USDT".to_string(),
+            ],
+            strategies: vec![
+                StrategyConfig {
+                    kind: StrategyKind::Momentum {
+                        lookback_period: 10,
+                        momentum_threshold: 0.02,
+                        min_volume: default_min_volume(),
+                        base_quantity: default_base_quantity(),
+                    },
+                    symbols: None,
+                    trigger: EvaluationTrigger::default(),
+                    mode: StrategyMode::default(),
+                    max_holding_secs: None,
+                },
+                StrategyConfig {
+                    kind: StrategyKind::MeanReversion {
+                        lookback_period: 20,
+                        deviation_threshold: 0.03,
+                    },
+                    symbols: None,
+                    trigger: EvaluationTrigger::default(),
+                    mode: StrategyMode::default(),
+                    max_holding_secs: None,
+                },
+            ],
+            aggregation: AggregationPolicy::default(),
+            risk: RiskParams::default(),
+            symbol_risk: HashMap::new(),
+            rate_limits: RateLimitConfig::default(),
+            retry: RetryConfig::default(),
+            account: AccountConfig::default(),
+            paper: PaperConfig::default(),
+            symbol_meta: HashMap::new(),
+            backtest: BacktestConfig::default(),
+            optimize: OptimizeConfig::default(),
+            recording: RecordingConfig::default(),
+            simulation: SimulationConfig::default(),
+            journal: JournalConfig::default(),
+            state: StateConfig::default(),
+            metrics: MetricsConfig::default(),
+            api: ApiConfig::default(),
+            notify: NotifyConfig::default(),
+            candles: CandleConfig::default(),
+            staleness: StalenessConfig::default(),
+            schedule: ScheduleConfig::default(),
+            supervision: SupervisionConfig::default(),
+            venues: VenuesConfig::default(),
+            attribution: AttributionConfig::default(),
+            allocation: AllocationConfig::default(),
+            calendar: CalendarConfig::default(),
+            holding: HoldingConfig::default(),
+            volatility: VolatilityConfig::default(),
+            breaker: BreakerConfig::default(),
+            tick_filter: TickFilterConfig::default(),
+            price_history: PriceHistoryConfig::default(),
+            marks: MarkConfig::default(),
+            correlation: CorrelationConfig::default(),
+            currency: CurrencyConfig::default(),
+            reconcile: ReconcileConfig::default(),
+            session: SessionConfig::default(),
+            report: ReportConfig::default(),
+            equity: EquityConfig::default(),
+            streaks: StreakConfig::default(),
+            order_guard: OrderGuardConfig::default(),
+            depth_sizing: DepthSizingConfig::default(),
+            basis: BasisConfig::default(),
+            funding: FundingConfig::default(),
+        }
+    }
+}
+
+impl BotConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path, e))?;
+        Self::from_toml(&contents)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, String> {
+        let config: Self =
+            toml::from_str(contents).map_err(|e| format!("Invalid config: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Symbols traded as perpetual futures: those [symbol_meta] marks as
+    // perps, and every symbol when the primary venue is Binance futures
+    pub fn perp_symbols(&self) -> Vec<String> {
+        let futures = matches!(
+            self.venues.venues.get(&self.venues.primary),
+            Some(VenueConfig::Binance(binance)) if binance.market.is_futures()
+        );
+        let mut perps: Vec<String> = self
+            .symbol_meta
+            .iter()
+            .filter(|(_, meta)| meta.instrument == InstrumentType::Perp)
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        if futures {
+            perps.extend(self.symbols.iter().cloned());
+        }
+        perps.sort();
+        perps.dedup();
+        perps
+    }
+
+    // Layers each symbol's partial override on top of the global risk params
+    pub fn resolved_symbol_risk(&self) -> Result<HashMap<String, RiskParams>, String> {
+        let base =
+            toml::Table::try_from(&self.risk).map_err(|e| format!("Invalid risk params: {}", e))?;
+
+        self.symbol_risk
+            .iter()
+            .map(|(symbol, overrides)| {
+                let mut merged = base.clone();
+                merged.extend(overrides.clone());
+                let params = merged
+                    .try_into::<RiskParams>()
+                    .map_err(|e| format!("Invalid risk override for {}: {}", symbol, e))?;
+                Ok((symbol.clone(), params))
+            })
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.rate_limits.validate()?;
+        self.retry.validate()?;
+        self.account.validate()?;
+        self.paper.validate()?;
+        self.backtest.validate()?;
+        self.optimize.validate()?;
+        self.recording.validate()?;
+        self.simulation.validate()?;
+        self.journal.validate()?;
+        self.state.validate()?;
+        self.metrics.validate()?;
+        self.api.validate()?;
+        self.notify.validate()?;
+        self.candles.validate()?;
+        self.staleness.validate()?;
+        self.schedule.validate()?;
+        self.calendar.validate()?;
+        self.holding.validate()?;
+        self.volatility.validate()?;
+        self.breaker.validate()?;
+        self.tick_filter.validate()?;
+        self.price_history.validate()?;
+        self.marks.validate()?;
+        self.correlation.validate()?;
+        self.currency.validate()?;
+        self.allocation.validate()?;
+        self.session.validate()?;
+        self.equity.validate()?;
+        if self.equity.csv_path.is_some() && !self.journal.enabled {
+            return Err("equity.csv_path needs [journal] enabled".to_string());
+        }
+        if self.risk.max_correlated_exposure.is_some() && !self.correlation.enabled {
+            return Err("risk.max_correlated_exposure needs [correlation] enabled".to_string());
+        }
+        self.reconcile.validate()?;
+        self.report.validate()?;
+        self.streaks.validate()?;
+        self.order_guard.validate()?;
+        self.depth_sizing.validate()?;
+        self.funding.validate()?;
+        self.supervision.validate()?;
+        self.venues.validate()?;
+        self.venues.validate_symbols(&self.symbols)?;
+        self.basis.validate()?;
+        if self.basis.enabled {
+            for symbol in [&self.basis.spot_symbol, &self.basis.perp_symbol] {
+                if !self.symbols.contains(symbol) {
+                    return Err(format!("Basis symbol {} isn't traded", symbol));
+                }
+            }
+            let perps = self.perp_symbols();
+            if !perps.contains(&self.basis.perp_symbol) {
+                return Err(format!(
+                    "Basis perp_symbol {} isn't a perp",
+                    self.basis.perp_symbol
+                ));
+            }
+            if perps.contains(&self.basis.spot_symbol) {
+                return Err(format!(
+                    "Basis spot_symbol {} is a perp",
+                    self.basis.spot_symbol
+                ));
+            }
+            for venue in [&self.basis.spot_venue, &self.basis.perp_venue]
+                .into_iter()
+                .flatten()
+            {
+                if *venue != VenueId::paper() && !self.venues.venues.contains_key(venue) {
+                    return Err(format!("Basis venue {} isn't configured", venue));
+                }
+            }
+        }
+        if self.mode != ExecutionMode::Paper && self.venues.primary == VenueId::paper() {
+            return Err(format!(
+                "{} mode needs a primary venue in [venues]",
+                self.mode
+            ));
+        }
+        self.risk.validate()?;
+        for params in self.resolved_symbol_risk()?.values() {
+            params.validate()?;
+        }
+        for (symbol, meta) in &self.symbol_meta {
+            meta.validate(symbol)?;
+        }
+
+        if let AggregationPolicy::ConfidenceWeightedNet { threshold } = self.aggregation
+            && threshold < 0.0
+        {
+            return Err("Aggregation threshold must be non-negative".to_string());
+        }
+
+        for strategy in &self.strategies {
+            if let StrategyKind::Momentum {
+                lookback_period,
+                momentum_threshold,
+                min_volume,
+                base_quantity,
+            } = strategy.kind
+            {
+                MomentumStrategy::with_params(
+                    lookback_period,
+                    momentum_threshold,
+                    min_volume,
+                    base_quantity,
+                )?;
+            }
+
+            // A model that won't load stops the bot at startup, not mid-run
+            if let StrategyKind::Ml {
+                model_path,
+                feature_window,
+                threshold,
+                base_quantity,
+            } = &strategy.kind
+            {
+                if *feature_window < 1 {
+                    return Err("MlStrategy feature_window must be at least 1".to_string());
+                }
+                if !(threshold.is_finite() && *threshold >= 0.0) {
+                    return Err(format!(
+                        "MlStrategy threshold must be non-negative, got {}",
+                        threshold
+                    ));
+                }
+                MlStrategy::load(model_path, *feature_window, *threshold, *base_quantity)?;
+            }
+
+            if let StrategyKind::SpreadScalper(params) = &strategy.kind {
+                params.validate()?;
+            }
+
+            if let StrategyKind::Donchian { period: 0, .. } = strategy.kind {
+                return Err("DonchianBreakoutStrategy period must be at least 1".to_string());
+            }
+
+            if let StrategyKind::Macd {
+                fast_period,
+                slow_period,
+                signal_period,
+                ..
+            } = strategy.kind
+                && !(fast_period > 0 && fast_period < slow_period && signal_period > 0)
+            {
+                return Err(format!(
+                    "MacdStrategy needs 0 < fast_period < slow_period and signal_period > 0, got {}/{}/{}",
+                    fast_period, slow_period, signal_period
+                ));
+            }
+
+            if let StrategyKind::Ofi {
+                levels,
+                window,
+                entry_zscore,
+                ..
+            } = strategy.kind
+                && !(levels > 0 && window >= 2 && entry_zscore > 0.0)
+            {
+                return Err(format!(
+                    "OfiStrategy needs levels > 0, window >= 2 and a positive entry_zscore, got {}/{}/{}",
+                    levels, window, entry_zscore
+                ));
+            }
+
+            if strategy.max_holding_secs == Some(0) {
+                return Err(format!(
+                    "Strategy {} max_holding_secs must be positive",
+                    strategy.kind.name()
+                ));
+            }
+
+            if let Some(symbols) = &strategy.symbols
+                && let Some(unknown) = symbols.iter().find(|s| !self.symbols.contains(s))
+            {
+                return Err(format!(
+                    "Strategy {} assigned to unknown symbol {}",
+                    strategy.kind.name(),
+                    unknown
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// A strategy instance and the symbols it trades
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyConfig {
+    #[serde(flatten)]
+    pub kind: StrategyKind,
+    // Symbol whitelist; None means the strategy runs on every symbol
+    #[serde(default)]
+    pub symbols: Option<Vec<String>>,
+    // Whether it runs on the evaluation timer or on every new price
+    #[serde(default)]
+    pub trigger: EvaluationTrigger,
+    // live, shadow (evaluated and tracked, never traded) or disabled
+    #[serde(default)]
+    pub mode: StrategyMode,
+    // Longest a position it opened is held, in seconds
+    #[serde(default)]
+    pub max_holding_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StrategyKind {
+    Momentum {
+        lookback_period: usize,
+        momentum_threshold: f64,
+        #[serde(default = "default_min_volume")]
+        min_volume: f64,
+        #[serde(default = "default_base_quantity")]
+        base_quantity: f64,
+    },
+    MeanReversion {
+        lookback_period: usize,
+        deviation_threshold: f64,
+    },
+    // An ONNX model scoring recent returns, volume and book imbalance
+    Ml {
+        model_path: String,
+        feature_window: usize,
+        threshold: f64,
+        #[serde(default = "default_base_quantity")]
+        base_quantity: f64,
+    },
+    // Breakouts of the channel over the last `period` closed candles
+    Donchian {
+        period: usize,
+        timeframe: Timeframe,
+        #[serde(default)]
+        exit: DonchianExit,
+        #[serde(default = "default_base_quantity")]
+        base_quantity: f64,
+    },
+    // MACD line crossing its signal line on closed candles
+    Macd {
+        fast_period: usize,
+        slow_period: usize,
+        signal_period: usize,
+        timeframe: Timeframe,
+        #[serde(default = "default_base_quantity")]
+        base_quantity: f64,
+    },
+    // Passive limits into a wide spread on the side the book leans to
+    SpreadScalper(ScalperParams),
+    // Z-score extremes of order-flow imbalance over the top `levels`
+    Ofi {
+        #[serde(default = "default_ofi_levels")]
+        levels: usize,
+        window: usize,
+        entry_zscore: f64,
+        #[serde(default = "default_base_quantity")]
+        base_quantity: f64,
+    },
+}
+
+impl StrategyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyKind::Momentum { .. } => "MomentumStrategy",
+            StrategyKind::MeanReversion { .. } => "MeanReversionStrategy",
+            StrategyKind::Ml { .. } => "MlStrategy",
+            StrategyKind::Donchian { .. } => "DonchianBreakoutStrategy",
+            StrategyKind::Macd { .. } => "MacdStrategy",
+            StrategyKind::SpreadScalper(_) => "SpreadScalperStrategy",
+            StrategyKind::Ofi { .. } => "OfiStrategy",
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn TradingStrategy> {
+        match *self {
+            StrategyKind::Momentum {
+                lookback_period,
+                momentum_threshold,
+                min_volume,
+                base_quantity,
+            } => Box::new(
+                MomentumStrategy::with_params(
+                    lookback_period,
+                    momentum_threshold,
+                    min_volume,
+                    base_quantity,
+                )
+                .expect("momentum params are checked by BotConfig::validate"),
+            ),
+            StrategyKind::MeanReversion {
+                lookback_period,
+                deviation_threshold,
+            } => Box::new(MeanReversionStrategy::new(
+                lookback_period,
+                deviation_threshold,
+            )),
+            StrategyKind::Ml {
+                ref model_path,
+                feature_window,
+                threshold,
+                base_quantity,
+            } => Box::new(
+                MlStrategy::load(model_path, feature_window, threshold, base_quantity)
+                    .expect("models are loaded by BotConfig::validate"),
+            ),
+            StrategyKind::Donchian {
+                period,
+                timeframe,
+                exit,
+                base_quantity,
+            } => Box::new(DonchianBreakoutStrategy::new(
+                period,
+                timeframe,
+                exit,
+                base_quantity,
+            )),
+            StrategyKind::Macd {
+                fast_period,
+                slow_period,
+                signal_period,
+                timeframe,
+                base_quantity,
+            } => Box::new(MacdStrategy::new(
+                fast_period,
+                slow_period,
+                signal_period,
+                timeframe,
+                base_quantity,
+            )),
+            StrategyKind::SpreadScalper(ref params) => {
+                Box::new(SpreadScalperStrategy::new(params.clone()))
+            }
+            StrategyKind::Ofi {
+                levels,
+                window,
+                entry_zscore,
+                base_quantity,
+            } => Box::new(OfiStrategy::new(
+                levels,
+                window,
+                entry_zscore,
+                base_quantity,
+            )),
+        }
+    }
+}
+
+fn default_min_volume() -> f64 {
+    MomentumStrategy::DEFAULT_MIN_VOLUME
+}
+
+fn default_ofi_levels() -> usize {
+    1
+}
+
+fn default_base_quantity() -> f64 {
+    MomentumStrategy::DEFAULT_BASE_QUANTITY
+}
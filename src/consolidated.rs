@@ -0,0 +1,367 @@
+use crate::OrderBook;
+use crate::book::PriceKey;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+// A price level on one venue
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VenueLevel {
+    pub venue: String,
+    pub price: f64,
+    pub quantity: f64,
+}
+
+// Quantity a venue shows within some distance of the consolidated mid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct VenueDepth {
+    pub bid_quantity: f64,
+    pub ask_quantity: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct VenueBook {
+    bids: BTreeMap<PriceKey, f64>,
+    asks: BTreeMap<PriceKey, f64>,
+    // When the venue's data is from, in milliseconds
+    at_ms: u64,
+}
+
+type Levels = BTreeMap<(PriceKey, String), f64>;
+
+fn set_level(
+    merged: &mut Levels,
+    own: &mut BTreeMap<PriceKey, f64>,
+    venue: &str,
+    level: (f64, f64),
+) {
+    let (price, quantity) = level;
+    if quantity > 0.0 {
+        own.insert(PriceKey(price), quantity);
+        merged.insert((PriceKey(price), venue.to_string()), quantity);
+    } else {
+        own.remove(&PriceKey(price));
+        merged.remove(&(PriceKey(price), venue.to_string()));
+    }
+}
+
+/// One symbol's levels across every venue, each tagged with its venue.
+/// A venue's levels change only when that venue's book does; a venue whose
+/// data is older than `max_age_ms` is left out of every read until it
+/// updates again.
+#[derive(Debug, Clone)]
+pub struct ConsolidatedBook {
+    symbol: String,
+    max_age_ms: u64,
+    venues: HashMap<String, VenueBook>,
+    // Ascending by price, then venue
+    bids: Levels,
+    asks: Levels,
+}
+
+impl ConsolidatedBook {
+    pub fn new(symbol: impl Into<String>, max_age_ms: u64) -> Self {
+        Self {
+            symbol: symbol.into(),
+            max_age_ms,
+            venues: HashMap::new(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    // Replaces the venue's levels with `book`'s
+    pub fn update_venue(&mut self, venue: &str, book: &OrderBook, at_ms: u64) {
+        self.remove_venue(venue);
+        self.apply_levels(venue, &book.bids, &book.asks, at_ms);
+    }
+
+    // Sets the venue's quantity at each price; zero removes the level
+    pub fn apply_levels(
+        &mut self,
+        venue: &str,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+        at_ms: u64,
+    ) {
+        let own = self.venues.entry(venue.to_string()).or_default();
+        for &level in bids {
+            set_level(&mut self.bids, &mut own.bids, venue, level);
+        }
+        for &level in asks {
+            set_level(&mut self.asks, &mut own.asks, venue, level);
+        }
+        own.at_ms = own.at_ms.max(at_ms);
+    }
+
+    pub fn remove_venue(&mut self, venue: &str) {
+        let Some(own) = self.venues.remove(venue) else {
+            return;
+        };
+        for price in own.bids.keys() {
+            self.bids.remove(&(*price, venue.to_string()));
+        }
+        for price in own.asks.keys() {
+            self.asks.remove(&(*price, venue.to_string()));
+        }
+    }
+
+    fn is_fresh(&self, venue: &str, now_ms: u64) -> bool {
+        self.venues
+            .get(venue)
+            .is_some_and(|own| now_ms.saturating_sub(own.at_ms) <= self.max_age_ms)
+    }
+
+    // Venues whose levels are currently included
+    pub fn fresh_venues(&self, now_ms: u64) -> Vec<String> {
+        let mut venues: Vec<String> = self
+            .venues
+            .keys()
+            .filter(|venue| self.is_fresh(venue, now_ms))
+            .cloned()
+            .collect();
+        venues.sort();
+        venues
+    }
+
+    // One venue's bids, highest first; empty if it is stale
+    pub fn venue_bids(&self, venue: &str, now_ms: u64) -> Vec<(f64, f64)> {
+        match self.venues.get(venue) {
+            Some(own) if self.is_fresh(venue, now_ms) => {
+                own.bids.iter().rev().map(|(p, q)| (p.0, *q)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // One venue's asks, lowest first; empty if it is stale
+    pub fn venue_asks(&self, venue: &str, now_ms: u64) -> Vec<(f64, f64)> {
+        match self.venues.get(venue) {
+            Some(own) if self.is_fresh(venue, now_ms) => {
+                own.asks.iter().map(|(p, q)| (p.0, *q)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn levels<'a>(
+        &'a self,
+        levels: impl Iterator<Item = (&'a (PriceKey, String), &'a f64)> + 'a,
+        now_ms: u64,
+    ) -> impl Iterator<Item = VenueLevel> + 'a {
+        levels
+            .filter(move |((_, venue), _)| self.is_fresh(venue, now_ms))
+            .map(|((price, venue), quantity)| VenueLevel {
+                venue: venue.clone(),
+                price: price.0,
+                quantity: *quantity,
+            })
+    }
+
+    // The best `depth` bid levels across fresh venues, highest first
+    pub fn bids(&self, now_ms: u64, depth: usize) -> Vec<VenueLevel> {
+        self.levels(self.bids.iter().rev(), now_ms)
+            .take(depth)
+            .collect()
+    }
+
+    // The best `depth` ask levels across fresh venues, lowest first
+    pub fn asks(&self, now_ms: u64, depth: usize) -> Vec<VenueLevel> {
+        self.levels(self.asks.iter(), now_ms).take(depth).collect()
+    }
+
+    pub fn best_bid(&self, now_ms: u64) -> Option<VenueLevel> {
+        self.levels(self.bids.iter().rev(), now_ms).next()
+    }
+
+    pub fn best_ask(&self, now_ms: u64) -> Option<VenueLevel> {
+        self.levels(self.asks.iter(), now_ms).next()
+    }
+
+    pub fn mid_price(&self, now_ms: u64) -> Option<f64> {
+        let bid = self.best_bid(now_ms)?;
+        let ask = self.best_ask(now_ms)?;
+        Some((bid.price + ask.price) / 2.0)
+    }
+
+    /// Per fresh venue, the bid quantity priced at or above mid minus
+    /// `bps` and the ask quantity at or below mid plus `bps`, around the
+    /// consolidated mid. Empty without a two-sided book.
+    pub fn depth_within_bps(&self, bps: f64, now_ms: u64) -> BTreeMap<String, VenueDepth> {
+        let Some(mid) = self.mid_price(now_ms) else {
+            return BTreeMap::new();
+        };
+        let band = mid * bps / 10_000.0;
+        let mut depth: BTreeMap<String, VenueDepth> = self
+            .fresh_venues(now_ms)
+            .into_iter()
+            .map(|venue| (venue, VenueDepth::default()))
+            .collect();
+        for level in self
+            .levels(self.bids.iter().rev(), now_ms)
+            .take_while(|level| level.price >= mid - band)
+        {
+            depth.entry(level.venue).or_default().bid_quantity += level.quantity;
+        }
+        for level in self
+            .levels(self.asks.iter(), now_ms)
+            .take_while(|level| level.price <= mid + band)
+        {
+            depth.entry(level.venue).or_default().ask_quantity += level.quantity;
+        }
+        depth
+    }
+}
+
+// A ConsolidatedBook per symbol
+#[derive(Debug, Clone)]
+pub struct ConsolidatedBooks {
+    max_age_ms: u64,
+    books: HashMap<String, ConsolidatedBook>,
+}
+
+impl ConsolidatedBooks {
+    pub fn new(max_age_ms: u64) -> Self {
+        Self {
+            max_age_ms,
+            books: HashMap::new(),
+        }
+    }
+
+    // Replaces the venue's levels for the book's symbol
+    pub fn update(&mut self, venue: &str, book: &OrderBook, at_ms: u64) {
+        let max_age_ms = self.max_age_ms;
+        self.books
+            .entry(book.symbol.clone())
+            .or_insert_with(|| ConsolidatedBook::new(book.symbol.clone(), max_age_ms))
+            .update_venue(venue, book, at_ms);
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&ConsolidatedBook> {
+        self.books.get(symbol)
+    }
+
+    pub fn get_mut(&mut self, symbol: &str) -> Option<&mut ConsolidatedBook> {
+        self.books.get_mut(symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn level(venue: &str, price: f64, quantity: f64) -> VenueLevel {
+        VenueLevel {
+            venue: venue.to_string(),
+            price,
+            quantity,
+        }
+    }
+
+    // Binance at 1000 ms and Coinbase at 1500 ms, interleaving
+    fn two_venues() -> ConsolidatedBooks {
+        let mut books = ConsolidatedBooks::new(2_000);
+        books.update(
+            "binance",
+            &fixtures::book(
+                "BTC/USDT",
+                &[(100.0, 1.0), (99.0, 2.0)],
+                &[(101.0, 1.0), (103.0, 2.0)],
+            ),
+            1_000,
+        );
+        books.update(
+            "coinbase",
+            &fixtures::book("BTC/USDT", &[(100.5, 0.5), (99.0, 3.0)], &[(102.0, 1.5)]),
+            1_500,
+        );
+        books
+    }
+
+    #[test]
+    fn levels_merge_in_price_order_tagged_by_venue() {
+        let books = two_venues();
+        let merged = books.get("BTC/USDT").unwrap();
+        let now = 2_000;
+        assert_eq!(
+            merged.bids(now, 10),
+            vec![
+                level("coinbase", 100.5, 0.5),
+                level("binance", 100.0, 1.0),
+                level("coinbase", 99.0, 3.0),
+                level("binance", 99.0, 2.0),
+            ]
+        );
+        assert_eq!(
+            merged.asks(now, 10),
+            vec![
+                level("binance", 101.0, 1.0),
+                level("coinbase", 102.0, 1.5),
+                level("binance", 103.0, 2.0),
+            ]
+        );
+        assert_eq!(merged.best_bid(now), Some(level("coinbase", 100.5, 0.5)));
+        assert_eq!(merged.mid_price(now), Some(100.75));
+
+        // Within 50 bps of 100.75: bids from 100.25, asks to 101.25
+        let depth = merged.depth_within_bps(50.0, now);
+        assert_eq!(
+            depth["binance"],
+            VenueDepth {
+                bid_quantity: 0.0,
+                ask_quantity: 1.0
+            }
+        );
+        assert_eq!(
+            depth["coinbase"],
+            VenueDepth {
+                bid_quantity: 0.5,
+                ask_quantity: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn a_venue_update_replaces_only_its_own_levels() {
+        let mut books = two_venues();
+        books.update(
+            "coinbase",
+            &fixtures::book("BTC/USDT", &[(99.5, 1.0)], &[(100.8, 1.0)]),
+            1_800,
+        );
+        let merged = books.get("BTC/USDT").unwrap();
+        assert_eq!(merged.best_bid(2_000), Some(level("binance", 100.0, 1.0)));
+        assert_eq!(merged.best_ask(2_000), Some(level("coinbase", 100.8, 1.0)));
+        assert_eq!(merged.venue_bids("coinbase", 2_000), vec![(99.5, 1.0)]);
+        assert_eq!(merged.bids(2_000, 10).len(), 3);
+    }
+
+    #[test]
+    fn a_stale_venue_drops_out_until_it_updates() {
+        let mut books = two_venues();
+        // Binance's data is 2.1 s old, past the 2 s limit
+        let now = 3_100;
+        let merged = books.get("BTC/USDT").unwrap();
+        assert_eq!(merged.fresh_venues(now), vec!["coinbase".to_string()]);
+        assert!(merged.venue_asks("binance", now).is_empty());
+        assert_eq!(
+            merged.bids(now, 10),
+            vec![level("coinbase", 100.5, 0.5), level("coinbase", 99.0, 3.0)]
+        );
+        assert_eq!(merged.best_ask(now), Some(level("coinbase", 102.0, 1.5)));
+        let depth = merged.depth_within_bps(1_000.0, now);
+        assert_eq!(depth.keys().collect::<Vec<_>>(), vec!["coinbase"]);
+
+        books
+            .get_mut("BTC/USDT")
+            .unwrap()
+            .apply_levels("binance", &[], &[(101.5, 1.0)], 3_000);
+        let merged = books.get("BTC/USDT").unwrap();
+        assert_eq!(merged.best_ask(now), Some(level("binance", 101.0, 1.0)));
+        assert_eq!(merged.bids(now, 10).len(), 4);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::Price;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+// Rolling correlations of returns between every pair of symbols
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorrelationConfig {
+    pub enabled: bool,
+    // Seconds between the return samples, taken across every symbol at once
+    pub sample_secs: u64,
+    // Returns in each pair's window; a pair has no correlation until full
+    pub window: usize,
+    // Export each pair's correlation as a gauge
+    pub metrics: bool,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_secs: 60,
+            window: 60,
+            metrics: false,
+        }
+    }
+}
+
+impl CorrelationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sample_secs == 0 {
+            return Err("Correlation sample_secs must be positive".to_string());
+        }
+        if self.window < 3 {
+            return Err("Correlation window must be at least 3 returns".to_string());
+        }
+        Ok(())
+    }
+}
+
+// One pair's returns, with running sums for the correlation
+#[derive(Debug, Clone, Default)]
+struct PairWindow {
+    returns: VecDeque<(f64, f64)>,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_yy: f64,
+    sum_xy: f64,
+}
+
+impl PairWindow {
+    fn push(&mut self, x: f64, y: f64, capacity: usize) {
+        if self.returns.len() == capacity
+            && let Some((old_x, old_y)) = self.returns.pop_front()
+        {
+            self.add(old_x, old_y, -1.0);
+        }
+        self.returns.push_back((x, y));
+        self.add(x, y, 1.0);
+    }
+
+    fn add(&mut self, x: f64, y: f64, sign: f64) {
+        self.sum_x += sign * x;
+        self.sum_y += sign * y;
+        self.sum_xx += sign * x * x;
+        self.sum_yy += sign * y * y;
+        self.sum_xy += sign * x * y;
+    }
+
+    fn correlation(&self, capacity: usize) -> Option<f64> {
+        if self.returns.len() < capacity {
+            return None;
+        }
+        let n = self.returns.len() as f64;
+        let var_x = self.sum_xx - self.sum_x * self.sum_x / n;
+        let var_y = self.sum_yy - self.sum_y * self.sum_y / n;
+        // A series that hasn't moved (up to rounding in the sums) has no
+        // correlation to speak of
+        if var_x <= 1e-12 * self.sum_xx || var_y <= 1e-12 * self.sum_yy {
+            return None;
+        }
+        let covariance = self.sum_xy - self.sum_x * self.sum_y / n;
+        Some((covariance / (var_x * var_y).sqrt()).clamp(-1.0, 1.0))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Sampler {
+    // Sample period the latest prices fall in
+    period: Option<u64>,
+    // Each symbol's latest price
+    latest: BTreeMap<String, f64>,
+    // Each symbol's price at the last sample
+    sampled: HashMap<String, f64>,
+    // By symbol pair, in name order
+    pairs: HashMap<(String, String), PairWindow>,
+}
+
+/// Every pair's correlation of log returns over a rolling window. Returns
+/// are sampled across all symbols at once, every `sample_secs` of market
+/// time, from each symbol's latest price; a symbol that didn't trade in a
+/// period returns 0 for it. Each sample updates every pair's running sums,
+/// O(n²) in the number of symbols.
+#[derive(Debug, Default)]
+pub struct CorrelationTracker {
+    config: CorrelationConfig,
+    sampler: Mutex<Sampler>,
+}
+
+/// Correlations between `symbols`, row by row; `None` where a pair hasn't a
+/// full window of returns yet
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CorrelationMatrix {
+    pub symbols: Vec<String>,
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+impl CorrelationTracker {
+    pub fn new(config: CorrelationConfig) -> Self {
+        Self {
+            config,
+            sampler: Mutex::new(Sampler::default()),
+        }
+    }
+
+    pub fn config(&self) -> &CorrelationConfig {
+        &self.config
+    }
+
+    // Takes in a tick; true if it closed a sample, so the correlations moved
+    pub fn record_price(&self, price: &Price) -> bool {
+        if !(self.config.enabled && price.price > 0.0 && price.price.is_finite()) {
+            return false;
+        }
+        let period = price.timestamp / self.config.sample_secs;
+        let mut sampler = self.sampler.lock().unwrap();
+        // The sample is of the prices as the period before ended, so it's
+        // taken before this tick goes in
+        let closed = sampler.period.is_some_and(|current| period > current);
+        if closed {
+            self.sample(&mut sampler);
+        }
+        if sampler.period.is_none_or(|current| period > current) {
+            sampler.period = Some(period);
+        }
+        sampler.latest.insert(price.symbol.clone(), price.price);
+        closed
+    }
+
+    fn sample(&self, sampler: &mut Sampler) {
+        let Sampler {
+            latest,
+            sampled,
+            pairs,
+            ..
+        } = sampler;
+        let mut returns = Vec::with_capacity(latest.len());
+        for (symbol, price) in latest.iter() {
+            if let Some(previous) = sampled.insert(symbol.clone(), *price) {
+                returns.push((symbol, (price / previous).ln()));
+            }
+        }
+        // `latest` is in name order, so each pair's key is too
+        for (i, (a, x)) in returns.iter().enumerate() {
+            for (b, y) in &returns[i + 1..] {
+                pairs.entry(((*a).clone(), (*b).clone())).or_default().push(
+                    *x,
+                    *y,
+                    self.config.window,
+                );
+            }
+        }
+    }
+
+    /// Correlation of the two symbols' returns, or None until they have a
+    /// full window of them together
+    pub fn correlation(&self, a: &str, b: &str) -> Option<f64> {
+        if a == b {
+            return Some(1.0);
+        }
+        let key = if a < b {
+            (a.to_string(), b.to_string())
+        } else {
+            (b.to_string(), a.to_string())
+        };
+        self.sampler
+            .lock()
+            .unwrap()
+            .pairs
+            .get(&key)?
+            .correlation(self.config.window)
+    }
+
+    // Every pair with a correlation, in name order
+    pub fn pairs(&self) -> Vec<(String, String, f64)> {
+        let sampler = self.sampler.lock().unwrap();
+        let mut pairs: Vec<_> = sampler
+            .pairs
+            .iter()
+            .filter_map(|((a, b), window)| {
+                Some((
+                    a.clone(),
+                    b.clone(),
+                    window.correlation(self.config.window)?,
+                ))
+            })
+            .collect();
+        pairs.sort_by(|x, y| (&x.0, &x.1).cmp(&(&y.0, &y.1)));
+        pairs
+    }
+
+    pub fn matrix(&self) -> CorrelationMatrix {
+        let symbols: Vec<String> = self
+            .sampler
+            .lock()
+            .unwrap()
+            .latest
+            .keys()
+            .cloned()
+            .collect();
+        let values = symbols
+            .iter()
+            .map(|a| symbols.iter().map(|b| self.correlation(a, b)).collect())
+            .collect();
+        CorrelationMatrix { symbols, values }
+    }
+
+    // Forgets the symbol and every pair it's in
+    pub fn remove(&self, symbol: &str) {
+        let mut sampler = self.sampler.lock().unwrap();
+        sampler.latest.remove(symbol);
+        sampler.sampled.remove(symbol);
+        sampler.pairs.retain(|(a, b), _| a != symbol && b != symbol);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::price_history;
+
+    fn tracker(window: usize) -> CorrelationTracker {
+        CorrelationTracker::new(CorrelationConfig {
+            enabled: true,
+            sample_secs: 1,
+            window,
+            metrics: false,
+        })
+    }
+
+    // The ticks of each series in time order, a second apart
+    fn feed(tracker: &CorrelationTracker, series: &[Vec<Price>]) {
+        for i in 0..series[0].len() {
+            for ticks in series {
+                tracker.record_price(&ticks[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn moving_together_is_one_and_walking_apart_is_about_zero() {
+        let tracker = tracker(500);
+        let a = price_history("A", 502, 1);
+        // Twice A's price is the same returns
+        let b: Vec<Price> = a
+            .iter()
+            .map(|tick| Price {
+                symbol: "B".to_string(),
+                price: tick.price * 2.0,
+                ..tick.clone()
+            })
+            .collect();
+        let c = price_history("C", 502, 2);
+        feed(&tracker, &[a, b, c]);
+
+        let together = tracker.correlation("A", "B").unwrap();
+        assert!((together - 1.0).abs() < 1e-9, "{together}");
+        let apart = tracker.correlation("C", "A").unwrap();
+        assert!(apart.abs() < 0.1, "{apart}");
+        assert_eq!(tracker.correlation("A", "C"), Some(apart));
+    }
+
+    #[test]
+    fn short_history_or_a_flat_series_gives_none() {
+        let tracker = tracker(10);
+        let a = price_history("A", 10, 1);
+        let flat: Vec<Price> = a
+            .iter()
+            .map(|tick| Price {
+                symbol: "FLAT".to_string(),
+                price: 50.0,
+                ..tick.clone()
+            })
+            .collect();
+        let c = price_history("C", 10, 2);
+        feed(&tracker, &[a, flat, c]);
+
+        // Ten ticks close nine samples, so eight returns: short of the window
+        assert_eq!(tracker.correlation("A", "C"), None);
+        assert_eq!(tracker.correlation("A", "FLAT"), None);
+        assert!(tracker.pairs().is_empty());
+        let matrix = tracker.matrix();
+        assert_eq!(matrix.symbols, ["A", "C", "FLAT"]);
+        assert_eq!(matrix.values[0], [Some(1.0), None, None]);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::marks::MarkPriceCache;
+use crate::symbols::assets;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Mutex, RwLock};
+
+// The currency risk limits, PnL, equity and reports are in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurrencyConfig {
+    pub reporting: String,
+    // Assets taken as worth one of the reporting currency, e.g. USDC when
+    // reporting in USDT
+    pub pegged: Vec<String>,
+    // Fixed rates into the reporting currency, for assets no subscribed
+    // pair prices
+    pub rates: BTreeMap<String, f64>,
+}
+
+impl Default for CurrencyConfig {
+    fn default() -> Self {
+        Self {
+            reporting: "USDT".to_string(),
+            pegged: Vec::new(),
+            rates: BTreeMap::new(),
+        }
+    }
+}
+
+impl CurrencyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reporting.is_empty() {
+            return Err("Currency reporting can't be empty".to_string());
+        }
+        for (asset, rate) in &self.rates {
+            if !(rate.is_finite() && *rate > 0.0) {
+                return Err(format!("Currency rate for {} must be positive", asset));
+            }
+        }
+        Ok(())
+    }
+}
+
+// A subscribed symbol that prices an asset in the reporting currency (or
+// one pegged to it)
+#[derive(Debug, Clone, PartialEq)]
+struct Pair {
+    symbol: String,
+    // The asset is the symbol's quote, so its rate is 1 / the mark
+    inverse: bool,
+}
+
+/// Converts amounts in any quote asset into the reporting currency. An
+/// asset's rate is, in order: 1 for the reporting currency and the assets
+/// pegged to it, the last one a rates feed pushed, the mark of a
+/// subscribed pair between it and the reporting currency (BTC/USDT for
+/// BTC), then its fixed rate from the config. Without one it is taken 1:1
+/// and listed as unconverted until a rate turns up.
+#[derive(Debug)]
+pub struct CurrencyConverter {
+    reporting: String,
+    pegged: HashSet<String>,
+    fixed: BTreeMap<String, f64>,
+    pairs: RwLock<HashMap<String, Pair>>,
+    // Pushed by a rates feed, ahead of the pairs
+    feed: RwLock<HashMap<String, f64>>,
+    // Assets last converted 1:1 for lack of a rate
+    unconverted: Mutex<BTreeSet<String>>,
+}
+
+impl Default for CurrencyConverter {
+    fn default() -> Self {
+        Self::new(&CurrencyConfig::default())
+    }
+}
+
+impl CurrencyConverter {
+    pub fn new(config: &CurrencyConfig) -> Self {
+        Self {
+            reporting: config.reporting.clone(),
+            pegged: config.pegged.iter().cloned().collect(),
+            fixed: config.rates.clone(),
+            pairs: RwLock::new(HashMap::new()),
+            feed: RwLock::new(HashMap::new()),
+            unconverted: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    // Prices assets through these symbols' marks
+    pub fn with_symbols<'a>(self, symbols: impl IntoIterator<Item = &'a String>) -> Self {
+        for symbol in symbols {
+            self.add_symbol(symbol);
+        }
+        self
+    }
+
+    pub fn reporting(&self) -> &str {
+        &self.reporting
+    }
+
+    fn is_par(&self, asset: &str) -> bool {
+        asset == self.reporting || self.pegged.contains(asset)
+    }
+
+    // Takes a newly subscribed symbol as a pair for its assets, where it
+    // is one against the reporting currency
+    pub fn add_symbol(&self, symbol: &str) {
+        let Some((base, quote)) = assets(symbol) else {
+            return;
+        };
+        let (asset, inverse) = match (self.is_par(base), self.is_par(quote)) {
+            (false, true) => (base, false),
+            (true, false) => (quote, true),
+            _ => return,
+        };
+        self.pairs
+            .write()
+            .unwrap()
+            .entry(asset.to_string())
+            .or_insert_with(|| Pair {
+                symbol: symbol.to_string(),
+                inverse,
+            });
+    }
+
+    // A rate from a dedicated feed, which takes precedence over the pairs
+    pub fn set_rate(&self, asset: &str, rate: f64) {
+        if rate.is_finite() && rate > 0.0 {
+            self.feed.write().unwrap().insert(asset.to_string(), rate);
+        }
+    }
+
+    // The asset's rate into the reporting currency, if one is known now
+    pub fn rate(&self, asset: &str, marks: &MarkPriceCache) -> Option<f64> {
+        if self.is_par(asset) {
+            return Some(1.0);
+        }
+        if let Some(rate) = self.feed.read().unwrap().get(asset) {
+            return Some(*rate);
+        }
+        let pair = self.pairs.read().unwrap().get(asset).cloned();
+        let marked = pair.and_then(|pair| {
+            let mark = marks.price(&pair.symbol).filter(|mark| *mark > 0.0)?;
+            Some(if pair.inverse { 1.0 / mark } else { mark })
+        });
+        marked.or_else(|| self.fixed.get(asset).copied())
+    }
+
+    // The rate, or 1:1 when there is none; the asset is then flagged as
+    // unconverted
+    pub fn rate_or_par(&self, asset: &str, marks: &MarkPriceCache) -> f64 {
+        let rate = self.rate(asset, marks);
+        let mut unconverted = self.unconverted.lock().unwrap();
+        match rate {
+            Some(rate) => {
+                unconverted.remove(asset);
+                rate
+            }
+            None => {
+                unconverted.insert(asset.to_string());
+                1.0
+            }
+        }
+    }
+
+    // An amount of the asset in the reporting currency
+    pub fn convert(&self, asset: &str, amount: f64, marks: &MarkPriceCache) -> f64 {
+        if amount == 0.0 {
+            return 0.0;
+        }
+        amount * self.rate_or_par(asset, marks)
+    }
+
+    /// Of these assets, those with no way to a rate: not pegged, no fixed
+    /// rate and no pair subscribed. Rates a feed may push later aren't
+    /// counted.
+    pub fn missing<'a>(&self, assets: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let pairs = self.pairs.read().unwrap();
+        let missing: BTreeSet<String> = assets
+            .into_iter()
+            .filter(|asset| {
+                !(self.is_par(asset)
+                    || self.fixed.contains_key(*asset)
+                    || pairs.contains_key(*asset))
+            })
+            .map(str::to_string)
+            .collect();
+        missing.into_iter().collect()
+    }
+
+    // Assets last converted 1:1 for lack of a rate
+    pub fn unconverted(&self) -> Vec<String> {
+        self.unconverted.lock().unwrap().iter().cloned().collect()
+    }
+}
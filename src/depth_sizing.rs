@@ -0,0 +1,97 @@
+use crate::{Order, OrderBook, OrderSide, OrderType, TimeInForce};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// What becomes of the part of a market order the depth cap cuts off
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepthRemainder {
+    #[default]
+    Drop,
+    // Sent after it as a limit order resting at the near touch
+    Limit,
+}
+
+// Sizing market orders to the liquidity near mid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DepthSizingConfig {
+    pub enabled: bool,
+    // How far from mid resting quantity counts as available
+    pub band_bps: f64,
+    // Share of the available quantity one market order may take
+    pub max_participation: f64,
+    pub remainder: DepthRemainder,
+}
+
+impl Default for DepthSizingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            band_bps: 10.0,
+            max_participation: 0.2,
+            remainder: DepthRemainder::Drop,
+        }
+    }
+}
+
+impl DepthSizingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.band_bps <= 0.0 {
+            return Err("Depth sizing band_bps must be positive".to_string());
+        }
+        if self.max_participation <= 0.0 || self.max_participation > 1.0 {
+            return Err("Depth sizing max_participation must be in (0, 1]".to_string());
+        }
+        Ok(())
+    }
+
+    /// Caps a market order at max_participation of what the book shows
+    /// within band_bps of mid on the side it takes. Returns the split if
+    /// the order was cut; other orders, and books without a mid, are left
+    /// alone.
+    pub fn size(&self, order: &mut Order, book: &OrderBook) -> Option<DepthSplit> {
+        if !self.enabled || !matches!(order.order_type, OrderType::Market) {
+            return None;
+        }
+        let available = book.depth_within_bps(order.side, self.band_bps)?;
+        let cap = available * self.max_participation;
+        if order.quantity <= cap {
+            return None;
+        }
+        let cut = order.quantity - cap;
+        order.quantity = cap;
+        let touch = match order.side {
+            OrderSide::Buy => book.best_bid(),
+            OrderSide::Sell => book.best_ask(),
+        };
+        let follow_up = match (self.remainder, touch) {
+            (DepthRemainder::Limit, Some((price, _))) => Some(Order {
+                id: Uuid::new_v4().to_string(),
+                client_order_id: Uuid::new_v4().to_string(),
+                order_type: OrderType::Limit,
+                quantity: cut,
+                price: Some(price),
+                time_in_force: TimeInForce::Gtc,
+                ..order.clone()
+            }),
+            _ => None,
+        };
+        Some(DepthSplit {
+            available,
+            cut,
+            follow_up,
+        })
+    }
+}
+
+// A market order cut down to the book's depth
+#[derive(Debug, Clone)]
+pub struct DepthSplit {
+    // Quantity within the band on the side the order takes
+    pub available: f64,
+    // Taken off the market order
+    pub cut: f64,
+    // The cut part as a resting limit, under DepthRemainder::Limit
+    pub follow_up: Option<Order>,
+}
@@ -0,0 +1,204 @@
+use crate::candles::{Candle, SymbolCandles, Timeframe};
+use crate::indicators::{RollingWindow, SymbolIndicators};
+use crate::monitor::ExitReason;
+use crate::staleness::DataAge;
+use crate::{OrderBook, OrderSide, Position, Price, TimeInForce, TradingSignal, TradingStrategy};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// How a Donchian breakout position is closed by the strategy itself
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DonchianExit {
+    // Held until the opposite breakout, or one of the bot's own exits
+    #[default]
+    None,
+    // Closed when a bar closes back through the middle of the channel
+    MidChannel,
+}
+
+// One symbol's channel over the `period` closed bars before the newest
+#[derive(Debug, Clone)]
+struct Channel {
+    highs: RollingWindow,
+    lows: RollingWindow,
+    // Open time of the newest bar taken in
+    last_bar: Option<u64>,
+    // The side the newest close is beyond the channel on, if any
+    breakout: Option<OrderSide>,
+    // The side of the last entry, until it's exited
+    position: Option<OrderSide>,
+}
+
+impl Channel {
+    fn new(period: usize) -> Self {
+        Self {
+            highs: RollingWindow::new(period),
+            lows: RollingWindow::new(period),
+            last_bar: None,
+            breakout: None,
+            position: None,
+        }
+    }
+
+    // Takes in a closed bar: compared with the channel before it, then
+    // added to it. Returns the side to trade and how sure.
+    fn on_bar(&mut self, candle: &Candle, exit: DonchianExit) -> Option<(OrderSide, f64)> {
+        let signal = if self.highs.is_full() {
+            self.evaluate(candle.close, exit)
+        } else {
+            None
+        };
+        self.highs.push(candle.high);
+        self.lows.push(candle.low);
+        self.last_bar = Some(candle.open_time);
+        signal
+    }
+
+    fn evaluate(&mut self, close: f64, exit: DonchianExit) -> Option<(OrderSide, f64)> {
+        let (upper, lower) = (self.highs.max()?, self.lows.min()?);
+        let width = upper - lower;
+        // Distance as a share of the channel's width; a flat channel
+        // broken at all is as sure as it gets
+        let confidence = |distance: f64| {
+            if width > 0.0 {
+                (distance / width).min(1.0)
+            } else {
+                1.0
+            }
+        };
+
+        let beyond = if close > upper {
+            Some((OrderSide::Buy, confidence(close - upper)))
+        } else if close < lower {
+            Some((OrderSide::Sell, confidence(lower - close)))
+        } else {
+            None
+        };
+        // Edge-triggered: a close that stays beyond the channel bar after
+        // bar is one breakout
+        let previous = std::mem::replace(&mut self.breakout, beyond.map(|(side, _)| side));
+        if let Some((side, _)) = beyond
+            && previous != Some(side)
+        {
+            self.position = Some(side);
+            return beyond;
+        }
+
+        let mid = (upper + lower) / 2.0;
+        match (exit, self.position) {
+            (DonchianExit::MidChannel, Some(OrderSide::Buy)) if close < mid => {
+                self.position = None;
+                Some((OrderSide::Sell, confidence(2.0 * (mid - close))))
+            }
+            (DonchianExit::MidChannel, Some(OrderSide::Sell)) if close > mid => {
+                self.position = None;
+                Some((OrderSide::Buy, confidence(2.0 * (close - mid))))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Trades breakouts of the Donchian channel on one candle timeframe: long
+/// when a bar closes above the highest high of the `period` bars before
+/// it, short when it closes below their lowest low. Each symbol's channel
+/// is kept up to date one closed bar at a time, and a breakout signals
+/// once, on the bar that makes it. With `DonchianExit::MidChannel` the
+/// position the last breakout opened is closed when a bar closes back
+/// through the middle of the channel.
+pub struct DonchianBreakoutStrategy {
+    period: usize,
+    timeframe: Timeframe,
+    exit: DonchianExit,
+    base_quantity: f64,
+    channels: Mutex<HashMap<Arc<str>, Channel>>,
+}
+
+impl DonchianBreakoutStrategy {
+    // Panics if period is 0, since the channel needs a bar to span
+    pub fn new(
+        period: usize,
+        timeframe: Timeframe,
+        exit: DonchianExit,
+        base_quantity: f64,
+    ) -> Self {
+        assert!(
+            period >= 1,
+            "DonchianBreakoutStrategy period must be at least 1, got {}",
+            period
+        );
+        Self {
+            period,
+            timeframe,
+            exit,
+            base_quantity,
+            channels: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl TradingStrategy for DonchianBreakoutStrategy {
+    // Signals only come from closed candles
+    fn analyze(&self, _prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
+        None
+    }
+
+    fn name(&self) -> &str {
+        "DonchianBreakoutStrategy"
+    }
+
+    fn candle_timeframes(&self) -> Vec<Timeframe> {
+        vec![self.timeframe]
+    }
+
+    fn analyze_candles(
+        &self,
+        _prices: &[Price],
+        indicators: &SymbolIndicators,
+        candles: &SymbolCandles,
+        _data_age: DataAge,
+        _orderbook: &OrderBook,
+    ) -> Option<TradingSignal> {
+        let symbol = indicators.symbol();
+        let mut channels = self.channels.lock().unwrap();
+        let channel = channels
+            .entry(symbol.clone())
+            .or_insert_with(|| Channel::new(self.period));
+
+        // Usually one bar has closed since the last evaluation; a new
+        // channel is seeded with as many as it spans
+        let bars = candles.closed_since(self.timeframe, channel.last_bar, self.period + 1);
+        let newest = *bars.last()?;
+        // Of several bars signalling, the newest one's signal stands
+        let mut signal = None;
+        for candle in &bars {
+            signal = channel.on_bar(candle, self.exit).or(signal);
+        }
+
+        let (action, confidence) = signal?;
+        Some(TradingSignal {
+            symbol: symbol.clone(),
+            action,
+            confidence,
+            target_price: newest.close,
+            quantity: self.base_quantity,
+            time_in_force: TimeInForce::Gtc,
+            limit_price: None,
+        })
+    }
+
+    // The bot closed the position some other way, so there's nothing left
+    // for the mid-channel exit to close
+    fn on_position(&self, position: &Position, _exit: ExitReason) {
+        if let Some(channel) = self
+            .channels
+            .lock()
+            .unwrap()
+            .get_mut(position.symbol.as_str())
+        {
+            channel.position = None;
+        }
+    }
+}
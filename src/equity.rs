@@ -0,0 +1,171 @@
+use crate::performance::PerformanceReport;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// The account's equity sampled over a live or paper run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EquityConfig {
+    // Seconds between samples
+    pub sample_secs: u64,
+    // Samples kept in memory; the oldest go first
+    pub capacity: usize,
+    // Appends the samples to this CSV every flush_secs, through the
+    // journal's writer (so it needs [journal] enabled)
+    pub csv_path: Option<PathBuf>,
+    pub flush_secs: u64,
+}
+
+impl Default for EquityConfig {
+    fn default() -> Self {
+        Self {
+            sample_secs: 60,
+            capacity: 10_000,
+            csv_path: None,
+            flush_secs: 300,
+        }
+    }
+}
+
+impl EquityConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sample_secs == 0 || self.flush_secs == 0 {
+            return Err("Equity sample_secs and flush_secs must be positive".to_string());
+        }
+        if self.capacity < 2 {
+            return Err("Equity capacity must be at least 2 samples".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One sample of the account: equity (cash plus positions at their marks),
+/// the positions' realized and unrealized PnL, and their gross notional
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EquityPoint {
+    // Unix seconds
+    pub timestamp: u64,
+    pub equity: f64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub gross_exposure: f64,
+}
+
+#[derive(Debug, Default)]
+struct Series {
+    points: VecDeque<EquityPoint>,
+    // Sampled since the last flush
+    unflushed: Vec<EquityPoint>,
+    last_flush: Option<u64>,
+}
+
+/// The equity curve of a running bot: a sample every `sample_secs`, at
+/// most `capacity` of them. Samples not yet written to the CSV are held
+/// until the next flush.
+#[derive(Debug, Default)]
+pub struct EquityCurve {
+    config: EquityConfig,
+    series: Mutex<Series>,
+}
+
+// The equity curve with the return and drawdown statistics over it
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityReport {
+    pub points: Vec<EquityPoint>,
+    pub performance: PerformanceReport,
+}
+
+impl EquityCurve {
+    pub fn new(config: EquityConfig) -> Self {
+        Self {
+            config,
+            series: Mutex::new(Series::default()),
+        }
+    }
+
+    pub fn config(&self) -> &EquityConfig {
+        &self.config
+    }
+
+    // Takes the point in if a sample is due, or with `force` unless it's
+    // the last sample again; returns whether it was
+    pub fn record(&self, point: EquityPoint, force: bool) -> bool {
+        let mut series = self.series.lock().unwrap();
+        if let Some(last) = series.points.back()
+            && (*last == point
+                || !force && point.timestamp < last.timestamp + self.config.sample_secs)
+        {
+            return false;
+        }
+        if series.points.len() == self.config.capacity {
+            series.points.pop_front();
+        }
+        series.points.push_back(point);
+        if self.config.csv_path.is_some() {
+            series.unflushed.push(point);
+            series.last_flush.get_or_insert(point.timestamp);
+        }
+        true
+    }
+
+    /// The samples to append to the CSV, once flush_secs have passed since
+    /// the last flush; all of them with `force`
+    pub fn take_flush(&self, now_secs: u64, force: bool) -> Option<Vec<EquityPoint>> {
+        let mut series = self.series.lock().unwrap();
+        let due = series
+            .last_flush
+            .is_some_and(|last| now_secs >= last + self.config.flush_secs);
+        if series.unflushed.is_empty() || !(force || due) {
+            return None;
+        }
+        series.last_flush = Some(now_secs);
+        Some(std::mem::take(&mut series.unflushed))
+    }
+
+    pub fn points(&self) -> Vec<EquityPoint> {
+        self.series.lock().unwrap().points.iter().copied().collect()
+    }
+
+    /// The samples with Sharpe, Sortino and drawdown over them. Fill and
+    /// trade statistics are left empty: the curve doesn't keep fills.
+    pub fn report(&self) -> EquityReport {
+        let points = self.points();
+        let curve: Vec<(u64, f64)> = points.iter().map(|p| (p.timestamp, p.equity)).collect();
+        EquityReport {
+            performance: PerformanceReport::compute(&curve, &[]),
+            points,
+        }
+    }
+}
+
+/// Appends the points to the CSV at `path`, with a header if the file is
+/// new
+pub fn append_csv(path: &Path, points: &[EquityPoint]) -> Result<(), String> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let empty = file
+        .metadata()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len()
+        == 0;
+    let mut csv = String::new();
+    if empty {
+        csv.push_str("timestamp,equity,realized_pnl,unrealized_pnl,gross_exposure\n");
+    }
+    for p in points {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{}",
+            p.timestamp, p.equity, p.realized_pnl, p.unrealized_pnl, p.gross_exposure
+        );
+    }
+    file.write_all(csv.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
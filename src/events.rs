@@ -0,0 +1,148 @@
+use crate::monitor::ExitReason;
+use crate::reconcile::ReconcilePolicy;
+use crate::session::SessionSummary;
+use crate::shadow::StrategyMode;
+use crate::streaks::StreakKind;
+use crate::venue::VenueId;
+use crate::{Fill, KillSwitchReport, Order, OrderSide, Position, Price};
+use serde::{Deserialize, Serialize};
+
+// Events buffered per subscriber before the slowest start missing some
+pub const EVENT_CAPACITY: usize = 4096;
+
+/// What the trading pipeline publishes on the bot's event channel
+/// (TradingBot::subscribe_events), serialized with a `type` tag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotEvent {
+    Price(Price),
+    Signal {
+        strategy: String,
+        symbol: String,
+        action: OrderSide,
+        confidence: f64,
+        target_price: f64,
+        quantity: f64,
+    },
+    OrderSubmitted(Order),
+    Fill(Fill),
+    RiskRejection {
+        symbol: String,
+        side: OrderSide,
+        quantity: f64,
+        strategy: Option<String>,
+        reason: String,
+    },
+    // The position after a fill
+    Position(Position),
+    // The first order of the trading day stopped by the daily loss limit
+    DailyLossLimit {
+        daily_pnl: f64,
+        limit: f64,
+    },
+    // The trading day rolled over; its counters as it closed
+    SessionSummary(SessionSummary),
+    DrawdownBreached {
+        equity: f64,
+        peak: f64,
+        drawdown_pct: f64,
+        limit_pct: f64,
+    },
+    KillSwitch(KillSwitchReport),
+    // The symbol's newest price or book is older than the staleness limit;
+    // it stops trading until fresh data arrives
+    DataStale {
+        symbol: String,
+        age_ms: u64,
+        cancelled_orders: usize,
+    },
+    DataFresh {
+        symbol: String,
+    },
+    // The symbol's trading session closed (outside its trading windows or
+    // in a blackout): it opens no new positions, but its stops keep working
+    SessionClosed {
+        symbol: String,
+        reason: String,
+        cancelled_orders: usize,
+        flattened: bool,
+    },
+    SessionOpened {
+        symbol: String,
+    },
+    // The bot sent an order closing a position on its own
+    PositionExit {
+        symbol: String,
+        venue: VenueId,
+        quantity: f64,
+        // Whoever opened it
+        strategy: Option<String>,
+        reason: ExitReason,
+        held_secs: u64,
+    },
+    // The volatility breaker paused the symbol's entries after an abnormal
+    // move; its working orders were cancelled, its protection kept
+    VolatilityHalt {
+        symbol: String,
+        move_pct: f64,
+        z_score: Option<f64>,
+        cooldown_secs: u64,
+        cancelled_orders: usize,
+    },
+    VolatilityResumed {
+        symbol: String,
+    },
+    // A strategy panicked while analyzing the symbol; after too many panics
+    // in a row it is disabled
+    StrategyPanicked {
+        strategy: String,
+        symbol: String,
+        message: String,
+        consecutive: u32,
+        disabled: bool,
+    },
+    // A strategy lost or was rejected `count` times in a row and sits out
+    // in `mode` until `until` (seconds)
+    StrategyBenched {
+        strategy: String,
+        kind: StreakKind,
+        count: u32,
+        mode: StrategyMode,
+        until: u64,
+    },
+    // A benched strategy's cooldown ended and it went back to `mode`
+    StrategyReinstated {
+        strategy: String,
+        mode: StrategyMode,
+    },
+    // One of the bot's tasks panicked; it is restarted or the bot stops
+    TaskFailed {
+        task: String,
+        message: String,
+        // Restarts before this failure
+        restarts: u32,
+        restarting: bool,
+    },
+    // A venue feed's WebSocket connection came up or dropped
+    FeedState {
+        feed: String,
+        connected: bool,
+    },
+    // A feed failed to connect this many times in a row; it keeps retrying
+    FeedDown {
+        feed: String,
+        failures: u32,
+    },
+    // Reconciliation found the venue and the bot disagreeing, and resolved
+    // it by the policy; under Halt the bot stopped trading
+    ReconciliationMismatch {
+        venue: VenueId,
+        policy: ReconcilePolicy,
+        discrepancies: Vec<String>,
+        halted: bool,
+    },
+    // A subscriber fell behind and missed this many events
+    Dropped {
+        count: u64,
+    },
+}
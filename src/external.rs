@@ -0,0 +1,228 @@
+use crate::history::parse_timestamp_ms;
+use crate::symbols::assets;
+use crate::{OrderSide, TimeInForce, TradingSignal};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+// What signals are attributed to when they don't name a strategy
+pub const DEFAULT_STRATEGY: &str = "external";
+
+// When a signal stops being good: Unix seconds, or an RFC 3339 time such
+// as TradingView's {{timenow}} plus a margin
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Expiry {
+    UnixSecs(u64),
+    Timestamp(String),
+}
+
+impl Expiry {
+    pub fn to_ms(&self) -> Result<u64, String> {
+        match self {
+            Expiry::UnixSecs(secs) => Ok(secs * 1_000),
+            Expiry::Timestamp(value) => parse_timestamp_ms(value),
+        }
+    }
+}
+
+/// A signal generated outside the bot (a TradingView alert, a model), as
+/// POST /signals takes it:
+///
+/// ```json
+/// {"symbol": "SOL/USDT", "side": "buy", "confidence": 0.8,
+///  "target_price": 101.5, "quantity": 2.0, "strategy": "tv-breakout",
+///  "expires_at": 1717000000}
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExternalSignal {
+    pub symbol: String,
+    // "buy" or "sell", in any case
+    pub side: String,
+    #[serde(default = "default_confidence")]
+    pub confidence: f64,
+    pub target_price: f64,
+    pub quantity: f64,
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    // Rest as a limit at this price instead of going to market
+    #[serde(default)]
+    pub limit_price: Option<f64>,
+    // What the signal's orders are attributed to
+    #[serde(default)]
+    pub strategy: Option<String>,
+    // Without one, the signal is good for the endpoint's signal_ttl_secs
+    #[serde(default)]
+    pub expires_at: Option<Expiry>,
+    // The shared token, for senders that can't set headers
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+fn default_confidence() -> f64 {
+    1.0
+}
+
+// Why a signal was turned away
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignalRejection {
+    Malformed(String),
+    Expired { expired_at_ms: u64, now_ms: u64 },
+    // Not a symbol the bot is trading
+    UnknownSymbol(String),
+}
+
+impl fmt::Display for SignalRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalRejection::Malformed(reason) => write!(f, "malformed signal: {}", reason),
+            SignalRejection::Expired {
+                expired_at_ms,
+                now_ms,
+            } => write!(
+                f,
+                "signal expired at {} ms, {} ms ago",
+                expired_at_ms,
+                now_ms - expired_at_ms
+            ),
+            SignalRejection::UnknownSymbol(symbol) => {
+                write!(f, "{} isn't a symbol the bot trades", symbol)
+            }
+        }
+    }
+}
+
+// A signal accepted and waiting for its symbol's next evaluation
+#[derive(Debug, Clone)]
+pub struct PendingSignal {
+    pub strategy: String,
+    pub signal: TradingSignal,
+    pub expires_at_ms: u64,
+}
+
+impl ExternalSignal {
+    /// The signal checked: a well-formed symbol, a side, a positive
+    /// quantity and price (and limit price, if any), a confidence in
+    /// [0, 1], and not expired
+    pub fn validate(
+        self,
+        now_ms: u64,
+        default_ttl_ms: u64,
+    ) -> Result<PendingSignal, SignalRejection> {
+        let malformed = |reason: String| Err(SignalRejection::Malformed(reason));
+        if assets(&self.symbol).is_none() {
+            return malformed(format!(
+                "symbol must look like BASE/QUOTE, got {}",
+                self.symbol
+            ));
+        }
+        let action = match self.side.to_ascii_lowercase().as_str() {
+            "buy" => OrderSide::Buy,
+            "sell" => OrderSide::Sell,
+            _ => return malformed(format!("side must be buy or sell, got {}", self.side)),
+        };
+        if !(self.quantity.is_finite() && self.quantity > 0.0) {
+            return malformed(format!("quantity must be positive, got {}", self.quantity));
+        }
+        if !(self.target_price.is_finite() && self.target_price > 0.0) {
+            return malformed(format!(
+                "target_price must be positive, got {}",
+                self.target_price
+            ));
+        }
+        if let Some(price) = self.limit_price
+            && !(price.is_finite() && price > 0.0)
+        {
+            return malformed(format!("limit_price must be positive, got {}", price));
+        }
+        if !(0.0..=1.0).contains(&self.confidence) {
+            return malformed(format!(
+                "confidence must be between 0 and 1, got {}",
+                self.confidence
+            ));
+        }
+        let strategy = match self.strategy {
+            Some(strategy) if strategy.trim().is_empty() => {
+                return malformed("strategy must not be blank".to_string());
+            }
+            Some(strategy) => strategy,
+            None => DEFAULT_STRATEGY.to_string(),
+        };
+        let expires_at_ms = match &self.expires_at {
+            Some(expiry) => expiry.to_ms().map_err(SignalRejection::Malformed)?,
+            None => now_ms + default_ttl_ms,
+        };
+        if expires_at_ms <= now_ms {
+            return Err(SignalRejection::Expired {
+                expired_at_ms: expires_at_ms,
+                now_ms,
+            });
+        }
+        Ok(PendingSignal {
+            strategy,
+            signal: TradingSignal {
+                symbol: Arc::from(self.symbol.as_str()),
+                action,
+                confidence: self.confidence,
+                target_price: self.target_price,
+                quantity: self.quantity,
+                time_in_force: self.time_in_force,
+                limit_price: self.limit_price,
+            },
+            expires_at_ms,
+        })
+    }
+}
+
+/// Accepted signals by symbol, until the symbol's next evaluation takes
+/// them in alongside its strategies' signals. Each symbol keeps its
+/// newest MAX_PENDING.
+#[derive(Debug, Default)]
+pub struct ExternalSignals {
+    pending: Mutex<HashMap<String, Vec<PendingSignal>>>,
+}
+
+impl ExternalSignals {
+    const MAX_PENDING: usize = 100;
+
+    pub fn push(&self, signal: PendingSignal) {
+        let mut pending = self.pending.lock().unwrap();
+        let queue = pending.entry(signal.signal.symbol.to_string()).or_default();
+        if queue.len() == Self::MAX_PENDING {
+            queue.remove(0);
+        }
+        queue.push(signal);
+    }
+
+    pub fn has_pending(&self, symbol: &str) -> bool {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(symbol)
+            .is_some_and(|queue| !queue.is_empty())
+    }
+
+    // The symbol's signals still good at now_ms, oldest first; expired
+    // ones are dropped
+    pub fn take(&self, symbol: &str, now_ms: u64) -> Vec<PendingSignal> {
+        let Some(queue) = self.pending.lock().unwrap().remove(symbol) else {
+            return Vec::new();
+        };
+        let (live, expired): (Vec<_>, Vec<_>) = queue
+            .into_iter()
+            .partition(|signal| signal.expires_at_ms > now_ms);
+        for signal in expired {
+            println!(
+                "External signal from {} on {} expired before it was evaluated",
+                signal.strategy, symbol
+            );
+        }
+        live
+    }
+
+    // Drops whatever is waiting for the symbol
+    pub fn clear(&self, symbol: &str) {
+        self.pending.lock().unwrap().remove(symbol);
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Whether a fill added liquidity to the book or took it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+// Maker and taker rates in basis points of notional
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    pub maker_bps: f64,
+    pub taker_bps: f64,
+}
+
+impl FeeSchedule {
+    pub fn bps(&self, liquidity: Liquidity) -> f64 {
+        match liquidity {
+            Liquidity::Maker => self.maker_bps,
+            Liquidity::Taker => self.taker_bps,
+        }
+    }
+}
+
+// Commission charged on fills, consulted when fills are generated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeeModel {
+    pub maker_bps: f64,
+    pub taker_bps: f64,
+    // Floor on the fee for any single fill, in quote currency
+    pub min_fee: f64,
+    // Symbols with their own schedule (e.g. promotional zero-fee pairs)
+    pub symbols: HashMap<String, FeeSchedule>,
+}
+
+impl Default for FeeModel {
+    fn default() -> Self {
+        Self {
+            maker_bps: 2.0,
+            taker_bps: 10.0,
+            min_fee: 0.0,
+            symbols: HashMap::new(),
+        }
+    }
+}
+
+impl FeeModel {
+    // No fees at all, for tests and what-if comparisons
+    pub fn zero() -> Self {
+        Self {
+            maker_bps: 0.0,
+            taker_bps: 0.0,
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        // Negative maker fees (rebates) are fine; negative taker fees aren't
+        if self.taker_bps < 0.0 {
+            return Err("taker_bps must be non-negative".to_string());
+        }
+        if let Some(symbol) = self
+            .symbols
+            .iter()
+            .find_map(|(s, f)| (f.taker_bps < 0.0).then_some(s))
+        {
+            return Err(format!("taker_bps for {} must be non-negative", symbol));
+        }
+        if self.min_fee < 0.0 {
+            return Err("min_fee must be non-negative".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn schedule(&self, symbol: &str) -> FeeSchedule {
+        self.symbols.get(symbol).copied().unwrap_or(FeeSchedule {
+            maker_bps: self.maker_bps,
+            taker_bps: self.taker_bps,
+        })
+    }
+
+    // Fee for a fill of the given notional. Rebates (negative maker rates)
+    // are paid out as-is; the minimum only applies to positive fees.
+    pub fn fee(&self, symbol: &str, notional: f64, liquidity: Liquidity) -> f64 {
+        let fee = notional.abs() * self.schedule(symbol).bps(liquidity) / 10_000.0;
+        if fee > 0.0 {
+            fee.max(self.min_fee)
+        } else {
+            fee
+        }
+    }
+
+    // Smallest spread, in bps, that covers the fees of entering and exiting
+    // with the given liquidity on each side. Spread-capturing strategies
+    // should not quote inside this.
+    pub fn min_profitable_spread_bps(
+        &self,
+        symbol: &str,
+        entry: Liquidity,
+        exit: Liquidity,
+    ) -> f64 {
+        let schedule = self.schedule(symbol);
+        schedule.bps(entry) + schedule.bps(exit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_is_the_rate_with_a_floor_that_spares_rebates() {
+        let model = FeeModel {
+            maker_bps: -1.0,
+            taker_bps: 10.0,
+            min_fee: 0.5,
+            symbols: HashMap::from([(
+                "SOL/USDT".to_string(),
+                FeeSchedule {
+                    maker_bps: 0.0,
+                    taker_bps: 5.0,
+                },
+            )]),
+        };
+        assert_eq!(model.fee("BTC/USDT", 10_000.0, Liquidity::Taker), 10.0);
+        assert_eq!(model.fee("BTC/USDT", 100.0, Liquidity::Taker), 0.5);
+        assert_eq!(model.fee("BTC/USDT", 10_000.0, Liquidity::Maker), -1.0);
+        assert_eq!(model.fee("SOL/USDT", 10_000.0, Liquidity::Taker), 5.0);
+        assert_eq!(
+            model.min_profitable_spread_bps("BTC/USDT", Liquidity::Maker, Liquidity::Taker),
+            9.0
+        );
+    }
+}
@@ -0,0 +1,1611 @@
+use crate::binance::{OrderRef, VenueOrder, VenueStatus};
+use crate::clock::{Clock, SystemClock};
+use crate::fees::{FeeModel, Liquidity};
+use crate::history::{civil_from_days, parse_timestamp_ms};
+use crate::router::VenueExecutor;
+use crate::symbols::{SymbolFormat, SymbolId, SymbolMapper, SymbolMapping};
+use crate::time_sync::ServerTime;
+use crate::venue::VenueId;
+use crate::ws::{Backoff, ReconnectConfig};
+use crate::{
+    ExecutionError, Fill, MarketData, Order, OrderBook, OrderSide, OrderType, PositionSide, Price,
+    TimeInForce,
+};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{Notify, mpsc, oneshot, watch};
+
+// Overrides the config file so the password can stay out of it
+pub const PASSWORD_ENV: &str = "FIX_PASSWORD";
+
+// Field separator
+pub const SOH: u8 = 0x01;
+
+// Largest BodyLength accepted off the wire; order entry messages are a
+// few hundred bytes, so anything near this is garbage or hostile
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+
+// Tags the session layer uses
+const BEGIN_STRING: u32 = 8;
+const BODY_LENGTH: u32 = 9;
+const CHECKSUM: u32 = 10;
+const MSG_TYPE: u32 = 35;
+const SENDER_COMP_ID: u32 = 49;
+const TARGET_COMP_ID: u32 = 56;
+const MSG_SEQ_NUM: u32 = 34;
+const SENDING_TIME: u32 = 52;
+const POSS_DUP_FLAG: u32 = 43;
+const ORIG_SENDING_TIME: u32 = 122;
+const PASSWORD: u32 = 554;
+
+// FIX 4.4 order entry over a plain TCP session. Debug leaves the password
+// out.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixConfig {
+    // The acceptor. Plain TCP only: reach a TLS endpoint through a local
+    // tunnel such as stunnel.
+    pub host: String,
+    pub port: u16,
+    pub begin_string: String,
+    pub sender_comp_id: String,
+    pub target_comp_id: String,
+    // Sent on Logon as Username(553) and Password(554) where the venue
+    // wants them; the password falls back to FIX_PASSWORD
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub heartbeat_secs: u64,
+    // Start every session at sequence number 1 (ResetSeqNumFlag=Y). Off,
+    // sequence numbers carry over reconnects, but not restarts.
+    pub reset_on_logon: bool,
+    // Orders unacknowledged by then fail with ExecutionError::Timeout
+    pub timeout_ms: u64,
+    // Resend orders the venue asks for again rather than gap-filling
+    // them. Off by default: a resent order may be long stale.
+    pub resend_orders: bool,
+    // Retrying a dropped session
+    pub reconnect: ReconnectConfig,
+    pub fees: FeeModel,
+    // How the venue names our symbols; SOL/USDT unless set otherwise
+    pub mapping: SymbolMapping,
+}
+
+impl Default for FixConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 0,
+            begin_string: "FIX.4.4".to_string(),
+            sender_comp_id: String::new(),
+            target_comp_id: String::new(),
+            username: None,
+            password: None,
+            heartbeat_secs: 30,
+            reset_on_logon: true,
+            timeout_ms: 5_000,
+            resend_orders: false,
+            reconnect: ReconnectConfig::default(),
+            fees: FeeModel::default(),
+            mapping: SymbolMapping::default(),
+        }
+    }
+}
+
+impl fmt::Debug for FixConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("begin_string", &self.begin_string)
+            .field("sender_comp_id", &self.sender_comp_id)
+            .field("target_comp_id", &self.target_comp_id)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("heartbeat_secs", &self.heartbeat_secs)
+            .field("reset_on_logon", &self.reset_on_logon)
+            .field("timeout_ms", &self.timeout_ms)
+            .field("resend_orders", &self.resend_orders)
+            .field("reconnect", &self.reconnect)
+            .field("fees", &self.fees)
+            .field("mapping", &self.mapping)
+            .finish()
+    }
+}
+
+impl FixConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host.is_empty() || self.port == 0 {
+            return Err("FIX host and port must be set".to_string());
+        }
+        if self.sender_comp_id.is_empty() || self.target_comp_id.is_empty() {
+            return Err("FIX sender_comp_id and target_comp_id must be set".to_string());
+        }
+        if self.heartbeat_secs == 0 {
+            return Err("FIX heartbeat_secs must be positive".to_string());
+        }
+        if self.timeout_ms == 0 {
+            return Err("FIX timeout_ms must be positive".to_string());
+        }
+        self.reconnect.validate()?;
+        self.fees.validate()
+    }
+
+    pub fn symbol_mapper(&self) -> SymbolMapper {
+        SymbolMapper::new(SymbolFormat::Slashed, &self.mapping)
+    }
+
+    pub fn password(&self) -> Option<String> {
+        std::env::var(PASSWORD_ENV)
+            .ok()
+            .or_else(|| self.password.clone())
+            .filter(|password| !password.is_empty())
+    }
+}
+
+/// One FIX message as tag=value fields in order, without the BeginString,
+/// BodyLength and CheckSum that framing adds
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FixMessage {
+    fields: Vec<(u32, String)>,
+}
+
+impl FixMessage {
+    pub fn new(msg_type: &str) -> Self {
+        Self::default().with(MSG_TYPE, msg_type)
+    }
+
+    pub fn with(mut self, tag: u32, value: impl ToString) -> Self {
+        self.push(tag, value);
+        self
+    }
+
+    pub fn push(&mut self, tag: u32, value: impl ToString) {
+        self.fields.push((tag, value.to_string()));
+    }
+
+    // The first value of the tag
+    pub fn get(&self, tag: u32) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn fields(&self) -> &[(u32, String)] {
+        &self.fields
+    }
+
+    pub fn msg_type(&self) -> &str {
+        self.get(MSG_TYPE).unwrap_or_default()
+    }
+
+    pub fn seq_num(&self) -> Option<u64> {
+        self.get(MSG_SEQ_NUM)?.parse().ok()
+    }
+
+    fn flag(&self, tag: u32) -> bool {
+        self.get(tag) == Some("Y")
+    }
+
+    /// The message framed for the wire: BeginString and BodyLength ahead
+    /// of the fields, CheckSum (the byte sum mod 256) after them
+    pub fn encode(&self, begin_string: &str) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (tag, value) in &self.fields {
+            body.extend_from_slice(format!("{}={}", tag, value).as_bytes());
+            body.push(SOH);
+        }
+        let mut frame = format!(
+            "{}={}\x01{}={}\x01",
+            BEGIN_STRING,
+            begin_string,
+            BODY_LENGTH,
+            body.len()
+        )
+        .into_bytes();
+        frame.extend_from_slice(&body);
+        let checksum = checksum(&frame);
+        frame.extend_from_slice(format!("{}={:03}\x01", CHECKSUM, checksum).as_bytes());
+        frame
+    }
+
+    /// One whole frame, its BodyLength and CheckSum checked
+    pub fn decode(frame: &[u8]) -> Result<Self, String> {
+        let text = std::str::from_utf8(frame).map_err(|_| "FIX message isn't UTF-8".to_string())?;
+        let mut fields = Vec::new();
+        for field in text.strip_suffix('\x01').unwrap_or(text).split('\x01') {
+            let (tag, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("Malformed FIX field {:?}", field))?;
+            let tag = tag
+                .parse::<u32>()
+                .map_err(|_| format!("Malformed FIX tag {:?}", tag))?;
+            fields.push((tag, value.to_string()));
+        }
+        let (Some((BEGIN_STRING, _)), Some((BODY_LENGTH, length)), Some((CHECKSUM, sum))) =
+            (fields.first(), fields.get(1), fields.last())
+        else {
+            return Err("FIX message must start 8=, 9= and end 10=".to_string());
+        };
+        let body_start = text
+            .find("\x019=")
+            .and_then(|at| text[at + 1..].find('\x01').map(|end| at + 1 + end + 1))
+            .ok_or("FIX message without a BodyLength")?;
+        let trailer_start = text
+            .rfind("\x0110=")
+            .map(|at| at + 1)
+            .ok_or("FIX message without a CheckSum")?;
+        if length.parse::<usize>().ok() != Some(trailer_start - body_start) {
+            return Err(format!(
+                "FIX BodyLength {} but the body is {} bytes",
+                length,
+                trailer_start - body_start
+            ));
+        }
+        let computed = checksum(&frame[..trailer_start]);
+        if sum.parse::<u32>().ok() != Some(computed) {
+            return Err(format!("FIX CheckSum {} but computed {:03}", sum, computed));
+        }
+        fields.truncate(fields.len() - 1);
+        fields.drain(..2);
+        Ok(Self { fields })
+    }
+}
+
+// Fields separated by '|', with the password left out
+impl fmt::Display for FixMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (tag, value) in &self.fields {
+            match *tag {
+                PASSWORD => write!(f, "{}=<redacted>|", tag)?,
+                _ => write!(f, "{}={}|", tag, value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().map(|&b| u32::from(b)).sum::<u32>() % 256
+}
+
+/// Splits a byte stream into frames. Bytes ahead of a BeginString are
+/// skipped; a frame that fails its checks is dropped and reported.
+#[derive(Debug, Default)]
+pub struct FixDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FixDecoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn next_message(&mut self) -> Result<Option<FixMessage>, String> {
+        let Some(start) = self.buffer.windows(2).position(|w| w == b"8=") else {
+            // Keep a trailing '8' that may begin the next frame
+            let keep = usize::from(self.buffer.last() == Some(&b'8'));
+            self.buffer.drain(..self.buffer.len() - keep);
+            return Ok(None);
+        };
+        self.buffer.drain(..start);
+        // 8=...|9=N| then N body bytes and the 7-byte 10=xxx|
+        let Some(begin_end) = self.buffer.iter().position(|&b| b == SOH) else {
+            return Ok(None);
+        };
+        let rest = &self.buffer[begin_end + 1..];
+        let Some(length_end) = rest.iter().position(|&b| b == SOH) else {
+            return Ok(None);
+        };
+        let length = std::str::from_utf8(&rest[..length_end])
+            .ok()
+            .and_then(|field| field.strip_prefix("9="))
+            .and_then(|length| length.parse::<usize>().ok());
+        let Some(length) = length else {
+            // Not a frame after all; look for the next one
+            self.buffer.drain(..1);
+            return Err("FIX message without a BodyLength".to_string());
+        };
+        let frame_len = Some(length)
+            .filter(|&length| length <= MAX_BODY_LENGTH)
+            .and_then(|length| (begin_end + 1 + length_end + 1 + 7).checked_add(length));
+        let Some(frame_len) = frame_len else {
+            self.skip_to_next_frame();
+            return Err(format!(
+                "FIX BodyLength {} over the {} byte limit",
+                length, MAX_BODY_LENGTH
+            ));
+        };
+        if self.buffer.len() < frame_len {
+            return Ok(None);
+        }
+        let frame: Vec<u8> = self.buffer.drain(..frame_len).collect();
+        FixMessage::decode(&frame).map(Some)
+    }
+
+    // Drops the frame at the front up to the next BeginString, keeping a
+    // partial one at the end that more bytes may complete
+    fn skip_to_next_frame(&mut self) {
+        const BEGIN: &[u8] = b"8=FIX";
+        let next = self.buffer[1..]
+            .windows(BEGIN.len())
+            .position(|w| w == BEGIN)
+            .map(|at| at + 1)
+            .unwrap_or_else(|| {
+                let keep = (1..BEGIN.len())
+                    .rev()
+                    .find(|&n| self.buffer.len() > n && self.buffer.ends_with(&BEGIN[..n]))
+                    .unwrap_or(0);
+                self.buffer.len() - keep
+            });
+        self.buffer.drain(..next);
+    }
+}
+
+// UTCTimestamp, YYYYMMDD-HH:MM:SS.sss
+pub fn utc_timestamp(ms: u64) -> String {
+    let secs = ms / 1_000;
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}-{:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60,
+        ms % 1_000
+    )
+}
+
+// Milliseconds since the Unix epoch from a UTCTimestamp
+pub fn parse_utc_timestamp(value: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid UTCTimestamp {}", value);
+    let (date, time) = value.split_once('-').ok_or_else(invalid)?;
+    if date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    parse_timestamp_ms(&format!(
+        "{}-{}-{}T{}Z",
+        &date[..4],
+        &date[4..6],
+        &date[6..],
+        time
+    ))
+    .map_err(|_| invalid())
+}
+
+// What an incoming message left for the application
+#[derive(Debug, Default)]
+pub struct Received {
+    // Business messages, in sequence order
+    pub application: Vec<FixMessage>,
+    // Set when the session is over, with why
+    pub disconnect: Option<String>,
+}
+
+/// The session layer of one FIX connection: logon, heartbeats and test
+/// requests, and sequence numbers both ways. A gap in what arrives is
+/// asked for again with one ResendRequest and what arrived past it held
+/// until the gap is filled. What the counterparty asks for again is resent
+/// (orders only with resend_orders) or skipped with a gap fill. Frames to
+/// send collect in an outbox in sequence order.
+pub struct FixSession {
+    config: FixConfig,
+    next_out: u64,
+    next_in: u64,
+    // Application messages sent, by sequence number, for resends and for
+    // matching a Reject to its order
+    sent: BTreeMap<u64, FixMessage>,
+    // Arrived past a gap
+    held: BTreeMap<u64, FixMessage>,
+    resend_requested: bool,
+    logged_on: bool,
+    logon_sent_ms: Option<u64>,
+    last_sent_ms: u64,
+    last_received_ms: u64,
+    // The TestRequest unanswered, and when it was sent
+    test_request: Option<(String, u64)>,
+    outbox: Vec<Vec<u8>>,
+}
+
+impl FixSession {
+    const SENT_CAPACITY: usize = 10_000;
+
+    pub fn new(config: FixConfig) -> Self {
+        Self {
+            config,
+            next_out: 1,
+            next_in: 1,
+            sent: BTreeMap::new(),
+            held: BTreeMap::new(),
+            resend_requested: false,
+            logged_on: false,
+            logon_sent_ms: None,
+            last_sent_ms: 0,
+            last_received_ms: 0,
+            test_request: None,
+            outbox: Vec::new(),
+        }
+    }
+
+    pub fn is_logged_on(&self) -> bool {
+        self.logged_on
+    }
+
+    // The sequence numbers the next messages out and in will carry
+    pub fn next_sequence_numbers(&self) -> (u64, u64) {
+        (self.next_out, self.next_in)
+    }
+
+    // An application message sent lately
+    pub fn sent_message(&self, seq_num: u64) -> Option<&FixMessage> {
+        self.sent.get(&seq_num)
+    }
+
+    // Frames waiting to be written, oldest first
+    pub fn take_outbox(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    // Sends Logon, starting the session afresh
+    pub fn logon(&mut self, now_ms: u64) {
+        if self.config.reset_on_logon {
+            self.next_out = 1;
+            self.next_in = 1;
+            self.sent.clear();
+        }
+        self.held.clear();
+        self.resend_requested = false;
+        self.logged_on = false;
+        self.test_request = None;
+        self.outbox.clear();
+        let mut logon = FixMessage::new("A")
+            .with(98, 0)
+            .with(108, self.config.heartbeat_secs);
+        if self.config.reset_on_logon {
+            logon.push(141, "Y");
+        }
+        if let Some(username) = &self.config.username {
+            logon.push(553, username);
+        }
+        if let Some(password) = self.config.password() {
+            logon.push(PASSWORD, password);
+        }
+        self.send(logon, now_ms);
+        self.logon_sent_ms = Some(now_ms);
+        self.last_received_ms = now_ms;
+    }
+
+    // The connection is gone; nothing more is sent on it
+    pub fn disconnected(&mut self) {
+        self.logged_on = false;
+        self.logon_sent_ms = None;
+        self.test_request = None;
+        self.outbox.clear();
+    }
+
+    /// Queues the message under the next sequence number and returns it
+    pub fn send(&mut self, message: FixMessage, now_ms: u64) -> u64 {
+        let seq_num = self.next_out;
+        self.next_out += 1;
+        let framed = self.header(&message, seq_num, now_ms, None);
+        if !is_admin(message.msg_type()) {
+            if self.sent.len() == Self::SENT_CAPACITY {
+                self.sent.pop_first();
+            }
+            self.sent.insert(seq_num, framed.clone());
+        }
+        self.outbox.push(framed.encode(&self.config.begin_string));
+        self.last_sent_ms = now_ms;
+        seq_num
+    }
+
+    // The message with the standard header: MsgType, the comp ids,
+    // MsgSeqNum and SendingTime, plus PossDupFlag and OrigSendingTime on a
+    // resend
+    fn header(
+        &self,
+        message: &FixMessage,
+        seq_num: u64,
+        now_ms: u64,
+        orig_sending_time: Option<&str>,
+    ) -> FixMessage {
+        let mut framed = FixMessage::new(message.msg_type())
+            .with(SENDER_COMP_ID, &self.config.sender_comp_id)
+            .with(TARGET_COMP_ID, &self.config.target_comp_id)
+            .with(MSG_SEQ_NUM, seq_num)
+            .with(SENDING_TIME, utc_timestamp(now_ms));
+        if let Some(orig) = orig_sending_time {
+            framed.push(POSS_DUP_FLAG, "Y");
+            framed.push(ORIG_SENDING_TIME, orig);
+        }
+        let header = [
+            MSG_TYPE,
+            SENDER_COMP_ID,
+            TARGET_COMP_ID,
+            MSG_SEQ_NUM,
+            SENDING_TIME,
+            POSS_DUP_FLAG,
+            ORIG_SENDING_TIME,
+        ];
+        framed.fields.extend(
+            message
+                .fields
+                .iter()
+                .filter(|(tag, _)| !header.contains(tag))
+                .cloned(),
+        );
+        framed
+    }
+
+    fn logout(&mut self, text: &str, now_ms: u64) {
+        self.send(FixMessage::new("5").with(58, text), now_ms);
+    }
+
+    /// Takes in one message from the counterparty
+    pub fn receive(&mut self, message: FixMessage, now_ms: u64) -> Received {
+        let mut received = Received::default();
+        self.last_received_ms = now_ms;
+        self.test_request = None;
+        if message.get(SENDER_COMP_ID) != Some(&self.config.target_comp_id)
+            || message.get(TARGET_COMP_ID) != Some(&self.config.sender_comp_id)
+        {
+            let text = "CompID problem";
+            self.logout(text, now_ms);
+            received.disconnect = Some(text.to_string());
+            return received;
+        }
+        // SequenceReset-Reset sets the next number whatever this one's is
+        if message.msg_type() == "4" && !message.flag(123) {
+            if let Some(new_seq) = message.get(36).and_then(|seq| seq.parse().ok()) {
+                self.next_in = new_seq;
+            }
+            return received;
+        }
+        let Some(seq_num) = message.seq_num() else {
+            let text = "MsgSeqNum missing";
+            self.logout(text, now_ms);
+            received.disconnect = Some(text.to_string());
+            return received;
+        };
+        if message.msg_type() == "A" && message.flag(141) {
+            self.next_in = seq_num;
+        }
+        if seq_num > self.next_in {
+            // Logon and ResendRequest are acted on at once; everything is
+            // processed in order once the gap is filled
+            match message.msg_type() {
+                "A" => self.logged_on = true,
+                "2" => self.answer_resend_request(&message, now_ms),
+                _ => {}
+            }
+            self.held.insert(seq_num, message);
+            if !self.resend_requested {
+                self.resend_requested = true;
+                let request = FixMessage::new("2").with(7, self.next_in).with(16, 0);
+                self.send(request, now_ms);
+            }
+            return received;
+        }
+        if seq_num < self.next_in {
+            if message.flag(POSS_DUP_FLAG) {
+                return received;
+            }
+            let text = format!(
+                "MsgSeqNum too low, expecting {} but received {}",
+                self.next_in, seq_num
+            );
+            self.logout(&text, now_ms);
+            received.disconnect = Some(text);
+            return received;
+        }
+        self.next_in += 1;
+        self.process(message, now_ms, &mut received);
+        while let Some(entry) = self.held.first_entry() {
+            if *entry.key() > self.next_in {
+                break;
+            }
+            let (held_seq, held) = entry.remove_entry();
+            if held_seq < self.next_in {
+                continue;
+            }
+            self.next_in += 1;
+            match held.msg_type() {
+                "A" | "2" => {}
+                _ => self.process(held, now_ms, &mut received),
+            }
+        }
+        if self.held.is_empty() {
+            self.resend_requested = false;
+        }
+        received
+    }
+
+    fn process(&mut self, message: FixMessage, now_ms: u64, received: &mut Received) {
+        match message.msg_type() {
+            "0" => {}
+            "1" => {
+                let mut heartbeat = FixMessage::new("0");
+                if let Some(id) = message.get(112) {
+                    heartbeat.push(112, id);
+                }
+                self.send(heartbeat, now_ms);
+            }
+            "2" => self.answer_resend_request(&message, now_ms),
+            "4" => {
+                if let Some(new_seq) = message.get(36).and_then(|seq| seq.parse::<u64>().ok()) {
+                    self.next_in = self.next_in.max(new_seq);
+                }
+            }
+            "5" => {
+                let text = message.get(58).unwrap_or("logged out").to_string();
+                if self.logged_on {
+                    self.send(FixMessage::new("5"), now_ms);
+                }
+                self.logged_on = false;
+                received.disconnect = Some(text);
+            }
+            "A" => {
+                self.logged_on = true;
+                self.logon_sent_ms = None;
+            }
+            // Session-level Rejects go to the application as well, which
+            // matches them to the order they refused
+            "3" => {
+                println!(
+                    "FIX Reject of message {}: {}",
+                    message.get(45).unwrap_or("?"),
+                    message.get(58).unwrap_or_default()
+                );
+                received.application.push(message);
+            }
+            _ => received.application.push(message),
+        }
+    }
+
+    // Resends the application messages asked for that are still known and
+    // resendable; runs of anything else are skipped with a gap fill
+    fn answer_resend_request(&mut self, request: &FixMessage, now_ms: u64) {
+        let begin = request
+            .get(7)
+            .and_then(|seq| seq.parse::<u64>().ok())
+            .unwrap_or(1)
+            .max(1);
+        let last_sent = self.next_out - 1;
+        let end = match request.get(16).and_then(|seq| seq.parse::<u64>().ok()) {
+            Some(0) | None => last_sent,
+            Some(end) => end.min(last_sent),
+        };
+        let mut gap_from: Option<u64> = None;
+        for seq_num in begin..=end {
+            let resend = self
+                .sent
+                .get(&seq_num)
+                .filter(|_| self.config.resend_orders)
+                .cloned();
+            let Some(original) = resend else {
+                gap_from.get_or_insert(seq_num);
+                continue;
+            };
+            if let Some(from) = gap_from.take() {
+                self.gap_fill(from, seq_num, now_ms);
+            }
+            let orig = original.get(SENDING_TIME).unwrap_or_default().to_string();
+            let framed = self.header(&original, seq_num, now_ms, Some(&orig));
+            self.outbox.push(framed.encode(&self.config.begin_string));
+        }
+        if let Some(from) = gap_from {
+            self.gap_fill(from, end + 1, now_ms);
+        }
+        self.last_sent_ms = now_ms;
+    }
+
+    fn gap_fill(&mut self, from: u64, new_seq_num: u64, now_ms: u64) {
+        let gap_fill = FixMessage::new("4").with(123, "Y").with(36, new_seq_num);
+        let framed = self.header(&gap_fill, from, now_ms, Some(&utc_timestamp(now_ms)));
+        self.outbox.push(framed.encode(&self.config.begin_string));
+    }
+
+    /// Keeps the session alive: a Heartbeat when nothing has been sent for
+    /// a heartbeat interval, a TestRequest when nothing has arrived for a
+    /// fifth longer than that. Errs once a TestRequest or the Logon goes
+    /// unanswered for an interval; the connection should be dropped.
+    pub fn tick(&mut self, now_ms: u64) -> Result<(), String> {
+        let interval = self.config.heartbeat_secs * 1_000;
+        if let Some(sent) = self.logon_sent_ms
+            && now_ms.saturating_sub(sent) > interval.max(self.config.timeout_ms)
+        {
+            return Err("no answer to Logon".to_string());
+        }
+        if !self.logged_on {
+            return Ok(());
+        }
+        if let Some((_, sent)) = &self.test_request {
+            if now_ms.saturating_sub(*sent) > interval {
+                return Err("no answer to TestRequest".to_string());
+            }
+            return Ok(());
+        }
+        if now_ms.saturating_sub(self.last_received_ms) > interval + interval / 5 {
+            let id = format!("TEST-{}", now_ms);
+            self.send(FixMessage::new("1").with(112, &id), now_ms);
+            self.test_request = Some((id, now_ms));
+        } else if now_ms.saturating_sub(self.last_sent_ms) >= interval {
+            self.send(FixMessage::new("0"), now_ms);
+        }
+        Ok(())
+    }
+}
+
+// Session-level message types, never resent
+fn is_admin(msg_type: &str) -> bool {
+    matches!(msg_type, "0" | "1" | "2" | "3" | "4" | "5" | "A")
+}
+
+fn side(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "1",
+        OrderSide::Sell => "2",
+    }
+}
+
+/// NewOrderSingle (D) for an order, in the venue's name for its symbol.
+/// Post-only is ExecInst ParticipateDontInitiate (18=6) and reduce-only
+/// DoNotIncrease (18=E).
+pub fn new_order_single(
+    order: &Order,
+    symbol: &str,
+    now_ms: u64,
+) -> Result<FixMessage, ExecutionError> {
+    let invalid = |reason: &str| ExecutionError::InvalidOrder {
+        reason: reason.to_string(),
+    };
+    let mut message = FixMessage::new("D")
+        .with(11, &order.client_order_id)
+        .with(55, symbol)
+        .with(54, side(order.side))
+        .with(60, utc_timestamp(now_ms))
+        .with(38, order.quantity);
+    match order.order_type {
+        OrderType::Market => message.push(40, "1"),
+        OrderType::Limit => {
+            let price = order
+                .price
+                .ok_or_else(|| invalid("limit order without a price"))?;
+            message.push(40, "2");
+            message.push(44, price);
+        }
+        OrderType::StopMarket { stop_price } => {
+            message.push(40, "3");
+            message.push(99, stop_price);
+        }
+        OrderType::StopLimit {
+            stop_price,
+            limit_price,
+        } => {
+            message.push(40, "4");
+            message.push(44, limit_price);
+            message.push(99, stop_price);
+        }
+    }
+    // ExecInst takes several instructions, space separated
+    let mut exec_inst = Vec::new();
+    match order.time_in_force {
+        TimeInForce::Gtc => message.push(59, "1"),
+        TimeInForce::Ioc => message.push(59, "3"),
+        TimeInForce::Fok => message.push(59, "4"),
+        TimeInForce::PostOnly if order.order_type == OrderType::Limit => {
+            message.push(59, "1");
+            exec_inst.push("6");
+        }
+        TimeInForce::PostOnly => return Err(invalid("only limit orders can be post-only")),
+    }
+    if order.reduce_only {
+        exec_inst.push("E");
+    }
+    if !exec_inst.is_empty() {
+        message.push(18, exec_inst.join(" "));
+    }
+    Ok(message)
+}
+
+/// OrderCancelRequest (F) for an order, itself identified by cancel_id
+pub fn order_cancel_request(
+    order: &Order,
+    symbol: &str,
+    cancel_id: &str,
+    now_ms: u64,
+) -> FixMessage {
+    FixMessage::new("F")
+        .with(41, &order.client_order_id)
+        .with(11, cancel_id)
+        .with(55, symbol)
+        .with(54, side(order.side))
+        .with(38, order.quantity)
+        .with(60, utc_timestamp(now_ms))
+}
+
+// OrdStatus(39) as ours; pending states are Other
+pub fn venue_status(ord_status: &str) -> VenueStatus {
+    match ord_status {
+        "0" => VenueStatus::New,
+        "1" => VenueStatus::PartiallyFilled,
+        "2" => VenueStatus::Filled,
+        "4" => VenueStatus::Canceled,
+        "8" => VenueStatus::Rejected,
+        "C" => VenueStatus::Expired,
+        _ => VenueStatus::Other,
+    }
+}
+
+// An ExecutionReport (8)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixExecutionReport {
+    pub order_id: String,
+    // The order's, or a cancel's
+    pub client_order_id: String,
+    // Set on cancels: the order cancelled
+    pub orig_client_order_id: Option<String>,
+    pub exec_id: String,
+    // 0 new, 4 cancelled, 8 rejected, F trade...; only F carries a fill
+    pub exec_type: String,
+    pub status: VenueStatus,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub last_quantity: f64,
+    pub last_price: f64,
+    pub cum_quantity: f64,
+    pub leaves_quantity: f64,
+    // In the quote asset
+    pub commission: f64,
+    // LastLiquidityInd(851) = 1, where the venue sends it
+    pub maker: bool,
+    // Milliseconds since the Unix epoch; 0 if not given
+    pub transaction_time: u64,
+    pub text: Option<String>,
+    pub reject_reason: Option<i64>,
+}
+
+impl FixExecutionReport {
+    // The order the report is about
+    pub fn order_client_id(&self) -> &str {
+        self.orig_client_order_id
+            .as_deref()
+            .unwrap_or(&self.client_order_id)
+    }
+}
+
+pub fn parse_execution_report(message: &FixMessage) -> Result<FixExecutionReport, String> {
+    let required = |tag: u32| {
+        message
+            .get(tag)
+            .ok_or_else(|| format!("ExecutionReport without tag {}: {}", tag, message))
+    };
+    let number = |tag: u32| -> Result<f64, String> {
+        match message.get(tag) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("ExecutionReport tag {} isn't a number: {}", tag, value)),
+            None => Ok(0.0),
+        }
+    };
+    let side = match required(54)? {
+        "1" => OrderSide::Buy,
+        "2" => OrderSide::Sell,
+        other => return Err(format!("ExecutionReport with unknown side {}", other)),
+    };
+    Ok(FixExecutionReport {
+        order_id: required(37)?.to_string(),
+        client_order_id: required(11)?.to_string(),
+        orig_client_order_id: message.get(41).map(str::to_string),
+        exec_id: required(17)?.to_string(),
+        exec_type: required(150)?.to_string(),
+        status: venue_status(required(39)?),
+        symbol: required(55)?.to_string(),
+        side,
+        last_quantity: number(32)?,
+        last_price: number(31)?,
+        cum_quantity: number(14)?,
+        leaves_quantity: number(151)?,
+        commission: number(12)?.abs(),
+        maker: message.get(851) == Some("1"),
+        transaction_time: message
+            .get(60)
+            .map(parse_utc_timestamp)
+            .transpose()?
+            .unwrap_or(0),
+        text: message.get(58).map(str::to_string),
+        reject_reason: message.get(103).and_then(|reason| reason.parse().ok()),
+    })
+}
+
+// Orders placed lately, by client order id, with the venue's last word on
+// each; the oldest are forgotten
+#[derive(Debug, Default)]
+struct PlacedOrders {
+    orders: HashMap<String, (OrderRef, Option<VenueOrder>)>,
+    arrival: VecDeque<String>,
+}
+
+impl PlacedOrders {
+    const CAPACITY: usize = 10_000;
+
+    fn insert(&mut self, order: &Order) {
+        if self.arrival.len() == Self::CAPACITY
+            && let Some(oldest) = self.arrival.pop_front()
+        {
+            self.orders.remove(&oldest);
+        }
+        self.arrival.push_back(order.client_order_id.clone());
+        let order_ref = OrderRef {
+            order_id: order.id.clone(),
+            symbol: order.symbol.to_string(),
+            strategy: order.strategy.clone(),
+            position_side: order.position_side,
+        };
+        self.orders
+            .insert(order.client_order_id.clone(), (order_ref, None));
+    }
+}
+
+// ExecIDs booked lately
+#[derive(Debug, Default)]
+struct SeenExecutions {
+    seen: HashSet<String>,
+    arrival: VecDeque<String>,
+}
+
+impl SeenExecutions {
+    const CAPACITY: usize = 10_000;
+
+    // Whether the execution is new
+    fn insert(&mut self, exec_id: &str) -> bool {
+        if !self.seen.insert(exec_id.to_string()) {
+            return false;
+        }
+        if self.arrival.len() == Self::CAPACITY
+            && let Some(oldest) = self.arrival.pop_front()
+        {
+            self.seen.remove(&oldest);
+        }
+        self.arrival.push_back(exec_id.to_string());
+        true
+    }
+}
+
+type Pending = oneshot::Sender<Result<FixExecutionReport, ExecutionError>>;
+
+/// Live order entry over one FIX 4.4 session, connected on start() and
+/// kept up (logged on again after a drop) until stop(). Submits and
+/// cancels wait for the venue's first ExecutionReport on them. Fills go to
+/// the bot's fill stream once route_fills is given it, each ExecID booked
+/// once.
+pub struct FixExecutor {
+    config: FixConfig,
+    venue: VenueId,
+    clock: Arc<dyn Clock>,
+    session: Mutex<FixSession>,
+    // Raised when the outbox has frames to write
+    wake: Notify,
+    logged_on: watch::Sender<bool>,
+    // Submits and cancels waiting on a report, by their ClOrdID
+    pending: Mutex<HashMap<String, Pending>>,
+    placed: Mutex<PlacedOrders>,
+    seen: Mutex<SeenExecutions>,
+    fills: Mutex<Option<mpsc::UnboundedSender<Fill>>>,
+    task: OnceLock<tokio::task::JoinHandle<()>>,
+    mapper: SymbolMapper,
+}
+
+impl FixExecutor {
+    pub fn new(config: &FixConfig) -> Result<Self, String> {
+        config.validate()?;
+        Ok(Self {
+            config: config.clone(),
+            venue: VenueId::new("fix"),
+            clock: Arc::new(SystemClock),
+            session: Mutex::new(FixSession::new(config.clone())),
+            wake: Notify::new(),
+            logged_on: watch::channel(false).0,
+            pending: Mutex::new(HashMap::new()),
+            placed: Mutex::new(PlacedOrders::default()),
+            seen: Mutex::new(SeenExecutions::default()),
+            fills: Mutex::new(None),
+            task: OnceLock::new(),
+            mapper: config.symbol_mapper(),
+        })
+    }
+
+    // The venue id fills are booked against, when not "fix"
+    pub fn with_venue(mut self, venue: VenueId) -> Self {
+        self.venue = venue;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Where fills go: TradingBot::fill_sender
+    pub fn route_fills(&self, fills: mpsc::UnboundedSender<Fill>) {
+        *self.fills.lock().unwrap() = Some(fills);
+    }
+
+    pub fn is_logged_on(&self) -> bool {
+        *self.logged_on.borrow()
+    }
+
+    // Connects and keeps the session up; later calls do nothing
+    pub fn start(self: &Arc<Self>) {
+        self.task.get_or_init(|| tokio::spawn(self.clone().run()));
+    }
+
+    // Drops the session for good
+    pub fn stop(&self) {
+        if let Some(task) = self.task.get() {
+            task.abort();
+        }
+        self.session.lock().unwrap().disconnected();
+        self.logged_on.send_replace(false);
+    }
+
+    // Our symbol as the venue names it
+    fn venue_symbol(&self, symbol: &str) -> Result<String, ExecutionError> {
+        self.mapper
+            .to_venue(symbol)
+            .map_err(ExecutionError::Unsupported)
+    }
+
+    // The request submit_order would send, without its session header
+    pub fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        let symbol = self.venue_symbol(order.symbol.as_str())?;
+        new_order_single(order, &symbol, self.clock.now_ms()).map(|message| message.to_string())
+    }
+
+    /// Places the order and returns the venue's OrderID for it
+    pub async fn submit_order(&self, order: &Order) -> Result<String, ExecutionError> {
+        let symbol = self.venue_symbol(order.symbol.as_str())?;
+        let message = new_order_single(order, &symbol, self.clock.now_ms())?;
+        self.placed.lock().unwrap().insert(order);
+        let report = self.request(&order.client_order_id, message).await?;
+        Ok(report.order_id)
+    }
+
+    pub async fn cancel_order(&self, order: &Order) -> Result<(), ExecutionError> {
+        let symbol = self.venue_symbol(order.symbol.as_str())?;
+        let cancel_id = uuid::Uuid::new_v4().to_string();
+        let message = order_cancel_request(order, &symbol, &cancel_id, self.clock.now_ms());
+        match self.request(&cancel_id, message).await {
+            Err(ExecutionError::UnknownOrder { .. }) => Err(ExecutionError::UnknownOrder {
+                order_id: order.id.clone(),
+            }),
+            result => result.map(|_| ()),
+        }
+    }
+
+    // The venue's last report on an order placed here lately
+    pub fn order_status(&self, order: &Order) -> Option<VenueOrder> {
+        self.placed
+            .lock()
+            .unwrap()
+            .orders
+            .get(&order.client_order_id)
+            .and_then(|(_, status)| status.clone())
+    }
+
+    // Sends the message once logged on and waits for the first report on
+    // the ClOrdID
+    async fn request(
+        &self,
+        client_order_id: &str,
+        message: FixMessage,
+    ) -> Result<FixExecutionReport, ExecutionError> {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let mut logged_on = self.logged_on.subscribe();
+        if !matches!(
+            tokio::time::timeout(timeout, logged_on.wait_for(|on| *on)).await,
+            Ok(Ok(_))
+        ) {
+            return Err(ExecutionError::Network(
+                "FIX session isn't logged on".to_string(),
+            ));
+        }
+        let (sender, answer) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(client_order_id.to_string(), sender);
+        {
+            let mut session = self.session.lock().unwrap();
+            if !session.is_logged_on() {
+                self.pending.lock().unwrap().remove(client_order_id);
+                return Err(ExecutionError::Network(
+                    "FIX session isn't logged on".to_string(),
+                ));
+            }
+            session.send(message, self.clock.now_ms());
+        }
+        self.wake.notify_one();
+        match tokio::time::timeout(timeout, answer).await {
+            Ok(Ok(result)) => result,
+            _ => {
+                self.pending.lock().unwrap().remove(client_order_id);
+                Err(ExecutionError::Timeout)
+            }
+        }
+    }
+
+    fn answer(&self, client_order_id: &str, result: Result<FixExecutionReport, ExecutionError>) {
+        if let Some(sender) = self.pending.lock().unwrap().remove(client_order_id) {
+            let _ = sender.send(result);
+        }
+    }
+
+    // A business message from the venue
+    fn on_application(&self, message: &FixMessage) {
+        match message.msg_type() {
+            "8" => match parse_execution_report(message) {
+                Ok(report) => self.on_execution_report(report),
+                Err(e) => println!("{}", e),
+            },
+            // OrderCancelReject; CxlRejReason 1 is an unknown order
+            "9" => {
+                let cancel_id = message.get(11).unwrap_or_default();
+                let text = message.get(58).unwrap_or_default().to_string();
+                let error = match message.get(102) {
+                    Some("1") => ExecutionError::UnknownOrder {
+                        order_id: message.get(41).unwrap_or_default().to_string(),
+                    },
+                    reason => ExecutionError::VenueRejected {
+                        code: reason.and_then(|r| r.parse().ok()).unwrap_or(0),
+                        message: text,
+                    },
+                };
+                self.answer(cancel_id, Err(error));
+            }
+            // A session-level Reject, matched to what it refused by RefSeqNum
+            "3" => {
+                let refused = message
+                    .get(45)
+                    .and_then(|seq| seq.parse().ok())
+                    .and_then(|seq| {
+                        let session = self.session.lock().unwrap();
+                        session
+                            .sent_message(seq)
+                            .and_then(|sent| sent.get(11))
+                            .map(str::to_string)
+                    });
+                if let Some(client_order_id) = refused {
+                    let reason = message.get(58).unwrap_or("rejected").to_string();
+                    self.answer(
+                        &client_order_id,
+                        Err(ExecutionError::InvalidOrder { reason }),
+                    );
+                }
+            }
+            // BusinessMessageReject, by BusinessRejectRefID
+            "j" => {
+                if let Some(client_order_id) = message.get(379) {
+                    let error = ExecutionError::VenueRejected {
+                        code: message.get(380).and_then(|r| r.parse().ok()).unwrap_or(0),
+                        message: message.get(58).unwrap_or_default().to_string(),
+                    };
+                    self.answer(client_order_id, Err(error));
+                }
+            }
+            other => println!("Ignoring FIX message type {}", other),
+        }
+    }
+
+    fn on_execution_report(&self, report: FixExecutionReport) {
+        let order = {
+            let mut placed = self.placed.lock().unwrap();
+            placed
+                .orders
+                .get_mut(report.order_client_id())
+                .map(|(order, status)| {
+                    *status = Some(VenueOrder {
+                        venue_order_id: report.order_id.clone(),
+                        client_order_id: report.order_client_id().to_string(),
+                        status: report.status,
+                        quantity: report.cum_quantity + report.leaves_quantity,
+                        executed_quantity: report.cum_quantity,
+                    });
+                    order.clone()
+                })
+        };
+        if let Some(fill) = self.fill(&report, order)
+            && let Some(fills) = &*self.fills.lock().unwrap()
+        {
+            // The receiver only goes away at shutdown
+            let _ = fills.send(fill);
+        }
+        let result = if report.exec_type == "8" || report.status == VenueStatus::Rejected {
+            Err(ExecutionError::VenueRejected {
+                code: report.reject_reason.unwrap_or(0),
+                message: report.text.clone().unwrap_or_default(),
+            })
+        } else {
+            Ok(report.clone())
+        };
+        self.answer(&report.client_order_id, result);
+    }
+
+    // The fill a trade report carries, unless already booked
+    fn fill(&self, report: &FixExecutionReport, order: Option<OrderRef>) -> Option<Fill> {
+        if report.exec_type != "F"
+            || report.last_quantity <= 0.0
+            || !self.seen.lock().unwrap().insert(&report.exec_id)
+        {
+            return None;
+        }
+        let timestamp = match report.transaction_time {
+            0 => self.clock.now_ms(),
+            ms => ms,
+        } / 1_000;
+        Some(Fill {
+            order_id: order
+                .as_ref()
+                .map_or(report.client_order_id.clone(), |order| {
+                    order.order_id.clone()
+                }),
+            symbol: order.as_ref().map_or_else(
+                || SymbolId::intern(&self.mapper.to_canonical(&report.symbol)),
+                |order| SymbolId::intern(&order.symbol),
+            ),
+            side: report.side,
+            quantity: report.last_quantity,
+            price: report.last_price,
+            fee: report.commission,
+            liquidity: if report.maker {
+                Liquidity::Maker
+            } else {
+                Liquidity::Taker
+            },
+            timestamp,
+            position_side: order
+                .as_ref()
+                .map_or(PositionSide::Both, |order| order.position_side),
+            strategy: order.and_then(|order| order.strategy),
+            venue: self.venue.clone(),
+        })
+    }
+
+    // Connects, logs on and serves the session, again after every drop
+    async fn run(self: Arc<Self>) {
+        let mut backoff = Backoff::new(self.config.reconnect.clone());
+        loop {
+            let address = format!("{}:{}", self.config.host, self.config.port);
+            let reason = match TcpStream::connect(&address).await {
+                Ok(stream) => self.serve(stream, &mut backoff).await,
+                Err(e) => format!("failed to connect to {}: {}", address, e),
+            };
+            println!("FIX session {} ended: {}", address, reason);
+            self.session.lock().unwrap().disconnected();
+            self.logged_on.send_replace(false);
+            // Whatever was waiting may or may not have reached the venue
+            for (_, sender) in self.pending.lock().unwrap().drain() {
+                let _ = sender.send(Err(ExecutionError::Timeout));
+            }
+            tokio::time::sleep(backoff.next_delay()).await;
+        }
+    }
+
+    // One connection, until it drops; returns why it did
+    async fn serve(&self, stream: TcpStream, backoff: &mut Backoff) -> String {
+        let (mut reader, mut writer) = stream.into_split();
+        self.session.lock().unwrap().logon(self.clock.now_ms());
+        let mut decoder = FixDecoder::default();
+        let mut buffer = vec![0u8; 16 * 1024];
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            let mut disconnect = None;
+            tokio::select! {
+                read = reader.read(&mut buffer) => {
+                    let read = match read {
+                        Ok(0) => return "closed by the venue".to_string(),
+                        Ok(read) => read,
+                        Err(e) => return e.to_string(),
+                    };
+                    decoder.push(&buffer[..read]);
+                    loop {
+                        let message = match decoder.next_message() {
+                            Ok(Some(message)) => message,
+                            Ok(None) => break,
+                            // Garbled messages are dropped, as if never sent
+                            Err(e) => {
+                                println!("Dropped a FIX message: {}", e);
+                                continue;
+                            }
+                        };
+                        let received = {
+                            let mut session = self.session.lock().unwrap();
+                            let received = session.receive(message, self.clock.now_ms());
+                            let now_on = session.is_logged_on();
+                            if self
+                                .logged_on
+                                .send_if_modified(|on| std::mem::replace(on, now_on) != now_on)
+                                && now_on
+                            {
+                                backoff.reset();
+                            }
+                            received
+                        };
+                        for message in &received.application {
+                            self.on_application(message);
+                        }
+                        if received.disconnect.is_some() {
+                            disconnect = received.disconnect;
+                            break;
+                        }
+                    }
+                }
+                _ = self.wake.notified() => {}
+                _ = ticker.tick() => {
+                    if let Err(e) = self.session.lock().unwrap().tick(self.clock.now_ms()) {
+                        disconnect = Some(e);
+                    }
+                }
+            }
+            let frames = self.session.lock().unwrap().take_outbox();
+            for frame in frames {
+                if let Err(e) = writer.write_all(&frame).await {
+                    return e.to_string();
+                }
+            }
+            if let Some(reason) = disconnect {
+                return reason;
+            }
+        }
+    }
+}
+
+impl ServerTime for FixExecutor {
+    // FIX has no time request; the local clock stands in
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        Box::pin(async move { Ok(self.clock.now_ms()) })
+    }
+}
+
+impl VenueExecutor for FixExecutor {
+    fn submit(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(async move { self.submit_order(&order).await })
+    }
+}
+
+// What a venue without market data serves: nothing
+pub(crate) struct NoMarketData;
+
+impl MarketData for NoMarketData {
+    fn get_price<'a>(&'a self, _symbol: &'a str) -> BoxFuture<'a, Option<Price>> {
+        Box::pin(async { None })
+    }
+
+    fn get_orderbook<'a>(&'a self, _symbol: &'a str) -> BoxFuture<'a, Option<OrderBook>> {
+        Box::pin(async { None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Logon as captured from a FIX 4.2 acceptor
+    const CAPTURED_LOGON: &[u8] = b"8=FIX.4.2\x019=65\x0135=A\x0149=SERVER\x0156=CLIENT\x0134=177\x0152=20090107-18:15:16\x0198=0\x01108=30\x0110=062\x01";
+
+    fn session(resend_orders: bool) -> FixSession {
+        FixSession::new(FixConfig {
+            sender_comp_id: "BOT".to_string(),
+            target_comp_id: "VENUE".to_string(),
+            resend_orders,
+            ..FixConfig::default()
+        })
+    }
+
+    fn inbound(msg_type: &str, seq_num: u64) -> FixMessage {
+        FixMessage::new(msg_type)
+            .with(SENDER_COMP_ID, "VENUE")
+            .with(TARGET_COMP_ID, "BOT")
+            .with(MSG_SEQ_NUM, seq_num)
+    }
+
+    fn sent(session: &mut FixSession) -> Vec<FixMessage> {
+        session
+            .take_outbox()
+            .iter()
+            .map(|frame| FixMessage::decode(frame).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn a_captured_frame_decodes_and_encodes_back_byte_for_byte() {
+        let message = FixMessage::decode(CAPTURED_LOGON).unwrap();
+        assert_eq!(message.msg_type(), "A");
+        assert_eq!(message.seq_num(), Some(177));
+        assert_eq!(message.get(108), Some("30"));
+        assert_eq!(message.encode("FIX.4.2"), CAPTURED_LOGON);
+
+        let mut corrupt = CAPTURED_LOGON.to_vec();
+        let at = corrupt.len() - 4;
+        corrupt[at] = b'3';
+        assert!(
+            FixMessage::decode(&corrupt)
+                .unwrap_err()
+                .contains("CheckSum")
+        );
+        let mut short = CAPTURED_LOGON.to_vec();
+        short[10..15].copy_from_slice(b"9=64\x01");
+        assert!(
+            FixMessage::decode(&short)
+                .unwrap_err()
+                .contains("BodyLength")
+        );
+    }
+
+    #[test]
+    fn a_stream_split_anywhere_yields_each_frame_once() {
+        let order = FixMessage::new("D").with(11, "c-1").with(55, "SOL/USDT");
+        let mut stream = b"noise".to_vec();
+        stream.extend_from_slice(CAPTURED_LOGON);
+        stream.extend_from_slice(&order.encode("FIX.4.4"));
+        for split in 0..stream.len() {
+            let mut decoder = FixDecoder::default();
+            let mut decoded = Vec::new();
+            for chunk in [&stream[..split], &stream[split..]] {
+                decoder.push(chunk);
+                while let Some(message) = decoder.next_message().unwrap() {
+                    decoded.push(message);
+                }
+            }
+            assert_eq!(decoded.len(), 2, "split at {}", split);
+            assert_eq!(decoded[0].seq_num(), Some(177));
+            assert_eq!(decoded[1], order);
+        }
+    }
+
+    #[test]
+    fn an_oversized_body_length_is_dropped_up_to_the_next_frame() {
+        let mut stream = b"8=FIX.4.2\x019=1000000\x0135=A\x0149=SERVER\x01".to_vec();
+        stream.extend_from_slice(CAPTURED_LOGON);
+        let mut decoder = FixDecoder::default();
+        decoder.push(&stream);
+        assert!(decoder.next_message().unwrap_err().contains("1000000"));
+        let message = decoder.next_message().unwrap().unwrap();
+        assert_eq!(message.seq_num(), Some(177));
+        assert_eq!(decoder.next_message(), Ok(None));
+
+        // Alone, it doesn't wait for a megabyte of body to arrive
+        let mut decoder = FixDecoder::default();
+        decoder.push(b"8=FIX.4.2\x019=1000000\x0135=A\x018=FI");
+        assert!(decoder.next_message().is_err());
+        assert_eq!(decoder.buffer, b"8=FI");
+    }
+
+    #[test]
+    fn an_overflowing_body_length_is_an_error_not_a_panic() {
+        let mut stream = format!("8=FIX.4.4\x019={}\x0135=0\x01", usize::MAX).into_bytes();
+        stream.extend_from_slice(CAPTURED_LOGON);
+        let mut decoder = FixDecoder::default();
+        decoder.push(&stream);
+        assert!(
+            decoder
+                .next_message()
+                .unwrap_err()
+                .contains(&usize::MAX.to_string())
+        );
+        assert_eq!(
+            decoder.next_message().unwrap().and_then(|m| m.seq_num()),
+            Some(177)
+        );
+    }
+
+    #[test]
+    fn a_sequence_gap_is_asked_for_once_and_delivered_in_order() {
+        let mut session = session(false);
+        session.logon(0);
+        session.take_outbox();
+        session.receive(inbound("A", 1), 10);
+        assert!(session.is_logged_on());
+        let report = |seq_num| inbound("8", seq_num).with(11, format!("c-{}", seq_num));
+
+        assert_eq!(session.receive(report(2), 20).application.len(), 1);
+        // 3 and 4 are lost
+        assert!(session.receive(report(5), 30).application.is_empty());
+        assert!(session.receive(report(6), 40).application.is_empty());
+        let requests = sent(&mut session);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].msg_type(), "2");
+        assert_eq!(requests[0].get(7), Some("3"));
+        assert_eq!(requests[0].get(16), Some("0"));
+
+        let mut delivered = Vec::new();
+        for seq_num in [3, 4] {
+            let resent = report(seq_num).with(POSS_DUP_FLAG, "Y");
+            delivered.extend(session.receive(resent, 50).application);
+        }
+        let ids: Vec<_> = delivered.iter().map(|m| m.get(11).unwrap()).collect();
+        assert_eq!(ids, ["c-3", "c-4", "c-5", "c-6"]);
+        assert_eq!(session.next_sequence_numbers().1, 7);
+        // A duplicate from before is dropped without a logout
+        let duplicate = session.receive(report(4).with(POSS_DUP_FLAG, "Y"), 60);
+        assert!(duplicate.application.is_empty() && duplicate.disconnect.is_none());
+        assert!(sent(&mut session).is_empty());
+    }
+
+    #[test]
+    fn a_resend_request_is_answered_with_orders_and_gap_fills() {
+        for resend_orders in [true, false] {
+            let mut session = session(resend_orders);
+            session.logon(0);
+            session.send(FixMessage::new("D").with(11, "c-2"), 1_000);
+            session.send(FixMessage::new("0"), 2_000);
+            session.send(FixMessage::new("D").with(11, "c-4"), 3_000);
+            session.receive(inbound("A", 1), 3_000);
+            session.take_outbox();
+
+            let request = inbound("2", 2).with(7, 1).with(16, 0);
+            assert!(session.receive(request, 4_000).application.is_empty());
+            let answer: Vec<_> = sent(&mut session)
+                .iter()
+                .map(|m| {
+                    let what = match m.msg_type() {
+                        "4" => format!("gap fill to {}", m.get(36).unwrap()),
+                        _ => m.get(11).unwrap().to_string(),
+                    };
+                    assert_eq!(m.get(POSS_DUP_FLAG), Some("Y"));
+                    (m.seq_num().unwrap(), what)
+                })
+                .collect();
+            let expected: Vec<(u64, String)> = if resend_orders {
+                vec![
+                    (1, "gap fill to 2".into()),
+                    (2, "c-2".into()),
+                    (3, "gap fill to 4".into()),
+                    (4, "c-4".into()),
+                ]
+            } else {
+                vec![(1, "gap fill to 5".into())]
+            };
+            assert_eq!(answer, expected);
+            // Resends keep their numbers; the next new message is 5
+            assert_eq!(session.next_sequence_numbers().0, 5);
+        }
+    }
+
+    #[test]
+    fn an_execution_report_parses_into_a_fill() {
+        let report = inbound("8", 2)
+            .with(37, "V-1")
+            .with(11, "c-1")
+            .with(17, "E-1")
+            .with(150, "F")
+            .with(39, "1")
+            .with(55, "SOLUSDT")
+            .with(54, "2")
+            .with(32, "0.5")
+            .with(31, "101.25")
+            .with(14, "0.5")
+            .with(151, "1.5")
+            .with(12, "-0.01")
+            .with(60, "20240102-03:04:05.678");
+        let parsed = parse_execution_report(&report).unwrap();
+        assert_eq!(parsed.side, OrderSide::Sell);
+        assert_eq!(parsed.status, VenueStatus::PartiallyFilled);
+        assert_eq!((parsed.last_quantity, parsed.last_price), (0.5, 101.25));
+        assert_eq!(parsed.commission, 0.01);
+        assert_eq!(
+            utc_timestamp(parsed.transaction_time),
+            "20240102-03:04:05.678"
+        );
+        assert!(parse_execution_report(&inbound("8", 3)).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::fees::Liquidity;
+use crate::symbols::SymbolId;
+use crate::venue::VenueId;
+use crate::{
+    Fill, Order, OrderBook, OrderSide, OrderType, Position, PositionSide, Price, TimeInForce,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Deterministic market data for benchmarks and tests: the same arguments
+// always give the same data, so runs stay comparable
+
+// Timestamp of every fixture's first price, 2024-01-01T00:00:00Z
+const START_SECS: u64 = 1_704_067_200;
+
+// A random walk of `n` prices starting at 100, moving up to 10 bps a step
+pub fn price_series(n: usize, seed: u64) -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut price = 100.0;
+    (0..n)
+        .map(|_| {
+            price *= 1.0 + rng.gen_range(-0.001..0.001);
+            price
+        })
+        .collect()
+}
+
+// `price_series` as ticks a second apart, with volumes
+pub fn price_history(symbol: &str, n: usize, seed: u64) -> Vec<Price> {
+    let mut rng = StdRng::seed_from_u64(seed ^ 0x9e37_79b9_7f4a_7c15);
+    price_series(n, seed)
+        .into_iter()
+        .enumerate()
+        .map(|(i, price)| Price {
+            symbol: symbol.to_string(),
+            price,
+            timestamp: START_SECS + i as u64,
+            volume: rng.gen_range(100.0..10_000.0),
+        })
+        .collect()
+}
+
+// `levels` a side around `mid`, 1 bp apart
+pub fn order_book(symbol: &str, mid: f64, levels: usize, seed: u64) -> OrderBook {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let step = mid * 0.0001;
+    let mut bids = Vec::with_capacity(levels);
+    let mut asks = Vec::with_capacity(levels);
+    for level in 1..=levels {
+        let offset = step * level as f64;
+        bids.push((mid - offset, rng.gen_range(10.0..1_000.0)));
+        asks.push((mid + offset, rng.gen_range(10.0..1_000.0)));
+    }
+    OrderBook::new(symbol.to_string(), bids, asks, START_SECS)
+}
+
+// Exactly these levels, sorted best first as any book is
+pub fn book(symbol: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> OrderBook {
+    OrderBook::new(symbol.to_string(), bids.to_vec(), asks.to_vec(), START_SECS)
+}
+
+// Small long and short positions in `n` symbols named SYM0/USDT,
+// SYM1/USDT, ...
+pub fn positions(n: usize, seed: u64) -> Vec<Position> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..n)
+        .map(|i| Position {
+            symbol: SymbolId::intern(&symbol(i)),
+            quantity: if i % 2 == 0 { 1.0 } else { -1.0 } * rng.gen_range(0.1..1.0),
+            avg_price: rng.gen_range(90.0..110.0),
+            unrealized_pnl: 0.0,
+            realized_pnl: 0.0,
+            venue: VenueId::paper(),
+            side: PositionSide::Both,
+        })
+        .collect()
+}
+
+// The name `positions` gives its i-th symbol
+pub fn symbol(i: usize) -> String {
+    format!("SYM{}/USDT", i)
+}
+
+pub fn market_order(symbol: &str, side: OrderSide, quantity: f64) -> Order {
+    Order {
+        id: "fixture".to_string(),
+        client_order_id: "fixture".to_string(),
+        symbol: SymbolId::intern(symbol),
+        side,
+        order_type: OrderType::Market,
+        quantity,
+        price: None,
+        time_in_force: TimeInForce::Gtc,
+        timestamp: START_SECS,
+        strategy: None,
+        link_group: None,
+        venue: VenueId::paper(),
+        position_side: PositionSide::Both,
+        reduce_only: false,
+    }
+}
+
+pub fn limit_order(symbol: &str, side: OrderSide, quantity: f64, price: f64) -> Order {
+    Order {
+        order_type: OrderType::Limit,
+        price: Some(price),
+        ..market_order(symbol, side, quantity)
+    }
+}
+
+pub fn stop_order(symbol: &str, side: OrderSide, quantity: f64, stop_price: f64) -> Order {
+    Order {
+        order_type: OrderType::StopMarket { stop_price },
+        ..market_order(symbol, side, quantity)
+    }
+}
+
+// A fee-free taker fill on the paper venue
+pub fn fill(symbol: &str, side: OrderSide, quantity: f64, price: f64) -> Fill {
+    Fill {
+        order_id: "fixture".to_string(),
+        symbol: SymbolId::intern(symbol),
+        side,
+        quantity,
+        price,
+        fee: 0.0,
+        liquidity: Liquidity::Taker,
+        timestamp: START_SECS,
+        strategy: None,
+        venue: VenueId::paper(),
+        position_side: PositionSide::Both,
+    }
+}
@@ -0,0 +1,101 @@
+use crate::PositionSide;
+use crate::venue::VenueId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// Funding on perpetual futures, paid between longs and shorts at fixed
+// times of day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FundingConfig {
+    pub enabled: bool,
+    // Seconds between funding times, counted from midnight UTC
+    pub interval_secs: u64,
+    // Rate per interval by symbol, until a venue reports its own
+    pub rates: HashMap<String, f64>,
+    // Seconds between polls of the primary venue's rates; 0 never polls
+    pub poll_secs: u64,
+}
+
+impl Default for FundingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 8 * 3_600,
+            rates: HashMap::new(),
+            poll_secs: 60,
+        }
+    }
+}
+
+impl FundingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_secs == 0 || !SECONDS_PER_DAY.is_multiple_of(self.interval_secs) {
+            return Err("Funding interval_secs must divide a day".to_string());
+        }
+        if let Some((symbol, _)) = self.rates.iter().find(|(_, rate)| rate.abs() >= 1.0) {
+            return Err(format!("Funding rate for {} must be below 1", symbol));
+        }
+        Ok(())
+    }
+
+    // The latest funding time at or before `now_secs`
+    pub fn funding_time(&self, now_secs: u64) -> u64 {
+        now_secs / self.interval_secs * self.interval_secs
+    }
+}
+
+/// The latest funding rate per symbol: as configured, then as the venue
+/// reports it. A positive rate has longs pay shorts.
+#[derive(Debug, Default)]
+pub struct FundingRates {
+    rates: RwLock<HashMap<String, f64>>,
+}
+
+impl FundingRates {
+    pub fn new(rates: HashMap<String, f64>) -> Self {
+        Self {
+            rates: RwLock::new(rates),
+        }
+    }
+
+    pub fn set(&self, symbol: &str, rate: f64) {
+        self.rates.write().unwrap().insert(symbol.to_string(), rate);
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<f64> {
+        self.rates.read().unwrap().get(symbol).copied()
+    }
+
+    // Every symbol's rate, by symbol
+    pub fn all(&self) -> Vec<(String, f64)> {
+        let mut rates: Vec<(String, f64)> = self
+            .rates
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(symbol, rate)| (symbol.clone(), *rate))
+            .collect();
+        rates.sort_by(|a, b| a.0.cmp(&b.0));
+        rates
+    }
+}
+
+// One position's funding at a funding time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FundingPayment {
+    pub venue: VenueId,
+    pub symbol: String,
+    pub side: PositionSide,
+    pub rate: f64,
+    pub mark: f64,
+    // Signed, at the mark
+    pub notional: f64,
+    // Received; negative when paid
+    pub amount: f64,
+    // Seconds
+    pub timestamp: u64,
+}
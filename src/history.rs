@@ -0,0 +1,677 @@
+use crate::clock::SystemClock;
+use crate::rest_client::{RateLimitedClient, RequestPriority, RestLimitConfig};
+use crate::retry::{self, RetryConfig};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MS_PER_DAY: u64 = 86_400_000;
+const MS_PER_HOUR: u64 = 3_600_000;
+
+// One candle; times in milliseconds since the Unix epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kline {
+    pub open_time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+// One aggregated trade (fills at one price from one taker order)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggTrade {
+    pub id: u64,
+    pub time: u64,
+    pub price: f64,
+    pub quantity: f64,
+}
+
+// What to download
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataKind {
+    // Candles of the given interval ("1m", "15m", "1h", "1d", ...)
+    Klines { interval: String },
+    AggTrades,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchError {
+    // The venue asked us to back off; retried after `retry_after`
+    RateLimited { retry_after: Duration },
+    Http { status: u16, body: String },
+    Network(String),
+    Parse(String),
+}
+
+impl FetchError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::RateLimited { .. } | FetchError::Network(_) => true,
+            FetchError::Http { status, .. } => *status >= 500,
+            FetchError::Parse(_) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::RateLimited { retry_after } => {
+                write!(f, "Rate limited by the venue for {:?}", retry_after)
+            }
+            FetchError::Http { status, body } => write!(f, "HTTP {}: {}", status, body),
+            FetchError::Network(e) => write!(f, "Network error: {}", e),
+            FetchError::Parse(e) => write!(f, "Unparseable response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// A venue's public market history. Each call returns one page, oldest
+/// first; the downloader does the paging.
+pub trait HistoricalData {
+    // Candles opening in [start, end), at most `limit`
+    fn klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start: u64,
+        end: u64,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<Kline>, FetchError>> + Send;
+
+    // Trades from id `from_id` on, or (with no id) those in [start, end),
+    // which may span at most an hour. At most `limit`.
+    fn agg_trades(
+        &self,
+        symbol: &str,
+        from_id: Option<u64>,
+        start: u64,
+        end: u64,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<AggTrade>, FetchError>> + Send;
+}
+
+// Binance spot REST history (/api/v3/klines and /api/v3/aggTrades)
+pub struct BinanceHistory {
+    base_url: String,
+    // Paces requests by the venue's weights, as market data
+    client: RateLimitedClient,
+    retry: RetryConfig,
+}
+
+impl BinanceHistory {
+    pub const DEFAULT_BASE_URL: &'static str = "https://api.binance.com";
+
+    pub fn new(base_url: &str, limits: RestLimitConfig) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: RateLimitedClient::new(reqwest::Client::new(), limits),
+            retry: RetryConfig {
+                max_attempts: 5,
+                initial_backoff_ms: 500,
+                max_backoff_ms: 10_000,
+            },
+        }
+    }
+
+    // "SOL/USDT" -> "SOLUSDT"
+    fn venue_symbol(symbol: &str) -> String {
+        symbol.replace('/', "")
+    }
+
+    async fn get(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<serde_json::Value, FetchError> {
+        retry::retry(
+            &self.retry,
+            &SystemClock,
+            FetchError::is_retryable,
+            || async {
+                let response = self
+                    .client
+                    .send(RequestPriority::MarketData, path, |client| {
+                        client
+                            .get(format!("{}{}", self.base_url, path))
+                            .query(query)
+                    })
+                    .await
+                    .map_err(|e| FetchError::Network(e.to_string()))?;
+
+                let status = response.status().as_u16();
+                // 429 is a warning, 418 an IP ban for ignoring it; both say how
+                // long, and the client holds further requests back until then
+                if status == 429 || status == 418 {
+                    let retry_after = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse().ok())
+                        .map_or(Duration::from_secs(1), Duration::from_secs);
+                    return Err(FetchError::RateLimited { retry_after });
+                }
+                if status != 200 {
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(FetchError::Http { status, body });
+                }
+                response
+                    .json()
+                    .await
+                    .map_err(|e| FetchError::Parse(e.to_string()))
+            },
+        )
+        .await
+    }
+}
+
+impl HistoricalData for BinanceHistory {
+    async fn klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start: u64,
+        end: u64,
+        limit: usize,
+    ) -> Result<Vec<Kline>, FetchError> {
+        let rows = self
+            .get(
+                "/api/v3/klines",
+                &[
+                    ("symbol", Self::venue_symbol(symbol)),
+                    ("interval", interval.to_string()),
+                    ("startTime", start.to_string()),
+                    // Binance's endTime is inclusive
+                    ("endTime", end.saturating_sub(1).to_string()),
+                    ("limit", limit.to_string()),
+                ],
+            )
+            .await?;
+        parse_klines(&rows)
+    }
+
+    async fn agg_trades(
+        &self,
+        symbol: &str,
+        from_id: Option<u64>,
+        start: u64,
+        end: u64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>, FetchError> {
+        let mut query = vec![
+            ("symbol", Self::venue_symbol(symbol)),
+            ("limit", limit.to_string()),
+        ];
+        match from_id {
+            Some(id) => query.push(("fromId", id.to_string())),
+            None => {
+                query.push(("startTime", start.to_string()));
+                query.push(("endTime", end.saturating_sub(1).to_string()));
+            }
+        }
+        let rows = self.get("/api/v3/aggTrades", &query).await?;
+        parse_agg_trades(&rows)
+    }
+}
+
+// Binance klines: [openTime, "open", "high", "low", "close", "volume", ...]
+pub fn parse_klines(rows: &serde_json::Value) -> Result<Vec<Kline>, FetchError> {
+    let rows = rows
+        .as_array()
+        .ok_or_else(|| FetchError::Parse("klines response is not an array".to_string()))?;
+    rows.iter()
+        .map(|row| {
+            Ok(Kline {
+                open_time: row[0]
+                    .as_u64()
+                    .ok_or_else(|| FetchError::Parse(format!("bad kline open time: {}", row)))?,
+                open: number(&row[1])?,
+                high: number(&row[2])?,
+                low: number(&row[3])?,
+                close: number(&row[4])?,
+                volume: number(&row[5])?,
+            })
+        })
+        .collect()
+}
+
+// Binance aggTrades: {"a": id, "p": "price", "q": "qty", "T": time, ...}
+pub fn parse_agg_trades(rows: &serde_json::Value) -> Result<Vec<AggTrade>, FetchError> {
+    let rows = rows
+        .as_array()
+        .ok_or_else(|| FetchError::Parse("aggTrades response is not an array".to_string()))?;
+    rows.iter()
+        .map(|row| {
+            let field = |key: &str| {
+                row[key]
+                    .as_u64()
+                    .ok_or_else(|| FetchError::Parse(format!("bad aggTrade {}: {}", key, row)))
+            };
+            Ok(AggTrade {
+                id: field("a")?,
+                time: field("T")?,
+                price: number(&row["p"])?,
+                quantity: number(&row["q"])?,
+            })
+        })
+        .collect()
+}
+
+// Prices and quantities come as strings
+fn number(value: &serde_json::Value) -> Result<f64, FetchError> {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .or_else(|| value.as_f64())
+        .ok_or_else(|| FetchError::Parse(format!("bad number: {}", value)))
+}
+
+// Milliseconds in a kline interval such as "1m", "4h" or "1d"
+pub fn interval_ms(interval: &str) -> Option<u64> {
+    let (count, unit) = interval.split_at(interval.len().checked_sub(1)?);
+    let count: u64 = count.parse().ok()?;
+    let unit_ms = match unit {
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => MS_PER_HOUR,
+        "d" => MS_PER_DAY,
+        "w" => 7 * MS_PER_DAY,
+        _ => return None,
+    };
+    (count > 0).then_some(count * unit_ms)
+}
+
+/// Parses "YYYY-MM-DD" (UTC midnight) or a Unix timestamp in seconds into
+/// milliseconds since the epoch
+pub fn parse_date_ms(value: &str) -> Result<u64, String> {
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(seconds * 1_000);
+    }
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(format!(
+            "Expected YYYY-MM-DD or Unix seconds, got {}",
+            value
+        ));
+    };
+    let (Ok(year), Ok(month), Ok(day)) = (
+        year.parse::<i64>(),
+        month.parse::<u32>(),
+        day.parse::<u32>(),
+    ) else {
+        return Err(format!("Invalid date {}", value));
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(format!("Invalid date {}", value));
+    }
+    let days = days_from_civil(year, month, day);
+    u64::try_from(days)
+        .map(|days| days * MS_PER_DAY)
+        .map_err(|_| format!("Date {} is before 1970", value))
+}
+
+/// Parses an RFC 3339 UTC timestamp ("2023-02-09T20:30:37.167359596Z")
+/// into milliseconds since the epoch; digits past milliseconds are dropped
+pub fn parse_timestamp_ms(value: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid timestamp {}", value);
+    let (date, time) = value
+        .strip_suffix('Z')
+        .and_then(|value| value.split_once('T'))
+        .ok_or_else(invalid)?;
+    let date_ms = parse_date_ms(date).map_err(|_| invalid())?;
+    let (clock, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let parts = clock
+        .split(':')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    let [hours, minutes, seconds] = parts[..] else {
+        return Err(invalid());
+    };
+    if hours > 23 || minutes > 59 || seconds > 60 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let millis = format!("{:0<3}", &fraction[..fraction.len().min(3)])
+        .parse::<u64>()
+        .map_err(|_| invalid())?;
+    Ok(date_ms + ((hours * 60 + minutes) * 60 + seconds) * 1_000 + millis)
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar (Howard
+// Hinnant's days_from_civil)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The (year, month, day) of a count of days since 1970-01-01 (Howard
+// Hinnant's civil_from_days)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// What a download did
+#[derive(Debug, Clone, Default)]
+pub struct FetchSummary {
+    pub chunks_downloaded: usize,
+    // Chunks already on disk from an earlier, interrupted run
+    pub chunks_skipped: usize,
+    pub rows: usize,
+}
+
+/// Downloads a symbol's history for [start, end) into a CSV the backtester
+/// loads. The range is fetched in UTC-day chunks, each written to
+/// `<output>.parts/` once complete, so an interrupted download picks up
+/// where it stopped; the chunks are then stitched into `output`.
+pub struct Downloader<S> {
+    source: S,
+    page_limit: usize,
+}
+
+impl<S: HistoricalData> Downloader<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            page_limit: 1_000,
+        }
+    }
+
+    pub async fn download(
+        &self,
+        symbol: &str,
+        kind: &DataKind,
+        start: u64,
+        end: u64,
+        output: &Path,
+    ) -> Result<FetchSummary, String> {
+        if start >= end {
+            return Err("Download range is empty".to_string());
+        }
+        if let DataKind::Klines { interval } = kind
+            && interval_ms(interval).is_none()
+        {
+            return Err(format!("Unknown kline interval {}", interval));
+        }
+
+        let mut parts_dir = output.as_os_str().to_owned();
+        parts_dir.push(".parts");
+        let parts_dir = PathBuf::from(parts_dir);
+        std::fs::create_dir_all(&parts_dir)
+            .map_err(|e| format!("Failed to create {}: {}", parts_dir.display(), e))?;
+
+        let mut summary = FetchSummary::default();
+        let mut chunks = Vec::new();
+        let mut chunk_start = start;
+        while chunk_start < end {
+            let chunk_end = ((chunk_start / MS_PER_DAY + 1) * MS_PER_DAY).min(end);
+            let path = parts_dir.join(format!("{}-{}.csv", chunk_start, chunk_end));
+            if path.exists() {
+                summary.chunks_skipped += 1;
+            } else {
+                let rows = match kind {
+                    DataKind::Klines { interval } => {
+                        self.kline_rows(symbol, interval, chunk_start, chunk_end)
+                            .await
+                    }
+                    DataKind::AggTrades => self.trade_rows(symbol, chunk_start, chunk_end).await,
+                }
+                .map_err(|e| format!("Download of {} failed: {}", path.display(), e))?;
+                // Written under a temporary name so a crash never leaves a
+                // partial chunk that looks complete
+                let tmp = path.with_extension("csv.tmp");
+                std::fs::write(&tmp, rows.concat())
+                    .and_then(|_| std::fs::rename(&tmp, &path))
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                summary.chunks_downloaded += 1;
+                println!("Downloaded {} ({} rows)", path.display(), rows.len());
+            }
+            chunks.push(path);
+            chunk_start = chunk_end;
+        }
+
+        let mut csv = String::from(match kind {
+            DataKind::Klines { .. } => "timestamp,symbol,open,high,low,close,volume\n",
+            DataKind::AggTrades => "timestamp,symbol,price,volume\n",
+        });
+        for path in &chunks {
+            let chunk = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            summary.rows += chunk.lines().count();
+            csv.push_str(&chunk);
+        }
+        std::fs::write(output, csv)
+            .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+        Ok(summary)
+    }
+
+    // Pages through a chunk's candles; one CSV line each. Timestamps are
+    // written in seconds, as the backtester reads them.
+    async fn kline_rows(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<String>, FetchError> {
+        let step = interval_ms(interval).unwrap_or(MS_PER_DAY);
+        let mut rows = Vec::new();
+        let mut cursor = start;
+        while cursor < end {
+            let page = self
+                .source
+                .klines(symbol, interval, cursor, end, self.page_limit)
+                .await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            cursor = last.open_time + step;
+            rows.extend(
+                page.iter()
+                    .filter(|k| k.open_time >= start && k.open_time < end)
+                    .map(|k| {
+                        format!(
+                            "{},{},{},{},{},{},{}\n",
+                            k.open_time / 1_000,
+                            symbol,
+                            k.open,
+                            k.high,
+                            k.low,
+                            k.close,
+                            k.volume
+                        )
+                    }),
+            );
+            if page.len() < self.page_limit {
+                break;
+            }
+        }
+        Ok(rows)
+    }
+
+    // Finds the chunk's first trade an hour window at a time (the most one
+    // time-bounded request may span), then pages forward by trade id
+    async fn trade_rows(
+        &self,
+        symbol: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<String>, FetchError> {
+        let mut rows = Vec::new();
+        let mut window = start;
+        let mut page = Vec::new();
+        while page.is_empty() && window < end {
+            let window_end = (window + MS_PER_HOUR).min(end);
+            page = self
+                .source
+                .agg_trades(symbol, None, window, window_end, self.page_limit)
+                .await?;
+            window = window_end;
+        }
+
+        while let Some(last_id) = page.last().map(|t| t.id) {
+            let mut done = false;
+            for trade in &page {
+                if trade.time >= end {
+                    done = true;
+                    break;
+                }
+                if trade.time >= start {
+                    rows.push(format!(
+                        "{},{},{},{}\n",
+                        trade.time / 1_000,
+                        symbol,
+                        trade.price,
+                        trade.quantity
+                    ));
+                }
+            }
+            if done || page.len() < self.page_limit {
+                break;
+            }
+            page = self
+                .source
+                .agg_trades(symbol, Some(last_id + 1), start, end, self.page_limit)
+                .await?;
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{scratch_dir, serve};
+    use axum::Router;
+    use axum::extract::{Query, State};
+    use axum::routing::get;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const MINUTE: u64 = 60_000;
+    // 2024-01-01T23:57:00Z
+    const START: u64 = 1_704_153_420_000;
+
+    // Binance's klines endpoint over a minute candle at every minute, its
+    // close the minute's index since START; counts the requests
+    async fn klines(
+        State(requests): State<Arc<AtomicUsize>>,
+        Query(query): Query<HashMap<String, String>>,
+    ) -> axum::Json<serde_json::Value> {
+        requests.fetch_add(1, Ordering::SeqCst);
+        let number = |key: &str| query[key].parse::<u64>().unwrap();
+        let (start, end, limit) = (number("startTime"), number("endTime"), number("limit"));
+        assert_eq!(query["symbol"], "SOLUSDT");
+        let first = start.div_ceil(MINUTE) * MINUTE;
+        let rows: Vec<serde_json::Value> = (first..=end)
+            .step_by(MINUTE as usize)
+            .take(limit as usize)
+            .map(|open_time| {
+                let close = ((open_time - START) / MINUTE).to_string();
+                serde_json::json!([
+                    open_time,
+                    "1",
+                    "2",
+                    "0.5",
+                    close,
+                    "10",
+                    open_time + MINUTE - 1
+                ])
+            })
+            .collect();
+        axum::Json(rows.into())
+    }
+
+    #[tokio::test]
+    async fn pages_are_stitched_across_day_chunks_and_resumed() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let base_url = serve(
+            Router::new()
+                .route("/api/v3/klines", get(klines))
+                .with_state(requests.clone()),
+        )
+        .await;
+        let mut downloader =
+            Downloader::new(BinanceHistory::new(&base_url, RestLimitConfig::default()));
+        downloader.page_limit = 2;
+        let output = scratch_dir("klines").join("SOL-USDT.csv");
+        let kind = DataKind::Klines {
+            interval: "1m".to_string(),
+        };
+
+        // 23:57 to 00:03: three candles either side of midnight, two a page
+        let end = START + 6 * MINUTE;
+        let summary = downloader
+            .download("SOL/USDT", &kind, START, end, &output)
+            .await
+            .unwrap();
+        assert_eq!((summary.chunks_downloaded, summary.rows), (2, 6));
+        // Each day: a full page, then a short one that ends it
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+        let csv = std::fs::read_to_string(&output).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("timestamp,symbol,open,high,low,close,volume")
+        );
+        assert_eq!(
+            lines.next(),
+            Some(format!("{},SOL/USDT,1,2,0.5,0,10", START / 1_000).as_str())
+        );
+        let closes: Vec<&str> = csv
+            .lines()
+            .skip(1)
+            .map(|l| l.split(',').nth(5).unwrap())
+            .collect();
+        assert_eq!(closes, ["0", "1", "2", "3", "4", "5"]);
+        // In the format the backtester loads
+        let records = crate::backtest::parse_csv(&csv, "SOL/USDT").unwrap();
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[5].price.price, 5.0);
+
+        // Run again: every chunk is on disk already, so nothing is fetched
+        let summary = downloader
+            .download("SOL/USDT", &kind, START, end, &output)
+            .await
+            .unwrap();
+        assert_eq!((summary.chunks_downloaded, summary.chunks_skipped), (0, 2));
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), csv);
+
+        // As if interrupted before the second day landed
+        let midnight = START + 3 * MINUTE;
+        let parts = output.with_extension("csv.parts");
+        std::fs::remove_file(parts.join(format!("{midnight}-{end}.csv"))).unwrap();
+        let summary = downloader
+            .download("SOL/USDT", &kind, START, end, &output)
+            .await
+            .unwrap();
+        assert_eq!((summary.chunks_downloaded, summary.chunks_skipped), (1, 1));
+        assert_eq!(requests.load(Ordering::SeqCst), 6);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), csv);
+    }
+}
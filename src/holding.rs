@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Time stops: positions held too long are closed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HoldingConfig {
+    // Longest any position is held, in seconds; None = no limit
+    pub max_holding_secs: Option<u64>,
+    // Per-symbol limits. Where a symbol, the opening strategy and the
+    // default all set one, the shortest applies.
+    pub symbols: HashMap<String, u64>,
+    // Whether adding to a position restarts its clock; otherwise it runs
+    // from the fill that opened it
+    pub reset_on_extend: bool,
+}
+
+impl HoldingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_holding_secs == Some(0) {
+            return Err("Holding max_holding_secs must be positive".to_string());
+        }
+        if let Some((symbol, _)) = self.symbols.iter().find(|(_, secs)| **secs == 0) {
+            return Err(format!("Holding limit for {} must be positive", symbol));
+        }
+        Ok(())
+    }
+
+    // The symbol's limit before the opening strategy's is considered
+    pub fn limit_secs(&self, symbol: &str) -> Option<u64> {
+        let symbol_limit = self.symbols.get(symbol).copied();
+        match (self.max_holding_secs, symbol_limit) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
@@ -0,0 +1,935 @@
+use crate::OrderBook;
+use crate::candles::Timeframe;
+use crate::symbols::{Symbol, SymbolId, SymbolMap};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+// The shared volatility estimates: realized volatility of tick returns and
+// ATR on candles, kept for every symbol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolatilityConfig {
+    // Log returns in the realized volatility estimate
+    pub window: usize,
+    // Return periods per year to annualize by (31536000 for one return a
+    // second); None leaves it per return
+    pub annualization_periods: Option<f64>,
+    // Bars the ATR is taken over, and how many
+    pub atr_timeframe: Timeframe,
+    pub atr_period: usize,
+}
+
+impl Default for VolatilityConfig {
+    fn default() -> Self {
+        Self {
+            window: 100,
+            annualization_periods: None,
+            atr_timeframe: Timeframe::M1,
+            atr_period: 14,
+        }
+    }
+}
+
+impl VolatilityConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window < 2 {
+            return Err("Volatility window must be at least 2 returns".to_string());
+        }
+        if let Some(periods) = self.annualization_periods
+            && !(periods.is_finite() && periods > 0.0)
+        {
+            return Err("Volatility annualization_periods must be positive".to_string());
+        }
+        if self.atr_period == 0 {
+            return Err("Volatility atr_period must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+// A symbol's volatility, as the risk manager and sizing read it; None
+// while an estimate is still warming up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct VolatilityEstimate {
+    // Standard deviation of log returns, annualized if configured
+    pub realized: Option<f64>,
+    // In price units
+    pub atr: Option<f64>,
+}
+
+// Fixed-length rolling window over a stream of values. Sum, mean and variance
+// are maintained incrementally (windowed Welford) and max/min through
+// monotonic deques, so every read is O(1) and every push amortized O(1).
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    capacity: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+    mean: f64,
+    m2: f64,
+    seq: u64,
+    max_deque: VecDeque<(u64, f64)>, // (sequence, value), values decreasing
+    min_deque: VecDeque<(u64, f64)>, // (sequence, value), values increasing
+}
+
+impl RollingWindow {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "rolling window capacity must be positive");
+        Self {
+            capacity,
+            values: VecDeque::with_capacity(capacity),
+            sum: 0.0,
+            mean: 0.0,
+            m2: 0.0,
+            seq: 0,
+            max_deque: VecDeque::new(),
+            min_deque: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        if self.values.len() == self.capacity {
+            let old = self.values.pop_front().unwrap();
+            self.values.push_back(value);
+            self.sum += value - old;
+
+            // Replace old with value in one step
+            let n = self.capacity as f64;
+            let old_mean = self.mean;
+            self.mean += (value - old) / n;
+            self.m2 += (value - old) * (value - self.mean + old - old_mean);
+            // Guard against tiny negative values from rounding
+            if self.m2 < 0.0 {
+                self.m2 = 0.0;
+            }
+        } else {
+            self.values.push_back(value);
+            self.sum += value;
+
+            let n = self.values.len() as f64;
+            let delta = value - self.mean;
+            self.mean += delta / n;
+            self.m2 += delta * (value - self.mean);
+        }
+
+        let seq = self.seq;
+        self.seq += 1;
+        let expired = seq.saturating_sub(self.capacity as u64 - 1);
+
+        while self.max_deque.back().is_some_and(|&(_, v)| v <= value) {
+            self.max_deque.pop_back();
+        }
+        self.max_deque.push_back((seq, value));
+        while self.max_deque.front().is_some_and(|&(s, _)| s < expired) {
+            self.max_deque.pop_front();
+        }
+
+        while self.min_deque.back().is_some_and(|&(_, v)| v >= value) {
+            self.min_deque.pop_back();
+        }
+        self.min_deque.push_back((seq, value));
+        while self.min_deque.front().is_some_and(|&(s, _)| s < expired) {
+            self.min_deque.pop_front();
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.values.len() == self.capacity
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.values.is_empty() {
+            None
+        } else {
+            Some(self.mean)
+        }
+    }
+
+    // Sample variance (n - 1 denominator)
+    pub fn variance(&self) -> Option<f64> {
+        let n = self.values.len();
+        if n < 2 {
+            None
+        } else {
+            Some(self.m2 / (n - 1) as f64)
+        }
+    }
+
+    pub fn std_dev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max_deque.front().map(|&(_, v)| v)
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.min_deque.front().map(|&(_, v)| v)
+    }
+
+    pub fn newest(&self) -> Option<f64> {
+        self.values.back().copied()
+    }
+
+    pub fn oldest(&self) -> Option<f64> {
+        self.values.front().copied()
+    }
+
+    // The window's values, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = f64> + '_ {
+        self.values.iter().copied()
+    }
+}
+
+// Exponential moving average with the usual 2 / (period + 1) smoothing
+#[derive(Debug, Clone)]
+pub struct Ema {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Self::with_alpha(2.0 / (period as f64 + 1.0))
+    }
+
+    pub fn with_alpha(alpha: f64) -> Self {
+        Self { alpha, value: None }
+    }
+
+    pub fn update(&mut self, value: f64) -> f64 {
+        let next = match self.value {
+            Some(prev) => prev + self.alpha * (value - prev),
+            None => value,
+        };
+        self.value = Some(next);
+        next
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// MACD: the fast EMA of closes less the slow one, the signal line (an EMA
+/// of that difference) and the histogram between the two. Every EMA starts
+/// at its first value, and nothing is given until the slow EMA has had
+/// `slow` closes and the signal line `signal` MACD values.
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+    warmup: usize,
+    closes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdValue {
+    pub macd: f64,
+    pub signal: f64,
+    // MACD less signal; positive while the MACD line is above
+    pub histogram: f64,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        assert!(
+            fast > 0 && fast < slow && signal > 0,
+            "MACD needs 0 < fast < slow and a positive signal period"
+        );
+        Self {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+            warmup: slow + signal - 1,
+            closes: 0,
+        }
+    }
+
+    pub fn update(&mut self, close: f64) -> Option<MacdValue> {
+        // A garbage close is skipped rather than poisoning every EMA
+        if close.is_finite() {
+            let macd = self.fast.update(close) - self.slow.update(close);
+            self.signal.update(macd);
+            self.closes += 1;
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<MacdValue> {
+        if self.closes < self.warmup {
+            return None;
+        }
+        let macd = self.fast.value()? - self.slow.value()?;
+        let signal = self.signal.value()?;
+        Some(MacdValue {
+            macd,
+            signal,
+            histogram: macd - signal,
+        })
+    }
+}
+
+/// Realized volatility: the sample standard deviation of the last `window`
+/// log returns, scaled by the square root of the periods per year when
+/// annualized. None until the window is full.
+#[derive(Debug, Clone)]
+pub struct RealizedVolatility {
+    returns: RollingWindow,
+    last_price: Option<f64>,
+    scale: f64,
+}
+
+impl RealizedVolatility {
+    pub fn new(window: usize, annualization_periods: Option<f64>) -> Self {
+        Self {
+            returns: RollingWindow::new(window),
+            last_price: None,
+            scale: annualization_periods.map_or(1.0, f64::sqrt),
+        }
+    }
+
+    pub fn update(&mut self, price: f64) {
+        // A price with no log return is skipped rather than poisoning the
+        // window
+        if !(price.is_finite() && price > 0.0) {
+            return;
+        }
+        if let Some(last) = self.last_price {
+            self.returns.push((price / last).ln());
+        }
+        self.last_price = Some(price);
+    }
+
+    // Moves the reference price without recording a return, for a level
+    // shift that shouldn't count as volatility
+    pub fn rebase(&mut self, price: f64) {
+        if price.is_finite() && price > 0.0 {
+            self.last_price = Some(price);
+        }
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        if !self.returns.is_full() {
+            return None;
+        }
+        self.returns.std_dev().map(|std_dev| std_dev * self.scale)
+    }
+}
+
+/// Average true range with Wilder's smoothing: the mean of the first
+/// `period` true ranges, then atr += (range - atr) / period per bar. A
+/// bar's true range reaches back to the previous close, so gaps count; the
+/// first bar has only its own high - low.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    period: usize,
+    previous_close: Option<f64>,
+    // True ranges summed while warming up
+    warmup_sum: f64,
+    bars: usize,
+    value: Option<f64>,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "ATR period must be positive");
+        Self {
+            period,
+            previous_close: None,
+            warmup_sum: 0.0,
+            bars: 0,
+            value: None,
+        }
+    }
+
+    pub fn update(&mut self, high: f64, low: f64, close: f64) -> Option<f64> {
+        let range = match self.previous_close {
+            Some(previous) => (high - low)
+                .max((high - previous).abs())
+                .max((low - previous).abs()),
+            None => high - low,
+        };
+        self.previous_close = Some(close);
+        let n = self.period as f64;
+        self.value = match self.value {
+            Some(atr) => Some(atr + (range - atr) / n),
+            None => {
+                self.warmup_sum += range;
+                self.bars += 1;
+                (self.bars == self.period).then(|| self.warmup_sum / n)
+            }
+        };
+        self.value
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+// An order-flow imbalance to track: over the top `levels` of the book,
+// summed over the last `window` book updates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OfiSpec {
+    pub levels: usize,
+    pub window: usize,
+}
+
+// One side's top levels, as (price, quantity)
+type Levels = Vec<(f64, f64)>;
+
+/// Order-flow imbalance between successive books (Cont, Kukanov and
+/// Stoikov), summed over the top `levels`. At each level, a bid that moved
+/// up adds its whole new quantity, one that moved down takes away its whole
+/// old quantity, and one at the same price adds the change in quantity;
+/// asks count the same way with the sign reversed. A level counts only
+/// while both books have it. The rolling sum is over the last `window`
+/// updates, and its z-score is taken against its own last `window` values.
+#[derive(Debug, Clone)]
+pub struct Ofi {
+    levels: usize,
+    // Top levels of the last book, as (bids, asks)
+    previous: Option<(Levels, Levels)>,
+    flows: RollingWindow,
+    sums: RollingWindow,
+}
+
+impl Ofi {
+    pub fn new(spec: OfiSpec) -> Self {
+        assert!(
+            spec.levels > 0 && spec.window >= 2,
+            "OFI needs at least one level and a window of 2"
+        );
+        Self {
+            levels: spec.levels,
+            previous: None,
+            flows: RollingWindow::new(spec.window),
+            sums: RollingWindow::new(spec.window),
+        }
+    }
+
+    // Takes in the next book, returning its flow since the one before
+    pub fn update(&mut self, book: &OrderBook) -> Option<f64> {
+        let bids: Levels = book.bids.iter().take(self.levels).copied().collect();
+        let asks: Levels = book.asks.iter().take(self.levels).copied().collect();
+        // A one-sided book has no inside to compare against; the next
+        // full book starts afresh
+        if bids.is_empty() || asks.is_empty() {
+            self.previous = None;
+            return None;
+        }
+        let flow = self
+            .previous
+            .as_ref()
+            .map(|(previous_bids, previous_asks)| {
+                let bid: f64 = previous_bids
+                    .iter()
+                    .zip(&bids)
+                    .map(|(&(p0, q0), &(p1, q1))| match p1.total_cmp(&p0) {
+                        std::cmp::Ordering::Greater => q1,
+                        std::cmp::Ordering::Less => -q0,
+                        std::cmp::Ordering::Equal => q1 - q0,
+                    })
+                    .sum();
+                let ask: f64 = previous_asks
+                    .iter()
+                    .zip(&asks)
+                    .map(|(&(p0, q0), &(p1, q1))| match p1.total_cmp(&p0) {
+                        std::cmp::Ordering::Less => -q1,
+                        std::cmp::Ordering::Greater => q0,
+                        std::cmp::Ordering::Equal => q0 - q1,
+                    })
+                    .sum();
+                bid + ask
+            });
+        self.previous = Some((bids, asks));
+        let flow = flow?;
+        self.flows.push(flow);
+        self.sums.push(self.flows.sum());
+        Some(flow)
+    }
+
+    // The newest update's flow
+    pub fn last(&self) -> Option<f64> {
+        self.flows.newest()
+    }
+
+    // Flow over the last `window` updates; None until there are that many
+    pub fn sum(&self) -> Option<f64> {
+        self.flows.is_full().then(|| self.flows.sum())
+    }
+
+    // The rolling sum's z-score against its last `window` values; None
+    // until they're all in, or while the sum hasn't moved
+    pub fn zscore(&self) -> Option<f64> {
+        if !(self.flows.is_full() && self.sums.is_full()) {
+            return None;
+        }
+        let std_dev = self.sums.std_dev()?;
+        if std_dev <= 1e-12 {
+            return None;
+        }
+        Some((self.sums.newest()? - self.sums.mean()?) / std_dev)
+    }
+}
+
+// Incrementally-maintained indicators for a single symbol
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndicators {
+    symbol: Arc<str>,
+    price_windows: BTreeMap<usize, RollingWindow>,
+    volume_windows: BTreeMap<usize, RollingWindow>,
+    emas: BTreeMap<usize, Ema>,
+    volatility: Option<RealizedVolatility>,
+    ofis: BTreeMap<OfiSpec, Ofi>,
+    ticks: u64,
+}
+
+impl SymbolIndicators {
+    fn update(&mut self, price: f64, volume: f64) {
+        if let Some(volatility) = &mut self.volatility {
+            volatility.update(price);
+        }
+        for window in self.price_windows.values_mut() {
+            window.push(price);
+        }
+        for window in self.volume_windows.values_mut() {
+            window.push(volume);
+        }
+        for ema in self.emas.values_mut() {
+            ema.update(price);
+        }
+        self.ticks += 1;
+    }
+
+    fn update_book(&mut self, book: &OrderBook) {
+        for ofi in self.ofis.values_mut() {
+            ofi.update(book);
+        }
+    }
+
+    pub fn price(&self, window: usize) -> Option<&RollingWindow> {
+        self.price_windows.get(&window)
+    }
+
+    pub fn volume(&self, window: usize) -> Option<&RollingWindow> {
+        self.volume_windows.get(&window)
+    }
+
+    pub fn ema(&self, period: usize) -> Option<f64> {
+        self.emas.get(&period).and_then(Ema::value)
+    }
+
+    // Order-flow imbalance as registered with the hub
+    pub fn ofi(&self, spec: OfiSpec) -> Option<&Ofi> {
+        self.ofis.get(&spec)
+    }
+
+    // The configured realized volatility; None until it has warmed up
+    pub fn realized_volatility(&self) -> Option<f64> {
+        self.volatility.as_ref().and_then(RealizedVolatility::value)
+    }
+
+    // Handed out with every signal on the symbol instead of a copy of its name
+    pub fn symbol(&self) -> &Arc<str> {
+        &self.symbol
+    }
+
+    // Number of ticks seen since the symbol was first updated
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+}
+
+// Owns the rolling indicators for every symbol. Updated once per new price
+// by the market data tasks, read by strategies without touching the history.
+#[derive(Debug, Clone, Default)]
+pub struct IndicatorHub {
+    windows: Vec<usize>,
+    ema_periods: Vec<usize>,
+    ofi_specs: Vec<OfiSpec>,
+    // (window, annualization periods) of the realized volatility estimate
+    volatility: Option<(usize, Option<f64>)>,
+    symbols: SymbolMap<SymbolIndicators>,
+}
+
+impl IndicatorHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Track price and volume windows of the given length for every symbol.
+    // Windows registered after data has arrived start empty and warm up.
+    pub fn register_window(&mut self, window: usize) {
+        if window == 0 || self.windows.contains(&window) {
+            return;
+        }
+        self.windows.push(window);
+        for indicators in self.symbols.values_mut() {
+            indicators
+                .price_windows
+                .insert(window, RollingWindow::new(window));
+            indicators
+                .volume_windows
+                .insert(window, RollingWindow::new(window));
+        }
+    }
+
+    pub fn register_ema(&mut self, period: usize) {
+        if period == 0 || self.ema_periods.contains(&period) {
+            return;
+        }
+        self.ema_periods.push(period);
+        for indicators in self.symbols.values_mut() {
+            indicators.emas.insert(period, Ema::new(period));
+        }
+    }
+
+    // Track order-flow imbalance for every symbol, from its books
+    pub fn register_ofi(&mut self, spec: OfiSpec) {
+        if spec.levels == 0 || spec.window < 2 || self.ofi_specs.contains(&spec) {
+            return;
+        }
+        self.ofi_specs.push(spec);
+        for indicators in self.symbols.values_mut() {
+            indicators.ofis.insert(spec, Ofi::new(spec));
+        }
+    }
+
+    // Track realized volatility for every symbol. Replaces any estimate
+    // already running, which then warms up again.
+    pub fn register_volatility(&mut self, window: usize, annualization_periods: Option<f64>) {
+        self.volatility = Some((window, annualization_periods));
+        for indicators in self.symbols.values_mut() {
+            indicators.volatility = Some(RealizedVolatility::new(window, annualization_periods));
+        }
+    }
+
+    pub fn update(&mut self, symbol: &Symbol, price: f64, volume: f64) {
+        self.symbol_mut(symbol).update(price, volume);
+    }
+
+    // Books only feed order-flow imbalance; none is kept unless registered
+    pub fn update_book(&mut self, symbol: &Symbol, book: &OrderBook) {
+        if self.ofi_specs.is_empty() {
+            return;
+        }
+        self.symbol_mut(symbol).update_book(book);
+    }
+
+    pub fn get(&self, id: SymbolId) -> Option<&SymbolIndicators> {
+        self.symbols.get(id)
+    }
+
+    pub fn remove(&mut self, id: SymbolId) {
+        self.symbols.remove(id);
+    }
+
+    fn symbol_mut(&mut self, symbol: &Symbol) -> &mut SymbolIndicators {
+        if self.symbols.get(symbol.id()).is_none() {
+            let indicators = self.new_symbol_indicators(symbol);
+            self.symbols.insert(symbol.id(), indicators);
+        }
+        self.symbols.get_mut(symbol.id()).unwrap()
+    }
+
+    fn new_symbol_indicators(&self, symbol: &Symbol) -> SymbolIndicators {
+        SymbolIndicators {
+            symbol: symbol.shared_name().clone(),
+            price_windows: self
+                .windows
+                .iter()
+                .map(|&w| (w, RollingWindow::new(w)))
+                .collect(),
+            volume_windows: self
+                .windows
+                .iter()
+                .map(|&w| (w, RollingWindow::new(w)))
+                .collect(),
+            emas: self.ema_periods.iter().map(|&p| (p, Ema::new(p))).collect(),
+            volatility: self
+                .volatility
+                .map(|(window, periods)| RealizedVolatility::new(window, periods)),
+            ofis: self
+                .ofi_specs
+                .iter()
+                .map(|&spec| (spec, Ofi::new(spec)))
+                .collect(),
+            ticks: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * a.abs().max(1.0)
+    }
+
+    #[test]
+    fn rolling_window_matches_recomputing_the_window() {
+        let prices = fixtures::price_series(500, 7);
+        let mut window = RollingWindow::new(20);
+        for (i, &price) in prices.iter().enumerate() {
+            window.push(price);
+            let slice = &prices[(i + 1).saturating_sub(20)..=i];
+            let n = slice.len() as f64;
+            let mean = slice.iter().sum::<f64>() / n;
+            assert!(close(window.sum(), slice.iter().sum()));
+            assert!(close(window.mean().unwrap(), mean));
+            assert_eq!(window.max(), slice.iter().copied().reduce(f64::max));
+            assert_eq!(window.min(), slice.iter().copied().reduce(f64::min));
+            assert_eq!(window.oldest(), slice.first().copied());
+            if slice.len() > 1 {
+                let variance = slice.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / (n - 1.0);
+                assert!(close(window.variance().unwrap(), variance));
+            }
+        }
+        assert!(window.is_full());
+    }
+
+    #[test]
+    fn ema_starts_at_its_first_value() {
+        let mut ema = Ema::new(3);
+        assert_eq!(ema.value(), None);
+        assert_eq!(ema.update(10.0), 10.0);
+        assert_eq!(ema.update(20.0), 15.0);
+        assert_eq!(ema.update(20.0), 17.5);
+    }
+
+    #[test]
+    fn hub_keeps_windows_per_symbol() {
+        let (sol, btc) = (Symbol::intern("SOL/USDT"), Symbol::intern("BTC/USDT"));
+        let mut hub = IndicatorHub::new();
+        hub.register_window(3);
+        for price in [1.0, 2.0, 3.0, 4.0] {
+            hub.update(&sol, price, 10.0);
+        }
+        hub.update(&btc, 50.0, 1.0);
+        let sol = hub.get(sol.id()).unwrap();
+        assert_eq!(sol.price(3).unwrap().sum(), 9.0);
+        assert_eq!(sol.volume(3).unwrap().sum(), 30.0);
+        assert_eq!(sol.ticks(), 4);
+        assert_eq!(hub.get(btc.id()).unwrap().price(3).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn realized_volatility_matches_hand_worked_returns() {
+        let mut daily = RealizedVolatility::new(3, None);
+        let mut annual = RealizedVolatility::new(3, Some(365.0));
+        for price in [100.0, 102.0, 101.0, 104.0] {
+            daily.update(price);
+            annual.update(price);
+        }
+        // Four prices fill the window with 3 returns
+        assert!(daily.value().is_some());
+        daily.update(103.0);
+        annual.update(103.0);
+        // Sample std dev of ln(101/102), ln(104/101) and ln(103/104)
+        assert!(close(daily.value().unwrap(), 0.022_532_730_613_568));
+        assert!(close(annual.value().unwrap(), 0.430_487_213_921_415));
+
+        let mut warming = RealizedVolatility::new(3, None);
+        for price in [100.0, 102.0, 0.0, 101.0] {
+            warming.update(price);
+            assert_eq!(warming.value(), None);
+        }
+    }
+
+    #[test]
+    fn atr_matches_hand_worked_true_ranges() {
+        let mut atr = Atr::new(3);
+        // True ranges 2, 2, then 3.5 for the gap up from 10.5
+        assert_eq!(atr.update(10.0, 8.0, 9.0), None);
+        assert_eq!(atr.update(11.0, 9.0, 10.5), None);
+        assert_eq!(atr.update(14.0, 13.0, 13.5), Some(2.5));
+        // 1.5 back to the previous close: 2.5 + (1.5 - 2.5) / 3
+        assert!(close(atr.update(13.0, 12.0, 12.0).unwrap(), 13.0 / 6.0));
+        assert!(close(atr.value().unwrap(), 13.0 / 6.0));
+    }
+
+    // MACD(3, 6, 3) worked out independently, every EMA seeded with its
+    // first value
+    #[test]
+    fn macd_matches_a_reference_series_to_three_decimals() {
+        let closes = [
+            10.0, 11.0, 12.0, 11.0, 13.0, 14.0, 13.0, 15.0, 16.0, 15.0, 14.0, 13.0, 12.0, 12.5,
+        ];
+        let reference = [
+            (0.853, 0.738, 0.115),
+            (1.036, 0.887, 0.149),
+            (0.739, 0.813, -0.074),
+            (0.313, 0.563, -0.250),
+            (-0.098, 0.233, -0.331),
+            (-0.445, -0.106, -0.339),
+            (-0.398, -0.252, -0.146),
+        ];
+        let mut macd = Macd::new(3, 6, 3);
+        let values: Vec<Option<MacdValue>> = closes.iter().map(|&c| macd.update(c)).collect();
+        // Nothing until the slow EMA and then the signal line have warmed up
+        assert!(values[..7].iter().all(Option::is_none));
+        for (value, (line, signal, histogram)) in values[7..].iter().zip(reference) {
+            let value = value.unwrap();
+            assert!((value.macd - line).abs() < 5e-4, "{value:?}");
+            assert!((value.signal - signal).abs() < 5e-4, "{value:?}");
+            assert!((value.histogram - histogram).abs() < 5e-4, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn a_flat_series_gives_a_zero_macd_not_nan() {
+        let mut macd = Macd::new(3, 6, 3);
+        for _ in 0..20 {
+            macd.update(100.0);
+        }
+        // A garbage close is skipped
+        let value = macd.update(f64::NAN).unwrap();
+        assert_eq!(
+            value,
+            MacdValue {
+                macd: 0.0,
+                signal: 0.0,
+                histogram: 0.0
+            }
+        );
+    }
+
+    // Each step changes one thing: a bid added to at the same price, an ask
+    // taken from at the same price, the best bid pulled (and the second one
+    // with it), a lower ask stepping in, then a higher bid
+    fn scripted_books() -> Vec<OrderBook> {
+        vec![
+            fixtures::book(
+                "SOL/USDT",
+                &[(100.0, 5.0), (99.0, 4.0)],
+                &[(101.0, 5.0), (102.0, 4.0)],
+            ),
+            fixtures::book(
+                "SOL/USDT",
+                &[(100.0, 7.0), (99.0, 4.0)],
+                &[(101.0, 5.0), (102.0, 4.0)],
+            ),
+            fixtures::book(
+                "SOL/USDT",
+                &[(100.0, 7.0), (99.0, 4.0)],
+                &[(101.0, 4.0), (102.0, 4.0)],
+            ),
+            fixtures::book(
+                "SOL/USDT",
+                &[(99.0, 4.0), (98.0, 6.0)],
+                &[(101.0, 4.0), (102.0, 4.0)],
+            ),
+            fixtures::book(
+                "SOL/USDT",
+                &[(99.0, 4.0), (98.0, 6.0)],
+                &[(100.5, 2.0), (101.0, 4.0)],
+            ),
+            fixtures::book(
+                "SOL/USDT",
+                &[(99.5, 3.0), (99.0, 4.0)],
+                &[(100.5, 2.0), (101.0, 4.0)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn ofi_matches_hand_computed_flows_on_a_scripted_book() {
+        let spec = OfiSpec {
+            levels: 1,
+            window: 2,
+        };
+        let mut ofi = Ofi::new(spec);
+        let flows: Vec<Option<f64>> = scripted_books().iter().map(|b| ofi.update(b)).collect();
+        // +2 bid at 100; 1 off the 101 ask; the whole 7 at 100 gone; a new
+        // 2 offered at 100.5; a new 3 bid at 99.5
+        assert_eq!(
+            flows,
+            [
+                None,
+                Some(2.0),
+                Some(1.0),
+                Some(-7.0),
+                Some(-2.0),
+                Some(3.0)
+            ]
+        );
+        assert_eq!(ofi.last(), Some(3.0));
+        assert_eq!(ofi.sum(), Some(1.0));
+        // Window-2 sums of 3, -6, -9, 1: the last two are -9 then 1
+        let zscore = ofi.zscore().unwrap();
+        assert!(close(zscore, std::f64::consts::FRAC_1_SQRT_2), "{zscore}");
+
+        // Two levels add the second level's own moves: the 99 bid dropping to
+        // 98, the 102 ask to 101, and the 98 bid back up to 99
+        let mut two = Ofi::new(OfiSpec {
+            levels: 2,
+            window: 2,
+        });
+        let flows: Vec<Option<f64>> = scripted_books().iter().map(|b| two.update(b)).collect();
+        assert_eq!(
+            flows,
+            [
+                None,
+                Some(2.0),
+                Some(1.0),
+                Some(-11.0),
+                Some(-6.0),
+                Some(7.0)
+            ]
+        );
+        assert_eq!(two.sum(), Some(1.0));
+
+        // A one-sided book breaks the chain; the next full one starts it again
+        assert_eq!(
+            ofi.update(&fixtures::book("SOL/USDT", &[(99.5, 3.0)], &[])),
+            None
+        );
+        let books = scripted_books();
+        assert_eq!(ofi.update(&books[0]), None);
+        assert_eq!(ofi.update(&books[1]), Some(2.0));
+    }
+
+    #[test]
+    fn the_hub_keeps_ofi_per_symbol_from_its_books() {
+        let spec = OfiSpec {
+            levels: 1,
+            window: 2,
+        };
+        let sol = Symbol::intern("SOL/USDT");
+        let mut hub = IndicatorHub::new();
+        let books = scripted_books();
+        // Nothing registered, nothing kept
+        hub.update_book(&sol, &books[0]);
+        assert!(hub.get(sol.id()).is_none());
+
+        hub.register_ofi(spec);
+        for book in &books[..3] {
+            hub.update_book(&sol, book);
+        }
+        let ofi = hub.get(sol.id()).unwrap().ofi(spec).unwrap();
+        assert_eq!(ofi.last(), Some(1.0));
+        assert_eq!(ofi.sum(), Some(3.0));
+    }
+}
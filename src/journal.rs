@@ -0,0 +1,993 @@
+use crate::equity::{self, EquityPoint};
+use crate::fees::Liquidity;
+use crate::latency::OrderLatency;
+use crate::monitor::ExitReason;
+use crate::report::{BookedFill, PositionRow};
+use crate::session::SessionStats;
+use crate::shadow::StrategyMode;
+use crate::streaks::{Bench, Streak, StreakKind};
+use crate::symbols::SymbolId;
+use crate::venue::VenueId;
+use crate::{Fill, Order, OrderSide, Position, PositionSide};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tokio::sync::mpsc;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// Positions replayed from fills, per symbol and leg
+type ReplayedPositions = HashMap<(SymbolId, PositionSide), Position>;
+
+// Schema changes, applied in order; PRAGMA user_version records how many
+// have run. Append new ones, never edit old ones.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE orders (
+        order_id TEXT PRIMARY KEY,
+        client_order_id TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        side TEXT NOT NULL,
+        order_type TEXT NOT NULL,
+        quantity REAL NOT NULL,
+        price REAL,
+        time_in_force TEXT NOT NULL,
+        strategy TEXT,
+        link_group TEXT,
+        submitted_at INTEGER NOT NULL
+    );
+    CREATE TABLE order_events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        order_id TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        event TEXT NOT NULL,
+        detail TEXT,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE INDEX order_events_order ON order_events (order_id);
+    CREATE TABLE fills (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        order_id TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        side TEXT NOT NULL,
+        quantity REAL NOT NULL,
+        price REAL NOT NULL,
+        fee REAL NOT NULL,
+        liquidity TEXT NOT NULL,
+        strategy TEXT,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE INDEX fills_symbol_time ON fills (symbol, timestamp);
+    CREATE INDEX fills_time ON fills (timestamp);
+    CREATE TABLE risk_rejections (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        symbol TEXT NOT NULL,
+        side TEXT NOT NULL,
+        quantity REAL NOT NULL,
+        strategy TEXT,
+        reason TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+",
+    "
+    ALTER TABLE orders ADD COLUMN feed_us INTEGER;
+    ALTER TABLE orders ADD COLUMN strategy_us INTEGER;
+    ALTER TABLE orders ADD COLUMN risk_us INTEGER;
+    ALTER TABLE orders ADD COLUMN submit_us INTEGER;
+    ALTER TABLE orders ADD COLUMN total_us INTEGER;
+",
+    "
+    ALTER TABLE orders ADD COLUMN venue TEXT NOT NULL DEFAULT 'paper';
+    ALTER TABLE fills ADD COLUMN venue TEXT NOT NULL DEFAULT 'paper';
+",
+    "
+    ALTER TABLE orders ADD COLUMN exit_reason TEXT;
+",
+    "
+    CREATE TABLE reconciliations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        venue TEXT NOT NULL,
+        discrepancy TEXT NOT NULL,
+        subject TEXT NOT NULL,
+        local REAL,
+        venue_value REAL,
+        action TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+",
+    "
+    CREATE TABLE strategy_streaks (
+        strategy TEXT PRIMARY KEY,
+        losing_trades INTEGER NOT NULL,
+        rejections INTEGER NOT NULL,
+        bench_kind TEXT,
+        benched_until INTEGER,
+        restore_mode TEXT,
+        updated_at INTEGER NOT NULL
+    );
+",
+    "
+    ALTER TABLE orders ADD COLUMN reduce_only INTEGER NOT NULL DEFAULT 0;
+",
+    "
+    ALTER TABLE orders ADD COLUMN position_side TEXT NOT NULL DEFAULT 'both';
+    ALTER TABLE fills ADD COLUMN position_side TEXT NOT NULL DEFAULT 'both';
+",
+    "
+    CREATE TABLE sessions (
+        session INTEGER PRIMARY KEY,
+        started_at INTEGER NOT NULL,
+        realized_pnl REAL NOT NULL,
+        fees REAL NOT NULL,
+        fills INTEGER NOT NULL,
+        trades INTEGER NOT NULL,
+        peak_equity REAL NOT NULL,
+        max_drawdown_pct REAL NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    // Most entries written per transaction
+    pub batch_size: usize,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("journal.sqlite"),
+            batch_size: 256,
+        }
+    }
+}
+
+impl JournalConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.batch_size == 0 {
+            return Err("Journal batch_size must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+// What happened to an order after it was submitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderEvent {
+    Accepted,
+    Rejected,
+    // A stop's trigger price was reached and it went to market
+    Triggered,
+    Cancelled,
+    // Superseded by a replacement order; the detail names it
+    Replaced,
+    // What an IOC/FOK or market order couldn't fill was dropped
+    RemainderCancelled,
+}
+
+impl OrderEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderEvent::Accepted => "accepted",
+            OrderEvent::Rejected => "rejected",
+            OrderEvent::Triggered => "triggered",
+            OrderEvent::Cancelled => "cancelled",
+            OrderEvent::Replaced => "replaced",
+            OrderEvent::RemainderCancelled => "remainder_cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum JournalEntry {
+    OrderSubmitted(Order),
+    OrderEvent {
+        order_id: String,
+        symbol: String,
+        event: OrderEvent,
+        detail: Option<String>,
+        timestamp: u64,
+    },
+    Fill(Fill),
+    // How long the pipeline took to produce a submitted order
+    OrderLatency {
+        order_id: String,
+        latency: OrderLatency,
+    },
+    // Why the bot sent an order closing a position on its own
+    ExitReason {
+        order_id: String,
+        reason: ExitReason,
+    },
+    RiskRejection {
+        symbol: String,
+        side: OrderSide,
+        quantity: f64,
+        strategy: Option<String>,
+        reason: String,
+        timestamp: u64,
+    },
+    // A difference reconciliation found against a venue and what was done
+    // about it. `subject` is the order id, symbol or asset.
+    Reconciliation {
+        venue: VenueId,
+        discrepancy: String,
+        subject: String,
+        local: Option<f64>,
+        venue_value: Option<f64>,
+        action: String,
+        timestamp: u64,
+    },
+    // A strategy's losing and rejection streaks, replacing the last ones
+    Streak {
+        strategy: String,
+        streak: Streak,
+        timestamp: u64,
+    },
+    // A trading session's counters, replacing the last ones
+    Session {
+        stats: SessionStats,
+        timestamp: u64,
+    },
+    // Equity samples to append to the CSV at `path`; written to the file,
+    // not the database
+    EquityCurve {
+        path: PathBuf,
+        points: Vec<EquityPoint>,
+    },
+}
+
+/// Handle the trading path records through. Entries go over a channel to a
+/// writer thread, so recording never waits on the database.
+#[derive(Clone)]
+pub struct Journal {
+    writer: Arc<Mutex<Option<Writer>>>,
+}
+
+// The channel into the writer thread and the thread itself, shared by
+// every handle so one shutdown closes the journal for all of them
+struct Writer {
+    entries: mpsc::UnboundedSender<JournalEntry>,
+    thread: JoinHandle<()>,
+}
+
+impl Journal {
+    /// Opens (and migrates) the database and starts its writer thread,
+    /// which commits whatever has queued up, up to `batch_size` entries per
+    /// transaction. The thread runs until [`Journal::shutdown`].
+    pub fn open(config: &JournalConfig) -> Result<Self, String> {
+        let mut store = JournalStore::open(&config.path)?;
+        let (entries, mut queue) = mpsc::unbounded_channel();
+        let batch_size = config.batch_size;
+        let thread = std::thread::spawn(move || {
+            while let Some(first) = queue.blocking_recv() {
+                let mut batch = vec![first];
+                while batch.len() < batch_size {
+                    match queue.try_recv() {
+                        Ok(entry) => batch.push(entry),
+                        Err(_) => break,
+                    }
+                }
+                if let Err(e) = store.write(&batch) {
+                    println!("Failed to journal {} entries: {}", batch.len(), e);
+                }
+            }
+        });
+        Ok(Self {
+            writer: Arc::new(Mutex::new(Some(Writer { entries, thread }))),
+        })
+    }
+
+    pub fn record(&self, entry: JournalEntry) {
+        // Entries recorded after shutdown are dropped
+        if let Some(writer) = &*self.writer.lock().unwrap() {
+            let _ = writer.entries.send(entry);
+        }
+    }
+
+    /// Closes the channel and blocks until the writer thread has written
+    /// out everything queued before it. Later calls do nothing.
+    pub fn shutdown(&self) {
+        let Some(Writer { entries, thread }) = self.writer.lock().unwrap().take() else {
+            return;
+        };
+        drop(entries);
+        if thread.join().is_err() {
+            println!("Trade journal writer panicked");
+        }
+    }
+}
+
+// Per-strategy totals over the journal
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategySummary {
+    pub strategy: String,
+    pub fills: usize,
+    pub volume: f64,
+    pub notional: f64,
+    pub fees: f64,
+    pub realized_pnl: f64,
+}
+
+// The journal database: schema, writes and queries
+pub struct JournalStore {
+    conn: Connection,
+}
+
+impl JournalStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open journal {}: {}", path.display(), e))?;
+        Self::with_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, String> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| format!("Failed to open in-memory journal: {}", e))?;
+        Self::with_connection(conn)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, String> {
+        let mut store = Self { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    // Brings the schema up to date
+    fn migrate(&mut self) -> Result<(), String> {
+        let version: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read journal schema version: {}", e))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self
+                .conn
+                .transaction()
+                .map_err(|e| format!("Journal migration {} failed: {}", index + 1, e))?;
+            tx.execute_batch(migration)
+                .and_then(|_| tx.pragma_update(None, "user_version", index + 1))
+                .and_then(|_| tx.commit())
+                .map_err(|e| format!("Journal migration {} failed: {}", index + 1, e))?;
+        }
+        Ok(())
+    }
+
+    // Writes the entries in one transaction
+    pub fn write(&mut self, entries: &[JournalEntry]) -> Result<(), rusqlite::Error> {
+        let tx = self.conn.transaction()?;
+        for entry in entries {
+            match entry {
+                JournalEntry::OrderSubmitted(order) => {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO orders (order_id, client_order_id, symbol, side, \
+                         order_type, quantity, price, time_in_force, strategy, link_group, \
+                         submitted_at, venue, reduce_only, position_side) \
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                        params![
+                            order.id,
+                            order.client_order_id,
+                            order.symbol.as_str(),
+                            format!("{:?}", order.side),
+                            format!("{:?}", order.order_type),
+                            order.quantity,
+                            order.price,
+                            format!("{:?}", order.time_in_force),
+                            order.strategy,
+                            order.link_group,
+                            order.timestamp as i64,
+                            order.venue.as_str(),
+                            order.reduce_only,
+                            order.position_side.as_str(),
+                        ],
+                    )?;
+                }
+                JournalEntry::OrderEvent {
+                    order_id,
+                    symbol,
+                    event,
+                    detail,
+                    timestamp,
+                } => {
+                    tx.execute(
+                        "INSERT INTO order_events (order_id, symbol, event, detail, timestamp) \
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![order_id, symbol, event.as_str(), detail, *timestamp as i64],
+                    )?;
+                }
+                JournalEntry::Fill(fill) => {
+                    tx.execute(
+                        "INSERT INTO fills (order_id, symbol, side, quantity, price, fee, \
+                         liquidity, strategy, timestamp, venue, position_side) \
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        params![
+                            fill.order_id,
+                            fill.symbol.as_str(),
+                            format!("{:?}", fill.side),
+                            fill.quantity,
+                            fill.price,
+                            fill.fee,
+                            format!("{:?}", fill.liquidity),
+                            fill.strategy,
+                            fill.timestamp as i64,
+                            fill.venue.as_str(),
+                            fill.position_side.as_str(),
+                        ],
+                    )?;
+                }
+                JournalEntry::OrderLatency { order_id, latency } => {
+                    tx.execute(
+                        "UPDATE orders SET feed_us = ?2, strategy_us = ?3, risk_us = ?4, \
+                         submit_us = ?5, total_us = ?6 WHERE order_id = ?1",
+                        params![
+                            order_id,
+                            latency.feed_us as i64,
+                            latency.strategy_us as i64,
+                            latency.risk_us as i64,
+                            latency.submit_us as i64,
+                            latency.total_us as i64,
+                        ],
+                    )?;
+                }
+                JournalEntry::ExitReason { order_id, reason } => {
+                    tx.execute(
+                        "UPDATE orders SET exit_reason = ?2 WHERE order_id = ?1",
+                        params![order_id, reason.as_str()],
+                    )?;
+                }
+                JournalEntry::RiskRejection {
+                    symbol,
+                    side,
+                    quantity,
+                    strategy,
+                    reason,
+                    timestamp,
+                } => {
+                    tx.execute(
+                        "INSERT INTO risk_rejections (symbol, side, quantity, strategy, reason, \
+                         timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![
+                            symbol,
+                            format!("{:?}", side),
+                            quantity,
+                            strategy,
+                            reason,
+                            *timestamp as i64
+                        ],
+                    )?;
+                }
+                JournalEntry::Reconciliation {
+                    venue,
+                    discrepancy,
+                    subject,
+                    local,
+                    venue_value,
+                    action,
+                    timestamp,
+                } => {
+                    tx.execute(
+                        "INSERT INTO reconciliations (venue, discrepancy, subject, local, \
+                         venue_value, action, timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            venue.as_str(),
+                            discrepancy,
+                            subject,
+                            local,
+                            venue_value,
+                            action,
+                            *timestamp as i64
+                        ],
+                    )?;
+                }
+                JournalEntry::Streak {
+                    strategy,
+                    streak,
+                    timestamp,
+                } => {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO strategy_streaks (strategy, losing_trades, \
+                         rejections, bench_kind, benched_until, restore_mode, updated_at) \
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            strategy,
+                            streak.losing_trades,
+                            streak.rejections,
+                            streak.benched.map(|bench| bench.kind.as_str()),
+                            streak.benched.map(|bench| bench.until as i64),
+                            streak.benched.map(|bench| bench.restore.as_str()),
+                            *timestamp as i64
+                        ],
+                    )?;
+                }
+                JournalEntry::Session { stats, timestamp } => {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO sessions (session, started_at, realized_pnl, \
+                         fees, fills, trades, peak_equity, max_drawdown_pct, updated_at) \
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                        params![
+                            stats.session as i64,
+                            stats.started_at as i64,
+                            stats.realized_pnl,
+                            stats.fees,
+                            stats.fills as i64,
+                            stats.trades as i64,
+                            stats.peak_equity,
+                            stats.max_drawdown_pct,
+                            *timestamp as i64
+                        ],
+                    )?;
+                }
+                JournalEntry::EquityCurve { path, points } => {
+                    if let Err(e) = equity::append_csv(path, points) {
+                        println!("{}", e);
+                    }
+                }
+            }
+        }
+        tx.commit()
+    }
+
+    /// Fills in [from, to) (Unix seconds), oldest first, optionally for one
+    /// symbol only
+    pub fn trades(&self, symbol: Option<&str>, from: u64, to: u64) -> Result<Vec<Fill>, String> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT order_id, symbol, side, quantity, price, fee, liquidity, strategy, \
+                 timestamp, venue, position_side FROM fills WHERE (?1 IS NULL OR symbol = ?1) \
+                 AND timestamp >= ?2 AND timestamp < ?3 ORDER BY timestamp, id",
+            )
+            .map_err(|e| format!("Journal query failed: {}", e))?;
+        let rows = statement
+            .query_map(params![symbol, from as i64, to as i64], |row| {
+                let side: String = row.get(2)?;
+                let liquidity: String = row.get(6)?;
+                Ok(Fill {
+                    order_id: row.get(0)?,
+                    symbol: SymbolId::intern(&row.get::<_, String>(1)?),
+                    side: if side == "Sell" {
+                        OrderSide::Sell
+                    } else {
+                        OrderSide::Buy
+                    },
+                    quantity: row.get(3)?,
+                    price: row.get(4)?,
+                    fee: row.get(5)?,
+                    liquidity: if liquidity == "Maker" {
+                        Liquidity::Maker
+                    } else {
+                        Liquidity::Taker
+                    },
+                    strategy: row.get(7)?,
+                    timestamp: row.get::<_, i64>(8)? as u64,
+                    venue: VenueId::new(row.get::<_, String>(9)?),
+                    position_side: PositionSide::parse(&row.get::<_, String>(10)?)
+                        .unwrap_or_default(),
+                })
+            })
+            .map_err(|e| format!("Journal query failed: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Journal query failed: {}", e))
+    }
+
+    /// Realized PnL (net of fees) per UTC day, keyed by the day's start in
+    /// Unix seconds. Every fill is replayed through average-cost accounting,
+    /// so a day's figure includes closes of positions opened earlier.
+    pub fn daily_realized_pnl(&self) -> Result<BTreeMap<u64, f64>, String> {
+        let mut positions = ReplayedPositions::new();
+        let mut days = BTreeMap::new();
+        for fill in self.trades(None, 0, i64::MAX as u64)? {
+            let realized = positions
+                .entry((fill.symbol, fill.position_side))
+                .or_insert_with(|| Position::flat(fill.symbol))
+                .apply_trade(fill.signed_quantity(), fill.price, fill.fee);
+            *days
+                .entry(fill.timestamp / SECONDS_PER_DAY * SECONDS_PER_DAY)
+                .or_insert(0.0) += realized;
+        }
+        Ok(days)
+    }
+
+    /// Fills in [from, to) with the PnL each realized, every earlier fill
+    /// replayed through average-cost accounting per venue, symbol and leg
+    /// as RiskManager books them, and the positions still open at `to`,
+    /// marked at their symbol's last fill price. No rates are journaled,
+    /// so every amount stays in its quote asset.
+    pub fn booked_fills(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> Result<(Vec<BookedFill>, Vec<PositionRow>), String> {
+        let mut positions: BTreeMap<(SymbolId, VenueId, PositionSide), Position> = BTreeMap::new();
+        let mut last_prices: HashMap<SymbolId, f64> = HashMap::new();
+        let mut booked = Vec::new();
+        for fill in self.trades(None, 0, to)? {
+            last_prices.insert(fill.symbol, fill.price);
+            let position = positions
+                .entry((fill.symbol, fill.venue.clone(), fill.position_side))
+                .or_insert_with(|| {
+                    Position::flat_leg(&fill.venue, fill.symbol, fill.position_side)
+                });
+            let closing = position.quantity != 0.0
+                && position.quantity.signum() != fill.signed_quantity().signum();
+            let realized_pnl = position.apply_trade(fill.signed_quantity(), fill.price, fill.fee);
+            if fill.timestamp >= from {
+                booked.push(BookedFill {
+                    fill,
+                    realized_pnl,
+                    closing,
+                    rate: 1.0,
+                });
+            }
+        }
+        let open = positions
+            .into_values()
+            .filter(|position| position.quantity != 0.0)
+            .map(|position| {
+                let mark = last_prices[&position.symbol];
+                PositionRow {
+                    unrealized_pnl: position.quantity * (mark - position.avg_price),
+                    venue: position.venue,
+                    symbol: position.symbol.to_string(),
+                    side: position.side,
+                    quantity: position.quantity,
+                    avg_price: position.avg_price,
+                    mark,
+                    rate: 1.0,
+                }
+            })
+            .collect();
+        Ok((booked, open))
+    }
+
+    // Each strategy's streaks as last journaled
+    pub fn streaks(&self) -> Result<Vec<(String, Streak)>, String> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT strategy, losing_trades, rejections, bench_kind, benched_until, \
+                 restore_mode FROM strategy_streaks ORDER BY strategy",
+            )
+            .map_err(|e| format!("Journal query failed: {}", e))?;
+        let rows = statement
+            .query_map([], |row| {
+                let kind: Option<String> = row.get(3)?;
+                let until: Option<i64> = row.get(4)?;
+                let restore: Option<String> = row.get(5)?;
+                let benched = (|| {
+                    Some(Bench {
+                        kind: StreakKind::parse(&kind?)?,
+                        until: until? as u64,
+                        restore: StrategyMode::parse(&restore?)?,
+                    })
+                })();
+                Ok((
+                    row.get::<_, String>(0)?,
+                    Streak {
+                        losing_trades: row.get(1)?,
+                        rejections: row.get(2)?,
+                        benched,
+                    },
+                ))
+            })
+            .map_err(|e| format!("Journal query failed: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Journal query failed: {}", e))
+    }
+
+    // A session's counters as last journaled
+    pub fn session(&self, session: u64) -> Result<Option<SessionStats>, String> {
+        let result = self.conn.query_row(
+            "SELECT session, started_at, realized_pnl, fees, fills, trades, peak_equity, \
+             max_drawdown_pct FROM sessions WHERE session = ?1",
+            params![session as i64],
+            |row| {
+                Ok(SessionStats {
+                    session: row.get::<_, i64>(0)? as u64,
+                    started_at: row.get::<_, i64>(1)? as u64,
+                    realized_pnl: row.get(2)?,
+                    fees: row.get(3)?,
+                    fills: row.get::<_, i64>(4)? as usize,
+                    trades: row.get::<_, i64>(5)? as usize,
+                    peak_equity: row.get(6)?,
+                    max_drawdown_pct: row.get(7)?,
+                })
+            },
+        );
+        match result {
+            Ok(stats) => Ok(Some(stats)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(format!("Journal query failed: {}", e)),
+        }
+    }
+
+    /// Totals per strategy. Realized PnL replays each strategy's own fills,
+    /// as if it traded alone; fills without a strategy are grouped under
+    /// `performance::UNATTRIBUTED`.
+    pub fn strategy_summaries(&self) -> Result<Vec<StrategySummary>, String> {
+        let mut summaries: BTreeMap<String, (StrategySummary, ReplayedPositions)> = BTreeMap::new();
+        for fill in self.trades(None, 0, i64::MAX as u64)? {
+            let name = fill
+                .strategy
+                .clone()
+                .unwrap_or_else(|| crate::performance::UNATTRIBUTED.to_string());
+            let (summary, positions) = summaries.entry(name.clone()).or_insert_with(|| {
+                (
+                    StrategySummary {
+                        strategy: name,
+                        fills: 0,
+                        volume: 0.0,
+                        notional: 0.0,
+                        fees: 0.0,
+                        realized_pnl: 0.0,
+                    },
+                    HashMap::new(),
+                )
+            });
+            summary.fills += 1;
+            summary.volume += fill.quantity;
+            summary.notional += fill.quantity * fill.price;
+            summary.fees += fill.fee;
+            summary.realized_pnl += positions
+                .entry((fill.symbol, fill.position_side))
+                .or_insert_with(|| Position::flat(fill.symbol))
+                .apply_trade(fill.signed_quantity(), fill.price, fill.fee);
+        }
+        Ok(summaries.into_values().map(|(s, _)| s).collect())
+    }
+}
+
+// Fills as CSV, one row each
+pub fn trades_csv(fills: &[Fill]) -> String {
+    let mut csv =
+        String::from("timestamp,order_id,symbol,side,quantity,price,fee,liquidity,strategy\n");
+    for fill in fills {
+        csv.push_str(&format!(
+            "{},{},{},{:?},{},{},{},{:?},{}\n",
+            fill.timestamp,
+            fill.order_id,
+            fill.symbol,
+            fill.side,
+            fill.quantity,
+            fill.price,
+            fill.fee,
+            fill.liquidity,
+            fill.strategy.as_deref().unwrap_or("")
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    // 2024-01-01T00:00:00Z, a day boundary
+    const START_SECS: u64 = 1_704_067_200;
+
+    fn fill(
+        strategy: Option<&str>,
+        symbol: &str,
+        side: OrderSide,
+        quantity: f64,
+        price: f64,
+        fee: f64,
+        timestamp: u64,
+    ) -> Fill {
+        Fill {
+            fee,
+            timestamp,
+            strategy: strategy.map(str::to_string),
+            ..fixtures::fill(symbol, side, quantity, price)
+        }
+    }
+
+    // Momentum opens 10 SOL at 100 and takes 4 off at 110 on day one,
+    // alongside an unattributed BTC buy; mean reversion sells the other 6
+    // at 90 the next day
+    fn journal() -> JournalStore {
+        let mut store = JournalStore::open_in_memory().unwrap();
+        let momentum = Some("momentum");
+        let day = START_SECS + SECONDS_PER_DAY;
+        store
+            .write(&[
+                JournalEntry::Fill(fill(
+                    momentum,
+                    "SOL/USDT",
+                    OrderSide::Buy,
+                    10.0,
+                    100.0,
+                    1.0,
+                    START_SECS + 10,
+                )),
+                JournalEntry::Fill(fill(
+                    momentum,
+                    "SOL/USDT",
+                    OrderSide::Sell,
+                    4.0,
+                    110.0,
+                    0.5,
+                    START_SECS + 20,
+                )),
+                JournalEntry::Fill(fill(
+                    None,
+                    "BTC/USDT",
+                    OrderSide::Buy,
+                    1.0,
+                    50_000.0,
+                    0.0,
+                    START_SECS + 30,
+                )),
+                JournalEntry::Fill(fill(
+                    Some("mean_reversion"),
+                    "SOL/USDT",
+                    OrderSide::Sell,
+                    6.0,
+                    90.0,
+                    0.5,
+                    day + 5,
+                )),
+            ])
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn migrations_run_once() {
+        let mut store = JournalStore::open_in_memory().unwrap();
+        store.migrate().unwrap();
+        let version: usize = store
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn trades_by_symbol_and_range() {
+        let store = journal();
+        let day = START_SECS + SECONDS_PER_DAY;
+        let first_day = store.trades(Some("SOL/USDT"), START_SECS, day).unwrap();
+        assert_eq!(first_day.len(), 2);
+        assert_eq!(first_day[0].side, OrderSide::Buy);
+        assert_eq!(first_day[1].price, 110.0);
+        assert_eq!(first_day[1].strategy.as_deref(), Some("momentum"));
+
+        let everything = store
+            .trades(None, START_SECS, day + SECONDS_PER_DAY)
+            .unwrap();
+        assert_eq!(everything.len(), 4);
+        let second_day = store.trades(None, day, day + SECONDS_PER_DAY).unwrap();
+        assert_eq!(second_day.len(), 1);
+        assert_eq!(second_day[0].strategy.as_deref(), Some("mean_reversion"));
+        assert!(
+            store
+                .trades(Some("ETH/USDT"), 0, u64::MAX / 2)
+                .unwrap()
+                .is_empty()
+        );
+
+        assert_eq!(
+            trades_csv(&second_day),
+            "timestamp,order_id,symbol,side,quantity,price,fee,liquidity,strategy\n\
+             1704153605,fixture,SOL/USDT,Sell,6,90,0.5,Taker,mean_reversion\n"
+        );
+    }
+
+    #[test]
+    fn realized_pnl_per_day() {
+        let days: Vec<(u64, f64)> = journal()
+            .daily_realized_pnl()
+            .unwrap()
+            .into_iter()
+            .collect();
+        // -1 fee on the buy, then 4 x 10 less a 0.5 fee; then 6 x -10 less 0.5
+        assert_eq!(
+            days,
+            vec![(START_SECS, 38.5), (START_SECS + SECONDS_PER_DAY, -60.5)]
+        );
+    }
+
+    #[test]
+    fn per_strategy_summaries() {
+        let summaries = journal().strategy_summaries().unwrap();
+        let rows: Vec<(&str, usize, f64, f64, f64, f64)> = summaries
+            .iter()
+            .map(|s| {
+                (
+                    s.strategy.as_str(),
+                    s.fills,
+                    s.volume,
+                    s.notional,
+                    s.fees,
+                    s.realized_pnl,
+                )
+            })
+            .collect();
+        // Mean reversion's sale opens a short of its own, costing its fee
+        assert_eq!(
+            rows,
+            vec![
+                ("mean_reversion", 1, 6.0, 540.0, 0.5, -0.5),
+                ("momentum", 2, 14.0, 1_440.0, 1.5, 38.5),
+                (crate::performance::UNATTRIBUTED, 1, 1.0, 50_000.0, 0.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn orders_and_rejections_are_recorded() {
+        let mut store = JournalStore::open_in_memory().unwrap();
+        let mut order = fixtures::limit_order("SOL/USDT", OrderSide::Buy, 2.0, 99.0);
+        order.id = "order-1".to_string();
+        store
+            .write(&[
+                JournalEntry::OrderSubmitted(order),
+                JournalEntry::OrderLatency {
+                    order_id: "order-1".to_string(),
+                    latency: OrderLatency {
+                        feed_us: 120,
+                        strategy_us: 30,
+                        risk_us: 5,
+                        submit_us: 45,
+                        total_us: 200,
+                    },
+                },
+                JournalEntry::ExitReason {
+                    order_id: "order-1".to_string(),
+                    reason: ExitReason::TimeStop,
+                },
+                JournalEntry::OrderEvent {
+                    order_id: "order-1".to_string(),
+                    symbol: "SOL/USDT".to_string(),
+                    event: OrderEvent::Cancelled,
+                    detail: None,
+                    timestamp: START_SECS,
+                },
+                JournalEntry::RiskRejection {
+                    symbol: "SOL/USDT".to_string(),
+                    side: OrderSide::Sell,
+                    quantity: 5.0,
+                    strategy: Some("momentum".to_string()),
+                    reason: "Position size limit exceeded".to_string(),
+                    timestamp: START_SECS,
+                },
+            ])
+            .unwrap();
+
+        let query =
+            |sql: &str| -> String { store.conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(
+            query(
+                "SELECT side || ' ' || price || ' ' || time_in_force FROM orders WHERE order_id = 'order-1'"
+            ),
+            "Buy 99.0 Gtc"
+        );
+        assert_eq!(
+            query("SELECT event FROM order_events WHERE order_id = 'order-1'"),
+            "cancelled"
+        );
+        assert_eq!(
+            query(
+                "SELECT feed_us || ' ' || strategy_us || ' ' || risk_us || ' ' || submit_us || ' ' || total_us FROM orders WHERE order_id = 'order-1'"
+            ),
+            "120 30 5 45 200"
+        );
+        assert_eq!(
+            query("SELECT exit_reason FROM orders WHERE order_id = 'order-1'"),
+            "time_stop"
+        );
+        assert_eq!(
+            query("SELECT strategy || ': ' || reason FROM risk_rejections"),
+            "momentum: Position size limit exceeded"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Most recent samples kept per stage for the percentile summary
+const MAX_SAMPLES: usize = 10_000;
+
+// A step of the data → order pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    // From the newest price arriving to its evaluation starting
+    Feed,
+    // Running the strategies and aggregating their signals
+    Strategy,
+    // Rounding the order onto the symbol's grids and the risk check
+    Risk,
+    // Handing the order to the executor until it is accepted
+    Submit,
+    // From the price arriving to the order being accepted
+    Total,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Feed,
+        Stage::Strategy,
+        Stage::Risk,
+        Stage::Submit,
+        Stage::Total,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Feed => "feed",
+            Stage::Strategy => "strategy",
+            Stage::Risk => "risk",
+            Stage::Submit => "submit",
+            Stage::Total => "total",
+        }
+    }
+}
+
+/// Monotonic marks taken as one evaluation moves through the pipeline,
+/// read off `Instant` once per mark. Only evaluations that end in a
+/// submitted order get every mark.
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    pub received: Instant,
+    pub evaluation_started: Option<Instant>,
+    pub evaluation_finished: Option<Instant>,
+    pub risk_checked: Option<Instant>,
+    pub submitted: Option<Instant>,
+}
+
+impl Timing {
+    // Starts timing from when the data being evaluated arrived
+    pub fn new(received: Instant) -> Self {
+        Self {
+            received,
+            evaluation_started: None,
+            evaluation_finished: None,
+            risk_checked: None,
+            submitted: None,
+        }
+    }
+
+    pub fn start_evaluation(&mut self) {
+        self.evaluation_started = Some(Instant::now());
+    }
+
+    pub fn finish_evaluation(&mut self) {
+        self.evaluation_finished = Some(Instant::now());
+    }
+
+    pub fn check_risk(&mut self) {
+        self.risk_checked = Some(Instant::now());
+    }
+
+    pub fn submit(&mut self) {
+        self.submitted = Some(Instant::now());
+    }
+
+    // Per-stage latencies, once every mark has been taken
+    pub fn latency(&self) -> Option<OrderLatency> {
+        let started = self.evaluation_started?;
+        let finished = self.evaluation_finished?;
+        let checked = self.risk_checked?;
+        let submitted = self.submitted?;
+        let micros = |from: Instant, to: Instant| to.duration_since(from).as_micros() as u64;
+        Some(OrderLatency {
+            feed_us: micros(self.received, started),
+            strategy_us: micros(started, finished),
+            risk_us: micros(finished, checked),
+            submit_us: micros(checked, submitted),
+            total_us: micros(self.received, submitted),
+        })
+    }
+}
+
+// How long one order spent in each stage, in microseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderLatency {
+    pub feed_us: u64,
+    pub strategy_us: u64,
+    pub risk_us: u64,
+    pub submit_us: u64,
+    pub total_us: u64,
+}
+
+impl OrderLatency {
+    pub fn stage(&self, stage: Stage) -> Duration {
+        Duration::from_micros(match stage {
+            Stage::Feed => self.feed_us,
+            Stage::Strategy => self.strategy_us,
+            Stage::Risk => self.risk_us,
+            Stage::Submit => self.submit_us,
+            Stage::Total => self.total_us,
+        })
+    }
+}
+
+// Percentiles of one stage over the recorded orders
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StageSummary {
+    pub stage: Stage,
+    pub count: usize,
+    pub p50_us: u64,
+    pub p99_us: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub stages: Vec<StageSummary>,
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.stages.is_empty() {
+            return write!(f, "no orders timed");
+        }
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<8} p50 {:>8} us  p99 {:>8} us  ({} orders)",
+                stage.stage.as_str(),
+                stage.p50_us,
+                stage.p99_us,
+                stage.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The latest per-stage latencies of submitted orders, for the percentile
+/// summary printed on shutdown
+#[derive(Debug)]
+pub struct LatencyRecorder {
+    // One queue per Stage::ALL entry
+    samples: Mutex<Vec<VecDeque<u64>>>,
+}
+
+impl Default for LatencyRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyRecorder {
+    pub fn new() -> Self {
+        Self {
+            samples: Mutex::new(vec![VecDeque::new(); Stage::ALL.len()]),
+        }
+    }
+
+    pub fn record(&self, latency: &OrderLatency) {
+        let mut samples = self.samples.lock().unwrap();
+        for (stage, stage_samples) in Stage::ALL.iter().zip(samples.iter_mut()) {
+            if stage_samples.len() == MAX_SAMPLES {
+                stage_samples.pop_front();
+            }
+            stage_samples.push_back(latency.stage(*stage).as_micros() as u64);
+        }
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        let samples = self.samples.lock().unwrap();
+        let stages = Stage::ALL
+            .iter()
+            .zip(samples.iter())
+            .filter(|(_, stage_samples)| !stage_samples.is_empty())
+            .map(|(&stage, stage_samples)| {
+                let mut sorted: Vec<u64> = stage_samples.iter().copied().collect();
+                sorted.sort_unstable();
+                // Nearest rank
+                let percentile = |p: f64| {
+                    let rank = (p * sorted.len() as f64).ceil() as usize;
+                    sorted[rank.clamp(1, sorted.len()) - 1]
+                };
+                StageSummary {
+                    stage,
+                    count: sorted.len(),
+                    p50_us: percentile(0.50),
+                    p99_us: percentile(0.99),
+                }
+            })
+            .collect();
+        LatencySummary { stages }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latency(total_us: u64) -> OrderLatency {
+        OrderLatency {
+            feed_us: total_us / 4,
+            strategy_us: total_us / 4,
+            risk_us: total_us / 4,
+            submit_us: total_us / 4,
+            total_us,
+        }
+    }
+
+    #[test]
+    fn marks_are_taken_in_pipeline_order() {
+        let mut timing = Timing::new(Instant::now());
+        let pause = || std::thread::sleep(Duration::from_millis(2));
+        pause();
+        timing.start_evaluation();
+        pause();
+        timing.finish_evaluation();
+        pause();
+        timing.check_risk();
+        // An evaluation that stops short of an order isn't timed
+        assert!(timing.latency().is_none());
+        pause();
+        timing.submit();
+
+        let marks = [
+            Some(timing.received),
+            timing.evaluation_started,
+            timing.evaluation_finished,
+            timing.risk_checked,
+            timing.submitted,
+        ];
+        let marks: Vec<Instant> = marks.into_iter().map(Option::unwrap).collect();
+        assert!(marks.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let latency = timing.latency().unwrap();
+        for stage in [Stage::Feed, Stage::Strategy, Stage::Risk, Stage::Submit] {
+            assert!(
+                latency.stage(stage) >= Duration::from_millis(2),
+                "{stage:?}"
+            );
+        }
+        // Each stage is truncated to the microsecond on its own
+        let stages = latency.feed_us + latency.strategy_us + latency.risk_us + latency.submit_us;
+        assert!((stages..stages + 4).contains(&latency.total_us));
+    }
+
+    #[test]
+    fn the_summary_takes_nearest_rank_percentiles() {
+        let recorder = LatencyRecorder::new();
+        assert_eq!(recorder.summary().to_string(), "no orders timed");
+        for total_us in (1..=100).map(|i| i * 40) {
+            recorder.record(&latency(total_us));
+        }
+        let summary = recorder.summary();
+        assert_eq!(summary.stages.len(), Stage::ALL.len());
+        let total = summary.stages[4];
+        assert_eq!(total.stage, Stage::Total);
+        assert_eq!(
+            (total.count, total.p50_us, total.p99_us),
+            (100, 2_000, 3_960)
+        );
+        assert_eq!(
+            (summary.stages[0].p50_us, summary.stages[0].p99_us),
+            (500, 990)
+        );
+    }
+}
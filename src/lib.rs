@@ -0,0 +1,7014 @@
+pub mod account;
+pub mod aggregator;
+pub mod allocation;
+pub mod api;
+pub mod attribution;
+pub mod backtest;
+pub mod basis;
+pub mod binance;
+pub mod book;
+pub mod breaker;
+pub mod builder;
+pub mod calendar;
+pub mod candles;
+pub mod clock;
+pub mod coinbase;
+pub mod config;
+pub mod consolidated;
+pub mod correlation;
+pub mod currency;
+pub mod depth_sizing;
+pub mod donchian;
+pub mod equity;
+pub mod events;
+pub mod external;
+pub mod fees;
+pub mod fix;
+pub mod fixtures;
+pub mod funding;
+pub mod history;
+pub mod holding;
+pub mod indicators;
+pub mod journal;
+pub mod latency;
+pub mod macd;
+pub mod margin;
+pub mod marks;
+pub mod metrics;
+pub mod ml;
+pub mod monitor;
+pub mod notify;
+pub mod ofi;
+pub mod optimize;
+pub mod order_guard;
+pub mod orders;
+pub mod paper;
+pub mod performance;
+pub mod price_buffer;
+pub mod quoting;
+pub mod rate_limit;
+pub mod reconcile;
+pub mod recording;
+pub mod report;
+pub mod rest_client;
+pub mod retry;
+pub mod router;
+pub mod scalper;
+pub mod schedule;
+pub mod session;
+pub mod shadow;
+pub mod simulation;
+pub mod staleness;
+pub mod state;
+pub mod streaks;
+pub mod supervisor;
+pub mod symbols;
+pub mod tick_filter;
+pub mod time_sync;
+pub mod user_data;
+pub mod venue;
+pub mod ws;
+
+use account::Account;
+use aggregator::{CandidateSignal, SignalAggregator};
+use allocation::{BudgetExceeded, BudgetUsage, StrategyAllocator};
+use attribution::{AttributionConfig, ClosedTrade, StrategyPnl, StrategyStats};
+use basis::{BasisAction, BasisStrategy};
+use breaker::{Trip, TrippedSymbol, VolatilityBreaker};
+use builder::TradingBotBuilder;
+use calendar::{SessionState, TradingCalendar};
+use candles::{Candle, CandleAggregator, SymbolCandles, Timeframe};
+use clock::{Clock, SystemClock};
+use config::BotConfig;
+use correlation::{CorrelationMatrix, CorrelationTracker};
+use currency::CurrencyConverter;
+use depth_sizing::DepthSizingConfig;
+use equity::{EquityCurve, EquityPoint, EquityReport};
+use events::BotEvent;
+use external::{ExternalSignal, ExternalSignals, PendingSignal, SignalRejection};
+use fees::{FeeModel, Liquidity};
+use funding::{FundingConfig, FundingPayment, FundingRates};
+use futures::future::BoxFuture;
+use holding::HoldingConfig;
+use indicators::{IndicatorHub, OfiSpec, SymbolIndicators, VolatilityEstimate};
+use journal::{Journal, JournalEntry, OrderEvent};
+use latency::{LatencyRecorder, LatencySummary, Stage, Timing};
+use marks::{MarkConfig, MarkPriceCache};
+use metrics::Metrics;
+use monitor::{ExitReason, ExitRules, Level, PositionMonitor, ProtectiveLevels};
+use order_guard::{GuardDecision, OrderGuardConfig};
+use orders::{OpenOrderTracker, OpenOrders, OrderState, OrderStatus};
+use paper::{
+    InjectedFault, LatencyModel, LatencyStats, MatchingEngine, PaperConfig, RemainderPolicy,
+    SelfMatchPolicy, WorkingOrder,
+};
+use price_buffer::{Insertion, PriceBuffer, PriceHistoryConfig};
+use rate_limit::{OrderRateLimiter, RateLimitConfig, RateLimitKind, RateLimitUsage};
+use reconcile::{Discrepancy, ReconcileConfig, ReconcilePolicy, ReconcileReport, Resolution};
+use recording::{MarketRecorder, RecordingFeed};
+use report::{Blotter, BookedFill, DailyReport, LimitRow, PositionRow, ReportConfig};
+use retry::RetryConfig;
+use schedule::{Due, EvaluationTrigger, ScheduleConfig};
+use serde::{Deserialize, Serialize};
+use session::{SessionConfig, SessionStats, SessionSummary, SessionTracker};
+use shadow::StrategyMode;
+use simulation::{SimulatedMarket, SimulationConfig};
+use staleness::{DataAge, Freshness, FreshnessTracker, StalenessConfig};
+use state::{BotSnapshot, StateConfig};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use streaks::{Streak, StreakKind, StreakTracker};
+use supervisor::{SupervisionConfig, TaskFailurePolicy, panic_message};
+use symbols::{Symbol, SymbolId, SymbolMap, SymbolMeta, SymbolRegistry};
+use tick_filter::{BadTickAction, Screening, TickFilter};
+use tokio::sync::{Mutex, Notify, RwLock, Semaphore, broadcast, mpsc, watch};
+use uuid::Uuid;
+use venue::{Exchange, ExecutionMode, PaperExchange, VenueId};
+
+// Core data structures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Price {
+    pub symbol: String,
+    pub price: f64,
+    pub timestamp: u64,
+    pub volume: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBook {
+    pub symbol: String,
+    pub bids: Vec<(f64, f64)>, // (price, quantity)
+    pub asks: Vec<(f64, f64)>, // (price, quantity)
+    pub timestamp: u64,
+}
+
+impl OrderBook {
+    // Builds a book with bids sorted descending and asks ascending, since
+    // exchange payloads don't all guarantee level ordering
+    pub fn new(
+        symbol: String,
+        bids: Vec<(f64, f64)>,
+        asks: Vec<(f64, f64)>,
+        timestamp: u64,
+    ) -> Self {
+        let mut book = Self {
+            symbol,
+            bids,
+            asks,
+            timestamp,
+        };
+        book.sort_levels();
+        book
+    }
+
+    pub fn sort_levels(&mut self) {
+        self.bids.sort_by(|a, b| b.0.total_cmp(&a.0));
+        self.asks.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+
+    // Checks the ordering invariant in debug builds
+    pub fn debug_validate(&self) {
+        debug_assert!(
+            self.bids.windows(2).all(|w| w[0].0 >= w[1].0),
+            "bids for {} are not sorted descending",
+            self.symbol
+        );
+        debug_assert!(
+            self.asks.windows(2).all(|w| w[0].0 <= w[1].0),
+            "asks for {} are not sorted ascending",
+            self.symbol
+        );
+    }
+
+    // Best bid level as (price, quantity)
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.first().copied()
+    }
+
+    // Best ask level as (price, quantity)
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.first().copied()
+    }
+
+    pub fn is_crossed(&self) -> bool {
+        matches!((self.best_bid(), self.best_ask()), (Some((bid, _)), Some((ask, _))) if bid >= ask)
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        let (bid, _) = self.best_bid()?;
+        let (ask, _) = self.best_ask()?;
+        Some((bid + ask) / 2.0)
+    }
+
+    // Negative for a crossed book
+    pub fn spread(&self) -> Option<f64> {
+        let (bid, _) = self.best_bid()?;
+        let (ask, _) = self.best_ask()?;
+        Some(ask - bid)
+    }
+
+    pub fn spread_bps(&self) -> Option<f64> {
+        let mid = self.mid_price()?;
+        if mid <= 0.0 {
+            return None;
+        }
+        Some(self.spread()? / mid * 10_000.0)
+    }
+
+    pub fn bid_depth(&self, levels: usize) -> f64 {
+        self.bids.iter().take(levels).map(|(_, q)| q).sum()
+    }
+
+    pub fn ask_depth(&self, levels: usize) -> f64 {
+        self.asks.iter().take(levels).map(|(_, q)| q).sum()
+    }
+
+    // Quantity an order on `side` can take within `bps` of mid: asks up to
+    // mid + bps for a buy, bids down to mid - bps for a sell. None without
+    // a two-sided book.
+    pub fn depth_within_bps(&self, side: OrderSide, bps: f64) -> Option<f64> {
+        let mid = self.mid_price()?;
+        let band = mid * bps / 10_000.0;
+        let depth = match side {
+            OrderSide::Buy => self
+                .asks
+                .iter()
+                .take_while(|(price, _)| *price <= mid + band)
+                .map(|(_, q)| q)
+                .sum(),
+            OrderSide::Sell => self
+                .bids
+                .iter()
+                .take_while(|(price, _)| *price >= mid - band)
+                .map(|(_, q)| q)
+                .sum(),
+        };
+        Some(depth)
+    }
+
+    // Combined bid and ask quantity over the top `levels` of each side
+    pub fn total_depth(&self, levels: usize) -> Option<f64> {
+        if self.bids.is_empty() && self.asks.is_empty() {
+            return None;
+        }
+        Some(self.bid_depth(levels) + self.ask_depth(levels))
+    }
+
+    // (bid depth - ask depth) / total depth over the top `levels`, in [-1, 1].
+    // Positive values mean more resting buy interest.
+    pub fn imbalance(&self, levels: usize) -> Option<f64> {
+        let bid_depth = self.bid_depth(levels);
+        let ask_depth = self.ask_depth(levels);
+        let total = bid_depth + ask_depth;
+        if total <= 0.0 {
+            return None;
+        }
+        Some((bid_depth - ask_depth) / total)
+    }
+
+    // Top-of-book price weighted by the opposite side's size, which leans
+    // toward the side more likely to be traded through
+    pub fn microprice(&self) -> Option<f64> {
+        let (bid, bid_qty) = self.best_bid()?;
+        let (ask, ask_qty) = self.best_ask()?;
+        let total = bid_qty + ask_qty;
+        if total <= 0.0 {
+            return None;
+        }
+        Some((bid * ask_qty + ask * bid_qty) / total)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OrderType {
+    Market,
+    Limit,
+    // Held untriggered until the market trades through stop_price (ask for
+    // buy stops, bid for sell stops), then sent as a market order
+    StopMarket { stop_price: f64 },
+    // As StopMarket, but becomes a limit order at limit_price
+    StopLimit { stop_price: f64, limit_price: f64 },
+}
+
+impl OrderType {
+    pub fn is_stop(&self) -> bool {
+        matches!(
+            self,
+            OrderType::StopMarket { .. } | OrderType::StopLimit { .. }
+        )
+    }
+}
+
+// How long an order stays working
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeInForce {
+    // Good till cancelled
+    #[default]
+    Gtc,
+    // Immediate or cancel: fill what crosses now, cancel the rest
+    Ioc,
+    // Fill or kill: fill entirely right now or not at all
+    Fok,
+    // Limit only; rejected if it would cross the book instead of resting
+    PostOnly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Order {
+    pub id: String,
+    // Idempotency key: resubmitting the same client id never creates a
+    // second working order
+    pub client_order_id: String,
+    pub symbol: SymbolId,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub quantity: f64,
+    pub price: Option<f64>,
+    pub time_in_force: TimeInForce,
+    pub timestamp: u64,
+    pub strategy: Option<String>, // Name of the originating strategy, if any
+    // One-cancels-other group this order belongs to; set by submit_oco
+    pub link_group: Option<String>,
+    // Where the order is sent
+    #[serde(default)]
+    pub venue: VenueId,
+    // The position leg it trades; Both outside hedge mode
+    #[serde(default)]
+    pub position_side: PositionSide,
+    // Only ever takes a position toward flat: capped at the position when
+    // it executes, and cancelled if there's none left to reduce
+    #[serde(default)]
+    pub reduce_only: bool,
+}
+
+impl Order {
+    // How much of the order reduces `position` (signed) without taking it
+    // through flat; zero if it's flat or on the order's side
+    pub fn reducing_quantity(&self, position: f64) -> f64 {
+        let reducible = match self.side {
+            OrderSide::Buy => -position,
+            OrderSide::Sell => position,
+        };
+        self.quantity.min(reducible.max(0.0))
+    }
+
+    /// Caps a reduce-only order at the position it reduces, as booked in
+    /// `positions`, so a stale size or a second exit can't flip it. False
+    /// if there is none left to reduce, in which case the order should be
+    /// cancelled rather than sent. Other orders are left as is.
+    pub async fn cap_reduce_only(&mut self, positions: &PositionBook) -> bool {
+        if !self.reduce_only {
+            return true;
+        }
+        let held = positions
+            .read()
+            .await
+            .get(&self.position_key())
+            .map_or(0.0, |p| p.quantity);
+        let quantity = self.reducing_quantity(held);
+        if quantity <= f64::EPSILON {
+            return false;
+        }
+        if quantity < self.quantity {
+            println!(
+                "Reduce-only order {} capped from {} to {} ({} position {})",
+                self.id, self.quantity, quantity, self.symbol, held
+            );
+            self.quantity = quantity;
+        }
+        true
+    }
+
+    // The position (or leg) the order trades
+    pub fn position_key(&self) -> PositionKey {
+        (self.venue.clone(), self.symbol, self.position_side)
+    }
+}
+
+// A protective stop and a take-profit limit on the same position, linked
+// one-cancels-other: once either leg fills, the other is cancelled
+#[derive(Debug, Clone)]
+pub struct OcoOrder {
+    pub stop_loss: Order,
+    pub take_profit: Order,
+    // Fraction of a leg that must fill before the other is cancelled
+    // outright; below it the other leg is only reduced in proportion
+    pub cancel_threshold: f64,
+}
+
+// An execution against an order; an order may fill in several pieces.
+// Simulated by the executor in paper mode, parsed from execution reports
+// in live mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub order_id: String,
+    pub symbol: SymbolId,
+    pub side: OrderSide,
+    pub quantity: f64,
+    pub price: f64,
+    pub fee: f64,
+    pub liquidity: Liquidity,
+    pub timestamp: u64,
+    // Strategy (or strategies, joined with '+') behind the order, if any
+    pub strategy: Option<String>,
+    // Where the order executed
+    #[serde(default)]
+    pub venue: VenueId,
+    // The position leg it books to, as on its order
+    #[serde(default)]
+    pub position_side: PositionSide,
+}
+
+impl Fill {
+    // Quantity signed by side: positive for buys, negative for sells
+    pub fn signed_quantity(&self) -> f64 {
+        match self.side {
+            OrderSide::Buy => self.quantity,
+            OrderSide::Sell => -self.quantity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub symbol: SymbolId,
+    pub quantity: f64,
+    pub avg_price: f64,
+    pub unrealized_pnl: f64,
+    pub realized_pnl: f64,
+    // Positions are held per venue: the same symbol on two venues is two
+    // positions
+    #[serde(default)]
+    pub venue: VenueId,
+    // Net, or one leg of a hedge-mode pair
+    #[serde(default)]
+    pub side: PositionSide,
+}
+
+/// Which position an order or fill goes to. Outside hedge mode there is
+/// one position per venue and symbol, signed: buys and sells net against
+/// each other and a short is a negative quantity. In hedge mode long and
+/// short are separate legs: the long leg is never below zero, the short
+/// leg never above it, and a sell on the long leg reduces it rather than
+/// adding to the short.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionSide {
+    #[default]
+    Both,
+    Long,
+    Short,
+}
+
+impl PositionSide {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PositionSide::Both => "both",
+            PositionSide::Long => "long",
+            PositionSide::Short => "short",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "both" => Some(PositionSide::Both),
+            "long" => Some(PositionSide::Long),
+            "short" => Some(PositionSide::Short),
+            _ => None,
+        }
+    }
+
+    // The hedge-mode leg an order on this side opens or adds to
+    pub fn opened_by(side: OrderSide) -> Self {
+        match side {
+            OrderSide::Buy => PositionSide::Long,
+            OrderSide::Sell => PositionSide::Short,
+        }
+    }
+}
+
+impl std::fmt::Display for PositionSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Where a position is held: venue, symbol and leg
+pub type PositionKey = (VenueId, SymbolId, PositionSide);
+
+impl Position {
+    // Flat on the paper venue
+    pub fn flat(symbol: impl Into<SymbolId>) -> Self {
+        Self::flat_on(&VenueId::paper(), symbol)
+    }
+
+    pub fn flat_on(venue: &VenueId, symbol: impl Into<SymbolId>) -> Self {
+        Self::flat_leg(venue, symbol, PositionSide::Both)
+    }
+
+    pub fn flat_leg(venue: &VenueId, symbol: impl Into<SymbolId>, side: PositionSide) -> Self {
+        Self {
+            symbol: symbol.into(),
+            quantity: 0.0,
+            avg_price: 0.0,
+            unrealized_pnl: 0.0,
+            realized_pnl: 0.0,
+            venue: venue.clone(),
+            side,
+        }
+    }
+
+    pub fn key(&self) -> PositionKey {
+        (self.venue.clone(), self.symbol, self.side)
+    }
+
+    // Applies a signed trade and returns the PnL it realized. Fees are
+    // charged to realized PnL whether or not the trade closes anything, so
+    // a round trip at one price nets exactly minus its fees.
+    pub fn apply_trade(&mut self, quantity: f64, price: f64, fee: f64) -> f64 {
+        // Reducing (or flipping) the position realizes PnL on the closed part
+        let mut realized = 0.0;
+        let reducing = self.quantity != 0.0 && self.quantity.signum() != quantity.signum();
+        if reducing {
+            let closed = quantity.abs().min(self.quantity.abs());
+            realized = closed * (price - self.avg_price) * self.quantity.signum();
+        }
+        realized -= fee;
+        self.realized_pnl += realized;
+
+        // Update position
+        let new_quantity = self.quantity + quantity;
+        if new_quantity.abs() < f64::EPSILON {
+            self.quantity = 0.0;
+            self.avg_price = 0.0;
+        } else if !reducing {
+            let total_cost = self.quantity * self.avg_price + quantity * price;
+            self.quantity = new_quantity;
+            self.avg_price = total_cost / new_quantity;
+        } else if new_quantity.signum() != self.quantity.signum() {
+            // Flipped through flat: the remainder opens at the trade price
+            self.quantity = new_quantity;
+            self.avg_price = price;
+        } else {
+            self.quantity = new_quantity;
+        }
+        realized
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TradingSignal {
+    // Shared with the symbol's indicators, so signals don't copy the name
+    pub symbol: Arc<str>,
+    pub action: OrderSide,
+    pub confidence: f64,
+    pub target_price: f64,
+    pub quantity: f64,
+    pub time_in_force: TimeInForce,
+    // Rest as a limit order at this price; None for a market order
+    pub limit_price: Option<f64>,
+}
+
+/// How far from the average price a position's stop sits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StopMode {
+    // stop_loss_pct of the price
+    #[default]
+    Percent,
+    // `multiple` times the symbol's ATR when the position last changed;
+    // stop_loss_pct until the ATR has warmed up
+    AtrMultiple {
+        multiple: f64,
+    },
+}
+
+// Risk management parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskParams {
+    pub max_position_size: f64,
+    pub max_loss_per_trade: f64,
+    pub max_daily_loss: f64,
+    pub stop_loss_pct: f64,
+    pub stop_mode: StopMode,
+    pub take_profit_pct: f64,
+    // Trail a stop this fraction behind the best price since entry
+    pub trailing_stop_pct: Option<f64>,
+    // Max distance of a limit price from mid
+    pub max_price_deviation_pct: f64,
+    // Max share of the opposite touch's notional a market order may take
+    pub max_depth_fraction: f64,
+    // Max |quantity| * price held per symbol
+    pub max_position_notional: f64,
+    // Max quantity * price of a single order
+    pub max_order_notional: f64,
+    // Max share of equity held in one symbol (its notional on every venue
+    // over equity); orders that shrink the holding always pass
+    pub max_symbol_concentration_pct: Option<f64>,
+    // Max |signed notional| held across a symbol and every symbol whose
+    // returns correlate with it at correlation_threshold or more, so
+    // correlated positions count as one; needs [correlation] enabled
+    pub max_correlated_exposure: Option<f64>,
+    pub correlation_threshold: f64,
+    // Max sum of |position notional| across all symbols
+    pub max_gross_exposure: f64,
+    // Max |sum of signed position notional| across all symbols
+    pub max_net_exposure: f64,
+    // Halt when equity falls this far below its intraday high-water mark
+    pub max_drawdown_pct: f64,
+    // Most orders left working on the book across all symbols, and per
+    // symbol; market, IOC and FOK orders don't rest and aren't limited
+    pub max_open_orders_total: usize,
+    pub max_open_orders_per_symbol: usize,
+    // Protect every open position with a resting stop-market order at the
+    // stop distance from its average price
+    pub place_stop_orders: bool,
+    // With place_stop_orders, rest the stop as one leg of an OCO bracket
+    // whose other leg takes profit at take_profit_pct
+    pub place_take_profit_orders: bool,
+    // Have the position monitor close positions with a market order at
+    // whichever of the stop and take-profit levels no resting order holds
+    pub monitor_exits: bool,
+}
+
+impl Default for RiskParams {
+    fn default() -> Self {
+        Self {
+            max_position_size: 1000.0,
+            max_loss_per_trade: 100.0,
+            max_daily_loss: 500.0,
+            stop_loss_pct: 0.02, // 2%
+            stop_mode: StopMode::Percent,
+            take_profit_pct: 0.04, // 4%
+            trailing_stop_pct: None,
+            max_price_deviation_pct: 0.05, // 5%
+            max_depth_fraction: 0.5,
+            max_position_notional: 50_000.0,
+            max_order_notional: 10_000.0,
+            max_symbol_concentration_pct: None,
+            max_correlated_exposure: None,
+            correlation_threshold: 0.8,
+            max_gross_exposure: 100_000.0,
+            max_net_exposure: 50_000.0,
+            max_drawdown_pct: 0.10, // 10%
+            max_open_orders_total: 200,
+            max_open_orders_per_symbol: 50,
+            place_stop_orders: false,
+            place_take_profit_orders: false,
+            monitor_exits: false,
+        }
+    }
+}
+
+impl RiskParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if let StopMode::AtrMultiple { multiple } = self.stop_mode
+            && !(multiple.is_finite() && multiple > 0.0)
+        {
+            return Err("ATR stop multiple must be positive".to_string());
+        }
+        if self
+            .trailing_stop_pct
+            .is_some_and(|pct| !(pct > 0.0 && pct < 1.0))
+        {
+            return Err("Trailing stop pct must be between 0 and 1".to_string());
+        }
+        if self
+            .max_symbol_concentration_pct
+            .is_some_and(|pct| pct <= 0.0)
+        {
+            return Err("Max symbol concentration pct must be positive".to_string());
+        }
+        if self
+            .max_correlated_exposure
+            .is_some_and(|limit| limit <= 0.0)
+        {
+            return Err("Max correlated exposure must be positive".to_string());
+        }
+        if !(self.correlation_threshold > 0.0 && self.correlation_threshold <= 1.0) {
+            return Err("Correlation threshold must be in (0, 1]".to_string());
+        }
+        Ok(())
+    }
+
+    /// Distance from `price` to the stop, given the symbol's ATR if known.
+    /// Protective stops and the max_loss_per_trade check both use it.
+    pub fn stop_distance(&self, price: f64, atr: Option<f64>) -> f64 {
+        match (self.stop_mode, atr) {
+            (StopMode::AtrMultiple { multiple }, Some(atr)) => multiple * atr,
+            _ => price * self.stop_loss_pct,
+        }
+    }
+}
+
+// Why the risk manager refused an order
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskRejection {
+    DailyLossLimit {
+        daily_pnl: f64,
+        limit: f64,
+    },
+    PositionLimit {
+        resulting_quantity: f64,
+        limit: f64,
+    },
+    PotentialLoss {
+        potential_loss: f64,
+        limit: f64,
+    },
+    OrderNotional {
+        notional: f64,
+        limit: f64,
+    },
+    PositionNotional {
+        notional: f64,
+        limit: f64,
+    },
+    GrossExposure {
+        exposure: f64,
+        limit: f64,
+    },
+    NetExposure {
+        exposure: f64,
+        limit: f64,
+    },
+    NoMarketData,
+    PriceDeviation {
+        price: f64,
+        mid: f64,
+        max_deviation_pct: f64,
+    },
+    InsufficientDepth {
+        notional: f64,
+        top_of_book_notional: f64,
+        max_fraction: f64,
+    },
+    InsufficientBuyingPower {
+        asset: String,
+        required: f64,
+        available: f64,
+    },
+    OpenOrders {
+        open: usize,
+        limit: usize,
+    },
+    SymbolOpenOrders {
+        symbol: String,
+        open: usize,
+        limit: usize,
+    },
+    Concentration {
+        symbol: String,
+        // Share of equity the symbol would make up
+        concentration: f64,
+        limit: f64,
+    },
+    // The symbol and those correlated with it would hold this much
+    CorrelatedExposure {
+        symbol: String,
+        // Symbols counted with it
+        correlated: Vec<String>,
+        exposure: f64,
+        limit: f64,
+    },
+    // A hedge-mode order that would take its leg past flat
+    LegFlip {
+        side: PositionSide,
+        resulting_quantity: f64,
+    },
+    // Initial margin on perps, with the order's, would pass the share of
+    // equity it may take up
+    InsufficientMargin {
+        required: f64,
+        available: f64,
+    },
+    // The order would take a strategy past its allocation budget
+    StrategyBudget(BudgetExceeded),
+}
+
+impl std::fmt::Display for RiskRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RiskRejection::DailyLossLimit { daily_pnl, limit } => write!(
+                f,
+                "Daily loss limit exceeded (pnl {:.2}, limit {:.2})",
+                daily_pnl, limit
+            ),
+            RiskRejection::PositionLimit {
+                resulting_quantity,
+                limit,
+            } => write!(
+                f,
+                "Position size limit exceeded ({:.4} > {:.4})",
+                resulting_quantity, limit
+            ),
+            RiskRejection::PotentialLoss {
+                potential_loss,
+                limit,
+            } => write!(
+                f,
+                "Potential loss too high ({:.2} > {:.2})",
+                potential_loss, limit
+            ),
+            RiskRejection::OrderNotional { notional, limit } => write!(
+                f,
+                "Order notional too high ({:.2} > {:.2})",
+                notional, limit
+            ),
+            RiskRejection::PositionNotional { notional, limit } => write!(
+                f,
+                "Position notional limit exceeded ({:.2} > {:.2})",
+                notional, limit
+            ),
+            RiskRejection::GrossExposure { exposure, limit } => write!(
+                f,
+                "Gross exposure limit exceeded ({:.2} > {:.2})",
+                exposure, limit
+            ),
+            RiskRejection::NetExposure { exposure, limit } => write!(
+                f,
+                "Net exposure limit exceeded ({:.2} > {:.2})",
+                exposure, limit
+            ),
+            RiskRejection::NoMarketData => write!(f, "No market data to check the order against"),
+            RiskRejection::PriceDeviation {
+                price,
+                mid,
+                max_deviation_pct,
+            } => write!(
+                f,
+                "Limit price {:.4} deviates more than {:.2}% from mid {:.4}",
+                price,
+                max_deviation_pct * 100.0,
+                mid
+            ),
+            RiskRejection::InsufficientDepth {
+                notional,
+                top_of_book_notional,
+                max_fraction,
+            } => write!(
+                f,
+                "Market order notional {:.2} exceeds {:.0}% of top-of-book notional {:.2}",
+                notional,
+                max_fraction * 100.0,
+                top_of_book_notional
+            ),
+            RiskRejection::InsufficientBuyingPower {
+                asset,
+                required,
+                available,
+            } => write!(
+                f,
+                "Insufficient buying power in {} ({:.2} required, {:.2} available)",
+                asset, required, available
+            ),
+            RiskRejection::OpenOrders { open, limit } => {
+                write!(
+                    f,
+                    "Too many open orders ({} working, limit {})",
+                    open, limit
+                )
+            }
+            RiskRejection::SymbolOpenOrders {
+                symbol,
+                open,
+                limit,
+            } => write!(
+                f,
+                "Too many open orders on {} ({} working, limit {})",
+                symbol, open, limit
+            ),
+            RiskRejection::Concentration {
+                symbol,
+                concentration,
+                limit,
+            } => write!(
+                f,
+                "{} would be {:.2}% of equity (limit {:.2}%)",
+                symbol,
+                concentration * 100.0,
+                limit * 100.0
+            ),
+            RiskRejection::CorrelatedExposure {
+                symbol,
+                correlated,
+                exposure,
+                limit,
+            } => write!(
+                f,
+                "Correlated exposure of {} with [{}] too high ({:.2} > {:.2})",
+                symbol,
+                correlated.join(", "),
+                exposure,
+                limit
+            ),
+            RiskRejection::LegFlip {
+                side,
+                resulting_quantity,
+            } => write!(
+                f,
+                "Order would take the {} leg past flat ({:.4})",
+                side, resulting_quantity
+            ),
+            RiskRejection::InsufficientMargin {
+                required,
+                available,
+            } => write!(
+                f,
+                "Insufficient margin ({:.2} required, {:.2} available)",
+                required, available
+            ),
+            RiskRejection::StrategyBudget(exceeded) => write!(f, "Strategy {}", exceeded),
+        }
+    }
+}
+
+impl std::error::Error for RiskRejection {}
+
+// Notable risk state changes, broadcast to anyone subscribed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RiskEvent {
+    DrawdownBreached {
+        equity: f64,
+        peak: f64,
+        drawdown_pct: f64,
+        limit_pct: f64,
+    },
+    DailyRollover {
+        day: u64,
+        daily_pnl: f64,
+    },
+    // The symbol moved abnormally far, so new entries on it are paused
+    VolatilityHalt {
+        symbol: String,
+        move_pct: f64,
+        z_score: Option<f64>,
+        cooldown_secs: u64,
+    },
+    VolatilityResumed {
+        symbol: String,
+    },
+}
+
+// Strategy trait for different trading strategies
+pub trait TradingStrategy: Send + Sync {
+    fn analyze(&self, prices: &[Price], orderbook: &OrderBook) -> Option<TradingSignal>;
+    fn name(&self) -> &str;
+
+    // Rolling window lengths this strategy reads from the IndicatorHub
+    fn indicator_windows(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    // Order-flow imbalances this strategy reads from the IndicatorHub
+    fn ofi_specs(&self) -> Vec<OfiSpec> {
+        Vec::new()
+    }
+
+    // O(1) entry point reading the symbol's incrementally-updated indicators.
+    // Strategies that don't use indicators fall back to the slice-based analyze.
+    fn analyze_incremental(
+        &self,
+        prices: &[Price],
+        _indicators: &SymbolIndicators,
+        orderbook: &OrderBook,
+    ) -> Option<TradingSignal> {
+        self.analyze(prices, orderbook)
+    }
+
+    // Candle timeframes this strategy reads from the CandleAggregator
+    fn candle_timeframes(&self) -> Vec<Timeframe> {
+        Vec::new()
+    }
+
+    // Entry point for bar-based strategies, given the symbol's candles on
+    // every timeframe and how old its data is. Tick-based strategies fall
+    // back to analyze_incremental.
+    fn analyze_candles(
+        &self,
+        prices: &[Price],
+        indicators: &SymbolIndicators,
+        _candles: &SymbolCandles,
+        _data_age: DataAge,
+        orderbook: &OrderBook,
+    ) -> Option<TradingSignal> {
+        self.analyze_incremental(prices, indicators, orderbook)
+    }
+
+    // Told when the bot closes a position this strategy opened, as the
+    // closing order goes out
+    fn on_position(&self, _position: &Position, _exit: ExitReason) {}
+
+    // Told of each fill on an order this strategy placed, as it's booked
+    fn on_fill(&self, _fill: &Fill) {}
+
+    // Asked after each evaluation of the symbol: true has the bot cancel
+    // the orders this strategy has resting there before any new order goes
+    // out. Only orders working on the paper executor are found.
+    fn cancel_working(&self, _symbol: &str) -> bool {
+        false
+    }
+}
+
+// Simple momentum strategy implementation
+pub struct MomentumStrategy {
+    lookback_period: usize,
+    momentum_threshold: f64,
+    min_volume: f64,
+    base_quantity: f64,
+}
+
+impl MomentumStrategy {
+    pub const DEFAULT_MIN_VOLUME: f64 = 1000.0;
+    pub const DEFAULT_BASE_QUANTITY: f64 = 100.0;
+
+    pub fn new(lookback_period: usize, momentum_threshold: f64) -> Result<Self, String> {
+        Self::with_params(
+            lookback_period,
+            momentum_threshold,
+            Self::DEFAULT_MIN_VOLUME,
+            Self::DEFAULT_BASE_QUANTITY,
+        )
+    }
+
+    // Momentum needs two points to compare, so lookback_period must be at
+    // least 2
+    pub fn with_params(
+        lookback_period: usize,
+        momentum_threshold: f64,
+        min_volume: f64,
+        base_quantity: f64,
+    ) -> Result<Self, String> {
+        if lookback_period < 2 {
+            return Err(format!(
+                "MomentumStrategy lookback_period must be at least 2, got {}",
+                lookback_period
+            ));
+        }
+        Ok(Self {
+            lookback_period,
+            momentum_threshold,
+            min_volume,
+            base_quantity,
+        })
+    }
+
+    fn signal_from(
+        &self,
+        symbol: impl Into<Arc<str>>,
+        newest: f64,
+        oldest: f64,
+        volume_avg: f64,
+    ) -> Option<TradingSignal> {
+        // A zero (or garbage) reference price would give an infinite change
+        if oldest <= 0.0 || !oldest.is_finite() || !newest.is_finite() {
+            return None;
+        }
+        let price_change = (newest - oldest) / oldest;
+
+        if price_change.abs() > self.momentum_threshold && volume_avg > self.min_volume {
+            let action = if price_change > 0.0 {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            };
+
+            return Some(TradingSignal {
+                symbol: symbol.into(),
+                action,
+                confidence: price_change.abs().min(1.0),
+                target_price: newest,
+                quantity: self.base_quantity,
+                time_in_force: TimeInForce::Gtc,
+                limit_price: None,
+            });
+        }
+
+        None
+    }
+}
+
+impl TradingStrategy for MomentumStrategy {
+    fn analyze(&self, prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
+        // The lookback window, read in place
+        let window = &prices[prices.len().checked_sub(self.lookback_period)?..];
+        let volume_avg =
+            window.iter().rev().map(|p| p.volume).sum::<f64>() / self.lookback_period as f64;
+
+        self.signal_from(
+            prices[0].symbol.as_str(),
+            window.last()?.price,
+            window.first()?.price,
+            volume_avg,
+        )
+    }
+
+    fn name(&self) -> &str {
+        "MomentumStrategy"
+    }
+
+    fn indicator_windows(&self) -> Vec<usize> {
+        vec![self.lookback_period]
+    }
+
+    fn analyze_incremental(
+        &self,
+        prices: &[Price],
+        indicators: &SymbolIndicators,
+        orderbook: &OrderBook,
+    ) -> Option<TradingSignal> {
+        let (Some(price_window), Some(volume_window)) = (
+            indicators.price(self.lookback_period),
+            indicators.volume(self.lookback_period),
+        ) else {
+            return self.analyze(prices, orderbook);
+        };
+
+        if !price_window.is_full() {
+            return None;
+        }
+
+        self.signal_from(
+            indicators.symbol().clone(),
+            price_window.newest()?,
+            price_window.oldest()?,
+            volume_window.mean()?,
+        )
+    }
+}
+
+// Mean reversion strategy
+pub struct MeanReversionStrategy {
+    lookback_period: usize,
+    deviation_threshold: f64,
+}
+
+impl MeanReversionStrategy {
+    pub fn new(lookback_period: usize, deviation_threshold: f64) -> Self {
+        Self {
+            lookback_period,
+            deviation_threshold,
+        }
+    }
+}
+
+impl MeanReversionStrategy {
+    fn signal_from(
+        &self,
+        symbol: impl Into<Arc<str>>,
+        current_price: f64,
+        mean: f64,
+    ) -> Option<TradingSignal> {
+        let deviation = (current_price - mean) / mean;
+
+        if deviation.abs() > self.deviation_threshold {
+            let action = if deviation > 0.0 {
+                OrderSide::Sell // Price above mean, sell
+            } else {
+                OrderSide::Buy // Price below mean, buy
+            };
+
+            return Some(TradingSignal {
+                symbol: symbol.into(),
+                action,
+                confidence: deviation.abs().min(1.0),
+                target_price: mean,
+                quantity: 50.0,
+                time_in_force: TimeInForce::Gtc,
+                limit_price: None,
+            });
+        }
+
+        None
+    }
+}
+
+impl TradingStrategy for MeanReversionStrategy {
+    fn analyze(&self, prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
+        // The lookback window, read in place
+        let window = &prices[prices.len().checked_sub(self.lookback_period)?..];
+        let current_price = window.last()?.price;
+        let mean = window.iter().rev().map(|p| p.price).sum::<f64>() / window.len() as f64;
+
+        self.signal_from(prices[0].symbol.as_str(), current_price, mean)
+    }
+
+    fn name(&self) -> &str {
+        "MeanReversionStrategy"
+    }
+
+    fn indicator_windows(&self) -> Vec<usize> {
+        vec![self.lookback_period]
+    }
+
+    fn analyze_incremental(
+        &self,
+        prices: &[Price],
+        indicators: &SymbolIndicators,
+        orderbook: &OrderBook,
+    ) -> Option<TradingSignal> {
+        let Some(window) = indicators.price(self.lookback_period) else {
+            return self.analyze(prices, orderbook);
+        };
+
+        if !window.is_full() {
+            return None;
+        }
+
+        self.signal_from(
+            indicators.symbol().clone(),
+            window.newest()?,
+            window.mean()?,
+        )
+    }
+}
+
+// Risk manager
+pub struct RiskManager {
+    params: RiskParams,
+    symbol_params: RwLock<HashMap<String, RiskParams>>, // Per-symbol overrides of params
+    // The trading day's PnL, fees, trades and equity peak
+    session: Arc<SessionTracker>,
+    // By (venue, symbol); a symbol is marked at one price on every venue
+    positions: PositionBook,
+    marks: Arc<MarkPriceCache>,
+    correlations: Arc<CorrelationTracker>,
+    // Turns amounts in each quote asset into the reporting currency, which
+    // limits, equity and the session's PnL are in
+    currency: Arc<CurrencyConverter>,
+    // Each symbol's latest warmed-up ATR, for ATR-multiple stops
+    atrs: RwLock<HashMap<String, f64>>,
+    account: Mutex<Account>,
+    // Whether the drawdown breaker has tripped this session
+    drawdown_tripped: Mutex<bool>,
+    // The session's fills, rolled over with it
+    blotter: Mutex<Blotter>,
+    // Blotters of sessions rolled over, with their summaries, until taken
+    // for their reports
+    closed_days: Mutex<Vec<(Blotter, SessionSummary)>>,
+    // The executor's working orders, for the open order limits
+    open_orders: Arc<OpenOrderTracker>,
+    events: broadcast::Sender<RiskEvent>,
+    // Decides when the trading day rolls over
+    clock: Arc<dyn Clock>,
+}
+
+impl RiskManager {
+    pub fn new(params: RiskParams) -> Self {
+        Self::with_symbol_params(params, HashMap::new())
+    }
+
+    pub fn with_symbol_params(
+        params: RiskParams,
+        symbol_params: HashMap<String, RiskParams>,
+    ) -> Self {
+        let (events, _) = broadcast::channel(64);
+        let account = Account::default();
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let session =
+            SessionTracker::new(&SessionConfig::default(), clock.now_secs(), account.cash());
+        Self {
+            drawdown_tripped: Mutex::new(false),
+            blotter: Mutex::new(Blotter::new(session.session())),
+            closed_days: Mutex::new(Vec::new()),
+            open_orders: Arc::new(OpenOrderTracker::default()),
+            params,
+            symbol_params: RwLock::new(symbol_params),
+            session: Arc::new(session),
+            positions: Arc::new(RwLock::new(HashMap::new())),
+            marks: Arc::new(MarkPriceCache::default()),
+            correlations: Arc::new(CorrelationTracker::default()),
+            currency: Arc::new(CurrencyConverter::default()),
+            atrs: RwLock::new(HashMap::new()),
+            account: Mutex::new(account),
+            events,
+            clock,
+        }
+    }
+
+    // Replaces the system clock; the current trading day is taken from it
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self.restart_session();
+        self
+    }
+
+    // Rolls the trading day over at midnight at the configured UTC offset
+    pub fn with_session(mut self, config: &SessionConfig) -> Self {
+        self.session = Arc::new(SessionTracker::new(config, self.clock.now_secs(), 0.0));
+        self.restart_session();
+        self
+    }
+
+    // A fresh session, as of the clock and account now
+    fn restart_session(&mut self) {
+        // Nothing else holds the account while the bot is being built
+        let cash = match self.account.try_lock() {
+            Ok(account) => self.cash_locked(&account),
+            Err(_) => 0.0,
+        };
+        self.session.start(self.clock.now_secs(), cash);
+        *self.blotter.get_mut() = Blotter::new(self.session.session());
+    }
+
+    // Counts open orders against the limits as the executor sees them
+    pub fn with_open_orders(mut self, open_orders: Arc<OpenOrderTracker>) -> Self {
+        self.open_orders = open_orders;
+        self
+    }
+
+    // The positions as booked, kept current as fills come in
+    pub fn position_book(&self) -> PositionBook {
+        Arc::clone(&self.positions)
+    }
+
+    // Shares a mark price cache with the market data tasks that feed it
+    pub fn with_marks(mut self, marks: Arc<MarkPriceCache>) -> Self {
+        self.marks = marks;
+        self
+    }
+
+    // Shares the correlations the market data tasks keep up to date
+    pub fn with_correlations(mut self, correlations: Arc<CorrelationTracker>) -> Self {
+        self.correlations = correlations;
+        self
+    }
+
+    // Converts into the converter's reporting currency; the drawdown peak
+    // starts at the cash in it
+    pub fn with_currency(mut self, currency: Arc<CurrencyConverter>) -> Self {
+        self.currency = currency;
+        self.restart_session();
+        self
+    }
+
+    // Replaces the default account; the drawdown peak starts at its cash
+    pub fn with_account(mut self, account: Account) -> Self {
+        self.account = Mutex::new(account);
+        self.restart_session();
+        self
+    }
+
+    // Default params, which also govern the portfolio-wide limits
+    pub fn params(&self) -> &RiskParams {
+        &self.params
+    }
+
+    // Params governing a symbol: its override if set, otherwise the defaults
+    pub async fn params_for(&self, symbol: &str) -> RiskParams {
+        self.symbol_params
+            .read()
+            .await
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| self.params.clone())
+    }
+
+    // Installs or replaces a symbol's override at runtime
+    pub async fn set_symbol_params(&self, symbol: &str, params: RiskParams) {
+        println!("Risk params for {} updated: {:?}", symbol, params);
+        self.symbol_params
+            .write()
+            .await
+            .insert(symbol.to_string(), params);
+    }
+
+    // Drops a symbol's override so it falls back to the defaults
+    pub async fn clear_symbol_params(&self, symbol: &str) {
+        self.symbol_params.write().await.remove(symbol);
+    }
+
+    pub fn subscribe_events(&self) -> broadcast::Receiver<RiskEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: RiskEvent) {
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    // Cash plus open positions marked at the latest price (cost if no price
+    // has been seen)
+    pub async fn equity(&self) -> f64 {
+        let positions = self.positions.read().await;
+        let account = self.account.lock().await;
+        self.equity_locked(&account, &positions)
+    }
+
+    // Equity now, with the positions' PnL and gross notional at their marks
+    pub async fn equity_point(&self) -> EquityPoint {
+        let positions = self.positions.read().await;
+        let account = self.account.lock().await;
+        let mut point = EquityPoint {
+            timestamp: self.clock.now_secs(),
+            equity: self.equity_locked(&account, &positions),
+            ..EquityPoint::default()
+        };
+        for position in positions.values() {
+            let mark = self
+                .marks
+                .price(position.symbol.as_str())
+                .unwrap_or(position.avg_price);
+            let rate = self.quote_rate(&account, position.symbol.as_str());
+            point.realized_pnl += position.realized_pnl * rate;
+            point.unrealized_pnl += position.quantity * (mark - position.avg_price) * rate;
+            point.gross_exposure += (position.quantity * mark * rate).abs();
+        }
+        point
+    }
+
+    // What one of the symbol's quote asset is worth in the reporting
+    // currency
+    fn quote_rate(&self, account: &Account, symbol: &str) -> f64 {
+        self.currency
+            .rate_or_par(account.quote_for(symbol), &self.marks)
+    }
+
+    // Every cash balance, in the reporting currency
+    fn cash_locked(&self, account: &Account) -> f64 {
+        account
+            .balances()
+            .iter()
+            .map(|(asset, balance)| self.currency.convert(asset, *balance, &self.marks))
+            .fold(0.0, |total, cash| total + cash)
+    }
+
+    fn equity_locked(&self, account: &Account, positions: &HashMap<PositionKey, Position>) -> f64 {
+        let marked: f64 = positions
+            .values()
+            .map(|p| {
+                Self::marked_value(account, p, &self.marks)
+                    * self.quote_rate(account, p.symbol.as_str())
+            })
+            .sum();
+        self.cash_locked(account) + marked
+    }
+
+    // What a position adds to equity at its mark: its market value when
+    // it was paid for in cash, or its unrealized PnL for a perp, which was
+    // only margined
+    fn marked_value(account: &Account, position: &Position, marks: &MarkPriceCache) -> f64 {
+        let mark = marks
+            .price(position.symbol.as_str())
+            .unwrap_or(position.avg_price);
+        if account.is_perp(position.symbol.as_str()) {
+            position.quantity * (mark - position.avg_price)
+        } else {
+            position.quantity * mark
+        }
+    }
+
+    // Initial margin the perp positions tie up, at their marks, in the
+    // reporting currency
+    fn used_margin(&self, account: &Account, positions: &HashMap<PositionKey, Position>) -> f64 {
+        positions
+            .values()
+            .filter(|p| account.is_perp(p.symbol.as_str()))
+            .map(|p| {
+                let mark = self.marks.price(p.symbol.as_str()).unwrap_or(p.avg_price);
+                account.margin().initial_margin(p.quantity * mark)
+                    * self.quote_rate(account, p.symbol.as_str())
+            })
+            .sum()
+    }
+
+    pub async fn balances(&self) -> HashMap<String, f64> {
+        self.account.lock().await.balances().clone()
+    }
+
+    // Overwrites local balances with ones fetched from the exchange
+    pub async fn reconcile_balances(&self, balances: HashMap<String, f64>) {
+        self.account.lock().await.reconcile(balances);
+    }
+
+    // Takes balances a venue pushed for the cash assets the account holds
+    pub async fn update_balances(&self, balances: &HashMap<String, f64>) {
+        self.account.lock().await.update_balances(balances);
+    }
+
+    // How much more gross notional the account can take on in the symbol's
+    // quote asset: leverage x equity in that asset, less what's already held.
+    // Perps hold their initial margin instead.
+    pub async fn buying_power(&self, symbol: &str) -> f64 {
+        let positions = self.positions.read().await;
+        let account = self.account.lock().await;
+        Self::buying_power_locked(&account, &positions, &self.marks, account.quote_for(symbol))
+    }
+
+    fn buying_power_locked(
+        account: &Account,
+        positions: &HashMap<PositionKey, Position>,
+        marks: &MarkPriceCache,
+        quote: &str,
+    ) -> f64 {
+        let mut equity = account.balance(quote);
+        let mut gross = 0.0;
+        for position in positions.values() {
+            if account.quote_for(position.symbol.as_str()) != quote {
+                continue;
+            }
+            let mark = marks
+                .price(position.symbol.as_str())
+                .unwrap_or(position.avg_price);
+            equity += Self::marked_value(account, position, marks);
+            gross += if account.is_perp(position.symbol.as_str()) {
+                account.margin().initial_margin(position.quantity * mark)
+            } else {
+                position.quantity.abs() * mark
+            };
+        }
+        equity * account.leverage() - gross
+    }
+
+    // Marks the equity curve at the current equity
+    pub async fn update_equity(&self) -> Option<RiskEvent> {
+        let equity = self.equity().await;
+        self.mark_equity(equity).await
+    }
+
+    /// Marks the equity curve, rolling the session over once its day is
+    /// done. Returns the breach event the first time drawdown from the
+    /// session's peak exceeds max_drawdown_pct; the breaker then stays
+    /// tripped until reset_drawdown() or the next rollover.
+    pub async fn mark_equity(&self, equity: f64) -> Option<RiskEvent> {
+        let mut tripped = self.drawdown_tripped.lock().await;
+
+        if let Some(summary) = self.session.roll(self.clock.now_secs(), equity) {
+            self.emit(RiskEvent::DailyRollover {
+                day: summary.stats.session,
+                daily_pnl: summary.stats.realized_pnl,
+            });
+            let closed = std::mem::replace(
+                &mut *self.blotter.lock().await,
+                Blotter::new(self.session.session()),
+            );
+            self.closed_days.lock().await.push((closed, summary));
+            *tripped = false;
+        }
+
+        let peak = self.session.mark_equity(equity);
+        if *tripped || peak <= 0.0 {
+            return None;
+        }
+
+        let drawdown_pct = (peak - equity) / peak;
+        if drawdown_pct > self.params.max_drawdown_pct {
+            *tripped = true;
+            let event = RiskEvent::DrawdownBreached {
+                equity,
+                peak,
+                drawdown_pct,
+                limit_pct: self.params.max_drawdown_pct,
+            };
+            self.emit(event.clone());
+            return Some(event);
+        }
+
+        None
+    }
+
+    // Re-arms the drawdown breaker with the current equity as the new peak
+    pub async fn reset_drawdown(&self) {
+        let equity = self.equity().await;
+        let mut tripped = self.drawdown_tripped.lock().await;
+        self.session.reset_peak(equity);
+        *tripped = false;
+    }
+
+    // The trading day's counters, which daily_pnl() reads
+    pub fn session(&self) -> &Arc<SessionTracker> {
+        &self.session
+    }
+
+    // Latest known price per symbol, fed by the market data tasks
+    pub fn marks(&self) -> &Arc<MarkPriceCache> {
+        &self.marks
+    }
+
+    // Rolling correlations between the symbols' returns
+    pub fn correlations(&self) -> &Arc<CorrelationTracker> {
+        &self.correlations
+    }
+
+    pub fn currency(&self) -> &Arc<CurrencyConverter> {
+        &self.currency
+    }
+
+    // The symbol's quote asset, and what one of it is worth in the
+    // reporting currency now
+    pub async fn quote_conversion(&self, symbol: &str) -> (String, f64) {
+        let account = self.account.lock().await;
+        (
+            account.quote_for(symbol).to_string(),
+            self.quote_rate(&account, symbol),
+        )
+    }
+
+    pub async fn mark_price(&self, symbol: &str) -> Option<f64> {
+        self.marks.price(symbol)
+    }
+
+    /// Brings every position's unrealized PnL up to date with its mark.
+    /// Positions without a mark yet are left as they are.
+    pub async fn update_unrealized_pnl(&self) {
+        for position in self.positions.write().await.values_mut() {
+            if let Some(mark) = self.marks.price(position.symbol.as_str()) {
+                position.unrealized_pnl = position.quantity * (mark - position.avg_price);
+            }
+        }
+    }
+
+    // Latest ATR per symbol, fed by the market data tasks as bars close
+    pub async fn update_atr(&self, symbol: &str, atr: f64) {
+        self.atrs.write().await.insert(symbol.to_string(), atr);
+    }
+
+    // The symbol's stop distance from `price` under its params
+    pub async fn stop_distance(&self, symbol: &str, price: f64) -> f64 {
+        let atr = self.atrs.read().await.get(symbol).copied();
+        self.params_for(symbol).await.stop_distance(price, atr)
+    }
+
+    pub async fn validate_order(
+        &self,
+        order: &Order,
+        current_price: f64,
+        orderbook: &OrderBook,
+    ) -> Result<(), RiskRejection> {
+        self.check_order(order, None, current_price, orderbook)
+            .await
+    }
+
+    /// validate_order for an order sent to take the place of the working
+    /// order `replaces` (a cancel/replace), whose slot it takes under the
+    /// open order limits
+    pub async fn validate_replacement(
+        &self,
+        order: &Order,
+        replaces: &str,
+        current_price: f64,
+        orderbook: &OrderBook,
+    ) -> Result<(), RiskRejection> {
+        self.check_order(order, Some(replaces), current_price, orderbook)
+            .await
+    }
+
+    async fn check_order(
+        &self,
+        order: &Order,
+        replaces: Option<&str>,
+        current_price: f64,
+        orderbook: &OrderBook,
+    ) -> Result<(), RiskRejection> {
+        // Per-symbol limits come from the symbol's params; portfolio-wide
+        // limits (daily loss, exposure, drawdown) always use the defaults
+        let params = self.params_for(order.symbol.as_str()).await;
+        let daily_pnl = self.session.realized_pnl();
+
+        // Check daily loss limit
+        if daily_pnl < -self.params.max_daily_loss {
+            return Err(RiskRejection::DailyLossLimit {
+                daily_pnl,
+                limit: self.params.max_daily_loss,
+            });
+        }
+
+        self.check_open_orders(&params, order, replaces)?;
+
+        // Notional limits are in the reporting currency: the order's is
+        // converted from its quote asset, and every other position's from
+        // its own
+        let rate = {
+            let account = self.account.lock().await;
+            self.quote_rate(&account, order.symbol.as_str())
+        };
+
+        // Check order notional
+        let order_notional = order.quantity * current_price;
+        if order_notional * rate > params.max_order_notional {
+            return Err(RiskRejection::OrderNotional {
+                notional: order_notional * rate,
+                limit: params.max_order_notional,
+            });
+        }
+
+        // Check position size on the order's venue, treating a symbol we've
+        // never traded there as flat
+        let positions = self.positions.read().await;
+        let key = order.position_key();
+        let current_quantity = positions
+            .get(&key)
+            .map_or(0.0, |position| position.quantity);
+        let new_quantity = match order.side {
+            OrderSide::Buy => current_quantity + order.quantity,
+            OrderSide::Sell => current_quantity - order.quantity,
+        };
+
+        // A hedge-mode leg only ever holds its own side
+        let flipped = match order.position_side {
+            PositionSide::Both => false,
+            PositionSide::Long => new_quantity < -f64::EPSILON,
+            PositionSide::Short => new_quantity > f64::EPSILON,
+        };
+        if flipped {
+            return Err(RiskRejection::LegFlip {
+                side: order.position_side,
+                resulting_quantity: new_quantity,
+            });
+        }
+
+        if new_quantity.abs() > params.max_position_size {
+            return Err(RiskRejection::PositionLimit {
+                resulting_quantity: new_quantity,
+                limit: params.max_position_size,
+            });
+        }
+
+        let position_notional = new_quantity.abs() * current_price * rate;
+        if position_notional > params.max_position_notional {
+            return Err(RiskRejection::PositionNotional {
+                notional: position_notional,
+                limit: params.max_position_notional,
+            });
+        }
+
+        let account = self.account.lock().await;
+        if let Some(limit) = params.max_symbol_concentration_pct {
+            self.check_concentration(&positions, &account, order, current_price * rate, limit)?;
+        }
+        if let Some(limit) = self.params.max_correlated_exposure {
+            self.check_correlated_exposure(
+                &positions,
+                &account,
+                order,
+                current_price * rate,
+                limit,
+            )?;
+        }
+
+        // Check portfolio exposure with the candidate order applied. Other
+        // positions, on every venue, are marked at their latest price,
+        // falling back to cost.
+        let mut gross_exposure = new_quantity.abs() * current_price * rate;
+        let mut net_exposure = new_quantity * current_price * rate;
+        for (position_key, position) in positions.iter() {
+            if *position_key == key {
+                continue;
+            }
+            let mark = self
+                .marks
+                .price(position.symbol.as_str())
+                .unwrap_or(position.avg_price)
+                * self.quote_rate(&account, position.symbol.as_str());
+            gross_exposure += position.quantity.abs() * mark;
+            net_exposure += position.quantity * mark;
+        }
+
+        // Check buying power, or margin for a perp. Only the part of the
+        // order that grows the position needs capital; reducing a position
+        // (and the fee on it) is always allowed so risk can be taken off.
+        // Buying power is in the order's quote asset, as the cash is.
+        let added_notional = (new_quantity.abs() - current_quantity.abs()).max(0.0) * current_price;
+        if added_notional > 0.0 && account.is_perp(order.symbol.as_str()) {
+            let margin = account.margin();
+            let available = self.equity_locked(&account, &positions) * margin.max_margin_usage;
+            let required = self.used_margin(&account, &positions)
+                + margin.initial_margin(added_notional) * rate;
+            if required > available {
+                return Err(RiskRejection::InsufficientMargin {
+                    required,
+                    available,
+                });
+            }
+        } else if added_notional > 0.0 {
+            let quote = account.quote_for(order.symbol.as_str());
+            let available = Self::buying_power_locked(&account, &positions, &self.marks, quote);
+            let fee = account
+                .fees()
+                .fee(order.symbol.as_str(), order_notional, Liquidity::Taker);
+            let required = added_notional + fee;
+            if required > available {
+                return Err(RiskRejection::InsufficientBuyingPower {
+                    asset: quote.to_string(),
+                    required,
+                    available,
+                });
+            }
+        }
+        drop(account);
+        drop(positions);
+
+        if gross_exposure > self.params.max_gross_exposure {
+            return Err(RiskRejection::GrossExposure {
+                exposure: gross_exposure,
+                limit: self.params.max_gross_exposure,
+            });
+        }
+        if net_exposure.abs() > self.params.max_net_exposure {
+            return Err(RiskRejection::NetExposure {
+                exposure: net_exposure.abs(),
+                limit: self.params.max_net_exposure,
+            });
+        }
+
+        // Check potential loss, were the order stopped out where its
+        // protective stop would sit
+        let atr = self.atrs.read().await.get(order.symbol.as_str()).copied();
+        let potential_loss = order.quantity * params.stop_distance(current_price, atr) * rate;
+        if potential_loss > params.max_loss_per_trade {
+            return Err(RiskRejection::PotentialLoss {
+                potential_loss,
+                limit: params.max_loss_per_trade,
+            });
+        }
+        Self::check_fat_finger(&params, order, orderbook)
+    }
+
+    // Checks the symbol's notional on every venue, with the order applied,
+    // as a share of equity, both in the reporting currency (as is
+    // `current_price`). A reduce-only order, or any that shrinks the
+    // holding without flipping it, always passes, so exposure can be taken
+    // off.
+    fn check_concentration(
+        &self,
+        positions: &HashMap<PositionKey, Position>,
+        account: &Account,
+        order: &Order,
+        current_price: f64,
+        limit: f64,
+    ) -> Result<(), RiskRejection> {
+        // Capped at the position when it executes, so it can't flip it
+        if order.reduce_only {
+            return Ok(());
+        }
+        let held: f64 = positions
+            .values()
+            .filter(|p| p.symbol == order.symbol)
+            .map(|p| p.quantity)
+            .sum();
+        let resulting = held
+            + match order.side {
+                OrderSide::Buy => order.quantity,
+                OrderSide::Sell => -order.quantity,
+            };
+        if resulting.abs() <= held.abs() && resulting * held >= 0.0 {
+            return Ok(());
+        }
+
+        let equity = self.equity_locked(account, positions);
+        let notional = resulting.abs() * current_price;
+        let concentration = if equity > 0.0 {
+            notional / equity
+        } else {
+            f64::INFINITY
+        };
+        if concentration > limit {
+            return Err(RiskRejection::Concentration {
+                symbol: order.symbol.to_string(),
+                concentration,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    // Checks the signed notional of the order's symbol and every symbol
+    // correlated with it, on every venue, with the order applied: highly
+    // correlated longs (or shorts) are one concentrated bet. Symbols
+    // without a correlation yet count as uncorrelated. An order that
+    // shrinks the group's exposure always passes. Notionals, and
+    // `current_price`, are in the reporting currency.
+    fn check_correlated_exposure(
+        &self,
+        positions: &HashMap<PositionKey, Position>,
+        account: &Account,
+        order: &Order,
+        current_price: f64,
+        limit: f64,
+    ) -> Result<(), RiskRejection> {
+        let threshold = self.params.correlation_threshold;
+        let mut correlated = Vec::new();
+        let mut exposure = 0.0;
+        for position in positions.values() {
+            let mark = if position.symbol == order.symbol {
+                current_price
+            } else if self
+                .correlations
+                .correlation(order.symbol.as_str(), position.symbol.as_str())
+                .is_some_and(|correlation| correlation >= threshold)
+            {
+                if !correlated.contains(&position.symbol) && position.quantity != 0.0 {
+                    correlated.push(position.symbol);
+                }
+                self.marks
+                    .price(position.symbol.as_str())
+                    .unwrap_or(position.avg_price)
+                    * self.quote_rate(account, position.symbol.as_str())
+            } else {
+                continue;
+            };
+            exposure += position.quantity * mark;
+        }
+        let resulting = exposure
+            + match order.side {
+                OrderSide::Buy => order.quantity,
+                OrderSide::Sell => -order.quantity,
+            } * current_price;
+        if resulting.abs() <= exposure.abs() && resulting * exposure >= 0.0 {
+            return Ok(());
+        }
+        if resulting.abs() > limit {
+            correlated.sort();
+            return Err(RiskRejection::CorrelatedExposure {
+                symbol: order.symbol.to_string(),
+                correlated: correlated.iter().map(ToString::to_string).collect(),
+                exposure: resulting.abs(),
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    // Caps how many orders rest on the book, so a runaway strategy can't
+    // blanket it. A replacement frees the slot of the order it replaces.
+    fn check_open_orders(
+        &self,
+        params: &RiskParams,
+        order: &Order,
+        replaces: Option<&str>,
+    ) -> Result<(), RiskRejection> {
+        let rests = !matches!(order.order_type, OrderType::Market)
+            && !matches!(order.time_in_force, TimeInForce::Ioc | TimeInForce::Fok);
+        if !rests {
+            return Ok(());
+        }
+        let (open, open_on_symbol) = self
+            .open_orders
+            .counts_excluding(order.symbol.as_str(), replaces);
+        if open >= params.max_open_orders_total {
+            return Err(RiskRejection::OpenOrders {
+                open,
+                limit: params.max_open_orders_total,
+            });
+        }
+        if open_on_symbol >= params.max_open_orders_per_symbol {
+            return Err(RiskRejection::SymbolOpenOrders {
+                symbol: order.symbol.to_string(),
+                open: open_on_symbol,
+                limit: params.max_open_orders_per_symbol,
+            });
+        }
+        Ok(())
+    }
+
+    // Sanity-checks the order against the current book so a buggy strategy
+    // can't send a limit far through the market or sweep the book
+    fn check_fat_finger(
+        params: &RiskParams,
+        order: &Order,
+        orderbook: &OrderBook,
+    ) -> Result<(), RiskRejection> {
+        match order.order_type {
+            OrderType::Limit => {
+                let mid = orderbook.mid_price().ok_or(RiskRejection::NoMarketData)?;
+                let price = order.price.ok_or(RiskRejection::NoMarketData)?;
+                if (price - mid).abs() / mid > params.max_price_deviation_pct {
+                    return Err(RiskRejection::PriceDeviation {
+                        price,
+                        mid,
+                        max_deviation_pct: params.max_price_deviation_pct,
+                    });
+                }
+            }
+            OrderType::Market => {
+                // A market buy takes the best ask, a market sell the best
+                // bid; anything past the touch would sweep deeper levels
+                let touch = match order.side {
+                    OrderSide::Buy => orderbook.best_ask(),
+                    OrderSide::Sell => orderbook.best_bid(),
+                };
+                let (price, quantity) = touch
+                    .filter(|&(_, quantity)| quantity > 0.0)
+                    .ok_or(RiskRejection::NoMarketData)?;
+                let notional = order.quantity * price;
+                let top_of_book_notional = quantity * price;
+                if notional > top_of_book_notional * params.max_depth_fraction {
+                    return Err(RiskRejection::InsufficientDepth {
+                        notional,
+                        top_of_book_notional,
+                        max_fraction: params.max_depth_fraction,
+                    });
+                }
+            }
+            // Stops sit away from the market by design
+            OrderType::StopMarket { .. } | OrderType::StopLimit { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Caps a reduce-only order at the position it reduces on its venue,
+    /// as [`Order::cap_reduce_only`] does.
+    pub async fn cap_reduce_only(&self, order: &mut Order) -> bool {
+        order.cap_reduce_only(&self.positions).await
+    }
+
+    pub async fn position(
+        &self,
+        venue: &VenueId,
+        symbol: &str,
+        side: PositionSide,
+    ) -> Option<Position> {
+        self.positions
+            .read()
+            .await
+            .get(&(venue.clone(), SymbolId::get(symbol)?, side))
+            .cloned()
+    }
+
+    // Every position, with unrealized PnL at the latest price (zero before
+    // the first tick)
+    pub async fn positions(&self) -> Vec<Position> {
+        self.positions
+            .read()
+            .await
+            .values()
+            .map(|p| Position {
+                unrealized_pnl: self
+                    .marks
+                    .price(p.symbol.as_str())
+                    .map_or(0.0, |mark| p.quantity * (mark - p.avg_price)),
+                ..p.clone()
+            })
+            .collect()
+    }
+
+    pub async fn daily_pnl(&self) -> f64 {
+        self.session.realized_pnl()
+    }
+
+    // The day's fills so far, and the daily PnL they add up to
+    pub async fn blotter(&self) -> (Blotter, f64) {
+        let blotter = self.blotter.lock().await;
+        (blotter.clone(), self.session.realized_pnl())
+    }
+
+    // Blotters of the sessions rolled over since the last call, with each
+    // one's summary
+    pub async fn take_closed_days(&self) -> Vec<(Blotter, SessionSummary)> {
+        std::mem::take(&mut *self.closed_days.lock().await)
+    }
+
+    /// How much of each risk limit the book takes up: the day's loss,
+    /// drawdown from the intraday peak, margin on perps, gross and net
+    /// exposure and every open position's notional, all at the latest marks
+    /// and in the reporting currency
+    pub async fn limit_usage(&self) -> Vec<LimitRow> {
+        let daily_pnl = self.daily_pnl().await;
+        let equity = self.equity().await;
+        let peak = self.session.stats().peak_equity.max(equity);
+        let drawdown = if peak > 0.0 {
+            (peak - equity) / peak
+        } else {
+            0.0
+        };
+        let mut limits = vec![
+            LimitRow::new(
+                "daily_loss",
+                (-daily_pnl).max(0.0),
+                self.params.max_daily_loss,
+            ),
+            LimitRow::new("drawdown", drawdown, self.params.max_drawdown_pct),
+        ];
+        {
+            let positions = self.positions.read().await;
+            let account = self.account.lock().await;
+            if account.has_perps() {
+                limits.push(LimitRow::new(
+                    "margin",
+                    self.used_margin(&account, &positions),
+                    equity * account.margin().max_margin_usage,
+                ));
+            }
+        }
+
+        let mut positions = self.positions().await;
+        positions.sort_by(|a, b| (&a.symbol, &a.venue, a.side).cmp(&(&b.symbol, &b.venue, b.side)));
+        let (mut gross, mut net) = (0.0, 0.0);
+        let mut position_limits = Vec::new();
+        for position in positions.iter().filter(|p| p.quantity != 0.0) {
+            let mark = self
+                .marks
+                .price(position.symbol.as_str())
+                .unwrap_or(position.avg_price);
+            let rate = self.quote_rate(&*self.account.lock().await, position.symbol.as_str());
+            let notional = position.quantity * mark * rate;
+            gross += notional.abs();
+            net += notional;
+            position_limits.push(LimitRow::new(
+                match position.side {
+                    PositionSide::Both => {
+                        format!("position {} {}", position.venue, position.symbol)
+                    }
+                    side => format!("position {} {} {}", position.venue, position.symbol, side),
+                },
+                notional.abs(),
+                self.params_for(position.symbol.as_str())
+                    .await
+                    .max_position_notional,
+            ));
+        }
+        limits.push(LimitRow::new(
+            "gross_exposure",
+            gross,
+            self.params.max_gross_exposure,
+        ));
+        limits.push(LimitRow::new(
+            "net_exposure",
+            net.abs(),
+            self.params.max_net_exposure,
+        ));
+        limits.extend(position_limits);
+        limits
+    }
+
+    /// Puts back positions, balances and the day's PnL saved at `saved_at`
+    /// (seconds) before a restart; the PnL only if that was this session,
+    /// and the journal didn't already restore it. Positions are marked at
+    /// their average price until the next tick, so their unrealized PnL
+    /// starts at zero.
+    pub async fn restore(
+        &self,
+        positions: Vec<Position>,
+        balances: HashMap<String, f64>,
+        daily_pnl: f64,
+        saved_at: u64,
+    ) {
+        *self.positions.write().await = positions
+            .into_iter()
+            .map(|position| {
+                let position = Position {
+                    unrealized_pnl: 0.0,
+                    ..position
+                };
+                (position.key(), position)
+            })
+            .collect();
+        self.account.lock().await.restore(balances);
+        self.session.restore_pnl(saved_at, daily_pnl);
+    }
+
+    /// Sets a position to the quantity a venue reports. It keeps its
+    /// average price unless opened or flipped by this, when the mark (or
+    /// the old average, without one) is taken; realized PnL and cash are
+    /// left alone. Venues report one net figure: it is the net position
+    /// that is set, to what the figure leaves after any hedge-mode legs.
+    pub async fn adopt_position(&self, venue: &VenueId, symbol: &str, quantity: f64) -> Position {
+        let mark = self.marks.price(symbol);
+        let mut positions = self.positions.write().await;
+        let legs: f64 = positions
+            .values()
+            .filter(|p| p.venue == *venue && p.symbol == symbol && p.side != PositionSide::Both)
+            .map(|p| p.quantity)
+            .sum();
+        let quantity = quantity - legs;
+        let position = positions
+            .entry((venue.clone(), SymbolId::intern(symbol), PositionSide::Both))
+            .or_insert_with(|| Position::flat_on(venue, symbol));
+        if position.quantity * quantity <= 0.0 {
+            position.avg_price = mark.unwrap_or(position.avg_price);
+        }
+        position.quantity = quantity;
+        position.unrealized_pnl =
+            quantity * (mark.unwrap_or(position.avg_price) - position.avg_price);
+        position.clone()
+    }
+
+    /// Books a fill against its position and the account's cash and
+    /// returns the PnL it realized, net of its fee. Fills are the only way
+    /// positions change; partial fills simply arrive as several.
+    pub async fn apply_fill(&self, fill: &Fill) -> f64 {
+        let (realized, closing) = self
+            .update_position(
+                &(fill.venue.clone(), fill.symbol, fill.position_side),
+                fill.signed_quantity(),
+                fill.price,
+                fill.fee,
+            )
+            .await;
+
+        let mut account = self.account.lock().await;
+        if account.is_perp(fill.symbol.as_str()) {
+            account.apply_perp_fill(fill.symbol.as_str(), realized, fill.fee);
+        } else {
+            account.apply_fill(
+                fill.symbol.as_str(),
+                fill.signed_quantity(),
+                fill.price,
+                fill.fee,
+            );
+        }
+        // The session counts in the reporting currency, at the rate now
+        let rate = self.quote_rate(&account, fill.symbol.as_str());
+        drop(account);
+        let mut blotter = self.blotter.lock().await;
+        self.session
+            .record_fill(realized * rate, fill.fee * rate, closing);
+        blotter.record(BookedFill {
+            fill: fill.clone(),
+            realized_pnl: realized,
+            closing,
+            rate,
+        });
+        realized
+    }
+
+    pub async fn is_perp(&self, symbol: &str) -> bool {
+        self.account.lock().await.is_perp(symbol)
+    }
+
+    // Where a perp position would be liquidated; None for spot, which
+    // can't be
+    pub async fn liquidation_price(&self, position: &Position) -> Option<f64> {
+        let account = self.account.lock().await;
+        if !account.is_perp(position.symbol.as_str()) {
+            return None;
+        }
+        account.margin().liquidation_price(position)
+    }
+
+    /// Settles funding on the symbol's perp positions at the funding time
+    /// `timestamp`: each pays `rate` times its notional at the mark (longs
+    /// pay a positive rate, shorts receive it). Payments go to the
+    /// position's realized PnL and cash in the quote asset, and to the
+    /// day's PnL in the reporting currency.
+    pub async fn apply_funding(
+        &self,
+        symbol: &str,
+        rate: f64,
+        timestamp: u64,
+    ) -> Vec<FundingPayment> {
+        let mut positions = self.positions.write().await;
+        let mut account = self.account.lock().await;
+        if !account.is_perp(symbol) {
+            return Vec::new();
+        }
+        let to_reporting = self.quote_rate(&account, symbol);
+        let mut payments = Vec::new();
+        for position in positions.values_mut() {
+            if position.symbol != symbol || position.quantity == 0.0 {
+                continue;
+            }
+            let mark = self.marks.price(symbol).unwrap_or(position.avg_price);
+            let notional = position.quantity * mark;
+            let amount = -notional * rate;
+            position.realized_pnl += amount;
+            account.apply_funding(symbol, amount);
+            payments.push(FundingPayment {
+                venue: position.venue.clone(),
+                symbol: symbol.to_string(),
+                side: position.side,
+                rate,
+                mark,
+                notional,
+                amount,
+                timestamp,
+            });
+        }
+        drop(account);
+        drop(positions);
+        let total: f64 = payments.iter().map(|payment| payment.amount).sum();
+        if total != 0.0 {
+            let mut blotter = self.blotter.lock().await;
+            self.session.record_funding(total * to_reporting);
+            blotter.record_funding(total * to_reporting);
+        }
+        payments
+    }
+
+    // Applies a signed trade to the position (or leg) and returns the PnL
+    // it realized, and whether it reduced the position
+    async fn update_position(
+        &self,
+        key: &PositionKey,
+        quantity: f64,
+        price: f64,
+        fee: f64,
+    ) -> (f64, bool) {
+        if quantity == 0.0 {
+            return (0.0, false);
+        }
+
+        let mut positions = self.positions.write().await;
+        let (venue, symbol, side) = key;
+        let position = positions
+            .entry(key.clone())
+            .or_insert_with(|| Position::flat_leg(venue, *symbol, *side));
+        let closing = position.quantity != 0.0 && position.quantity.signum() != quantity.signum();
+        (position.apply_trade(quantity, price, fee), closing)
+    }
+}
+
+// Where the bot's prices and books come from: the simulator, a recording
+// being replayed, or (eventually) a venue
+pub trait MarketData: Send + Sync {
+    // Latest tick for the symbol, or None if there is nothing (new) to report
+    fn get_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Price>>;
+
+    fn get_orderbook<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<OrderBook>>;
+}
+
+// Market data feed simulator
+pub struct MarketDataFeed {
+    symbols: Vec<String>,
+    market: std::sync::Mutex<SimulatedMarket>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MarketData for MarketDataFeed {
+    fn get_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Price>> {
+        Box::pin(self.get_price(symbol))
+    }
+
+    fn get_orderbook<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<OrderBook>> {
+        Box::pin(self.get_orderbook(symbol))
+    }
+}
+
+impl MarketDataFeed {
+    pub fn new(symbols: Vec<String>) -> Self {
+        Self::with_simulation(symbols, &SimulationConfig::default(), Arc::new(SystemClock))
+    }
+
+    // A feed whose prices follow `simulation`, stamped by `clock`
+    pub fn with_simulation(
+        symbols: Vec<String>,
+        simulation: &SimulationConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            symbols,
+            market: std::sync::Mutex::new(SimulatedMarket::new(simulation)),
+            clock,
+        }
+    }
+
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    // Simulate market data - in real implementation, connect to actual APIs
+    pub async fn get_price(&self, symbol: &str) -> Option<Price> {
+        // This is a simulation - replace with actual API calls
+        let timestamp = self.clock.now_secs();
+        Some(self.market.lock().unwrap().next_price(symbol, timestamp))
+    }
+
+    pub async fn get_orderbook(&self, symbol: &str) -> Option<OrderBook> {
+        // Simulate orderbook data around the symbol's latest price
+        let timestamp = self.clock.now_secs();
+        Some(self.market.lock().unwrap().book(symbol, timestamp))
+    }
+}
+
+// Why the executor could not carry out a request
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionError {
+    RateLimited { kind: RateLimitKind },
+    NoLiquidity { symbol: String },
+    // The venue didn't answer in time; the order may or may not be live
+    Timeout,
+    Network(String),
+    // Not a working order (never placed, already filled or cancelled)
+    UnknownOrder { order_id: String },
+    ReplaceRejected { order_id: String, reason: String },
+    InvalidOrder { reason: String },
+    PostOnlyWouldCross { price: f64, best: f64 },
+    // Would trade against the bot's own resting order
+    SelfMatch { resting_order_id: String },
+    FillOrKillUnfilled { quantity: f64, available: f64 },
+    // Nothing left (or too little) after rounding down to the lot size
+    BelowMinQuantity { quantity: f64, min_qty: f64 },
+    BelowMinNotional { notional: f64, min_notional: f64 },
+    InsufficientBalance,
+    // Held back: our clock is too far off the venue's for signed requests
+    ClockSkew { offset_ms: i64 },
+    // Any other refusal by a live venue, with its own code and message
+    VenueRejected { code: i64, message: String },
+    // The venue (or its connector) doesn't offer this
+    Unsupported(String),
+}
+
+impl ExecutionError {
+    // Transient failures worth retrying with the same client order id.
+    // Everything else is a definite answer and retrying won't change it.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ExecutionError::Timeout | ExecutionError::Network(_))
+    }
+}
+
+impl std::fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionError::RateLimited { kind } => {
+                write!(f, "Rate limited: {:?} budget exhausted", kind)
+            }
+            ExecutionError::NoLiquidity { symbol } => {
+                write!(f, "No liquidity to fill against on {}", symbol)
+            }
+            ExecutionError::Timeout => write!(f, "Timed out waiting for the venue"),
+            ExecutionError::Network(e) => write!(f, "Network error: {}", e),
+            ExecutionError::UnknownOrder { order_id } => {
+                write!(f, "No working order {}", order_id)
+            }
+            ExecutionError::ReplaceRejected { order_id, reason } => {
+                write!(f, "Replace of {} rejected: {}", order_id, reason)
+            }
+            ExecutionError::InvalidOrder { reason } => write!(f, "Invalid order: {}", reason),
+            ExecutionError::PostOnlyWouldCross { price, best } => write!(
+                f,
+                "Post-only price {:.4} would cross the book at {:.4}",
+                price, best
+            ),
+            ExecutionError::SelfMatch { resting_order_id } => write!(
+                f,
+                "Would trade against our own resting order {}",
+                resting_order_id
+            ),
+            ExecutionError::FillOrKillUnfilled {
+                quantity,
+                available,
+            } => write!(
+                f,
+                "Fill-or-kill for {:.4} killed; only {:.4} available",
+                quantity, available
+            ),
+            ExecutionError::BelowMinQuantity { quantity, min_qty } => write!(
+                f,
+                "Quantity {} after rounding is below the minimum {}",
+                quantity, min_qty
+            ),
+            ExecutionError::BelowMinNotional {
+                notional,
+                min_notional,
+            } => write!(
+                f,
+                "Notional {:.4} after rounding is below the minimum {:.4}",
+                notional, min_notional
+            ),
+            ExecutionError::InsufficientBalance => {
+                write!(f, "Insufficient balance for the order")
+            }
+            ExecutionError::ClockSkew { offset_ms } => write!(
+                f,
+                "Order entry halted: local clock is {}ms off the venue's",
+                offset_ms.abs()
+            ),
+            ExecutionError::VenueRejected { code, message } => {
+                write!(f, "Rejected by the venue ({}): {}", code, message)
+            }
+            ExecutionError::Unsupported(what) => write!(f, "Not supported by the venue: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+// Latest order book per symbol, shared between the trading loop that
+// fetches books and the paper executor that fills against them
+pub type BookCache = Arc<RwLock<HashMap<String, OrderBook>>>;
+
+// What working limits are matched against: a book's levels (best first),
+// or a trade print of a quantity at a price
+#[derive(Debug, Clone, Copy)]
+enum MatchAgainst<'a> {
+    Book(&'a [(f64, f64)], &'a [(f64, f64)]),
+    Print(f64, f64),
+}
+
+// Positions by venue and symbol as the risk manager books them, shared with
+// the executor that caps reduce-only orders against them
+pub type PositionBook = Arc<RwLock<HashMap<PositionKey, Position>>>;
+
+// Outcome of a bulk cancel
+#[derive(Debug, Clone, Default)]
+pub struct CancelAllReport {
+    pub cancelled: Vec<String>,
+    pub failed: Vec<(String, ExecutionError)>,
+}
+
+// Where a client order id's submission stands
+enum Submission {
+    // An attempt is placing it. Duplicates wait for the attempt's sender to
+    // drop, which it does however the attempt ends.
+    Pending(watch::Receiver<()>),
+    Accepted(String),
+}
+
+// Client order ids being placed or already accepted, the latter mapped to
+// their order id. Accepted ids are bounded so they don't grow for the life
+// of the process; the oldest are forgotten.
+#[derive(Default)]
+struct SubmittedOrders {
+    ids: HashMap<String, Submission>,
+    arrival: VecDeque<String>,
+}
+
+impl SubmittedOrders {
+    const CAPACITY: usize = 10_000;
+
+    fn insert(&mut self, client_order_id: &str, order_id: &str) {
+        if self.arrival.len() == Self::CAPACITY
+            && let Some(oldest) = self.arrival.pop_front()
+        {
+            self.ids.remove(&oldest);
+        }
+        self.arrival.push_back(client_order_id.to_string());
+        self.ids.insert(
+            client_order_id.to_string(),
+            Submission::Accepted(order_id.to_string()),
+        );
+    }
+}
+
+// An attempt's hold on a client order id, marked pending while the attempt
+// runs. Dropped without being accepted (the attempt failed or was given
+// up), it frees the id for a retry; either way duplicates waiting on it
+// wake.
+struct Claim<'a> {
+    submitted: &'a std::sync::Mutex<SubmittedOrders>,
+    client_order_id: String,
+    accepted: bool,
+    _done: watch::Sender<()>,
+}
+
+impl<'a> Claim<'a> {
+    // Claims the id unless it is accepted or already being placed, in which
+    // case that is returned instead
+    fn take(
+        submitted: &'a std::sync::Mutex<SubmittedOrders>,
+        client_order_id: &str,
+    ) -> Result<Self, Submission> {
+        let mut orders = submitted.lock().unwrap();
+        match orders.ids.get(client_order_id) {
+            Some(Submission::Accepted(order_id)) => Err(Submission::Accepted(order_id.clone())),
+            Some(Submission::Pending(done)) => Err(Submission::Pending(done.clone())),
+            None => {
+                let (done, waiters) = watch::channel(());
+                orders
+                    .ids
+                    .insert(client_order_id.to_string(), Submission::Pending(waiters));
+                Ok(Self {
+                    submitted,
+                    client_order_id: client_order_id.to_string(),
+                    accepted: false,
+                    _done: done,
+                })
+            }
+        }
+    }
+
+    fn accept(&mut self, order_id: &str) {
+        self.submitted
+            .lock()
+            .unwrap()
+            .insert(&self.client_order_id, order_id);
+        self.accepted = true;
+    }
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        if !self.accepted {
+            self.submitted
+                .lock()
+                .unwrap()
+                .ids
+                .remove(&self.client_order_id);
+        }
+    }
+}
+
+// Order execution engine
+pub struct OrderExecutor {
+    open_orders: Arc<Mutex<OpenOrders>>,
+    // Counts of open_orders, for the risk manager
+    open_order_tracker: Arc<OpenOrderTracker>,
+    // Only held to read or mark a client order id, never across a submission
+    submitted: std::sync::Mutex<SubmittedOrders>,
+    // One per symbol, held while an order travels to the book, so each
+    // symbol's orders arrive in the order they were sent while other
+    // symbols' go in parallel
+    symbol_turns: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    retry: RetryConfig,
+    rate_limiter: OrderRateLimiter,
+    // Books that simulated market orders fill against in paper mode
+    order_books: BookCache,
+    paper: PaperConfig,
+    // The simulated venue's delays and transient failures
+    latency: LatencyModel,
+    // Price-time queues of the resting limit orders in open_orders
+    matching: std::sync::Mutex<MatchingEngine>,
+    fees: FeeModel,
+    // Tick/lot grids and minimums orders are normalized to before submit
+    symbols: SymbolRegistry,
+    // What reduce-only orders are capped at; without it they go as they are
+    positions: Option<PositionBook>,
+    fills: mpsc::UnboundedSender<Fill>,
+    fill_receiver: std::sync::Mutex<Option<mpsc::UnboundedReceiver<Fill>>>,
+    clock: Arc<dyn Clock>,
+    journal: Option<Journal>,
+}
+
+impl Default for OrderExecutor {
+    fn default() -> Self {
+        Self::with_rate_limits(&RateLimitConfig::default())
+    }
+}
+
+impl OrderExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rate_limits(config: &RateLimitConfig) -> Self {
+        let (fills, fill_receiver) = mpsc::unbounded_channel();
+        let open_orders = OpenOrders::default();
+        Self {
+            open_order_tracker: open_orders.tracker(),
+            open_orders: Arc::new(Mutex::new(open_orders)),
+            submitted: std::sync::Mutex::new(SubmittedOrders::default()),
+            symbol_turns: std::sync::Mutex::new(HashMap::new()),
+            retry: RetryConfig::default(),
+            rate_limiter: OrderRateLimiter::new(config),
+            order_books: Arc::new(RwLock::new(HashMap::new())),
+            paper: PaperConfig::default(),
+            latency: LatencyModel::default(),
+            matching: std::sync::Mutex::new(MatchingEngine::new()),
+            fees: FeeModel::zero(),
+            symbols: SymbolRegistry::default(),
+            positions: None,
+            fills,
+            fill_receiver: std::sync::Mutex::new(Some(fill_receiver)),
+            clock: Arc::new(SystemClock),
+            journal: None,
+        }
+    }
+
+    // Stamps fills and paces the simulated venue latency
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // How many orders are working, kept current as they come and go
+    pub fn open_order_tracker(&self) -> Arc<OpenOrderTracker> {
+        Arc::clone(&self.open_order_tracker)
+    }
+
+    // Records every submission and status change
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    fn journal_event(&self, order: &Order, event: OrderEvent, detail: Option<String>) {
+        if let Some(journal) = &self.journal {
+            journal.record(JournalEntry::OrderEvent {
+                order_id: order.id.clone(),
+                symbol: order.symbol.to_string(),
+                event,
+                detail,
+                timestamp: self.clock.now_secs(),
+            });
+        }
+    }
+
+    fn journal_submission(&self, order: &Order) {
+        if let Some(journal) = &self.journal {
+            journal.record(JournalEntry::OrderSubmitted(order.clone()));
+        }
+        self.journal_event(order, OrderEvent::Accepted, None);
+    }
+
+    // Queues a resting limit order for matching, behind those already
+    // resting at its price
+    fn queue_limit(&self, state: &OrderState) {
+        if state.order.order_type == OrderType::Limit
+            && let Some(price) = state.order.price
+        {
+            self.matching.lock().unwrap().add(
+                state.order.symbol.as_str(),
+                &state.order.id,
+                state.order.side,
+                price,
+                state.remaining_quantity(),
+            );
+        }
+    }
+
+    // Simulates market order fills against these books
+    pub fn with_order_books(mut self, order_books: BookCache) -> Self {
+        self.order_books = order_books;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_paper_config(mut self, paper: PaperConfig) -> Self {
+        self.matching =
+            std::sync::Mutex::new(MatchingEngine::new().with_queue_position(paper.queue_position));
+        self.latency = LatencyModel::new(paper.latency.clone());
+        self.paper = paper;
+        self
+    }
+
+    // Commission charged on simulated fills
+    pub fn with_fee_model(mut self, fees: FeeModel) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    pub fn fee_model(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    // The delays and failures simulated on paper orders so far
+    pub fn simulated_latency(&self) -> LatencyStats {
+        self.latency.stats()
+    }
+
+    pub fn with_symbol_meta(mut self, symbols: SymbolRegistry) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    pub fn symbol_meta(&self, symbol: &str) -> Option<&SymbolMeta> {
+        self.symbols.get(symbol)
+    }
+
+    // The risk manager's positions, for capping reduce-only orders
+    pub fn with_positions(mut self, positions: PositionBook) -> Self {
+        self.positions = Some(positions);
+        self
+    }
+
+    // Caps a reduce-only order at the position booked when it executes;
+    // without the risk manager's positions it goes out as is
+    async fn cap_reduce_only(&self, order: &mut Order) -> bool {
+        match &self.positions {
+            Some(positions) => order.cap_reduce_only(positions).await,
+            None => true,
+        }
+    }
+
+    // A reduce-only order with nothing left to reduce is cancelled unfilled
+    fn cancel_unreducible(&self, order: &Order) {
+        println!(
+            "Reduce-only order {} cancelled: no {} position left to reduce",
+            order.id, order.symbol
+        );
+        self.journal_event(
+            order,
+            OrderEvent::Cancelled,
+            Some("reduce-only with no position to reduce".to_string()),
+        );
+    }
+
+    /// Rounds the order onto its symbol's tick and lot grids and rejects it
+    /// if it falls below the minimum quantity or notional afterwards. Every
+    /// submit goes through this; callers that size or risk-check orders
+    /// should run it first so they see the quantity that will be sent.
+    /// Market orders are checked against the latest mid. Symbols with no
+    /// registered meta pass through untouched.
+    pub async fn normalize_order(&self, order: &mut Order) -> Result<(), ExecutionError> {
+        let Some(meta) = self.symbols.get(order.symbol.as_str()) else {
+            return Ok(());
+        };
+        let mid = self
+            .order_books
+            .read()
+            .await
+            .get(order.symbol.as_str())
+            .and_then(OrderBook::mid_price);
+        meta.normalize(order, mid)
+    }
+
+    // The stream of fills for every order sent through this executor. There
+    // is a single consumer; returns None once it has been taken.
+    pub fn take_fills(&self) -> Option<mpsc::UnboundedReceiver<Fill>> {
+        self.fill_receiver.lock().unwrap().take()
+    }
+
+    // Lets a live venue's fills join the same stream
+    pub fn fill_sender(&self) -> mpsc::UnboundedSender<Fill> {
+        self.fills.clone()
+    }
+
+    // Current submit/cancel budgets, for metrics
+    pub fn rate_limit_usage(&self) -> RateLimitUsage {
+        self.rate_limiter.usage(self.clock.as_ref())
+    }
+
+    /// Submits an order, retrying transient failures with backoff. The
+    /// client order id makes this idempotent: a retry or duplicate of an
+    /// order that was already accepted returns the original order id
+    /// without placing anything new.
+    pub async fn submit_order(&self, mut order: Order) -> Result<String, ExecutionError> {
+        if order.link_group.is_some() {
+            return Err(ExecutionError::InvalidOrder {
+                reason: "linked orders are placed with submit_oco".to_string(),
+            });
+        }
+        // Stops are capped when they trigger
+        if !order.order_type.is_stop() && !self.cap_reduce_only(&mut order).await {
+            if let Some(journal) = &self.journal {
+                journal.record(JournalEntry::OrderSubmitted(order.clone()));
+            }
+            self.cancel_unreducible(&order);
+            return Ok(order.id);
+        }
+        self.normalize_order(&mut order).await?;
+        self.prevent_self_match(&mut order).await?;
+        retry::retry(
+            &self.retry,
+            self.clock.as_ref(),
+            ExecutionError::is_retryable,
+            || self.place_order(&order),
+        )
+        .await
+    }
+
+    // Checks the order against our own resting limits on the other side of
+    // its symbol and venue, and if it would trade against any of them,
+    // cancels them, reprices the order short of them or rejects it, as the
+    // self-match policy says
+    async fn prevent_self_match(&self, order: &mut Order) -> Result<(), ExecutionError> {
+        let policy = self.paper.self_match;
+        if policy == SelfMatchPolicy::Allow || order.time_in_force == TimeInForce::PostOnly {
+            return Ok(());
+        }
+        // How far through the book the order reaches: a market order as far
+        // as it would walk the latest book (anywhere, without one)
+        let limit = match order.order_type {
+            OrderType::Limit => order.price,
+            OrderType::Market => {
+                let books = self.order_books.read().await;
+                books.get(order.symbol.as_str()).and_then(|book| {
+                    let opposite = match order.side {
+                        OrderSide::Buy => &book.asks,
+                        OrderSide::Sell => &book.bids,
+                    };
+                    paper::walk_book(opposite, order.side, order.quantity, None)
+                        .last()
+                        .map(|fill| fill.price)
+                })
+            }
+            // Stops are left to trigger
+            _ => return Ok(()),
+        };
+        let mut resting: Vec<Order> = self
+            .open_orders_for_symbol(order.symbol.as_str())
+            .await
+            .into_iter()
+            .map(|state| state.order)
+            .filter(|resting| {
+                resting.venue == order.venue
+                    && resting.side != order.side
+                    && resting.order_type == OrderType::Limit
+                    && resting.price.is_some_and(|price| {
+                        limit.is_none_or(|limit| paper::crosses(order.side, limit, price))
+                    })
+            })
+            .collect();
+        // Best first: the one the order would reach first
+        resting.sort_by(|a, b| {
+            let (a, b) = (a.price.unwrap_or(0.0), b.price.unwrap_or(0.0));
+            match order.side {
+                OrderSide::Sell => b.total_cmp(&a),
+                OrderSide::Buy => a.total_cmp(&b),
+            }
+        });
+        let Some(best) = resting.first() else {
+            return Ok(());
+        };
+
+        match policy {
+            SelfMatchPolicy::Allow => Ok(()),
+            SelfMatchPolicy::Reject => {
+                let e = ExecutionError::SelfMatch {
+                    resting_order_id: best.id.clone(),
+                };
+                println!("Self-match: rejected order {}: {}", order.id, e);
+                if let Some(journal) = &self.journal {
+                    journal.record(JournalEntry::OrderSubmitted(order.clone()));
+                }
+                self.journal_event(order, OrderEvent::Rejected, Some(e.to_string()));
+                Err(e)
+            }
+            SelfMatchPolicy::CancelResting => {
+                for resting in &resting {
+                    println!(
+                        "Self-match: cancelling resting order {} ahead of order {}",
+                        resting.id, order.id
+                    );
+                    match self.cancel_order(&resting.id).await {
+                        // Gone already: filled or cancelled since
+                        Ok(()) | Err(ExecutionError::UnknownOrder { .. }) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(())
+            }
+            SelfMatchPolicy::Reprice => {
+                let best_price = best.price.unwrap_or_default();
+                let tick = self
+                    .symbol_meta(order.symbol.as_str())
+                    .map(|meta| meta.tick_size)
+                    .filter(|tick| *tick > 0.0);
+                let price = match (order.side, tick) {
+                    (OrderSide::Sell, Some(tick)) => best_price + tick,
+                    (OrderSide::Sell, None) => best_price.next_up(),
+                    (OrderSide::Buy, Some(tick)) => best_price - tick,
+                    (OrderSide::Buy, None) => best_price.next_down(),
+                };
+                if order.order_type == OrderType::Market {
+                    order.order_type = OrderType::Limit;
+                    order.time_in_force = TimeInForce::Ioc;
+                }
+                order.price = Some(price);
+                println!(
+                    "Self-match: repriced order {} to {} to stay clear of resting order {}",
+                    order.id, price, best.id
+                );
+                Ok(())
+            }
+        }
+    }
+
+    fn symbol_turn(&self, symbol: &str) -> Arc<Mutex<()>> {
+        self.symbol_turns
+            .lock()
+            .unwrap()
+            .entry(symbol.to_string())
+            .or_default()
+            .clone()
+    }
+
+    // A single attempt at the venue
+    async fn place_order(&self, order: &Order) -> Result<String, ExecutionError> {
+        // Only a concurrent attempt at the same client order id waits, for
+        // that attempt to end; then the id is accepted or free again
+        let mut claim = loop {
+            match Claim::take(&self.submitted, &order.client_order_id) {
+                Ok(claim) => break claim,
+                Err(Submission::Accepted(order_id)) => {
+                    println!(
+                        "Duplicate submit of client order {}; already accepted as {}",
+                        order.client_order_id, order_id
+                    );
+                    return Ok(order_id);
+                }
+                Err(Submission::Pending(mut done)) => {
+                    let _ = done.changed().await;
+                }
+            }
+        };
+
+        self.rate_limiter
+            .acquire(RateLimitKind::Submit, self.clock.as_ref())
+            .await
+            .map_err(|kind| ExecutionError::RateLimited { kind })?;
+
+        // In real implementation, submit to exchange API
+        println!("Submitting order: {:?}", order);
+
+        let fault = self.latency.fault();
+        match fault {
+            Some(InjectedFault::Timeout) => {
+                self.clock.sleep(self.latency.timeout()).await;
+                return Err(ExecutionError::Timeout);
+            }
+            Some(InjectedFault::Rejected) => {
+                self.clock.sleep(self.latency.ack_delay()).await;
+                return Err(ExecutionError::Network(
+                    "venue busy (simulated)".to_string(),
+                ));
+            }
+            Some(InjectedFault::AckLost) | None => {}
+        }
+        // The order only meets the book once it gets there, behind any
+        // order for the symbol sent before it
+        let turn = self.symbol_turn(order.symbol.as_str());
+        let turn = turn.lock().await;
+        let delay = self.latency.fill_delay();
+        if !delay.is_zero() {
+            self.clock.sleep(delay).await;
+        }
+
+        let executed = self.simulate_execution(order).await;
+        drop(turn);
+        let fills = match executed {
+            Ok(fills) => fills,
+            Err(e) => {
+                if let Some(journal) = &self.journal {
+                    journal.record(JournalEntry::OrderSubmitted(order.clone()));
+                }
+                self.journal_event(order, OrderEvent::Rejected, Some(e.to_string()));
+                return Err(e);
+            }
+        };
+        self.journal_submission(order);
+        for fill in fills {
+            // The receiver only goes away at shutdown
+            let _ = self.fills.send(fill);
+        }
+        claim.accept(&order.id);
+        drop(claim);
+
+        // A stop placed through the market triggers straight away
+        if order.order_type.is_stop() {
+            self.trigger_stops(order.symbol.as_str()).await;
+        }
+
+        // The venue has the order, but we never hear so; a retry finds the
+        // client order id already accepted
+        if fault == Some(InjectedFault::AckLost) {
+            self.clock.sleep(self.latency.timeout()).await;
+            return Err(ExecutionError::Timeout);
+        }
+
+        // Then the acknowledgement makes its way back
+        let delay = self.latency.ack_delay();
+        if !delay.is_zero() {
+            self.clock.sleep(delay).await;
+        }
+
+        Ok(order.id.clone())
+    }
+
+    // Paper execution against the latest book for the symbol. Whatever
+    // crosses fills immediately, level by level; what happens to the rest
+    // depends on the time in force (and, for GTC market orders, the
+    // remainder policy). Returns the fills, one per level consumed.
+    async fn simulate_execution(&self, order: &Order) -> Result<Vec<Fill>, ExecutionError> {
+        let limit = match order.order_type {
+            OrderType::Market => None,
+            OrderType::Limit => Some(order.price.ok_or_else(|| ExecutionError::InvalidOrder {
+                reason: "limit order without a price".to_string(),
+            })?),
+            OrderType::StopMarket { .. } | OrderType::StopLimit { .. } => {
+                if order.time_in_force == TimeInForce::PostOnly {
+                    return Err(ExecutionError::InvalidOrder {
+                        reason: "stop orders can't be post-only".to_string(),
+                    });
+                }
+                let mut state = OrderState::new(order.clone());
+                state.status = OrderStatus::Untriggered;
+                self.open_orders.lock().await.insert(state);
+                return Ok(Vec::new());
+            }
+        };
+
+        let books = self.order_books.read().await;
+        let opposite = books
+            .get(order.symbol.as_str())
+            .map(|book| match order.side {
+                OrderSide::Buy => book.asks.as_slice(),
+                OrderSide::Sell => book.bids.as_slice(),
+            });
+
+        if order.time_in_force == TimeInForce::PostOnly {
+            let Some(limit) = limit else {
+                return Err(ExecutionError::InvalidOrder {
+                    reason: "post-only requires a limit order".to_string(),
+                });
+            };
+            if let Some(&(best, _)) = opposite.and_then(|levels| levels.first())
+                && paper::crosses(order.side, limit, best)
+            {
+                return Err(ExecutionError::PostOnlyWouldCross { price: limit, best });
+            }
+            drop(books);
+            let state = OrderState::new(order.clone());
+            self.queue_limit(&state);
+            self.open_orders.lock().await.insert(state);
+            return Ok(Vec::new());
+        }
+
+        let levels = opposite
+            .map(|levels| paper::walk_book(levels, order.side, order.quantity, limit))
+            .unwrap_or_default();
+        drop(books);
+
+        let filled: f64 = levels.iter().map(|l| l.quantity).sum();
+        let remaining = order.quantity - filled;
+
+        if order.time_in_force == TimeInForce::Fok && remaining > f64::EPSILON {
+            return Err(ExecutionError::FillOrKillUnfilled {
+                quantity: order.quantity,
+                available: filled,
+            });
+        }
+        if levels.is_empty() && order.order_type == OrderType::Market {
+            return Err(ExecutionError::NoLiquidity {
+                symbol: order.symbol.to_string(),
+            });
+        }
+
+        if remaining > f64::EPSILON {
+            let keep_working = match (order.time_in_force, order.order_type) {
+                (TimeInForce::Gtc, OrderType::Limit) => true,
+                (TimeInForce::Gtc, OrderType::Market) => {
+                    self.paper.remainder == RemainderPolicy::KeepWorking
+                }
+                _ => false,
+            };
+            if keep_working {
+                if filled > 0.0 {
+                    println!(
+                        "Order {} filled {:.4} of {:.4}; remainder working",
+                        order.id, filled, order.quantity
+                    );
+                }
+                let mut state = OrderState::new(order.clone());
+                if filled > 0.0 {
+                    state.filled_quantity = filled;
+                    state.status = OrderStatus::PartiallyFilled;
+                }
+                self.queue_limit(&state);
+                self.open_orders.lock().await.insert(state);
+            } else {
+                println!(
+                    "Order {} filled {:.4} of {:.4}; remainder cancelled",
+                    order.id, filled, order.quantity
+                );
+                self.journal_event(
+                    order,
+                    OrderEvent::RemainderCancelled,
+                    Some(format!("filled {} of {}", filled, order.quantity)),
+                );
+            }
+        }
+        if let Some(vwap) = paper::vwap(&levels) {
+            println!(
+                "Order {} filled {:.4} @ VWAP {:.4} over {} level(s)",
+                order.id,
+                filled,
+                vwap,
+                levels.len()
+            );
+        }
+
+        let timestamp = self.clock.now_secs();
+        Ok(levels
+            .into_iter()
+            .map(|level| Fill {
+                order_id: order.id.clone(),
+                symbol: order.symbol,
+                side: order.side,
+                quantity: level.quantity,
+                price: level.price,
+                fee: self.fees.fee(
+                    order.symbol.as_str(),
+                    level.quantity * level.price,
+                    Liquidity::Taker,
+                ),
+                liquidity: Liquidity::Taker,
+                timestamp,
+                strategy: order.strategy.clone(),
+                venue: order.venue.clone(),
+                position_side: order.position_side,
+            })
+            .collect())
+    }
+
+    /// Converts every untriggered stop on the symbol that the latest book
+    /// has traded through into its market or limit order and executes it.
+    /// Returns how many stops fired.
+    pub async fn trigger_stops(&self, symbol: &str) -> usize {
+        self.fire_stops(symbol, &mut HashSet::new(), None).await
+    }
+
+    /// Runs resting orders on the symbol against the latest book: stops it
+    /// has traded through fire, then working limit orders it now crosses
+    /// fill. Called whenever a new book for the symbol arrives. At most one
+    /// leg of each one-cancels-other group executes per call, the stop
+    /// first. Returns how many orders executed.
+    pub async fn match_resting_orders(&self, symbol: &str) -> usize {
+        let mut fired = HashSet::new();
+        self.fire_stops(symbol, &mut fired, None).await
+            + self.fill_limits(symbol, &mut fired, None).await
+    }
+
+    // `fired` holds the link groups that already had a leg execute in this
+    // pass; `only` restricts the pass to one link group
+    async fn fire_stops(
+        &self,
+        symbol: &str,
+        fired: &mut HashSet<String>,
+        only: Option<&str>,
+    ) -> usize {
+        let (best_bid, best_ask) = match self.order_books.read().await.get(symbol) {
+            Some(book) => (
+                book.best_bid().map(|(p, _)| p),
+                book.best_ask().map(|(p, _)| p),
+            ),
+            None => return 0,
+        };
+
+        let triggered: Vec<Order> = {
+            let mut open_orders = self.open_orders.lock().await;
+            let ids: Vec<String> = open_orders
+                .for_symbol(symbol)
+                .filter(|s| s.status == OrderStatus::Untriggered)
+                .filter(|s| only.is_none_or(|g| s.order.link_group.as_deref() == Some(g)))
+                .filter(|s| {
+                    let stop_price = match s.order.order_type {
+                        OrderType::StopMarket { stop_price }
+                        | OrderType::StopLimit { stop_price, .. } => stop_price,
+                        _ => return false,
+                    };
+                    paper::stop_triggered(s.order.side, stop_price, best_bid, best_ask)
+                })
+                .filter(|s| {
+                    s.order
+                        .link_group
+                        .as_ref()
+                        .is_none_or(|g| fired.insert(g.clone()))
+                })
+                .map(|s| s.order.id.clone())
+                .collect();
+            ids.iter()
+                .filter_map(|id| open_orders.remove(id))
+                .map(|s| s.order)
+                .collect()
+        };
+
+        for mut order in triggered.iter().cloned() {
+            match order.order_type {
+                OrderType::StopLimit { limit_price, .. } => {
+                    order.order_type = OrderType::Limit;
+                    order.price = Some(limit_price);
+                }
+                _ => order.order_type = OrderType::Market,
+            }
+            println!(
+                "Stop {} on {} triggered (bid {:?}, ask {:?})",
+                order.id, symbol, best_bid, best_ask
+            );
+            self.journal_event(&order, OrderEvent::Triggered, None);
+            if !self.cap_reduce_only(&mut order).await {
+                self.cancel_unreducible(&order);
+                continue;
+            }
+            match self.simulate_execution(&order).await {
+                Ok(fills) => {
+                    let filled: f64 = fills.iter().map(|f| f.quantity).sum();
+                    for fill in fills {
+                        let _ = self.fills.send(fill);
+                    }
+                    self.settle_link(&order, order.quantity, filled).await;
+                }
+                Err(e) => println!("Triggered stop {} failed: {}", order.id, e),
+            }
+        }
+        triggered.len()
+    }
+
+    // Fills working limit orders the latest book trades through, as maker
+    // at their limit price
+    async fn fill_limits(
+        &self,
+        symbol: &str,
+        fired: &mut HashSet<String>,
+        only: Option<&str>,
+    ) -> usize {
+        let Some((bids, asks)) = self
+            .order_books
+            .read()
+            .await
+            .get(symbol)
+            .map(|book| (book.bids.clone(), book.asks.clone()))
+        else {
+            return 0;
+        };
+        self.match_limits(symbol, MatchAgainst::Book(&bids, &asks), fired, only)
+            .await
+    }
+
+    /// Fills working limit orders on the symbol that a trade print of
+    /// `quantity` at `price` reaches, for feeds that report trades. The
+    /// print's size is shared out in price-time priority. Returns how many
+    /// orders filled.
+    pub async fn match_trade(&self, symbol: &str, price: f64, quantity: f64) -> usize {
+        let print = MatchAgainst::Print(price, quantity);
+        self.match_limits(symbol, print, &mut HashSet::new(), None)
+            .await
+    }
+
+    // Runs the matching engine over the symbol's working limit orders
+    // against a book or a print and books what it matched. `fired` and `only`
+    // are as for fire_stops.
+    async fn match_limits(
+        &self,
+        symbol: &str,
+        against: MatchAgainst<'_>,
+        fired: &mut HashSet<String>,
+        only: Option<&str>,
+    ) -> usize {
+        let mut open_orders = self.open_orders.lock().await;
+        let matches = {
+            let mut engine = self.matching.lock().unwrap();
+            // Drop what was cancelled, replaced or filled elsewhere since
+            engine.retain(symbol, |working| match open_orders.get(&working.order_id) {
+                Some(state) if state.order.order_type == OrderType::Limit => {
+                    working.remaining = state.remaining_quantity();
+                    true
+                }
+                _ => false,
+            });
+            let admit = |working: &WorkingOrder| {
+                let Some(state) = open_orders.get(&working.order_id) else {
+                    return false;
+                };
+                let group = state.order.link_group.as_deref();
+                // One leg of a link group per pass
+                only.is_none_or(|g| group == Some(g))
+                    && group.is_none_or(|g| fired.insert(g.to_string()))
+            };
+            match against {
+                MatchAgainst::Book(bids, asks) => engine.match_book(symbol, bids, asks, admit),
+                MatchAgainst::Print(price, quantity) => {
+                    engine.match_trade(symbol, price, quantity, admit)
+                }
+            }
+        };
+        let filled: Vec<_> = matches
+            .into_iter()
+            .filter_map(|m| {
+                let state = open_orders.get(&m.order_id).cloned()?;
+                open_orders.record_fill(&m.order_id, m.quantity);
+                Some((state, m))
+            })
+            .collect();
+        drop(open_orders);
+
+        for (state, m) in &filled {
+            println!(
+                "Resting order {} filled {:.4} @ {:.4}",
+                m.order_id, m.quantity, m.price
+            );
+            let _ = self.fills.send(Fill {
+                order_id: m.order_id.clone(),
+                symbol: SymbolId::intern(symbol),
+                side: m.side,
+                quantity: m.quantity,
+                price: m.price,
+                fee: self
+                    .fees
+                    .fee(symbol, m.quantity * m.price, Liquidity::Maker),
+                liquidity: Liquidity::Maker,
+                timestamp: self.clock.now_secs(),
+                strategy: state.order.strategy.clone(),
+                venue: state.order.venue.clone(),
+                position_side: state.order.position_side,
+            });
+            self.settle_link(&state.order, state.remaining_quantity(), m.quantity)
+                .await;
+        }
+        filled.len()
+    }
+
+    // One-cancels-other: after `filled` of a linked order's `remaining`
+    // quantity executes, cancels the other legs once the order has filled
+    // past its group's threshold, and otherwise shrinks them in proportion
+    async fn settle_link(&self, order: &Order, remaining: f64, filled: f64) {
+        let Some(group) = &order.link_group else {
+            return;
+        };
+        if filled <= 0.0 || remaining <= 0.0 {
+            return;
+        }
+
+        let mut open_orders = self.open_orders.lock().await;
+        let threshold = open_orders.link_threshold(group).unwrap_or(1.0);
+        let leg_filled = order.quantity - remaining + filled;
+        let ratio = ((remaining - filled) / remaining).max(0.0);
+        let cancel =
+            ratio <= f64::EPSILON || leg_filled >= order.quantity * threshold - f64::EPSILON;
+        let removed = open_orders.settle_linked(group, &order.id, ratio, cancel);
+        drop(open_orders);
+
+        for id in removed {
+            println!(
+                "Cancelled order {} (one-cancels-other with {})",
+                id, order.id
+            );
+            if let Some(journal) = &self.journal {
+                journal.record(JournalEntry::OrderEvent {
+                    order_id: id,
+                    symbol: order.symbol.to_string(),
+                    event: OrderEvent::Cancelled,
+                    detail: Some(format!("one-cancels-other with {}", order.id)),
+                    timestamp: self.clock.now_secs(),
+                });
+            }
+        }
+        if !cancel {
+            println!(
+                "Orders linked to {} reduced to {:.0}% of their remaining quantity",
+                order.id,
+                ratio * 100.0
+            );
+        }
+    }
+
+    /// Places a stop-loss and take-profit pair linked one-cancels-other and
+    /// returns the link group id. Once either leg fills past
+    /// `cancel_threshold` the other is cancelled; a smaller partial fill
+    /// shrinks the other by the same fraction. A book that reaches both
+    /// legs at once fires only the stop. Either leg may execute straight
+    /// away if it is already through the market.
+    pub async fn submit_oco(&self, oco: OcoOrder) -> Result<String, ExecutionError> {
+        let OcoOrder {
+            mut stop_loss,
+            mut take_profit,
+            cancel_threshold,
+        } = oco;
+        let invalid = |reason: &str| ExecutionError::InvalidOrder {
+            reason: reason.to_string(),
+        };
+        if !stop_loss.order_type.is_stop() {
+            return Err(invalid("OCO stop-loss leg must be a stop order"));
+        }
+        if take_profit.order_type != OrderType::Limit || take_profit.price.is_none() {
+            return Err(invalid("OCO take-profit leg must be a priced limit order"));
+        }
+        if stop_loss.symbol != take_profit.symbol || stop_loss.side != take_profit.side {
+            return Err(invalid("OCO legs must be on the same symbol and side"));
+        }
+        if stop_loss.time_in_force != TimeInForce::Gtc
+            || take_profit.time_in_force != TimeInForce::Gtc
+        {
+            return Err(invalid("OCO legs must be GTC"));
+        }
+        if !(cancel_threshold > 0.0 && cancel_threshold <= 1.0) {
+            return Err(invalid("OCO cancel threshold must be in (0, 1]"));
+        }
+        self.normalize_order(&mut stop_loss).await?;
+        self.normalize_order(&mut take_profit).await?;
+
+        let group = Uuid::new_v4().to_string();
+        stop_loss.link_group = Some(group.clone());
+        take_profit.link_group = Some(group.clone());
+        let symbol = stop_loss.symbol;
+
+        let (Ok(mut stop_claim), Ok(mut take_profit_claim)) = (
+            Claim::take(&self.submitted, &stop_loss.client_order_id),
+            Claim::take(&self.submitted, &take_profit.client_order_id),
+        ) else {
+            return Err(invalid("OCO leg reuses a submitted client order id"));
+        };
+        for _ in 0..2 {
+            self.rate_limiter
+                .acquire(RateLimitKind::Submit, self.clock.as_ref())
+                .await
+                .map_err(|kind| ExecutionError::RateLimited { kind })?;
+        }
+
+        // In real implementation, submit to the exchange's OCO endpoint
+        println!(
+            "Submitting OCO {}: {:?} / {:?}",
+            group, stop_loss, take_profit
+        );
+        stop_claim.accept(&stop_loss.id);
+        take_profit_claim.accept(&take_profit.id);
+        self.journal_submission(&stop_loss);
+        self.journal_submission(&take_profit);
+        {
+            let mut open_orders = self.open_orders.lock().await;
+            open_orders.link(&group, cancel_threshold);
+            let mut stop = OrderState::new(stop_loss);
+            stop.status = OrderStatus::Untriggered;
+            open_orders.insert(stop);
+            let take_profit = OrderState::new(take_profit);
+            self.queue_limit(&take_profit);
+            open_orders.insert(take_profit);
+        }
+
+        let mut fired = HashSet::new();
+        self.fire_stops(symbol.as_str(), &mut fired, Some(&group))
+            .await;
+        self.fill_limits(symbol.as_str(), &mut fired, Some(&group))
+            .await;
+        Ok(group)
+    }
+
+    pub async fn open_orders(&self) -> Vec<OrderState> {
+        let states: Vec<OrderState> = self.open_orders.lock().await.iter().cloned().collect();
+        self.with_queue_ahead(states)
+    }
+
+    // Fills in the matching engine's queue estimates
+    fn with_queue_ahead(&self, mut states: Vec<OrderState>) -> Vec<OrderState> {
+        let engine = self.matching.lock().unwrap();
+        for state in &mut states {
+            state.queue_ahead = engine.queue_ahead(state.order.symbol.as_str(), &state.order.id);
+        }
+        states
+    }
+
+    // Cancel thresholds of the live one-cancels-other groups
+    pub async fn link_thresholds(&self) -> HashMap<String, f64> {
+        self.open_orders.lock().await.link_thresholds().clone()
+    }
+
+    /// Puts orders saved before a restart back to work, with their fills
+    /// so far. Their client order ids count as accepted again, so
+    /// resubmitting one still places nothing new.
+    pub async fn restore_orders(
+        &self,
+        orders: Vec<OrderState>,
+        link_thresholds: HashMap<String, f64>,
+    ) {
+        let mut open_orders = self.open_orders.lock().await;
+        let mut submitted = self.submitted.lock().unwrap();
+        for (group, threshold) in link_thresholds {
+            open_orders.link(&group, threshold);
+        }
+        for state in orders {
+            submitted.insert(&state.order.client_order_id, &state.order.id);
+            self.queue_limit(&state);
+            open_orders.restore(state);
+        }
+    }
+
+    pub async fn open_orders_for_symbol(&self, symbol: &str) -> Vec<OrderState> {
+        let states = self
+            .open_orders
+            .lock()
+            .await
+            .for_symbol(symbol)
+            .cloned()
+            .collect();
+        self.with_queue_ahead(states)
+    }
+
+    pub async fn cancel_order(&self, order_id: &str) -> Result<(), ExecutionError> {
+        self.cancel(order_id, true).await
+    }
+
+    // cancel_order, drawing on the cancel budget only if `throttled`
+    async fn cancel(&self, order_id: &str, throttled: bool) -> Result<(), ExecutionError> {
+        if self.open_orders.lock().await.get(order_id).is_none() {
+            return Err(ExecutionError::UnknownOrder {
+                order_id: order_id.to_string(),
+            });
+        }
+
+        if throttled {
+            self.rate_limiter
+                .acquire(RateLimitKind::Cancel, self.clock.as_ref())
+                .await
+                .map_err(|kind| ExecutionError::RateLimited { kind })?;
+        }
+
+        // It may have gone while we waited for a cancel token
+        let Some(cancelled) = self.open_orders.lock().await.remove(order_id) else {
+            return Err(ExecutionError::UnknownOrder {
+                order_id: order_id.to_string(),
+            });
+        };
+        println!("Cancelled order: {}", order_id);
+        self.journal_event(&cancelled.order, OrderEvent::Cancelled, None);
+        Ok(())
+    }
+
+    /// Atomically moves a working order to a new price and/or total
+    /// quantity and returns the replacement's order id. Quantity already
+    /// filled carries over (the new quantity is the new total, so it must
+    /// exceed it), as do fills that land while the replace is in flight.
+    /// Live connectors map this onto the venue's cancel/replace.
+    pub async fn replace_order(
+        &self,
+        order_id: &str,
+        new_price: Option<f64>,
+        new_quantity: Option<f64>,
+    ) -> Result<String, ExecutionError> {
+        let reject = |reason: &str| ExecutionError::ReplaceRejected {
+            order_id: order_id.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let mut open_orders = self.open_orders.lock().await;
+        let Some(current) = open_orders.get(order_id).cloned() else {
+            return Err(ExecutionError::UnknownOrder {
+                order_id: order_id.to_string(),
+            });
+        };
+        if current.status == OrderStatus::PendingReplace {
+            return Err(reject("a replace is already in flight"));
+        }
+        if current.order.order_type.is_stop() {
+            return Err(reject("stop orders can't be replaced"));
+        }
+        if new_price.is_some_and(|p| p <= 0.0) {
+            return Err(reject("price must be positive"));
+        }
+        // On the symbol's grids, like any other submit
+        let mut amended = Order {
+            price: new_price.or(current.order.price),
+            quantity: new_quantity.unwrap_or(current.order.quantity),
+            ..current.order.clone()
+        };
+        if let Some(meta) = self.symbols.get(amended.symbol.as_str()) {
+            meta.normalize(&mut amended, None)?;
+        }
+        if amended.quantity <= current.filled_quantity {
+            return Err(reject("new quantity is already filled"));
+        }
+        open_orders.begin_replace(order_id);
+        drop(open_orders);
+
+        if let Err(kind) = self
+            .rate_limiter
+            .acquire(RateLimitKind::Submit, self.clock.as_ref())
+            .await
+        {
+            self.open_orders.lock().await.abort_replace(order_id);
+            return Err(ExecutionError::RateLimited { kind });
+        }
+
+        let replacement = Order {
+            id: Uuid::new_v4().to_string(),
+            client_order_id: Uuid::new_v4().to_string(),
+            price: amended.price,
+            quantity: amended.quantity,
+            timestamp: self.clock.now_secs(),
+            ..current.order
+        };
+        let new_id = replacement.id.clone();
+        let client_order_id = replacement.client_order_id.clone();
+
+        // The ack: swap in the replacement unless the order completed or was
+        // cancelled while we waited
+        let replaced = self
+            .open_orders
+            .lock()
+            .await
+            .complete_replace(order_id, replacement)
+            .cloned();
+        let Some(replaced) = replaced else {
+            return Err(ExecutionError::UnknownOrder {
+                order_id: order_id.to_string(),
+            });
+        };
+        // A replacement joins the back of the queue at its price
+        self.queue_limit(&replaced);
+        self.submitted
+            .lock()
+            .unwrap()
+            .insert(&client_order_id, &new_id);
+        if let Some(journal) = &self.journal {
+            journal.record(JournalEntry::OrderEvent {
+                order_id: order_id.to_string(),
+                symbol: replaced.order.symbol.to_string(),
+                event: OrderEvent::Replaced,
+                detail: Some(new_id.clone()),
+                timestamp: self.clock.now_secs(),
+            });
+        }
+        self.journal_submission(&replaced.order);
+
+        println!(
+            "Replaced order {} with {}: {:?} x {:.4} ({:.4} filled)",
+            order_id,
+            new_id,
+            replaced.order.price,
+            replaced.order.quantity,
+            replaced.filled_quantity
+        );
+        Ok(new_id)
+    }
+
+    /// Cancels every order that is working at the moment of the call.
+    ///
+    /// The set of orders is snapshotted up front; that snapshot is the
+    /// barrier. Orders submitted after it are left working, never dropped
+    /// silently, so callers that need a quiet book must stop submitting
+    /// first (as the kill switch does). Orders that fill or are cancelled
+    /// elsewhere in the meantime are skipped.
+    pub async fn cancel_all(&self) -> CancelAllReport {
+        let order_ids = self.open_order_ids().await;
+        self.cancel_each(order_ids, true).await
+    }
+
+    // cancel_all for the kill switch. Its cancels skip the rate limiter, so
+    // an exhausted budget under the Reject policy can't leave orders
+    // working on a halted bot.
+    pub async fn emergency_cancel_all(&self) -> CancelAllReport {
+        let order_ids = self.open_order_ids().await;
+        self.cancel_each(order_ids, false).await
+    }
+
+    async fn open_order_ids(&self) -> Vec<String> {
+        self.open_orders
+            .lock()
+            .await
+            .iter()
+            .map(|s| s.order.id.clone())
+            .collect()
+    }
+
+    // cancel_all restricted to one symbol, with the same barrier semantics
+    pub async fn cancel_all_for_symbol(&self, symbol: &str) -> CancelAllReport {
+        let order_ids: Vec<String> = self
+            .open_orders
+            .lock()
+            .await
+            .for_symbol(symbol)
+            .map(|s| s.order.id.clone())
+            .collect();
+        self.cancel_each(order_ids, true).await
+    }
+
+    // Cancels every working leg of a one-cancels-other group
+    pub async fn cancel_group(&self, group: &str) -> CancelAllReport {
+        let order_ids: Vec<String> = self
+            .open_orders
+            .lock()
+            .await
+            .linked(group)
+            .map(|s| s.order.id.clone())
+            .collect();
+        self.cancel_each(order_ids, true).await
+    }
+
+    async fn cancel_each(&self, order_ids: Vec<String>, throttled: bool) -> CancelAllReport {
+        let mut report = CancelAllReport::default();
+        for order_id in order_ids {
+            match self.cancel(&order_id, throttled).await {
+                Ok(()) => report.cancelled.push(order_id),
+                Err(ExecutionError::UnknownOrder { .. }) => {}
+                Err(e) => report.failed.push((order_id, e)),
+            }
+        }
+        report
+    }
+}
+
+// A registered strategy plus its runtime controls. The enabled and shadow
+// flags and the counters are atomics so they can be read and flipped
+// without blocking the trading loop.
+pub struct StrategyEntry {
+    strategy: Box<dyn TradingStrategy>,
+    symbols: Option<HashSet<String>>, // None = all symbols
+    trigger: EvaluationTrigger,
+    enabled: AtomicBool,
+    // Signals become hypothetical fills instead of orders. Kept apart from
+    // enabled so a shadow strategy disabled by its panics comes back in
+    // shadow.
+    shadow: AtomicBool,
+    signal_count: AtomicU64,
+    order_count: AtomicU64,
+    signals_by_symbol: std::sync::Mutex<HashMap<String, u64>>,
+    panic_count: AtomicU64,
+    // Panics since the strategy last returned normally
+    consecutive_panics: AtomicU32,
+    // Longest a position it opened is held, in seconds
+    max_holding_secs: Option<u64>,
+}
+
+impl StrategyEntry {
+    pub fn new(strategy: Box<dyn TradingStrategy>) -> Self {
+        Self {
+            strategy,
+            symbols: None,
+            trigger: EvaluationTrigger::default(),
+            enabled: AtomicBool::new(true),
+            shadow: AtomicBool::new(false),
+            signal_count: AtomicU64::new(0),
+            order_count: AtomicU64::new(0),
+            signals_by_symbol: std::sync::Mutex::new(HashMap::new()),
+            panic_count: AtomicU64::new(0),
+            consecutive_panics: AtomicU32::new(0),
+            max_holding_secs: None,
+        }
+    }
+
+    // Restrict the strategy to the given symbols
+    pub fn with_symbols(mut self, symbols: Vec<String>) -> Self {
+        self.symbols = Some(symbols.into_iter().collect());
+        self
+    }
+
+    pub fn with_trigger(mut self, trigger: EvaluationTrigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    pub fn with_mode(self, mode: StrategyMode) -> Self {
+        self.set_mode(mode);
+        self
+    }
+
+    // Positions the strategy opens are closed once held this long
+    pub fn with_max_holding_secs(mut self, secs: Option<u64>) -> Self {
+        self.max_holding_secs = secs;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    pub fn is_shadow(&self) -> bool {
+        self.shadow.load(Ordering::Acquire)
+    }
+
+    pub fn mode(&self) -> StrategyMode {
+        if !self.is_enabled() {
+            StrategyMode::Disabled
+        } else if self.is_shadow() {
+            StrategyMode::Shadow
+        } else {
+            StrategyMode::Live
+        }
+    }
+
+    // Disabled leaves the shadow flag alone, so re-enabling restores
+    // whichever of live and shadow the strategy was in
+    fn set_mode(&self, mode: StrategyMode) {
+        match mode {
+            StrategyMode::Live => self.shadow.store(false, Ordering::Release),
+            StrategyMode::Shadow => self.shadow.store(true, Ordering::Release),
+            StrategyMode::Disabled => {}
+        }
+        self.enabled
+            .store(mode != StrategyMode::Disabled, Ordering::Release);
+    }
+
+    pub fn applies_to(&self, symbol: &str) -> bool {
+        self.symbols.as_ref().is_none_or(|s| s.contains(symbol))
+    }
+
+    fn record_signal(&self, symbol: &str) {
+        self.signal_count.fetch_add(1, Ordering::Relaxed);
+        *self
+            .signals_by_symbol
+            .lock()
+            .unwrap()
+            .entry(symbol.to_string())
+            .or_insert(0) += 1;
+    }
+
+    // Counts a panic and returns how many the strategy has raised in a row
+    fn record_panic(&self) -> u32 {
+        self.panic_count.fetch_add(1, Ordering::Relaxed);
+        self.consecutive_panics.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn info(&self) -> StrategyInfo {
+        let mut symbols: Option<Vec<String>> =
+            self.symbols.as_ref().map(|s| s.iter().cloned().collect());
+        if let Some(symbols) = symbols.as_mut() {
+            symbols.sort();
+        }
+
+        StrategyInfo {
+            name: self.strategy.name().to_string(),
+            enabled: self.is_enabled(),
+            mode: self.mode(),
+            symbols,
+            trigger: self.trigger,
+            signal_count: self.signal_count.load(Ordering::Relaxed),
+            order_count: self.order_count.load(Ordering::Relaxed),
+            signals_by_symbol: self.signals_by_symbol.lock().unwrap().clone(),
+            panic_count: self.panic_count.load(Ordering::Relaxed),
+            streak: Streak::default(),
+        }
+    }
+}
+
+// Snapshot of a strategy's runtime state, as reported by list_strategies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyInfo {
+    pub name: String,
+    pub enabled: bool,
+    pub mode: StrategyMode,
+    pub symbols: Option<Vec<String>>, // None = all symbols
+    pub trigger: EvaluationTrigger,
+    pub signal_count: u64,
+    pub order_count: u64,
+    pub signals_by_symbol: HashMap<String, u64>,
+    pub panic_count: u64,
+    // Losing trades and risk rejections in a row, and any cooldown
+    #[serde(default)]
+    pub streak: Streak,
+}
+
+// Longest a symbol's evaluation task waits for a price before re-checking
+// staleness, when it is driven by data rather than a timer
+const IDLE_WAKE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Main trading bot. Every component is shared behind an Arc, so clones are
+// cheap handles onto the same bot (used to hand it to spawned tasks).
+#[derive(Clone)]
+pub struct TradingBot {
+    strategies: Arc<RwLock<Vec<StrategyEntry>>>,
+    aggregator: Arc<SignalAggregator>,
+    risk_manager: Arc<RiskManager>,
+    market_feed: Arc<dyn MarketData>,
+    order_books: BookCache,
+    order_executor: Arc<OrderExecutor>,
+    price_history: Arc<RwLock<SymbolMap<PriceBuffer>>>,
+    price_history_config: PriceHistoryConfig,
+    indicators: Arc<RwLock<IndicatorHub>>,
+    candles: Arc<RwLock<CandleAggregator>>,
+    // Bars the shared ATR is taken over
+    atr_timeframe: Timeframe,
+    staleness: StalenessConfig,
+    freshness: Arc<Mutex<FreshnessTracker>>,
+    schedule: ScheduleConfig,
+    calendar: Arc<TradingCalendar>,
+    // Each symbol's session as last checked; symbols not in it are open
+    sessions: Arc<std::sync::Mutex<HashMap<String, SessionState>>>,
+    breaker: Arc<std::sync::Mutex<VolatilityBreaker>>,
+    tick_filter: Arc<std::sync::Mutex<TickFilter>>,
+    supervision: SupervisionConfig,
+    // Wakes each symbol's evaluation task when a price for it arrives
+    data_ready: Arc<std::sync::Mutex<SymbolMap<Arc<Notify>>>>,
+    // Bounds how many symbols are evaluated at once
+    evaluation_slots: Arc<Semaphore>,
+    // When each symbol's newest price arrived, where its pipeline timing
+    // starts
+    price_received: Arc<std::sync::Mutex<SymbolMap<Instant>>>,
+    latency: Arc<LatencyRecorder>,
+    is_running: Arc<Mutex<bool>>,
+    halted: Arc<AtomicBool>,
+    // Day (plus one) the daily loss limit was last announced on
+    loss_limit_day: Arc<AtomicU64>,
+    // By id, the paper venue among them. Stops, brackets and working
+    // order management are the paper executor's alone.
+    venues: Arc<BTreeMap<VenueId, Arc<dyn Exchange>>>,
+    // Where the bot's own orders go
+    venue: VenueId,
+    // As assembled: an unconfirmed live config runs as a dry run
+    mode: ExecutionMode,
+    // Resting protective order per symbol
+    protective_orders: Arc<Mutex<HashMap<String, Protection>>>,
+    clock: Arc<dyn Clock>,
+    journal: Option<Journal>,
+    state: StateConfig,
+    metrics: Arc<Metrics>,
+    // Symbols whose prices are being polled
+    symbols: Arc<Mutex<BTreeMap<String, Subscription>>>,
+    // Signals from outside the bot awaiting their symbol's evaluation
+    external_signals: Arc<ExternalSignals>,
+    // PnL split out by the strategy behind each fill
+    strategy_stats: Arc<std::sync::Mutex<StrategyStats>>,
+    // Per-strategy budgets and the notional working orders reserve
+    allocator: Arc<StrategyAllocator>,
+    // Equity sampled as the drawdown breaker checks it
+    equity_curve: Arc<EquityCurve>,
+    // Hypothetical fills of each shadow strategy, kept per strategy since
+    // shadow positions are never netted against anything
+    shadow_stats: Arc<std::sync::Mutex<BTreeMap<String, StrategyStats>>>,
+    attribution: AttributionConfig,
+    marks: MarkConfig,
+    holding: HoldingConfig,
+    // Each position's protective levels, and when and by whom it was opened
+    monitor: Arc<std::sync::Mutex<PositionMonitor>>,
+    // Wakes the position monitor task when a price crosses a level
+    monitor_ready: Arc<Notify>,
+    reconcile: ReconcileConfig,
+    // Client order ids of venue orders reconciliation adopted, by venue
+    adopted_orders: Arc<std::sync::Mutex<HashMap<VenueId, HashSet<String>>>>,
+    report: ReportConfig,
+    // What becomes of a signal's order when its strategy already has one
+    // working for the same intent
+    order_guard: OrderGuardConfig,
+    // Signals open separate long and short legs rather than netting
+    hedge_mode: bool,
+    // Caps market orders at a share of the depth near mid
+    depth_sizing: DepthSizingConfig,
+    funding: FundingConfig,
+    // Each perp's latest funding rate
+    funding_rates: Arc<FundingRates>,
+    // The latest funding time settled, in seconds
+    funding_time: Arc<AtomicU64>,
+    // The spot/perp basis trade and whether it's open
+    basis: Arc<std::sync::Mutex<BasisStrategy>>,
+    // Losing and rejection streaks per strategy, and who is benched
+    streaks: Arc<std::sync::Mutex<StreakTracker>>,
+    events: broadcast::Sender<BotEvent>,
+}
+
+// What protects a position: a lone stop order, or an OCO bracket of stop
+// and take-profit
+#[derive(Debug, Clone)]
+enum Protection {
+    Stop { order_id: String },
+    Bracket { group: String },
+}
+
+impl Protection {
+    fn covers(&self, state: &OrderState) -> bool {
+        match self {
+            Protection::Stop { order_id } => *order_id == state.order.id,
+            Protection::Bracket { group } => state.order.link_group.as_ref() == Some(group),
+        }
+    }
+}
+
+// What TradingBot::recover put back
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    pub positions: Vec<String>,
+    pub working_orders: usize,
+    // Symbols whose protective stop (or bracket) was placed again
+    pub protected: Vec<String>,
+}
+
+// A symbol's price polling and evaluation tasks, which run while `active`
+// is set
+#[derive(Debug)]
+struct Subscription {
+    active: Arc<AtomicBool>,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+// What TradingBot::unsubscribe did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UnsubscribeReport {
+    pub cancelled_orders: usize,
+    // Whether a closing order was sent for the position
+    pub flattened: bool,
+}
+
+// Account-wide PnL, as reported by the API. The figures are in the
+// reporting currency; balances and by_quote are as held.
+#[derive(Debug, Clone, Serialize)]
+pub struct PnlReport {
+    pub daily_pnl: f64,
+    // The trading day's counters, daily_pnl among them
+    pub session: SessionStats,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub equity: f64,
+    pub currency: String,
+    // Assets taken 1:1 with the reporting currency for lack of a rate
+    pub unconverted: Vec<String>,
+    // Positions' PnL by quote asset, in that asset
+    pub by_quote: BTreeMap<String, QuotePnl>,
+    pub balances: HashMap<String, f64>,
+    pub halted: bool,
+    // Attributed to the strategy that opened each position
+    pub by_strategy: BTreeMap<String, StrategyPnl>,
+    // What shadow strategies would have made; none of it is in the figures
+    // above
+    pub shadow_by_strategy: BTreeMap<String, StrategyPnl>,
+}
+
+// PnL of the positions quoted in one asset, in that asset
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuotePnl {
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    // One of the asset in the reporting currency
+    pub rate: f64,
+}
+
+// What the kill switch did
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KillSwitchReport {
+    pub cancelled_orders: usize,
+    pub flattened_positions: Vec<String>,
+    pub failed_positions: Vec<String>,
+}
+
+impl TradingBot {
+    pub fn new(symbols: Vec<String>) -> Self {
+        TradingBotBuilder::new().with_symbols(symbols).assemble()
+    }
+
+    pub fn from_config(config: &BotConfig) -> Self {
+        TradingBotBuilder::from_config(config).assemble()
+    }
+
+    // A bot whose timestamps, trading days and loop pacing follow `clock`
+    pub fn from_config_with_clock(config: &BotConfig, clock: Arc<dyn Clock>) -> Self {
+        TradingBotBuilder::from_config(config)
+            .with_clock(clock)
+            .assemble()
+    }
+
+    // A bot assembled from injected components, validated when built
+    pub fn builder() -> TradingBotBuilder {
+        TradingBotBuilder::new()
+    }
+
+    // The simulator, wrapped in a recorder when recording is enabled
+    fn market_feed(config: &BotConfig, clock: Arc<dyn Clock>) -> Arc<dyn MarketData> {
+        let feed: Arc<dyn MarketData> = Arc::new(MarketDataFeed::with_simulation(
+            config.symbols.clone(),
+            &config.simulation,
+            clock.clone(),
+        ));
+        if !config.recording.enabled {
+            return feed;
+        }
+        match MarketRecorder::new(&config.recording) {
+            Ok(recorder) => Arc::new(RecordingFeed::new(feed, recorder.with_clock(clock))),
+            Err(e) => {
+                println!("Market data recording disabled: {}", e);
+                feed
+            }
+        }
+    }
+
+    // Replaces the market data source (a replay, a venue connection),
+    // which the paper venue then fills against
+    pub fn with_market_data(mut self, feed: Arc<dyn MarketData>) -> Self {
+        let paper = PaperExchange::new(
+            feed.clone(),
+            self.order_executor.clone(),
+            self.clock.clone(),
+        );
+        Arc::make_mut(&mut self.venues).insert(VenueId::paper(), Arc::new(paper));
+        self.market_feed = feed;
+        self
+    }
+
+    // Every venue the bot can trade on, its own paper venue included
+    pub fn venues(&self) -> impl Iterator<Item = &Arc<dyn Exchange>> {
+        self.venues.values()
+    }
+
+    pub fn venue(&self, id: &VenueId) -> Option<&Arc<dyn Exchange>> {
+        self.venues.get(id)
+    }
+
+    // Where the bot's own orders go
+    pub fn primary_venue(&self) -> &VenueId {
+        &self.venue
+    }
+
+    // Whether orders are paper, dry-run or live
+    pub fn execution_mode(&self) -> ExecutionMode {
+        self.mode
+    }
+
+    // Sends the order to the venue it names
+    async fn submit_to_venue(&self, mut order: Order) -> Result<String, ExecutionError> {
+        let Some(exchange) = self.venues.get(&order.venue) else {
+            return Err(ExecutionError::InvalidOrder {
+                reason: format!("unknown venue {}", order.venue),
+            });
+        };
+        // The paper executor caps it again when it executes
+        if !self.risk_manager.cap_reduce_only(&mut order).await {
+            println!(
+                "Reduce-only order {} not sent: no {} position on {} to reduce",
+                order.id, order.symbol, order.venue
+            );
+            return Ok(order.id);
+        }
+        exchange.submit_order(order).await
+    }
+
+    // What metrics::serve exposes
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    // Live activity: ticks, signals, orders, fills, rejections, positions.
+    // A subscriber that falls behind misses events rather than slowing
+    // trading down.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<BotEvent> {
+        self.events.subscribe()
+    }
+
+    // For components outside the bot, such as feed supervisors, to publish
+    // on the same channel
+    pub fn event_sender(&self) -> broadcast::Sender<BotEvent> {
+        self.events.clone()
+    }
+
+    // Where a venue's user data stream sends fills: booked exactly as paper
+    // fills are
+    pub fn fill_sender(&self) -> mpsc::UnboundedSender<Fill> {
+        self.order_executor.fill_sender()
+    }
+
+    pub async fn update_balances(&self, balances: &HashMap<String, f64>) {
+        self.risk_manager.update_balances(balances).await;
+    }
+
+    // The newest `n` closed candles of the symbol, oldest first
+    pub async fn candles(&self, symbol: &str, timeframe: Timeframe, n: usize) -> Vec<Candle> {
+        let Some(id) = SymbolId::get(symbol) else {
+            return Vec::new();
+        };
+        let mut candles = self.candles.write().await;
+        candles.advance(self.clock.now_secs());
+        candles.candles(id, timeframe, n)
+    }
+
+    /// The symbol's realized volatility and ATR, for risk checks and
+    /// sizing; each is None until it has warmed up
+    pub async fn volatility(&self, symbol: &str) -> VolatilityEstimate {
+        let Some(id) = SymbolId::get(symbol) else {
+            return VolatilityEstimate {
+                realized: None,
+                atr: None,
+            };
+        };
+        let realized = self
+            .indicators
+            .read()
+            .await
+            .get(id)
+            .and_then(|indicators| indicators.realized_volatility());
+        let mut candles = self.candles.write().await;
+        candles.advance(self.clock.now_secs());
+        let atr = candles
+            .get(id)
+            .and_then(|candles| candles.atr(self.atr_timeframe));
+        VolatilityEstimate { realized, atr }
+    }
+
+    // Builds the event only if someone is listening
+    fn publish(&self, event: impl FnOnce() -> BotEvent) {
+        if self.events.receiver_count() > 0 {
+            let _ = self.events.send(event());
+        }
+    }
+
+    // Commission schedule, for strategies sizing a minimum profitable spread
+    pub fn fee_model(&self) -> &FeeModel {
+        self.order_executor.fee_model()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted.load(Ordering::Acquire)
+    }
+
+    // Positions sorted by symbol, marked at the latest prices
+    pub async fn positions(&self) -> Vec<Position> {
+        let mut positions = self.risk_manager.positions().await;
+        positions.sort_by_key(|a| a.symbol);
+        positions
+    }
+
+    pub async fn open_orders(&self) -> Vec<OrderState> {
+        self.order_executor.open_orders().await
+    }
+
+    pub async fn pnl(&self) -> PnlReport {
+        let positions = self.risk_manager.positions().await;
+        let mut rates = Vec::with_capacity(positions.len());
+        let mut by_quote: BTreeMap<String, QuotePnl> = BTreeMap::new();
+        for position in &positions {
+            let (quote, rate) = self
+                .risk_manager
+                .quote_conversion(position.symbol.as_str())
+                .await;
+            let row = by_quote.entry(quote).or_default();
+            row.realized_pnl += position.realized_pnl;
+            row.unrealized_pnl += position.unrealized_pnl;
+            row.rate = rate;
+            rates.push(rate);
+        }
+        let currency = self.risk_manager.currency();
+        PnlReport {
+            daily_pnl: self.risk_manager.daily_pnl().await,
+            session: self.risk_manager.session().stats(),
+            realized_pnl: positions
+                .iter()
+                .zip(&rates)
+                .map(|(p, rate)| p.realized_pnl * rate)
+                .sum(),
+            unrealized_pnl: positions
+                .iter()
+                .zip(&rates)
+                .map(|(p, rate)| p.unrealized_pnl * rate)
+                .sum(),
+            equity: self.risk_manager.equity().await,
+            currency: currency.reporting().to_string(),
+            unconverted: currency.unconverted(),
+            by_quote,
+            balances: self.risk_manager.balances().await,
+            halted: self.is_halted(),
+            by_strategy: self.strategy_pnl().await,
+            shadow_by_strategy: self.shadow_strategy_pnl().await,
+        }
+    }
+
+    // The day's report so far, from the fills RiskManager booked today
+    pub async fn daily_report(&self) -> DailyReport {
+        let (blotter, daily_pnl) = self.risk_manager.blotter().await;
+        self.report_for(&blotter, daily_pnl).await
+    }
+
+    // A day's fills with the book as it stands now
+    async fn report_for(&self, blotter: &Blotter, daily_pnl: f64) -> DailyReport {
+        let mut positions = Vec::new();
+        for p in self.risk_manager.positions().await {
+            if p.quantity == 0.0 {
+                continue;
+            }
+            let (_, rate) = self.risk_manager.quote_conversion(p.symbol.as_str()).await;
+            positions.push(PositionRow {
+                mark: self
+                    .risk_manager
+                    .marks()
+                    .price(p.symbol.as_str())
+                    .unwrap_or(p.avg_price),
+                venue: p.venue,
+                symbol: p.symbol.to_string(),
+                side: p.side,
+                quantity: p.quantity,
+                avg_price: p.avg_price,
+                unrealized_pnl: p.unrealized_pnl,
+                rate,
+            });
+        }
+        positions.sort_by(|a, b| (&a.symbol, &a.venue, a.side).cmp(&(&b.symbol, &b.venue, b.side)));
+        let limits = self.risk_manager.limit_usage().await;
+        let currency = self.risk_manager.currency();
+        DailyReport::new(
+            blotter.day(),
+            self.clock.now_secs(),
+            blotter.fills(),
+            positions,
+            limits,
+        )
+        .with_funding_pnl(blotter.funding())
+        .with_daily_pnl(daily_pnl)
+        .with_currency(currency.reporting(), currency.unconverted())
+    }
+
+    // Logs the report and, if enabled, writes it out
+    fn publish_report(&self, report: &DailyReport) {
+        println!("{}", report.to_text());
+        match report.write(&self.report.dir, &self.report.formats) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("Wrote {}", path.display());
+                }
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    /// Each strategy's realized and unrealized PnL, fees, trades and hit
+    /// rate, with open lots marked at their symbol's latest price
+    pub async fn strategy_pnl(&self) -> BTreeMap<String, StrategyPnl> {
+        let symbols = self.strategy_stats.lock().unwrap().open_symbols();
+        let marks = self.marks(symbols).await;
+        self.strategy_stats
+            .lock()
+            .unwrap()
+            .snapshot(|symbol| marks.get(symbol).copied())
+    }
+
+    /// The same for shadow strategies' hypothetical fills
+    pub async fn shadow_strategy_pnl(&self) -> BTreeMap<String, StrategyPnl> {
+        let symbols = self
+            .shadow_stats
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(StrategyStats::open_symbols)
+            .collect();
+        let marks = self.marks(symbols).await;
+        self.shadow_stats
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(|stats| stats.snapshot(|symbol| marks.get(symbol).copied()))
+            .collect()
+    }
+
+    // Latest prices of the given symbols, where known
+    async fn marks(&self, symbols: Vec<String>) -> HashMap<String, f64> {
+        let mut marks = HashMap::new();
+        for symbol in symbols {
+            if let Some(mark) = self.risk_manager.mark_price(&symbol).await {
+                marks.insert(symbol, mark);
+            }
+        }
+        marks
+    }
+
+    // Per-strategy PnL gauges, marked at the latest prices
+    async fn update_strategy_metrics(
+        &self,
+    ) -> (BTreeMap<String, StrategyPnl>, BTreeMap<String, StrategyPnl>) {
+        let live = self.strategy_pnl().await;
+        let shadow = self.shadow_strategy_pnl().await;
+        for (mode, report) in [(StrategyMode::Live, &live), (StrategyMode::Shadow, &shadow)] {
+            for (strategy, pnl) in report {
+                self.metrics
+                    .strategy_realized_pnl
+                    .with_label_values(&[strategy, mode.as_str()])
+                    .set(pnl.realized_pnl);
+                self.metrics
+                    .strategy_unrealized_pnl
+                    .with_label_values(&[strategy, mode.as_str()])
+                    .set(pnl.unrealized_pnl);
+            }
+        }
+        (live, shadow)
+    }
+
+    // Books what a shadow strategy's signal would have filled at
+    fn shadow_fill(&self, strategy: &str, signal: &TradingSignal, orderbook: &OrderBook) {
+        let Some(fill) = shadow::hypothetical_fill(
+            strategy,
+            signal,
+            orderbook,
+            self.order_executor.fee_model(),
+            &self.venue,
+            self.clock.now_secs(),
+        ) else {
+            println!(
+                "Shadow signal from {} on {} had no book to fill against",
+                strategy, signal.symbol
+            );
+            return;
+        };
+        println!("Shadow fill: {:?}", fill);
+        let closed = self
+            .shadow_stats
+            .lock()
+            .unwrap()
+            .entry(strategy.to_string())
+            .or_default()
+            .apply_fill(&fill);
+        self.count_trades(StrategyMode::Shadow, closed);
+    }
+
+    fn count_trades(&self, mode: StrategyMode, closed: Vec<ClosedTrade>) {
+        for trade in closed {
+            self.metrics
+                .strategy_trades
+                .with_label_values(&[&trade.strategy, mode.as_str()])
+                .inc();
+            if trade.pnl > 0.0 {
+                self.metrics
+                    .strategy_winning_trades
+                    .with_label_values(&[&trade.strategy, mode.as_str()])
+                    .inc();
+            }
+        }
+    }
+
+    // Symbols being traded, sorted
+    pub async fn symbols(&self) -> Vec<String> {
+        self.symbols.lock().await.keys().cloned().collect()
+    }
+
+    /// Starts trading another symbol while the bot runs. Its prices are
+    /// polled from now on and the trading loop picks it up once it has
+    /// enough history.
+    pub async fn subscribe(&self, symbol: &str) -> Result<(), String> {
+        if !*self.is_running.lock().await {
+            return Err("the bot is not running".to_string());
+        }
+        if !self.watch_symbol(symbol.to_string()).await {
+            return Err(format!("{} is already being traded", symbol));
+        }
+        println!("Subscribed to {}", symbol);
+        Ok(())
+    }
+
+    /// Stops trading a symbol: its price polling ends and its history,
+    /// indicators and candles are dropped, so the trading loop no longer
+    /// sees it. With `flatten`, its orders (protection included) are
+    /// cancelled and the position is closed with a market order; without,
+    /// both are left as they are.
+    pub async fn unsubscribe(
+        &self,
+        symbol: &str,
+        flatten: bool,
+    ) -> Result<UnsubscribeReport, String> {
+        let Some(subscription) = self.symbols.lock().await.remove(symbol) else {
+            return Err(format!("{} is not being traded", symbol));
+        };
+        // Let both tasks finish their pass so neither can record a price or
+        // place an order after the history is gone
+        subscription.active.store(false, Ordering::Release);
+        let id = Symbol::intern(symbol).id();
+        if let Some(ready) = self.data_ready.lock().unwrap().remove(id) {
+            ready.notify_one();
+        }
+        futures::future::join_all(subscription.tasks).await;
+
+        {
+            let mut history = self.price_history.write().await;
+            history.remove(id);
+            self.indicators.write().await.remove(id);
+            self.candles.write().await.remove(id);
+        }
+        self.freshness.lock().await.remove(symbol);
+        self.sessions.lock().unwrap().remove(symbol);
+        self.breaker.lock().unwrap().remove(symbol);
+        self.risk_manager.correlations().remove(symbol);
+        self.tick_filter.lock().unwrap().remove(symbol);
+        self.price_received.lock().unwrap().remove(id);
+        self.external_signals.clear(symbol);
+
+        let mut report = UnsubscribeReport::default();
+        if flatten {
+            let cancels = self.order_executor.cancel_all_for_symbol(symbol).await;
+            report.cancelled_orders = cancels.cancelled.len();
+            for (order_id, e) in cancels.failed {
+                println!("Failed to cancel {}: {}", order_id, e);
+            }
+            self.protective_orders.lock().await.remove(symbol);
+            report.flattened = self.flatten_symbol(symbol).await;
+        }
+        println!("Unsubscribed from {}: {:?}", symbol, report);
+        Ok(report)
+    }
+
+    /// Queues a signal from outside the bot for its symbol's next
+    /// evaluation, where it is aggregated with the strategies' signals,
+    /// risk-checked and executed as theirs are. Signals without an expiry
+    /// are good for default_ttl_ms.
+    pub async fn submit_external_signal(
+        &self,
+        signal: ExternalSignal,
+        default_ttl_ms: u64,
+    ) -> Result<PendingSignal, SignalRejection> {
+        let pending = signal.validate(self.clock.now_ms(), default_ttl_ms)?;
+        let symbol = pending.signal.symbol.to_string();
+        if !self.symbols.lock().await.contains_key(&symbol) {
+            return Err(SignalRejection::UnknownSymbol(symbol));
+        }
+        println!(
+            "External signal from {} on {}: {:?}",
+            pending.strategy, symbol, pending.signal
+        );
+        self.external_signals.push(pending.clone());
+        let id = Symbol::intern(&symbol).id();
+        if let Some(ready) = self.data_ready.lock().unwrap().get(id) {
+            ready.notify_one();
+        }
+        Ok(pending)
+    }
+
+    // Spawns the tasks polling the symbol's price and book and evaluating
+    // it; false if there are some
+    async fn watch_symbol(&self, symbol: String) -> bool {
+        let mut symbols = self.symbols.lock().await;
+        if symbols.contains_key(&symbol) {
+            return false;
+        }
+        let active = Arc::new(AtomicBool::new(true));
+        let ready = Arc::new(Notify::new());
+        let interned = Symbol::intern(&symbol);
+        // Its marks may price a quote asset in the reporting currency
+        self.risk_manager.currency().add_symbol(&symbol);
+        self.data_ready
+            .lock()
+            .unwrap()
+            .insert(interned.id(), ready.clone());
+
+        let (bot, running, polled) = (self.clone(), active.clone(), interned);
+        let polling = self.spawn_supervised(format!("{} polling", symbol), move || {
+            let (bot, running, polled) = (bot.clone(), running.clone(), polled);
+            async move { bot.polling_loop(&polled, &running).await }
+        });
+        let (bot, running, evaluated) = (self.clone(), active.clone(), interned);
+        let evaluation = self.spawn_supervised(format!("{} evaluation", symbol), move || {
+            let (bot, running, evaluated, ready) =
+                (bot.clone(), running.clone(), evaluated, ready.clone());
+            async move { bot.evaluation_loop(&evaluated, &running, &ready).await }
+        });
+
+        symbols.insert(
+            symbol,
+            Subscription {
+                active,
+                tasks: vec![polling, evaluation],
+            },
+        );
+        true
+    }
+
+    /// Positions, balances and working orders as they stand now. Protective
+    /// orders are left out; recover() places fresh ones.
+    pub async fn snapshot(&self) -> BotSnapshot {
+        let protective: Vec<Protection> = self
+            .protective_orders
+            .lock()
+            .await
+            .values()
+            .cloned()
+            .collect();
+        let working_orders: Vec<OrderState> = self
+            .order_executor
+            .open_orders()
+            .await
+            .into_iter()
+            .filter(|s| !protective.iter().any(|p| p.covers(s)))
+            .collect();
+        let mut link_thresholds = self.order_executor.link_thresholds().await;
+        link_thresholds.retain(|group, _| {
+            working_orders
+                .iter()
+                .any(|s| s.order.link_group.as_ref() == Some(group))
+        });
+
+        BotSnapshot {
+            saved_at_ms: self.clock.now_ms(),
+            positions: self.risk_manager.positions().await,
+            balances: self.risk_manager.balances().await,
+            daily_pnl: self.risk_manager.daily_pnl().await,
+            working_orders,
+            link_thresholds,
+        }
+    }
+
+    // Writes a snapshot to the configured state path
+    pub async fn save_state(&self) -> Result<(), String> {
+        state::save_snapshot(&self.state.path, &self.snapshot().await)
+    }
+
+    /// Reloads the snapshot at the configured state path, if there is one:
+    /// positions at their last average price, balances, working orders, and
+    /// the day's PnL if the snapshot is from the same trading session. Then places
+    /// protective stops around every open position again. Call it before
+    /// start(), whose periodic snapshots would otherwise overwrite the saved
+    /// state. Live connectors will reconcile the result against the venue.
+    pub async fn recover(&self) -> Result<Option<RecoveryReport>, String> {
+        let Some(snapshot) = state::load_snapshot(&self.state.path)? else {
+            return Ok(None);
+        };
+        let mut report = RecoveryReport {
+            positions: snapshot
+                .positions
+                .iter()
+                .filter(|p| p.quantity != 0.0)
+                .map(|p| p.symbol.to_string())
+                .collect(),
+            working_orders: snapshot.working_orders.len(),
+            protected: Vec::new(),
+        };
+
+        let mut rules = Vec::new();
+        for position in &snapshot.positions {
+            rules.push(self.exit_rules(position).await);
+        }
+        {
+            let mut stats = self.strategy_stats.lock().unwrap();
+            for position in &snapshot.positions {
+                stats.restore_position(position);
+            }
+            let mut monitor = self.monitor.lock().unwrap();
+            for (position, rules) in snapshot.positions.iter().zip(rules) {
+                monitor.restore_position(position, rules, self.clock.now_secs());
+            }
+        }
+        self.risk_manager
+            .restore(
+                snapshot.positions,
+                snapshot.balances,
+                snapshot.daily_pnl,
+                snapshot.saved_at_ms / 1_000,
+            )
+            .await;
+        self.order_executor
+            .restore_orders(snapshot.working_orders, snapshot.link_thresholds)
+            .await;
+        for symbol in &report.positions {
+            self.refresh_protective_stop(symbol).await;
+            if self.protective_orders.lock().await.contains_key(symbol) {
+                report.protected.push(symbol.clone());
+            }
+        }
+
+        println!(
+            "Recovered {} position(s) and {} working order(s) from {}",
+            report.positions.len(),
+            report.working_orders,
+            self.state.path.display()
+        );
+        Ok(Some(report))
+    }
+
+    /// Stops acting on signals, cancels every pending order and flattens
+    /// every open position with a market order. The flattening orders bypass
+    /// strategies and risk checks but still go through position/PnL tracking.
+    /// The bot stays halted (market data keeps flowing) until resume().
+    pub async fn kill_switch(&self) -> KillSwitchReport {
+        self.halted.store(true, Ordering::Release);
+        // Wait out any in-flight evaluation pass so no order slips through
+        drop(self.strategies.write().await);
+        println!("🛑 Kill switch activated: halting trading and flattening");
+
+        let mut report = KillSwitchReport::default();
+
+        let cancels = self.order_executor.emergency_cancel_all().await;
+        report.cancelled_orders = cancels.cancelled.len();
+        for (order_id, e) in cancels.failed {
+            println!("Kill switch failed to cancel {}: {}", order_id, e);
+        }
+
+        for position in self.risk_manager.positions().await {
+            if position.quantity == 0.0 {
+                continue;
+            }
+
+            // Positions are closed out when the fills come back
+            let order = self.closing_order(&position);
+            match self.submit_to_venue(order).await {
+                Ok(_) => report.flattened_positions.push(position.symbol.to_string()),
+                Err(e) => {
+                    println!("Kill switch failed to flatten {}: {}", position.symbol, e);
+                    report.failed_positions.push(position.symbol.to_string());
+                }
+            }
+        }
+
+        println!("🛑 Kill switch complete: {:?}", report);
+        self.publish(|| BotEvent::KillSwitch(report.clone()));
+        report
+    }
+
+    // Sends a closing market order for the symbol's position on every venue
+    // holding one; true if any was sent
+    async fn flatten_symbol(&self, symbol: &str) -> bool {
+        let mut flattened = false;
+        for position in self.risk_manager.positions().await {
+            if position.symbol != symbol || position.quantity == 0.0 {
+                continue;
+            }
+            match self.submit_to_venue(self.closing_order(&position)).await {
+                Ok(_) => flattened = true,
+                Err(e) => println!("Failed to flatten {} on {}: {}", symbol, position.venue, e),
+            }
+        }
+        flattened
+    }
+
+    // A market order taking the position to flat
+    fn closing_order(&self, position: &Position) -> Order {
+        Order {
+            id: Uuid::new_v4().to_string(),
+            client_order_id: Uuid::new_v4().to_string(),
+            symbol: position.symbol,
+            side: if position.quantity > 0.0 {
+                OrderSide::Sell
+            } else {
+                OrderSide::Buy
+            },
+            order_type: OrderType::Market,
+            quantity: position.quantity.abs(),
+            price: None,
+            time_in_force: TimeInForce::Gtc,
+            timestamp: self.clock.now_secs(),
+            strategy: None,
+            link_group: None,
+            venue: position.venue.clone(),
+            position_side: position.side,
+            reduce_only: true,
+        }
+    }
+
+    // Leaves the halted state entered by the kill switch. The drawdown
+    // breaker is re-armed from the current equity.
+    pub async fn resume(&self) {
+        self.risk_manager.reset_drawdown().await;
+        self.halted.store(false, Ordering::Release);
+        println!("▶️ Trading resumed");
+    }
+
+    /// Checks every live venue's open orders and balances against the
+    /// bot's orders, positions and cash in the symbols it watches or holds,
+    /// and resolves what differs by the configured policy. Each difference
+    /// is journaled with what was done about it. Only live venues are
+    /// checked: on paper and in a dry run the bot's books are the venue's.
+    pub async fn reconcile(&self) -> ReconcileReport {
+        let symbols: Vec<String> = self.symbols.lock().await.keys().cloned().collect();
+        self.reconcile_symbols(symbols).await
+    }
+
+    async fn reconcile_symbols(&self, mut symbols: Vec<String>) -> ReconcileReport {
+        let mut report = ReconcileReport::default();
+        if self.mode != ExecutionMode::Live {
+            return report;
+        }
+        let positions = self.risk_manager.positions().await;
+        symbols.extend(positions.iter().map(|p| p.symbol.to_string()));
+        symbols.sort();
+        symbols.dedup();
+        let policy = self.reconcile.policy;
+
+        for (id, exchange) in self.venues.iter() {
+            if *id == VenueId::paper() {
+                continue;
+            }
+            let queried =
+                futures::future::try_join(exchange.open_orders(&symbols), exchange.balances())
+                    .await;
+            let (open_orders, balances) = match queried {
+                Ok(queried) => queried,
+                Err(e) => {
+                    println!("Couldn't reconcile with {}: {}", id, e);
+                    report.errors.push((id.clone(), e.to_string()));
+                    continue;
+                }
+            };
+            report.venues.push(id.clone());
+
+            // Flat in every symbol but those held there, hedge-mode legs
+            // summed as the venue nets them
+            let mut held: BTreeMap<String, f64> =
+                symbols.iter().map(|s| (s.clone(), 0.0)).collect();
+            for position in positions.iter().filter(|p| p.venue == *id) {
+                *held.entry(position.symbol.to_string()).or_default() += position.quantity;
+            }
+            // The account's cash is the primary venue's
+            let cash = match *id == self.venue {
+                true => Some(self.risk_manager.balances().await),
+                false => None,
+            };
+            let adopted = {
+                let mut adopted_orders = self.adopted_orders.lock().unwrap();
+                let adopted = adopted_orders.entry(id.clone()).or_default();
+                // Forget adopted orders once they stop working
+                adopted.retain(|client_order_id| {
+                    open_orders
+                        .iter()
+                        .any(|open| open.state.order.client_order_id == *client_order_id)
+                });
+                adopted.clone()
+            };
+            let discrepancies = reconcile::diff(
+                &open_orders,
+                &adopted,
+                &held,
+                cash.as_ref(),
+                &balances,
+                self.reconcile.tolerance_pct,
+            );
+            if discrepancies.is_empty() {
+                continue;
+            }
+
+            let mut lines = Vec::new();
+            for discrepancy in discrepancies {
+                let resolution = self.resolve(exchange, &discrepancy).await;
+                println!(
+                    "⚖️ Reconciliation with {}: {}; {}",
+                    id, discrepancy, resolution
+                );
+                if let Some(journal) = &self.journal {
+                    let quantities = discrepancy.quantities();
+                    journal.record(JournalEntry::Reconciliation {
+                        venue: id.clone(),
+                        discrepancy: discrepancy.kind().to_string(),
+                        subject: discrepancy.subject().to_string(),
+                        local: quantities.map(|(local, _)| local),
+                        venue_value: quantities.map(|(_, venue)| venue),
+                        action: resolution.to_string(),
+                        timestamp: self.clock.now_secs(),
+                    });
+                }
+                lines.push(discrepancy.to_string());
+                report
+                    .discrepancies
+                    .push((id.clone(), discrepancy, resolution));
+            }
+            report.halted |= policy == ReconcilePolicy::Halt;
+            self.publish(|| BotEvent::ReconciliationMismatch {
+                venue: id.clone(),
+                policy,
+                discrepancies: lines,
+                halted: policy == ReconcilePolicy::Halt,
+            });
+        }
+
+        if report.halted {
+            self.halted.store(true, Ordering::Release);
+            println!("🛑 Reconciliation mismatch: trading halted until resumed");
+        }
+        report
+    }
+
+    // Acts on a difference by the policy; under Halt nothing is changed
+    async fn resolve(&self, exchange: &Arc<dyn Exchange>, discrepancy: &Discrepancy) -> Resolution {
+        let policy = self.reconcile.policy;
+        match discrepancy {
+            _ if policy == ReconcilePolicy::Halt => Resolution::Halted,
+            Discrepancy::UnknownOrder(order) if policy == ReconcilePolicy::CancelUnknown => {
+                match exchange.cancel_order(order).await {
+                    Ok(()) => Resolution::Cancelled,
+                    Err(e) => Resolution::Failed(e.to_string()),
+                }
+            }
+            Discrepancy::UnknownOrder(order) => {
+                self.adopted_orders
+                    .lock()
+                    .unwrap()
+                    .entry(exchange.id().clone())
+                    .or_default()
+                    .insert(order.client_order_id.clone());
+                Resolution::Adopted
+            }
+            Discrepancy::Position { symbol, venue, .. } => {
+                let position = self
+                    .risk_manager
+                    .adopt_position(exchange.id(), symbol, *venue)
+                    .await;
+                let rules = self.exit_rules(&position).await;
+                self.monitor.lock().unwrap().restore_position(
+                    &position,
+                    rules,
+                    self.clock.now_secs(),
+                );
+                Resolution::Adopted
+            }
+            Discrepancy::Balance { asset, venue, .. } => {
+                self.risk_manager
+                    .update_balances(&HashMap::from([(asset.clone(), *venue)]))
+                    .await;
+                Resolution::Adopted
+            }
+        }
+    }
+
+    /// Enables or disables a strategy by name without restarting the bot.
+    ///
+    /// The flag is flipped under the strategy write lock, which the trading
+    /// loop holds for reading during a whole evaluation pass. Once this
+    /// returns, no pass that still sees the old state is in flight, so a
+    /// disabled strategy cannot produce any further orders. Returns false if
+    /// no strategy with that name is registered.
+    pub async fn set_strategy_enabled(&self, name: &str, enabled: bool) -> bool {
+        // A benched strategy goes back to the mode it was benched from
+        let benched = self.streaks.lock().unwrap().streak(name).benched;
+        if enabled && let Some(bench) = benched {
+            return self.set_strategy_mode(name, bench.restore).await;
+        }
+        let mode = match self
+            .strategies
+            .read()
+            .await
+            .iter()
+            .find(|s| s.strategy.name() == name)
+        {
+            Some(entry) if enabled && entry.is_shadow() => StrategyMode::Shadow,
+            Some(_) if enabled => StrategyMode::Live,
+            Some(_) => StrategyMode::Disabled,
+            None => return false,
+        };
+        self.set_strategy_mode(name, mode).await
+    }
+
+    /// Puts a strategy live, in shadow or disabled, under the same lock as
+    /// set_strategy_enabled: once this returns, no pass is still acting on
+    /// the strategy's old mode. Returns false if no strategy with that name
+    /// is registered.
+    pub async fn set_strategy_mode(&self, name: &str, mode: StrategyMode) -> bool {
+        let strategies = self.strategies.write().await;
+        match strategies.iter().find(|s| s.strategy.name() == name) {
+            Some(entry) => {
+                // A re-enabled strategy gets its full allowance of panics back
+                entry.consecutive_panics.store(0, Ordering::Relaxed);
+                entry.set_mode(mode);
+                // ...and its streaks start over, ending any cooldown
+                if self.streaks.lock().unwrap().clear(name) {
+                    self.journal_streak(name, Streak::default());
+                }
+                println!("Strategy {} is now {}", name, mode);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // With place_stop_orders on, keeps one stop-market order resting against
+    // the symbol's position, re-placed whenever the position changes, so the
+    // stop-loss holds even if this process stalls. With
+    // place_take_profit_orders too, the stop is bracketed with a take-profit
+    // limit, one-cancels-other.
+    async fn refresh_protective_stop(&self, symbol: &str) {
+        let params = self.risk_manager.params_for(symbol).await;
+        if !params.place_stop_orders {
+            return;
+        }
+
+        let mut protective = self.protective_orders.lock().await;
+        match protective.remove(symbol) {
+            Some(Protection::Stop { order_id }) => {
+                match self.order_executor.cancel_order(&order_id).await {
+                    // Already gone: it fired or was cancelled
+                    Ok(()) | Err(ExecutionError::UnknownOrder { .. }) => {}
+                    Err(e) => println!("Failed to cancel stop {}: {}", order_id, e),
+                }
+            }
+            Some(Protection::Bracket { group }) => {
+                for (order_id, e) in self.order_executor.cancel_group(&group).await.failed {
+                    println!("Failed to cancel bracket leg {}: {}", order_id, e);
+                }
+            }
+            None => {}
+        }
+
+        // Hedge-mode legs are left to the position monitor
+        let Some(position) = self
+            .risk_manager
+            .position(&VenueId::paper(), symbol, PositionSide::Both)
+            .await
+        else {
+            return;
+        };
+        if position.quantity == 0.0 {
+            return;
+        }
+
+        let side = if position.quantity > 0.0 {
+            OrderSide::Sell
+        } else {
+            OrderSide::Buy
+        };
+        let (stop_price, take_profit_price) = self.protective_prices(&position, &params).await;
+        let stop = Order {
+            id: Uuid::new_v4().to_string(),
+            client_order_id: Uuid::new_v4().to_string(),
+            symbol: SymbolId::intern(symbol),
+            side,
+            order_type: OrderType::StopMarket { stop_price },
+            quantity: position.quantity.abs(),
+            price: None,
+            time_in_force: TimeInForce::Gtc,
+            timestamp: self.clock.now_secs(),
+            strategy: None,
+            link_group: None,
+            // Stops are triggered by the paper executor
+            venue: VenueId::paper(),
+            position_side: PositionSide::Both,
+            reduce_only: true,
+        };
+
+        if params.place_take_profit_orders {
+            let take_profit = Order {
+                id: Uuid::new_v4().to_string(),
+                client_order_id: Uuid::new_v4().to_string(),
+                order_type: OrderType::Limit,
+                price: Some(take_profit_price),
+                ..stop.clone()
+            };
+            let bracket = OcoOrder {
+                stop_loss: stop,
+                take_profit,
+                cancel_threshold: 1.0,
+            };
+            match self.order_executor.submit_oco(bracket).await {
+                Ok(group) => {
+                    protective.insert(symbol.to_string(), Protection::Bracket { group });
+                }
+                Err(e) => println!("Failed to place protective bracket on {}: {}", symbol, e),
+            }
+            return;
+        }
+
+        match self.order_executor.submit_order(stop).await {
+            Ok(order_id) => {
+                protective.insert(symbol.to_string(), Protection::Stop { order_id });
+            }
+            Err(e) => println!("Failed to place protective stop on {}: {}", symbol, e),
+        }
+    }
+
+    // The position's stop-loss and take-profit prices, fixed from its
+    // average price until it next changes
+    async fn protective_prices(&self, position: &Position, params: &RiskParams) -> (f64, f64) {
+        let avg_price = position.avg_price;
+        let stop_distance = self
+            .risk_manager
+            .stop_distance(position.symbol.as_str(), avg_price)
+            .await;
+        if position.quantity > 0.0 {
+            (
+                avg_price - stop_distance,
+                avg_price * (1.0 + params.take_profit_pct),
+            )
+        } else {
+            (
+                avg_price + stop_distance,
+                avg_price * (1.0 - params.take_profit_pct),
+            )
+        }
+    }
+
+    // What the position monitor watches on the position: the levels its
+    // resting protective orders hold, and the ones it closes at itself
+    async fn exit_rules(&self, position: &Position) -> ExitRules {
+        if position.quantity == 0.0 {
+            return ExitRules::default();
+        }
+        let params = self.risk_manager.params_for(position.symbol.as_str()).await;
+        let (stop_price, take_profit_price) = self.protective_prices(position, &params).await;
+        // Protective orders rest on the paper executor alone, and only for
+        // net positions; the monitor watches a hedge-mode leg's levels
+        // instead
+        let hedged = position.side != PositionSide::Both;
+        let resting_stop =
+            params.place_stop_orders && position.venue == VenueId::paper() && !hedged;
+        let resting_take_profit = resting_stop && params.place_take_profit_orders;
+        let monitored_stop = params.monitor_exits || (hedged && params.place_stop_orders);
+        let monitored_take_profit = params.monitor_exits
+            || (hedged && params.place_stop_orders && params.place_take_profit_orders);
+        ExitRules {
+            stop_loss: (monitored_stop || resting_stop).then_some(Level {
+                price: stop_price,
+                resting: resting_stop,
+            }),
+            take_profit: (monitored_take_profit || resting_take_profit).then_some(Level {
+                price: take_profit_price,
+                resting: resting_take_profit,
+            }),
+            trailing_stop_pct: params.trailing_stop_pct,
+        }
+    }
+
+    /// Every open position's protective levels: stop-loss, take-profit,
+    /// trailing stop and when its time stop falls due
+    pub async fn protective_levels(&self) -> Vec<ProtectiveLevels> {
+        let strategies = self.strategies.read().await;
+        self.monitor
+            .lock()
+            .unwrap()
+            .levels(|symbol, opener| self.holding_limit(&strategies, symbol, opener))
+    }
+
+    pub async fn list_strategies(&self) -> Vec<StrategyInfo> {
+        let strategies = self.strategies.read().await;
+        let streaks = self.streaks.lock().unwrap();
+        strategies
+            .iter()
+            .map(|entry| StrategyInfo {
+                streak: streaks.streak(entry.strategy.name()),
+                ..entry.info()
+            })
+            .collect()
+    }
+
+    pub async fn start(&self, symbols: Vec<String>) {
+        *self.is_running.lock().await = true;
+        println!("Starting trading bot for symbols: {:?}", symbols);
+
+        let mut tasks = Vec::new();
+        self.restore_benched().await;
+
+        // Book fills into positions. Not joined: fills for orders still in
+        // flight at shutdown must be booked too, and the stream ends with
+        // the executor.
+        if let Some(fills) = self.order_executor.take_fills() {
+            // Shared so a restarted task picks up where the last one was
+            let fills = Arc::new(Mutex::new(fills));
+            let bot = self.clone();
+            self.spawn_supervised("fill booking".to_string(), move || {
+                let (bot, fills) = (bot.clone(), fills.clone());
+                async move {
+                    while let Some(fill) = fills.lock().await.recv().await {
+                        bot.on_fill(&fill).await;
+                    }
+                }
+            });
+        }
+
+        // Square the books with live venues before trading, and then
+        // every interval_secs
+        if self.reconcile.enabled {
+            self.reconcile_symbols(symbols.clone()).await;
+            if self.reconcile.interval_secs > 0 {
+                let bot = self.clone();
+                tasks.push(
+                    self.spawn_supervised("reconciliation".to_string(), move || {
+                        let bot = bot.clone();
+                        async move {
+                            let interval = Duration::from_secs(bot.reconcile.interval_secs);
+                            loop {
+                                bot.clock.sleep(interval).await;
+                                if !*bot.is_running.lock().await {
+                                    break;
+                                }
+                                bot.reconcile().await;
+                            }
+                        }
+                    }),
+                );
+            }
+        }
+
+        // Keep the perps' funding rates current from the primary venue
+        if self.funding.enabled && self.funding.poll_secs > 0 && self.venue != VenueId::paper() {
+            let bot = self.clone();
+            let polled = symbols.clone();
+            tasks.push(self.spawn_supervised("funding rates".to_string(), move || {
+                let (bot, symbols) = (bot.clone(), polled.clone());
+                async move {
+                    let interval = Duration::from_secs(bot.funding.poll_secs);
+                    while *bot.is_running.lock().await {
+                        bot.poll_funding_rates(&symbols).await;
+                        bot.clock.sleep(interval).await;
+                    }
+                }
+            }));
+        }
+
+        // Start market data collection for each symbol
+        for symbol in symbols {
+            self.watch_symbol(symbol).await;
+        }
+
+        // Log how each strategy is doing
+        if self.attribution.log_interval_secs > 0 {
+            let bot = self.clone();
+            tasks.push(
+                self.spawn_supervised("strategy PnL summary".to_string(), move || {
+                    let bot = bot.clone();
+                    async move {
+                        let interval = Duration::from_secs(bot.attribution.log_interval_secs);
+                        loop {
+                            bot.clock.sleep(interval).await;
+                            if !*bot.is_running.lock().await {
+                                break;
+                            }
+                            let (live, shadow) = bot.update_strategy_metrics().await;
+                            if !live.is_empty() {
+                                println!("Strategy PnL:");
+                                for (strategy, pnl) in live {
+                                    println!("  {}: {}", strategy, pnl);
+                                }
+                            }
+                            if !shadow.is_empty() {
+                                println!("Shadow strategy PnL (hypothetical, not traded):");
+                                for (strategy, pnl) in shadow {
+                                    println!("  {}: {}", strategy, pnl);
+                                }
+                            }
+                        }
+                    }
+                }),
+            );
+        }
+
+        // Mark positions to market between fills
+        let bot = self.clone();
+        tasks.push(
+            self.spawn_supervised("mark to market".to_string(), move || {
+                let bot = bot.clone();
+                async move {
+                    let interval = Duration::from_millis(bot.marks.refresh_interval_ms);
+                    loop {
+                        bot.clock.sleep(interval).await;
+                        if !*bot.is_running.lock().await {
+                            break;
+                        }
+                        bot.risk_manager.update_unrealized_pnl().await;
+                    }
+                }
+            }),
+        );
+
+        // Close positions as their exit rules trigger: woken by a price
+        // crossing a level, and at least every IDLE_WAKE_INTERVAL for time
+        // stops
+        let bot = self.clone();
+        tasks.push(
+            self.spawn_supervised("position monitor".to_string(), move || {
+                let bot = bot.clone();
+                async move {
+                    while *bot.is_running.lock().await {
+                        bot.check_exits().await;
+                        tokio::select! {
+                            _ = bot.monitor_ready.notified() => {}
+                            _ = bot.clock.sleep(IDLE_WAKE_INTERVAL) => {}
+                        }
+                    }
+                }
+            }),
+        );
+
+        // Snapshot state for recover() after a restart
+        if self.state.enabled {
+            let bot = self.clone();
+            tasks.push(
+                self.spawn_supervised("state snapshots".to_string(), move || {
+                    let bot = bot.clone();
+                    async move {
+                        let interval = Duration::from_millis(bot.state.snapshot_interval_ms);
+                        loop {
+                            bot.clock.sleep(interval).await;
+                            if !*bot.is_running.lock().await {
+                                break;
+                            }
+                            if let Err(e) = bot.save_state().await {
+                                println!("{}", e);
+                            }
+                        }
+                    }
+                }),
+            );
+        }
+
+        // Start trading logic
+        let trading_task = self.run_trading_loop().await;
+        tasks.push(trading_task);
+
+        // Wait for all tasks, the price polling ones included
+        futures::future::join_all(tasks).await;
+        let subscriptions = std::mem::take(&mut *self.symbols.lock().await);
+        futures::future::join_all(subscriptions.into_values().flat_map(|s| s.tasks)).await;
+    }
+
+    // Books a fill into positions and re-places the position's protection
+    async fn on_fill(&self, fill: &Fill) {
+        println!("Fill: {:?}", fill);
+        if let Some(journal) = &self.journal {
+            journal.record(JournalEntry::Fill(fill.clone()));
+        }
+        self.risk_manager.apply_fill(fill).await;
+        self.journal_session(self.risk_manager.session().stats());
+        let position = self
+            .risk_manager
+            .position(&fill.venue, fill.symbol.as_str(), fill.position_side)
+            .await;
+        if let Some(position) = &position
+            && let Some(liquidation) = self.risk_manager.liquidation_price(position).await
+        {
+            println!(
+                "{} {} liquidates around {:.4} (isolated estimate)",
+                position.venue, position.symbol, liquidation
+            );
+        }
+        let rules = match &position {
+            Some(position) => self.exit_rules(position).await,
+            None => ExitRules::default(),
+        };
+        self.monitor
+            .lock()
+            .unwrap()
+            .apply_fill(fill, rules, self.holding.reset_on_extend);
+        let closed = self.strategy_stats.lock().unwrap().apply_fill(fill);
+        self.allocator.on_fill(&fill.order_id, fill.quantity);
+        if let Some(openers) = fill.strategy.as_deref() {
+            for entry in self.strategies.read().await.iter() {
+                if openers.split('+').any(|name| name == entry.strategy.name()) {
+                    entry.strategy.on_fill(fill);
+                }
+            }
+        }
+        self.track_trades(&closed).await;
+        self.count_trades(StrategyMode::Live, closed);
+        self.update_strategy_metrics().await;
+        self.publish(|| BotEvent::Fill(fill.clone()));
+        if let Some(position) = position {
+            self.publish(|| BotEvent::Position(position));
+        }
+        self.metrics
+            .fills
+            .with_label_values(&[fill.symbol.as_str()])
+            .inc();
+        self.update_position_metrics(fill.symbol.as_str()).await;
+        self.metrics
+            .daily_pnl
+            .set(self.risk_manager.daily_pnl().await);
+        self.refresh_protective_stop(fill.symbol.as_str()).await;
+    }
+
+    // A gauge per symbol pair with a correlation, when they're exported
+    fn update_correlation_metrics(&self) {
+        let correlations = self.risk_manager.correlations();
+        if !correlations.config().metrics {
+            return;
+        }
+        for (symbol, other, correlation) in correlations.pairs() {
+            self.metrics
+                .symbol_correlation
+                .with_label_values(&[&symbol, &other])
+                .set(correlation);
+        }
+    }
+
+    // Position gauges for the symbol, summed over venues and marked at its
+    // latest price
+    async fn update_position_metrics(&self, symbol: &str) {
+        let mark = self.risk_manager.mark_price(symbol).await;
+        let (mut quantity, mut unrealized, mut held) = (0.0, 0.0, false);
+        for position in self.risk_manager.positions().await {
+            if position.symbol != symbol {
+                continue;
+            }
+            let mark = mark.unwrap_or(position.avg_price);
+            quantity += position.quantity;
+            unrealized += position.quantity * (mark - position.avg_price);
+            held = true;
+        }
+        if !held {
+            return;
+        }
+        self.metrics
+            .position_quantity
+            .with_label_values(&[symbol])
+            .set(quantity);
+        self.metrics
+            .unrealized_pnl
+            .with_label_values(&[symbol])
+            .set(unrealized);
+    }
+
+    // Caches the symbol's latest book, which evaluations and paper fills
+    // read, and notes its age
+    async fn record_book(&self, symbol: &Symbol, orderbook: OrderBook) {
+        orderbook.debug_validate();
+        self.risk_manager.marks().record_book(&orderbook);
+        self.indicators
+            .write()
+            .await
+            .update_book(symbol, &orderbook);
+        self.freshness
+            .lock()
+            .await
+            .record_book(&orderbook.symbol, orderbook.timestamp * 1_000);
+        self.order_books
+            .write()
+            .await
+            .insert(orderbook.symbol.clone(), orderbook);
+    }
+
+    // Runs the tick through the spike filter; false if it's dropped
+    fn screen_tick(&self, price: &Price) -> bool {
+        let mut filter = self.tick_filter.lock().unwrap();
+        match filter.screen(price) {
+            Screening::Accepted => true,
+            Screening::Confirmed => {
+                println!(
+                    "{} confirmed at {} after a run of outlying ticks",
+                    price.symbol, price.price
+                );
+                true
+            }
+            Screening::Bad { deviation_pct } => {
+                self.metrics
+                    .bad_ticks
+                    .with_label_values(&[&price.symbol])
+                    .inc();
+                let reject = filter.action() == BadTickAction::Reject;
+                println!(
+                    "⚠️ Bad tick on {}: {} is {:+.2}% from the last accepted price ({})",
+                    price.symbol,
+                    price.price,
+                    deviation_pct * 100.0,
+                    if reject { "dropped" } else { "flagged" }
+                );
+                !reject
+            }
+        }
+    }
+
+    // Adds a tick to the symbol's price history, indicators and candles and
+    // marks its position to it
+    async fn record_price(&self, symbol: &Symbol, price: Price) {
+        if !self.screen_tick(&price) {
+            return;
+        }
+        let received = Instant::now();
+        let mut history = self.price_history.write().await;
+        let insertion = history
+            .get_or_insert_with(symbol.id(), || PriceBuffer::new(&self.price_history_config))
+            .insert(price.clone());
+        if insertion != Insertion::Appended {
+            drop(history);
+            // Marks, indicators and candles only move forward, so a late
+            // tick goes no further than the history, if that
+            let counter = match insertion {
+                Insertion::Duplicate => &self.metrics.duplicate_ticks,
+                _ => &self.metrics.out_of_order_ticks,
+            };
+            counter.with_label_values(&[&price.symbol]).inc();
+            return;
+        }
+        self.risk_manager.marks().record_price(&price);
+        if self.risk_manager.correlations().record_price(&price) {
+            self.update_correlation_metrics();
+        }
+        self.metrics
+            .price_updates
+            .with_label_values(&[&price.symbol])
+            .inc();
+        self.update_position_metrics(&price.symbol).await;
+        self.publish(|| BotEvent::Price(price.clone()));
+
+        // Updated under the history lock so both stay in step
+        self.indicators
+            .write()
+            .await
+            .update(symbol, price.price, price.volume);
+        let atr = {
+            let mut candles = self.candles.write().await;
+            candles.update(symbol, &price);
+            candles
+                .get(symbol.id())
+                .and_then(|candles| candles.atr(self.atr_timeframe))
+        };
+        if let Some(atr) = atr {
+            self.risk_manager.update_atr(&price.symbol, atr).await;
+        }
+        self.freshness
+            .lock()
+            .await
+            .record_price(&price.symbol, self.clock.now_ms());
+        let trip =
+            self.breaker
+                .lock()
+                .unwrap()
+                .on_price(&price.symbol, price.price, self.clock.now_ms());
+        drop(history);
+        if let Some(trip) = trip {
+            self.trip_breaker(&price.symbol, trip).await;
+        }
+        if self
+            .monitor
+            .lock()
+            .unwrap()
+            .on_price(&price.symbol, price.price)
+        {
+            self.monitor_ready.notify_one();
+        }
+
+        self.price_received
+            .lock()
+            .unwrap()
+            .insert(symbol.id(), received);
+        if let Some(ready) = self.data_ready.lock().unwrap().get(symbol.id()) {
+            ready.notify_one();
+        }
+    }
+
+    async fn run_trading_loop(&self) -> tokio::task::JoinHandle<()> {
+        let bot = self.clone();
+        self.spawn_supervised("trading loop".to_string(), move || {
+            let bot = bot.clone();
+            async move { bot.trading_loop().await }
+        })
+    }
+
+    // Spawns the task `run` makes under supervision. A task that returns is
+    // done; one that panics has the failure published and is then made
+    // again, or the bot is stopped, as [supervision] says.
+    fn spawn_supervised<F, Fut>(&self, task: String, run: F) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let bot = self.clone();
+        tokio::spawn(async move {
+            let mut restarts = 0;
+            loop {
+                let payload = match tokio::spawn(run()).await {
+                    Err(e) if e.is_panic() => e.into_panic(),
+                    // Returned, or aborted along with the runtime
+                    _ => return,
+                };
+                if !*bot.is_running.lock().await {
+                    return;
+                }
+                let message = panic_message(&*payload);
+                let restarting = bot.supervision.on_task_failure == TaskFailurePolicy::Restart
+                    && restarts < bot.supervision.max_task_restarts;
+                println!(
+                    "🔥 Task {} panicked: {}; {}",
+                    task,
+                    message,
+                    if restarting {
+                        "restarting it"
+                    } else {
+                        "stopping the bot"
+                    }
+                );
+                bot.metrics
+                    .task_failures
+                    .with_label_values(&[task.as_str()])
+                    .inc();
+                bot.publish(|| BotEvent::TaskFailed {
+                    task: task.clone(),
+                    message: message.clone(),
+                    restarts,
+                    restarting,
+                });
+                if !restarting {
+                    bot.stop().await;
+                    return;
+                }
+                restarts += 1;
+                bot.clock
+                    .sleep(Duration::from_millis(bot.supervision.restart_delay_ms))
+                    .await;
+            }
+        })
+    }
+
+    // Counts a strategy's panic on the symbol, and disables the strategy
+    // once it has panicked max_strategy_panics times in a row
+    fn strategy_panicked(&self, entry: &StrategyEntry, symbol: &str, payload: &(dyn Any + Send)) {
+        let name = entry.strategy.name();
+        let message = panic_message(payload);
+        let consecutive = entry.record_panic();
+        let disabled = consecutive >= self.supervision.max_strategy_panics;
+        if disabled {
+            entry.enabled.store(false, Ordering::Release);
+        }
+        println!(
+            "🔥 Strategy {} panicked on {} ({} in a row): {}{}",
+            name,
+            symbol,
+            consecutive,
+            message,
+            if disabled { "; disabled it" } else { "" }
+        );
+        self.metrics
+            .strategy_panics
+            .with_label_values(&[name])
+            .inc();
+        self.publish(|| BotEvent::StrategyPanicked {
+            strategy: name.to_string(),
+            symbol: symbol.to_string(),
+            message,
+            consecutive,
+            disabled,
+        });
+    }
+
+    // Counts closed trades toward their strategies' losing streaks,
+    // benching those that reach the limit
+    async fn track_trades(&self, closed: &[ClosedTrade]) {
+        if !self.streaks.lock().unwrap().config().enabled || closed.is_empty() {
+            return;
+        }
+        let strategies = self.strategies.read().await;
+        for trade in closed {
+            let reached = self
+                .streaks
+                .lock()
+                .unwrap()
+                .record_trade(&trade.strategy, trade.pnl);
+            match reached {
+                Some(count) => self.bench_strategy(
+                    &strategies,
+                    &trade.strategy,
+                    StreakKind::LosingTrades,
+                    count,
+                ),
+                None => self.journal_streak(
+                    &trade.strategy,
+                    self.streaks.lock().unwrap().streak(&trade.strategy),
+                ),
+            }
+        }
+    }
+
+    // Counts a risk rejection against each strategy behind the order
+    fn track_rejection(&self, strategies: &[StrategyEntry], sources: Option<&str>) {
+        if !self.streaks.lock().unwrap().config().enabled {
+            return;
+        }
+        for name in sources.into_iter().flat_map(|sources| sources.split('+')) {
+            let reached = self.streaks.lock().unwrap().record_rejection(name);
+            match reached {
+                Some(count) => self.bench_strategy(strategies, name, StreakKind::Rejections, count),
+                None => self.journal_streak(name, self.streaks.lock().unwrap().streak(name)),
+            }
+        }
+    }
+
+    // An order passed the risk checks, ending its strategies' rejection
+    // streaks
+    fn track_accepted(&self, sources: Option<&str>) {
+        for name in sources.into_iter().flat_map(|sources| sources.split('+')) {
+            let mut streaks = self.streaks.lock().unwrap();
+            if streaks.record_accepted(name) {
+                let streak = streaks.streak(name);
+                drop(streaks);
+                self.journal_streak(name, streak);
+            }
+        }
+    }
+
+    // Moves a live strategy whose streak reached its limit to the bench
+    // mode for the cooldown
+    fn bench_strategy(
+        &self,
+        strategies: &[StrategyEntry],
+        name: &str,
+        kind: StreakKind,
+        count: u32,
+    ) {
+        let Some(entry) = strategies
+            .iter()
+            .find(|entry| entry.strategy.name() == name && entry.mode() == StrategyMode::Live)
+        else {
+            return;
+        };
+        let (bench, mode) = {
+            let mut streaks = self.streaks.lock().unwrap();
+            let mode = streaks.config().bench_mode;
+            let bench = streaks.bench(name, kind, StrategyMode::Live, self.clock.now_secs());
+            (bench, mode)
+        };
+        entry.set_mode(mode);
+        self.journal_streak(name, self.streaks.lock().unwrap().streak(name));
+        println!(
+            "⏸️ Strategy {} had {} in a row; {} until {}",
+            name,
+            kind.describe(count),
+            mode,
+            bench.until
+        );
+        self.publish(|| BotEvent::StrategyBenched {
+            strategy: name.to_string(),
+            kind,
+            count,
+            mode,
+            until: bench.until,
+        });
+    }
+
+    // Puts strategies whose cooldown is over back in their old mode
+    async fn reinstate_benched(&self) {
+        let expired = self
+            .streaks
+            .lock()
+            .unwrap()
+            .take_expired(self.clock.now_secs());
+        if expired.is_empty() {
+            return;
+        }
+        let strategies = self.strategies.read().await;
+        for (name, mode) in expired {
+            if let Some(entry) = strategies
+                .iter()
+                .find(|entry| entry.strategy.name() == name)
+            {
+                entry.set_mode(mode);
+            }
+            self.journal_streak(&name, Streak::default());
+            println!("▶️ Strategy {} is back {} after its cooldown", name, mode);
+            self.publish(|| BotEvent::StrategyReinstated {
+                strategy: name.clone(),
+                mode,
+            });
+        }
+    }
+
+    // Strategies benched before a restart sit out the rest of the cooldown
+    async fn restore_benched(&self) {
+        let benched: Vec<(String, StrategyMode)> = {
+            let streaks = self.streaks.lock().unwrap();
+            let mode = streaks.config().bench_mode;
+            streaks
+                .benched()
+                .map(|(name, _)| (name.to_string(), mode))
+                .collect()
+        };
+        let strategies = self.strategies.read().await;
+        for (name, mode) in benched {
+            if let Some(entry) = strategies
+                .iter()
+                .find(|entry| entry.strategy.name() == name)
+            {
+                entry.set_mode(mode);
+                println!("⏸️ Strategy {} is still benched ({})", name, mode);
+            }
+        }
+    }
+
+    // The session's counters, for a restart to pick up
+    fn journal_session(&self, stats: SessionStats) {
+        if let Some(journal) = &self.journal {
+            journal.record(JournalEntry::Session {
+                stats,
+                timestamp: self.clock.now_secs(),
+            });
+        }
+    }
+
+    fn journal_streak(&self, strategy: &str, streak: Streak) {
+        if let Some(journal) = &self.journal {
+            journal.record(JournalEntry::Streak {
+                strategy: strategy.to_string(),
+                streak,
+                timestamp: self.clock.now_secs(),
+            });
+        }
+    }
+
+    // Account-wide checks between evaluations: funding, the basis trade,
+    // the drawdown halt, the daily PnL gauge and benched strategies'
+    // cooldowns
+    async fn trading_loop(&self) {
+        let interval = self
+            .schedule
+            .evaluation_interval()
+            .unwrap_or(IDLE_WAKE_INTERVAL);
+        while *self.is_running.lock().await {
+            self.settle_funding().await;
+            self.check_basis().await;
+            self.check_drawdown().await;
+            self.metrics
+                .daily_pnl
+                .set(self.risk_manager.daily_pnl().await);
+            self.reinstate_benched().await;
+            self.clock.sleep(interval).await;
+        }
+    }
+
+    // Polls the symbol's price and book every poll interval
+    async fn polling_loop(&self, symbol: &Symbol, active: &AtomicBool) {
+        let poll_interval = self.schedule.poll_interval(symbol.name());
+        while active.load(Ordering::Acquire) && *self.is_running.lock().await {
+            let price = self.market_feed.get_price(symbol.name()).await;
+            // The book is fetched here rather than on every evaluation, and
+            // cached before the price wakes a data-triggered evaluation
+            if let Some(orderbook) = self.market_feed.get_orderbook(symbol.name()).await {
+                self.record_book(symbol, orderbook).await;
+            }
+            if let Some(price) = price {
+                self.record_price(symbol, price).await;
+            }
+
+            self.clock.sleep(poll_interval).await;
+        }
+    }
+
+    // Evaluates one symbol: timer-triggered strategies every evaluation
+    // interval, data-triggered ones as soon as it has a new price (and with
+    // no interval, timer-triggered ones too). Every symbol has its own loop,
+    // so a slow book fetch or submission only holds up its own symbol.
+    async fn evaluation_loop(&self, interned: &Symbol, active: &AtomicBool, data_ready: &Notify) {
+        let symbol = interned.name();
+        let interval = self.schedule.evaluation_interval();
+        let mut next_timer_ms = self.clock.now_ms();
+        let mut seen_ticks = 0;
+        while active.load(Ordering::Acquire) && *self.is_running.lock().await {
+            let now_ms = self.clock.now_ms();
+            let timer_due = match interval {
+                Some(interval) if now_ms >= next_timer_ms => {
+                    next_timer_ms = now_ms + interval.as_millis() as u64;
+                    true
+                }
+                _ => false,
+            };
+
+            let slot = self
+                .evaluation_slots
+                .acquire()
+                .await
+                .expect("the evaluation semaphore is never closed");
+            let started = Instant::now();
+            let ticks = self
+                .indicators
+                .read()
+                .await
+                .get(interned.id())
+                .map_or(0, SymbolIndicators::ticks);
+            let has_data = ticks != seen_ticks;
+            seen_ticks = ticks;
+
+            if ticks >= 10 && !self.is_halted() {
+                let orderbook = self.order_books.read().await.get(symbol).cloned();
+                // Held for the rest of the pass so enable/disable takes
+                // effect atomically
+                let strategies = self.strategies.read().await;
+                let due = Due {
+                    timer: timer_due || (interval.is_none() && has_data),
+                    data: has_data,
+                };
+                if self.check_freshness(symbol).await
+                    && let Some(orderbook) = orderbook
+                {
+                    if !(self.check_session(symbol).await && self.check_breaker(symbol).await) {
+                        // Stops and brackets keep working while it's closed
+                        self.order_executor.match_resting_orders(symbol).await;
+                    } else if due.any() || self.external_signals.has_pending(symbol) {
+                        self.evaluate_symbol(interned, orderbook, &strategies, due)
+                            .await;
+                    }
+                }
+            }
+            drop(slot);
+            self.metrics
+                .loop_iteration_seconds
+                .observe(started.elapsed().as_secs_f64());
+
+            let data_driven = interval.is_none()
+                || self.strategies.read().await.iter().any(|entry| {
+                    entry.trigger == EvaluationTrigger::Data && entry.applies_to(symbol)
+                });
+            let wait = match interval {
+                Some(_) => Duration::from_millis(next_timer_ms.saturating_sub(self.clock.now_ms())),
+                None => IDLE_WAKE_INTERVAL,
+            };
+            if data_driven {
+                tokio::select! {
+                    _ = data_ready.notified() => {}
+                    _ = self.clock.sleep(wait) => {}
+                }
+            } else {
+                self.clock.sleep(wait).await;
+            }
+        }
+    }
+
+    // How old the symbol's newest price and book are
+    pub async fn data_age(&self, symbol: &str) -> DataAge {
+        self.freshness.lock().await.age(symbol, self.clock.now_ms())
+    }
+
+    // Whether the symbol's data is fresh enough to trade on. A symbol going
+    // stale has its working orders cancelled; its protective stops are left
+    // resting. It trades again as soon as fresh data arrives.
+    async fn check_freshness(&self, symbol: &str) -> bool {
+        if !self.staleness.enabled {
+            return true;
+        }
+        let mut freshness = self.freshness.lock().await;
+        let change = freshness.check(symbol, self.clock.now_ms(), self.staleness.max_age_ms);
+        let stale = freshness.is_stale(symbol);
+        drop(freshness);
+
+        match change {
+            Some(Freshness::BecameStale { age_ms }) => {
+                let cancelled_orders = self.cancel_working_orders(symbol).await;
+                println!(
+                    "⚠️ {} market data is {} ms old: halted it and cancelled {} order(s)",
+                    symbol, age_ms, cancelled_orders
+                );
+                self.publish(|| BotEvent::DataStale {
+                    symbol: symbol.to_string(),
+                    age_ms,
+                    cancelled_orders,
+                });
+            }
+            Some(Freshness::BecameFresh) => {
+                println!("▶️ {} market data is fresh again: trading resumed", symbol);
+                self.publish(|| BotEvent::DataFresh {
+                    symbol: symbol.to_string(),
+                });
+            }
+            None => {}
+        }
+        !stale
+    }
+
+    // Whether the symbol's trading session is open. On closing, its working
+    // orders are cancelled if the calendar says so and, ahead of a blackout
+    // that calls for it, its position is flattened; its protective stops
+    // are left resting either way.
+    async fn check_session(&self, symbol: &str) -> bool {
+        if !self.calendar.is_enabled() {
+            return true;
+        }
+        let state = self.calendar.state(symbol, self.clock.now_ms());
+        let previous = self
+            .sessions
+            .lock()
+            .unwrap()
+            .insert(symbol.to_string(), state.clone())
+            .unwrap_or(SessionState::Open);
+        if state == previous {
+            return state.is_open();
+        }
+
+        if state.is_open() {
+            println!("▶️ {} trading session open", symbol);
+            self.publish(|| BotEvent::SessionOpened {
+                symbol: symbol.to_string(),
+            });
+            return true;
+        }
+        let cancelled_orders = if self.calendar.cancels_orders() {
+            self.cancel_working_orders(symbol).await
+        } else {
+            0
+        };
+        let flattened = matches!(state, SessionState::Blackout { flatten: true, .. })
+            && self.flatten_symbol(symbol).await;
+        println!(
+            "⏸️ {} trading session closed ({}): cancelled {} order(s){}",
+            symbol,
+            state,
+            cancelled_orders,
+            if flattened { ", flattening" } else { "" }
+        );
+        self.publish(|| BotEvent::SessionClosed {
+            symbol: symbol.to_string(),
+            reason: state.to_string(),
+            cancelled_orders,
+            flattened,
+        });
+        false
+    }
+
+    // Pauses the symbol's entries after an abnormal move: its working
+    // orders are cancelled, its protective ones left resting
+    async fn trip_breaker(&self, symbol: &str, trip: Trip) {
+        // Before the cancels, which can wait out the cooldown on the
+        // cancel budget
+        let cooldown_secs = trip.until_ms.saturating_sub(self.clock.now_ms()) / 1000;
+        let cancelled_orders = self.cancel_working_orders(symbol).await;
+        println!(
+            "🚨 {} moved {:.2}% (z-score {}): paused for {}s, cancelled {} order(s)",
+            symbol,
+            trip.move_pct * 100.0,
+            trip.z_score
+                .map_or_else(|| "n/a".to_string(), |z| format!("{:.1}", z)),
+            cooldown_secs,
+            cancelled_orders
+        );
+        self.risk_manager.emit(RiskEvent::VolatilityHalt {
+            symbol: symbol.to_string(),
+            move_pct: trip.move_pct,
+            z_score: trip.z_score,
+            cooldown_secs,
+        });
+        self.publish(|| BotEvent::VolatilityHalt {
+            symbol: symbol.to_string(),
+            move_pct: trip.move_pct,
+            z_score: trip.z_score,
+            cooldown_secs,
+            cancelled_orders,
+        });
+    }
+
+    // Whether the volatility breaker lets the symbol open positions,
+    // resuming it once its cooldown is over
+    async fn check_breaker(&self, symbol: &str) -> bool {
+        let now_ms = self.clock.now_ms();
+        let mut breaker = self.breaker.lock().unwrap();
+        if !breaker.is_enabled() {
+            return true;
+        }
+        if breaker.take_resumed(symbol, now_ms) {
+            drop(breaker);
+            println!(
+                "▶️ {} volatility breaker cooled down: trading resumed",
+                symbol
+            );
+            self.risk_manager.emit(RiskEvent::VolatilityResumed {
+                symbol: symbol.to_string(),
+            });
+            self.publish(|| BotEvent::VolatilityResumed {
+                symbol: symbol.to_string(),
+            });
+            return true;
+        }
+        !breaker.is_tripped(symbol, now_ms)
+    }
+
+    // Symbols the volatility breaker has paused, with their cooldown left
+    pub fn tripped_symbols(&self) -> Vec<TrippedSymbol> {
+        self.breaker.lock().unwrap().tripped(self.clock.now_ms())
+    }
+
+    // Rolling correlations between the symbols' returns, as a matrix
+    pub fn correlations(&self) -> CorrelationMatrix {
+        self.risk_manager.correlations().matrix()
+    }
+
+    /// How much of its budget each budgeted strategy has deployed or
+    /// reserved
+    pub fn strategy_budgets(&self) -> BTreeMap<String, BudgetUsage> {
+        self.allocator.usage(&self.strategy_stats.lock().unwrap())
+    }
+
+    // Cancels the symbol's resting orders other than its protection and
+    // returns how many were cancelled
+    async fn cancel_working_orders(&self, symbol: &str) -> usize {
+        let protective: Vec<Protection> = self
+            .protective_orders
+            .lock()
+            .await
+            .values()
+            .cloned()
+            .collect();
+        let mut cancelled = 0;
+        for state in self.order_executor.open_orders_for_symbol(symbol).await {
+            if protective.iter().any(|p| p.covers(&state)) {
+                continue;
+            }
+            match self.order_executor.cancel_order(&state.order.id).await {
+                Ok(()) => cancelled += 1,
+                // Filled or cancelled in the meantime
+                Err(ExecutionError::UnknownOrder { .. }) => {}
+                Err(e) => println!("Failed to cancel {}: {}", state.order.id, e),
+            }
+        }
+        cancelled
+    }
+
+    // Cancels the orders a strategy has resting on the symbol, at its request
+    async fn cancel_strategy_orders(&self, strategy: &str, symbol: &str) {
+        for state in self.order_executor.open_orders_for_symbol(symbol).await {
+            if state.order.strategy.as_deref() != Some(strategy) {
+                continue;
+            }
+            match self.order_executor.cancel_order(&state.order.id).await {
+                Ok(()) => println!(
+                    "Cancelled {} order {} on {} at its request",
+                    strategy, state.order.id, symbol
+                ),
+                // Filled or cancelled in the meantime
+                Err(ExecutionError::UnknownOrder { .. }) => {}
+                Err(e) => println!("Failed to cancel {}: {}", state.order.id, e),
+            }
+        }
+    }
+
+    /// Settles funding on perp positions once a funding time has passed,
+    /// at each symbol's latest rate. Funding times missed while the bot
+    /// wasn't running aren't made up.
+    async fn settle_funding(&self) {
+        if !self.funding.enabled {
+            return;
+        }
+        let funding_time = self.funding.funding_time(self.clock.now_secs());
+        if self.funding_time.fetch_max(funding_time, Ordering::AcqRel) >= funding_time {
+            return;
+        }
+        let mut paid = false;
+        for (symbol, rate) in self.funding_rates.all() {
+            for payment in self
+                .risk_manager
+                .apply_funding(&symbol, rate, funding_time)
+                .await
+            {
+                paid = true;
+                println!(
+                    "💸 Funding on {} {}: {:+.4} ({:.4}% of {:.2})",
+                    payment.venue,
+                    payment.symbol,
+                    payment.amount,
+                    payment.rate * 100.0,
+                    payment.notional
+                );
+            }
+        }
+        if paid {
+            self.journal_session(self.risk_manager.session().stats());
+        }
+    }
+
+    /// Opens the spot/perp basis trade (long spot, short perp) once the
+    /// annualized basis reaches its entry threshold, and unwinds it once
+    /// the basis compresses to the exit threshold. Both legs go out as a
+    /// unit through submit_legs.
+    async fn check_basis(&self) {
+        let config = self.basis.lock().unwrap().config().clone();
+        if !config.enabled || self.is_halted() {
+            return;
+        }
+        let marks = self.risk_manager.marks();
+        let (Some(spot), Some(perp)) = (
+            marks.price(&config.spot_symbol),
+            marks.price(&config.perp_symbol),
+        ) else {
+            return;
+        };
+        let action = self
+            .basis
+            .lock()
+            .unwrap()
+            .evaluate(spot, perp, self.funding.interval_secs);
+        let (quantity, basis, spot_side, perp_side) = match action {
+            None => return,
+            Some(BasisAction::Enter { quantity, basis }) => {
+                (quantity, basis, OrderSide::Buy, OrderSide::Sell)
+            }
+            Some(BasisAction::Exit { quantity, basis }) => {
+                (quantity, basis, OrderSide::Sell, OrderSide::Buy)
+            }
+        };
+        let entering = spot_side == OrderSide::Buy;
+        let leg = |symbol: &str, side, venue: &Option<VenueId>| Order {
+            id: Uuid::new_v4().to_string(),
+            client_order_id: Uuid::new_v4().to_string(),
+            symbol: SymbolId::intern(symbol),
+            side,
+            order_type: OrderType::Market,
+            quantity,
+            price: None,
+            time_in_force: TimeInForce::Gtc,
+            timestamp: self.clock.now_secs(),
+            strategy: Some(BasisStrategy::NAME.to_string()),
+            link_group: None,
+            venue: venue.clone().unwrap_or_else(|| self.venue.clone()),
+            position_side: PositionSide::Both,
+            reduce_only: !entering,
+        };
+        let legs = vec![
+            leg(&config.spot_symbol, spot_side, &config.spot_venue),
+            leg(&config.perp_symbol, perp_side, &config.perp_venue),
+        ];
+        println!(
+            "Basis on {}/{} at {:.2}% annualized: {} {:.4} each leg",
+            config.spot_symbol,
+            config.perp_symbol,
+            basis * 100.0,
+            if entering { "entering" } else { "unwinding" },
+            quantity
+        );
+        match self.submit_legs(legs).await {
+            Ok(legs) if entering => self.basis.lock().unwrap().opened(legs[0].quantity),
+            Ok(_) => self.basis.lock().unwrap().closed(),
+            Err(e) => println!("Basis trade not done: {}", e),
+        }
+    }
+
+    /// Sends orders as a unit: all of them or none. Every leg is rounded to
+    /// the same quantity and risk-checked before any goes out. If a venue
+    /// rejects one, the legs already sent are taken straight back out at
+    /// market. Returns the legs as sent.
+    pub async fn submit_legs(&self, mut legs: Vec<Order>) -> Result<Vec<Order>, String> {
+        // Each leg rounds down onto its own lot size; all take the smallest
+        let mut quantity = f64::INFINITY;
+        for leg in &mut legs {
+            self.order_executor
+                .normalize_order(leg)
+                .await
+                .map_err(|e| format!("{} leg: {}", leg.symbol, e))?;
+            quantity = quantity.min(leg.quantity);
+        }
+        for leg in &mut legs {
+            leg.quantity = quantity;
+            self.order_executor
+                .normalize_order(leg)
+                .await
+                .map_err(|e| format!("{} leg: {}", leg.symbol, e))?;
+        }
+        for leg in &legs {
+            let book = self
+                .order_books
+                .read()
+                .await
+                .get(leg.symbol.as_str())
+                .cloned();
+            let (Some(book), Some(price)) = (
+                book,
+                self.risk_manager.mark_price(leg.symbol.as_str()).await,
+            ) else {
+                return Err(format!("no market for the {} leg", leg.symbol));
+            };
+            if let Err(reason) = self.risk_manager.validate_order(leg, price, &book).await {
+                self.metrics
+                    .orders_rejected
+                    .with_label_values(&["risk"])
+                    .inc();
+                return Err(format!("{} leg rejected: {}", leg.symbol, reason));
+            }
+        }
+
+        let mut sent: Vec<Order> = Vec::new();
+        for leg in &legs {
+            match self.submit_to_venue(leg.clone()).await {
+                Ok(order_id) => {
+                    self.metrics
+                        .orders_submitted
+                        .with_label_values(&[leg.symbol.as_str()])
+                        .inc();
+                    self.publish(|| BotEvent::OrderSubmitted(leg.clone()));
+                    println!("Leg submitted: {} {:?} {}", order_id, leg.side, leg.symbol);
+                    sent.push(leg.clone());
+                }
+                Err(e) => {
+                    for done in &sent {
+                        self.unwind_leg(done).await;
+                    }
+                    return Err(format!("{} leg failed: {}", leg.symbol, e));
+                }
+            }
+        }
+        Ok(legs)
+    }
+
+    // Takes a sent leg back out with a market order the other way. Its
+    // fill may not be booked yet, so this isn't reduce-only.
+    async fn unwind_leg(&self, leg: &Order) {
+        let unwind = Order {
+            id: Uuid::new_v4().to_string(),
+            client_order_id: Uuid::new_v4().to_string(),
+            side: match leg.side {
+                OrderSide::Buy => OrderSide::Sell,
+                OrderSide::Sell => OrderSide::Buy,
+            },
+            order_type: OrderType::Market,
+            price: None,
+            time_in_force: TimeInForce::Gtc,
+            timestamp: self.clock.now_secs(),
+            link_group: None,
+            reduce_only: false,
+            ..leg.clone()
+        };
+        match self.submit_to_venue(unwind.clone()).await {
+            Ok(order_id) => println!("↩️ Unwound {} leg {} with {}", leg.symbol, leg.id, order_id),
+            Err(e) => println!(
+                "🚨 Failed to unwind {} leg {}: {} - position left open",
+                leg.symbol, leg.id, e
+            ),
+        }
+    }
+
+    // Asks the primary venue for each perp's funding rate
+    async fn poll_funding_rates(&self, symbols: &[String]) {
+        let Some(exchange) = self.venues.get(&self.venue) else {
+            return;
+        };
+        for symbol in symbols {
+            if !self.risk_manager.is_perp(symbol).await {
+                continue;
+            }
+            match exchange.funding_rate(symbol).await {
+                Ok(rate) => self.set_funding_rate(symbol, rate),
+                Err(e) => println!("Funding rate for {} unavailable: {}", symbol, e),
+            }
+        }
+    }
+
+    // Takes a perp's funding rate, as a venue reports it or by hand
+    pub fn set_funding_rate(&self, symbol: &str, rate: f64) {
+        self.funding_rates.set(symbol, rate);
+    }
+
+    // Drawdown circuit breaker
+    async fn check_drawdown(&self) {
+        let point = self.risk_manager.equity_point().await;
+        if self.equity_curve.record(point, false) {
+            self.flush_equity(false);
+        }
+        if !self.is_halted()
+            && let Some(event) = self.risk_manager.mark_equity(point.equity).await
+        {
+            println!("🚨 Risk event: {:?}", event);
+            if let RiskEvent::DrawdownBreached {
+                equity,
+                peak,
+                drawdown_pct,
+                limit_pct,
+            } = event
+            {
+                self.publish(|| BotEvent::DrawdownBreached {
+                    equity,
+                    peak,
+                    drawdown_pct,
+                    limit_pct,
+                });
+            }
+            self.kill_switch().await;
+        }
+        // Taken either way, so closed days don't pile up
+        for (blotter, summary) in self.risk_manager.take_closed_days().await {
+            self.journal_session(summary.stats.clone());
+            self.journal_session(self.risk_manager.session().stats());
+            self.publish(|| BotEvent::SessionSummary(summary.clone()));
+            if self.report.enabled {
+                let report = self
+                    .report_for(&blotter, summary.stats.realized_pnl)
+                    .await
+                    .with_session(summary);
+                self.publish_report(&report);
+            }
+        }
+    }
+
+    // Hands the samples due for the equity CSV to the journal's writer
+    fn flush_equity(&self, force: bool) {
+        if let (Some(journal), Some(path)) = (&self.journal, &self.equity_curve.config().csv_path)
+            && let Some(points) = self.equity_curve.take_flush(self.clock.now_secs(), force)
+        {
+            journal.record(JournalEntry::EquityCurve {
+                path: path.clone(),
+                points,
+            });
+        }
+    }
+
+    // The sampled equity curve, with Sharpe, Sortino and drawdown over it
+    pub fn equity_curve(&self) -> EquityReport {
+        self.equity_curve.report()
+    }
+
+    // The shortest of the symbol's, the opening strategy's and the default
+    // holding limit
+    fn holding_limit(
+        &self,
+        strategies: &[StrategyEntry],
+        symbol: &str,
+        opener: Option<&str>,
+    ) -> Option<u64> {
+        let strategy_limit = opener.and_then(|opener| {
+            opener
+                .split('+')
+                .filter_map(|name| {
+                    strategies
+                        .iter()
+                        .find(|entry| entry.strategy.name() == name)?
+                        .max_holding_secs
+                })
+                .min()
+        });
+        match (self.holding.limit_secs(symbol), strategy_limit) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // Closes the positions the monitor has due, each for the first of its
+    // stop-loss, trailing stop, take-profit and time stop to trigger, and
+    // tells the strategy that opened it
+    async fn check_exits(&self) {
+        let strategies = self.strategies.read().await;
+        let exits = self
+            .monitor
+            .lock()
+            .unwrap()
+            .take_exits(self.clock.now_secs(), |symbol, opener| {
+                self.holding_limit(&strategies, symbol, opener)
+            });
+
+        for exit in exits {
+            let position = self
+                .risk_manager
+                .position(&exit.venue, &exit.symbol, exit.side)
+                .await
+                .filter(|position| position.quantity != 0.0);
+            let Some(position) = position else {
+                continue;
+            };
+            let order = self.closing_order(&position);
+            let order_id = match self.submit_to_venue(order).await {
+                Ok(order_id) => order_id,
+                Err(e) => {
+                    println!(
+                        "{} exit failed to close {}: {}",
+                        exit.reason, position.symbol, e
+                    );
+                    self.monitor
+                        .lock()
+                        .unwrap()
+                        .exit_failed(&exit.venue, &exit.symbol, exit.side);
+                    continue;
+                }
+            };
+            match exit.price {
+                Some(price) => println!(
+                    "🛡️ {} {} at {}: closing {} with order {}",
+                    position.symbol, exit.reason, price, position.quantity, order_id
+                ),
+                None => println!(
+                    "⏱️ {} held {}s: closing {} with order {}",
+                    position.symbol, exit.held_secs, position.quantity, order_id
+                ),
+            }
+            if let Some(journal) = &self.journal {
+                journal.record(JournalEntry::ExitReason {
+                    order_id: order_id.clone(),
+                    reason: exit.reason,
+                });
+            }
+            self.publish(|| BotEvent::PositionExit {
+                symbol: position.symbol.to_string(),
+                venue: position.venue.clone(),
+                quantity: position.quantity,
+                strategy: exit.strategy.clone(),
+                reason: exit.reason,
+                held_secs: exit.held_secs,
+            });
+            let openers = exit.strategy.as_deref().unwrap_or_default();
+            for entry in strategies.iter() {
+                if openers.split('+').any(|name| name == entry.strategy.name()) {
+                    entry.strategy.on_position(&position, exit.reason);
+                }
+            }
+        }
+    }
+
+    // One evaluation of a symbol against its latest book: resting orders
+    // are matched, then the strategies' signals are aggregated into at most
+    // one order, which is risk-checked and submitted. The live loop and the
+    // backtester both come through here.
+    async fn evaluate_symbol(
+        &self,
+        interned: &Symbol,
+        orderbook: OrderBook,
+        strategies: &[StrategyEntry],
+        due: Due,
+    ) {
+        let (id, symbol) = (interned.id(), interned.name());
+        let received = self.price_received.lock().unwrap().get(id).copied();
+        let mut timing = Timing::new(received.unwrap_or_else(Instant::now));
+        timing.start_evaluation();
+        self.order_executor.match_resting_orders(symbol).await;
+        let data_age = self.data_age(symbol).await;
+
+        // Prices, indicators and candles are only read while the strategies
+        // run, so a slow submission never holds up other symbols' updates
+        let history = self.price_history.read().await;
+        let indicators = self.indicators.read().await;
+        let (Some(prices), Some(symbol_indicators)) = (history.get(id), indicators.get(id)) else {
+            return;
+        };
+        let prices = prices.as_slice();
+        // Bars that ended without a tick are closed before anyone reads them
+        self.candles.write().await.advance(self.clock.now_secs());
+        let candles = self.candles.read().await;
+        let no_candles = SymbolCandles::default();
+        let symbol_candles = candles.get(id).unwrap_or(&no_candles);
+
+        // Run strategies and collect their signals for this symbol
+        let mut candidates = Vec::new();
+        let mut cancels = Vec::new();
+        for entry in strategies.iter() {
+            if !entry.is_enabled() || !entry.applies_to(symbol) || !due.includes(entry.trigger) {
+                continue;
+            }
+
+            let strategy = &entry.strategy;
+            // A panicking strategy mustn't take the symbol's evaluation task
+            // (or the other strategies) down with it
+            let analyzed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                strategy.analyze_candles(
+                    prices,
+                    symbol_indicators,
+                    symbol_candles,
+                    data_age,
+                    &orderbook,
+                )
+            }));
+            let signal = match analyzed {
+                Ok(signal) => {
+                    entry.consecutive_panics.store(0, Ordering::Relaxed);
+                    signal
+                }
+                Err(payload) => {
+                    self.strategy_panicked(entry, symbol, &*payload);
+                    continue;
+                }
+            };
+            if strategy.cancel_working(symbol) && !entry.is_shadow() {
+                cancels.push(strategy.name());
+            }
+            if let Some(signal) = signal {
+                entry.record_signal(symbol);
+                self.metrics
+                    .signals
+                    .with_label_values(&[strategy.name()])
+                    .inc();
+                self.publish(|| BotEvent::Signal {
+                    strategy: strategy.name().to_string(),
+                    symbol: signal.symbol.to_string(),
+                    action: signal.action,
+                    confidence: signal.confidence,
+                    target_price: signal.target_price,
+                    quantity: signal.quantity,
+                });
+                println!(
+                    "Signal from {} on {}: {:?}",
+                    strategy.name(),
+                    symbol,
+                    signal
+                );
+                if entry.is_shadow() {
+                    self.shadow_fill(strategy.name(), &signal, &orderbook);
+                    continue;
+                }
+                candidates.push(CandidateSignal {
+                    strategy: strategy.name().to_string(),
+                    signal,
+                });
+            }
+        }
+        // Signals from outside the bot join in after the strategies'
+        for external in self.external_signals.take(symbol, self.clock.now_ms()) {
+            self.metrics
+                .signals
+                .with_label_values(&[&external.strategy])
+                .inc();
+            let signal = &external.signal;
+            self.publish(|| BotEvent::Signal {
+                strategy: external.strategy.clone(),
+                symbol: signal.symbol.to_string(),
+                action: signal.action,
+                confidence: signal.confidence,
+                target_price: signal.target_price,
+                quantity: signal.quantity,
+            });
+            candidates.push(CandidateSignal {
+                strategy: external.strategy,
+                signal: external.signal,
+            });
+        }
+        drop(candles);
+        drop(indicators);
+        drop(history);
+        for strategy in cancels {
+            self.cancel_strategy_orders(strategy, symbol).await;
+        }
+
+        // At most one net order per symbol per pass
+        let Some(aggregated) = self.aggregator.aggregate(&candidates) else {
+            if candidates.len() > 1 {
+                println!(
+                    "No net signal on {} from {} conflicting signals ({:?})",
+                    symbol,
+                    candidates.len(),
+                    self.aggregator.policy()
+                );
+            }
+            return;
+        };
+        let signal = aggregated.signal;
+        timing.finish_evaluation();
+
+        // Create order
+        let mut order = Order {
+            id: Uuid::new_v4().to_string(),
+            client_order_id: Uuid::new_v4().to_string(),
+            symbol: SymbolId::intern(&signal.symbol),
+            side: signal.action,
+            order_type: if signal.limit_price.is_some() {
+                OrderType::Limit
+            } else {
+                OrderType::Market
+            },
+            quantity: signal.quantity,
+            price: signal.limit_price,
+            time_in_force: signal.time_in_force,
+            timestamp: self.clock.now_secs(),
+            strategy: Some(aggregated.sources.join("+")),
+            link_group: None,
+            venue: self.venue.clone(),
+            // In hedge mode a buy adds to the long leg and a sell to the
+            // short one; legs are closed by exits and flattening
+            position_side: if self.hedge_mode {
+                PositionSide::opened_by(signal.action)
+            } else {
+                PositionSide::Both
+            },
+            reduce_only: false,
+        };
+
+        // A market order takes at most its share of the depth near mid; the
+        // rest follows it as a resting limit or is dropped
+        let mut follow_up = None;
+        if let Some(split) = self.depth_sizing.size(&mut order, &orderbook) {
+            println!(
+                "Market order on {} cut by {:.4} to {:.4} ({:.4} within {} bps of mid)",
+                order.symbol,
+                split.cut,
+                order.quantity,
+                split.available,
+                self.depth_sizing.band_bps
+            );
+            follow_up = split.follow_up;
+            if order.quantity <= f64::EPSILON {
+                match follow_up.take() {
+                    Some(limit) => order = limit,
+                    None => return,
+                }
+            }
+        }
+
+        // Round onto the symbol's grids first so risk checks see the
+        // quantity that will actually be sent
+        if let Err(e) = self.order_executor.normalize_order(&mut order).await {
+            println!("Order rejected: {}", e);
+            self.metrics
+                .orders_rejected
+                .with_label_values(&["rules"])
+                .inc();
+            return;
+        }
+
+        // The strategy may still have an order working for the same intent
+        let replaces = match self.guard_duplicate(&order) {
+            GuardDecision::Submit => None,
+            GuardDecision::Skip(_) => return,
+            GuardDecision::Replace(working) => Some(working.id),
+        };
+
+        // Validate with risk manager
+        let validated = match &replaces {
+            Some(working) => {
+                self.risk_manager
+                    .validate_replacement(&order, working, signal.target_price, &orderbook)
+                    .await
+            }
+            None => {
+                self.risk_manager
+                    .validate_order(&order, signal.target_price, &orderbook)
+                    .await
+            }
+        };
+        // Then held to its strategies' budgets, which reserves what it adds
+        let validated = match validated {
+            Ok(()) => {
+                self.reserve_budget(&mut order, signal.target_price, replaces.as_deref())
+                    .await
+            }
+            Err(reason) => Err(reason),
+        };
+        if let Err(reason) = validated {
+            println!("Order rejected: {}", reason);
+            self.metrics
+                .orders_rejected
+                .with_label_values(&["risk"])
+                .inc();
+            self.publish(|| BotEvent::RiskRejection {
+                symbol: order.symbol.to_string(),
+                side: order.side,
+                quantity: order.quantity,
+                strategy: order.strategy.clone(),
+                reason: reason.to_string(),
+            });
+            self.track_rejection(strategies, order.strategy.as_deref());
+            if let RiskRejection::DailyLossLimit { daily_pnl, limit } = reason {
+                // Once a day, not for every order the limit stops
+                let day = self.risk_manager.session().session() + 1;
+                if self.loss_limit_day.swap(day, Ordering::AcqRel) != day {
+                    self.publish(|| BotEvent::DailyLossLimit { daily_pnl, limit });
+                }
+            }
+            if let Some(journal) = &self.journal {
+                journal.record(JournalEntry::RiskRejection {
+                    symbol: order.symbol.to_string(),
+                    side: order.side,
+                    quantity: order.quantity,
+                    strategy: order.strategy,
+                    reason: reason.to_string(),
+                    timestamp: order.timestamp,
+                });
+            }
+        } else {
+            // Submit order
+            timing.check_risk();
+            self.track_accepted(order.strategy.as_deref());
+            let submit_started = std::time::Instant::now();
+            let submitted = match &replaces {
+                Some(working) => {
+                    self.order_executor
+                        .replace_order(working, order.price, Some(order.quantity))
+                        .await
+                }
+                None => self.submit_to_venue(order.clone()).await,
+            };
+            self.metrics
+                .order_submit_seconds
+                .observe(submit_started.elapsed().as_secs_f64());
+            match submitted {
+                Err(e) => {
+                    println!("Order submission failed: {}", e);
+                    self.allocator.release(&order.id);
+                    self.metrics
+                        .orders_rejected
+                        .with_label_values(&["venue"])
+                        .inc();
+                }
+                Ok(order_id) => {
+                    self.allocator.submitted(&order.id, &order_id);
+                    timing.submit();
+                    self.record_latency(&order_id, &timing);
+                    self.metrics
+                        .orders_submitted
+                        .with_label_values(&[order.symbol.as_str()])
+                        .inc();
+                    self.publish(|| BotEvent::OrderSubmitted(order.clone()));
+                    for entry in strategies.iter() {
+                        if aggregated
+                            .sources
+                            .iter()
+                            .any(|name| name == entry.strategy.name())
+                        {
+                            entry.order_count.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    // Positions update when fills arrive
+                    println!("Order submitted: {}", order_id);
+                    if let Some(follow_up) = follow_up {
+                        self.submit_follow_up(follow_up, signal.target_price, &orderbook)
+                            .await;
+                    }
+                }
+            }
+        }
+    }
+
+    // Sends the limit picking up what depth sizing cut off a market order,
+    // through the same rounding and risk checks
+    async fn submit_follow_up(&self, mut order: Order, price: f64, orderbook: &OrderBook) {
+        if let Err(e) = self.order_executor.normalize_order(&mut order).await {
+            println!("Follow-up limit on {} dropped: {}", order.symbol, e);
+            return;
+        }
+        let validated = match self
+            .risk_manager
+            .validate_order(&order, price, orderbook)
+            .await
+        {
+            Ok(()) => self.reserve_budget(&mut order, price, None).await,
+            Err(reason) => Err(reason),
+        };
+        if let Err(reason) = validated {
+            println!("Follow-up limit on {} rejected: {}", order.symbol, reason);
+            self.metrics
+                .orders_rejected
+                .with_label_values(&["risk"])
+                .inc();
+            return;
+        }
+        match self.submit_to_venue(order.clone()).await {
+            Ok(order_id) => {
+                self.allocator.submitted(&order.id, &order_id);
+                self.metrics
+                    .orders_submitted
+                    .with_label_values(&[order.symbol.as_str()])
+                    .inc();
+                self.publish(|| BotEvent::OrderSubmitted(order.clone()));
+                println!("Follow-up limit submitted: {}", order_id);
+            }
+            Err(e) => {
+                self.allocator.release(&order.id);
+                println!("Follow-up limit submission failed: {}", e);
+            }
+        }
+    }
+
+    // Holds the order to its strategies' budgets and reserves what it adds.
+    // An order the budget policy cut down goes back onto the symbol's
+    // grids, and is rejected if that leaves too little of it.
+    async fn reserve_budget(
+        &self,
+        order: &mut Order,
+        price: f64,
+        replacing: Option<&str>,
+    ) -> Result<(), RiskRejection> {
+        let tracker = self.order_executor.open_order_tracker();
+        let requested = order.quantity;
+        let shrunk = self
+            .allocator
+            .reserve(
+                order,
+                price,
+                replacing,
+                &self.strategy_stats.lock().unwrap(),
+                |id| tracker.contains(id),
+            )
+            .map_err(RiskRejection::StrategyBudget)?;
+        let Some(exceeded) = shrunk else {
+            return Ok(());
+        };
+        if let Err(e) = self.order_executor.normalize_order(order).await {
+            self.allocator.release(&order.id);
+            println!("Order cut to its strategy budget dropped: {}", e);
+            return Err(RiskRejection::StrategyBudget(exceeded));
+        }
+        println!(
+            "Order on {} cut from {:.4} to {:.4}: {}",
+            order.symbol, requested, order.quantity, exceeded
+        );
+        Ok(())
+    }
+
+    // Checks a signal's order against the working orders its strategy has
+    // on the same symbol and side, and logs the guard's decision
+    fn guard_duplicate(&self, order: &Order) -> GuardDecision {
+        let Some(strategy) = order.strategy.as_deref() else {
+            return GuardDecision::Submit;
+        };
+        let working = self.order_executor.open_order_tracker().working_for(
+            strategy,
+            order.symbol.as_str(),
+            order.side,
+        );
+        let decision = self.order_guard.decide(&working, order);
+        match &decision {
+            GuardDecision::Submit if !working.is_empty() => println!(
+                "Order guard: {} {:?} {} order {} sent alongside working {}",
+                strategy,
+                order.side,
+                order.symbol,
+                order.client_order_id,
+                working
+                    .iter()
+                    .map(|w| w.client_order_id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            GuardDecision::Submit => {}
+            GuardDecision::Skip(working) => println!(
+                "Order guard: {} {:?} {} order {} skipped; {} is already working at {:?}",
+                strategy,
+                order.side,
+                order.symbol,
+                order.client_order_id,
+                working.client_order_id,
+                working.price
+            ),
+            GuardDecision::Replace(working) => println!(
+                "Order guard: {} {:?} {} order {} replaces working {} ({:?} -> {:?})",
+                strategy,
+                order.side,
+                order.symbol,
+                order.client_order_id,
+                working.client_order_id,
+                working.price,
+                order.price
+            ),
+        }
+        decision
+    }
+
+    // Books a submitted order's pipeline timing into the metrics, the
+    // shutdown summary and the journal
+    fn record_latency(&self, order_id: &str, timing: &Timing) {
+        let Some(latency) = timing.latency() else {
+            return;
+        };
+        for stage in Stage::ALL {
+            self.metrics
+                .pipeline_stage_seconds
+                .with_label_values(&[stage.as_str()])
+                .observe(latency.stage(stage).as_secs_f64());
+        }
+        self.latency.record(&latency);
+        if let Some(journal) = &self.journal {
+            journal.record(JournalEntry::OrderLatency {
+                order_id: order_id.to_string(),
+                latency,
+            });
+        }
+    }
+
+    // p50/p99 per pipeline stage over recently submitted orders
+    pub fn latency_summary(&self) -> LatencySummary {
+        self.latency.summary()
+    }
+
+    pub async fn stop(&self) {
+        *self.is_running.lock().await = false;
+        let cancels = self.order_executor.cancel_all().await;
+        if !cancels.cancelled.is_empty() || !cancels.failed.is_empty() {
+            println!(
+                "Cancelled {} working order(s) on shutdown, {} failed",
+                cancels.cancelled.len(),
+                cancels.failed.len()
+            );
+        }
+        if self.state.enabled
+            && let Err(e) = self.save_state().await
+        {
+            println!("{}", e);
+        }
+        if self.report.enabled {
+            let report = self.daily_report().await;
+            self.publish_report(&report);
+        }
+        // The last sample is the equity the bot stopped with
+        self.equity_curve
+            .record(self.risk_manager.equity_point().await, true);
+        self.flush_equity(true);
+        // Wait for the writer, so the last sample and any queued fills are
+        // on disk once stop returns
+        if let Some(journal) = self.journal.clone() {
+            let _ = tokio::task::spawn_blocking(move || journal.shutdown()).await;
+        }
+        println!("Pipeline latency:\n{}", self.latency_summary());
+        println!("Trading bot stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,135 @@
+use crate::candles::{SymbolCandles, Timeframe};
+use crate::indicators::{Macd, MacdValue, SymbolIndicators};
+use crate::staleness::DataAge;
+use crate::{OrderBook, OrderSide, Price, TimeInForce, TradingSignal, TradingStrategy};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// One symbol's MACD over the strategy's timeframe
+#[derive(Debug, Clone)]
+struct MacdState {
+    macd: Macd,
+    // Open time of the newest bar taken in
+    last_bar: Option<u64>,
+    // The value after the bar before it
+    previous: Option<MacdValue>,
+}
+
+impl MacdState {
+    // Takes in a closed bar's close, returning the side to trade on it
+    fn on_close(&mut self, close: f64) -> Option<OrderSide> {
+        let current = self.macd.update(close);
+        let previous = std::mem::replace(&mut self.previous, current);
+        let (previous, current) = (previous?, current?);
+        let slope = current.histogram - previous.histogram;
+        // A cross is the bar the MACD line moves to the other side of the
+        // signal line, so it signals once; the histogram has to be moving
+        // the same way
+        if previous.histogram <= 0.0 && current.histogram > 0.0 && slope > 0.0 {
+            Some(OrderSide::Buy)
+        } else if previous.histogram >= 0.0 && current.histogram < 0.0 && slope < 0.0 {
+            Some(OrderSide::Sell)
+        } else {
+            None
+        }
+    }
+}
+
+/// Trades crosses of the MACD line over its signal line on one candle
+/// timeframe: buys when the MACD line crosses above with the histogram
+/// rising, sells when it crosses below with the histogram falling. Each
+/// symbol's MACD is updated once per closed bar, and a cross signals on
+/// the bar it happens.
+pub struct MacdStrategy {
+    fast_period: usize,
+    slow_period: usize,
+    signal_period: usize,
+    timeframe: Timeframe,
+    base_quantity: f64,
+    states: Mutex<HashMap<Arc<str>, MacdState>>,
+}
+
+impl MacdStrategy {
+    // Panics unless 0 < fast_period < slow_period and signal_period > 0
+    pub fn new(
+        fast_period: usize,
+        slow_period: usize,
+        signal_period: usize,
+        timeframe: Timeframe,
+        base_quantity: f64,
+    ) -> Self {
+        assert!(
+            fast_period > 0 && fast_period < slow_period && signal_period > 0,
+            "MacdStrategy needs 0 < fast_period < slow_period and signal_period > 0, got {}/{}/{}",
+            fast_period,
+            slow_period,
+            signal_period
+        );
+        Self {
+            fast_period,
+            slow_period,
+            signal_period,
+            timeframe,
+            base_quantity,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Bars before the first MACD value can signal
+    fn warmup(&self) -> usize {
+        self.slow_period + self.signal_period
+    }
+}
+
+impl TradingStrategy for MacdStrategy {
+    // Signals only come from closed candles
+    fn analyze(&self, _prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
+        None
+    }
+
+    fn name(&self) -> &str {
+        "MacdStrategy"
+    }
+
+    fn candle_timeframes(&self) -> Vec<Timeframe> {
+        vec![self.timeframe]
+    }
+
+    fn analyze_candles(
+        &self,
+        _prices: &[Price],
+        indicators: &SymbolIndicators,
+        candles: &SymbolCandles,
+        _data_age: DataAge,
+        _orderbook: &OrderBook,
+    ) -> Option<TradingSignal> {
+        let symbol = indicators.symbol();
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(symbol.clone()).or_insert_with(|| MacdState {
+            macd: Macd::new(self.fast_period, self.slow_period, self.signal_period),
+            last_bar: None,
+            previous: None,
+        });
+
+        // A new symbol's MACD is warmed up on the bars already closed
+        let bars = candles.closed_since(self.timeframe, state.last_bar, self.warmup());
+        let newest = *bars.last()?;
+        let mut signal = None;
+        for candle in &bars {
+            signal = state.on_close(candle.close).or(signal);
+            state.last_bar = Some(candle.open_time);
+        }
+
+        let action = signal?;
+        let histogram = state.previous?.histogram;
+        Some(TradingSignal {
+            symbol: symbol.clone(),
+            action,
+            confidence: (histogram / newest.close).abs().min(1.0),
+            target_price: newest.close,
+            quantity: self.base_quantity,
+            time_in_force: TimeInForce::Gtc,
+            limit_price: None,
+        })
+    }
+}
@@ -1,580 +1,336 @@
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::sync::{Mutex, RwLock};
-use tokio::time;
-use uuid::Uuid;
-
-// Core data structures
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Price {
-    pub symbol: String,
-    pub price: f64,
-    pub timestamp: u64,
-    pub volume: f64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OrderBook {
-    pub symbol: String,
-    pub bids: Vec<(f64, f64)>, // (price, quantity)
-    pub asks: Vec<(f64, f64)>, // (price, quantity)
-    pub timestamp: u64,
-}
-
-#[derive(Debug, Clone)]
-pub enum OrderSide {
-    Buy,
-    Sell,
-}
-
-#[derive(Debug, Clone)]
-pub enum OrderType {
-    Market,
-    Limit,
-}
-
-#[derive(Debug, Clone)]
-pub struct Order {
-    pub id: String,
-    pub symbol: String,
-    pub side: OrderSide,
-    pub order_type: OrderType,
-    pub quantity: f64,
-    pub price: Option<f64>,
-    pub timestamp: u64,
-}
-
-#[derive(Debug, Clone)]
-pub struct Position {
-    pub symbol: String,
-    pub quantity: f64,
-    pub avg_price: f64,
-    pub unrealized_pnl: f64,
-}
-
-#[derive(Debug, Clone)]
-pub struct TradingSignal {
-    pub symbol: String,
-    pub action: OrderSide,
-    pub confidence: f64,
-    pub target_price: f64,
-    pub quantity: f64,
-}
-
-// Risk management parameters
-#[derive(Debug, Clone)]
-pub struct RiskParams {
-    pub max_position_size: f64,
-    pub max_loss_per_trade: f64,
-    pub max_daily_loss: f64,
-    pub stop_loss_pct: f64,
-    pub take_profit_pct: f64,
-}
-
-impl Default for RiskParams {
-    fn default() -> Self {
-        Self {
-            max_position_size: 1000.0,
-            max_loss_per_trade: 100.0,
-            max_daily_loss: 500.0,
-            stop_loss_pct: 0.02,   // 2%
-            take_profit_pct: 0.04, // 4%
-        }
-    }
-}
-
-// Strategy trait for different trading strategies
-pub trait TradingStrategy: Send + Sync {
-    fn analyze(&self, prices: &[Price], orderbook: &OrderBook) -> Option<TradingSignal>;
-    fn name(&self) -> &str;
-}
-
-// Simple momentum strategy implementation
-pub struct MomentumStrategy {
-    lookback_period: usize,
-    momentum_threshold: f64,
-}
-
-impl MomentumStrategy {
-    pub fn new(lookback_period: usize, momentum_threshold: f64) -> Self {
-        Self {
-            lookback_period,
-            momentum_threshold,
-        }
-    }
-}
-
-impl TradingStrategy for MomentumStrategy {
-    fn analyze(&self, prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
-        if prices.len() < self.lookback_period {
-            return None;
-        }
-
-        let recent_prices: Vec<f64> = prices
-            .iter()
-            .rev()
-            .take(self.lookback_period)
-            .map(|p| p.price)
-            .collect();
-
-        if recent_prices.len() < 2 {
-            return None;
-        }
-
-        let price_change = (recent_prices[0] - recent_prices[recent_prices.len() - 1])
-            / recent_prices[recent_prices.len() - 1];
-
-        let volume_avg = prices
-            .iter()
-            .rev()
-            .take(self.lookback_period)
-            .map(|p| p.volume)
-            .sum::<f64>()
-            / self.lookback_period as f64;
-
-        if price_change.abs() > self.momentum_threshold && volume_avg > 1000.0 {
-            let action = if price_change > 0 {
-                OrderSide::Buy
-            } else {
-                OrderSide::Sell
-            };
-
-            return Some(TradingSignal {
-                symbol: prices[0].symbol.clone(),
-                action,
-                confidence: price_change.abs().min(1.0),
-                target_price: recent_prices[0],
-                quantity: 100.0, // Base quantity
-            });
-        }
-
-        None
-    }
-
-    fn name(&self) -> &str {
-        "MomentumStrategy"
-    }
-}
-
-// Mean reversion strategy
-pub struct MeanReversionStrategy {
-    lookback_period: usize,
-    deviation_threshold: f64,
-}
-
-impl MeanReversionStrategy {
-    pub fn new(lookback_period: usize, deviation_threshold: f64) -> Self {
-        Self {
-            lookback_period,
-            deviation_threshold,
-        }
-    }
-}
-
-impl TradingStrategy for MeanReversionStrategy {
-    fn analyze(&self, prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
-        if prices.len() < self.lookback_period {
-            return None;
-        }
-
-        let recent_prices: Vec<f64> = prices
-            .iter()
-            .rev()
-            .take(self.lookback_period)
-            .map(|p| p.price)
-            .collect();
-
-        let mean = recent_prices.iter().sum::<f64>() / recent_prices.len() as f64;
-        let current_price = recent_prices[0];
-        let deviation = (current_price - mean) / mean;
-
-        if deviation.abs() > self.deviation_threshold {
-            let action = if deviation > 0 {
-                OrderSide::Sell // Price above mean, sell
-            } else {
-                OrderSide::Buy // Price below mean, buy
-            };
-
-            return Some(TradingSignal {
-                symbol: prices[0].symbol.clone(),
-                action,
-                confidence: deviation.abs().min(1.0),
-                target_price: mean,
-                quantity: 50.0,
-            });
-        }
-
-        None
-    }
-
-    fn name(&self) -> &str {
-        "MeanReversionStrategy"
-    }
-}
-
-// Risk manager
-pub struct RiskManager {
-    params: RiskParams,
-    daily_pnl: Arc<Mutex<f64>>,
-    positions: Arc<RwLock<HashMap<String, Position>>>,
-}
-
-impl RiskManager {
-    pub fn new(params: RiskParams) -> Self {
-        Self {
-            params,
-            daily_pnl: Arc::new(Mutex::new(0.0)),
-            positions: Arc::new(RwLock::new(HashMap::new())),
-        }
-    }
-
-    pub async fn validate_order(&self, order: &Order, current_price: f64) -> bool {
-        let daily_pnl = *self.daily_pnl.lock().await;
-
-        // Check daily loss limit
-        if daily_pnl < -self.params.max_daily_loss {
-            println!("Order rejected: Daily loss limit exceeded");
-            return false;
-        }
-
-        // Check position size
-        let positions = self.positions.read().await;
-        if let Some(position) = positions.get(&order.symbol) {
-            let new_quantity = match order.side {
-                OrderSide::Buy => position.quantity + order.quantity,
-                OrderSide::Sell => position.quantity - order.quantity,
-            };
-
-            if new_quantity.abs() > self.params.max_position_size {
-                println!("Order rejected: Position size limit exceeded");
-                return false;
-            }
-        }
-
-        // Check potential loss
-        let potential_loss = order.quantity * current_price * self.params.stop_loss_pct;
-        if potential_loss > self.params.max_loss_per_trade {
-            println!("Order rejected: Potential loss too high");
-            return false;
-        }
-
-        true
-    }
-
-    pub async fn update_position(&self, symbol: &str, quantity: f64, price: f64) {
-        let mut positions = self.positions.write().await;
-        let position = positions.entry(symbol.to_string()).or_insert(Position {
-            symbol: symbol.to_string(),
-            quantity: 0.0,
-            avg_price: 0.0,
-            unrealized_pnl: 0.0,
-        });
-
-        // Update position
-        let total_cost = position.quantity * position.avg_price + quantity * price;
-        position.quantity += quantity;
-
-        if position.quantity != 0.0 {
-            position.avg_price = total_cost / position.quantity;
-        }
-    }
-}
-
-// Market data feed simulator
-pub struct MarketDataFeed {
-    symbols: Vec<String>,
-    client: Client,
-}
-
-impl MarketDataFeed {
-    pub fn new(symbols: Vec<String>) -> Self {
-        Self {
-            symbols,
-            client: Client::new(),
-        }
-    }
-
-    // Simulate market data - in real implementation, connect to actual APIs
-    pub async fn get_price(&self, symbol: &str) -> Option<Price> {
-        // This is a simulation - replace with actual API calls
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-
-        Some(Price {
-            symbol: symbol.to_string(),
-            price: rng.gen_range(0.1..100.0),
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            volume: rng.gen_range(100.0..10000.0),
-        })
-    }
-
-    pub async fn get_orderbook(&self, symbol: &str) -> Option<OrderBook> {
-        // Simulate orderbook data
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        let base_price = rng.gen_range(0.1..100.0);
-
-        let mut bids = Vec::new();
-        let mut asks = Vec::new();
-
-        for i in 1..=5 {
-            bids.push((base_price - i as f64 * 0.01, rng.gen_range(10.0..1000.0)));
-            asks.push((base_price + i as f64 * 0.01, rng.gen_range(10.0..1000.0)));
-        }
-
-        Some(OrderBook {
-            symbol: symbol.to_string(),
-            bids,
-            asks,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        })
-    }
-}
-
-// Order execution engine
-pub struct OrderExecutor {
-    client: Client,
-    pending_orders: Arc<Mutex<Vec<Order>>>,
-}
-
-impl OrderExecutor {
-    pub fn new() -> Self {
-        Self {
-            client: Client::new(),
-            pending_orders: Arc::new(Mutex::new(Vec::new())),
-        }
-    }
-
-    pub async fn submit_order(&self, order: Order) -> Result<String, String> {
-        // In real implementation, submit to exchange API
-        println!("Submitting order: {:?}", order);
-
-        let mut pending = self.pending_orders.lock().await;
-        pending.push(order.clone());
-
-        // Simulate order execution delay
-        tokio::time::sleep(Duration::from_millis(10)).await;
-
-        Ok(order.id)
-    }
-
-    pub async fn cancel_order(&self, order_id: &str) -> Result<(), String> {
-        let mut pending = self.pending_orders.lock().await;
-        pending.retain(|o| o.id != order_id);
-        println!("Cancelled order: {}", order_id);
-        Ok(())
-    }
-}
-
-// Main trading bot
-pub struct TradingBot {
-    strategies: Vec<Box<dyn TradingStrategy>>,
-    risk_manager: RiskManager,
-    market_feed: MarketDataFeed,
-    order_executor: OrderExecutor,
-    price_history: Arc<RwLock<HashMap<String, Vec<Price>>>>,
-    is_running: Arc<Mutex<bool>>,
-}
-
-impl TradingBot {
-    pub fn new(symbols: Vec<String>) -> Self {
-        let strategies: Vec<Box<dyn TradingStrategy>> = vec![
-            Box::new(MomentumStrategy::new(10, 0.02)),
-            Box::new(MeanReversionStrategy::new(20, 0.03)),
-        ];
-
-        Self {
-            strategies,
-            risk_manager: RiskManager::new(RiskParams::default()),
-            market_feed: MarketDataFeed::new(symbols.clone()),
-            order_executor: OrderExecutor::new(),
-            price_history: Arc::new(RwLock::new(HashMap::new())),
-            is_running: Arc::new(Mutex::new(false)),
-        }
-    }
-
-    pub async fn start(&self, symbols: Vec<String>) {
-        *self.is_running.lock().await = true;
-        println!("Starting trading bot for symbols: {:?}", symbols);
-
-        let mut tasks = Vec::new();
-
-        // Start market data collection for each symbol
-        for symbol in symbols {
-            let symbol_clone = symbol.clone();
-            let market_feed = &self.market_feed;
-            let price_history = Arc::clone(&self.price_history);
-            let is_running = Arc::clone(&self.is_running);
-
-            let market_feed_ptr = market_feed as *const MarketDataFeed;
-
-            let task = tokio::spawn(async move {
-                let market_feed = unsafe { &*market_feed_ptr };
-
-                while *is_running.lock().await {
-                    if let Some(price) = market_feed.get_price(&symbol_clone).await {
-                        let mut history = price_history.write().await;
-                        let symbol_history =
-                            history.entry(symbol_clone.clone()).or_insert_with(Vec::new);
-
-                        symbol_history.push(price);
-
-                        // Keep only last 1000 prices
-                        if symbol_history.len() > 1000 {
-                            symbol_history.remove(0);
-                        }
-                    }
-
-                    tokio::time::sleep(Duration::from_millis(100)).await;
-                }
-            });
-
-            tasks.push(task);
-        }
-
-        // Start trading logic
-        let trading_task = self.run_trading_loop().await;
-        tasks.push(trading_task);
-
-        // Wait for all tasks
-        futures::future::join_all(tasks).await;
-    }
-
-    async fn run_trading_loop(&self) -> tokio::task::JoinHandle<()> {
-        let price_history = Arc::clone(&self.price_history);
-        let is_running = Arc::clone(&self.is_running);
-        let strategies = &self.strategies as *const Vec<Box<dyn TradingStrategy>>;
-        let risk_manager = &self.risk_manager as *const RiskManager;
-        let order_executor = &self.order_executor as *const OrderExecutor;
-        let market_feed = &self.market_feed as *const MarketDataFeed;
-
-        tokio::spawn(async move {
-            let strategies = unsafe { &*strategies };
-            let risk_manager = unsafe { &*risk_manager };
-            let order_executor = unsafe { &*order_executor };
-            let market_feed = unsafe { &*market_feed };
-
-            while *is_running.lock().await {
-                let history = price_history.read().await;
-
-                for (symbol, prices) in history.iter() {
-                    if prices.len() < 10 {
-                        continue;
-                    }
-
-                    if let Some(orderbook) = market_feed.get_orderbook(symbol).await {
-                        // Run strategies
-                        for strategy in strategies.iter() {
-                            if let Some(signal) = strategy.analyze(prices, &orderbook) {
-                                println!("Signal from {}: {:?}", strategy.name(), signal);
-
-                                // Create order
-                                let order = Order {
-                                    id: Uuid::new_v4().to_string(),
-                                    symbol: signal.symbol.clone(),
-                                    side: signal.action,
-                                    order_type: OrderType::Market,
-                                    quantity: signal.quantity,
-                                    price: None,
-                                    timestamp: std::time::SystemTime::now()
-                                        .duration_since(std::time::UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_secs(),
-                                };
-
-                                // Validate with risk manager
-                                if risk_manager
-                                    .validate_order(&order, signal.target_price)
-                                    .await
-                                {
-                                    // Submit order
-                                    if let Ok(order_id) =
-                                        order_executor.submit_order(order.clone()).await
-                                    {
-                                        println!("Order submitted: {}", order_id);
-
-                                        // Update position
-                                        let quantity = match order.side {
-                                            OrderSide::Buy => order.quantity,
-                                            OrderSide::Sell => -order.quantity,
-                                        };
-
-                                        risk_manager
-                                            .update_position(
-                                                &order.symbol,
-                                                quantity,
-                                                signal.target_price,
-                                            )
-                                            .await;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                tokio::time::sleep(Duration::from_millis(50)).await; // High frequency - 20 Hz
-            }
-        })
-    }
-
-    pub async fn stop(&self) {
-        *self.is_running.lock().await = false;
-        println!("Trading bot stopped");
-    }
-}
-
-// Example usage and main function
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    env_logger::init();
-
-    // Define trading symbols
-    let symbols = vec![
-        "SOL/USDT".to_string(),
-        "BTC/USDT".to_string(),
-        "ETH/USDT".to_string(),
-    ];
-
-    // Create and start the trading bot
-    let bot = TradingBot::new(symbols.clone());
-
-    println!("Starting high-frequency trading bot...");
-
-    // Run for a specific duration or until interrupted
-    let bot_task = tokio::spawn(async move {
-        bot.start(symbols).await;
-    });
-
-    // Run for 60 seconds then stop (in production, you'd run indefinitely)
-    tokio::time::sleep(Duration::from_secs(60)).await;
-
-    println!("Shutting down...");
-    bot_task.abort();
-
-    Ok(())
-}
-
-// Add to Cargo.toml dependencies:
-/*
-[dependencies]
-tokio = { version = "1.0", features = ["full"] }
-serde = { version = "1.0", features = ["derive"] }
-serde_json = "1.0"
-reqwest = { version = "0.11", features = ["json"] }
-uuid = { version = "1.0", features = ["v4"] }
-futures = "0.3"
-rand = "0.8"
-env_logger = "0.10"
-log = "0.4"
-*/
+use hft_trading_bot::builder::TradingBotBuilder;
+use hft_trading_bot::config::BotConfig;
+use hft_trading_bot::recording::{ReplayFeed, ReplaySpeed};
+use hft_trading_bot::rest_client::RestLimitConfig;
+use hft_trading_bot::symbols::{SymbolFormat, SymbolMapper, SymbolMapping, SymbolRegistry};
+use hft_trading_bot::venue::ExecutionMode;
+use hft_trading_bot::{
+    TradingBot, api, backtest, history, journal, metrics, notify, optimize, recording, report,
+    simulation,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Example usage and main function
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    env_logger::init();
+
+    let args: Vec<String> = std::env::args().collect();
+
+    // `fetch-data <symbol> <start> <end> <out.csv> [--interval 1m | --trades]
+    // [--base-url URL]` downloads history for the backtester. Dates are
+    // YYYY-MM-DD (UTC) or Unix seconds; the end is exclusive.
+    if args.get(1).map(String::as_str) == Some("fetch-data") {
+        const USAGE: &str = "usage: hft_trading_bot fetch-data <symbol> <start> <end> <out.csv> \
+                             [--interval 1m | --trades] [--base-url URL]";
+        let [symbol, start, end, output] = match args.get(2..6) {
+            Some([a, b, c, d]) => [a, b, c, d],
+            _ => return Err(USAGE.into()),
+        };
+        let mut kind = history::DataKind::Klines {
+            interval: "1m".to_string(),
+        };
+        let mut base_url = history::BinanceHistory::DEFAULT_BASE_URL.to_string();
+        let mut flags = args[6..].iter();
+        while let Some(flag) = flags.next() {
+            match flag.as_str() {
+                "--interval" => {
+                    let interval = flags.next().ok_or(USAGE)?;
+                    kind = history::DataKind::Klines {
+                        interval: interval.clone(),
+                    };
+                }
+                "--trades" => kind = history::DataKind::AggTrades,
+                "--base-url" => base_url = flags.next().ok_or(USAGE)?.clone(),
+                _ => return Err(USAGE.into()),
+            }
+        }
+        let downloader = history::Downloader::new(history::BinanceHistory::new(
+            &base_url,
+            RestLimitConfig::default(),
+        ));
+        let summary = downloader
+            .download(
+                symbol,
+                &kind,
+                history::parse_date_ms(start)?,
+                history::parse_date_ms(end)?,
+                std::path::Path::new(output),
+            )
+            .await?;
+        println!(
+            "Wrote {} rows to {} ({} chunks downloaded, {} already present)",
+            summary.rows, output, summary.chunks_downloaded, summary.chunks_skipped
+        );
+        return Ok(());
+    }
+
+    // `journal-trades <journal.sqlite> <YYYY-MM-DD> [symbol]` prints a UTC
+    // day's fills from the trade journal as CSV
+    if args.get(1).map(String::as_str) == Some("journal-trades") {
+        let (Some(path), Some(day)) = (args.get(2), args.get(3)) else {
+            return Err(
+                "usage: hft_trading_bot journal-trades <journal.sqlite> <YYYY-MM-DD> [symbol]"
+                    .into(),
+            );
+        };
+        let path = std::path::Path::new(path);
+        if !path.exists() {
+            return Err(format!("No journal at {}", path.display()).into());
+        }
+        let store = journal::JournalStore::open(path)?;
+        let from = history::parse_date_ms(day)? / 1_000;
+        let fills = store.trades(args.get(4).map(String::as_str), from, from + 86_400)?;
+        print!("{}", journal::trades_csv(&fills));
+        return Ok(());
+    }
+
+    // `report <journal.sqlite> <YYYY-MM-DD> [dir]` rebuilds a UTC day's PnL
+    // report from the trade journal, writing CSV and JSON to dir if given
+    if args.get(1).map(String::as_str) == Some("report") {
+        let (Some(path), Some(day)) = (args.get(2), args.get(3)) else {
+            return Err("usage: hft_trading_bot report <journal.sqlite> <YYYY-MM-DD> [dir]".into());
+        };
+        let path = std::path::Path::new(path);
+        if !path.exists() {
+            return Err(format!("No journal at {}", path.display()).into());
+        }
+        let store = journal::JournalStore::open(path)?;
+        let from = history::parse_date_ms(day)? / 1_000;
+        let (fills, positions) = store.booked_fills(from, from + 86_400)?;
+        let generated_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let report =
+            report::DailyReport::new(from / 86_400, generated_at, &fills, positions, Vec::new());
+        print!("{}", report.to_text());
+        if let Some(dir) = args.get(4) {
+            let formats = [report::ReportFormat::Csv, report::ReportFormat::Json];
+            for path in report.write(std::path::Path::new(dir), &formats)? {
+                println!("Wrote {}", path.display());
+            }
+        }
+        return Ok(());
+    }
+
+    // Load configuration (BOT_CONFIG points at a TOML file; defaults otherwise)
+    let mut config = match std::env::var("BOT_CONFIG") {
+        Ok(path) => BotConfig::load(&path)?,
+        Err(_) => BotConfig::default(),
+    };
+
+    // Trading rules from the venue fill in symbols the config doesn't cover.
+    // They're named as the primary venue names them, or as Binance does.
+    if let Ok(url) = std::env::var("EXCHANGE_INFO_URL") {
+        let mapper = config
+            .venues
+            .venues
+            .get(&config.venues.primary)
+            .map_or_else(
+                || SymbolMapper::new(SymbolFormat::Concatenated, &SymbolMapping::default()),
+                |venue| venue.symbol_mapper(),
+            );
+        let registry = SymbolRegistry::fetch(&url, &mapper).await?;
+        for (symbol, meta) in registry.into_inner() {
+            config.symbol_meta.entry(symbol).or_insert(meta);
+        }
+    }
+    let symbols = config.symbols.clone();
+
+    // `backtest <data.csv> [config.toml]` replays history instead of trading
+    if args.get(1).map(String::as_str) == Some("backtest") {
+        let Some(data_path) = args.get(2) else {
+            return Err("usage: hft_trading_bot backtest <data.csv> [config.toml]".into());
+        };
+        if let Some(path) = args.get(3) {
+            config = BotConfig::load(path)?;
+        }
+        let default_symbol = config.symbols.first().cloned().unwrap_or_default();
+        let records = backtest::load_csv(data_path, &default_symbol)?;
+        let result = backtest::Backtester::new(&config).run(&records).await;
+        println!("{}", result);
+        println!("{}", serde_json::to_string_pretty(&result.performance)?);
+        return Ok(());
+    }
+
+    // `simulate [config.toml] [--seed N]` runs the bot against the simulated
+    // market on a virtual clock; a seeded run is exactly repeatable
+    if args.get(1).map(String::as_str) == Some("simulate") {
+        let mut rest = args[2..].iter();
+        while let Some(arg) = rest.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let seed = rest
+                        .next()
+                        .and_then(|s| s.parse().ok())
+                        .ok_or("usage: hft_trading_bot simulate [config.toml] [--seed N]")?;
+                    config.simulation.seed = Some(seed);
+                }
+                path => {
+                    let seed = config.simulation.seed;
+                    config = BotConfig::load(path)?;
+                    config.simulation.seed = seed.or(config.simulation.seed);
+                }
+            }
+        }
+        let simulation = simulation::Simulator::new(&config).run().await;
+        println!("{}", simulation.result);
+        return Ok(());
+    }
+
+    // `replay <recording> [config.toml] [--realtime]` feeds a market data
+    // recording back through the bot: as fast as possible through the
+    // backtester, or in real time through the live loop
+    if args.get(1).map(String::as_str) == Some("replay") {
+        let Some(recording) = args.get(2) else {
+            return Err(
+                "usage: hft_trading_bot replay <recording dir|file> [config.toml] [--realtime]"
+                    .into(),
+            );
+        };
+        let realtime = args[3..].iter().any(|a| a == "--realtime");
+        if let Some(path) = args[3..].iter().find(|a| !a.starts_with("--")) {
+            config = BotConfig::load(path)?;
+        }
+        // Replaying must not record a copy of itself, journal its trades or
+        // overwrite the live bot's state
+        config.recording.enabled = false;
+        config.journal.enabled = false;
+        config.state.enabled = false;
+
+        let events = recording::load_recording(std::path::Path::new(recording))?;
+        if !realtime {
+            let result = backtest::Backtester::new(&config).replay(&events).await;
+            println!("{}", result);
+            return Ok(());
+        }
+
+        let feed = Arc::new(ReplayFeed::new(events, ReplaySpeed::RealTime));
+        let bot = TradingBot::from_config(&config).with_market_data(feed.clone());
+        let symbols = feed.symbols();
+        let bot_task = tokio::spawn(async move { bot.start(symbols).await });
+        while !feed.is_finished() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        // Let the last ticks be evaluated
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        bot_task.abort();
+        return Ok(());
+    }
+
+    // `optimize <data.csv> <config.toml> [out.csv|out.json]` backtests every
+    // combination in the config's [optimize] grid (or walks it forward)
+    if args.get(1).map(String::as_str) == Some("optimize") {
+        let (Some(data_path), Some(config_path)) = (args.get(2), args.get(3)) else {
+            return Err(
+                "usage: hft_trading_bot optimize <data.csv> <config.toml> [out.csv|out.json]"
+                    .into(),
+            );
+        };
+        config = BotConfig::load(config_path)?;
+        let default_symbol = config.symbols.first().cloned().unwrap_or_default();
+        let records = backtest::load_csv(data_path, &default_symbol)?;
+        let optimizer = optimize::Optimizer::new(&config);
+        let json_output = args.get(4).is_some_and(|path| path.ends_with(".json"));
+        let output = if config.optimize.walk_forward.is_some() {
+            let windows = optimizer.walk_forward(&records).await?;
+            if json_output {
+                serde_json::to_string_pretty(&windows)?
+            } else {
+                optimize::walk_forward_csv(&windows)
+            }
+        } else {
+            let rows = optimizer.sweep(&records).await?;
+            if json_output {
+                serde_json::to_string_pretty(&rows)?
+            } else {
+                optimize::sweep_csv(&rows)
+            }
+        };
+        match args.get(4) {
+            Some(path) => std::fs::write(path, output)?,
+            None => print!("{}", output),
+        }
+        return Ok(());
+    }
+
+    // A live config only trades live when started with the flag as well
+    const LIVE_FLAG: &str = "--i-know-this-is-live";
+    let live_confirmed = args.iter().any(|arg| arg == LIVE_FLAG);
+    let bot = match config.mode {
+        ExecutionMode::Live if !live_confirmed => {
+            return Err(format!(
+                "mode = \"live\" sends real orders; start with {} to confirm",
+                LIVE_FLAG
+            )
+            .into());
+        }
+        ExecutionMode::Live => TradingBotBuilder::from_config(&config)
+            .confirm_live()
+            .build()?,
+        _ => {
+            if live_confirmed {
+                println!(
+                    "{} ignored: the config's mode is {}",
+                    LIVE_FLAG, config.mode
+                );
+            }
+            TradingBot::from_config(&config)
+        }
+    };
+
+    println!(
+        "Execution mode: {} (orders go to {})",
+        bot.execution_mode(),
+        bot.primary_venue()
+    );
+
+    // Pick up any state left by a previous run
+    if config.state.enabled {
+        bot.recover().await?;
+    }
+    if config.metrics.enabled {
+        let addr = metrics::serve(bot.metrics(), &config.metrics.bind).await?;
+        println!("Serving metrics on http://{}/metrics", addr);
+    }
+    if config.api.enabled {
+        let addr = api::serve(bot.clone(), &config.api).await?;
+        println!("Serving API on http://{}", addr);
+    }
+    if config.notify.enabled {
+        notify::Notifier::new(&config.notify)?.spawn(bot.subscribe_events());
+    }
+
+    println!("Starting high-frequency trading bot...");
+
+    // Run for a specific duration or until interrupted
+    let running = bot.clone();
+    let bot_task = tokio::spawn(async move {
+        running.start(symbols).await;
+    });
+
+    // Run for 60 seconds then stop (in production, you'd run indefinitely)
+    tokio::time::sleep(Duration::from_secs(60)).await;
+
+    println!("Shutting down...");
+    bot.stop().await;
+    let _ = bot_task.await;
+
+    Ok(())
+}
+
+// Add to Cargo.toml dependencies:
+/*
+[dependencies]
+tokio = { version = "1.0", features = ["full"] }
+serde = { version = "1.0", features = ["derive"] }
+serde_json = "1.0"
+reqwest = { version = "0.11", features = ["json"] }
+uuid = { version = "1.0", features = ["v4"] }
+futures = "0.3"
+rand = "0.8"
+env_logger = "0.10"
+log = "0.4"
+*/
@@ -0,0 +1,71 @@
+use crate::Position;
+use serde::{Deserialize, Serialize};
+
+// Margin on perpetual futures. Spot positions are paid for in full and
+// don't use it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarginConfig {
+    // Initial margin is notional / leverage
+    pub leverage: f64,
+    // Fraction of notional below which equity gets a position liquidated
+    pub maintenance_margin_pct: f64,
+    // Fraction of equity initial margin may take up
+    pub max_margin_usage: f64,
+}
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        Self {
+            leverage: 5.0,
+            maintenance_margin_pct: 0.005,
+            max_margin_usage: 0.8,
+        }
+    }
+}
+
+impl MarginConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.leverage < 1.0 {
+            return Err("Margin leverage must be at least 1.0".to_string());
+        }
+        if !(0.0..1.0).contains(&self.maintenance_margin_pct) {
+            return Err("Margin maintenance_margin_pct must be in [0, 1)".to_string());
+        }
+        if self.maintenance_margin_pct >= self.initial_margin_pct() {
+            return Err(
+                "Margin maintenance_margin_pct must be below the initial margin (1 / leverage)"
+                    .to_string(),
+            );
+        }
+        if self.max_margin_usage <= 0.0 || self.max_margin_usage > 1.0 {
+            return Err("Margin max_margin_usage must be in (0, 1]".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn initial_margin_pct(&self) -> f64 {
+        1.0 / self.leverage
+    }
+
+    pub fn initial_margin(&self, notional: f64) -> f64 {
+        notional.abs() * self.initial_margin_pct()
+    }
+
+    /// Where the position would be liquidated, were it margined on its own
+    /// with its initial margin: the price at which what's left of that
+    /// margin falls to the maintenance margin. Cross margin lets the rest
+    /// of the account's equity carry it further, so this is the near side
+    /// of the real figure. None when flat.
+    pub fn liquidation_price(&self, position: &Position) -> Option<f64> {
+        if position.quantity == 0.0 {
+            return None;
+        }
+        let cushion = self.initial_margin_pct() - self.maintenance_margin_pct;
+        Some(if position.quantity > 0.0 {
+            position.avg_price * (1.0 - cushion)
+        } else {
+            position.avg_price * (1.0 + cushion)
+        })
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{OrderBook, Price};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+// Which price positions are marked at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkSource {
+    #[default]
+    LastTrade,
+    // The book's mid; the last trade until the symbol's first book
+    Mid,
+}
+
+// Marking positions to market
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkConfig {
+    pub source: MarkSource,
+    // How often positions' unrealized PnL is brought up to date
+    pub refresh_interval_ms: u64,
+}
+
+impl Default for MarkConfig {
+    fn default() -> Self {
+        Self {
+            source: MarkSource::LastTrade,
+            refresh_interval_ms: 1_000,
+        }
+    }
+}
+
+impl MarkConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.refresh_interval_ms == 0 {
+            return Err("Marks refresh_interval_ms must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Mark {
+    price: f64,
+    // Seconds, from the price or book it came from
+    timestamp: u64,
+    from_book: bool,
+}
+
+/// The current price of every symbol, written by the market data tasks
+/// and read by risk checks, equity and position marking, without touching
+/// the price history. Reads and writes are a map lookup under a short
+/// lock.
+#[derive(Debug, Default)]
+pub struct MarkPriceCache {
+    source: MarkSource,
+    marks: RwLock<HashMap<String, Mark>>,
+}
+
+impl MarkPriceCache {
+    pub fn new(source: MarkSource) -> Self {
+        Self {
+            source,
+            marks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn source(&self) -> MarkSource {
+        self.source
+    }
+
+    // The symbol's mark and when it was set (seconds)
+    pub fn get(&self, symbol: &str) -> Option<(f64, u64)> {
+        self.marks
+            .read()
+            .unwrap()
+            .get(symbol)
+            .map(|mark| (mark.price, mark.timestamp))
+    }
+
+    pub fn price(&self, symbol: &str) -> Option<f64> {
+        self.get(symbol).map(|(price, _)| price)
+    }
+
+    pub fn record_price(&self, price: &Price) {
+        let mut marks = self.marks.write().unwrap();
+        if self.source == MarkSource::Mid
+            && marks.get(&price.symbol).is_some_and(|mark| mark.from_book)
+        {
+            return;
+        }
+        marks.insert(
+            price.symbol.clone(),
+            Mark {
+                price: price.price,
+                timestamp: price.timestamp,
+                from_book: false,
+            },
+        );
+    }
+
+    pub fn record_book(&self, book: &OrderBook) {
+        if self.source != MarkSource::Mid {
+            return;
+        }
+        let Some(mid) = book.mid_price() else {
+            return;
+        };
+        self.marks.write().unwrap().insert(
+            book.symbol.clone(),
+            Mark {
+                price: mid,
+                timestamp: book.timestamp,
+                from_book: true,
+            },
+        );
+    }
+
+    pub fn remove(&self, symbol: &str) {
+        self.marks.write().unwrap().remove(symbol);
+    }
+}
@@ -0,0 +1,339 @@
+use axum::Router;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use prometheus::{
+    Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    Opts, Registry, TextEncoder,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+// Latency buckets in seconds, from 100us up to a second
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+// Prometheus endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    // Address the /metrics endpoint listens on
+    pub bind: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:9898".to_string(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.bind.parse::<SocketAddr>().is_err() {
+            return Err(format!("Invalid metrics bind address: {}", self.bind));
+        }
+        Ok(())
+    }
+}
+
+/// The bot's counters, gauges and histograms, in a registry of its own so
+/// several bots (the optimizer's) never collide. Every update is an atomic
+/// add or store behind a label lookup, cheap enough for the trading loop.
+pub struct Metrics {
+    registry: Registry,
+    pub price_updates: IntCounterVec,
+    pub signals: IntCounterVec,
+    pub strategy_panics: IntCounterVec,
+    pub task_failures: IntCounterVec,
+    pub orders_submitted: IntCounterVec,
+    // Labeled by where the order was stopped: "risk", "rules" (tick/lot
+    // and minimums) or "venue"
+    pub orders_rejected: IntCounterVec,
+    pub fills: IntCounterVec,
+    pub position_quantity: GaugeVec,
+    pub unrealized_pnl: GaugeVec,
+    pub daily_pnl: Gauge,
+    pub loop_iteration_seconds: Histogram,
+    pub order_submit_seconds: Histogram,
+    // Per submitted order, labeled by latency::Stage
+    pub pipeline_stage_seconds: HistogramVec,
+    // Events that event stream subscribers fell too far behind to receive
+    pub events_dropped: IntCounter,
+    // Fraction of each venue's REST request weight limit in use
+    pub rest_weight_utilization: GaugeVec,
+    // 1 while a feed's WebSocket is connected, 0 otherwise
+    pub feed_connected: GaugeVec,
+    pub feed_reconnects: IntCounterVec,
+    // Books dropped for failing the venue's checksum, by symbol
+    pub book_checksum_failures: IntCounterVec,
+    // Ticks the spike filter caught, by symbol, whether dropped or flagged
+    pub bad_ticks: IntCounterVec,
+    // Ticks the price history turned away or slotted in late, by symbol
+    pub duplicate_ticks: IntCounterVec,
+    pub out_of_order_ticks: IntCounterVec,
+    // Rolling correlation of returns, by symbol pair
+    pub symbol_correlation: GaugeVec,
+    // Attributed to the strategy that opened the position (see attribution),
+    // labeled by strategy and mode: "live", or "shadow" for hypothetical fills
+    pub strategy_realized_pnl: GaugeVec,
+    pub strategy_unrealized_pnl: GaugeVec,
+    pub strategy_trades: IntCounterVec,
+    pub strategy_winning_trades: IntCounterVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, label: &str| {
+            let metric = IntCounterVec::new(Opts::new(name, help), &[label])
+                .expect("valid counter definition");
+            registry
+                .register(Box::new(metric.clone()))
+                .expect("metric names are unique");
+            metric
+        };
+        let gauge = |name: &str, help: &str| {
+            let metric =
+                GaugeVec::new(Opts::new(name, help), &["symbol"]).expect("valid gauge definition");
+            registry
+                .register(Box::new(metric.clone()))
+                .expect("metric names are unique");
+            metric
+        };
+        let histogram = |name: &str, help: &str| {
+            let metric = Histogram::with_opts(
+                HistogramOpts::new(name, help).buckets(LATENCY_BUCKETS.to_vec()),
+            )
+            .expect("valid histogram definition");
+            registry
+                .register(Box::new(metric.clone()))
+                .expect("metric names are unique");
+            metric
+        };
+
+        let price_updates = counter("hft_price_updates_total", "Price ticks received", "symbol");
+        let signals = counter("hft_signals_total", "Signals generated", "strategy");
+        let strategy_panics = counter(
+            "hft_strategy_panics_total",
+            "Panics caught from strategies",
+            "strategy",
+        );
+        let task_failures = counter("hft_task_failures_total", "Bot tasks that panicked", "task");
+        let orders_submitted = counter(
+            "hft_orders_submitted_total",
+            "Orders accepted by the executor",
+            "symbol",
+        );
+        let orders_rejected = counter(
+            "hft_orders_rejected_total",
+            "Orders rejected before or at submission",
+            "reason",
+        );
+        let fills = counter("hft_fills_total", "Fills received", "symbol");
+        let position_quantity = gauge("hft_position_quantity", "Signed position quantity");
+        let unrealized_pnl = gauge(
+            "hft_unrealized_pnl",
+            "Unrealized PnL of the position at the latest price",
+        );
+        let loop_iteration_seconds = histogram(
+            "hft_loop_iteration_seconds",
+            "Time taken by one evaluation pass of a symbol",
+        );
+        let order_submit_seconds =
+            histogram("hft_order_submit_seconds", "Time taken to submit an order");
+        let pipeline_stage_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "hft_pipeline_stage_seconds",
+                "Time a submitted order spent in each stage from price to submission",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["stage"],
+        )
+        .expect("valid histogram definition");
+        registry
+            .register(Box::new(pipeline_stage_seconds.clone()))
+            .expect("metric names are unique");
+        let daily_pnl = Gauge::new("hft_daily_pnl", "Realized PnL for the current trading day")
+            .expect("valid gauge definition");
+        registry
+            .register(Box::new(daily_pnl.clone()))
+            .expect("metric names are unique");
+        let events_dropped = IntCounter::new(
+            "hft_events_dropped_total",
+            "Events dropped for event stream subscribers that fell behind",
+        )
+        .expect("valid counter definition");
+        registry
+            .register(Box::new(events_dropped.clone()))
+            .expect("metric names are unique");
+        let rest_weight_utilization = GaugeVec::new(
+            Opts::new(
+                "hft_rest_weight_utilization",
+                "Fraction of the venue's REST request weight limit used in the current window",
+            ),
+            &["venue"],
+        )
+        .expect("valid gauge definition");
+        registry
+            .register(Box::new(rest_weight_utilization.clone()))
+            .expect("metric names are unique");
+        let feed_connected = GaugeVec::new(
+            Opts::new(
+                "hft_feed_connected",
+                "Whether the feed's WebSocket connection is up",
+            ),
+            &["feed"],
+        )
+        .expect("valid gauge definition");
+        registry
+            .register(Box::new(feed_connected.clone()))
+            .expect("metric names are unique");
+        let feed_reconnects = counter(
+            "hft_feed_reconnects_total",
+            "WebSocket reconnections after a feed dropped",
+            "feed",
+        );
+        let book_checksum_failures = counter(
+            "hft_book_checksum_failures_total",
+            "Order books dropped for not matching the venue's checksum",
+            "symbol",
+        );
+        let bad_ticks = counter(
+            "hft_bad_ticks_total",
+            "Ticks the spike filter caught as bad prints",
+            "symbol",
+        );
+        let duplicate_ticks = counter(
+            "hft_duplicate_ticks_total",
+            "Ticks dropped as exact repeats of one already kept",
+            "symbol",
+        );
+        let out_of_order_ticks = counter(
+            "hft_out_of_order_ticks_total",
+            "Ticks older than the newest kept, dropped or inserted in order",
+            "symbol",
+        );
+        let symbol_correlation = GaugeVec::new(
+            Opts::new(
+                "hft_symbol_correlation",
+                "Rolling correlation between the two symbols' returns",
+            ),
+            &["symbol", "other"],
+        )
+        .expect("valid gauge definition");
+        registry
+            .register(Box::new(symbol_correlation.clone()))
+            .expect("metric names are unique");
+        let strategy_gauge = |name: &str, help: &str| {
+            let metric = GaugeVec::new(Opts::new(name, help), &["strategy", "mode"])
+                .expect("valid gauge definition");
+            registry
+                .register(Box::new(metric.clone()))
+                .expect("metric names are unique");
+            metric
+        };
+        let strategy_counter = |name: &str, help: &str| {
+            let metric = IntCounterVec::new(Opts::new(name, help), &["strategy", "mode"])
+                .expect("valid counter definition");
+            registry
+                .register(Box::new(metric.clone()))
+                .expect("metric names are unique");
+            metric
+        };
+        let strategy_realized_pnl = strategy_gauge(
+            "hft_strategy_realized_pnl",
+            "Realized PnL of positions the strategy opened, before fees",
+        );
+        let strategy_unrealized_pnl = strategy_gauge(
+            "hft_strategy_unrealized_pnl",
+            "Unrealized PnL of the strategy's open lots as of its last update",
+        );
+        let strategy_trades = strategy_counter(
+            "hft_strategy_trades_total",
+            "Fills that closed part of a position the strategy opened",
+        );
+        let strategy_winning_trades = strategy_counter(
+            "hft_strategy_winning_trades_total",
+            "Closing fills that gave the strategy a positive realized PnL",
+        );
+
+        Self {
+            registry,
+            price_updates,
+            signals,
+            strategy_panics,
+            task_failures,
+            orders_submitted,
+            orders_rejected,
+            fills,
+            position_quantity,
+            unrealized_pnl,
+            daily_pnl,
+            loop_iteration_seconds,
+            order_submit_seconds,
+            pipeline_stage_seconds,
+            events_dropped,
+            rest_weight_utilization,
+            feed_connected,
+            feed_reconnects,
+            book_checksum_failures,
+            bad_ticks,
+            duplicate_ticks,
+            out_of_order_ticks,
+            symbol_correlation,
+            strategy_realized_pnl,
+            strategy_unrealized_pnl,
+            strategy_trades,
+            strategy_winning_trades,
+        }
+    }
+
+    // Everything in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding into a Vec can't fail");
+        String::from_utf8(buffer).expect("the text format is UTF-8")
+    }
+}
+
+async fn scrape(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        metrics.render(),
+    )
+}
+
+/// Serves GET /metrics on `bind` in a background task and returns the
+/// address actually bound (useful with port 0)
+pub async fn serve(metrics: Arc<Metrics>, bind: &str) -> Result<SocketAddr, String> {
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .map_err(|e| format!("Failed to bind metrics endpoint {}: {}", bind, e))?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to bind metrics endpoint {}: {}", bind, e))?;
+    let app = Router::new()
+        .route("/metrics", get(scrape))
+        .with_state(metrics);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            println!("Metrics endpoint stopped: {}", e);
+        }
+    });
+    Ok(addr)
+}
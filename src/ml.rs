@@ -0,0 +1,338 @@
+use crate::indicators::SymbolIndicators;
+use crate::{OrderBook, OrderSide, Price, TimeInForce, TradingSignal, TradingStrategy};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Book levels the imbalance feature is taken over
+pub const IMBALANCE_LEVELS: usize = 5;
+
+/// A model scoring a symbol's feature vector. Positive scores say buy,
+/// negative sell, and the magnitude is how sure the model is.
+pub trait Model: Send + Sync {
+    fn predict(&self, features: &[f32]) -> Result<f32, String>;
+}
+
+// Features per evaluation: feature_window returns, the newest volume over
+// the window's mean, and the book imbalance
+pub fn feature_count(feature_window: usize) -> usize {
+    feature_window + 2
+}
+
+/// The feature vector from a symbol's last feature_window + 1 prices
+/// (oldest first), its volumes and its book. None while a price is unusable.
+pub fn features(
+    prices: &[f64],
+    newest_volume: f64,
+    mean_volume: f64,
+    orderbook: &OrderBook,
+) -> Option<Vec<f32>> {
+    let mut features = Vec::with_capacity(prices.len() + 1);
+    for pair in prices.windows(2) {
+        let (previous, current) = (pair[0], pair[1]);
+        // A zero (or garbage) reference price would give an infinite return
+        if previous <= 0.0 || !previous.is_finite() || !current.is_finite() {
+            return None;
+        }
+        features.push(((current - previous) / previous) as f32);
+    }
+    let relative_volume = if mean_volume > 0.0 {
+        newest_volume / mean_volume
+    } else {
+        0.0
+    };
+    features.push(relative_volume as f32);
+    features.push(orderbook.imbalance(IMBALANCE_LEVELS).unwrap_or(0.0) as f32);
+    Some(features)
+}
+
+/// Trades on a model's score: a score whose magnitude is above the
+/// threshold becomes a signal with that magnitude as its confidence. A
+/// model that fails to score disables the strategy instead of taking the
+/// evaluation loop down with it.
+pub struct MlStrategy {
+    model: Box<dyn Model>,
+    feature_window: usize,
+    threshold: f64,
+    base_quantity: f64,
+    disabled: AtomicBool,
+}
+
+impl MlStrategy {
+    // Panics if feature_window is 0, since there'd be no return to score
+    pub fn new(
+        model: Box<dyn Model>,
+        feature_window: usize,
+        threshold: f64,
+        base_quantity: f64,
+    ) -> Self {
+        assert!(
+            feature_window >= 1,
+            "MlStrategy feature_window must be at least 1, got {}",
+            feature_window
+        );
+        Self {
+            model,
+            feature_window,
+            threshold,
+            base_quantity,
+            disabled: AtomicBool::new(false),
+        }
+    }
+
+    // The strategy with the ONNX model at model_path, which must take
+    // feature_count(feature_window) inputs
+    pub fn load(
+        model_path: &str,
+        feature_window: usize,
+        threshold: f64,
+        base_quantity: f64,
+    ) -> Result<Self, String> {
+        let model = load_model(model_path, feature_count(feature_window))?;
+        Ok(Self::new(model, feature_window, threshold, base_quantity))
+    }
+
+    // Whether a failed inference has switched the strategy off
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::Relaxed)
+    }
+
+    fn signal_from(
+        &self,
+        symbol: impl Into<Arc<str>>,
+        newest: f64,
+        features: &[f32],
+    ) -> Option<TradingSignal> {
+        if self.is_disabled() {
+            return None;
+        }
+        let score = match self.model.predict(features) {
+            Ok(score) if score.is_finite() => score as f64,
+            Ok(score) => return self.disable(format!("model scored {}", score)),
+            Err(e) => return self.disable(e),
+        };
+        if score.abs() <= self.threshold {
+            return None;
+        }
+        let action = if score > 0.0 {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        };
+        Some(TradingSignal {
+            symbol: symbol.into(),
+            action,
+            confidence: score.abs().min(1.0),
+            target_price: newest,
+            quantity: self.base_quantity,
+            time_in_force: TimeInForce::Gtc,
+            limit_price: None,
+        })
+    }
+
+    fn disable(&self, reason: String) -> Option<TradingSignal> {
+        if !self.disabled.swap(true, Ordering::Relaxed) {
+            println!("MlStrategy inference failed, disabling it: {}", reason);
+        }
+        None
+    }
+}
+
+impl TradingStrategy for MlStrategy {
+    fn analyze(&self, prices: &[Price], orderbook: &OrderBook) -> Option<TradingSignal> {
+        // The feature window plus the price its first return is taken from
+        let window = &prices[prices.len().checked_sub(self.feature_window + 1)?..];
+        let closes: Vec<f64> = window.iter().map(|p| p.price).collect();
+        let newest = window.last()?;
+        let mean_volume = window.iter().map(|p| p.volume).sum::<f64>() / window.len() as f64;
+        let features = features(&closes, newest.volume, mean_volume, orderbook)?;
+        self.signal_from(newest.symbol.as_str(), newest.price, &features)
+    }
+
+    fn name(&self) -> &str {
+        "MlStrategy"
+    }
+
+    fn indicator_windows(&self) -> Vec<usize> {
+        vec![self.feature_window + 1]
+    }
+
+    fn analyze_incremental(
+        &self,
+        prices: &[Price],
+        indicators: &SymbolIndicators,
+        orderbook: &OrderBook,
+    ) -> Option<TradingSignal> {
+        let (Some(price_window), Some(volume_window)) = (
+            indicators.price(self.feature_window + 1),
+            indicators.volume(self.feature_window + 1),
+        ) else {
+            return self.analyze(prices, orderbook);
+        };
+
+        if !price_window.is_full() {
+            return None;
+        }
+
+        let closes: Vec<f64> = price_window.iter().collect();
+        let features = features(
+            &closes,
+            volume_window.newest()?,
+            volume_window.mean()?,
+            orderbook,
+        )?;
+        self.signal_from(
+            indicators.symbol().clone(),
+            price_window.newest()?,
+            &features,
+        )
+    }
+}
+
+#[cfg(feature = "onnx")]
+fn load_model(path: &str, inputs: usize) -> Result<Box<dyn Model>, String> {
+    Ok(Box::new(OnnxModel::load(path, inputs)?))
+}
+
+#[cfg(not(feature = "onnx"))]
+fn load_model(path: &str, _inputs: usize) -> Result<Box<dyn Model>, String> {
+    Err(format!(
+        "Can't load model {}: built without the onnx feature (cargo build --features onnx)",
+        path
+    ))
+}
+
+/// An ONNX model run by ONNX Runtime, loaded from ORT_DYLIB_PATH (or
+/// libonnxruntime on the library path). The model takes one float tensor
+/// of shape [1, features] and its first output's first value is the score.
+#[cfg(feature = "onnx")]
+pub struct OnnxModel {
+    session: std::sync::Mutex<ort::session::Session>,
+    input: String,
+}
+
+#[cfg(feature = "onnx")]
+impl OnnxModel {
+    pub fn load(path: &str, inputs: usize) -> Result<Self, String> {
+        // ort panics rather than erroring when the runtime library is missing
+        let session =
+            std::panic::catch_unwind(|| ort::session::Session::builder()?.commit_from_file(path))
+                .map_err(|_| {
+                    format!(
+                        "Can't load model {}: ONNX Runtime isn't installed (set ORT_DYLIB_PATH)",
+                        path
+                    )
+                })?
+                .map_err(|e| format!("Failed to load model {}: {}", path, e))?;
+
+        let input = session
+            .inputs
+            .first()
+            .ok_or_else(|| format!("Model {} has no inputs", path))?;
+        // A dynamic (negative) width takes whatever it's given
+        if let Some(&width) = input.input_type.tensor_shape().and_then(|s| s.last())
+            && width >= 0
+            && width as usize != inputs
+        {
+            return Err(format!(
+                "Model {} takes {} features, the strategy gives it {}",
+                path, width, inputs
+            ));
+        }
+        if session.outputs.is_empty() {
+            return Err(format!("Model {} has no outputs", path));
+        }
+        Ok(Self {
+            input: input.name.clone(),
+            session: std::sync::Mutex::new(session),
+        })
+    }
+}
+
+#[cfg(feature = "onnx")]
+impl Model for OnnxModel {
+    fn predict(&self, features: &[f32]) -> Result<f32, String> {
+        let tensor = ort::value::Tensor::from_array(([1usize, features.len()], features.to_vec()))
+            .map_err(|e| e.to_string())?;
+        let mut session = self.session.lock().unwrap();
+        let outputs = session
+            .run(ort::inputs![self.input.as_str() => tensor])
+            .map_err(|e| e.to_string())?;
+        let (_, scores) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| e.to_string())?;
+        scores
+            .first()
+            .copied()
+            .ok_or_else(|| "model returned an empty score".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{order_book, price_history};
+
+    // The last six prices rise 1% a step
+    fn rising() -> Vec<Price> {
+        let mut prices = price_history("SOL/USDT", 20, 7);
+        let start = prices.len() - 6;
+        for (i, price) in prices[start..].iter_mut().enumerate() {
+            price.price = 100.0 * 1.01f64.powi(i as i32);
+        }
+        prices
+    }
+
+    struct Failing;
+
+    impl Model for Failing {
+        fn predict(&self, _features: &[f32]) -> Result<f32, String> {
+            Err("input rank mismatch".to_string())
+        }
+    }
+
+    #[test]
+    fn a_failing_model_disables_the_strategy_instead_of_panicking() {
+        let strategy = MlStrategy::new(Box::new(Failing), 5, 0.1, 1.0);
+        let book = order_book("SOL/USDT", 105.0, 5, 1);
+        assert!(strategy.analyze(&rising(), &book).is_none());
+        assert!(strategy.is_disabled());
+    }
+
+    #[cfg(not(feature = "onnx"))]
+    #[test]
+    fn loading_a_model_without_the_onnx_feature_is_an_error() {
+        let error = MlStrategy::load("data/models/tiny_score.onnx", 5, 0.1, 1.0)
+            .err()
+            .unwrap();
+        assert!(error.contains("onnx feature"));
+    }
+
+    // Needs ONNX Runtime: ORT_DYLIB_PATH or libonnxruntime on the library
+    // path. The model is tanh(10 × sum of returns + 0.5 × imbalance) over
+    // a feature window of 5.
+    #[cfg(feature = "onnx")]
+    #[test]
+    fn the_tiny_model_scores_a_rising_market_as_a_buy() {
+        let path = "data/models/tiny_score.onnx";
+        let strategy = MlStrategy::load(path, 5, 0.1, 2.0).unwrap();
+        let prices = rising();
+        let book = order_book("SOL/USDT", 105.0, 5, 1);
+
+        let signal = strategy.analyze(&prices, &book).unwrap();
+        let returns = 5.0 * 0.01;
+        let imbalance = book.imbalance(IMBALANCE_LEVELS).unwrap();
+        let expected = (10.0 * returns + 0.5 * imbalance).tanh();
+        assert_eq!(signal.action, OrderSide::Buy);
+        assert!((signal.confidence - expected).abs() < 1e-4);
+        assert_eq!(signal.quantity, 2.0);
+        assert!(!strategy.is_disabled());
+
+        assert!(
+            MlStrategy::load(path, 4, 0.1, 2.0)
+                .err()
+                .unwrap()
+                .contains("takes 7")
+        );
+        assert!(MlStrategy::load("data/models/missing.onnx", 5, 0.1, 2.0).is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::symbols::SymbolId;
+use crate::venue::VenueId;
+use crate::{Fill, Position, PositionKey, PositionSide};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Why the bot closed a position on its own, as recorded in the journal.
+/// In priority order: when several rules trigger on the same tick, the
+/// first of them is the one acted on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    // The price crossed the stop-loss level
+    StopLoss,
+    // The price fell back the trailing distance from its best since entry
+    TrailingStop,
+    // The price reached the take-profit level
+    TakeProfit,
+    // Held past its maximum holding period
+    TimeStop,
+}
+
+impl ExitReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExitReason::StopLoss => "stop_loss",
+            ExitReason::TrailingStop => "trailing_stop",
+            ExitReason::TakeProfit => "take_profit",
+            ExitReason::TimeStop => "time_stop",
+        }
+    }
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A price a position is closed at
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Level {
+    pub price: f64,
+    // Held by a resting order the executor fills; the monitor leaves it be
+    pub resting: bool,
+}
+
+// What protects a position, fixed from its average price whenever it changes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExitRules {
+    pub stop_loss: Option<Level>,
+    pub take_profit: Option<Level>,
+    // Fraction behind the best price since entry
+    pub trailing_stop_pct: Option<f64>,
+}
+
+// A position's protection, as the status API shows it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProtectiveLevels {
+    pub venue: VenueId,
+    pub symbol: String,
+    pub side: PositionSide,
+    pub quantity: f64,
+    pub stop_loss: Option<Level>,
+    pub take_profit: Option<Level>,
+    pub trailing_stop: Option<f64>,
+    // Highest price since entry for a long, lowest for a short
+    pub best_price: f64,
+    // Seconds
+    pub opened_at: u64,
+    // When the time stop closes it, if one applies
+    pub time_stop_at: Option<u64>,
+    pub strategy: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Guard {
+    // Signed, as the fills left it
+    quantity: f64,
+    // Seconds, from the fill's timestamp
+    opened_at: u64,
+    // Whoever's fill opened it ("A+B" for an aggregated order)
+    strategy: Option<String>,
+    rules: ExitRules,
+    best_price: f64,
+    // The first rule a price crossed, and that price, until it's acted on
+    triggered: Option<(ExitReason, f64)>,
+    // A closing order is out; not sent again until the position changes
+    exiting: bool,
+}
+
+// A position to close, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exit {
+    pub venue: VenueId,
+    pub symbol: String,
+    pub side: PositionSide,
+    pub strategy: Option<String>,
+    pub reason: ExitReason,
+    // The price that crossed the level; None for a time stop
+    pub price: Option<f64>,
+    pub held_secs: u64,
+}
+
+/// Every open position's protective state: its stop-loss and take-profit
+/// levels, the trailing stop's best price and when it was opened and by
+/// whom. Prices latch the first rule they trigger, fills keep the state in
+/// step with the position, and take_exits hands over what is due. Hedge-mode
+/// legs are guarded apart, like positions on different venues.
+#[derive(Debug, Default)]
+pub struct PositionMonitor {
+    positions: HashMap<PositionKey, Guard>,
+}
+
+impl PositionMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Books a fill. `rules` are for the position it leaves: a new or
+    /// flipped position starts afresh from the fill, one that grew or
+    /// shrank keeps its clock (unless `reset_on_extend`) and best price.
+    pub fn apply_fill(&mut self, fill: &Fill, rules: ExitRules, reset_on_extend: bool) {
+        let key = (fill.venue.clone(), fill.symbol, fill.position_side);
+        let delta = fill.signed_quantity();
+        let Some(guard) = self.positions.get_mut(&key) else {
+            self.positions
+                .insert(key, Guard::opened_by(fill, delta, rules));
+            return;
+        };
+        let quantity = guard.quantity + delta;
+        if quantity.abs() < f64::EPSILON {
+            self.positions.remove(&key);
+        } else if quantity.signum() != guard.quantity.signum() {
+            // Flipped through flat: a new position, opened by this fill
+            *guard = Guard::opened_by(fill, quantity, rules);
+        } else {
+            if reset_on_extend && delta.signum() == guard.quantity.signum() {
+                guard.opened_at = fill.timestamp;
+            }
+            guard.quantity = quantity;
+            guard.rules = rules;
+            guard.track(fill.price);
+            guard.triggered = None;
+            guard.exiting = false;
+        }
+    }
+
+    // A position restored from a snapshot or set to a venue's figure; its
+    // clock starts over now
+    pub fn restore_position(&mut self, position: &Position, rules: ExitRules, now_secs: u64) {
+        let key = position.key();
+        if position.quantity.abs() < f64::EPSILON {
+            self.positions.remove(&key);
+            return;
+        }
+        self.positions.insert(
+            key,
+            Guard {
+                quantity: position.quantity,
+                opened_at: now_secs,
+                strategy: None,
+                rules,
+                best_price: position.avg_price,
+                triggered: None,
+                exiting: false,
+            },
+        );
+    }
+
+    /// Moves the symbol's trailing stops along and checks its positions'
+    /// levels against the price; true if one was newly crossed
+    pub fn on_price(&mut self, symbol: &str, price: f64) -> bool {
+        let mut crossed = false;
+        for ((_, held, _), guard) in &mut self.positions {
+            if held != symbol {
+                continue;
+            }
+            guard.track(price);
+            if guard.exiting || guard.triggered.is_some() {
+                continue;
+            }
+            guard.triggered = guard.crossed(price).map(|reason| (reason, price));
+            crossed |= guard.triggered.is_some();
+        }
+        crossed
+    }
+
+    /// Positions due to be closed: a crossed level, or else held longer
+    /// than `limit_secs` allows (given the symbol and the opening
+    /// strategy). They are then taken to be exiting until another fill
+    /// changes them.
+    pub fn take_exits(
+        &mut self,
+        now_secs: u64,
+        limit_secs: impl Fn(&str, Option<&str>) -> Option<u64>,
+    ) -> Vec<Exit> {
+        let mut exits = Vec::new();
+        for ((venue, symbol, side), guard) in &mut self.positions {
+            if guard.exiting {
+                continue;
+            }
+            let held_secs = now_secs.saturating_sub(guard.opened_at);
+            let (reason, price) = match guard.triggered {
+                Some((reason, price)) => (reason, Some(price)),
+                None if limit_secs(symbol.as_str(), guard.strategy.as_deref())
+                    .is_some_and(|limit| held_secs > limit) =>
+                {
+                    (ExitReason::TimeStop, None)
+                }
+                None => continue,
+            };
+            guard.exiting = true;
+            exits.push(Exit {
+                venue: venue.clone(),
+                symbol: symbol.to_string(),
+                side: *side,
+                strategy: guard.strategy.clone(),
+                reason,
+                price,
+                held_secs,
+            });
+        }
+        exits.sort_by(|a, b| (&a.symbol, &a.venue, a.side).cmp(&(&b.symbol, &b.venue, b.side)));
+        exits
+    }
+
+    // The closing order couldn't be sent; try again on the next check
+    pub fn exit_failed(&mut self, venue: &VenueId, symbol: &str, side: PositionSide) {
+        if let Some(guard) = SymbolId::get(symbol)
+            .and_then(|symbol| self.positions.get_mut(&(venue.clone(), symbol, side)))
+        {
+            guard.exiting = false;
+        }
+    }
+
+    pub fn levels(
+        &self,
+        limit_secs: impl Fn(&str, Option<&str>) -> Option<u64>,
+    ) -> Vec<ProtectiveLevels> {
+        let mut levels: Vec<ProtectiveLevels> = self
+            .positions
+            .iter()
+            .map(|((venue, symbol, side), guard)| ProtectiveLevels {
+                venue: venue.clone(),
+                symbol: symbol.to_string(),
+                side: *side,
+                quantity: guard.quantity,
+                stop_loss: guard.rules.stop_loss,
+                take_profit: guard.rules.take_profit,
+                trailing_stop: guard.trailing_stop(),
+                best_price: guard.best_price,
+                opened_at: guard.opened_at,
+                time_stop_at: limit_secs(symbol.as_str(), guard.strategy.as_deref())
+                    .map(|limit| guard.opened_at + limit),
+                strategy: guard.strategy.clone(),
+            })
+            .collect();
+        levels.sort_by(|a, b| (&a.symbol, &a.venue, a.side).cmp(&(&b.symbol, &b.venue, b.side)));
+        levels
+    }
+}
+
+impl Guard {
+    fn opened_by(fill: &Fill, quantity: f64, rules: ExitRules) -> Self {
+        Self {
+            quantity,
+            opened_at: fill.timestamp,
+            strategy: fill.strategy.clone(),
+            rules,
+            best_price: fill.price,
+            triggered: None,
+            exiting: false,
+        }
+    }
+
+    fn is_long(&self) -> bool {
+        self.quantity > 0.0
+    }
+
+    fn track(&mut self, price: f64) {
+        if self.is_long() {
+            self.best_price = self.best_price.max(price);
+        } else {
+            self.best_price = self.best_price.min(price);
+        }
+    }
+
+    fn trailing_stop(&self) -> Option<f64> {
+        let pct = self.rules.trailing_stop_pct?;
+        Some(if self.is_long() {
+            self.best_price * (1.0 - pct)
+        } else {
+            self.best_price * (1.0 + pct)
+        })
+    }
+
+    // The highest-priority rule the price triggers, leaving levels that
+    // rest as orders to the executor
+    fn crossed(&self, price: f64) -> Option<ExitReason> {
+        let long = self.is_long();
+        // At or beyond the level on the losing side, or the winning side
+        let behind = |level: f64| if long { price <= level } else { price >= level };
+        let ahead = |level: f64| if long { price >= level } else { price <= level };
+        let monitored = |level: Option<Level>| level.filter(|level| !level.resting);
+        if monitored(self.rules.stop_loss).is_some_and(|stop| behind(stop.price)) {
+            Some(ExitReason::StopLoss)
+        } else if self.trailing_stop().is_some_and(behind) {
+            Some(ExitReason::TrailingStop)
+        } else if monitored(self.rules.take_profit).is_some_and(|tp| ahead(tp.price)) {
+            Some(ExitReason::TakeProfit)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,667 @@
+use crate::OrderSide;
+use crate::events::BotEvent;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Instant;
+
+// Override the config file so secrets can stay out of it
+pub const TELEGRAM_TOKEN_ENV: &str = "TELEGRAM_BOT_TOKEN";
+pub const WEBHOOK_URL_ENV: &str = "NOTIFY_WEBHOOK_URL";
+
+// Lines quoted in a coalesced message before the rest are only counted
+const MAX_LINES: usize = 5;
+
+// Which events produce a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyEvents {
+    pub fills: bool,
+    pub risk_rejections: bool,
+    pub daily_loss_limit: bool,
+    pub drawdown: bool,
+    pub kill_switch: bool,
+    pub strategy_panics: bool,
+    pub task_failures: bool,
+    pub feed_down: bool,
+    pub reconciliation: bool,
+    // A strategy benched after a losing or rejection streak, and back
+    pub strategy_benched: bool,
+    // The trading day's summary at rollover
+    pub session_summary: bool,
+}
+
+impl Default for NotifyEvents {
+    fn default() -> Self {
+        Self {
+            fills: false,
+            risk_rejections: false,
+            daily_loss_limit: true,
+            drawdown: true,
+            kill_switch: true,
+            strategy_panics: true,
+            task_failures: true,
+            feed_down: true,
+            reconciliation: true,
+            strategy_benched: true,
+            session_summary: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelegramConfig {
+    // Falls back to TELEGRAM_BOT_TOKEN
+    pub bot_token: Option<String>,
+    pub chat_id: String,
+    pub api_url: String,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            bot_token: None,
+            chat_id: String::new(),
+            api_url: "https://api.telegram.org".to_string(),
+        }
+    }
+}
+
+// Body shape a webhook expects the message in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    // {"content": "..."}
+    #[default]
+    Discord,
+    // {"text": "..."}
+    Slack,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    // Falls back to NOTIFY_WEBHOOK_URL
+    pub url: Option<String>,
+    pub format: WebhookFormat,
+}
+
+// Messages about fills and risk events, sent to chat apps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub enabled: bool,
+    pub telegram: Option<TelegramConfig>,
+    pub webhook: Option<WebhookConfig>,
+    pub events: NotifyEvents,
+    // Events of one kind arriving within this long of the first are sent
+    // together as one message
+    pub coalesce_ms: u64,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            telegram: None,
+            webhook: None,
+            events: NotifyEvents::default(),
+            coalesce_ms: 5_000,
+        }
+    }
+}
+
+impl NotifyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.telegram.is_none() && self.webhook.is_none() {
+            return Err("Notifications need a [notify.telegram] or [notify.webhook]".to_string());
+        }
+        if self
+            .telegram
+            .as_ref()
+            .is_some_and(|telegram| telegram.chat_id.is_empty())
+        {
+            return Err("Telegram notifications need a chat_id".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Somewhere a message can be delivered
+pub trait NotifyBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn send<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<(), String>>;
+}
+
+pub struct TelegramBackend {
+    client: reqwest::Client,
+    url: String,
+    chat_id: String,
+}
+
+impl TelegramBackend {
+    pub fn new(client: reqwest::Client, config: &TelegramConfig) -> Result<Self, String> {
+        let token = std::env::var(TELEGRAM_TOKEN_ENV)
+            .ok()
+            .or_else(|| config.bot_token.clone())
+            .ok_or_else(|| {
+                format!(
+                    "Telegram notifications need a bot_token or {}",
+                    TELEGRAM_TOKEN_ENV
+                )
+            })?;
+        Ok(Self {
+            client,
+            url: format!(
+                "{}/bot{}/sendMessage",
+                config.api_url.trim_end_matches('/'),
+                token
+            ),
+            chat_id: config.chat_id.clone(),
+        })
+    }
+}
+
+impl NotifyBackend for TelegramBackend {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn send<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<(), String>> {
+        let body = serde_json::json!({ "chat_id": self.chat_id, "text": text });
+        Box::pin(post(&self.client, &self.url, body))
+    }
+}
+
+// Discord, Slack and anything else taking a JSON POST
+pub struct WebhookBackend {
+    client: reqwest::Client,
+    url: String,
+    format: WebhookFormat,
+}
+
+impl WebhookBackend {
+    pub fn new(client: reqwest::Client, config: &WebhookConfig) -> Result<Self, String> {
+        let url = std::env::var(WEBHOOK_URL_ENV)
+            .ok()
+            .or_else(|| config.url.clone())
+            .ok_or_else(|| format!("Webhook notifications need a url or {}", WEBHOOK_URL_ENV))?;
+        Ok(Self {
+            client,
+            url,
+            format: config.format,
+        })
+    }
+}
+
+impl NotifyBackend for WebhookBackend {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn send<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<(), String>> {
+        let body = match self.format {
+            WebhookFormat::Discord => serde_json::json!({ "content": text }),
+            WebhookFormat::Slack => serde_json::json!({ "text": text }),
+        };
+        Box::pin(post(&self.client, &self.url, body))
+    }
+}
+
+async fn post(client: &reqwest::Client, url: &str, body: serde_json::Value) -> Result<(), String> {
+    let response = client
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    Ok(())
+}
+
+// What a message is about; events of one kind are coalesced together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    KillSwitch,
+    Reconciliation,
+    TaskFailure,
+    FeedDown,
+    StrategyPanic,
+    StrategyBench,
+    Drawdown,
+    DailyLossLimit,
+    Session,
+    RiskRejection,
+    Fill,
+}
+
+impl Kind {
+    fn plural(&self) -> &'static str {
+        match self {
+            Kind::KillSwitch => "kill switch activations",
+            Kind::Reconciliation => "reconciliation mismatches",
+            Kind::TaskFailure => "task failures",
+            Kind::FeedDown => "feed outages",
+            Kind::StrategyPanic => "strategy panics",
+            Kind::StrategyBench => "strategy benchings",
+            Kind::Drawdown => "drawdown breaches",
+            Kind::DailyLossLimit => "daily loss limit alerts",
+            Kind::Session => "session summaries",
+            Kind::RiskRejection => "risk rejections",
+            Kind::Fill => "fills",
+        }
+    }
+}
+
+fn side(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+/// Turns bot events into chat messages. Events of each kind are held for
+/// `coalesce_ms` after the first arrives and then sent as one message, so
+/// a burst of fifty fills is a single summary. Delivery failures are
+/// logged and dropped; the notifier only ever reads the event channel, so
+/// it can never hold up trading.
+pub struct Notifier {
+    backends: Vec<Box<dyn NotifyBackend>>,
+    events: NotifyEvents,
+    coalesce: Duration,
+}
+
+impl Notifier {
+    pub fn new(config: &NotifyConfig) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| format!("Failed to build notification client: {}", e))?;
+        let mut backends: Vec<Box<dyn NotifyBackend>> = Vec::new();
+        if let Some(telegram) = &config.telegram {
+            backends.push(Box::new(TelegramBackend::new(client.clone(), telegram)?));
+        }
+        if let Some(webhook) = &config.webhook {
+            backends.push(Box::new(WebhookBackend::new(client, webhook)?));
+        }
+        Ok(Self::with_backends(backends, config))
+    }
+
+    pub fn with_backends(backends: Vec<Box<dyn NotifyBackend>>, config: &NotifyConfig) -> Self {
+        Self {
+            backends,
+            events: config.events.clone(),
+            coalesce: Duration::from_millis(config.coalesce_ms),
+        }
+    }
+
+    // Runs until the event channel closes
+    pub fn spawn(self, events: broadcast::Receiver<BotEvent>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.run(events))
+    }
+
+    async fn run(self, mut events: broadcast::Receiver<BotEvent>) {
+        let mut pending: BTreeMap<Kind, Vec<String>> = BTreeMap::new();
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let event = match deadline {
+                Some(at) => tokio::select! {
+                    event = events.recv() => Some(event),
+                    _ = tokio::time::sleep_until(at) => None,
+                },
+                None => Some(events.recv().await),
+            };
+            match event {
+                Some(Ok(event)) => {
+                    if let Some((kind, line)) = self.describe(&event) {
+                        pending.entry(kind).or_default().push(line);
+                        deadline.get_or_insert_with(|| Instant::now() + self.coalesce);
+                    }
+                }
+                Some(Err(RecvError::Lagged(count))) => {
+                    println!("Notifier fell behind; {} events skipped", count);
+                }
+                Some(Err(RecvError::Closed)) => {
+                    self.flush(&mut pending).await;
+                    return;
+                }
+                None => {
+                    self.flush(&mut pending).await;
+                    deadline = None;
+                }
+            }
+        }
+    }
+
+    // The event's kind and one-line description, if it is to be sent
+    fn describe(&self, event: &BotEvent) -> Option<(Kind, String)> {
+        match event {
+            BotEvent::Fill(fill) if self.events.fills => Some((
+                Kind::Fill,
+                format!(
+                    "{} {} {} @ {:.4} ({})",
+                    fill.symbol,
+                    side(fill.side),
+                    fill.quantity,
+                    fill.price,
+                    fill.strategy.as_deref().unwrap_or("manual")
+                ),
+            )),
+            BotEvent::RiskRejection {
+                symbol,
+                side: order_side,
+                quantity,
+                reason,
+                ..
+            } if self.events.risk_rejections => Some((
+                Kind::RiskRejection,
+                format!(
+                    "{} {} {} rejected: {}",
+                    symbol,
+                    side(*order_side),
+                    quantity,
+                    reason
+                ),
+            )),
+            BotEvent::DailyLossLimit { daily_pnl, limit } if self.events.daily_loss_limit => {
+                Some((
+                    Kind::DailyLossLimit,
+                    format!(
+                        "⚠️ Daily loss limit hit: PnL {:.2} (limit {:.2}); new orders are blocked",
+                        daily_pnl, limit
+                    ),
+                ))
+            }
+            BotEvent::SessionSummary(summary) if self.events.session_summary => Some((
+                Kind::Session,
+                format!(
+                    "📅 Session {} closed: PnL {:.2}, fees {:.2}, {} fill(s), {} trade(s), \
+                     peak {:.2}, max drawdown {:.2}%",
+                    summary.stats.session,
+                    summary.stats.realized_pnl,
+                    summary.stats.fees,
+                    summary.stats.fills,
+                    summary.stats.trades,
+                    summary.stats.peak_equity,
+                    summary.stats.max_drawdown_pct * 100.0
+                ),
+            )),
+            BotEvent::DrawdownBreached {
+                equity,
+                peak,
+                drawdown_pct,
+                ..
+            } if self.events.drawdown => Some((
+                Kind::Drawdown,
+                format!(
+                    "🚨 Drawdown {:.2}% from peak {:.2} (equity {:.2})",
+                    drawdown_pct * 100.0,
+                    peak,
+                    equity
+                ),
+            )),
+            BotEvent::KillSwitch(report) if self.events.kill_switch => Some((
+                Kind::KillSwitch,
+                format!(
+                    "🛑 Kill switch fired: {} order(s) cancelled, flattened {:?}, failed {:?}",
+                    report.cancelled_orders, report.flattened_positions, report.failed_positions
+                ),
+            )),
+            BotEvent::StrategyPanicked {
+                strategy,
+                symbol,
+                message,
+                consecutive,
+                disabled,
+            } if self.events.strategy_panics => Some((
+                Kind::StrategyPanic,
+                format!(
+                    "🔥 {} panicked on {} ({} in a row): {}{}",
+                    strategy,
+                    symbol,
+                    consecutive,
+                    message,
+                    if *disabled { "; disabled" } else { "" }
+                ),
+            )),
+            BotEvent::StrategyBenched {
+                strategy,
+                kind,
+                count,
+                mode,
+                until,
+            } if self.events.strategy_benched => Some((
+                Kind::StrategyBench,
+                format!(
+                    "⏸️ {} had {} in a row; {} until {}",
+                    strategy,
+                    kind.describe(*count),
+                    mode,
+                    until
+                ),
+            )),
+            BotEvent::StrategyReinstated { strategy, mode } if self.events.strategy_benched => {
+                Some((
+                    Kind::StrategyBench,
+                    format!("▶️ {} is back {} after its cooldown", strategy, mode),
+                ))
+            }
+            BotEvent::TaskFailed {
+                task,
+                message,
+                restarting,
+                ..
+            } if self.events.task_failures => Some((
+                Kind::TaskFailure,
+                format!(
+                    "🔥 Task {} panicked: {}; {}",
+                    task,
+                    message,
+                    if *restarting {
+                        "restarting it"
+                    } else {
+                        "bot stopped"
+                    }
+                ),
+            )),
+            BotEvent::FeedDown { feed, failures } if self.events.feed_down => Some((
+                Kind::FeedDown,
+                format!(
+                    "📡 Feed {} failed to connect {} times in a row; still retrying",
+                    feed, failures
+                ),
+            )),
+            BotEvent::ReconciliationMismatch {
+                venue,
+                policy,
+                discrepancies,
+                halted,
+            } if self.events.reconciliation => Some((
+                Kind::Reconciliation,
+                format!(
+                    "⚖️ {} disagrees with the bot on {} point(s) ({}): {}{}",
+                    venue,
+                    discrepancies.len(),
+                    policy.as_str(),
+                    discrepancies.join("; "),
+                    if *halted { "; trading halted" } else { "" }
+                ),
+            )),
+            _ => None,
+        }
+    }
+
+    async fn flush(&self, pending: &mut BTreeMap<Kind, Vec<String>>) {
+        for (kind, lines) in std::mem::take(pending) {
+            let text = coalesce(kind, &lines);
+            for backend in &self.backends {
+                if let Err(e) = backend.send(&text).await {
+                    println!("Failed to send {} notification: {}", backend.name(), e);
+                }
+            }
+        }
+    }
+}
+
+// One message for all of a kind's lines
+fn coalesce(kind: Kind, lines: &[String]) -> String {
+    if let [line] = lines {
+        return line.clone();
+    }
+    let mut text = format!("{} {}:", lines.len(), kind.plural());
+    for line in lines.iter().take(MAX_LINES) {
+        text.push_str("\n• ");
+        text.push_str(line);
+    }
+    if lines.len() > MAX_LINES {
+        text.push_str(&format!("\n…and {} more", lines.len() - MAX_LINES));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KillSwitchReport;
+    use crate::fixtures::fill;
+    use crate::tests::serve;
+    use axum::Router;
+    use axum::extract::{Path, State};
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use serde_json::{Value, json};
+    use std::sync::{Arc, Mutex};
+
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    // Records every POST; anything under /down fails
+    async fn receive(
+        State(received): State<Received>,
+        Path(path): Path<String>,
+        axum::Json(body): axum::Json<Value>,
+    ) -> StatusCode {
+        received.lock().unwrap().push((path.clone(), body));
+        if path.starts_with("down") {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    async fn mock_server() -> (String, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route("/{*path}", post(receive))
+            .with_state(received.clone());
+        (serve(app).await, received)
+    }
+
+    fn config(base: &str, webhook: &str) -> NotifyConfig {
+        NotifyConfig {
+            enabled: true,
+            telegram: Some(TelegramConfig {
+                bot_token: Some("token".to_string()),
+                chat_id: "42".to_string(),
+                api_url: base.to_string(),
+            }),
+            webhook: Some(WebhookConfig {
+                url: Some(format!("{base}/{webhook}")),
+                format: WebhookFormat::Slack,
+            }),
+            events: NotifyEvents {
+                fills: true,
+                ..NotifyEvents::default()
+            },
+            coalesce_ms: 100,
+        }
+    }
+
+    fn fill_event(price: f64) -> BotEvent {
+        let mut fill = fill("SOL/USDT", OrderSide::Buy, 1.0, price);
+        fill.strategy = Some("momentum".to_string());
+        BotEvent::Fill(fill)
+    }
+
+    #[tokio::test]
+    async fn a_burst_is_one_message_per_kind_in_each_format() {
+        let (base, received) = mock_server().await;
+        let (events, receiver) = broadcast::channel(64);
+        let notifier = Notifier::new(&config(&base, "hook"))
+            .unwrap()
+            .spawn(receiver);
+
+        for i in 0..7 {
+            events.send(fill_event(100.0 + i as f64)).unwrap();
+        }
+        events
+            .send(BotEvent::KillSwitch(KillSwitchReport {
+                cancelled_orders: 2,
+                flattened_positions: vec!["SOL/USDT".to_string()],
+                failed_positions: Vec::new(),
+            }))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        // After the window: a message of its own
+        events.send(fill_event(120.0)).unwrap();
+        drop(events);
+        notifier.await.unwrap();
+
+        let kill_switch = "🛑 Kill switch fired: 2 order(s) cancelled, flattened [\"SOL/USDT\"], \
+                           failed []";
+        let burst = "7 fills:\
+                     \n• SOL/USDT buy 1 @ 100.0000 (momentum)\
+                     \n• SOL/USDT buy 1 @ 101.0000 (momentum)\
+                     \n• SOL/USDT buy 1 @ 102.0000 (momentum)\
+                     \n• SOL/USDT buy 1 @ 103.0000 (momentum)\
+                     \n• SOL/USDT buy 1 @ 104.0000 (momentum)\
+                     \n…and 2 more";
+        let single = "SOL/USDT buy 1 @ 120.0000 (momentum)";
+        let mut expected = Vec::new();
+        for text in [kill_switch, burst, single] {
+            expected.push((
+                "bottoken/sendMessage".to_string(),
+                json!({ "chat_id": "42", "text": text }),
+            ));
+            expected.push(("hook".to_string(), json!({ "text": text })));
+        }
+        assert_eq!(*received.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn a_failing_backend_is_skipped() {
+        let (base, received) = mock_server().await;
+        let (events, receiver) = broadcast::channel(64);
+        let notifier = Notifier::new(&config(&base, "down"))
+            .unwrap()
+            .spawn(receiver);
+
+        events.send(fill_event(100.0)).unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        events.send(fill_event(101.0)).unwrap();
+        drop(events);
+        notifier.await.unwrap();
+
+        let paths: Vec<String> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(p, _)| p.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "bottoken/sendMessage",
+                "down",
+                "bottoken/sendMessage",
+                "down"
+            ]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::indicators::{OfiSpec, SymbolIndicators};
+use crate::{OrderBook, OrderSide, Price, TimeInForce, TradingSignal, TradingStrategy};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Trades extremes of order-flow imbalance: buys when the rolling OFI's
+/// z-score reaches `entry_zscore`, sells when it reaches -`entry_zscore`.
+/// Each symbol signals once per extreme, and again only after its z-score
+/// has come back inside the threshold.
+pub struct OfiStrategy {
+    spec: OfiSpec,
+    entry_zscore: f64,
+    base_quantity: f64,
+    // The extreme each symbol last signalled on, until it's left
+    extremes: Mutex<HashMap<Arc<str>, OrderSide>>,
+}
+
+impl OfiStrategy {
+    // Panics unless levels > 0, window >= 2 and entry_zscore > 0
+    pub fn new(levels: usize, window: usize, entry_zscore: f64, base_quantity: f64) -> Self {
+        assert!(
+            levels > 0 && window >= 2 && entry_zscore > 0.0,
+            "OfiStrategy needs levels > 0, window >= 2 and a positive entry_zscore, got {}/{}/{}",
+            levels,
+            window,
+            entry_zscore
+        );
+        Self {
+            spec: OfiSpec { levels, window },
+            entry_zscore,
+            base_quantity,
+            extremes: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl TradingStrategy for OfiStrategy {
+    // Signals only come from the order-flow indicator
+    fn analyze(&self, _prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
+        None
+    }
+
+    fn name(&self) -> &str {
+        "OfiStrategy"
+    }
+
+    fn ofi_specs(&self) -> Vec<OfiSpec> {
+        vec![self.spec]
+    }
+
+    fn analyze_incremental(
+        &self,
+        _prices: &[Price],
+        indicators: &SymbolIndicators,
+        orderbook: &OrderBook,
+    ) -> Option<TradingSignal> {
+        let symbol = indicators.symbol();
+        let zscore = indicators.ofi(self.spec)?.zscore()?;
+        let target_price = orderbook.mid_price()?;
+        let side = if zscore >= self.entry_zscore {
+            Some(OrderSide::Buy)
+        } else if zscore <= -self.entry_zscore {
+            Some(OrderSide::Sell)
+        } else {
+            None
+        };
+
+        let mut extremes = self.extremes.lock().unwrap();
+        let Some(action) = side else {
+            extremes.remove(symbol);
+            return None;
+        };
+        if extremes.insert(symbol.clone(), action) == Some(action) {
+            return None;
+        }
+        Some(TradingSignal {
+            symbol: symbol.clone(),
+            action,
+            // Full confidence at twice the threshold
+            confidence: (zscore.abs() / (2.0 * self.entry_zscore)).min(1.0),
+            target_price,
+            quantity: self.base_quantity,
+            time_in_force: TimeInForce::Gtc,
+            limit_price: None,
+        })
+    }
+}
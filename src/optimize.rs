@@ -0,0 +1,359 @@
+use crate::backtest::{Backtester, MarketRecord};
+use crate::config::BotConfig;
+use crate::performance::PerformanceReport;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+// The statistic combinations are ranked by; higher is better
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptimizeMetric {
+    Sharpe,
+    NetPnl,
+}
+
+impl OptimizeMetric {
+    // None when the report can't produce the metric (Sharpe with no variance)
+    pub fn score(&self, report: &PerformanceReport) -> Option<f64> {
+        match self {
+            OptimizeMetric::Sharpe => report.sharpe,
+            OptimizeMetric::NetPnl => Some(report.net_pnl),
+        }
+    }
+}
+
+// Optimize on `train_rows` rows, evaluate the winner on the `test_rows`
+// after them, then roll both windows forward by `test_rows`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardConfig {
+    pub train_rows: usize,
+    pub test_rows: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptimizeConfig {
+    pub metric: OptimizeMetric,
+    // Backtests run at once
+    pub workers: usize,
+    // Values to try per config path ("strategies.0.lookback_period"); every
+    // combination is run
+    pub grid: BTreeMap<String, Vec<toml::Value>>,
+    pub walk_forward: Option<WalkForwardConfig>,
+}
+
+impl Default for OptimizeConfig {
+    fn default() -> Self {
+        Self {
+            metric: OptimizeMetric::Sharpe,
+            workers: 4,
+            grid: BTreeMap::new(),
+            walk_forward: None,
+        }
+    }
+}
+
+impl OptimizeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.workers == 0 {
+            return Err("Optimize workers must be at least 1".to_string());
+        }
+        if let Some((path, _)) = self.grid.iter().find(|(_, values)| values.is_empty()) {
+            return Err(format!("Optimize grid entry {} has no values", path));
+        }
+        if let Some(walk_forward) = &self.walk_forward
+            && (walk_forward.train_rows == 0 || walk_forward.test_rows == 0)
+        {
+            return Err("Walk-forward train_rows and test_rows must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    // Every combination of grid values, in a fixed order
+    pub fn combinations(&self) -> Vec<Params> {
+        self.grid
+            .iter()
+            .fold(vec![Params::new()], |combinations, (path, values)| {
+                combinations
+                    .iter()
+                    .flat_map(|params| {
+                        values.iter().map(move |value| {
+                            let mut params = params.clone();
+                            params.insert(path.clone(), value.clone());
+                            params
+                        })
+                    })
+                    .collect()
+            })
+    }
+}
+
+// One combination: config path -> value
+pub type Params = BTreeMap<String, toml::Value>;
+
+/// Returns `base` with each path set to its value. Paths are dotted keys
+/// into the config's TOML form, with array indices as numbers
+/// ("strategies.1.deviation_threshold", "risk.stop_loss_pct").
+pub fn apply_params(base: &BotConfig, params: &Params) -> Result<BotConfig, String> {
+    let mut root = toml::Value::try_from(base).map_err(|e| format!("Invalid config: {}", e))?;
+    for (path, value) in params {
+        let mut node = &mut root;
+        for key in path.split('.') {
+            node = match node {
+                toml::Value::Table(table) => table.get_mut(key),
+                toml::Value::Array(array) => {
+                    key.parse::<usize>().ok().and_then(|i| array.get_mut(i))
+                }
+                _ => None,
+            }
+            .ok_or_else(|| format!("Optimize path {} not found in config", path))?;
+        }
+        *node = value.clone();
+    }
+    let config: BotConfig = root
+        .try_into()
+        .map_err(|e| format!("Invalid parameters {:?}: {}", params, e))?;
+    config.validate()?;
+    Ok(config)
+}
+
+// One combination's backtest
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizationRow {
+    pub rank: usize,
+    pub params: Params,
+    // The configured metric's value; None ranks last
+    pub score: Option<f64>,
+    pub report: PerformanceReport,
+}
+
+// One walk-forward step: the combination that won in sample and how it did
+// on the next, unseen window
+#[derive(Debug, Clone, Serialize)]
+pub struct WalkForwardWindow {
+    // Row timestamps, inclusive
+    pub train_start: u64,
+    pub train_end: u64,
+    pub test_start: u64,
+    pub test_end: u64,
+    pub params: Params,
+    pub in_sample_score: Option<f64>,
+    pub out_of_sample_score: Option<f64>,
+    pub out_of_sample: PerformanceReport,
+}
+
+/// Runs the backtester over a parameter grid. Combinations run in parallel
+/// on up to `workers` tasks, each with its own bot, so results depend only
+/// on the data and the parameters.
+pub struct Optimizer {
+    base: BotConfig,
+    settings: OptimizeConfig,
+}
+
+impl Optimizer {
+    pub fn new(config: &BotConfig) -> Self {
+        Self {
+            base: config.clone(),
+            settings: config.optimize.clone(),
+        }
+    }
+
+    /// Backtests every combination on `records` and ranks them by the
+    /// metric, best first. Ties keep grid order.
+    pub async fn sweep(&self, records: &[MarketRecord]) -> Result<Vec<OptimizationRow>, String> {
+        let records: Arc<[MarketRecord]> = records.into();
+        let mut rows = self.run_grid(&records, 0..records.len()).await?;
+        rows.sort_by(|a, b| rank_order(a.score, b.score));
+        for (rank, row) in rows.iter_mut().enumerate() {
+            row.rank = rank + 1;
+        }
+        Ok(rows)
+    }
+
+    /// Sweeps each training window and backtests its winner on the test
+    /// window that follows. Each window starts from a fresh, flat bot, so
+    /// strategies warm up again at the start of every test window.
+    pub async fn walk_forward(
+        &self,
+        records: &[MarketRecord],
+    ) -> Result<Vec<WalkForwardWindow>, String> {
+        let Some(WalkForwardConfig {
+            train_rows,
+            test_rows,
+        }) = self.settings.walk_forward.clone()
+        else {
+            return Err("No [optimize.walk_forward] windows configured".to_string());
+        };
+        let records: Arc<[MarketRecord]> = records.into();
+
+        let mut windows = Vec::new();
+        let mut train_start = 0;
+        while train_start + train_rows + test_rows <= records.len() {
+            let train = train_start..train_start + train_rows;
+            let test = train.end..train.end + test_rows;
+
+            let rows = self.run_grid(&records, train.clone()).await?;
+            let best = rows
+                .into_iter()
+                .reduce(|best, row| {
+                    if rank_order(row.score, best.score).is_lt() {
+                        row
+                    } else {
+                        best
+                    }
+                })
+                .expect("the grid has at least one combination");
+
+            let config = apply_params(&self.base, &best.params)?;
+            let out_of_sample = Backtester::new(&config)
+                .run(&records[test.clone()])
+                .await
+                .performance
+                .overall;
+
+            windows.push(WalkForwardWindow {
+                train_start: records[train.start].price.timestamp,
+                train_end: records[train.end - 1].price.timestamp,
+                test_start: records[test.start].price.timestamp,
+                test_end: records[test.end - 1].price.timestamp,
+                in_sample_score: best.score,
+                out_of_sample_score: self.settings.metric.score(&out_of_sample),
+                params: best.params,
+                out_of_sample,
+            });
+            train_start += test_rows;
+        }
+
+        if windows.is_empty() {
+            return Err(format!(
+                "Walk-forward needs at least {} rows, data has {}",
+                train_rows + test_rows,
+                records.len()
+            ));
+        }
+        Ok(windows)
+    }
+
+    // Unranked results in grid order
+    async fn run_grid(
+        &self,
+        records: &Arc<[MarketRecord]>,
+        range: std::ops::Range<usize>,
+    ) -> Result<Vec<OptimizationRow>, String> {
+        let workers = Arc::new(Semaphore::new(self.settings.workers));
+        let mut tasks = Vec::new();
+        for params in self.settings.combinations() {
+            let config = apply_params(&self.base, &params)?;
+            let records = records.clone();
+            let range = range.clone();
+            let workers = workers.clone();
+            let metric = self.settings.metric;
+            tasks.push(tokio::spawn(async move {
+                let _permit = workers.acquire_owned().await;
+                let report = Backtester::new(&config)
+                    .run(&records[range])
+                    .await
+                    .performance
+                    .overall;
+                OptimizationRow {
+                    rank: 0,
+                    score: metric.score(&report),
+                    params,
+                    report,
+                }
+            }));
+        }
+
+        let mut rows = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            rows.push(
+                task.await
+                    .map_err(|e| format!("Backtest task failed: {}", e))?,
+            );
+        }
+        Ok(rows)
+    }
+}
+
+// Best first; combinations without a score go last
+fn rank_order(a: Option<f64>, b: Option<f64>) -> std::cmp::Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
+
+// The ranked table, one row per combination with a column per grid path
+pub fn sweep_csv(rows: &[OptimizationRow]) -> String {
+    let paths: Vec<&String> = rows
+        .first()
+        .map_or(Vec::new(), |r| r.params.keys().collect());
+    let mut csv = String::from("rank");
+    for path in &paths {
+        csv.push(',');
+        csv.push_str(path);
+    }
+    csv.push_str(",score,net_pnl,total_return,sharpe,max_drawdown,trades,hit_rate,fees\n");
+    for row in rows {
+        csv.push_str(&row.rank.to_string());
+        for value in row.params.values() {
+            csv.push(',');
+            csv.push_str(&value.to_string());
+        }
+        let report = &row.report;
+        csv.push_str(&format!(
+            ",{},{},{},{},{},{},{},{}\n",
+            optional(row.score),
+            report.net_pnl,
+            report.total_return,
+            optional(report.sharpe),
+            report.max_drawdown,
+            report.trades,
+            optional(report.hit_rate),
+            report.fees
+        ));
+    }
+    csv
+}
+
+// One row per walk-forward window
+pub fn walk_forward_csv(windows: &[WalkForwardWindow]) -> String {
+    let paths: Vec<&String> = windows
+        .first()
+        .map_or(Vec::new(), |w| w.params.keys().collect());
+    let mut csv = String::from("train_start,train_end,test_start,test_end");
+    for path in &paths {
+        csv.push(',');
+        csv.push_str(path);
+    }
+    csv.push_str(",in_sample_score,out_of_sample_score,net_pnl,max_drawdown,trades\n");
+    for window in windows {
+        csv.push_str(&format!(
+            "{},{},{},{}",
+            window.train_start, window.train_end, window.test_start, window.test_end
+        ));
+        for value in window.params.values() {
+            csv.push(',');
+            csv.push_str(&value.to_string());
+        }
+        let report = &window.out_of_sample;
+        csv.push_str(&format!(
+            ",{},{},{},{},{}\n",
+            optional(window.in_sample_score),
+            optional(window.out_of_sample_score),
+            report.net_pnl,
+            report.max_drawdown,
+            report.trades
+        ));
+    }
+    csv
+}
+
+// Blank for a missing value
+fn optional(value: Option<f64>) -> String {
+    value.map_or(String::new(), |v| v.to_string())
+}
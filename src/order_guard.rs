@@ -0,0 +1,99 @@
+use crate::Order;
+use serde::{Deserialize, Serialize};
+
+// What to do when a strategy signals again while its last order still works
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    // Leave the working order be and drop the new one
+    #[default]
+    Skip,
+    // Move the working order to the new order's price and quantity
+    Replace,
+    // Send it alongside, up to max_stacked working orders
+    Stack,
+}
+
+// Guarding against stacked working orders for the same intent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrderGuardConfig {
+    pub enabled: bool,
+    pub policy: DuplicatePolicy,
+    // Limit prices this fraction apart count as the same intent
+    pub price_tolerance_pct: f64,
+    // Working orders a strategy may stack on one symbol and side
+    pub max_stacked: usize,
+}
+
+impl Default for OrderGuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            policy: DuplicatePolicy::Skip,
+            price_tolerance_pct: 0.001,
+            max_stacked: 3,
+        }
+    }
+}
+
+impl OrderGuardConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..1.0).contains(&self.price_tolerance_pct) {
+            return Err("Order guard price_tolerance_pct must be in [0, 1)".to_string());
+        }
+        if self.max_stacked == 0 {
+            return Err("Order guard max_stacked must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    // A working order stands for the same intent as the new one: both
+    // limits within the tolerance, or both market orders
+    fn same_intent(&self, working: &Order, order: &Order) -> bool {
+        match (working.price, order.price) {
+            (Some(working), Some(price)) => {
+                (working - price).abs() <= self.price_tolerance_pct * price + f64::EPSILON
+            }
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// What to do with a new order, given the working orders its strategy
+    /// already has on the same symbol and side (oldest first). Those
+    /// outside the price tolerance don't count.
+    pub fn decide(&self, working: &[Order], order: &Order) -> GuardDecision {
+        if !self.enabled {
+            return GuardDecision::Submit;
+        }
+        let mut same: Vec<&Order> = working
+            .iter()
+            .filter(|working| self.same_intent(working, order))
+            .collect();
+        let Some(newest) = same.pop() else {
+            return GuardDecision::Submit;
+        };
+        match self.policy {
+            DuplicatePolicy::Skip => GuardDecision::Skip(newest.clone()),
+            DuplicatePolicy::Replace
+                if newest.price == order.price && newest.quantity == order.quantity =>
+            {
+                GuardDecision::Skip(newest.clone())
+            }
+            DuplicatePolicy::Replace => GuardDecision::Replace(newest.clone()),
+            DuplicatePolicy::Stack if same.len() + 1 < self.max_stacked => GuardDecision::Submit,
+            DuplicatePolicy::Stack => GuardDecision::Skip(newest.clone()),
+        }
+    }
+}
+
+// The guard's call on a new order
+#[derive(Debug, Clone)]
+pub enum GuardDecision {
+    Submit,
+    // Dropped in favour of this working order
+    Skip(Order),
+    // Sent as a replacement for this working order
+    Replace(Order),
+}
@@ -0,0 +1,324 @@
+use crate::{Order, OrderSide};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderStatus {
+    Working,
+    PartiallyFilled,
+    // A replace has been requested but not yet acknowledged
+    PendingReplace,
+    // A stop order waiting for its trigger price
+    Untriggered,
+}
+
+// Snapshot of a working order and how much of it has filled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderState {
+    pub order: Order,
+    pub filled_quantity: f64,
+    pub status: OrderStatus,
+    // Paper limits with [paper] queue_position: displayed quantity
+    // estimated ahead of the order at its price, as of the last book
+    #[serde(default)]
+    pub queue_ahead: Option<f64>,
+}
+
+impl OrderState {
+    pub fn new(order: Order) -> Self {
+        Self {
+            order,
+            filled_quantity: 0.0,
+            status: OrderStatus::Working,
+            queue_ahead: None,
+        }
+    }
+
+    pub fn remaining_quantity(&self) -> f64 {
+        self.order.quantity - self.filled_quantity
+    }
+
+    // Status implied by the filled quantity alone
+    fn settled_status(&self) -> OrderStatus {
+        if self.filled_quantity > 0.0 {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Working
+        }
+    }
+}
+
+/// The orders working: how many in total and per symbol, and which each
+/// strategy has where. OpenOrders keeps it in step with itself; the risk
+/// manager and the signal stage hold handles so they can check it without
+/// locking the executor's book.
+#[derive(Debug, Default)]
+pub struct OpenOrderTracker {
+    // By order id
+    orders: std::sync::Mutex<HashMap<String, Order>>,
+}
+
+impl OpenOrderTracker {
+    fn add(&self, order: &Order) {
+        self.orders
+            .lock()
+            .unwrap()
+            .insert(order.id.clone(), order.clone());
+    }
+
+    fn remove(&self, order_id: &str) {
+        self.orders.lock().unwrap().remove(order_id);
+    }
+
+    pub fn count(&self) -> usize {
+        self.orders.lock().unwrap().len()
+    }
+
+    pub fn count_for(&self, symbol: &str) -> usize {
+        self.orders
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|order| order.symbol == symbol)
+            .count()
+    }
+
+    // Working orders in total and on the symbol, leaving out `excluding`
+    // (an order about to be replaced), counted under the one lock
+    pub fn counts_excluding(&self, symbol: &str, excluding: Option<&str>) -> (usize, usize) {
+        let orders = self.orders.lock().unwrap();
+        let mut counts = (0, 0);
+        for (order_id, order) in orders.iter() {
+            if excluding == Some(order_id.as_str()) {
+                continue;
+            }
+            counts.0 += 1;
+            if order.symbol == symbol {
+                counts.1 += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn contains(&self, order_id: &str) -> bool {
+        self.orders.lock().unwrap().contains_key(order_id)
+    }
+
+    // The strategy's working orders on the symbol and side, oldest first
+    pub fn working_for(&self, strategy: &str, symbol: &str, side: OrderSide) -> Vec<Order> {
+        let mut working: Vec<Order> = self
+            .orders
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|order| {
+                order.strategy.as_deref() == Some(strategy)
+                    && order.symbol == symbol
+                    && order.side == side
+            })
+            .cloned()
+            .collect();
+        working.sort_by_key(|order| order.timestamp);
+        working
+    }
+}
+
+// Working orders keyed by client order id, with an index from order id so
+// either can be looked up directly
+#[derive(Debug, Default)]
+pub struct OpenOrders {
+    by_client_id: HashMap<String, OrderState>,
+    client_ids: HashMap<String, String>,
+    // Replaced order id -> the order live in its place now (chains are
+    // collapsed), so fills still reported against an old id land correctly
+    replaced: HashMap<String, String>,
+    // One-cancels-other link group -> fraction of a leg that must fill
+    // before the other legs are cancelled outright
+    link_thresholds: HashMap<String, f64>,
+    tracker: Arc<OpenOrderTracker>,
+}
+
+impl OpenOrders {
+    // A shared count of the orders working here
+    pub fn tracker(&self) -> Arc<OpenOrderTracker> {
+        Arc::clone(&self.tracker)
+    }
+
+    pub fn insert(&mut self, state: OrderState) {
+        self.tracker.add(&state.order);
+        self.client_ids
+            .insert(state.order.id.clone(), state.order.client_order_id.clone());
+        self.by_client_id
+            .insert(state.order.client_order_id.clone(), state);
+    }
+
+    pub fn get(&self, order_id: &str) -> Option<&OrderState> {
+        let client_id = self.client_ids.get(order_id)?;
+        self.by_client_id.get(client_id)
+    }
+
+    fn get_mut(&mut self, order_id: &str) -> Option<&mut OrderState> {
+        let client_id = self.client_ids.get(order_id)?;
+        self.by_client_id.get_mut(client_id)
+    }
+
+    // The order currently live for this id, following replacements
+    fn resolve<'a>(&'a self, order_id: &'a str) -> &'a str {
+        self.replaced.get(order_id).map_or(order_id, String::as_str)
+    }
+
+    // Marks a replace as in flight. Returns the order as it stands.
+    pub fn begin_replace(&mut self, order_id: &str) -> Option<OrderState> {
+        let state = self.get_mut(order_id)?;
+        state.status = OrderStatus::PendingReplace;
+        Some(state.clone())
+    }
+
+    // Backs out of a replace that the venue (or the rate limiter) refused
+    pub fn abort_replace(&mut self, order_id: &str) {
+        if let Some(state) = self.get_mut(order_id) {
+            state.status = state.settled_status();
+        }
+    }
+
+    // Swaps the old order for its replacement, carrying over whatever filled
+    // while the replace was in flight. None if the old order is gone or the
+    // fills already complete the replacement.
+    pub fn complete_replace(&mut self, order_id: &str, replacement: Order) -> Option<&OrderState> {
+        let client_id = self.client_ids.remove(order_id)?;
+        let old = self.by_client_id.remove(&client_id)?;
+        self.tracker.remove(order_id);
+        let mut state = OrderState {
+            order: replacement,
+            filled_quantity: old.filled_quantity,
+            status: OrderStatus::Working,
+            queue_ahead: None,
+        };
+        state.status = state.settled_status();
+        // Fills during the replace may have completed the smaller order
+        if state.remaining_quantity() <= f64::EPSILON {
+            self.replaced.retain(|_, id| id != order_id);
+            return None;
+        }
+
+        let new_id = state.order.id.clone();
+        for live in self.replaced.values_mut().filter(|id| *id == order_id) {
+            *live = new_id.clone();
+        }
+        self.replaced.insert(order_id.to_string(), new_id.clone());
+        self.insert(state);
+        self.get(&new_id)
+    }
+
+    // Books a fill against the live order for `order_id`, dropping it once
+    // it is complete. Returns the updated state, or None if the order isn't
+    // working any more.
+    pub fn record_fill(&mut self, order_id: &str, quantity: f64) -> Option<OrderState> {
+        let order_id = self.resolve(order_id).to_string();
+        let state = self.get_mut(&order_id)?;
+        state.filled_quantity += quantity;
+        if state.remaining_quantity() <= f64::EPSILON {
+            return self.remove(&order_id);
+        }
+        if state.status != OrderStatus::PendingReplace {
+            state.status = state.settled_status();
+        }
+        Some(state.clone())
+    }
+
+    // Registers a one-cancels-other group ahead of inserting its legs
+    pub fn link(&mut self, group: &str, cancel_threshold: f64) {
+        self.link_thresholds
+            .insert(group.to_string(), cancel_threshold);
+    }
+
+    pub fn link_threshold(&self, group: &str) -> Option<f64> {
+        self.link_thresholds.get(group).copied()
+    }
+
+    pub fn link_thresholds(&self) -> &HashMap<String, f64> {
+        &self.link_thresholds
+    }
+
+    // Re-inserts an order saved before a restart. A replace that was in
+    // flight is treated as never sent.
+    pub fn restore(&mut self, mut state: OrderState) {
+        if state.status == OrderStatus::PendingReplace {
+            state.status = state.settled_status();
+        }
+        self.insert(state);
+    }
+
+    pub fn linked<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a OrderState> {
+        self.iter()
+            .filter(move |s| s.order.link_group.as_deref() == Some(group))
+    }
+
+    // Applies an execution on one leg of a link group to the other legs:
+    // each is removed when `cancel` is set, otherwise its remaining quantity
+    // is scaled by `ratio` (the leg's remaining after / before the fill).
+    // Returns the ids of the legs removed.
+    pub fn settle_linked(
+        &mut self,
+        group: &str,
+        order_id: &str,
+        ratio: f64,
+        cancel: bool,
+    ) -> Vec<String> {
+        let siblings: Vec<String> = self
+            .linked(group)
+            .filter(|s| s.order.id != order_id)
+            .map(|s| s.order.id.clone())
+            .collect();
+
+        let mut removed = Vec::new();
+        for id in siblings {
+            if !cancel && let Some(state) = self.get_mut(&id) {
+                let remaining = state.remaining_quantity() * ratio;
+                state.order.quantity = state.filled_quantity + remaining;
+                if remaining > f64::EPSILON {
+                    continue;
+                }
+            }
+            if self.remove(&id).is_some() {
+                removed.push(id);
+            }
+        }
+        removed
+    }
+
+    pub fn get_by_client_id(&self, client_order_id: &str) -> Option<&OrderState> {
+        self.by_client_id.get(client_order_id)
+    }
+
+    pub fn remove(&mut self, order_id: &str) -> Option<OrderState> {
+        let client_id = self.client_ids.remove(order_id)?;
+        self.replaced.retain(|_, new_id| new_id != order_id);
+        let state = self.by_client_id.remove(&client_id)?;
+        self.tracker.remove(order_id);
+        // The group goes with its last leg
+        if let Some(group) = &state.order.link_group
+            && self.linked(group).next().is_none()
+        {
+            self.link_thresholds.remove(group);
+        }
+        Some(state)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_client_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_client_id.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &OrderState> {
+        self.by_client_id.values()
+    }
+
+    pub fn for_symbol<'a>(&'a self, symbol: &'a str) -> impl Iterator<Item = &'a OrderState> {
+        self.iter().filter(move |s| s.order.symbol == symbol)
+    }
+}
@@ -0,0 +1,786 @@
+use crate::OrderSide;
+use crate::simulation::standard_normal;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// What happens to the part of a simulated market order the book can't fill
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemainderPolicy {
+    // Drop it, like an IOC order
+    #[default]
+    Cancel,
+    // Leave it pending on the executor until it is cancelled
+    KeepWorking,
+}
+
+// What happens to an order that would trade against one of the bot's own
+// resting orders on the other side of the symbol
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfMatchPolicy {
+    // Let it trade against them
+    Allow,
+    // Cancel the resting orders it would hit, then submit it
+    #[default]
+    CancelResting,
+    // Limit it one tick short of our best resting price; a market order
+    // becomes an IOC limit there
+    Reprice,
+    // Refuse the new order
+    Reject,
+}
+
+// Paper-trading execution settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaperConfig {
+    pub remainder: RemainderPolicy,
+    pub self_match: SelfMatchPolicy,
+    // Have a resting limit wait behind the displayed size ahead of it at
+    // its price, instead of filling as soon as the other side reaches it
+    pub queue_position: bool,
+    pub latency: LatencyConfig,
+}
+
+impl PaperConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        self.latency.validate()
+    }
+}
+
+// How a simulated delay varies around its base
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JitterDistribution {
+    // Evenly within ±jitter_ms
+    #[default]
+    Uniform,
+    // Normal with a standard deviation of jitter_ms / 2, cut off at
+    // ±jitter_ms
+    Normal,
+}
+
+// The simulated venue's delays and transient failures on paper orders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LatencyConfig {
+    // From submitting an order to it reaching the book, where it fills
+    pub fill_ms: u64,
+    // From the order reaching the book to its acknowledgement coming back
+    pub ack_ms: u64,
+    // Either delay varies by up to this much either way
+    pub jitter_ms: u64,
+    pub distribution: JitterDistribution,
+    // Seeds the draws, so runs repeat
+    pub seed: u64,
+    // Chance an attempt never reaches the venue and times out after
+    // timeout_ms
+    pub timeout_probability: f64,
+    pub timeout_ms: u64,
+    // Chance the venue turns an attempt away as busy
+    pub reject_probability: f64,
+    // Chance the venue takes the order but its acknowledgement is lost, so
+    // the attempt times out after timeout_ms all the same
+    pub ack_loss_probability: f64,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self {
+            fill_ms: 0,
+            ack_ms: 10,
+            jitter_ms: 0,
+            distribution: JitterDistribution::Uniform,
+            seed: 0,
+            timeout_probability: 0.0,
+            timeout_ms: 1_000,
+            reject_probability: 0.0,
+            ack_loss_probability: 0.0,
+        }
+    }
+}
+
+impl LatencyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let probabilities = [
+            self.timeout_probability,
+            self.reject_probability,
+            self.ack_loss_probability,
+        ];
+        if !probabilities.iter().all(|p| (0.0..=1.0).contains(p)) {
+            return Err(
+                "Paper latency timeout, reject and ack_loss probabilities must be within 0..=1"
+                    .to_string(),
+            );
+        }
+        if probabilities.iter().sum::<f64>() > 1.0 {
+            return Err(
+                "Paper latency timeout, reject and ack_loss probabilities can't add up past 1"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+// A transient failure injected into a paper order attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectedFault {
+    Timeout,
+    Rejected,
+    // Placed, then timed out waiting for the acknowledgement
+    AckLost,
+}
+
+/// The delays drawn so far, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DelayStats {
+    pub count: u64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub mean_ms: f64,
+}
+
+impl DelayStats {
+    fn record(&mut self, ms: u64) {
+        self.min_ms = if self.count == 0 {
+            ms
+        } else {
+            self.min_ms.min(ms)
+        };
+        self.max_ms = self.max_ms.max(ms);
+        self.count += 1;
+        self.mean_ms += (ms as f64 - self.mean_ms) / self.count as f64;
+    }
+}
+
+/// What the latency model has simulated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub fills: DelayStats,
+    pub acks: DelayStats,
+    pub timeouts: u64,
+    pub rejects: u64,
+    pub acks_lost: u64,
+}
+
+/// Draws the paper venue's delays and transient failures from one seeded
+/// generator. Nothing is drawn for a delay without jitter or a failure
+/// with no chance, so the defaults consume no randomness.
+#[derive(Debug)]
+pub struct LatencyModel {
+    config: LatencyConfig,
+    rng: Mutex<StdRng>,
+    stats: Mutex<LatencyStats>,
+}
+
+impl Default for LatencyModel {
+    fn default() -> Self {
+        Self::new(LatencyConfig::default())
+    }
+}
+
+impl LatencyModel {
+    pub fn new(config: LatencyConfig) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(config.seed)),
+            config,
+            stats: Mutex::new(LatencyStats::default()),
+        }
+    }
+
+    pub fn config(&self) -> &LatencyConfig {
+        &self.config
+    }
+
+    // The base plus a jitter draw, never below zero
+    fn draw(&self, base_ms: u64) -> u64 {
+        let jitter = self.config.jitter_ms as f64;
+        if jitter == 0.0 {
+            return base_ms;
+        }
+        let mut rng = self.rng.lock().unwrap();
+        let offset = match self.config.distribution {
+            JitterDistribution::Uniform => rng.gen_range(-jitter..=jitter),
+            JitterDistribution::Normal => {
+                (standard_normal(&mut rng) * jitter / 2.0).clamp(-jitter, jitter)
+            }
+        };
+        (base_ms as f64 + offset).round().max(0.0) as u64
+    }
+
+    // How long the next order takes to reach the book
+    pub fn fill_delay(&self) -> Duration {
+        let ms = self.draw(self.config.fill_ms);
+        self.stats.lock().unwrap().fills.record(ms);
+        Duration::from_millis(ms)
+    }
+
+    // How long its acknowledgement then takes to come back
+    pub fn ack_delay(&self) -> Duration {
+        let ms = self.draw(self.config.ack_ms);
+        self.stats.lock().unwrap().acks.record(ms);
+        Duration::from_millis(ms)
+    }
+
+    // The failure, if any, the next attempt runs into
+    pub fn fault(&self) -> Option<InjectedFault> {
+        let (timeout, reject, ack_loss) = (
+            self.config.timeout_probability,
+            self.config.reject_probability,
+            self.config.ack_loss_probability,
+        );
+        if timeout + reject + ack_loss <= 0.0 {
+            return None;
+        }
+        let draw: f64 = self.rng.lock().unwrap().gen_range(0.0..1.0);
+        let fault = if draw < timeout {
+            InjectedFault::Timeout
+        } else if draw < timeout + reject {
+            InjectedFault::Rejected
+        } else if draw < timeout + reject + ack_loss {
+            InjectedFault::AckLost
+        } else {
+            return None;
+        };
+        let mut stats = self.stats.lock().unwrap();
+        match fault {
+            InjectedFault::Timeout => stats.timeouts += 1,
+            InjectedFault::Rejected => stats.rejects += 1,
+            InjectedFault::AckLost => stats.acks_lost += 1,
+        }
+        Some(fault)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.config.timeout_ms)
+    }
+
+    pub fn stats(&self) -> LatencyStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+// One level's worth of a simulated order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelFill {
+    pub price: f64,
+    pub quantity: f64,
+}
+
+// Consumes the opposite side's `levels` (best first) until `quantity` is
+// filled, the book runs out, or a level is past the limit price. One fill
+// per level touched.
+pub fn walk_book(
+    levels: &[(f64, f64)],
+    side: OrderSide,
+    quantity: f64,
+    limit: Option<f64>,
+) -> Vec<LevelFill> {
+    let mut remaining = quantity;
+    let mut fills = Vec::new();
+    for &(price, available) in levels {
+        if remaining <= 0.0 || limit.is_some_and(|limit| !crosses(side, limit, price)) {
+            break;
+        }
+        let take = remaining.min(available);
+        if take > 0.0 {
+            fills.push(LevelFill {
+                price,
+                quantity: take,
+            });
+            remaining -= take;
+        }
+    }
+    fills
+}
+
+// Whether a limit at `limit` on `side` would trade against a resting `price`
+pub fn crosses(side: OrderSide, limit: f64, price: f64) -> bool {
+    match side {
+        OrderSide::Buy => price <= limit,
+        OrderSide::Sell => price >= limit,
+    }
+}
+
+// Whether the touch has reached a stop: buy stops fire when the ask rises to
+// the stop price, sell stops when the bid falls to it
+pub fn stop_triggered(
+    side: OrderSide,
+    stop_price: f64,
+    best_bid: Option<f64>,
+    best_ask: Option<f64>,
+) -> bool {
+    match side {
+        OrderSide::Buy => best_ask.is_some_and(|ask| ask >= stop_price),
+        OrderSide::Sell => best_bid.is_some_and(|bid| bid <= stop_price),
+    }
+}
+
+// Volume-weighted average price of a set of fills
+pub fn vwap(fills: &[LevelFill]) -> Option<f64> {
+    let quantity: f64 = fills.iter().map(|f| f.quantity).sum();
+    if quantity <= 0.0 {
+        return None;
+    }
+    Some(fills.iter().map(|f| f.price * f.quantity).sum::<f64>() / quantity)
+}
+
+// A resting limit order as the matching engine queues it
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkingOrder {
+    pub order_id: String,
+    pub side: OrderSide,
+    pub price: f64,
+    pub remaining: f64,
+    // With queue position on, the displayed quantity estimated ahead of
+    // the order at its price; None until its level has been seen
+    pub queue_ahead: Option<f64>,
+    // The level's displayed size in the last book
+    level_size: Option<f64>,
+}
+
+impl WorkingOrder {
+    // Takes in the displayed size at the order's price on its own side of
+    // a new book. Whatever left the level left from ahead of the order;
+    // what joined it queued behind.
+    fn update_queue(&mut self, size: Option<f64>) {
+        let Some(size) = size else {
+            return;
+        };
+        let ahead = match (self.queue_ahead, self.level_size) {
+            (Some(ahead), Some(previous)) => ahead - (previous - size).max(0.0),
+            (Some(ahead), None) => ahead,
+            (None, _) => size,
+        };
+        self.queue_ahead = Some(ahead.min(size).max(0.0));
+        self.level_size = Some(size);
+    }
+
+    // Whether the queue ahead is used up (an unseen level counts as empty)
+    fn at_front(&self) -> bool {
+        self.queue_ahead.is_none_or(|ahead| ahead <= f64::EPSILON)
+    }
+}
+
+// Displayed size at `price` on one side of a book (levels best first):
+// 0 for a price inside the levels shown that has no level, None for one
+// past the last level, where the book doesn't say
+fn level_size(levels: &[(f64, f64)], side: OrderSide, price: f64) -> Option<f64> {
+    if let Some(&(_, quantity)) = levels.iter().find(|&&(p, _)| p == price) {
+        return Some(quantity);
+    }
+    let &(worst, _) = levels.last()?;
+    // A buy's level is inside the bids shown when a bid below it is shown
+    (!crosses(side, worst, price)).then_some(0.0)
+}
+
+// A crossing the engine matched against one of our resting orders, at the
+// order's limit price
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub order_id: String,
+    pub side: OrderSide,
+    pub price: f64,
+    pub quantity: f64,
+}
+
+// Our resting orders on one symbol, each side best price first and, within
+// a price, first come first served
+#[derive(Debug, Default)]
+struct Queues {
+    bids: Vec<WorkingOrder>,
+    asks: Vec<WorkingOrder>,
+    // The last book's levels, which new orders find their place behind
+    book: Option<(Levels, Levels)>,
+}
+
+// One side of a book, best first
+type Levels = Vec<(f64, f64)>;
+
+impl Queues {
+    fn side_mut(&mut self, side: OrderSide) -> &mut Vec<WorkingOrder> {
+        match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        }
+    }
+}
+
+/// Paper matching for resting limit orders. Each book or trade update
+/// fills them, in price-time priority among our own orders, against the
+/// displayed size on the other side that crosses their limits, so an
+/// order larger than the liquidity reaching it fills in pieces over
+/// successive updates. Displayed size is shared out within an update:
+/// what one order takes is gone for the orders queued behind it.
+///
+/// With queue position on, an order also waits behind the displayed size
+/// at its price when it was placed. That estimate shrinks as the level's
+/// size drops and as trades print at the price. Until it's used up, the
+/// order only fills if the other side trades through its price. A print
+/// at the price goes to the queue ahead first, and only the rest reaches
+/// the order.
+#[derive(Debug, Default)]
+pub struct MatchingEngine {
+    symbols: HashMap<String, Queues>,
+    queue_position: bool,
+}
+
+impl MatchingEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_queue_position(mut self, queue_position: bool) -> Self {
+        self.queue_position = queue_position;
+        self
+    }
+
+    // Queues an order behind every order already resting at its price. An
+    // order id that is already queued keeps its place.
+    pub fn add(
+        &mut self,
+        symbol: &str,
+        order_id: &str,
+        side: OrderSide,
+        price: f64,
+        quantity: f64,
+    ) {
+        let queues = self.symbols.entry(symbol.to_string()).or_default();
+        let mut order = WorkingOrder {
+            order_id: order_id.to_string(),
+            side,
+            price,
+            remaining: quantity,
+            queue_ahead: None,
+            level_size: None,
+        };
+        if self.queue_position
+            && let Some((bids, asks)) = &queues.book
+        {
+            let own = match side {
+                OrderSide::Buy => bids,
+                OrderSide::Sell => asks,
+            };
+            order.update_queue(level_size(own, side, price));
+        }
+        let queue = queues.side_mut(side);
+        if queue.iter().any(|o| o.order_id == order_id) {
+            return;
+        }
+        // Orders at the same price or better stay ahead of it
+        let position = queue.partition_point(|o| crosses(side, o.price, price));
+        queue.insert(position, order);
+    }
+
+    // The order's estimated queue ahead, with queue position on
+    pub fn queue_ahead(&self, symbol: &str, order_id: &str) -> Option<f64> {
+        self.orders(symbol)
+            .find(|o| o.order_id == order_id)?
+            .queue_ahead
+    }
+
+    pub fn remove(&mut self, symbol: &str, order_id: &str) -> Option<WorkingOrder> {
+        let queues = self.symbols.get_mut(symbol)?;
+        [&mut queues.bids, &mut queues.asks]
+            .into_iter()
+            .find_map(|queue| {
+                let position = queue.iter().position(|o| o.order_id == order_id)?;
+                Some(queue.remove(position))
+            })
+    }
+
+    // Keeps the symbol's orders for which `keep` returns true; `keep` may
+    // also update an order's remaining quantity
+    pub fn retain(&mut self, symbol: &str, mut keep: impl FnMut(&mut WorkingOrder) -> bool) {
+        if let Some(queues) = self.symbols.get_mut(symbol) {
+            queues.bids.retain_mut(&mut keep);
+            queues.asks.retain_mut(&mut keep);
+            queues.bids.retain(|o| o.remaining > f64::EPSILON);
+            queues.asks.retain(|o| o.remaining > f64::EPSILON);
+        }
+    }
+
+    // The symbol's resting orders, bids then asks, each in priority order
+    pub fn orders<'a>(&'a self, symbol: &str) -> impl Iterator<Item = &'a WorkingOrder> {
+        self.symbols
+            .get(symbol)
+            .into_iter()
+            .flat_map(|queues| queues.bids.iter().chain(&queues.asks))
+    }
+
+    /// Matches the symbol's resting orders against a new book (levels best
+    /// first): buys against the asks, then sells against the bids. `admit`
+    /// is asked, in priority order, about each order that would fill and
+    /// may hold it back. Fully filled orders leave the queue. Returns the
+    /// matches in the order they were made.
+    pub fn match_book(
+        &mut self,
+        symbol: &str,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+        mut admit: impl FnMut(&WorkingOrder) -> bool,
+    ) -> Vec<Match> {
+        let queue_position = self.queue_position;
+        let queues = self.symbols.entry(symbol.to_string()).or_default();
+        if queue_position {
+            for (queue, own) in [(&mut queues.bids, bids), (&mut queues.asks, asks)] {
+                for order in queue.iter_mut() {
+                    order.update_queue(level_size(own, order.side, order.price));
+                }
+            }
+            queues.book = Some((bids.to_vec(), asks.to_vec()));
+        }
+        let fills = Fills {
+            queue_position,
+            print: false,
+        };
+        let mut matches = Vec::new();
+        fills.fill_queue(&mut queues.bids, asks, &mut admit, &mut matches);
+        fills.fill_queue(&mut queues.asks, bids, &mut admit, &mut matches);
+        matches
+    }
+
+    // Matches against a trade print of `quantity` at `price`: the trade's
+    // size is what reaches our orders on either side
+    pub fn match_trade(
+        &mut self,
+        symbol: &str,
+        price: f64,
+        quantity: f64,
+        mut admit: impl FnMut(&WorkingOrder) -> bool,
+    ) -> Vec<Match> {
+        let print = [(price, quantity)];
+        let fills = Fills {
+            queue_position: self.queue_position,
+            print: true,
+        };
+        let mut matches = Vec::new();
+        if let Some(queues) = self.symbols.get_mut(symbol) {
+            fills.fill_queue(&mut queues.bids, &print, &mut admit, &mut matches);
+            fills.fill_queue(&mut queues.asks, &print, &mut admit, &mut matches);
+        }
+        matches
+    }
+}
+
+// How one matching pass treats the levels it's given
+struct Fills {
+    queue_position: bool,
+    // A trade print rather than a book
+    print: bool,
+}
+
+impl Fills {
+    // Fills one side's queue from the opposite side's levels
+    fn fill_queue(
+        &self,
+        queue: &mut Vec<WorkingOrder>,
+        levels: &[(f64, f64)],
+        admit: &mut impl FnMut(&WorkingOrder) -> bool,
+        matches: &mut Vec<Match>,
+    ) {
+        let mut available: Vec<f64> = levels.iter().map(|&(_, quantity)| quantity).collect();
+        for order in queue.iter_mut() {
+            let crossing = levels
+                .iter()
+                .take_while(|&&(price, _)| crosses(order.side, order.price, price))
+                .count();
+            let reachable = self.reachable(order, &levels[..crossing], &available[..crossing]);
+            let quantity = reachable.min(order.remaining);
+            if quantity <= f64::EPSILON || !admit(order) {
+                continue;
+            }
+            let mut left = quantity;
+            for level in &mut available[..crossing] {
+                let take = left.min(*level);
+                *level -= take;
+                left -= take;
+            }
+            order.remaining -= quantity;
+            matches.push(Match {
+                order_id: order.order_id.clone(),
+                side: order.side,
+                price: order.price,
+                quantity,
+            });
+        }
+        queue.retain(|o| o.remaining > f64::EPSILON);
+    }
+
+    // How much of the crossing levels (what's left of them) can reach the
+    // order, bringing its queue estimate up to date with them
+    fn reachable(
+        &self,
+        order: &mut WorkingOrder,
+        crossing: &[(f64, f64)],
+        available: &[f64],
+    ) -> f64 {
+        let total: f64 = available.iter().sum();
+        let Some(&(touch, printed)) = crossing.last() else {
+            return 0.0;
+        };
+        if !self.queue_position {
+            return total;
+        }
+        // Trading through the price clears everything queued at it
+        if touch != order.price || crossing.len() > 1 {
+            order.queue_ahead = Some(0.0);
+            return total;
+        }
+        let ahead = order.queue_ahead.unwrap_or(0.0);
+        if !self.print {
+            return if order.at_front() { total } else { 0.0 };
+        }
+        // A print at the price takes from the displayed queue first, which
+        // the next book shows smaller by as much
+        let used = ahead.min(printed);
+        order.queue_ahead = Some(ahead - used);
+        order.level_size = order.level_size.map(|size| (size - used).max(0.0));
+        (total - ahead).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(order_id: &str, side: OrderSide, price: f64, quantity: f64) -> Match {
+        Match {
+            order_id: order_id.to_string(),
+            side,
+            price,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn a_scripted_book_path_fills_in_price_time_priority() {
+        let mut engine = MatchingEngine::new();
+        engine.add("SOL/USDT", "a", OrderSide::Buy, 99.5, 3.0);
+        engine.add("SOL/USDT", "low", OrderSide::Buy, 99.0, 1.0);
+        engine.add("SOL/USDT", "b", OrderSide::Buy, 99.5, 2.0);
+        engine.add("SOL/USDT", "ask", OrderSide::Sell, 101.0, 1.0);
+        let mut step = |bids: &[(f64, f64)], asks: &[(f64, f64)]| {
+            engine.match_book("SOL/USDT", bids, asks, |_| true)
+        };
+
+        // Nothing crosses
+        assert!(step(&[(99.0, 1.0)], &[(100.0, 5.0)]).is_empty());
+        // The ask trades down to 99.5 with 2 shown: all of it goes to a,
+        // which was first at the price
+        assert_eq!(
+            step(&[(99.0, 1.0)], &[(99.5, 2.0), (100.0, 5.0)]),
+            [fill("a", OrderSide::Buy, 99.5, 2.0)]
+        );
+        // Through 99.5: a takes the 99.4 level, b what's left at 99.5
+        assert_eq!(
+            step(&[(99.0, 1.0)], &[(99.4, 1.0), (99.5, 4.0)]),
+            [
+                fill("a", OrderSide::Buy, 99.5, 1.0),
+                fill("b", OrderSide::Buy, 99.5, 2.0),
+            ]
+        );
+        // Buys fill before sells
+        assert_eq!(
+            step(&[(101.2, 0.4)], &[(98.9, 3.0)]),
+            [
+                fill("low", OrderSide::Buy, 99.0, 1.0),
+                fill("ask", OrderSide::Sell, 101.0, 0.4),
+            ]
+        );
+        let resting: Vec<_> = engine
+            .orders("SOL/USDT")
+            .map(|o| (o.order_id.as_str(), o.remaining))
+            .collect();
+        assert_eq!(resting, [("ask", 0.6)]);
+    }
+
+    #[test]
+    fn an_order_waits_behind_the_size_shown_at_its_price() {
+        let mut engine = MatchingEngine::new().with_queue_position(true);
+        engine.match_book("SOL/USDT", &[(99.5, 3.0)], &[(100.0, 1.0)], |_| true);
+        engine.add("SOL/USDT", "a", OrderSide::Buy, 99.5, 1.0);
+        assert_eq!(engine.queue_ahead("SOL/USDT", "a"), Some(3.0));
+
+        // A print at the price goes to the queue ahead first
+        assert!(
+            engine
+                .match_trade("SOL/USDT", 99.5, 2.0, |_| true)
+                .is_empty()
+        );
+        assert_eq!(engine.queue_ahead("SOL/USDT", "a"), Some(1.0));
+        assert_eq!(
+            engine.match_trade("SOL/USDT", 99.5, 2.0, |_| true),
+            [fill("a", OrderSide::Buy, 99.5, 1.0)]
+        );
+    }
+
+    #[test]
+    fn a_queued_buy_fills_once_the_volume_ahead_of_it_has_traded() {
+        let mut engine = MatchingEngine::new().with_queue_position(true);
+        let book = |engine: &mut MatchingEngine, bid: f64| {
+            engine.match_book("SOL/USDT", &[(100.0, bid)], &[(100.5, 5.0)], |_| true)
+        };
+        book(&mut engine, 10.0);
+        engine.add("SOL/USDT", "a", OrderSide::Buy, 100.0, 3.0);
+        let ahead = |engine: &MatchingEngine| engine.queue_ahead("SOL/USDT", "a");
+        assert_eq!(ahead(&engine), Some(10.0));
+
+        // 4 trade at the price: 6 still ahead
+        assert!(
+            engine
+                .match_trade("SOL/USDT", 100.0, 4.0, |_| true)
+                .is_empty()
+        );
+        assert_eq!(ahead(&engine), Some(6.0));
+        // The level shows 5: one more left from ahead of us
+        assert!(book(&mut engine, 5.0).is_empty());
+        assert_eq!(ahead(&engine), Some(5.0));
+        // 3 join behind us
+        assert!(book(&mut engine, 8.0).is_empty());
+        assert_eq!(ahead(&engine), Some(5.0));
+        // 7 more trade: 5 clear the queue, 2 reach us, 11 printed in all
+        assert_eq!(
+            engine.match_trade("SOL/USDT", 100.0, 7.0, |_| true),
+            [fill("a", OrderSide::Buy, 100.0, 2.0)]
+        );
+        assert_eq!(ahead(&engine), Some(0.0));
+        assert_eq!(
+            engine.match_trade("SOL/USDT", 100.0, 4.0, |_| true),
+            [fill("a", OrderSide::Buy, 100.0, 1.0)]
+        );
+        assert_eq!(engine.orders("SOL/USDT").count(), 0);
+    }
+
+    #[test]
+    fn a_touch_waits_for_the_queue_but_a_trade_through_does_not() {
+        let mut engine = MatchingEngine::new().with_queue_position(true);
+        engine.match_book("SOL/USDT", &[(99.0, 4.0)], &[(101.0, 5.0)], |_| true);
+        engine.add("SOL/USDT", "sell", OrderSide::Sell, 101.0, 1.0);
+        engine.add("SOL/USDT", "buy", OrderSide::Buy, 99.0, 1.0);
+
+        // A bid touching 101 while 5 are offered ahead of us
+        assert!(
+            engine
+                .match_book("SOL/USDT", &[(101.0, 2.0)], &[(101.0, 5.0)], |_| true)
+                .is_empty()
+        );
+        // The 101 offers are gone: we're at the front, and fill
+        assert_eq!(
+            engine.match_book("SOL/USDT", &[(101.0, 2.0)], &[(101.5, 3.0)], |_| true),
+            [fill("sell", OrderSide::Sell, 101.0, 1.0)]
+        );
+        // Offers through 99 fill the buy with all 4 still ahead at 99
+        assert_eq!(engine.queue_ahead("SOL/USDT", "buy"), Some(4.0));
+        assert_eq!(
+            engine.match_book("SOL/USDT", &[(99.0, 4.0)], &[(98.5, 2.0)], |_| true),
+            [fill("buy", OrderSide::Buy, 99.0, 1.0)]
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{Fill, Position, PositionSide};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 86_400.0;
+
+// Fills with no originating strategy (protective stops, the kill switch)
+pub const UNATTRIBUTED: &str = "unattributed";
+
+// Risk and return statistics for a run, or for one strategy or symbol of it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    pub starting_equity: f64,
+    pub final_equity: f64,
+    pub net_pnl: f64,
+    pub total_return: f64,
+    // Annualized from the per-period returns of the equity curve. None with
+    // fewer than two returns or no variance (downside variance for Sortino).
+    pub sharpe: Option<f64>,
+    pub sortino: Option<f64>,
+    // Largest fall from a running peak, as a fraction of the peak
+    pub max_drawdown: f64,
+    // Longest time spent below a previous peak
+    pub max_drawdown_duration_secs: u64,
+    pub fills: usize,
+    // Fills that closed (part of) a position; each is won or lost on its
+    // realized PnL net of its own fee
+    pub trades: usize,
+    pub hit_rate: Option<f64>,
+    pub average_win: Option<f64>,
+    // Positive: the mean size of a losing trade
+    pub average_loss: Option<f64>,
+    // Gross wins over gross losses; None without losing trades
+    pub profit_factor: Option<f64>,
+    // Traded notional over starting equity
+    pub turnover: f64,
+    pub fees: f64,
+}
+
+impl PerformanceReport {
+    /// Builds a report from an equity curve of (timestamp, equity) points,
+    /// oldest first, and the fills that produced it. The curve's spacing
+    /// sets the period Sharpe and Sortino are annualized from.
+    pub fn compute(equity_curve: &[(u64, f64)], fills: &[Fill]) -> Self {
+        let starting_equity = equity_curve.first().map_or(0.0, |&(_, e)| e);
+        let final_equity = equity_curve.last().map_or(starting_equity, |&(_, e)| e);
+        let net_pnl = final_equity - starting_equity;
+
+        let returns: Vec<f64> = equity_curve
+            .windows(2)
+            .filter(|w| w[0].1 != 0.0)
+            .map(|w| w[1].1 / w[0].1 - 1.0)
+            .collect();
+        let periods_per_year = periods_per_year(equity_curve);
+        let (max_drawdown, max_drawdown_duration_secs) = max_drawdown(equity_curve);
+
+        let trade_pnls = trade_pnls(fills);
+        let wins: Vec<f64> = trade_pnls.iter().copied().filter(|p| *p > 0.0).collect();
+        let losses: Vec<f64> = trade_pnls.iter().copied().filter(|p| *p < 0.0).collect();
+        let gross_win: f64 = wins.iter().sum();
+        let gross_loss: f64 = -losses.iter().sum::<f64>();
+        let traded_notional: f64 = fills.iter().map(|f| (f.quantity * f.price).abs()).sum();
+
+        Self {
+            starting_equity,
+            final_equity,
+            net_pnl,
+            total_return: if starting_equity != 0.0 {
+                net_pnl / starting_equity
+            } else {
+                0.0
+            },
+            sharpe: sharpe(&returns, periods_per_year),
+            sortino: sortino(&returns, periods_per_year),
+            max_drawdown,
+            max_drawdown_duration_secs,
+            fills: fills.len(),
+            trades: trade_pnls.len(),
+            hit_rate: (!trade_pnls.is_empty()).then(|| wins.len() as f64 / trade_pnls.len() as f64),
+            average_win: mean(&wins),
+            average_loss: (!losses.is_empty()).then(|| gross_loss / losses.len() as f64),
+            profit_factor: (gross_loss > 0.0).then(|| gross_win / gross_loss),
+            turnover: if starting_equity != 0.0 {
+                traded_notional / starting_equity
+            } else {
+                0.0
+            },
+            fees: fills.iter().map(|f| f.fee).sum(),
+        }
+    }
+}
+
+// A run's report overall and split by strategy and by symbol
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PerformanceBreakdown {
+    pub overall: PerformanceReport,
+    pub by_strategy: BTreeMap<String, PerformanceReport>,
+    pub by_symbol: BTreeMap<String, PerformanceReport>,
+}
+
+impl PerformanceBreakdown {
+    /// The overall report uses `equity_curve` as given (open positions
+    /// marked to market). A strategy or symbol has no equity of its own, so
+    /// its curve is `starting_equity` plus its realized PnL, sampled at the
+    /// same timestamps; with an empty curve the fill timestamps are used.
+    pub fn compute(starting_equity: f64, equity_curve: &[(u64, f64)], fills: &[Fill]) -> Self {
+        let timeline: Vec<u64> = if equity_curve.is_empty() {
+            fills.iter().map(|f| f.timestamp).collect()
+        } else {
+            equity_curve.iter().map(|&(t, _)| t).collect()
+        };
+
+        let mut by_strategy: BTreeMap<String, Vec<Fill>> = BTreeMap::new();
+        let mut by_symbol: BTreeMap<String, Vec<Fill>> = BTreeMap::new();
+        for fill in fills {
+            let strategy = fill.strategy.as_deref().unwrap_or(UNATTRIBUTED);
+            by_strategy
+                .entry(strategy.to_string())
+                .or_default()
+                .push(fill.clone());
+            by_symbol
+                .entry(fill.symbol.to_string())
+                .or_default()
+                .push(fill.clone());
+        }
+        let report = |fills: Vec<Fill>| {
+            let curve = realized_curve(starting_equity, &timeline, &fills);
+            PerformanceReport::compute(&curve, &fills)
+        };
+
+        Self {
+            overall: if equity_curve.is_empty() {
+                report(fills.to_vec())
+            } else {
+                PerformanceReport::compute(equity_curve, fills)
+            },
+            by_strategy: by_strategy
+                .into_iter()
+                .map(|(name, fills)| (name, report(fills)))
+                .collect(),
+            by_symbol: by_symbol
+                .into_iter()
+                .map(|(symbol, fills)| (symbol, report(fills)))
+                .collect(),
+        }
+    }
+}
+
+// Realized PnL (net of its own fee) of every fill that closed part of a
+// position, replaying the fills through the same average-cost accounting
+// the risk manager uses
+fn trade_pnls(fills: &[Fill]) -> Vec<f64> {
+    let mut positions: HashMap<(&str, PositionSide), Position> = HashMap::new();
+    let mut pnls = Vec::new();
+    for fill in fills {
+        let position = positions
+            .entry((fill.symbol.as_str(), fill.position_side))
+            .or_insert_with(|| Position::flat(fill.symbol));
+        let closing = position.quantity != 0.0
+            && position.quantity.signum() != fill.signed_quantity().signum();
+        let realized = position.apply_trade(fill.signed_quantity(), fill.price, fill.fee);
+        if closing {
+            pnls.push(realized);
+        }
+    }
+    pnls
+}
+
+// Equity as `starting_equity` plus realized PnL to date at each timestamp
+fn realized_curve(starting_equity: f64, timeline: &[u64], fills: &[Fill]) -> Vec<(u64, f64)> {
+    let mut positions: HashMap<(&str, PositionSide), Position> = HashMap::new();
+    let mut realized = 0.0;
+    let mut next = 0;
+    timeline
+        .iter()
+        .map(|&timestamp| {
+            while let Some(fill) = fills.get(next).filter(|f| f.timestamp <= timestamp) {
+                realized += positions
+                    .entry((fill.symbol.as_str(), fill.position_side))
+                    .or_insert_with(|| Position::flat(fill.symbol))
+                    .apply_trade(fill.signed_quantity(), fill.price, fill.fee);
+                next += 1;
+            }
+            (timestamp, starting_equity + realized)
+        })
+        .collect()
+}
+
+// From the curve's average spacing; one period a day if it has none
+fn periods_per_year(equity_curve: &[(u64, f64)]) -> f64 {
+    match (equity_curve.first(), equity_curve.last()) {
+        (Some(&(first, _)), Some(&(last, _))) if last > first => {
+            let spacing = (last - first) as f64 / (equity_curve.len() - 1) as f64;
+            SECONDS_PER_YEAR / spacing
+        }
+        _ => 365.0,
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+fn sharpe(returns: &[f64], periods_per_year: f64) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+    let mean = mean(returns)?;
+    let variance =
+        returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    let std_dev = variance.sqrt();
+    (std_dev > f64::EPSILON).then(|| mean / std_dev * periods_per_year.sqrt())
+}
+
+// As Sharpe, but only returns below zero count as risk
+fn sortino(returns: &[f64], periods_per_year: f64) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+    let mean = mean(returns)?;
+    let downside =
+        (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64).sqrt();
+    (downside > f64::EPSILON).then(|| mean / downside * periods_per_year.sqrt())
+}
+
+// (deepest drawdown fraction, longest seconds under water)
+fn max_drawdown(equity_curve: &[(u64, f64)]) -> (f64, u64) {
+    let mut max_drawdown: f64 = 0.0;
+    let mut longest = 0;
+    let Some(&(mut peak_time, mut peak)) = equity_curve.first() else {
+        return (0.0, 0);
+    };
+    for &(timestamp, equity) in equity_curve {
+        if equity >= peak {
+            peak = equity;
+            peak_time = timestamp;
+            continue;
+        }
+        if peak > 0.0 {
+            max_drawdown = max_drawdown.max((peak - equity) / peak);
+        }
+        longest = longest.max(timestamp - peak_time);
+    }
+    (max_drawdown, longest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use crate::fixtures::fill;
+
+    const DAY: u64 = 86_400;
+
+    fn daily(equity: &[f64]) -> Vec<(u64, f64)> {
+        equity
+            .iter()
+            .enumerate()
+            .map(|(i, &e)| (i as u64 * DAY, e))
+            .collect()
+    }
+
+    #[test]
+    fn hand_worked_trades_and_curve() {
+        // +100, then -20 and +20 closing a second position in two halves
+        let fills = [
+            fill("SOL/USDT", OrderSide::Buy, 10.0, 100.0),
+            fill("SOL/USDT", OrderSide::Sell, 10.0, 110.0),
+            fill("SOL/USDT", OrderSide::Buy, 10.0, 100.0),
+            fill("SOL/USDT", OrderSide::Sell, 5.0, 96.0),
+            fill("SOL/USDT", OrderSide::Sell, 5.0, 104.0),
+        ];
+        // Daily returns of +10%, -10%, +10%
+        let curve = daily(&[10_000.0, 11_000.0, 9_900.0, 10_890.0]);
+        let report = PerformanceReport::compute(&curve, &fills);
+
+        assert_eq!(report.fills, 5);
+        assert_eq!(report.trades, 3);
+        assert_eq!(report.hit_rate, Some(2.0 / 3.0));
+        assert_eq!(report.average_win, Some(60.0));
+        assert_eq!(report.average_loss, Some(20.0));
+        assert_eq!(report.profit_factor, Some(6.0));
+        // 4,100 traded against 10,000
+        assert!((report.turnover - 0.41).abs() < 1e-12);
+        assert!((report.total_return - 0.089).abs() < 1e-12);
+
+        // Mean 1/30, sample deviation 2/sqrt(300): 1/(2 sqrt 3) a day
+        let sharpe = report.sharpe.unwrap();
+        assert!((sharpe - 365f64.sqrt() / (2.0 * 3f64.sqrt())).abs() < 1e-9);
+        // Downside deviation sqrt(0.01 / 3): 1/sqrt 3 a day
+        let sortino = report.sortino.unwrap();
+        assert!((sortino - 365f64.sqrt() / 3f64.sqrt()).abs() < 1e-9);
+        // 11,000 to 9,900, and under water from day 1 to the end
+        assert!((report.max_drawdown - 0.1).abs() < 1e-12);
+        assert_eq!(report.max_drawdown_duration_secs, 2 * DAY);
+    }
+
+    #[test]
+    fn no_variance_has_no_sharpe() {
+        let flat = PerformanceReport::compute(&daily(&[10_000.0; 4]), &[]);
+        assert_eq!((flat.sharpe, flat.sortino), (None, None));
+        assert_eq!(flat.max_drawdown, 0.0);
+        // Gains only: no downside to scale by
+        let rising = PerformanceReport::compute(&daily(&[100.0, 110.0, 130.0]), &[]);
+        assert!(rising.sharpe.is_some());
+        assert_eq!(rising.sortino, None);
+        // A single return has no variance to speak of
+        let single = PerformanceReport::compute(&daily(&[100.0, 110.0]), &[]);
+        assert_eq!(single.sharpe, None);
+        // Without losing trades there is no profit factor
+        let fills = [
+            fill("SOL/USDT", OrderSide::Buy, 1.0, 100.0),
+            fill("SOL/USDT", OrderSide::Sell, 1.0, 101.0),
+        ];
+        let report = PerformanceReport::compute(&daily(&[100.0, 101.0]), &fills);
+        assert_eq!((report.hit_rate, report.profit_factor), (Some(1.0), None));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::Price;
+use serde::{Deserialize, Serialize};
+
+// What to do with a tick older than the newest one kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutOfOrderPolicy {
+    #[default]
+    Drop,
+    // Slot it into the history in timestamp order
+    Insert,
+}
+
+// Each symbol's recent prices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceHistoryConfig {
+    // Prices kept per symbol
+    pub capacity: usize,
+    pub out_of_order: OutOfOrderPolicy,
+}
+
+impl Default for PriceHistoryConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            out_of_order: OutOfOrderPolicy::Drop,
+        }
+    }
+}
+
+impl PriceHistoryConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity == 0 {
+            return Err("Price history capacity must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Where an incoming tick went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Insertion {
+    // Newest so far
+    Appended,
+    // Older than the newest, and slotted in behind it
+    Reordered,
+    // Older than the newest, and dropped
+    OutOfOrder,
+    // Same timestamp, price and volume as one already kept
+    Duplicate,
+}
+
+/// The last `capacity` prices of a symbol, oldest first and never out of
+/// timestamp order, whatever order the feed delivered them in. Storage
+/// grows to twice the capacity before the oldest half is let go, so
+/// appends are amortized O(1) and the prices stay one contiguous slice.
+#[derive(Debug, Clone)]
+pub struct PriceBuffer {
+    prices: Vec<Price>,
+    capacity: usize,
+    out_of_order: OutOfOrderPolicy,
+}
+
+impl PriceBuffer {
+    pub fn new(config: &PriceHistoryConfig) -> Self {
+        Self {
+            prices: Vec::with_capacity(config.capacity),
+            capacity: config.capacity,
+            out_of_order: config.out_of_order,
+        }
+    }
+
+    pub fn insert(&mut self, price: Price) -> Insertion {
+        let kept = self.as_slice();
+        // Just past every tick at or before this one's time
+        let at = kept.partition_point(|p| p.timestamp <= price.timestamp);
+        let duplicate = kept[..at]
+            .iter()
+            .rev()
+            .take_while(|p| p.timestamp == price.timestamp)
+            .any(|p| p.price == price.price && p.volume == price.volume);
+        if duplicate {
+            return Insertion::Duplicate;
+        }
+
+        let insertion = if at == kept.len() {
+            Insertion::Appended
+        } else if self.out_of_order == OutOfOrderPolicy::Insert {
+            Insertion::Reordered
+        } else {
+            return Insertion::OutOfOrder;
+        };
+        let offset = self.prices.len() - kept.len();
+        self.prices.insert(offset + at, price);
+        if self.prices.len() >= 2 * self.capacity {
+            self.prices.drain(..self.prices.len() - self.capacity);
+        }
+        insertion
+    }
+
+    pub fn as_slice(&self) -> &[Price] {
+        &self.prices[self.prices.len().saturating_sub(self.capacity)..]
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::price_history;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+
+    // 200 ticks, 20 of them delivered twice, in a shuffled order
+    fn shuffled() -> (Vec<Price>, Vec<Price>) {
+        let history = price_history("SOL/USDT", 200, 3);
+        let mut delivered = history.clone();
+        delivered.extend(history.iter().step_by(10).cloned());
+        delivered.shuffle(&mut StdRng::seed_from_u64(3));
+        (history, delivered)
+    }
+
+    fn buffer(capacity: usize, out_of_order: OutOfOrderPolicy) -> PriceBuffer {
+        PriceBuffer::new(&PriceHistoryConfig {
+            capacity,
+            out_of_order,
+        })
+    }
+
+    fn is_monotonic(prices: &[Price]) -> bool {
+        prices.windows(2).all(|w| w[0].timestamp <= w[1].timestamp)
+    }
+
+    #[test]
+    fn a_shuffled_feed_reorders_into_the_full_history() {
+        let (history, delivered) = shuffled();
+        let mut buffer = buffer(500, OutOfOrderPolicy::Insert);
+        let outcomes: Vec<Insertion> = delivered.into_iter().map(|p| buffer.insert(p)).collect();
+        let count = |insertion| outcomes.iter().filter(|&&o| o == insertion).count();
+        assert_eq!(count(Insertion::Duplicate), 20);
+        assert_eq!(count(Insertion::OutOfOrder), 0);
+        assert_eq!(
+            count(Insertion::Appended) + count(Insertion::Reordered),
+            200
+        );
+        let timestamps = |prices: &[Price]| prices.iter().map(|p| p.timestamp).collect::<Vec<_>>();
+        assert_eq!(timestamps(buffer.as_slice()), timestamps(&history));
+    }
+
+    #[test]
+    fn a_shuffled_feed_keeps_only_newer_ticks_by_default() {
+        let (_, delivered) = shuffled();
+        let mut buffer = buffer(50, OutOfOrderPolicy::Drop);
+        let mut newest = 0;
+        for price in delivered {
+            let timestamp = price.timestamp;
+            match buffer.insert(price) {
+                Insertion::Appended => newest = timestamp,
+                Insertion::OutOfOrder => assert!(timestamp < newest),
+                Insertion::Duplicate => assert!(timestamp <= newest),
+                Insertion::Reordered => panic!("reordered under the drop policy"),
+            }
+            assert!(is_monotonic(buffer.as_slice()));
+        }
+        assert!(buffer.len() <= 50);
+    }
+
+    #[test]
+    fn reordering_holds_across_trimming_to_capacity() {
+        let (history, delivered) = shuffled();
+        let mut buffer = buffer(30, OutOfOrderPolicy::Insert);
+        for price in delivered {
+            buffer.insert(price);
+            assert!(is_monotonic(buffer.as_slice()));
+        }
+        assert_eq!(buffer.len(), 30);
+        assert_eq!(
+            buffer.as_slice().last().unwrap().timestamp,
+            history[199].timestamp
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::OrderBook;
+use crate::fees::{FeeModel, Liquidity};
+
+// A quote and whether it would rest on the book or take from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    pub price: f64,
+    pub liquidity: Liquidity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quotes {
+    pub bid: Quote,
+    pub ask: Quote,
+    // Mid shifted for inventory, which the quotes are centred on
+    pub reservation_price: f64,
+}
+
+/// Prices two-sided quotes that never sit inside a losing spread. Each
+/// side's half-spread is its fee plus `target_edge_bps`, widened by
+/// `volatility_multiplier` times the recent tick volatility. Quotes are
+/// centred on the mid less `inventory_skew_bps` per unit of inventory, so
+/// a long book quotes lower (and a short one higher) to work it off. A
+/// side that would cross the book at that price is priced to cover the
+/// taker fee instead of the maker fee.
+#[derive(Debug, Clone)]
+pub struct QuoteCalibrator {
+    fees: FeeModel,
+    target_edge_bps: f64,
+    volatility_multiplier: f64,
+    inventory_skew_bps: f64,
+}
+
+impl QuoteCalibrator {
+    pub fn new(fees: FeeModel, target_edge_bps: f64) -> Self {
+        Self {
+            fees,
+            target_edge_bps,
+            volatility_multiplier: 0.0,
+            inventory_skew_bps: 0.0,
+        }
+    }
+
+    pub fn with_volatility_multiplier(mut self, multiplier: f64) -> Self {
+        self.volatility_multiplier = multiplier;
+        self
+    }
+
+    pub fn with_inventory_skew_bps(mut self, skew_bps: f64) -> Self {
+        self.inventory_skew_bps = skew_bps;
+        self
+    }
+
+    /// Half-spread, in bps of the reservation price, for a quote filled
+    /// with `liquidity`. `volatility` is the standard deviation of recent
+    /// tick returns (RealizedVolatility, unannualized).
+    pub fn half_spread_bps(
+        &self,
+        symbol: &str,
+        liquidity: Liquidity,
+        volatility: Option<f64>,
+    ) -> f64 {
+        let fee_bps = self.fees.schedule(symbol).bps(liquidity);
+        let volatility_bps = self.volatility_multiplier * volatility.unwrap_or(0.0) * 10_000.0;
+        // A maker rebate can pay for some of the edge, but never more
+        (fee_bps + self.target_edge_bps).max(0.0) + volatility_bps
+    }
+
+    // Quotes for the book, holding `inventory` (signed); None without a mid
+    pub fn quotes(
+        &self,
+        symbol: &str,
+        book: &OrderBook,
+        volatility: Option<f64>,
+        inventory: f64,
+    ) -> Option<Quotes> {
+        let mid = book.mid_price()?;
+        let reservation_price = mid * (1.0 - self.inventory_skew_bps * inventory / 10_000.0);
+        let price_at = |liquidity: Liquidity, side: f64| {
+            let half_spread = self.half_spread_bps(symbol, liquidity, volatility);
+            reservation_price * (1.0 + side * half_spread / 10_000.0)
+        };
+
+        // A side that would cross is priced to cover the taker fee, and
+        // takes liquidity if it crosses even then
+        let crosses = |price: f64, side: f64| {
+            if side < 0.0 {
+                book.best_ask()
+                    .is_some_and(|(best_ask, _)| price >= best_ask)
+            } else {
+                book.best_bid()
+                    .is_some_and(|(best_bid, _)| price <= best_bid)
+            }
+        };
+        let quote = |side: f64| {
+            let price = price_at(Liquidity::Maker, side);
+            if !crosses(price, side) {
+                return Quote {
+                    price,
+                    liquidity: Liquidity::Maker,
+                };
+            }
+            let price = price_at(Liquidity::Taker, side);
+            Quote {
+                price,
+                liquidity: if crosses(price, side) {
+                    Liquidity::Taker
+                } else {
+                    Liquidity::Maker
+                },
+            }
+        };
+        let (bid, ask) = (quote(-1.0), quote(1.0));
+        Some(Quotes {
+            bid,
+            ask,
+            reservation_price,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    // 2 bps maker, 10 bps taker, and 3 bps of edge on top, quoting books
+    // at mid 100 with the touch 10 bps either side
+    fn calibrator() -> QuoteCalibrator {
+        QuoteCalibrator::new(FeeModel::default(), 3.0)
+    }
+
+    fn assert_quotes(quotes: Quotes, bid: (f64, Liquidity), ask: (f64, Liquidity)) {
+        assert!((quotes.bid.price - bid.0).abs() < 1e-9, "{quotes:?}");
+        assert!((quotes.ask.price - ask.0).abs() < 1e-9, "{quotes:?}");
+        assert_eq!((quotes.bid.liquidity, quotes.ask.liquidity), (bid.1, ask.1));
+    }
+
+    #[test]
+    fn quotes_sit_the_maker_fee_plus_edge_from_mid() {
+        let book = fixtures::book("SOL/USDT", &[(99.9, 5.0)], &[(100.1, 5.0)]);
+        let quotes = calibrator().quotes("SOL/USDT", &book, None, 0.0).unwrap();
+        // 2 + 3 = 5 bps
+        assert_quotes(
+            quotes,
+            (99.95, Liquidity::Maker),
+            (100.05, Liquidity::Maker),
+        );
+        assert_eq!(quotes.reservation_price, 100.0);
+    }
+
+    #[test]
+    fn volatility_widens_the_spread() {
+        let book = fixtures::book("SOL/USDT", &[(99.9, 5.0)], &[(100.1, 5.0)]);
+        let calibrator = calibrator().with_volatility_multiplier(2.0);
+        // 2 bps tick volatility, doubled: 5 + 4 = 9 bps
+        let quotes = calibrator
+            .quotes("SOL/USDT", &book, Some(0.0002), 0.0)
+            .unwrap();
+        assert_quotes(
+            quotes,
+            (99.91, Liquidity::Maker),
+            (100.09, Liquidity::Maker),
+        );
+    }
+
+    #[test]
+    fn a_long_inventory_shifts_both_quotes_down() {
+        let book = fixtures::book("SOL/USDT", &[(99.9, 5.0)], &[(100.1, 5.0)]);
+        let calibrator = calibrator().with_inventory_skew_bps(5.0);
+        // 2 units at 5 bps each: reservation 99.9, then 5 bps either side
+        let quotes = calibrator.quotes("SOL/USDT", &book, None, 2.0).unwrap();
+        assert!((quotes.reservation_price - 99.9).abs() < 1e-9);
+        assert_quotes(
+            quotes,
+            (99.850_05, Liquidity::Maker),
+            (99.949_95, Liquidity::Maker),
+        );
+        // Short, the other way
+        let quotes = calibrator.quotes("SOL/USDT", &book, None, -2.0).unwrap();
+        assert!((quotes.reservation_price - 100.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_side_that_crosses_is_priced_to_cover_the_taker_fee() {
+        let book = fixtures::book("SOL/USDT", &[(99.9, 5.0)], &[(100.1, 5.0)]);
+        let calibrator = calibrator().with_inventory_skew_bps(5.0);
+        // Reservation 99.75: the 5 bps ask at 99.799875 would hit the
+        // 99.9 bid, so it's priced at 10 + 3 = 13 bps and still crosses
+        let quotes = calibrator.quotes("SOL/USDT", &book, None, 5.0).unwrap();
+        assert_quotes(
+            quotes,
+            (99.700_125, Liquidity::Maker),
+            (99.879_675, Liquidity::Taker),
+        );
+    }
+
+    #[test]
+    fn a_maker_rebate_pays_for_edge_but_never_more() {
+        let fees = FeeModel {
+            maker_bps: -1.0,
+            ..FeeModel::default()
+        };
+        assert_eq!(
+            QuoteCalibrator::new(fees.clone(), 3.0).half_spread_bps(
+                "SOL/USDT",
+                Liquidity::Maker,
+                None
+            ),
+            2.0
+        );
+        assert_eq!(
+            QuoteCalibrator::new(fees, 0.5).half_spread_bps("SOL/USDT", Liquidity::Maker, None),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::clock::Clock;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+// Classic token bucket: holds up to `capacity` tokens, refilled continuously
+// at `refill_per_sec`. Time is passed in (milliseconds since the epoch, as
+// the bot's clock gives it) so the bucket runs on virtual time too; it
+// starts full at the first time it sees.
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill_ms: Option<u64>,
+}
+
+impl TokenBucket {
+    pub fn new(refill_per_sec: f64, capacity: f64) -> Self {
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            last_refill_ms: None,
+        }
+    }
+
+    fn refill(&mut self, now_ms: u64) {
+        let last = *self.last_refill_ms.get_or_insert(now_ms);
+        let elapsed = now_ms.saturating_sub(last) as f64 / 1_000.0;
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill_ms = Some(now_ms.max(last));
+    }
+
+    pub fn try_acquire(&mut self, now_ms: u64) -> bool {
+        self.refill(now_ms);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    // How long until the next whole token is available, rounded up to the
+    // millisecond the clock counts in
+    pub fn time_until_available(&mut self, now_ms: u64) -> Duration {
+        self.refill(now_ms);
+        if self.tokens >= 1.0 || self.refill_per_sec <= 0.0 {
+            return Duration::ZERO;
+        }
+        let ms = ((1.0 - self.tokens) / self.refill_per_sec * 1_000.0).ceil();
+        Duration::from_millis((ms as u64).max(1))
+    }
+
+    pub fn available(&mut self, now_ms: u64) -> f64 {
+        self.refill(now_ms);
+        self.tokens
+    }
+
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+}
+
+// What to do with a request when its budget is exhausted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum ExhaustedPolicy {
+    // Fail immediately with ExecutionError::RateLimited
+    Reject,
+    // Wait for a token, with at most `max_queue_depth` requests waiting
+    Queue { max_queue_depth: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub orders_per_second: f64,
+    pub order_burst: usize,
+    pub cancels_per_second: f64,
+    pub cancel_burst: usize,
+    pub on_exhausted: ExhaustedPolicy,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.orders_per_second <= 0.0 || self.cancels_per_second <= 0.0 {
+            return Err("Rate limits must be positive".to_string());
+        }
+        if self.order_burst == 0 || self.cancel_burst == 0 {
+            return Err("Rate limit bursts must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            orders_per_second: 10.0,
+            order_burst: 20,
+            cancels_per_second: 20.0,
+            cancel_burst: 40,
+            on_exhausted: ExhaustedPolicy::Queue {
+                max_queue_depth: 100,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateLimitKind {
+    Submit,
+    Cancel,
+}
+
+// Snapshot of the current budgets, for metrics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitUsage {
+    pub submit_tokens_available: f64,
+    pub submit_capacity: f64,
+    pub cancel_tokens_available: f64,
+    pub cancel_capacity: f64,
+    pub queued_submits: usize,
+    pub queued_cancels: usize,
+    pub rejected_submits: u64,
+    pub rejected_cancels: u64,
+}
+
+// One kind of request's budget and the requests waiting on it
+struct Budget {
+    bucket: Mutex<TokenBucket>,
+    // Queued requests wait their turn here; tokio's mutex is fair (FIFO)
+    queue: tokio::sync::Mutex<()>,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+impl Budget {
+    fn new(refill_per_sec: f64, capacity: usize) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket::new(refill_per_sec, capacity as f64)),
+            queue: tokio::sync::Mutex::new(()),
+            queued: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+}
+
+// A request's place in its queue, given up when the request gets its token
+// or is dropped while waiting
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+// Separate submit and cancel budgets in front of the order executor.
+// Cancels queue separately so they never wait behind submissions.
+pub struct OrderRateLimiter {
+    submit: Budget,
+    cancel: Budget,
+    policy: ExhaustedPolicy,
+}
+
+impl OrderRateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            submit: Budget::new(config.orders_per_second, config.order_burst),
+            cancel: Budget::new(config.cancels_per_second, config.cancel_burst),
+            policy: config.on_exhausted.clone(),
+        }
+    }
+
+    fn budget(&self, kind: RateLimitKind) -> &Budget {
+        match kind {
+            RateLimitKind::Submit => &self.submit,
+            RateLimitKind::Cancel => &self.cancel,
+        }
+    }
+
+    // Takes a token of `kind`, waiting on `clock` for one under the Queue
+    // policy
+    pub async fn acquire(
+        &self,
+        kind: RateLimitKind,
+        clock: &dyn Clock,
+    ) -> Result<(), RateLimitKind> {
+        let budget = self.budget(kind);
+        match self.policy {
+            ExhaustedPolicy::Reject => {
+                if budget.bucket.lock().unwrap().try_acquire(clock.now_ms()) {
+                    Ok(())
+                } else {
+                    budget.rejected.fetch_add(1, Ordering::Relaxed);
+                    Err(kind)
+                }
+            }
+            ExhaustedPolicy::Queue { max_queue_depth } => {
+                if budget.queued.fetch_add(1, Ordering::AcqRel) >= max_queue_depth {
+                    budget.queued.fetch_sub(1, Ordering::AcqRel);
+                    budget.rejected.fetch_add(1, Ordering::Relaxed);
+                    return Err(kind);
+                }
+                let _slot = QueueSlot(&budget.queued);
+
+                let _turn = budget.queue.lock().await;
+                loop {
+                    let wait = {
+                        let mut bucket = budget.bucket.lock().unwrap();
+                        if bucket.try_acquire(clock.now_ms()) {
+                            return Ok(());
+                        }
+                        bucket.time_until_available(clock.now_ms())
+                    };
+                    clock.sleep(wait).await;
+                }
+            }
+        }
+    }
+
+    pub fn usage(&self, clock: &dyn Clock) -> RateLimitUsage {
+        let mut submit = self.submit.bucket.lock().unwrap();
+        let mut cancel = self.cancel.bucket.lock().unwrap();
+        RateLimitUsage {
+            submit_tokens_available: submit.available(clock.now_ms()),
+            submit_capacity: submit.capacity(),
+            cancel_tokens_available: cancel.available(clock.now_ms()),
+            cancel_capacity: cancel.capacity(),
+            queued_submits: self.submit.queued.load(Ordering::Acquire),
+            queued_cancels: self.cancel.queued.load(Ordering::Acquire),
+            rejected_submits: self.submit.rejected.load(Ordering::Relaxed),
+            rejected_cancels: self.cancel.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::VirtualClock;
+    use futures::future::BoxFuture;
+
+    fn limiter(burst: usize, policy: ExhaustedPolicy) -> OrderRateLimiter {
+        OrderRateLimiter::new(&RateLimitConfig {
+            orders_per_second: 10.0,
+            order_burst: burst,
+            cancels_per_second: 10.0,
+            cancel_burst: burst,
+            on_exhausted: policy,
+        })
+    }
+
+    // Time stands still and nothing that sleeps ever wakes, so queued
+    // requests stay queued
+    struct StoppedClock;
+
+    impl Clock for StoppedClock {
+        fn now_ms(&self) -> u64 {
+            0
+        }
+
+        fn sleep(&self, _duration: Duration) -> BoxFuture<'static, ()> {
+            Box::pin(futures::future::pending())
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_burst_is_paced_at_the_refill_rate() {
+        let clock = VirtualClock::new(0);
+        let limiter = limiter(
+            5,
+            ExhaustedPolicy::Queue {
+                max_queue_depth: 100,
+            },
+        );
+        let mut granted_at = Vec::new();
+        for _ in 0..15 {
+            limiter
+                .acquire(RateLimitKind::Submit, &clock)
+                .await
+                .unwrap();
+            granted_at.push(clock.now_ms());
+        }
+        // The burst goes at once, then one every 100 ms
+        assert_eq!(granted_at[..5], [0; 5]);
+        for (i, &at) in granted_at[5..].iter().enumerate() {
+            assert_eq!(at, (i as u64 + 1) * 100);
+        }
+        assert_eq!(limiter.usage(&clock).queued_submits, 0);
+    }
+
+    #[tokio::test]
+    async fn exhausted_budget_rejects_under_the_reject_policy() {
+        let clock = VirtualClock::new(0);
+        let limiter = limiter(3, ExhaustedPolicy::Reject);
+        for _ in 0..3 {
+            limiter
+                .acquire(RateLimitKind::Submit, &clock)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            limiter.acquire(RateLimitKind::Submit, &clock).await,
+            Err(RateLimitKind::Submit)
+        );
+        // Cancels have their own budget
+        limiter
+            .acquire(RateLimitKind::Cancel, &clock)
+            .await
+            .unwrap();
+        let usage = limiter.usage(&clock);
+        assert_eq!(usage.rejected_submits, 1);
+        assert_eq!(usage.rejected_cancels, 0);
+
+        clock.advance(Duration::from_millis(100));
+        limiter
+            .acquire(RateLimitKind::Submit, &clock)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn queue_depth_is_per_kind_and_released_by_dropped_waiters() {
+        let clock = StoppedClock;
+        let limiter = limiter(1, ExhaustedPolicy::Queue { max_queue_depth: 2 });
+        limiter
+            .acquire(RateLimitKind::Submit, &clock)
+            .await
+            .unwrap();
+
+        let mut first = Box::pin(limiter.acquire(RateLimitKind::Submit, &clock));
+        let mut second = Box::pin(limiter.acquire(RateLimitKind::Submit, &clock));
+        assert!(futures::poll!(first.as_mut()).is_pending());
+        assert!(futures::poll!(second.as_mut()).is_pending());
+        assert_eq!(limiter.usage(&clock).queued_submits, 2);
+
+        // The submit queue is full; the cancel queue isn't
+        assert_eq!(
+            limiter.acquire(RateLimitKind::Submit, &clock).await,
+            Err(RateLimitKind::Submit)
+        );
+        limiter
+            .acquire(RateLimitKind::Cancel, &clock)
+            .await
+            .unwrap();
+        assert_eq!(limiter.usage(&clock).queued_cancels, 0);
+
+        // Waiters given up on leave the queue
+        drop(first);
+        drop(second);
+        assert_eq!(limiter.usage(&clock).queued_submits, 0);
+    }
+}
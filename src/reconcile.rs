@@ -0,0 +1,241 @@
+use crate::Order;
+use crate::symbols::assets;
+use crate::venue::{OpenOrder, VenueId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+// What to do where the venue and the bot disagree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcilePolicy {
+    // Take the venue's word: orders the bot didn't place are left working
+    // and positions and balances set to the venue's figures
+    Adopt,
+    // Cancel orders the bot didn't place; positions and balances as Adopt
+    CancelUnknown,
+    // Change nothing, halt trading and alert
+    #[default]
+    Halt,
+}
+
+impl ReconcilePolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReconcilePolicy::Adopt => "adopt",
+            ReconcilePolicy::CancelUnknown => "cancel_unknown",
+            ReconcilePolicy::Halt => "halt",
+        }
+    }
+}
+
+// Checking open orders, positions and balances against live venues
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconcileConfig {
+    pub enabled: bool,
+    pub policy: ReconcilePolicy,
+    // Quantities apart by up to this fraction of the larger one agree
+    pub tolerance_pct: f64,
+    // Seconds between passes after the one at startup; 0 for startup only
+    pub interval_secs: u64,
+}
+
+impl Default for ReconcileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            policy: ReconcilePolicy::Halt,
+            tolerance_pct: 0.001,
+            interval_secs: 60,
+        }
+    }
+}
+
+impl ReconcileConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..1.0).contains(&self.tolerance_pct) {
+            return Err("Reconcile tolerance_pct must be in [0, 1)".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Where a venue and the bot disagree
+#[derive(Debug, Clone)]
+pub enum Discrepancy {
+    // Working at the venue, but not placed by the bot
+    UnknownOrder(Order),
+    // The position the venue's holding of the symbol's base asset implies
+    Position {
+        symbol: String,
+        local: f64,
+        venue: f64,
+    },
+    // A cash balance
+    Balance {
+        asset: String,
+        local: f64,
+        venue: f64,
+    },
+}
+
+impl Discrepancy {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Discrepancy::UnknownOrder(_) => "unknown_order",
+            Discrepancy::Position { .. } => "position",
+            Discrepancy::Balance { .. } => "balance",
+        }
+    }
+
+    // The order id, symbol or asset it concerns
+    pub fn subject(&self) -> &str {
+        match self {
+            Discrepancy::UnknownOrder(order) => &order.id,
+            Discrepancy::Position { symbol, .. } => symbol,
+            Discrepancy::Balance { asset, .. } => asset,
+        }
+    }
+
+    // The bot's figure and the venue's; none for an order
+    pub fn quantities(&self) -> Option<(f64, f64)> {
+        match self {
+            Discrepancy::UnknownOrder(_) => None,
+            Discrepancy::Position { local, venue, .. }
+            | Discrepancy::Balance { local, venue, .. } => Some((*local, *venue)),
+        }
+    }
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::UnknownOrder(order) => write!(
+                f,
+                "unknown order {} ({:?} {} {} @ {:?})",
+                order.id, order.side, order.quantity, order.symbol, order.price
+            ),
+            Discrepancy::Position {
+                symbol,
+                local,
+                venue,
+            } => write!(
+                f,
+                "{} position {} locally, {} at the venue",
+                symbol, local, venue
+            ),
+            Discrepancy::Balance {
+                asset,
+                local,
+                venue,
+            } => write!(
+                f,
+                "{} balance {} locally, {} at the venue",
+                asset, local, venue
+            ),
+        }
+    }
+}
+
+// What was done about a discrepancy
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    // The bot took the venue's state as its own
+    Adopted,
+    Cancelled,
+    // Left as it was, with trading halted
+    Halted,
+    // The action failed; the next pass finds it again
+    Failed(String),
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resolution::Adopted => f.write_str("adopted"),
+            Resolution::Cancelled => f.write_str("cancelled"),
+            Resolution::Halted => f.write_str("halted"),
+            Resolution::Failed(e) => write!(f, "failed: {}", e),
+        }
+    }
+}
+
+// What one reconciliation pass found and did
+#[derive(Debug, Clone, Default)]
+pub struct ReconcileReport {
+    // Venues compared
+    pub venues: Vec<VenueId>,
+    pub discrepancies: Vec<(VenueId, Discrepancy, Resolution)>,
+    // Venues that couldn't be queried
+    pub errors: Vec<(VenueId, String)>,
+    pub halted: bool,
+}
+
+// "SOL/USDT" -> "SOL"
+fn base_asset(symbol: &str) -> &str {
+    assets(symbol).map_or(symbol, |(base, _)| base)
+}
+
+/// Compares a venue's report with the bot's books, given the tolerance.
+/// Open orders count as unknown unless placed through the connection or
+/// in `adopted` (by client order id). `positions` are the bot's on the
+/// venue, flat ones included, each held against the venue's balance of
+/// its base asset: symbols sharing one are compared by their sum, the
+/// difference put on the first. `cash` is the bot's cash by asset where
+/// the venue's balances are the ones to check it against.
+pub fn diff(
+    open_orders: &[OpenOrder],
+    adopted: &HashSet<String>,
+    positions: &BTreeMap<String, f64>,
+    cash: Option<&HashMap<String, f64>>,
+    balances: &HashMap<String, f64>,
+    tolerance_pct: f64,
+) -> Vec<Discrepancy> {
+    let agree = |local: f64, venue: f64| {
+        (local - venue).abs() <= tolerance_pct * local.abs().max(venue.abs()) + f64::EPSILON
+    };
+    let mut discrepancies: Vec<Discrepancy> = open_orders
+        .iter()
+        .filter(|open| !open.known && !adopted.contains(&open.state.order.client_order_id))
+        .map(|open| Discrepancy::UnknownOrder(open.state.order.clone()))
+        .collect();
+
+    let mut by_asset: BTreeMap<&str, Vec<(&String, f64)>> = BTreeMap::new();
+    for (symbol, quantity) in positions {
+        by_asset
+            .entry(base_asset(symbol))
+            .or_default()
+            .push((symbol, *quantity));
+    }
+    for (asset, held) in by_asset {
+        // Cash is checked as a balance
+        if cash.is_some_and(|cash| cash.contains_key(asset)) {
+            continue;
+        }
+        let local: f64 = held.iter().map(|(_, quantity)| quantity).sum();
+        let venue = balances.get(asset).copied().unwrap_or(0.0);
+        if !agree(local, venue) {
+            let (symbol, quantity) = held[0];
+            discrepancies.push(Discrepancy::Position {
+                symbol: symbol.clone(),
+                local: quantity,
+                venue: quantity + venue - local,
+            });
+        }
+    }
+
+    let mut cash: Vec<(&String, &f64)> = cash.into_iter().flatten().collect();
+    cash.sort_by(|a, b| a.0.cmp(b.0));
+    for (asset, local) in cash {
+        let venue = balances.get(asset).copied().unwrap_or(0.0);
+        if !agree(*local, venue) {
+            discrepancies.push(Discrepancy::Balance {
+                asset: asset.clone(),
+                local: *local,
+                venue,
+            });
+        }
+    }
+    discrepancies
+}
@@ -0,0 +1,353 @@
+use crate::clock::{Clock, SystemClock};
+use crate::{MarketData, OrderBook, Price};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Capture of everything the market data feed hands the bot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub enabled: bool,
+    pub directory: PathBuf,
+    // A symbol's file is closed and a new one started past this size
+    pub max_file_bytes: u64,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: PathBuf::from("recordings"),
+            max_file_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl RecordingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.max_file_bytes == 0 {
+            return Err("Recording max_file_bytes must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MarketEvent {
+    Price(Price),
+    Book(OrderBook),
+}
+
+impl MarketEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            MarketEvent::Price(price) => &price.symbol,
+            MarketEvent::Book(book) => &book.symbol,
+        }
+    }
+}
+
+// One line of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    // When the bot received it, in milliseconds since the epoch
+    pub received_at_ms: u64,
+    #[serde(flatten)]
+    pub event: MarketEvent,
+}
+
+struct RecordingFile {
+    file: File,
+    bytes: u64,
+}
+
+/// Appends events as JSON lines to one file per symbol under the recording
+/// directory, named `<symbol>-<opened at ms>.jsonl` ("/" becomes "-"), and
+/// rolls over to a new file once `max_file_bytes` is reached. Every line is
+/// written straight through, so a crash loses at most the event in flight.
+/// Receive times are read off the bot's clock, so a replay runs on the same
+/// timeline as the run that was recorded.
+pub struct MarketRecorder {
+    directory: PathBuf,
+    max_file_bytes: u64,
+    files: std::sync::Mutex<HashMap<String, RecordingFile>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MarketRecorder {
+    pub fn new(config: &RecordingConfig) -> Result<Self, String> {
+        std::fs::create_dir_all(&config.directory).map_err(|e| {
+            format!(
+                "Failed to create recording directory {}: {}",
+                config.directory.display(),
+                e
+            )
+        })?;
+        Ok(Self {
+            directory: config.directory.clone(),
+            max_file_bytes: config.max_file_bytes,
+            files: std::sync::Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn record(&self, event: MarketEvent) {
+        let symbol = event.symbol().to_string();
+        let event = RecordedEvent {
+            received_at_ms: self.clock.now_ms(),
+            event,
+        };
+        let mut line = match serde_json::to_string(&event) {
+            Ok(line) => line,
+            Err(e) => {
+                println!("Failed to encode recorded event: {}", e);
+                return;
+            }
+        };
+        line.push('\n');
+
+        let mut files = self.files.lock().unwrap();
+        if files
+            .get(&symbol)
+            .is_some_and(|f| f.bytes >= self.max_file_bytes)
+        {
+            files.remove(&symbol);
+        }
+        if !files.contains_key(&symbol) {
+            match self.open(&symbol, event.received_at_ms) {
+                Ok(file) => {
+                    files.insert(symbol.clone(), file);
+                }
+                Err(e) => {
+                    println!("Failed to open recording for {}: {}", symbol, e);
+                    return;
+                }
+            }
+        }
+        let file = files.get_mut(&symbol).expect("opened above");
+        match file.file.write_all(line.as_bytes()) {
+            Ok(()) => file.bytes += line.len() as u64,
+            Err(e) => println!("Failed to record {} event: {}", symbol, e),
+        }
+    }
+
+    fn open(&self, symbol: &str, now_ms: u64) -> std::io::Result<RecordingFile> {
+        let path = self
+            .directory
+            .join(format!("{}-{}.jsonl", symbol.replace('/', "-"), now_ms));
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let bytes = file.metadata()?.len();
+        Ok(RecordingFile { file, bytes })
+    }
+}
+
+// Wraps a feed and records everything it returns
+pub struct RecordingFeed {
+    inner: Arc<dyn MarketData>,
+    recorder: MarketRecorder,
+}
+
+impl RecordingFeed {
+    pub fn new(inner: Arc<dyn MarketData>, recorder: MarketRecorder) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+impl MarketData for RecordingFeed {
+    fn get_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Price>> {
+        Box::pin(async move {
+            let price = self.inner.get_price(symbol).await?;
+            self.recorder.record(MarketEvent::Price(price.clone()));
+            Some(price)
+        })
+    }
+
+    fn get_orderbook<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<OrderBook>> {
+        Box::pin(async move {
+            let book = self.inner.get_orderbook(symbol).await?;
+            self.recorder.record(MarketEvent::Book(book.clone()));
+            Some(book)
+        })
+    }
+}
+
+/// Reads every `.jsonl` recording in `directory` (or a single file) and
+/// returns the events ordered by receive time
+pub fn load_recording(path: &Path) -> Result<Vec<RecordedEvent>, String> {
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .map_err(|e| format!("Failed to read recording {}: {}", path.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut events = Vec::new();
+    for file in files {
+        let reader = BufReader::new(
+            File::open(&file)
+                .map_err(|e| format!("Failed to open recording {}: {}", file.display(), e))?,
+        );
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(event) => events.push(event),
+                // A crash mid-write can leave a truncated last line
+                Err(e) => println!("Skipping {} line {}: {}", file.display(), index + 1, e),
+            }
+        }
+    }
+    // Stable, so events received in the same millisecond keep file order
+    events.sort_by_key(|e: &RecordedEvent| e.received_at_ms);
+    Ok(events)
+}
+
+// How a replay paces itself
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    // Each event is released when as much time has passed since the replay
+    // started as had passed since the recording started
+    RealTime,
+    AsFastAsPossible,
+}
+
+struct ReplayState {
+    // Per symbol, in receive order
+    prices: HashMap<String, VecDeque<RecordedEvent>>,
+    books: HashMap<String, VecDeque<RecordedEvent>>,
+    // Receive time of the last price handed out per symbol; books up to
+    // here are current
+    cursor: HashMap<String, u64>,
+    started: Option<Instant>,
+}
+
+/// Plays a recording back through the MarketData interface. Each symbol's
+/// prices come out in order, one per `get_price`; `get_orderbook` returns
+/// the newest book received no later than that symbol's last price. Returns
+/// None once a symbol's prices run out.
+pub struct ReplayFeed {
+    speed: ReplaySpeed,
+    first_received_ms: u64,
+    state: std::sync::Mutex<ReplayState>,
+}
+
+impl ReplayFeed {
+    pub fn new(events: Vec<RecordedEvent>, speed: ReplaySpeed) -> Self {
+        let mut prices: HashMap<String, VecDeque<RecordedEvent>> = HashMap::new();
+        let mut books: HashMap<String, VecDeque<RecordedEvent>> = HashMap::new();
+        let first_received_ms = events.first().map_or(0, |e| e.received_at_ms);
+        for event in events {
+            let queue = match event.event {
+                MarketEvent::Price(_) => &mut prices,
+                MarketEvent::Book(_) => &mut books,
+            };
+            queue
+                .entry(event.event.symbol().to_string())
+                .or_default()
+                .push_back(event);
+        }
+        Self {
+            first_received_ms,
+            speed,
+            state: std::sync::Mutex::new(ReplayState {
+                prices,
+                books,
+                cursor: HashMap::new(),
+                started: None,
+            }),
+        }
+    }
+
+    pub fn load(path: &Path, speed: ReplaySpeed) -> Result<Self, String> {
+        Ok(Self::new(load_recording(path)?, speed))
+    }
+
+    // Symbols with at least one recorded price, sorted
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.state.lock().unwrap().prices.keys().cloned().collect();
+        symbols.sort();
+        symbols
+    }
+
+    // True once every recorded price has been handed out
+    pub fn is_finished(&self) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .prices
+            .values()
+            .all(VecDeque::is_empty)
+    }
+
+    // How long to hold an event received at `received_at_ms`
+    fn delay(&self, received_at_ms: u64) -> Duration {
+        if self.speed == ReplaySpeed::AsFastAsPossible {
+            return Duration::ZERO;
+        }
+        let mut state = self.state.lock().unwrap();
+        let started = *state.started.get_or_insert_with(Instant::now);
+        let due = Duration::from_millis(received_at_ms - self.first_received_ms);
+        due.saturating_sub(started.elapsed())
+    }
+}
+
+impl MarketData for ReplayFeed {
+    fn get_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Price>> {
+        Box::pin(async move {
+            let received_at_ms = self
+                .state
+                .lock()
+                .unwrap()
+                .prices
+                .get(symbol)?
+                .front()?
+                .received_at_ms;
+            tokio::time::sleep(self.delay(received_at_ms)).await;
+
+            let mut state = self.state.lock().unwrap();
+            let event = state.prices.get_mut(symbol)?.pop_front()?;
+            state
+                .cursor
+                .insert(symbol.to_string(), event.received_at_ms);
+            match event.event {
+                MarketEvent::Price(price) => Some(price),
+                MarketEvent::Book(_) => None,
+            }
+        })
+    }
+
+    fn get_orderbook<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<OrderBook>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            let cursor = *state.cursor.get(symbol)?;
+            let books = state.books.get_mut(symbol)?;
+            // Drop books superseded by a newer one that is also current
+            while books.len() > 1 && books[1].received_at_ms <= cursor {
+                books.pop_front();
+            }
+            match &books.front()?.event {
+                MarketEvent::Book(book) if books[0].received_at_ms <= cursor => Some(book.clone()),
+                _ => None,
+            }
+        })
+    }
+}
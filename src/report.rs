@@ -0,0 +1,500 @@
+use crate::history::civil_from_days;
+use crate::performance::UNATTRIBUTED;
+use crate::session::SessionSummary;
+use crate::symbols::assets;
+use crate::venue::VenueId;
+use crate::{Fill, PositionSide};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+// The daily report, logged and written out on rollover and shutdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportConfig {
+    pub enabled: bool,
+    // Directory reports are written to, as report-YYYY-MM-DD.<format>
+    pub dir: PathBuf,
+    pub formats: Vec<ReportFormat>,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: PathBuf::from("reports"),
+            formats: vec![ReportFormat::Csv, ReportFormat::Json],
+        }
+    }
+}
+
+impl ReportConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.dir.as_os_str().is_empty() {
+            return Err("Report dir can't be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+// A fill and the PnL booking it realized, net of its fee, in its quote
+// asset
+#[derive(Debug, Clone)]
+pub struct BookedFill {
+    pub fill: Fill,
+    pub realized_pnl: f64,
+    // Reduced (or flipped) a position, making it a trade won or lost
+    pub closing: bool,
+    // One of the quote asset in the reporting currency, when booked
+    pub rate: f64,
+}
+
+impl BookedFill {
+    // The asset the fill is priced in
+    fn quote(&self) -> &str {
+        assets(self.fill.symbol.as_str()).map_or(self.fill.symbol.as_str(), |(_, quote)| quote)
+    }
+}
+
+/// The day's fills as booked, in order, and the funding its perpetual
+/// futures paid or received. RiskManager keeps it next to its daily PnL
+/// and rolls both over together, so the two always agree.
+#[derive(Debug, Clone, Default)]
+pub struct Blotter {
+    // Days since the epoch (UTC)
+    day: u64,
+    fills: Vec<BookedFill>,
+    funding: f64,
+}
+
+impl Blotter {
+    pub fn new(day: u64) -> Self {
+        Self {
+            day,
+            fills: Vec::new(),
+            funding: 0.0,
+        }
+    }
+
+    pub fn day(&self) -> u64 {
+        self.day
+    }
+
+    pub fn fills(&self) -> &[BookedFill] {
+        &self.fills
+    }
+
+    pub fn record(&mut self, fill: BookedFill) {
+        self.fills.push(fill);
+    }
+
+    // Funding received so far; negative when paid
+    pub fn funding(&self) -> f64 {
+        self.funding
+    }
+
+    pub fn record_funding(&mut self, amount: f64) {
+        self.funding += amount;
+    }
+}
+
+// Fills, volume, fees and realized PnL of a symbol or strategy
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PnlRow {
+    pub name: String,
+    pub fills: usize,
+    // Closing fills
+    pub trades: usize,
+    pub volume: f64,
+    pub notional: f64,
+    pub fees: f64,
+    // Net of fees
+    pub realized_pnl: f64,
+    // The best and worst closing fills' realized PnL
+    pub largest_win: Option<f64>,
+    pub largest_loss: Option<f64>,
+}
+
+impl PnlRow {
+    // Adds the fill, its amounts times `rate`
+    fn add(&mut self, booked: &BookedFill, rate: f64) {
+        self.fills += 1;
+        self.volume += booked.fill.quantity;
+        self.notional += booked.fill.quantity * booked.fill.price * rate;
+        self.fees += booked.fill.fee * rate;
+        self.realized_pnl += booked.realized_pnl * rate;
+        if !booked.closing {
+            return;
+        }
+        self.trades += 1;
+        let pnl = booked.realized_pnl * rate;
+        if pnl > 0.0 {
+            self.largest_win = Some(self.largest_win.map_or(pnl, |win| win.max(pnl)));
+        } else if pnl < 0.0 {
+            self.largest_loss = Some(self.largest_loss.map_or(pnl, |loss| loss.min(pnl)));
+        }
+    }
+}
+
+// An open position at report time; prices and PnL in its quote asset
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionRow {
+    pub venue: VenueId,
+    pub symbol: String,
+    pub side: PositionSide,
+    pub quantity: f64,
+    pub avg_price: f64,
+    pub mark: f64,
+    pub unrealized_pnl: f64,
+    // One of the quote asset in the reporting currency
+    pub rate: f64,
+}
+
+// How much of a risk limit is taken up
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LimitRow {
+    pub limit: String,
+    pub used: f64,
+    pub max: f64,
+    // used / max
+    pub utilization: f64,
+}
+
+impl PositionRow {
+    // "paper SOL/USDT", with the leg for a hedge-mode one
+    fn name(&self, separator: char) -> String {
+        match self.side {
+            PositionSide::Both => format!("{}{}{}", self.venue, separator, self.symbol),
+            side => format!(
+                "{}{}{}{}{}",
+                self.venue, separator, self.symbol, separator, side
+            ),
+        }
+    }
+}
+
+impl LimitRow {
+    pub fn new(limit: impl Into<String>, used: f64, max: f64) -> Self {
+        Self {
+            limit: limit.into(),
+            used,
+            max,
+            utilization: if max > 0.0 { used / max } else { 0.0 },
+        }
+    }
+}
+
+/// One trading day's trading: realized PnL by symbol and strategy (each fill's
+/// PnL goes to the strategy on the fill), the positions still open and the
+/// risk limits' utilization. `total` sums every fill of the day, so its
+/// realized PnL plus `funding_pnl` is the day's PnL as RiskManager booked it.
+/// Amounts are in the reporting currency, each fill's at the rate it was
+/// booked at, except `quotes` and the positions, which are in their quote
+/// assets as booked.
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyReport {
+    pub date: String,
+    // Seconds
+    pub generated_at: u64,
+    // The reporting currency; None for a report rebuilt from the journal,
+    // which takes every quote asset 1:1
+    pub currency: Option<String>,
+    // Quote assets taken 1:1 for lack of a rate
+    pub unconverted: Vec<String>,
+    pub total: PnlRow,
+    pub symbols: Vec<PnlRow>,
+    pub strategies: Vec<PnlRow>,
+    // By quote asset, unconverted
+    pub quotes: Vec<PnlRow>,
+    pub positions: Vec<PositionRow>,
+    pub unrealized_pnl: f64,
+    pub limits: Vec<LimitRow>,
+    // Funding received on perpetual futures; negative when paid
+    pub funding_pnl: f64,
+    // RiskManager's daily PnL, which total.realized_pnl plus funding_pnl
+    // ties out with;
+    // None for a report rebuilt from the journal
+    pub daily_pnl: Option<f64>,
+    // The session's counters as it rolled over; None for a day still open
+    pub session: Option<SessionSummary>,
+}
+
+// "2024-01-15" for the day (since the epoch)
+pub fn date(day: u64) -> String {
+    let (year, month, day) = civil_from_days(day as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+impl DailyReport {
+    pub fn new(
+        day: u64,
+        generated_at: u64,
+        fills: &[BookedFill],
+        positions: Vec<PositionRow>,
+        limits: Vec<LimitRow>,
+    ) -> Self {
+        let mut total = PnlRow {
+            name: "total".to_string(),
+            ..PnlRow::default()
+        };
+        let mut symbols: BTreeMap<&str, PnlRow> = BTreeMap::new();
+        let mut strategies: BTreeMap<&str, PnlRow> = BTreeMap::new();
+        let mut quotes: BTreeMap<&str, PnlRow> = BTreeMap::new();
+        let row = |name: &str| PnlRow {
+            name: name.to_string(),
+            ..PnlRow::default()
+        };
+        for booked in fills {
+            total.add(booked, booked.rate);
+            let symbol = booked.fill.symbol.as_str();
+            symbols
+                .entry(symbol)
+                .or_insert_with(|| row(symbol))
+                .add(booked, booked.rate);
+            let strategy = booked.fill.strategy.as_deref().unwrap_or(UNATTRIBUTED);
+            strategies
+                .entry(strategy)
+                .or_insert_with(|| row(strategy))
+                .add(booked, booked.rate);
+            let quote = booked.quote();
+            quotes
+                .entry(quote)
+                .or_insert_with(|| row(quote))
+                .add(booked, 1.0);
+        }
+        Self {
+            date: date(day),
+            generated_at,
+            currency: None,
+            unconverted: Vec::new(),
+            total,
+            symbols: symbols.into_values().collect(),
+            strategies: strategies.into_values().collect(),
+            quotes: quotes.into_values().collect(),
+            unrealized_pnl: positions.iter().map(|p| p.unrealized_pnl * p.rate).sum(),
+            positions,
+            limits,
+            funding_pnl: 0.0,
+            daily_pnl: None,
+            session: None,
+        }
+    }
+
+    pub fn with_funding_pnl(mut self, funding_pnl: f64) -> Self {
+        self.funding_pnl = funding_pnl;
+        self
+    }
+
+    pub fn with_daily_pnl(mut self, daily_pnl: f64) -> Self {
+        self.daily_pnl = Some(daily_pnl);
+        self
+    }
+
+    pub fn with_session(mut self, summary: SessionSummary) -> Self {
+        self.session = Some(summary);
+        self
+    }
+
+    // The currency the amounts were converted into, and the quote assets
+    // that had no rate to it
+    pub fn with_currency(mut self, currency: &str, unconverted: Vec<String>) -> Self {
+        self.currency = Some(currency.to_string());
+        self.unconverted = unconverted;
+        self
+    }
+
+    // Whether any fill was in a quote asset other than the reporting
+    // currency, so `quotes` differs from the converted figures
+    fn mixed_quotes(&self) -> bool {
+        self.quotes
+            .iter()
+            .any(|row| Some(&row.name) != self.currency.as_ref())
+    }
+
+    // The report as fixed-width tables, for the log
+    pub fn to_text(&self) -> String {
+        let mut text = match &self.currency {
+            Some(currency) => format!("📋 Daily report {} ({})\n", self.date, currency),
+            None => format!("📋 Daily report {}\n", self.date),
+        };
+        let pnl_table = |text: &mut String, title: &str, rows: &[PnlRow]| {
+            let _ = writeln!(
+                text,
+                "{:<24} {:>6} {:>6} {:>12} {:>10} {:>12} {:>10} {:>10}",
+                title, "fills", "trades", "notional", "fees", "realized", "best", "worst"
+            );
+            for row in rows {
+                let _ = writeln!(
+                    text,
+                    "{:<24} {:>6} {:>6} {:>12.2} {:>10.4} {:>12.4} {:>10} {:>10}",
+                    row.name,
+                    row.fills,
+                    row.trades,
+                    row.notional,
+                    row.fees,
+                    row.realized_pnl,
+                    row.largest_win
+                        .map_or("-".to_string(), |w| format!("{:.4}", w)),
+                    row.largest_loss
+                        .map_or("-".to_string(), |l| format!("{:.4}", l)),
+                );
+            }
+        };
+        pnl_table(&mut text, "symbol", &self.symbols);
+        pnl_table(&mut text, "strategy", &self.strategies);
+        pnl_table(&mut text, "", std::slice::from_ref(&self.total));
+        if self.mixed_quotes() {
+            pnl_table(&mut text, "quote (unconverted)", &self.quotes);
+        }
+        if !self.unconverted.is_empty() {
+            let _ = writeln!(
+                text,
+                "⚠️ No rate for {}; taken 1:1",
+                self.unconverted.join(", ")
+            );
+        }
+        if self.funding_pnl != 0.0 {
+            let _ = writeln!(text, "Funding PnL: {:.4}", self.funding_pnl);
+        }
+        if let Some(daily_pnl) = self.daily_pnl {
+            let _ = writeln!(text, "Daily PnL (risk manager): {:.4}", daily_pnl);
+        }
+        if let Some(summary) = &self.session {
+            let _ = writeln!(
+                text,
+                "Session closed at equity {:.4}: peak {:.4}, max drawdown {:.2}%",
+                summary.closing_equity,
+                summary.stats.peak_equity,
+                summary.stats.max_drawdown_pct * 100.0
+            );
+        }
+        if !self.positions.is_empty() {
+            let _ = writeln!(
+                text,
+                "{:<24} {:>12} {:>12} {:>12} {:>12}",
+                "position", "quantity", "avg price", "mark", "unrealized"
+            );
+            for p in &self.positions {
+                let _ = writeln!(
+                    text,
+                    "{:<24} {:>12.4} {:>12.4} {:>12.4} {:>12.4}",
+                    p.name(' '),
+                    p.quantity,
+                    p.avg_price,
+                    p.mark,
+                    p.unrealized_pnl
+                );
+            }
+            let _ = writeln!(text, "Unrealized PnL: {:.4}", self.unrealized_pnl);
+        }
+        if !self.limits.is_empty() {
+            let _ = writeln!(
+                text,
+                "{:<24} {:>12} {:>12} {:>8}",
+                "limit", "used", "max", "used %"
+            );
+            for l in &self.limits {
+                let _ = writeln!(
+                    text,
+                    "{:<24} {:>12.4} {:>12.4} {:>7.1}%",
+                    l.limit,
+                    l.used,
+                    l.max,
+                    l.utilization * 100.0
+                );
+            }
+        }
+        text
+    }
+
+    /// The report as CSV: one table, each row naming its section
+    /// (`symbol`, `strategy`, `total`, `quote`, `funding`, `unconverted`,
+    /// `position` or `limit`). Columns a section doesn't have are left
+    /// empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "section,name,fills,trades,volume,notional,fees,realized_pnl,largest_win,\
+             largest_loss,quantity,avg_price,mark,unrealized_pnl,used,max,utilization\n",
+        );
+        let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        let sections = [
+            ("symbol", &self.symbols[..]),
+            ("strategy", &self.strategies[..]),
+            ("total", std::slice::from_ref(&self.total)),
+            ("quote", &self.quotes[..]),
+        ];
+        for (section, rows) in sections {
+            for row in rows {
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{},{},{},{},{},{},,,,,,,",
+                    section,
+                    row.name,
+                    row.fills,
+                    row.trades,
+                    row.volume,
+                    row.notional,
+                    row.fees,
+                    row.realized_pnl,
+                    optional(row.largest_win),
+                    optional(row.largest_loss)
+                );
+            }
+        }
+        if self.funding_pnl != 0.0 {
+            let _ = writeln!(csv, "funding,total,,,,,,{},,,,,,,,,", self.funding_pnl);
+        }
+        for asset in &self.unconverted {
+            let _ = writeln!(csv, "unconverted,{},,,,,,,,,,,,,,,", asset);
+        }
+        for p in &self.positions {
+            let _ = writeln!(
+                csv,
+                "position,{},,,,,,,,,{},{},{},{},,,",
+                p.name(':'),
+                p.quantity,
+                p.avg_price,
+                p.mark,
+                p.unrealized_pnl
+            );
+        }
+        for l in &self.limits {
+            let _ = writeln!(
+                csv,
+                "limit,{},,,,,,,,,,,,,{},{},{}",
+                l.limit, l.used, l.max, l.utilization
+            );
+        }
+        csv
+    }
+
+    // Writes the report to `dir` in each format; returns the files written
+    pub fn write(&self, dir: &Path, formats: &[ReportFormat]) -> Result<Vec<PathBuf>, String> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let mut written = Vec::new();
+        for format in formats {
+            let (extension, contents) = match format {
+                ReportFormat::Csv => ("csv", self.to_csv()),
+                ReportFormat::Json => (
+                    "json",
+                    serde_json::to_string_pretty(self)
+                        .map_err(|e| format!("Failed to serialize report: {}", e))?,
+                ),
+            };
+            let path = dir.join(format!("report-{}.{}", self.date, extension));
+            std::fs::write(&path, contents)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
@@ -0,0 +1,424 @@
+use crate::clock::{Clock, SystemClock};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// How urgent a REST request is. As the weight budget runs low the least
+// urgent are held back first, and when a pause ends the most urgent go
+// first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    MarketData,
+    Orders,
+    Cancels,
+}
+
+impl RequestPriority {
+    const ALL: [RequestPriority; 3] = [
+        RequestPriority::MarketData,
+        RequestPriority::Orders,
+        RequestPriority::Cancels,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// A venue's request weight budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestLimitConfig {
+    // Weight the venue allows per window
+    pub weight_limit: u32,
+    pub window_ms: u64,
+    // Weight of each endpoint by path; others cost default_weight
+    pub endpoint_weights: BTreeMap<String, u32>,
+    pub default_weight: u32,
+    // Fractions of weight_limit market data and order entry may use;
+    // cancels may use all of it
+    pub market_data_share: f64,
+    pub orders_share: f64,
+    // Response header with the venue's count of weight used this window
+    pub used_weight_header: String,
+    // 429s retried (after their Retry-After) before the response is returned
+    pub max_retries: u32,
+}
+
+impl Default for RestLimitConfig {
+    fn default() -> Self {
+        // Binance spot's and USDⓈ-M futures' published weights
+        let endpoint_weights = [
+            ("/api/v3/order", 1),
+            ("/api/v3/time", 1),
+            ("/api/v3/userDataStream", 2),
+            ("/api/v3/klines", 2),
+            ("/api/v3/ticker/24hr", 2),
+            ("/api/v3/aggTrades", 4),
+            ("/api/v3/depth", 5),
+            ("/api/v3/openOrders", 6),
+            ("/api/v3/account", 20),
+            ("/api/v3/exchangeInfo", 20),
+            ("/fapi/v1/order", 1),
+            ("/fapi/v1/time", 1),
+            ("/fapi/v1/listenKey", 1),
+            ("/fapi/v1/ticker/24hr", 1),
+            ("/fapi/v1/premiumIndex", 1),
+            ("/fapi/v1/exchangeInfo", 1),
+            ("/fapi/v1/openOrders", 1),
+            ("/fapi/v1/depth", 2),
+            ("/fapi/v2/account", 5),
+        ]
+        .into_iter()
+        .map(|(path, weight)| (path.to_string(), weight))
+        .collect();
+        Self {
+            weight_limit: 6_000,
+            window_ms: 60_000,
+            endpoint_weights,
+            default_weight: 1,
+            market_data_share: 0.7,
+            orders_share: 0.9,
+            used_weight_header: "x-mbx-used-weight-1m".to_string(),
+            max_retries: 2,
+        }
+    }
+}
+
+impl RestLimitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.weight_limit == 0 || self.window_ms == 0 {
+            return Err("REST weight_limit and window_ms must be positive".to_string());
+        }
+        let share = |s: f64| s > 0.0 && s <= 1.0;
+        if !share(self.market_data_share) || !share(self.orders_share) {
+            return Err("REST weight shares must be in (0, 1]".to_string());
+        }
+        if self.market_data_share > self.orders_share {
+            return Err("REST market_data_share can't exceed orders_share".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn weight(&self, path: &str) -> u32 {
+        self.endpoint_weights
+            .get(path)
+            .copied()
+            .unwrap_or(self.default_weight)
+    }
+
+    fn share(&self, priority: RequestPriority) -> f64 {
+        match priority {
+            RequestPriority::MarketData => self.market_data_share,
+            RequestPriority::Orders => self.orders_share,
+            RequestPriority::Cancels => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct WeightLedger {
+    // (sent at, weight) of each request still inside the window
+    sent: VecDeque<(u64, u32)>,
+    // The venue's own count and when it was reported; it holds until the
+    // venue's (minute-aligned) window rolls over
+    reported: Option<(u32, u64)>,
+    // Set by a 429 or 418: nothing is sent before this
+    paused_until: u64,
+    // Requests waiting, per priority
+    waiting: [usize; 3],
+}
+
+impl WeightLedger {
+    fn used(&mut self, now: u64, window_ms: u64) -> u32 {
+        while self
+            .sent
+            .front()
+            .is_some_and(|&(at, _)| at + window_ms <= now)
+        {
+            self.sent.pop_front();
+        }
+        let local: u32 = self.sent.iter().map(|&(_, weight)| weight).sum();
+        let reported = self
+            .reported
+            .filter(|&(_, at)| at / window_ms == now / window_ms)
+            .map_or(0, |(used, _)| used);
+        local.max(reported)
+    }
+
+    // Until the budget next frees up: the oldest request leaving the
+    // window or the venue's window rolling over
+    fn next_change(&self, now: u64, window_ms: u64) -> u64 {
+        let oldest = self.sent.front().map(|&(at, _)| at + window_ms);
+        let rollover = self.reported.map(|_| (now / window_ms + 1) * window_ms);
+        oldest.into_iter().chain(rollover).min().unwrap_or(now + 1)
+    }
+}
+
+// Counts a request as waiting for as long as it lives, so one abandoned
+// mid-wait stops outranking the others
+struct Waiting<'a> {
+    ledger: &'a Mutex<WeightLedger>,
+    priority: RequestPriority,
+}
+
+impl<'a> Waiting<'a> {
+    fn new(ledger: &'a Mutex<WeightLedger>, priority: RequestPriority) -> Self {
+        ledger.lock().unwrap().waiting[priority.index()] += 1;
+        Self { ledger, priority }
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.ledger.lock().unwrap().waiting[self.priority.index()] -= 1;
+    }
+}
+
+/// A reqwest client that keeps a venue's request weight under its limit.
+/// Each request's weight comes from the config by endpoint; used weight is
+/// the larger of what was sent in the last window and what the venue last
+/// reported in its used-weight header. Requests that would take their
+/// priority past its share of the limit wait, as does everything after a
+/// 429 or 418 until its Retry-After is up. 429s are then retried.
+pub struct RateLimitedClient {
+    client: reqwest::Client,
+    config: RestLimitConfig,
+    ledger: Mutex<WeightLedger>,
+    clock: Arc<dyn Clock>,
+    utilization: Option<prometheus::Gauge>,
+}
+
+impl RateLimitedClient {
+    pub fn new(client: reqwest::Client, config: RestLimitConfig) -> Self {
+        Self {
+            client,
+            config,
+            ledger: Mutex::new(WeightLedger::default()),
+            clock: Arc::new(SystemClock),
+            utilization: None,
+        }
+    }
+
+    // Times the window and paces the waits
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Kept at the fraction of the weight limit in use
+    pub fn with_utilization_gauge(mut self, gauge: prometheus::Gauge) -> Self {
+        self.utilization = Some(gauge);
+        self
+    }
+
+    pub fn config(&self) -> &RestLimitConfig {
+        &self.config
+    }
+
+    // Fraction of the weight limit used in the current window
+    pub fn utilization(&self) -> f64 {
+        let now = self.clock.now_ms();
+        let used = self.ledger.lock().unwrap().used(now, self.config.window_ms);
+        used as f64 / self.config.weight_limit as f64
+    }
+
+    fn report_utilization(&self) {
+        if let Some(gauge) = &self.utilization {
+            gauge.set(self.utilization());
+        }
+    }
+
+    /// Sends the request `build` makes once the weight budget allows it,
+    /// retrying it on a 429 once the venue's Retry-After is up. The last
+    /// response is returned whatever its status.
+    pub async fn send(
+        &self,
+        priority: RequestPriority,
+        path: &str,
+        build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let weight = self.config.weight(path);
+        let mut retries = 0;
+        loop {
+            self.acquire(priority, weight).await;
+            let response = build(&self.client).send().await?;
+            let status = response.status().as_u16();
+            self.observe(&response);
+            if status == 429 && retries < self.config.max_retries {
+                retries += 1;
+                continue;
+            }
+            return Ok(response);
+        }
+    }
+
+    // Waits until `weight` fits within the priority's share and no more
+    // urgent request is waiting, then books it
+    async fn acquire(&self, priority: RequestPriority, weight: u32) {
+        let cap = (self.config.weight_limit as f64 * self.config.share(priority)) as u32;
+        let _waiting = Waiting::new(&self.ledger, priority);
+        loop {
+            let wait_until = {
+                let now = self.clock.now_ms();
+                let mut ledger = self.ledger.lock().unwrap();
+                let used = ledger.used(now, self.config.window_ms);
+                let outranked = RequestPriority::ALL
+                    .iter()
+                    .any(|&p| p > priority && ledger.waiting[p.index()] > 0);
+                if now < ledger.paused_until {
+                    ledger.paused_until
+                } else if used + weight > cap && used > 0 {
+                    ledger.next_change(now, self.config.window_ms)
+                } else if outranked {
+                    now + 1
+                } else {
+                    ledger.sent.push_back((now, weight));
+                    break;
+                }
+            };
+            let wait = wait_until.saturating_sub(self.clock.now_ms()).max(1);
+            self.clock.sleep(Duration::from_millis(wait)).await;
+        }
+        self.report_utilization();
+    }
+
+    // Takes in the venue's used weight and, on a 429 or 418, its Retry-After
+    fn observe(&self, response: &reqwest::Response) {
+        let now = self.clock.now_ms();
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        let status = response.status().as_u16();
+        {
+            let mut ledger = self.ledger.lock().unwrap();
+            if let Some(used) = header(&self.config.used_weight_header) {
+                ledger.reported = Some((used as u32, now));
+            }
+            // 429 is a warning, 418 an IP ban for ignoring it
+            if status == 429 || status == 418 {
+                let retry_after = header(reqwest::header::RETRY_AFTER.as_str()).unwrap_or(1);
+                ledger.paused_until = ledger.paused_until.max(now + retry_after * 1_000);
+                println!(
+                    "Venue returned {}; pausing REST requests for {}s",
+                    status, retry_after
+                );
+            }
+        }
+        self.report_utilization();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::VirtualClock;
+    use crate::tests::{PausedClock, START_MS, serve};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::get;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn config(weight_limit: u32) -> RestLimitConfig {
+        RestLimitConfig {
+            weight_limit,
+            endpoint_weights: BTreeMap::new(),
+            ..RestLimitConfig::default()
+        }
+    }
+
+    // Answers the first request with `first` and every later one with 200,
+    // reporting `used` weight each time
+    async fn venue(first: StatusCode, used: [u32; 2]) -> String {
+        let calls = Arc::new(AtomicU32::new(0));
+        let app = axum::Router::new().route(
+            "/api",
+            get(move || async move {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                let mut headers = HeaderMap::new();
+                let used = used[(call as usize).min(1)];
+                headers.insert("x-mbx-used-weight-1m", used.into());
+                headers.insert("retry-after", 2.into());
+                let status = if call == 0 { first } else { StatusCode::OK };
+                (status, headers)
+            }),
+        );
+        format!("{}/api", serve(app).await)
+    }
+
+    #[tokio::test]
+    async fn a_reported_weight_at_the_limit_holds_requests_to_the_next_window() {
+        let url = venue(StatusCode::OK, [10, 1]).await;
+        let clock = Arc::new(VirtualClock::new(START_MS));
+        let client =
+            RateLimitedClient::new(reqwest::Client::new(), config(10)).with_clock(clock.clone());
+        let sent_at = Mutex::new(Vec::new());
+        let request = |client: &reqwest::Client| {
+            sent_at.lock().unwrap().push(clock.now_ms());
+            client.get(&url)
+        };
+
+        client
+            .send(RequestPriority::Orders, "/api", request)
+            .await
+            .unwrap();
+        // Booked 1 locally, but the venue says all 10 are used
+        assert_eq!(client.utilization(), 1.0);
+        client
+            .send(RequestPriority::Orders, "/api", request)
+            .await
+            .unwrap();
+        assert_eq!(*sent_at.lock().unwrap(), [START_MS, START_MS + 60_000]);
+        assert_eq!(client.utilization(), 0.1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn after_a_429_cancels_go_first_and_market_data_last() {
+        let url = venue(StatusCode::TOO_MANY_REQUESTS, [1, 1]).await;
+        let clock = Arc::new(PausedClock::new(START_MS));
+        let client = RateLimitedClient::new(
+            reqwest::Client::new(),
+            RestLimitConfig {
+                max_retries: 0,
+                ..config(6_000)
+            },
+        )
+        .with_clock(clock.clone());
+        let sent = Mutex::new(Vec::new());
+        let send = |priority| {
+            let (client, url, sent, clock) = (&client, &url, &sent, &clock);
+            async move {
+                let request = |http: &reqwest::Client| {
+                    sent.lock().unwrap().push((priority, clock.now_ms()));
+                    http.get(url)
+                };
+                client.send(priority, "/api", request).await.unwrap()
+            }
+        };
+
+        let first = send(RequestPriority::MarketData).await;
+        assert_eq!(first.status().as_u16(), 429);
+        // Queued least urgent first, all while the venue's 2s pause lasts
+        tokio::join!(
+            send(RequestPriority::MarketData),
+            send(RequestPriority::Orders),
+            send(RequestPriority::Cancels),
+        );
+        let sent = sent.lock().unwrap();
+        let order: Vec<_> = sent[1..].iter().map(|&(priority, _)| priority).collect();
+        assert_eq!(
+            order,
+            [
+                RequestPriority::Cancels,
+                RequestPriority::Orders,
+                RequestPriority::MarketData
+            ]
+        );
+        assert!(sent[1..].iter().all(|&(_, at)| at >= START_MS + 2_000));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::clock::Clock;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+// Exponential backoff for transient failures (timeouts, dropped connections)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    // Total tries including the first; 1 disables retrying
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 50,
+            max_backoff_ms: 1_000,
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("Retry max_attempts must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    // Delay before retry number `retry` (0-based), doubling each time
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ms = self
+            .initial_backoff_ms
+            .saturating_mul(1u64 << retry.min(16))
+            .min(self.max_backoff_ms);
+        Duration::from_millis(ms)
+    }
+}
+
+// Runs `op` until it succeeds, fails with an error `is_retryable` rejects, or
+// the attempts run out, waiting out the backoff on `clock`. The last error
+// is returned.
+pub async fn retry<T, E, F, Fut>(
+    config: &RetryConfig,
+    clock: &dyn Clock,
+    is_retryable: impl Fn(&E) -> bool,
+    mut op: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < config.max_attempts && is_retryable(&e) => {
+                let backoff = config.backoff(attempt - 1);
+                println!(
+                    "Attempt {}/{} failed: {}; retrying in {:?}",
+                    attempt, config.max_attempts, e, backoff
+                );
+                clock.sleep(backoff).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,463 @@
+use crate::consolidated::ConsolidatedBook;
+use crate::fees::FeeModel;
+use crate::{ExecutionError, Order, OrderExecutor, OrderSide, OrderType};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Somewhere a routed order can be sent
+pub trait VenueExecutor: Send + Sync {
+    fn submit(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>>;
+}
+
+impl VenueExecutor for OrderExecutor {
+    fn submit(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(self.submit_order(order))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouterConfig {
+    // Spread orders across venues in proportion to their depth, rather
+    // than sending each to the single best venue
+    pub split: bool,
+    // Depth priced further than this from the best price across venues
+    // isn't counted as available to a market order
+    pub max_slippage_bps: f64,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            split: false,
+            max_slippage_bps: 50.0,
+        }
+    }
+}
+
+impl RouterConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_slippage_bps < 0.0 {
+            return Err("Router max_slippage_bps must be non-negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+// A venue's executor and what it charges
+pub struct RouteVenue {
+    pub name: String,
+    pub executor: Arc<dyn VenueExecutor>,
+    pub fees: FeeModel,
+}
+
+// What one venue offers for an order
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VenueQuote {
+    pub venue: String,
+    // Quantity within the order's limit (or the slippage bound)
+    pub available: f64,
+    // Average price of filling as much of the order as is available
+    pub average_price: f64,
+    pub taker_bps: f64,
+    // Average price with the taker fee added (buys) or taken off (sells)
+    pub effective_price: f64,
+}
+
+impl VenueQuote {
+    fn covers(&self, quantity: f64) -> bool {
+        self.available >= quantity
+    }
+}
+
+// An order bound for one venue
+#[derive(Debug, Clone)]
+pub struct ChildOrder {
+    pub venue: String,
+    pub order: Order,
+}
+
+// Where the order went
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RouteReport {
+    pub quotes: Vec<VenueQuote>,
+    // (venue, order id, quantity) per accepted child order
+    pub submitted: Vec<(String, String, f64)>,
+    // (venue, error) per rejection, including ones a fallback made up for
+    pub rejected: Vec<(String, String)>,
+    // Quantity no venue took
+    pub unrouted: f64,
+}
+
+/// Sends each order to the venue with the best price after fees that has
+/// the depth for it, falling back to the next best when a venue rejects.
+/// With `split`, the order is instead divided across venues in proportion
+/// to their available depth. Prices come from the consolidated book; every
+/// decision is logged with the quotes it was made on.
+pub struct SmartOrderRouter {
+    venues: Vec<RouteVenue>,
+    config: RouterConfig,
+}
+
+impl SmartOrderRouter {
+    pub fn new(venues: Vec<RouteVenue>, config: RouterConfig) -> Self {
+        Self { venues, config }
+    }
+
+    /// Each fresh venue's quote for the order, best first. Venues with the
+    /// depth for the whole order rank ahead of those without.
+    pub fn quotes(&self, order: &Order, book: &ConsolidatedBook, now_ms: u64) -> Vec<VenueQuote> {
+        let best = match order.side {
+            OrderSide::Buy => book.best_ask(now_ms),
+            OrderSide::Sell => book.best_bid(now_ms),
+        };
+        let Some(best) = best else {
+            return Vec::new();
+        };
+        let slippage = best.price * self.config.max_slippage_bps / 10_000.0;
+        let bound = match (order.side, order.order_type, order.price) {
+            (_, OrderType::Limit, Some(limit)) => limit,
+            (OrderSide::Buy, _, _) => best.price + slippage,
+            (OrderSide::Sell, _, _) => best.price - slippage,
+        };
+
+        let mut quotes: Vec<VenueQuote> = self
+            .venues
+            .iter()
+            .filter_map(|venue| {
+                let levels = match order.side {
+                    OrderSide::Buy => book.venue_asks(&venue.name, now_ms),
+                    OrderSide::Sell => book.venue_bids(&venue.name, now_ms),
+                };
+                let mut available = 0.0;
+                let mut notional = 0.0;
+                // Walks the venue's book until the order is covered
+                for (price, quantity) in levels {
+                    let within = match order.side {
+                        OrderSide::Buy => price <= bound,
+                        OrderSide::Sell => price >= bound,
+                    };
+                    if !within || available >= order.quantity {
+                        break;
+                    }
+                    let take = quantity.min(order.quantity - available);
+                    available += take;
+                    notional += take * price;
+                }
+                if available <= 0.0 {
+                    return None;
+                }
+                let average_price = notional / available;
+                let taker_bps = venue.fees.schedule(order.symbol.as_str()).taker_bps;
+                let fee = average_price * taker_bps / 10_000.0;
+                Some(VenueQuote {
+                    venue: venue.name.clone(),
+                    available,
+                    average_price,
+                    taker_bps,
+                    effective_price: match order.side {
+                        OrderSide::Buy => average_price + fee,
+                        OrderSide::Sell => average_price - fee,
+                    },
+                })
+            })
+            .collect();
+
+        quotes.sort_by(|a, b| {
+            let by_price = match order.side {
+                OrderSide::Buy => a.effective_price.total_cmp(&b.effective_price),
+                OrderSide::Sell => b.effective_price.total_cmp(&a.effective_price),
+            };
+            b.covers(order.quantity)
+                .cmp(&a.covers(order.quantity))
+                .then(by_price)
+        });
+        quotes
+    }
+
+    /// The child orders the order would be sent as, given `quotes`
+    pub fn plan(&self, order: &Order, quotes: &[VenueQuote]) -> Vec<ChildOrder> {
+        if !self.config.split {
+            return quotes
+                .first()
+                .map(|quote| vec![self.child(order, &quote.venue, order.quantity)])
+                .unwrap_or_default();
+        }
+        let total: f64 = quotes.iter().map(|q| q.available).sum();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+        quotes
+            .iter()
+            .map(|quote| {
+                let quantity = order.quantity * quote.available / total;
+                self.child(order, &quote.venue, quantity)
+            })
+            .collect()
+    }
+
+    fn child(&self, order: &Order, venue: &str, quantity: f64) -> ChildOrder {
+        ChildOrder {
+            venue: venue.to_string(),
+            order: Order {
+                id: Uuid::new_v4().to_string(),
+                // Stable per venue, so resubmitting the parent is idempotent
+                client_order_id: format!("{}-{}", order.client_order_id, venue),
+                quantity,
+                ..order.clone()
+            },
+        }
+    }
+
+    /// Routes and submits the order. A child the venue rejects goes to the
+    /// next best venue instead, until one accepts or none are left.
+    pub async fn route(&self, order: &Order, book: &ConsolidatedBook, now_ms: u64) -> RouteReport {
+        let quotes = self.quotes(order, book, now_ms);
+        println!(
+            "Routing {:?} {} {}: {}",
+            order.side,
+            order.quantity,
+            order.symbol,
+            if quotes.is_empty() {
+                "no venue quotes".to_string()
+            } else {
+                quotes
+                    .iter()
+                    .map(|q| {
+                        format!(
+                            "{} {:.4} ({:.4} + {} bps) for {}",
+                            q.venue, q.effective_price, q.average_price, q.taker_bps, q.available
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        );
+
+        let mut report = RouteReport {
+            unrouted: if quotes.is_empty() {
+                order.quantity
+            } else {
+                0.0
+            },
+            ..RouteReport::default()
+        };
+        for child in self.plan(order, &quotes) {
+            let fallbacks = quotes.iter().filter(|q| q.venue != child.venue);
+            let mut accepted = false;
+            for venue_name in
+                std::iter::once(child.venue.as_str()).chain(fallbacks.map(|q| q.venue.as_str()))
+            {
+                let Some(venue) = self.venues.iter().find(|v| v.name == venue_name) else {
+                    continue;
+                };
+                let routed = self.child(order, venue_name, child.order.quantity);
+                match venue.executor.submit(routed.order).await {
+                    Ok(order_id) => {
+                        println!(
+                            "Routed {} {} to {} as {}",
+                            child.order.quantity, order.symbol, venue_name, order_id
+                        );
+                        report.submitted.push((
+                            venue_name.to_string(),
+                            order_id,
+                            child.order.quantity,
+                        ));
+                        accepted = true;
+                        break;
+                    }
+                    Err(e) => {
+                        println!(
+                            "{} rejected {} {}: {}",
+                            venue_name, order.symbol, child.order.quantity, e
+                        );
+                        report
+                            .rejected
+                            .push((venue_name.to_string(), e.to_string()));
+                    }
+                }
+            }
+            if !accepted {
+                report.unrouted += child.order.quantity;
+            }
+        }
+        report.quotes = quotes;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, limit_order, market_order};
+    use std::sync::Mutex;
+
+    // Records what it was sent; rejects everything if `down`
+    #[derive(Default)]
+    struct MockVenue {
+        down: bool,
+        sent: Mutex<Vec<Order>>,
+    }
+
+    impl VenueExecutor for MockVenue {
+        fn submit(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+            Box::pin(async move {
+                if self.down {
+                    return Err(ExecutionError::Network("connection reset".to_string()));
+                }
+                let id = order.id.clone();
+                self.sent.lock().unwrap().push(order);
+                Ok(id)
+            })
+        }
+    }
+
+    fn fees(taker_bps: f64) -> FeeModel {
+        FeeModel {
+            taker_bps,
+            ..FeeModel::zero()
+        }
+    }
+
+    // "cheap" asks 100.00 but charges 30 bps; "dear" asks 100.20 for free
+    fn market() -> ConsolidatedBook {
+        let mut merged = ConsolidatedBook::new("SOL/USDT", 5_000);
+        merged.update_venue(
+            "cheap",
+            &fixtures::book("SOL/USDT", &[(99.9, 5.0)], &[(100.0, 3.0)]),
+            0,
+        );
+        merged.update_venue(
+            "dear",
+            &fixtures::book("SOL/USDT", &[(99.8, 5.0)], &[(100.2, 1.0)]),
+            0,
+        );
+        merged
+    }
+
+    fn router(
+        split: bool,
+        cheap: MockVenue,
+        dear: MockVenue,
+    ) -> (SmartOrderRouter, Arc<MockVenue>, Arc<MockVenue>) {
+        let (cheap, dear) = (Arc::new(cheap), Arc::new(dear));
+        let venues = vec![
+            RouteVenue {
+                name: "cheap".to_string(),
+                executor: cheap.clone(),
+                fees: fees(30.0),
+            },
+            RouteVenue {
+                name: "dear".to_string(),
+                executor: dear.clone(),
+                fees: fees(0.0),
+            },
+        ];
+        let config = RouterConfig {
+            split,
+            ..RouterConfig::default()
+        };
+        (SmartOrderRouter::new(venues, config), cheap, dear)
+    }
+
+    #[tokio::test]
+    async fn the_best_price_after_fees_wins() {
+        let (router, cheap, dear) = router(false, MockVenue::default(), MockVenue::default());
+        let order = market_order("SOL/USDT", OrderSide::Buy, 1.0);
+        let report = router.route(&order, &market(), 0).await;
+
+        // 100.00 + 30 bps = 100.30 against 100.20 flat
+        assert_eq!(report.quotes[0].venue, "dear");
+        assert!((report.quotes[0].effective_price - 100.2).abs() < 1e-9);
+        assert!((report.quotes[1].effective_price - 100.3).abs() < 1e-9);
+        assert_eq!(report.submitted.len(), 1);
+        assert_eq!(report.submitted[0].0, "dear");
+        assert_eq!(report.unrouted, 0.0);
+        let sent = dear.sent.lock().unwrap();
+        assert_eq!(sent[0].quantity, 1.0);
+        assert_eq!(sent[0].client_order_id, "fixture-dear");
+        assert!(cheap.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_venue_without_the_depth_ranks_behind_one_with_it() {
+        let (router, cheap, _) = router(false, MockVenue::default(), MockVenue::default());
+        // "dear" only shows 1 of the 2 wanted
+        let order = market_order("SOL/USDT", OrderSide::Buy, 2.0);
+        let report = router.route(&order, &market(), 0).await;
+        assert_eq!(
+            report.submitted,
+            vec![(
+                "cheap".to_string(),
+                cheap.sent.lock().unwrap()[0].id.clone(),
+                2.0
+            )]
+        );
+
+        // Selling, the higher bid loses to its fee: 99.90 - 30 bps is 99.60
+        let order = limit_order("SOL/USDT", OrderSide::Sell, 1.0, 99.5);
+        let quotes = router.quotes(&order, &market(), 0);
+        assert_eq!(quotes[0].venue, "dear");
+        assert!((quotes[1].effective_price - 99.6003).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn a_split_follows_available_depth() {
+        let (router, cheap, dear) = router(true, MockVenue::default(), MockVenue::default());
+        // Within 50 bps of 100.00: 3 on "cheap", 1 on "dear"
+        let order = market_order("SOL/USDT", OrderSide::Buy, 4.0);
+        let report = router.route(&order, &market(), 0).await;
+        assert_eq!(report.submitted.len(), 2);
+        assert_eq!(cheap.sent.lock().unwrap()[0].quantity, 3.0);
+        assert_eq!(dear.sent.lock().unwrap()[0].quantity, 1.0);
+
+        // Depth counts only up to the order's size: 2 on "cheap", 1 on "dear"
+        let order = market_order("SOL/USDT", OrderSide::Buy, 2.0);
+        let children = router.plan(&order, &router.quotes(&order, &market(), 0));
+        let split: Vec<(String, f64)> = children
+            .into_iter()
+            .map(|child| (child.venue, child.order.quantity))
+            .collect();
+        assert_eq!(
+            split,
+            vec![
+                ("cheap".to_string(), 4.0 / 3.0),
+                ("dear".to_string(), 2.0 / 3.0)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_rejection_falls_back_to_the_next_venue() {
+        let dear = MockVenue {
+            down: true,
+            ..MockVenue::default()
+        };
+        let (router, cheap, _) = router(false, MockVenue::default(), dear);
+        let order = market_order("SOL/USDT", OrderSide::Buy, 1.0);
+        let report = router.route(&order, &market(), 0).await;
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].0, "dear");
+        assert_eq!(report.submitted[0].0, "cheap");
+        assert_eq!(
+            cheap.sent.lock().unwrap()[0].client_order_id,
+            "fixture-cheap"
+        );
+
+        // Nowhere left to go
+        let (router, _, _) = router_down();
+        let report = router.route(&order, &market(), 0).await;
+        assert_eq!(report.rejected.len(), 2);
+        assert_eq!(report.unrouted, 1.0);
+    }
+
+    fn router_down() -> (SmartOrderRouter, Arc<MockVenue>, Arc<MockVenue>) {
+        let down = || MockVenue {
+            down: true,
+            ..MockVenue::default()
+        };
+        router(false, down(), down())
+    }
+}
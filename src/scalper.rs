@@ -0,0 +1,322 @@
+use crate::monitor::ExitReason;
+use crate::{
+    Fill, OrderBook, OrderSide, Position, Price, TimeInForce, TradingSignal, TradingStrategy,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// The scalper's settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScalperParams {
+    pub tick_size: f64,
+    // Spread, in ticks, wide enough to post into
+    pub min_spread_ticks: f64,
+    // Book imbalance that favours a side; the same against a position
+    // bails out of it
+    pub min_imbalance: f64,
+    #[serde(default = "default_imbalance_levels")]
+    pub imbalance_levels: usize,
+    // Profit target past the entry price, in ticks (1 or 2)
+    #[serde(default = "default_profit_ticks")]
+    pub profit_ticks: u32,
+    // How long an entry or a target may rest before it's pulled
+    pub timeout_secs: u64,
+    pub base_quantity: f64,
+    // Largest position the strategy holds, either way
+    pub max_inventory: f64,
+}
+
+fn default_imbalance_levels() -> usize {
+    5
+}
+
+fn default_profit_ticks() -> u32 {
+    1
+}
+
+impl ScalperParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tick_size <= 0.0 {
+            return Err("SpreadScalperStrategy tick_size must be positive".to_string());
+        }
+        if self.min_spread_ticks < 1.0 {
+            return Err("SpreadScalperStrategy min_spread_ticks must be at least 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.min_imbalance) {
+            return Err("SpreadScalperStrategy min_imbalance must be in [0, 1]".to_string());
+        }
+        if self.imbalance_levels == 0 {
+            return Err("SpreadScalperStrategy imbalance_levels must be at least 1".to_string());
+        }
+        if !(1..=2).contains(&self.profit_ticks) {
+            return Err("SpreadScalperStrategy profit_ticks must be 1 or 2".to_string());
+        }
+        if self.timeout_secs == 0 {
+            return Err("SpreadScalperStrategy timeout_secs must be positive".to_string());
+        }
+        if self.base_quantity <= 0.0 || self.max_inventory < self.base_quantity {
+            return Err(
+                "SpreadScalperStrategy needs 0 < base_quantity <= max_inventory".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+// A limit the strategy has resting
+#[derive(Debug, Clone, Copy)]
+struct Resting {
+    side: OrderSide,
+    // Not yet filled
+    quantity: f64,
+    // Seconds
+    posted: u64,
+}
+
+// One symbol's scalp
+#[derive(Debug, Clone, Copy, Default)]
+struct Scalp {
+    // Signed, from the strategy's own fills
+    inventory: f64,
+    // Average price of the inventory
+    entry_price: f64,
+    entry: Option<Resting>,
+    target: Option<Resting>,
+    // When a market exit went out (s); nothing else goes until it fills,
+    // or it's sent again after the timeout
+    exiting: Option<u64>,
+    // Resting orders to be cancelled after this evaluation
+    cancel: bool,
+}
+
+impl Scalp {
+    fn side(&self) -> Option<OrderSide> {
+        if self.inventory > f64::EPSILON {
+            Some(OrderSide::Buy)
+        } else if self.inventory < -f64::EPSILON {
+            Some(OrderSide::Sell)
+        } else {
+            None
+        }
+    }
+}
+
+/// Posts into a wide spread on the side the book leans to, and captures a
+/// tick or two of it. When the spread is at least `min_spread_ticks` and
+/// the imbalance favours a side by `min_imbalance`, it joins the inside on
+/// that side with a post-only limit. Once filled it rests a take-profit
+/// `profit_ticks` past the entry price. If the book turns against it, or
+/// the entry or the target rests longer than `timeout_secs`, the order is
+/// cancelled; an open position is then closed at market. It never sizes
+/// an entry past `max_inventory`, and bails out of anything beyond it.
+pub struct SpreadScalperStrategy {
+    params: ScalperParams,
+    scalps: Mutex<HashMap<String, Scalp>>,
+}
+
+impl SpreadScalperStrategy {
+    pub fn new(params: ScalperParams) -> Self {
+        Self {
+            params,
+            scalps: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // The book leaning against `side` by at least min_imbalance
+    fn against(&self, side: OrderSide, imbalance: f64) -> bool {
+        match side {
+            OrderSide::Buy => imbalance <= -self.params.min_imbalance,
+            OrderSide::Sell => imbalance >= self.params.min_imbalance,
+        }
+    }
+
+    fn evaluate(
+        &self,
+        scalp: &mut Scalp,
+        orderbook: &OrderBook,
+        now: u64,
+    ) -> Option<TradingSignal> {
+        let ((bid, _), (ask, _)) = (orderbook.best_bid()?, orderbook.best_ask()?);
+        let imbalance = orderbook.imbalance(self.params.imbalance_levels)?;
+        let timeout = self.params.timeout_secs;
+        if scalp.exiting.is_some_and(|sent| now < sent + timeout) {
+            return None;
+        }
+
+        // A resting entry is pulled once it's stale or the book turns;
+        // whatever of it filled is worked off below
+        if let Some(entry) = scalp.entry {
+            if now < entry.posted + timeout && !self.against(entry.side, imbalance) {
+                return None;
+            }
+            scalp.entry = None;
+            scalp.cancel = true;
+        }
+
+        let Some(side) = scalp.side() else {
+            // Flat: look for a spread worth posting into
+            if (ask - bid) / self.params.tick_size < self.params.min_spread_ticks - 1e-9 {
+                return None;
+            }
+            let (side, price) = if imbalance >= self.params.min_imbalance {
+                (OrderSide::Buy, bid)
+            } else if imbalance <= -self.params.min_imbalance {
+                (OrderSide::Sell, ask)
+            } else {
+                return None;
+            };
+            let quantity = self.params.base_quantity.min(self.params.max_inventory);
+            scalp.entry = Some(Resting {
+                side,
+                quantity,
+                posted: now,
+            });
+            return Some(self.signal(
+                orderbook,
+                side,
+                quantity,
+                imbalance.abs(),
+                Some(price),
+                TimeInForce::PostOnly,
+            ));
+        };
+        let exit_side = match side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+
+        let stale = scalp
+            .target
+            .is_some_and(|target| now >= target.posted + timeout);
+        if self.against(side, imbalance)
+            || stale
+            || scalp.exiting.is_some()
+            || scalp.inventory.abs() > self.params.max_inventory + f64::EPSILON
+        {
+            scalp.target = None;
+            scalp.exiting = Some(now);
+            scalp.cancel = true;
+            return Some(self.signal(
+                orderbook,
+                exit_side,
+                scalp.inventory.abs(),
+                1.0,
+                None,
+                TimeInForce::Gtc,
+            ));
+        }
+        if scalp.target.is_some() {
+            return None;
+        }
+        let offset = self.params.profit_ticks as f64 * self.params.tick_size;
+        let price = match side {
+            OrderSide::Buy => scalp.entry_price + offset,
+            OrderSide::Sell => scalp.entry_price - offset,
+        };
+        scalp.target = Some(Resting {
+            side: exit_side,
+            quantity: scalp.inventory.abs(),
+            posted: now,
+        });
+        Some(self.signal(
+            orderbook,
+            exit_side,
+            scalp.inventory.abs(),
+            1.0,
+            Some(price),
+            TimeInForce::Gtc,
+        ))
+    }
+
+    fn signal(
+        &self,
+        orderbook: &OrderBook,
+        action: OrderSide,
+        quantity: f64,
+        confidence: f64,
+        limit_price: Option<f64>,
+        time_in_force: TimeInForce,
+    ) -> TradingSignal {
+        TradingSignal {
+            symbol: orderbook.symbol.as_str().into(),
+            action,
+            confidence,
+            target_price: limit_price
+                .or_else(|| orderbook.mid_price())
+                .unwrap_or_default(),
+            quantity,
+            time_in_force,
+            limit_price,
+        }
+    }
+}
+
+impl TradingStrategy for SpreadScalperStrategy {
+    fn analyze(&self, prices: &[Price], orderbook: &OrderBook) -> Option<TradingSignal> {
+        let now = prices
+            .last()
+            .map_or(0, |p| p.timestamp)
+            .max(orderbook.timestamp);
+        let mut scalps = self.scalps.lock().unwrap();
+        let scalp = scalps.entry(orderbook.symbol.clone()).or_default();
+        self.evaluate(scalp, orderbook, now)
+    }
+
+    fn name(&self) -> &str {
+        "SpreadScalperStrategy"
+    }
+
+    fn on_fill(&self, fill: &Fill) {
+        let mut scalps = self.scalps.lock().unwrap();
+        let scalp = scalps.entry(fill.symbol.to_string()).or_default();
+        let signed = fill.signed_quantity();
+        if scalp.inventory * signed >= 0.0 {
+            let quantity = scalp.inventory.abs() + fill.quantity;
+            scalp.entry_price =
+                (scalp.entry_price * scalp.inventory.abs() + fill.price * fill.quantity) / quantity;
+        }
+        scalp.inventory += signed;
+        for resting in [&mut scalp.entry, &mut scalp.target] {
+            if let Some(order) = resting
+                && order.side == fill.side
+            {
+                order.quantity -= fill.quantity;
+                if order.quantity <= f64::EPSILON {
+                    *resting = None;
+                }
+            }
+        }
+        // Back to flat: the cycle is over, whichever way it ended
+        if scalp.side().is_none() {
+            *scalp = Scalp {
+                cancel: scalp.cancel,
+                ..Scalp::default()
+            };
+        }
+    }
+
+    // The bot closed the position itself, e.g. on a stop, so the target
+    // has nothing left to take profit on
+    fn on_position(&self, position: &Position, _exit: ExitReason) {
+        if let Some(scalp) = self
+            .scalps
+            .lock()
+            .unwrap()
+            .get_mut(position.symbol.as_str())
+        {
+            *scalp = Scalp {
+                cancel: true,
+                ..Scalp::default()
+            };
+        }
+    }
+
+    fn cancel_working(&self, symbol: &str) -> bool {
+        self.scalps
+            .lock()
+            .unwrap()
+            .get_mut(symbol)
+            .is_some_and(|scalp| std::mem::take(&mut scalp.cancel))
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 100;
+pub const DEFAULT_EVALUATION_INTERVAL_MS: u64 = 50;
+// Longest interval accepted; anything slower is most likely a unit mistake
+const MAX_INTERVAL_MS: u64 = 3_600_000;
+
+// How often prices are polled and strategies evaluated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    // Time between price polls of each symbol
+    pub poll_interval_ms: u64,
+    // Per-symbol overrides of poll_interval_ms
+    pub symbol_poll_intervals: HashMap<String, u64>,
+    // Time between evaluations of timer-triggered strategies; 0 evaluates
+    // them as fast as prices arrive
+    pub evaluation_interval_ms: u64,
+    // Symbols evaluated at once; each symbol is evaluated on its own task
+    pub max_concurrent_evaluations: usize,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: DEFAULT_POLL_INTERVAL_MS,
+            symbol_poll_intervals: HashMap::new(),
+            evaluation_interval_ms: DEFAULT_EVALUATION_INTERVAL_MS,
+            max_concurrent_evaluations: 8,
+        }
+    }
+}
+
+impl ScheduleConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        // The feed is polled, so a zero interval would spin on it
+        if self.poll_interval_ms == 0 || self.poll_interval_ms > MAX_INTERVAL_MS {
+            return Err(format!(
+                "Schedule poll_interval_ms must be between 1 and {}",
+                MAX_INTERVAL_MS
+            ));
+        }
+        for (symbol, &ms) in &self.symbol_poll_intervals {
+            if ms == 0 || ms > MAX_INTERVAL_MS {
+                return Err(format!(
+                    "Schedule poll interval for {} must be between 1 and {} ms",
+                    symbol, MAX_INTERVAL_MS
+                ));
+            }
+        }
+        if self.max_concurrent_evaluations == 0 {
+            return Err("Schedule max_concurrent_evaluations must be at least 1".to_string());
+        }
+        if self.evaluation_interval_ms > MAX_INTERVAL_MS {
+            return Err(format!(
+                "Schedule evaluation_interval_ms must be at most {}",
+                MAX_INTERVAL_MS
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn poll_interval(&self, symbol: &str) -> Duration {
+        Duration::from_millis(
+            self.symbol_poll_intervals
+                .get(symbol)
+                .copied()
+                .unwrap_or(self.poll_interval_ms),
+        )
+    }
+
+    // None when timer-triggered strategies follow the data instead
+    pub fn evaluation_interval(&self) -> Option<Duration> {
+        (self.evaluation_interval_ms > 0)
+            .then(|| Duration::from_millis(self.evaluation_interval_ms))
+    }
+}
+
+// When a strategy is evaluated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvaluationTrigger {
+    // Every evaluation_interval_ms
+    #[default]
+    Timer,
+    // Whenever the symbol has a new price
+    Data,
+}
+
+// Which triggers have fired for a symbol on this pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Due {
+    pub timer: bool,
+    pub data: bool,
+}
+
+impl Due {
+    // Both at once, as in a replay where every book is an evaluation
+    pub const ALL: Due = Due {
+        timer: true,
+        data: true,
+    };
+
+    pub fn includes(self, trigger: EvaluationTrigger) -> bool {
+        match trigger {
+            EvaluationTrigger::Timer => self.timer,
+            EvaluationTrigger::Data => self.data,
+        }
+    }
+
+    pub fn any(self) -> bool {
+        self.timer || self.data
+    }
+}
@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// When the trading day that daily limits and reports cover rolls over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    // "+HH:MM" or "-HH:MM": sessions run midnight to midnight at this
+    // offset from UTC
+    pub utc_offset: String,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            utc_offset: "+00:00".to_string(),
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        self.offset_secs().map(|_| ())
+    }
+
+    // The offset from UTC in seconds, east positive
+    pub fn offset_secs(&self) -> Result<i64, String> {
+        let invalid = || {
+            format!(
+                "Session utc_offset must be +HH:MM or -HH:MM, got {:?}",
+                self.utc_offset
+            )
+        };
+        // Exactly a sign, two digits, a colon and two digits: parse() alone
+        // would let "+-1:00" or "+01:-1" through
+        let &[sign, h1, h2, b':', m1, m2] = self.utc_offset.as_bytes() else {
+            return Err(invalid());
+        };
+        let sign = match sign {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return Err(invalid()),
+        };
+        if ![h1, h2, m1, m2].iter().all(u8::is_ascii_digit) {
+            return Err(invalid());
+        }
+        let digits = |tens: u8, ones: u8| i64::from(tens - b'0') * 10 + i64::from(ones - b'0');
+        let (hours, minutes) = (digits(h1, h2), digits(m1, m2));
+        let offset = hours * 3_600 + minutes * 60;
+        if minutes >= 60 || offset > 14 * 3_600 {
+            return Err(invalid());
+        }
+        Ok(sign * offset)
+    }
+}
+
+/// One session's running counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionStats {
+    // Days since the epoch at the configured offset
+    pub session: u64,
+    // Unix seconds the session began
+    pub started_at: u64,
+    // Realized PnL net of fees, plus funding: what the daily loss limit is
+    // held to
+    pub realized_pnl: f64,
+    pub fees: f64,
+    pub fills: usize,
+    // Fills that reduced a position
+    pub trades: usize,
+    // Equity high-water mark, and the deepest drawdown from it (a fraction)
+    pub peak_equity: f64,
+    pub max_drawdown_pct: f64,
+}
+
+/// A session as it closed at rollover
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSummary {
+    #[serde(flatten)]
+    pub stats: SessionStats,
+    // Unix seconds
+    pub ended_at: u64,
+    pub closing_equity: f64,
+}
+
+#[derive(Debug, Default)]
+struct Current {
+    stats: SessionStats,
+    // Counters were carried over a restart from the journal
+    restored: bool,
+}
+
+/// The trading day's counters: realized PnL, fees, fills and trades, and
+/// the equity peak and drawdown. The session rolls over at midnight at the
+/// configured UTC offset, on the first roll() after it, which starts a new
+/// one from zero with the equity then as its peak.
+#[derive(Debug)]
+pub struct SessionTracker {
+    offset_secs: i64,
+    current: Mutex<Current>,
+}
+
+impl SessionTracker {
+    // Panics on an offset SessionConfig::validate rejects
+    pub fn new(config: &SessionConfig, now_secs: u64, equity: f64) -> Self {
+        let tracker = Self {
+            offset_secs: config
+                .offset_secs()
+                .expect("the session offset is checked by BotConfig::validate"),
+            current: Mutex::new(Current::default()),
+        };
+        tracker.start(now_secs, equity);
+        tracker
+    }
+
+    // The session a time falls in
+    pub fn session_of(&self, secs: u64) -> u64 {
+        (secs as i64 + self.offset_secs)
+            .div_euclid(SECONDS_PER_DAY)
+            .max(0) as u64
+    }
+
+    // When a session began, in Unix seconds
+    pub fn start_of(&self, session: u64) -> u64 {
+        (session as i64 * SECONDS_PER_DAY - self.offset_secs).max(0) as u64
+    }
+
+    // Starts over with a fresh session at `now_secs`
+    pub fn start(&self, now_secs: u64, equity: f64) {
+        let session = self.session_of(now_secs);
+        *self.current.lock().unwrap() = Current {
+            stats: SessionStats {
+                session,
+                started_at: self.start_of(session),
+                peak_equity: equity,
+                ..SessionStats::default()
+            },
+            restored: false,
+        };
+    }
+
+    pub fn session(&self) -> u64 {
+        self.current.lock().unwrap().stats.session
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        self.current.lock().unwrap().stats.clone()
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        self.current.lock().unwrap().stats.realized_pnl
+    }
+
+    // A fill's realized PnL (net of its fee) and fee
+    pub fn record_fill(&self, realized_pnl: f64, fee: f64, closing: bool) {
+        let stats = &mut self.current.lock().unwrap().stats;
+        stats.realized_pnl += realized_pnl;
+        stats.fees += fee;
+        stats.fills += 1;
+        if closing {
+            stats.trades += 1;
+        }
+    }
+
+    // Funding received; negative when paid
+    pub fn record_funding(&self, amount: f64) {
+        self.current.lock().unwrap().stats.realized_pnl += amount;
+    }
+
+    // Takes in the equity now and returns the session's peak
+    pub fn mark_equity(&self, equity: f64) -> f64 {
+        let stats = &mut self.current.lock().unwrap().stats;
+        stats.peak_equity = stats.peak_equity.max(equity);
+        if stats.peak_equity > 0.0 {
+            let drawdown = (stats.peak_equity - equity) / stats.peak_equity;
+            stats.max_drawdown_pct = stats.max_drawdown_pct.max(drawdown);
+        }
+        stats.peak_equity
+    }
+
+    // Re-arms the peak at the equity now
+    pub fn reset_peak(&self, equity: f64) {
+        self.current.lock().unwrap().stats.peak_equity = equity;
+    }
+
+    /// Rolls over if `now_secs` is past the current session, returning the
+    /// session that closed. Nothing is carried into the new one.
+    pub fn roll(&self, now_secs: u64, equity: f64) -> Option<SessionSummary> {
+        let session = self.session_of(now_secs);
+        let mut current = self.current.lock().unwrap();
+        if session <= current.stats.session {
+            return None;
+        }
+        let closed = std::mem::replace(
+            &mut current.stats,
+            SessionStats {
+                session,
+                started_at: self.start_of(session),
+                peak_equity: equity,
+                ..SessionStats::default()
+            },
+        );
+        current.restored = false;
+        Some(SessionSummary {
+            ended_at: self.start_of(closed.session + 1),
+            stats: closed,
+            closing_equity: equity,
+        })
+    }
+
+    /// Takes back the counters journaled before a restart, if they are of
+    /// the current session; returns whether they were
+    pub fn restore(&self, stats: SessionStats) -> bool {
+        let mut current = self.current.lock().unwrap();
+        if stats.session != current.stats.session {
+            return false;
+        }
+        current.stats = SessionStats {
+            peak_equity: stats.peak_equity.max(current.stats.peak_equity),
+            ..stats
+        };
+        current.restored = true;
+        true
+    }
+
+    // The realized PnL a state snapshot saved at `saved_at` (seconds) had,
+    // unless the journal already gave the session back
+    pub fn restore_pnl(&self, saved_at: u64, realized_pnl: f64) {
+        let session = self.session_of(saved_at);
+        let mut current = self.current.lock().unwrap();
+        if session == current.stats.session && !current.restored {
+            current.stats.realized_pnl = realized_pnl;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(utc_offset: &str) -> Result<i64, String> {
+        SessionConfig {
+            utc_offset: utc_offset.to_string(),
+        }
+        .offset_secs()
+    }
+
+    #[test]
+    fn offsets_need_a_sign_and_two_digit_hours_and_minutes() {
+        assert_eq!(offset("+00:00"), Ok(0));
+        assert_eq!(offset("-05:00"), Ok(-18_000));
+        assert_eq!(offset("+05:30"), Ok(19_800));
+        assert_eq!(offset("+14:00"), Ok(50_400));
+        for bad in [
+            "05:00", "+-1:00", "+01:-1", "-+1:00", "+1:00", "+001:00", "+01:5", "+01:60", "+14:01",
+            "+15:00", "+01-00", " +01:00", "+٠١:00", "",
+        ] {
+            assert!(offset(bad).is_err(), "{bad:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn a_session_rolls_at_midnight_at_its_offset() {
+        let config = SessionConfig {
+            utc_offset: "-05:00".to_string(),
+        };
+        // 2024-01-01T12:00:00Z is 07:00 in New York
+        let noon = 1_704_110_400;
+        let tracker = SessionTracker::new(&config, noon, 10_000.0);
+        tracker.record_fill(-25.0, 1.0, true);
+        tracker.mark_equity(9_975.0);
+
+        // New York's midnight is 05:00 UTC
+        let midnight = noon + 17 * 3_600;
+        assert_eq!(tracker.roll(midnight - 1, 9_975.0), None);
+        let summary = tracker.roll(midnight, 9_980.0).unwrap();
+        assert_eq!(summary.ended_at, midnight);
+        assert_eq!(summary.stats.started_at, midnight - 86_400);
+        assert_eq!(
+            (summary.stats.realized_pnl, summary.stats.trades),
+            (-25.0, 1)
+        );
+        assert_eq!(summary.closing_equity, 9_980.0);
+        // The new day starts from nothing
+        let stats = tracker.stats();
+        assert_eq!(stats.started_at, midnight);
+        assert_eq!((stats.realized_pnl, stats.fills), (0.0, 0));
+        assert_eq!(stats.peak_equity, 9_980.0);
+    }
+}
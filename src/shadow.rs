@@ -0,0 +1,78 @@
+use crate::fees::{FeeModel, Liquidity};
+use crate::paper::{vwap, walk_book};
+use crate::symbols::SymbolId;
+use crate::venue::VenueId;
+use crate::{Fill, OrderBook, OrderSide, PositionSide, TradingSignal};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Whether a strategy's signals trade. A shadow strategy is evaluated on
+/// the same data as the live ones, but its signals become hypothetical
+/// fills against the book instead of orders: it never reaches the
+/// aggregator, the risk manager or a venue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyMode {
+    #[default]
+    Live,
+    Shadow,
+    Disabled,
+}
+
+impl StrategyMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StrategyMode::Live => "live",
+            StrategyMode::Shadow => "shadow",
+            StrategyMode::Disabled => "disabled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "live" => Some(StrategyMode::Live),
+            "shadow" => Some(StrategyMode::Shadow),
+            "disabled" => Some(StrategyMode::Disabled),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for StrategyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The fill a shadow signal's market order would have got: the opposite
+/// side of the book walked for the signal's quantity, at the VWAP of the
+/// levels it took, paying the taker fee. None if that side is empty.
+pub fn hypothetical_fill(
+    strategy: &str,
+    signal: &TradingSignal,
+    orderbook: &OrderBook,
+    fees: &FeeModel,
+    venue: &VenueId,
+    timestamp: u64,
+) -> Option<Fill> {
+    let levels = match signal.action {
+        OrderSide::Buy => &orderbook.asks,
+        OrderSide::Sell => &orderbook.bids,
+    };
+    let levels = walk_book(levels, signal.action, signal.quantity, None);
+    let price = vwap(&levels)?;
+    let quantity: f64 = levels.iter().map(|l| l.quantity).sum();
+    Some(Fill {
+        order_id: format!("shadow-{}", uuid::Uuid::new_v4()),
+        symbol: SymbolId::intern(&signal.symbol),
+        side: signal.action,
+        quantity,
+        price,
+        fee: fees.fee(&signal.symbol, quantity * price, Liquidity::Taker),
+        liquidity: Liquidity::Taker,
+        timestamp,
+        strategy: Some(strategy.to_string()),
+        venue: venue.clone(),
+        position_side: PositionSide::Both,
+    })
+}
@@ -0,0 +1,270 @@
+use crate::backtest::{BacktestResult, Backtester};
+use crate::clock::{Clock, VirtualClock};
+use crate::config::BotConfig;
+use crate::recording::{MarketEvent, RecordedEvent};
+use crate::schedule::DEFAULT_POLL_INTERVAL_MS;
+use crate::{MarketDataFeed, OrderBook, Price};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const MS_PER_YEAR: f64 = 365.0 * 86_400_000.0;
+
+// The simulated market: each symbol follows a geometric Brownian motion,
+// one step per price poll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationConfig {
+    // Fixes every random draw; None seeds from entropy (and logs the seed)
+    pub seed: Option<u64>,
+    pub start_price: f64,
+    // Per-symbol starting prices that override start_price
+    pub start_prices: HashMap<String, f64>,
+    // Annualized drift and volatility of the walk
+    pub drift: f64,
+    pub volatility: f64,
+    // Time each step represents
+    pub tick_interval_ms: u64,
+    // Per-tick volume is drawn uniformly from this range
+    pub min_volume: f64,
+    pub max_volume: f64,
+    pub book_levels: usize,
+    // Distance between successive book levels, starting from the price
+    pub level_step_bps: f64,
+    // Virtual start time of a `simulate` run, and how long it runs
+    pub start_time_ms: u64,
+    pub duration_secs: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            start_price: 100.0,
+            start_prices: HashMap::new(),
+            drift: 0.0,
+            volatility: 0.8,
+            tick_interval_ms: DEFAULT_POLL_INTERVAL_MS,
+            min_volume: 100.0,
+            max_volume: 10_000.0,
+            book_levels: 5,
+            level_step_bps: 1.0,
+            // 2024-01-01T00:00:00Z
+            start_time_ms: 1_704_067_200_000,
+            duration_secs: 60,
+        }
+    }
+}
+
+impl SimulationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.start_price <= 0.0 || self.start_prices.values().any(|p| *p <= 0.0) {
+            return Err("Simulation start prices must be positive".to_string());
+        }
+        if self.volatility < 0.0 {
+            return Err("Simulation volatility must be non-negative".to_string());
+        }
+        if self.tick_interval_ms == 0 {
+            return Err("Simulation tick_interval_ms must be positive".to_string());
+        }
+        if self.min_volume < 0.0 || self.max_volume <= self.min_volume {
+            return Err("Simulation volume range must satisfy 0 <= min < max".to_string());
+        }
+        if self.book_levels == 0 || self.level_step_bps <= 0.0 {
+            return Err("Simulation books need at least one level and a positive step".to_string());
+        }
+        Ok(())
+    }
+}
+
+// One symbol's walk. Prices and books draw from separate streams, so how
+// often books are requested never changes the price path.
+struct SymbolWalk {
+    price: f64,
+    price_rng: StdRng,
+    book_rng: StdRng,
+}
+
+/// Seeded GBM price paths and books around them. Every symbol's streams are
+/// derived from the seed and the symbol name alone, so a symbol's series is
+/// the same however calls for different symbols interleave.
+pub struct SimulatedMarket {
+    config: SimulationConfig,
+    seed: u64,
+    walks: HashMap<String, SymbolWalk>,
+}
+
+impl SimulatedMarket {
+    pub fn new(config: &SimulationConfig) -> Self {
+        Self {
+            config: config.clone(),
+            seed: config.seed.unwrap_or_else(rand::random),
+            walks: HashMap::new(),
+        }
+    }
+
+    fn walk(&mut self, symbol: &str) -> &mut SymbolWalk {
+        // Logged once the market is actually used, so an unseeded run can be
+        // reproduced
+        if self.walks.is_empty() && self.config.seed.is_none() {
+            println!("Simulation seed: {}", self.seed);
+        }
+        let (config, seed) = (&self.config, self.seed);
+        self.walks.entry(symbol.to_string()).or_insert_with(|| {
+            let stream = seed ^ fnv1a(symbol);
+            SymbolWalk {
+                price: config
+                    .start_prices
+                    .get(symbol)
+                    .copied()
+                    .unwrap_or(config.start_price),
+                price_rng: StdRng::seed_from_u64(stream),
+                book_rng: StdRng::seed_from_u64(stream.rotate_left(32) ^ 0x9e37_79b9_7f4a_7c15),
+            }
+        })
+    }
+
+    // Steps the symbol's walk once and returns the new tick
+    pub fn next_price(&mut self, symbol: &str, timestamp: u64) -> Price {
+        let dt = self.config.tick_interval_ms as f64 / MS_PER_YEAR;
+        let (drift, volatility) = (self.config.drift, self.config.volatility);
+        let (min_volume, max_volume) = (self.config.min_volume, self.config.max_volume);
+        let walk = self.walk(symbol);
+        let shock = standard_normal(&mut walk.price_rng);
+        walk.price *=
+            ((drift - volatility * volatility / 2.0) * dt + volatility * dt.sqrt() * shock).exp();
+        Price {
+            symbol: symbol.to_string(),
+            price: walk.price,
+            timestamp,
+            volume: walk.price_rng.gen_range(min_volume..max_volume),
+        }
+    }
+
+    // A book around the symbol's current price, with random depth per level
+    pub fn book(&mut self, symbol: &str, timestamp: u64) -> OrderBook {
+        let (levels, step_bps) = (self.config.book_levels, self.config.level_step_bps);
+        let walk = self.walk(symbol);
+        let step = walk.price * step_bps / 10_000.0;
+        let mut bids = Vec::with_capacity(levels);
+        let mut asks = Vec::with_capacity(levels);
+        for i in 1..=levels {
+            bids.push((
+                walk.price - i as f64 * step,
+                walk.book_rng.gen_range(10.0..1000.0),
+            ));
+            asks.push((
+                walk.price + i as f64 * step,
+                walk.book_rng.gen_range(10.0..1000.0),
+            ));
+        }
+        OrderBook::new(symbol.to_string(), bids, asks, timestamp)
+    }
+}
+
+// Box-Muller
+pub(crate) fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+// Stable across runs and platforms, unlike the std hasher
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// What a simulation did
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    // Every tick generated, in order
+    pub prices: Vec<Price>,
+    pub result: BacktestResult,
+}
+
+/// Runs the bot against the simulated market on a virtual clock. The feed
+/// is sampled on the live loop's `[schedule]` (a price per symbol every poll
+/// interval, a book per symbol every evaluation interval, or after each
+/// price when that is zero) and the events are
+/// replayed through the bot in order on a single task, so a seeded run is
+/// exactly repeatable and takes no wall-clock time.
+pub struct Simulator {
+    config: BotConfig,
+}
+
+impl Simulator {
+    pub fn new(config: &BotConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    pub async fn run(&self) -> SimulationResult {
+        let settings = &self.config.simulation;
+        let clock = Arc::new(VirtualClock::new(settings.start_time_ms));
+        let feed =
+            MarketDataFeed::with_simulation(self.config.symbols.clone(), settings, clock.clone());
+
+        let end_ms = settings.start_time_ms + settings.duration_secs * 1_000;
+        let schedule = &self.config.schedule;
+        let evaluation_ms = schedule
+            .evaluation_interval()
+            .map(|interval| interval.as_millis() as u64);
+        let mut events = Vec::new();
+        let mut prices = Vec::new();
+        let mut next_poll_ms: Vec<u64> = vec![settings.start_time_ms; self.config.symbols.len()];
+        let mut next_evaluation_ms = settings.start_time_ms;
+        while clock.now_ms() < end_ms {
+            let received_at_ms = clock.now_ms();
+            let mut polled = Vec::new();
+            for (symbol, next) in self.config.symbols.iter().zip(next_poll_ms.iter_mut()) {
+                if received_at_ms < *next {
+                    continue;
+                }
+                if let Some(price) = feed.get_price(symbol).await {
+                    prices.push(price.clone());
+                    events.push(RecordedEvent {
+                        received_at_ms,
+                        event: MarketEvent::Price(price),
+                    });
+                }
+                *next += schedule.poll_interval(symbol).as_millis() as u64;
+                polled.push(symbol);
+            }
+            let evaluated = match evaluation_ms {
+                Some(ms) if received_at_ms >= next_evaluation_ms => {
+                    next_evaluation_ms += ms;
+                    self.config.symbols.iter().collect()
+                }
+                Some(_) => Vec::new(),
+                // As fast as the data: a book right after each price
+                None => polled,
+            };
+            for symbol in evaluated {
+                if let Some(book) = feed.get_orderbook(symbol).await {
+                    events.push(RecordedEvent {
+                        received_at_ms,
+                        event: MarketEvent::Book(book),
+                    });
+                }
+            }
+
+            let next_ms = next_poll_ms
+                .iter()
+                .copied()
+                .chain(evaluation_ms.map(|_| next_evaluation_ms))
+                .min()
+                .unwrap_or(end_ms);
+            clock.advance_to(next_ms.max(received_at_ms + 1));
+        }
+
+        SimulationResult {
+            prices,
+            result: Backtester::new(&self.config).replay(&events).await,
+        }
+    }
+}
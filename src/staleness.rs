@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Per-symbol halt when market data stops arriving
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StalenessConfig {
+    pub enabled: bool,
+    // A symbol is stale once its newest price or book is older than this
+    pub max_age_ms: u64,
+}
+
+impl Default for StalenessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age_ms: 5_000,
+        }
+    }
+}
+
+impl StalenessConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.max_age_ms == 0 {
+            return Err("Staleness max_age_ms must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+// How old a symbol's newest data is, in milliseconds; None if none has
+// arrived yet
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DataAge {
+    pub price_ms: Option<u64>,
+    pub book_ms: Option<u64>,
+}
+
+impl DataAge {
+    // The older of the two
+    pub fn max_ms(&self) -> Option<u64> {
+        self.price_ms.max(self.book_ms)
+    }
+}
+
+// A symbol crossing the staleness threshold, either way
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Freshness {
+    BecameStale { age_ms: u64 },
+    BecameFresh,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SymbolData {
+    // Bot clock time the newest price was received at
+    price_at_ms: Option<u64>,
+    // Venue timestamp of the newest book
+    book_at_ms: Option<u64>,
+    stale: bool,
+}
+
+/// When each symbol last had a price and a book. A book's age is taken
+/// from its own timestamp, since a dead feed can keep handing out the
+/// last book it had; a price's from when it was received.
+#[derive(Debug, Clone, Default)]
+pub struct FreshnessTracker {
+    symbols: HashMap<String, SymbolData>,
+}
+
+impl FreshnessTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_price(&mut self, symbol: &str, now_ms: u64) {
+        self.symbols
+            .entry(symbol.to_string())
+            .or_default()
+            .price_at_ms = Some(now_ms);
+    }
+
+    pub fn record_book(&mut self, symbol: &str, book_timestamp_ms: u64) {
+        let data = self.symbols.entry(symbol.to_string()).or_default();
+        data.book_at_ms = data.book_at_ms.max(Some(book_timestamp_ms));
+    }
+
+    pub fn age(&self, symbol: &str, now_ms: u64) -> DataAge {
+        let Some(data) = self.symbols.get(symbol) else {
+            return DataAge::default();
+        };
+        DataAge {
+            price_ms: data.price_at_ms.map(|at| now_ms.saturating_sub(at)),
+            book_ms: data.book_at_ms.map(|at| now_ms.saturating_sub(at)),
+        }
+    }
+
+    pub fn remove(&mut self, symbol: &str) {
+        self.symbols.remove(symbol);
+    }
+
+    pub fn is_stale(&self, symbol: &str) -> bool {
+        self.symbols.get(symbol).is_some_and(|data| data.stale)
+    }
+
+    // Re-evaluates the symbol against `max_age_ms`, returning the change if
+    // it just went stale or recovered. Data that has never arrived doesn't
+    // count as stale: the symbol simply has nothing to trade on yet.
+    pub fn check(&mut self, symbol: &str, now_ms: u64, max_age_ms: u64) -> Option<Freshness> {
+        let age = self.age(symbol, now_ms).max_ms()?;
+        let data = self.symbols.get_mut(symbol)?;
+        let stale = age > max_age_ms;
+        if stale == data.stale {
+            return None;
+        }
+        data.stale = stale;
+        Some(if stale {
+            Freshness::BecameStale { age_ms: age }
+        } else {
+            Freshness::BecameFresh
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use crate::Position;
+use crate::orders::OrderState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Periodic snapshots of positions and working orders, reloaded by
+// TradingBot::recover after a restart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    pub snapshot_interval_ms: u64,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("state.json"),
+            snapshot_interval_ms: 1_000,
+        }
+    }
+}
+
+impl StateConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.snapshot_interval_ms == 0 {
+            return Err("State snapshot_interval_ms must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What the bot needs to pick up where it left off. Protective stops and
+/// brackets are left out: recovery places fresh ones around the restored
+/// positions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BotSnapshot {
+    // Milliseconds since the epoch, on the bot's clock
+    pub saved_at_ms: u64,
+    pub positions: Vec<Position>,
+    pub balances: HashMap<String, f64>,
+    pub daily_pnl: f64,
+    pub working_orders: Vec<OrderState>,
+    // Cancel thresholds of the one-cancels-other groups those orders are in
+    pub link_thresholds: HashMap<String, f64>,
+}
+
+/// Writes the snapshot to a temporary file beside `path` and renames it
+/// into place, so a crash mid-write leaves the previous snapshot intact
+pub fn save_snapshot(path: &Path, snapshot: &BotSnapshot) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(snapshot)
+        .map_err(|e| format!("Failed to encode state snapshot: {}", e))?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, json)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|e| format!("Failed to write state {}: {}", path.display(), e))
+}
+
+// None if no snapshot has been written yet
+pub fn load_snapshot(path: &Path) -> Result<Option<BotSnapshot>, String> {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read state {}: {}", path.display(), e)),
+    };
+    serde_json::from_slice(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse state {}: {}", path.display(), e))
+}
@@ -0,0 +1,211 @@
+use crate::shadow::StrategyMode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Benching strategies that keep losing or keep being rejected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreakConfig {
+    pub enabled: bool,
+    // Losing trades in a row that bench a strategy; 0 never does
+    pub max_losing_trades: u32,
+    // Risk rejections in a row that bench a strategy; 0 never does
+    pub max_rejections: u32,
+    // Shadow (keeps its hypothetical PnL going) or disabled
+    pub bench_mode: StrategyMode,
+    // How long a strategy sits out before it goes back to its old mode
+    pub cooldown_secs: u64,
+}
+
+impl Default for StreakConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_losing_trades: 5,
+            max_rejections: 10,
+            bench_mode: StrategyMode::Shadow,
+            cooldown_secs: 3_600,
+        }
+    }
+}
+
+impl StreakConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.bench_mode == StrategyMode::Live {
+            return Err("Streak bench_mode must be shadow or disabled".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Which streak benched a strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreakKind {
+    LosingTrades,
+    Rejections,
+}
+
+impl StreakKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StreakKind::LosingTrades => "losing_trades",
+            StreakKind::Rejections => "rejections",
+        }
+    }
+
+    // "5 losing trades", for messages
+    pub fn describe(self, count: u32) -> String {
+        match self {
+            StreakKind::LosingTrades => format!("{} losing trades", count),
+            StreakKind::Rejections => format!("{} risk rejections", count),
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "losing_trades" => Some(StreakKind::LosingTrades),
+            "rejections" => Some(StreakKind::Rejections),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for StreakKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A strategy sitting out a cooldown
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bench {
+    pub kind: StreakKind,
+    // Seconds
+    pub until: u64,
+    // The mode it goes back to
+    pub restore: StrategyMode,
+}
+
+// One strategy's streaks, as journaled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Streak {
+    pub losing_trades: u32,
+    pub rejections: u32,
+    pub benched: Option<Bench>,
+}
+
+/// Consecutive losing trades and risk rejections per strategy. A winning
+/// trade resets the losing streak and an order that passes the risk checks
+/// resets the rejection streak. A strategy is benched when either streak
+/// reaches its threshold, and its streaks start over when it comes back.
+#[derive(Debug, Clone, Default)]
+pub struct StreakTracker {
+    config: StreakConfig,
+    streaks: BTreeMap<String, Streak>,
+}
+
+impl StreakTracker {
+    pub fn new(config: StreakConfig) -> Self {
+        Self {
+            config,
+            streaks: BTreeMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &StreakConfig {
+        &self.config
+    }
+
+    // Streaks as journaled before a restart
+    pub fn restore(&mut self, streaks: Vec<(String, Streak)>) {
+        self.streaks.extend(streaks);
+    }
+
+    pub fn streak(&self, strategy: &str) -> Streak {
+        self.streaks.get(strategy).copied().unwrap_or_default()
+    }
+
+    pub fn benched(&self) -> impl Iterator<Item = (&str, Bench)> {
+        self.streaks
+            .iter()
+            .filter_map(|(name, streak)| Some((name.as_str(), streak.benched?)))
+    }
+
+    /// Books a closed trade's PnL; returns the losing streak if it just
+    /// reached the threshold. Breaking even leaves the streak as it is.
+    pub fn record_trade(&mut self, strategy: &str, pnl: f64) -> Option<u32> {
+        let threshold = self.config.max_losing_trades;
+        let streak = self.streaks.entry(strategy.to_string()).or_default();
+        if pnl > 0.0 {
+            streak.losing_trades = 0;
+        } else if pnl < 0.0 {
+            streak.losing_trades += 1;
+        }
+        (threshold > 0 && streak.benched.is_none() && streak.losing_trades >= threshold)
+            .then_some(streak.losing_trades)
+    }
+
+    // Books a risk rejection; returns the streak if it just reached the
+    // threshold
+    pub fn record_rejection(&mut self, strategy: &str) -> Option<u32> {
+        let threshold = self.config.max_rejections;
+        let streak = self.streaks.entry(strategy.to_string()).or_default();
+        streak.rejections += 1;
+        (threshold > 0 && streak.benched.is_none() && streak.rejections >= threshold)
+            .then_some(streak.rejections)
+    }
+
+    // An order passed the risk checks; true if that broke a streak
+    pub fn record_accepted(&mut self, strategy: &str) -> bool {
+        match self.streaks.get_mut(strategy) {
+            Some(streak) if streak.rejections > 0 => {
+                streak.rejections = 0;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Benches the strategy until `now_secs` plus the cooldown
+    pub fn bench(
+        &mut self,
+        strategy: &str,
+        kind: StreakKind,
+        restore: StrategyMode,
+        now_secs: u64,
+    ) -> Bench {
+        let bench = Bench {
+            kind,
+            until: now_secs + self.config.cooldown_secs,
+            restore,
+        };
+        self.streaks
+            .entry(strategy.to_string())
+            .or_default()
+            .benched = Some(bench);
+        bench
+    }
+
+    // Benched strategies whose cooldown is over, with the mode each goes
+    // back to; their streaks start over
+    pub fn take_expired(&mut self, now_secs: u64) -> Vec<(String, StrategyMode)> {
+        let mut expired = Vec::new();
+        for (name, streak) in &mut self.streaks {
+            if let Some(bench) = streak.benched.filter(|bench| bench.until <= now_secs) {
+                *streak = Streak::default();
+                expired.push((name.clone(), bench.restore));
+            }
+        }
+        expired
+    }
+
+    // The strategy's mode was set by hand: its streaks and any cooldown are
+    // dropped. True if there was anything to drop.
+    pub fn clear(&mut self, strategy: &str) -> bool {
+        self.streaks
+            .remove(strategy)
+            .is_some_and(|streak| streak != Streak::default())
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+// What happens when one of the bot's tasks panics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskFailurePolicy {
+    // Run the task again, up to max_task_restarts times
+    #[default]
+    Restart,
+    // Stop the bot as stop() would: orders cancelled, state saved
+    Shutdown,
+}
+
+// How strategy and task panics are contained
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SupervisionConfig {
+    // Consecutive panics after which a strategy is disabled
+    pub max_strategy_panics: u32,
+    pub on_task_failure: TaskFailurePolicy,
+    // Restarts per task before the bot shuts down instead
+    pub max_task_restarts: u32,
+    // Pause before a failed task is restarted
+    pub restart_delay_ms: u64,
+}
+
+impl Default for SupervisionConfig {
+    fn default() -> Self {
+        Self {
+            max_strategy_panics: 3,
+            on_task_failure: TaskFailurePolicy::Restart,
+            max_task_restarts: 5,
+            restart_delay_ms: 1_000,
+        }
+    }
+}
+
+impl SupervisionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_strategy_panics == 0 {
+            return Err("Supervision max_strategy_panics must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+// The message a panic was raised with, when it was a string
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
@@ -0,0 +1,931 @@
+use crate::{ExecutionError, Order, OrderSide, OrderType};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, LazyLock, RwLock};
+
+// Slack for quantities that are already on a step but landed a hair under
+// it in floating point (0.123 / 0.001 = 122.99999...)
+const STEP_EPSILON: f64 = 1e-9;
+
+// Quote assets a concatenated venue symbol (SOLUSDT) is split on when it
+// wasn't mapped from ours first, longest first
+const COMMON_QUOTES: &[&str] = &[
+    "FDUSD", "USDT", "USDC", "BUSD", "TUSD", "USD", "EUR", "TRY", "BTC", "ETH", "BNB",
+];
+
+// What trading a symbol buys: the asset itself, or a perpetual futures
+// contract on it, settled in the quote asset and traded on margin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstrumentType {
+    #[default]
+    Spot,
+    Perp,
+}
+
+impl InstrumentType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InstrumentType::Spot => "spot",
+            InstrumentType::Perp => "perp",
+        }
+    }
+}
+
+impl fmt::Display for InstrumentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A venue's trading rules for one symbol. Orders must sit on the price and
+// quantity grids and clear the minimums or the venue rejects them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolMeta {
+    // Filled in from a "BASE/QUOTE" symbol name when left empty
+    pub base_asset: String,
+    pub quote_asset: String,
+    // Price increment
+    pub tick_size: f64,
+    // Quantity increment
+    pub lot_size: f64,
+    pub min_qty: f64,
+    // Smallest quantity * price accepted, in the quote asset
+    pub min_notional: f64,
+    // Perps are margined and pay funding
+    pub instrument: InstrumentType,
+}
+
+impl Default for SymbolMeta {
+    fn default() -> Self {
+        Self {
+            base_asset: String::new(),
+            quote_asset: String::new(),
+            tick_size: 0.01,
+            lot_size: 0.001,
+            min_qty: 0.0,
+            min_notional: 0.0,
+            instrument: InstrumentType::Spot,
+        }
+    }
+}
+
+impl SymbolMeta {
+    pub fn validate(&self, symbol: &str) -> Result<(), String> {
+        if self.tick_size <= 0.0 || self.lot_size <= 0.0 {
+            return Err(format!(
+                "tick_size and lot_size for {} must be positive",
+                symbol
+            ));
+        }
+        if self.min_qty < 0.0 || self.min_notional < 0.0 {
+            return Err(format!(
+                "min_qty and min_notional for {} must be non-negative",
+                symbol
+            ));
+        }
+        Ok(())
+    }
+
+    // Quantity rounded down to the lot size, so rounding never trades more
+    // than was asked for
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        snap((quantity / self.lot_size + STEP_EPSILON).floor() * self.lot_size)
+    }
+
+    // Limit price rounded passively onto the tick grid (buys down, sells
+    // up), so rounding never makes an order more aggressive
+    pub fn round_limit_price(&self, side: OrderSide, price: f64) -> f64 {
+        let ticks = price / self.tick_size;
+        let ticks = match side {
+            OrderSide::Buy => (ticks + STEP_EPSILON).floor(),
+            OrderSide::Sell => (ticks - STEP_EPSILON).ceil(),
+        };
+        snap(ticks * self.tick_size)
+    }
+
+    // Nearest tick, for stop trigger prices
+    pub fn round_price(&self, price: f64) -> f64 {
+        snap((price / self.tick_size).round() * self.tick_size)
+    }
+
+    /// Puts the order's prices on the tick grid and its quantity on the lot
+    /// grid, then checks the minimums against the rounded order. Market
+    /// orders have no price of their own, so their notional is checked at
+    /// `reference_price` when one is known.
+    pub fn normalize(
+        &self,
+        order: &mut Order,
+        reference_price: Option<f64>,
+    ) -> Result<(), ExecutionError> {
+        order.quantity = self.round_quantity(order.quantity);
+        order.price = order
+            .price
+            .map(|price| self.round_limit_price(order.side, price));
+        order.order_type = match order.order_type {
+            OrderType::StopMarket { stop_price } => OrderType::StopMarket {
+                stop_price: self.round_price(stop_price),
+            },
+            OrderType::StopLimit {
+                stop_price,
+                limit_price,
+            } => OrderType::StopLimit {
+                stop_price: self.round_price(stop_price),
+                limit_price: self.round_limit_price(order.side, limit_price),
+            },
+            other => other,
+        };
+
+        if order.quantity <= 0.0 || order.quantity < self.min_qty {
+            return Err(ExecutionError::BelowMinQuantity {
+                quantity: order.quantity,
+                min_qty: self.min_qty.max(self.lot_size),
+            });
+        }
+
+        let price = match order.order_type {
+            OrderType::StopMarket { stop_price } => Some(stop_price),
+            OrderType::StopLimit { limit_price, .. } => Some(limit_price),
+            _ => order.price.or(reference_price),
+        };
+        if let Some(price) = price {
+            let notional = order.quantity * price;
+            if notional < self.min_notional {
+                return Err(ExecutionError::BelowMinNotional {
+                    notional,
+                    min_notional: self.min_notional,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// Trims the float noise left by multiplying back out of step units
+fn snap(value: f64) -> f64 {
+    (value * 1e12).round() / 1e12
+}
+
+// Trading rules per symbol. Symbols without an entry are passed through
+// unnormalized.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolRegistry {
+    symbols: HashMap<String, SymbolMeta>,
+}
+
+impl SymbolRegistry {
+    pub fn new(symbols: HashMap<String, SymbolMeta>) -> Self {
+        let symbols = symbols
+            .into_iter()
+            .map(|(symbol, mut meta)| {
+                if let Some((base, quote)) = assets(&symbol) {
+                    if meta.base_asset.is_empty() {
+                        meta.base_asset = base.to_string();
+                    }
+                    if meta.quote_asset.is_empty() {
+                        meta.quote_asset = quote.to_string();
+                    }
+                }
+                (symbol, meta)
+            })
+            .collect();
+        Self { symbols }
+    }
+
+    /// Parses a Binance-style exchangeInfo payload (PRICE_FILTER, LOT_SIZE
+    /// and MIN_NOTIONAL / NOTIONAL filters). Symbols are keyed by our name
+    /// for them, as the venue's mapper gives it. The futures payload's
+    /// perpetual contracts are taken as perps; its dated contracts are
+    /// skipped.
+    pub fn from_exchange_info(
+        info: &serde_json::Value,
+        mapper: &SymbolMapper,
+    ) -> Result<Self, String> {
+        let entries = info["symbols"]
+            .as_array()
+            .ok_or("exchange info has no symbols array")?;
+
+        let mut symbols = HashMap::new();
+        for entry in entries {
+            let (Some(venue), Some(base), Some(quote)) = (
+                entry["symbol"].as_str(),
+                entry["baseAsset"].as_str(),
+                entry["quoteAsset"].as_str(),
+            ) else {
+                continue;
+            };
+            let symbol = mapper.from_assets(venue, base, quote);
+            let Some((base, quote)) = assets(&symbol) else {
+                continue;
+            };
+            let instrument = match entry["contractType"].as_str() {
+                None => InstrumentType::Spot,
+                Some("PERPETUAL") => InstrumentType::Perp,
+                Some(_) => continue,
+            };
+            let mut meta = SymbolMeta {
+                base_asset: base.to_string(),
+                quote_asset: quote.to_string(),
+                instrument,
+                ..SymbolMeta::default()
+            };
+            for filter in entry["filters"].as_array().into_iter().flatten() {
+                match filter["filterType"].as_str() {
+                    Some("PRICE_FILTER") => {
+                        meta.tick_size = filter_value(filter, "tickSize")?;
+                    }
+                    Some("LOT_SIZE") => {
+                        meta.lot_size = filter_value(filter, "stepSize")?;
+                        meta.min_qty = filter_value(filter, "minQty")?;
+                    }
+                    // Futures name the minimum "notional"
+                    Some("MIN_NOTIONAL") if filter["notional"].is_string() => {
+                        meta.min_notional = filter_value(filter, "notional")?;
+                    }
+                    Some("MIN_NOTIONAL" | "NOTIONAL") => {
+                        meta.min_notional = filter_value(filter, "minNotional")?;
+                    }
+                    _ => {}
+                }
+            }
+            meta.validate(&symbol)?;
+            symbols.insert(symbol, meta);
+        }
+        Ok(Self { symbols })
+    }
+
+    /// Parses Coinbase's product list (GET /api/v3/brokerage/market/products).
+    /// Products are keyed by our name for their ids, as the venue's mapper
+    /// gives it.
+    pub fn from_coinbase_products(
+        response: &serde_json::Value,
+        mapper: &SymbolMapper,
+    ) -> Result<Self, String> {
+        let entries = response["products"]
+            .as_array()
+            .ok_or("products response has no products array")?;
+
+        let mut symbols = HashMap::new();
+        for entry in entries {
+            let Some(product) = entry["product_id"].as_str() else {
+                continue;
+            };
+            let symbol = mapper.to_canonical(product);
+            let Some((base, quote)) = assets(&symbol) else {
+                continue;
+            };
+            let decimal = |field: &str| {
+                entry[field]
+                    .as_str()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .ok_or_else(|| format!("product {} has no {}", symbol, field))
+            };
+            let meta = SymbolMeta {
+                base_asset: base.to_string(),
+                quote_asset: quote.to_string(),
+                tick_size: decimal("price_increment")?,
+                lot_size: decimal("base_increment")?,
+                min_qty: decimal("base_min_size")?,
+                min_notional: decimal("quote_min_size")?,
+                instrument: InstrumentType::Spot,
+            };
+            meta.validate(&symbol)?;
+            symbols.insert(symbol, meta);
+        }
+        Ok(Self { symbols })
+    }
+
+    // Downloads and parses the venue's exchange info
+    pub async fn fetch(url: &str, mapper: &SymbolMapper) -> Result<Self, String> {
+        let info: serde_json::Value = reqwest::get(url)
+            .await
+            .map_err(|e| format!("Failed to fetch exchange info: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid exchange info: {}", e))?;
+        Self::from_exchange_info(&info, mapper)
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&SymbolMeta> {
+        self.symbols.get(symbol)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SymbolMeta)> {
+        self.symbols
+            .iter()
+            .map(|(symbol, meta)| (symbol.as_str(), meta))
+    }
+
+    pub fn into_inner(self) -> HashMap<String, SymbolMeta> {
+        self.symbols
+    }
+}
+
+// Exchange info sends numbers as strings
+fn filter_value(filter: &serde_json::Value, key: &str) -> Result<f64, String> {
+    match &filter[key] {
+        serde_json::Value::String(s) => s
+            .parse()
+            .map_err(|_| format!("Invalid {} in exchange info: {}", key, s)),
+        serde_json::Value::Number(n) => n
+            .as_f64()
+            .ok_or_else(|| format!("Invalid {} in exchange info", key)),
+        _ => Err(format!("Missing {} in exchange info filter", key)),
+    }
+}
+
+// A symbol's base and quote assets: ("SOL", "USDT") for "SOL/USDT"
+pub fn assets(symbol: &str) -> Option<(&str, &str)> {
+    symbol
+        .split_once('/')
+        .filter(|(base, quote)| !base.is_empty() && !quote.is_empty() && !quote.contains('/'))
+}
+
+// How a venue writes the bot's "BASE/QUOTE"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolFormat {
+    // SOLUSDT
+    Concatenated,
+    // SOL-USD
+    Dashed,
+    // SOL/USDT, as the bot writes it
+    Slashed,
+}
+
+impl SymbolFormat {
+    fn separator(self) -> &'static str {
+        match self {
+            SymbolFormat::Concatenated => "",
+            SymbolFormat::Dashed => "-",
+            SymbolFormat::Slashed => "/",
+        }
+    }
+}
+
+// How a venue names the bot's symbols, in its [venues.<id>.mapping] table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolMapping {
+    // Overrides the venue kind's format
+    pub format: Option<SymbolFormat>,
+    // The venue's name for an asset where it differs from ours, e.g.
+    // USDT = "USD" for a venue that quotes SOL in dollars
+    pub asset_aliases: BTreeMap<String, String>,
+    // The venue's name for a symbol, ahead of the rules
+    pub symbols: BTreeMap<String, String>,
+}
+
+/// Translates between the bot's symbols ("SOL/USDT") and one venue's
+/// ("SOLUSDT", "SOL-USD"), at the venue's connector, so everything past it
+/// only sees ours. A symbol is looked up in the venue's `symbols` first,
+/// then written in its format with its assets aliased. Going back, symbols
+/// mapped before are remembered; otherwise a concatenated symbol is split
+/// on a known quote asset and left as it is if none fits.
+#[derive(Debug)]
+pub struct SymbolMapper {
+    format: SymbolFormat,
+    // Ours to the venue's, and back
+    aliases: BTreeMap<String, String>,
+    unaliases: HashMap<String, String>,
+    overrides: BTreeMap<String, String>,
+    // The venue's symbols back to ours, for every one mapped so far
+    known: RwLock<HashMap<String, String>>,
+}
+
+impl SymbolMapper {
+    pub fn new(format: SymbolFormat, mapping: &SymbolMapping) -> Self {
+        let known = mapping
+            .symbols
+            .iter()
+            .map(|(ours, venue)| (venue.clone(), ours.clone()))
+            .collect();
+        Self {
+            format: mapping.format.unwrap_or(format),
+            unaliases: mapping
+                .asset_aliases
+                .iter()
+                .map(|(ours, venue)| (venue.clone(), ours.clone()))
+                .collect(),
+            aliases: mapping.asset_aliases.clone(),
+            overrides: mapping.symbols.clone(),
+            known: RwLock::new(known),
+        }
+    }
+
+    pub fn format(&self) -> SymbolFormat {
+        self.format
+    }
+
+    // The venue's name for one of our symbols
+    pub fn to_venue(&self, symbol: &str) -> Result<String, String> {
+        if let Some(venue) = self.overrides.get(symbol) {
+            return Ok(venue.clone());
+        }
+        let (base, quote) = assets(symbol).ok_or_else(|| {
+            format!(
+                "{} isn't BASE/QUOTE and has no entry in the venue's mapping.symbols",
+                symbol
+            )
+        })?;
+        let alias = |asset: &str| {
+            self.aliases
+                .get(asset)
+                .cloned()
+                .unwrap_or_else(|| asset.to_string())
+        };
+        let (base, quote) = (alias(base), alias(quote));
+        let separator = self.format.separator();
+        if !separator.is_empty() && (base.contains(separator) || quote.contains(separator)) {
+            return Err(format!(
+                "{} can't be written with {:?} between its assets; map it in the venue's mapping.symbols",
+                symbol, separator
+            ));
+        }
+        let venue = format!("{}{}{}", base, separator, quote);
+        self.known
+            .write()
+            .unwrap()
+            .entry(venue.clone())
+            .or_insert_with(|| symbol.to_string());
+        Ok(venue)
+    }
+
+    // Our name for one of the venue's symbols
+    pub fn to_canonical(&self, venue: &str) -> String {
+        if let Some(symbol) = self.known.read().unwrap().get(venue) {
+            return symbol.clone();
+        }
+        let split = match self.format {
+            SymbolFormat::Concatenated => self
+                .unaliases
+                .keys()
+                .map(String::as_str)
+                .chain(COMMON_QUOTES.iter().copied())
+                .filter(|quote| venue.len() > quote.len() && venue.ends_with(quote))
+                .max_by_key(|quote| quote.len())
+                .map(|quote| venue.split_at(venue.len() - quote.len())),
+            format => venue.split_once(format.separator()),
+        };
+        match split {
+            Some((base, quote)) => self.pair(base, quote),
+            None => venue.to_string(),
+        }
+    }
+
+    /// Our name for a venue symbol whose assets the venue gave, as its
+    /// symbol listings do
+    pub fn from_assets(&self, venue: &str, base: &str, quote: &str) -> String {
+        if let Some(symbol) = self.known.read().unwrap().get(venue) {
+            return symbol.clone();
+        }
+        self.pair(base, quote)
+    }
+
+    // "BASE/QUOTE" from the venue's assets
+    fn pair(&self, base: &str, quote: &str) -> String {
+        let unalias = |asset: &str| {
+            self.unaliases
+                .get(asset)
+                .cloned()
+                .unwrap_or_else(|| asset.to_string())
+        };
+        format!("{}/{}", unalias(base), unalias(quote))
+    }
+}
+
+/// A symbol's index in the process's symbol table: cheap to copy, hash
+/// and compare, and an index into a SymbolMap. Prints and serializes as
+/// the symbol's name, and sorts by it; deserializing a name interns it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymbolId(u32);
+
+impl SymbolId {
+    // The name's id, adding it if it is new
+    pub fn intern(name: &str) -> Self {
+        Symbol::intern(name).id
+    }
+
+    // The id of a name already interned
+    pub fn get(name: &str) -> Option<Self> {
+        SYMBOLS.read().unwrap().get(name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        SYMBOLS.read().unwrap().names[self.index()]
+    }
+
+    pub fn symbol(self) -> Symbol {
+        Symbol {
+            id: self,
+            name: self.as_str(),
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<&str> for SymbolId {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl PartialEq<str> for SymbolId {
+    fn eq(&self, name: &str) -> bool {
+        self.as_str() == name
+    }
+}
+
+impl PartialEq<&str> for SymbolId {
+    fn eq(&self, name: &&str) -> bool {
+        self.as_str() == *name
+    }
+}
+
+impl PartialEq<String> for SymbolId {
+    fn eq(&self, name: &String) -> bool {
+        self.as_str() == name
+    }
+}
+
+impl Ord for SymbolId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if self == other {
+            return std::cmp::Ordering::Equal;
+        }
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialOrd for SymbolId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for SymbolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for SymbolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Serialize for SymbolId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SymbolId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self::intern(&name))
+    }
+}
+
+// Every symbol the process has seen, so an id resolves to its name
+// wherever it ends up
+static SYMBOLS: LazyLock<RwLock<SymbolTable>> = LazyLock::new(Default::default);
+
+/// Interns symbol names. Ids are handed out in order and never reused, so
+/// an id stays valid for the process's lifetime and a symbol subscribed
+/// to again gets its old id back. Names are kept for good, which is what
+/// lets an id hand out its name without a lock guard.
+#[derive(Debug, Default)]
+struct SymbolTable {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, SymbolId>,
+}
+
+impl SymbolTable {
+    fn intern(&mut self, name: &str) -> SymbolId {
+        if let Some(id) = self.get(name) {
+            return id;
+        }
+        let id = SymbolId(self.names.len() as u32);
+        let name: &'static str = Box::leak(name.into());
+        self.names.push(name);
+        self.ids.insert(name, id);
+        id
+    }
+
+    fn get(&self, name: &str) -> Option<SymbolId> {
+        self.ids.get(name).copied()
+    }
+}
+
+/// An interned symbol: its id for lookups and its name for display.
+/// Compares and hashes by id; prints and serializes as the name.
+#[derive(Debug, Clone, Copy)]
+pub struct Symbol {
+    id: SymbolId,
+    name: &'static str,
+}
+
+impl Symbol {
+    // The symbol's handle, adding it to the table if it is new
+    pub fn intern(name: &str) -> Self {
+        if let Some(symbol) = Self::get(name) {
+            return symbol;
+        }
+        let id = SYMBOLS.write().unwrap().intern(name);
+        id.symbol()
+    }
+
+    pub fn get(name: &str) -> Option<Self> {
+        let table = SYMBOLS.read().unwrap();
+        let id = table.get(name)?;
+        Some(Symbol {
+            id,
+            name: table.names[id.index()],
+        })
+    }
+
+    pub fn id(&self) -> SymbolId {
+        self.id
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // The name as an Arc, for holders that keep it
+    pub fn shared_name(&self) -> Arc<str> {
+        Arc::from(self.name)
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name)
+    }
+}
+
+/// Per-symbol values stored in a Vec indexed by SymbolId, so a lookup is
+/// a bounds check rather than a string hash
+#[derive(Debug, Clone)]
+pub struct SymbolMap<T> {
+    slots: Vec<Option<T>>,
+}
+
+impl<T> Default for SymbolMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SymbolMap<T> {
+    pub fn new() -> Self {
+        Self { slots: Vec::new() }
+    }
+
+    pub fn get(&self, id: SymbolId) -> Option<&T> {
+        self.slots.get(id.index())?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: SymbolId) -> Option<&mut T> {
+        self.slots.get_mut(id.index())?.as_mut()
+    }
+
+    pub fn insert(&mut self, id: SymbolId, value: T) -> Option<T> {
+        self.slot(id).replace(value)
+    }
+
+    pub fn get_or_insert_with(&mut self, id: SymbolId, value: impl FnOnce() -> T) -> &mut T {
+        self.slot(id).get_or_insert_with(value)
+    }
+
+    pub fn remove(&mut self, id: SymbolId) -> Option<T> {
+        self.slots.get_mut(id.index())?.take()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SymbolId, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| Some((SymbolId(i as u32), slot.as_ref()?)))
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(Option::as_mut)
+    }
+
+    fn slot(&mut self, id: SymbolId) -> &mut Option<T> {
+        if id.index() >= self.slots.len() {
+            self.slots.resize_with(id.index() + 1, || None);
+        }
+        &mut self.slots[id.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{limit_order, market_order};
+
+    fn meta() -> SymbolMeta {
+        SymbolMeta {
+            tick_size: 0.01,
+            lot_size: 0.001,
+            min_qty: 0.01,
+            min_notional: 5.0,
+            ..SymbolMeta::default()
+        }
+    }
+
+    #[test]
+    fn awkward_values_land_on_the_grids() {
+        let meta = meta();
+        assert_eq!(meta.round_quantity(0.1234567), 0.123);
+        // Already on the grid, a hair under it in floating point
+        assert_eq!(meta.round_quantity(0.123), 0.123);
+        assert_eq!(meta.round_limit_price(OrderSide::Buy, 100.0049), 100.0);
+        assert_eq!(meta.round_limit_price(OrderSide::Sell, 100.0049), 100.01);
+        assert_eq!(meta.round_price(100.0049), 100.0);
+
+        let mut order = limit_order("SOL/USDT", OrderSide::Buy, 0.1234567, 100.0049);
+        meta.normalize(&mut order, None).unwrap();
+        assert_eq!((order.quantity, order.price), (0.123, Some(100.0)));
+    }
+
+    #[test]
+    fn minimums_are_checked_after_rounding() {
+        let meta = meta();
+        // 0.0099 rounds to 0.009, under the minimum quantity
+        let mut order = limit_order("SOL/USDT", OrderSide::Buy, 0.0099, 100.0);
+        assert!(matches!(
+            meta.normalize(&mut order, None),
+            Err(ExecutionError::BelowMinQuantity { .. })
+        ));
+        // 0.0509 rounds to 0.05 and 99.999 to 99.99: 4.9995 of notional
+        let mut order = limit_order("SOL/USDT", OrderSide::Buy, 0.0509, 99.999);
+        assert!(matches!(
+            meta.normalize(&mut order, None),
+            Err(ExecutionError::BelowMinNotional { .. })
+        ));
+        // A market order is checked at the reference price, if there is one
+        let mut order = market_order("SOL/USDT", OrderSide::Buy, 0.04);
+        assert!(meta.normalize(&mut order.clone(), None).is_ok());
+        assert!(matches!(
+            meta.normalize(&mut order, Some(100.0)),
+            Err(ExecutionError::BelowMinNotional { .. })
+        ));
+    }
+
+    fn mapper(format: SymbolFormat, mapping: &str) -> SymbolMapper {
+        SymbolMapper::new(format, &toml::from_str(mapping).unwrap())
+    }
+
+    #[test]
+    fn symbols_round_trip_through_each_venue_format() {
+        let cases = [
+            (SymbolFormat::Concatenated, "", "SOL/USDT", "SOLUSDT"),
+            (SymbolFormat::Concatenated, "", "ETH/BTC", "ETHBTC"),
+            (SymbolFormat::Concatenated, "", "BTC/FDUSD", "BTCFDUSD"),
+            (
+                SymbolFormat::Dashed,
+                "asset_aliases = { USDT = \"USD\" }",
+                "SOL/USDT",
+                "SOL-USD",
+            ),
+            (SymbolFormat::Slashed, "", "SOL/USDT", "SOL/USDT"),
+            (
+                SymbolFormat::Concatenated,
+                "symbols = { \"1000PEPE/USDT\" = \"PEPE1000USDT\" }",
+                "1000PEPE/USDT",
+                "PEPE1000USDT",
+            ),
+        ];
+        for (format, mapping, ours, theirs) in cases {
+            let out = mapper(format, mapping);
+            assert_eq!(out.to_venue(ours).as_deref(), Ok(theirs), "{ours}");
+            assert_eq!(out.to_canonical(theirs), ours);
+            // A fresh mapper, that hasn't written the symbol, reads it back
+            // by the rules alone
+            assert_eq!(mapper(format, mapping).to_canonical(theirs), ours);
+        }
+        let dashed = mapper(SymbolFormat::Dashed, "asset_aliases = { USDT = \"USD\" }");
+        assert_eq!(dashed.from_assets("SOL-USD", "SOL", "USD"), "SOL/USDT");
+        // Nothing to split on: left as the venue wrote it
+        assert_eq!(
+            mapper(SymbolFormat::Concatenated, "").to_canonical("XYZ"),
+            "XYZ"
+        );
+    }
+
+    #[test]
+    fn a_symbol_a_venue_cant_write_is_an_error_at_startup() {
+        let binance = mapper(SymbolFormat::Concatenated, "");
+        assert!(binance.to_venue("SOLUSDT").is_err());
+        assert!(binance.to_venue("SOL/USDT/PERP").is_err());
+        let dashed = mapper(SymbolFormat::Dashed, "");
+        let error = dashed.to_venue("SOL-X/USD").unwrap_err();
+        assert!(error.contains("mapping.symbols"), "{error}");
+
+        let mut venues = crate::venue::VenuesConfig::default();
+        venues.venues.insert(
+            crate::venue::VenueId::new("coinbase"),
+            crate::venue::VenueConfig::Coinbase(crate::coinbase::CoinbaseConfig::default()),
+        );
+        assert!(venues.validate_symbols(&["SOL/USDT".to_string()]).is_ok());
+        let error = venues
+            .validate_symbols(&["SOL/USDT".to_string(), "SOLUSDT".to_string()])
+            .unwrap_err();
+        assert!(error.starts_with("Venue coinbase: SOLUSDT"), "{error}");
+    }
+
+    #[test]
+    fn interning_a_name_again_gives_back_its_id() {
+        let sol = Symbol::intern("SOL/USDT");
+        let btc = Symbol::intern("BTC/USDT");
+        assert_ne!(sol.id(), btc.id());
+        assert_eq!(Symbol::intern("SOL/USDT").id(), sol.id());
+        assert_eq!(SymbolId::intern("SOL/USDT"), sol.id());
+        assert_eq!(SymbolId::get("BTC/USDT"), Some(btc.id()));
+        assert_eq!(SymbolId::get("NEVER/INTERNED"), None);
+
+        assert_eq!(sol.id().as_str(), "SOL/USDT");
+        assert_eq!(btc.id().symbol(), btc);
+        assert_eq!(btc.id().symbol().name(), "BTC/USDT");
+    }
+
+    #[test]
+    fn symbols_compare_by_id_but_print_their_name() {
+        use std::hash::{BuildHasher, RandomState};
+
+        let sol = Symbol::intern("SOL/USDT");
+        // Another name under the same id is still the same symbol
+        let renamed = Symbol {
+            id: sol.id(),
+            name: "renamed",
+        };
+        assert_eq!(sol, renamed);
+        let hasher = RandomState::new();
+        assert_eq!(hasher.hash_one(sol), hasher.hash_one(renamed));
+        assert_eq!(hasher.hash_one(sol), hasher.hash_one(sol.id()));
+
+        assert_eq!(sol.to_string(), "SOL/USDT");
+        assert_eq!(sol.id().to_string(), "SOL/USDT");
+        assert_eq!(format!("{:?}", sol.id()), "\"SOL/USDT\"");
+        assert_eq!(serde_json::to_string(&sol).unwrap(), "\"SOL/USDT\"");
+        assert_eq!(serde_json::to_string(&sol.id()).unwrap(), "\"SOL/USDT\"");
+        assert!(sol.id() == "SOL/USDT");
+
+        // A name read back is interned
+        let eth: SymbolId = serde_json::from_str("\"ETH/USDT\"").unwrap();
+        assert_eq!(SymbolId::get("ETH/USDT"), Some(eth));
+        assert_eq!(eth.as_str(), "ETH/USDT");
+    }
+
+    #[test]
+    fn a_symbol_map_grows_past_gaps_and_skips_them() {
+        let mut map = SymbolMap::new();
+        assert_eq!(map.insert(SymbolId(3), "third"), None);
+        assert_eq!(map.slots.len(), 4);
+        assert_eq!(map.get(SymbolId(0)), None);
+        assert_eq!(map.get(SymbolId(3)), Some(&"third"));
+        assert_eq!(map.get(SymbolId(9)), None);
+        map.insert(SymbolId(1), "first");
+        assert_eq!(map.insert(SymbolId(3), "again"), Some("third"));
+
+        assert_eq!(map.remove(SymbolId(1)), Some("first"));
+        assert_eq!(map.get(SymbolId(1)), None);
+        assert_eq!(map.remove(SymbolId(1)), None);
+        assert_eq!(map.remove(SymbolId(9)), None);
+        assert_eq!(map.slots.len(), 4);
+
+        let entries: Vec<_> = map.iter().collect();
+        assert_eq!(entries, vec![(SymbolId(3), &"again")]);
+    }
+}
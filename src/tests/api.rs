@@ -0,0 +1,228 @@
+use super::{Scripted, config, with_id};
+use crate::builder::TradingBotBuilder;
+use crate::fixtures::limit_order;
+use crate::{OrderSide, StrategyEntry, TradingBot, api};
+use reqwest::{Method, StatusCode};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+const TOKEN: &str = "secret";
+
+// A paper bot trading SOL/USDT on the simulated market every 10 ms, with
+// its API on a free port
+async fn running_bot(strategy: Scripted) -> (TradingBot, String, tokio::task::JoinHandle<()>) {
+    let mut config = config(&["SOL/USDT"]);
+    config.simulation.seed = Some(1);
+    config.schedule.poll_interval_ms = 10;
+    config.schedule.evaluation_interval_ms = 10;
+    // Its minute-long sleep would hold up the shutdown
+    config.attribution.log_interval_secs = 0;
+    config.api.enabled = true;
+    config.api.bind = "127.0.0.1:0".to_string();
+    config.api.auth_token = Some(TOKEN.to_string());
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_strategy_entry(StrategyEntry::new(Box::new(strategy)))
+        .assemble();
+    let addr = api::serve(bot.clone(), &config.api).await.unwrap();
+    let running = tokio::spawn({
+        let bot = bot.clone();
+        async move { bot.start(vec!["SOL/USDT".to_string()]).await }
+    });
+    (bot, format!("http://{addr}"), running)
+}
+
+async fn call(method: Method, url: String, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = reqwest::Client::new()
+        .request(method, url)
+        .bearer_auth(TOKEN);
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let response = request.send().await.unwrap();
+    (response.status(), response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn every_endpoint_answers_against_a_paper_bot() {
+    let (buyer, script) = Scripted::new("buyer");
+    *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    let (bot, base, running) = running_bot(buyer).await;
+    let get = |path: &str| call(Method::GET, format!("{base}{path}"), None);
+    let post = |path: &str, body| call(Method::POST, format!("{base}{path}"), body);
+
+    // Buy once the history has warmed up, then go quiet
+    for _ in 0..200 {
+        if !bot.positions().await.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    *script.lock().unwrap() = None;
+    let order = limit_order("SOL/USDT", OrderSide::Buy, 0.5, 1.0);
+    bot.order_executor
+        .submit_order(with_id(order, "resting"))
+        .await
+        .unwrap();
+
+    let anonymous = reqwest::get(format!("{base}/positions")).await.unwrap();
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+    let (status, body) = get("/status").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["mode"], "paper");
+    assert_eq!(body["halted"], false);
+
+    let (_, positions) = get("/positions").await;
+    assert_eq!(positions[0]["symbol"], "SOL/USDT");
+    assert_eq!(positions[0]["quantity"], 1.0);
+    let (_, orders) = get("/orders/open").await;
+    assert_eq!(orders.as_array().unwrap().len(), 1);
+    assert_eq!(orders[0]["order"]["id"], "resting");
+    let (status, pnl) = get("/pnl").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(pnl["equity"].as_f64().unwrap() > 0.0);
+    assert_eq!(get("/report").await.0, StatusCode::OK);
+    assert_eq!(get("/equity").await.0, StatusCode::OK);
+
+    let (_, strategies) = get("/strategies").await;
+    assert_eq!(strategies[0]["name"], "buyer");
+    assert_eq!(strategies[0]["enabled"], true);
+    let (_, buyer) = post("/strategies/buyer/disable", None).await;
+    assert_eq!(buyer["enabled"], false);
+    let (_, buyer) = post("/strategies/buyer/enable", None).await;
+    assert_eq!(buyer["enabled"], true);
+    assert_eq!(
+        post("/strategies/nobody/enable", None).await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        post("/strategies/buyer/explode", None).await.0,
+        StatusCode::NOT_FOUND
+    );
+
+    let (status, symbols) = post("/symbols", Some(json!({ "symbol": "ETH/USDT" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(symbols.as_array().unwrap().contains(&json!("ETH/USDT")));
+    let (status, _) = post("/symbols", Some(json!({ "symbol": "ETHUSDT" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post("/symbols", Some(json!({ "symbol": "ETH/USDT" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (_, symbols) = get("/symbols").await;
+    assert_eq!(symbols.as_array().unwrap().len(), 2);
+    let delete = |path: &str| call(Method::DELETE, format!("{base}{path}"), None);
+    let (status, report) = delete("/symbols/ETH/USDT").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["flattened"], false);
+    assert_eq!(delete("/symbols/ETH/USDT").await.0, StatusCode::NOT_FOUND);
+
+    let signal = json!({
+        "symbol": "SOL/USDT",
+        "side": "sell",
+        "target_price": 100.0,
+        "quantity": 0.5,
+        "strategy": "webhook",
+    });
+    let (status, accepted) = post("/signals", Some(signal)).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(accepted["side"], "Sell");
+    assert_eq!(accepted["quantity"], 0.5);
+
+    let (_, report) = post("/kill-switch", None).await;
+    assert!(report["cancelled_orders"].as_u64().unwrap() >= 1);
+    assert_eq!(report["flattened_positions"], json!(["SOL/USDT"]));
+    assert_eq!(get("/status").await.1["halted"], true);
+    let (_, resumed) = post("/resume", None).await;
+    assert_eq!(resumed["halted"], false);
+
+    bot.stop().await;
+    running.await.unwrap();
+}
+
+#[tokio::test]
+async fn a_fill_reaches_the_event_stream() {
+    use futures::StreamExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let (buyer, script) = Scripted::new("buyer");
+    let (bot, base, running) = running_bot(buyer).await;
+    let url = format!(
+        "{}/events?access_token={TOKEN}&price_interval_ms=1000",
+        base.replacen("http", "ws", 1)
+    );
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+
+    let fill = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(message) = socket.next().await {
+            if let Message::Text(text) = message.unwrap() {
+                let event: Value = serde_json::from_str(&text).unwrap();
+                if event["type"] == "fill" {
+                    return event;
+                }
+            }
+        }
+        panic!("the stream closed before a fill");
+    })
+    .await
+    .expect("no fill within 5 s");
+    *script.lock().unwrap() = None;
+
+    assert_eq!(fill["symbol"], "SOL/USDT");
+    assert_eq!(fill["side"], "Buy");
+    assert_eq!(fill["quantity"], 1.0);
+    assert_eq!(fill["strategy"], "buyer");
+    assert_eq!(fill["venue"], "paper");
+    assert!(fill["price"].as_f64().unwrap() > 0.0);
+    assert!(fill["fee"].as_f64().unwrap() > 0.0);
+    assert!(!fill["order_id"].as_str().unwrap().is_empty());
+
+    bot.stop().await;
+    running.await.unwrap();
+}
+
+#[tokio::test]
+async fn a_valid_and_an_expired_signal_make_exactly_one_order() {
+    use crate::events::BotEvent;
+
+    let (quiet, _) = Scripted::new("quiet");
+    let (bot, base, running) = running_bot(quiet).await;
+    let mut events = bot.subscribe_events();
+    let post = |body| call(Method::POST, format!("{base}/signals"), Some(body));
+    let signal = |expires_at: u64| {
+        json!({
+            "symbol": "SOL/USDT",
+            "side": "buy",
+            "target_price": 100.0,
+            "quantity": 0.5,
+            "strategy": "webhook",
+            "expires_at": expires_at,
+        })
+    };
+
+    let (status, accepted) = post(signal(4_000_000_000)).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(accepted["strategy"], "webhook");
+    let (status, rejected) = post(signal(1_700_000_000)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(rejected["error"].as_str().unwrap().contains("expired"));
+    let (status, _) = post(json!({ "symbol": "SOL/USDT", "side": "buy" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Plenty of evaluations for a second order to show up if one would
+    let mut orders = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_millis(500), async {
+        loop {
+            if let Ok(BotEvent::OrderSubmitted(order)) = events.recv().await {
+                orders.push(order);
+            }
+        }
+    })
+    .await;
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].strategy.as_deref(), Some("webhook"));
+    assert_eq!(orders[0].side, OrderSide::Buy);
+    assert_eq!(orders[0].quantity, 0.5);
+
+    bot.stop().await;
+    running.await.unwrap();
+}
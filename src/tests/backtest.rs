@@ -0,0 +1,93 @@
+use crate::aggregator::AggregationPolicy;
+use crate::backtest::{self, BacktestResult, Backtester};
+use crate::config::BotConfig;
+use crate::optimize::{self, OptimizeMetric, Optimizer, WalkForwardConfig};
+use std::fmt::Write;
+
+// The example config, except that any one strategy's signal trades, so the
+// sample data makes some fills
+pub(super) fn sample_config() -> BotConfig {
+    let mut config = BotConfig::from_toml(include_str!("../../config.example.toml")).unwrap();
+    config.aggregation = AggregationPolicy::FirstWins;
+    config
+}
+
+// The summary, positions and every fill, without the random order ids
+fn render(result: &BacktestResult) -> String {
+    let mut out = result.to_string();
+    for fill in &result.fills {
+        writeln!(
+            out,
+            "Fill {} {} {:?} {:.4} @ {:.4} fee {:.4} {}",
+            fill.timestamp,
+            fill.symbol,
+            fill.side,
+            fill.quantity,
+            fill.price,
+            fill.fee,
+            fill.strategy.as_deref().unwrap_or("-")
+        )
+        .unwrap();
+    }
+    out
+}
+
+// Run with UPDATE_GOLDEN=1 to rewrite the expected output after a change
+// that is meant to move it
+#[tokio::test]
+async fn sample_ticks_backtest_matches_the_golden_file() {
+    const GOLDEN: &str = "data/golden/sample_ticks_backtest.txt";
+    let config = sample_config();
+    let records =
+        backtest::parse_csv(include_str!("../../data/sample_ticks.csv"), "SOL/USDT").unwrap();
+    let actual = render(&Backtester::new(&config).run(&records).await);
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+    }
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(actual, expected, "backtest output drifted from {GOLDEN}");
+}
+
+// The example grid over the sample data on `workers` tasks: the ranked
+// sweep and a walk-forward run, as their CSV tables
+async fn optimize_sample(workers: usize) -> (String, String) {
+    let mut config = sample_config();
+    config.optimize.metric = OptimizeMetric::NetPnl;
+    config.optimize.workers = workers;
+    config.optimize.walk_forward = Some(WalkForwardConfig {
+        train_rows: 120,
+        test_rows: 60,
+    });
+    let records =
+        backtest::parse_csv(include_str!("../../data/sample_ticks.csv"), "SOL/USDT").unwrap();
+    let optimizer = Optimizer::new(&config);
+    let sweep = optimizer.sweep(&records).await.unwrap();
+    let windows = optimizer.walk_forward(&records).await.unwrap();
+    (
+        optimize::sweep_csv(&sweep),
+        optimize::walk_forward_csv(&windows),
+    )
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn optimizer_results_depend_only_on_data_and_params() {
+    let (sweep, walk_forward) = optimize_sample(4).await;
+    // Six combinations, and not all alike, or the ranking proves nothing
+    let scores: Vec<&str> = sweep
+        .lines()
+        .skip(1)
+        .map(|row| row.split(',').nth(3).unwrap())
+        .collect();
+    assert_eq!(scores.len(), 6);
+    assert!(scores.iter().any(|score| *score != scores[0]));
+    assert_eq!(walk_forward.lines().count(), 3);
+
+    // Again in parallel, and then one at a time
+    assert_eq!(
+        optimize_sample(4).await,
+        (sweep.clone(), walk_forward.clone())
+    );
+    assert_eq!(optimize_sample(1).await, (sweep, walk_forward));
+}
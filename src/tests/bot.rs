@@ -0,0 +1,2019 @@
+use super::{
+    MockFeed, PausedClock, START_MS, Script, Scripted, TestBot, config, scratch_dir, with_id,
+};
+use crate::aggregator::AggregationPolicy;
+use crate::builder::TradingBotBuilder;
+use crate::candles::Timeframe;
+use crate::clock::Clock;
+use crate::depth_sizing::DepthRemainder;
+use crate::donchian::{DonchianBreakoutStrategy, DonchianExit};
+use crate::events::BotEvent;
+use crate::fees::FeeModel;
+use crate::fixtures::limit_order;
+use crate::latency::Stage;
+use crate::macd::MacdStrategy;
+use crate::monitor::ExitReason;
+use crate::order_guard::DuplicatePolicy;
+use crate::orders::OrderState;
+use crate::rate_limit::{ExhaustedPolicy, RateLimitConfig};
+use crate::reconcile::{ReconcilePolicy, Resolution};
+use crate::scalper::{ScalperParams, SpreadScalperStrategy};
+use crate::schedule::Due;
+use crate::shadow::StrategyMode;
+use crate::streaks::StreakKind;
+use crate::symbols::{InstrumentType, Symbol, SymbolId, SymbolMeta};
+use crate::venue::{Exchange, ExecutionMode, VenueId};
+use crate::{
+    ExecutionError, Fill, MarketData, Order, OrderBook, OrderSide, OrderType, Position,
+    PositionSide, Price, StopMode, StrategyEntry, TimeInForce, TradingBot, TradingSignal,
+    TradingStrategy,
+};
+use futures::future::BoxFuture;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[tokio::test]
+async fn disabled_strategy_places_no_more_orders() {
+    let (alpha, alpha_script) = Scripted::new("alpha");
+    let (beta, beta_script) = Scripted::new("beta");
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT"]),
+        vec![
+            StrategyEntry::new(Box::new(alpha)),
+            StrategyEntry::new(Box::new(beta)),
+        ],
+    );
+    *alpha_script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    let before = bot.submitted();
+    assert!(
+        before
+            .iter()
+            .any(|o| o.strategy.as_deref() == Some("alpha"))
+    );
+
+    assert!(bot.bot.set_strategy_enabled("alpha", false).await);
+    *beta_script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    for i in 0..5 {
+        bot.tick("SOL/USDT", 100.0 + i as f64 * 0.1).await;
+    }
+    let after = bot.submitted();
+    assert!(!after.is_empty());
+    assert!(after.iter().all(|o| o.strategy.as_deref() == Some("beta")));
+
+    let info = bot.bot.list_strategies().await;
+    let alpha = info.iter().find(|s| s.name == "alpha").unwrap();
+    assert!(!alpha.enabled);
+    assert_eq!(alpha.signal_count, 1);
+}
+
+// Indexes past the end of its prices on every pass
+struct Panicking;
+
+impl TradingStrategy for Panicking {
+    fn analyze(&self, prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
+        let _ = prices[prices.len()];
+        None
+    }
+
+    fn name(&self) -> &str {
+        "panicking"
+    }
+}
+
+#[tokio::test]
+async fn a_panicking_strategy_is_disabled_while_the_others_keep_trading() {
+    let (steady, script) = Scripted::new("steady");
+    let mut config = config(&["SOL/USDT"]);
+    config.supervision.max_strategy_panics = 3;
+    let mut bot = TestBot::with_strategies(
+        &config,
+        vec![
+            StrategyEntry::new(Box::new(Panicking)),
+            StrategyEntry::new(Box::new(steady)),
+        ],
+    );
+    *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    let mut panics = Vec::new();
+    let mut orders = 0;
+    for i in 0..5 {
+        bot.tick("SOL/USDT", 100.0 + i as f64 * 0.1).await;
+        for event in bot.events() {
+            match event {
+                BotEvent::StrategyPanicked {
+                    strategy,
+                    consecutive,
+                    disabled,
+                    ..
+                } => panics.push((strategy, consecutive, disabled)),
+                BotEvent::OrderSubmitted(order) => {
+                    assert_eq!(order.strategy.as_deref(), Some("steady"));
+                    orders += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Disabled on its third panic in a row, so passes four and five skip it
+    let panicking = "panicking".to_string();
+    assert_eq!(
+        panics,
+        [
+            (panicking.clone(), 1, false),
+            (panicking.clone(), 2, false),
+            (panicking, 3, true),
+        ]
+    );
+    assert_eq!(orders, 5);
+    let info = bot.bot.list_strategies().await;
+    assert!(!info.iter().find(|s| s.name == "panicking").unwrap().enabled);
+    assert!(info.iter().find(|s| s.name == "steady").unwrap().enabled);
+}
+
+#[tokio::test]
+async fn strategies_only_trade_their_assigned_symbols() {
+    let (sol_only, sol_script) = Scripted::new("sol_only");
+    let (btc_only, btc_script) = Scripted::new("btc_only");
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT", "BTC/USDT"]),
+        vec![
+            StrategyEntry::new(Box::new(sol_only)).with_symbols(vec!["SOL/USDT".to_string()]),
+            StrategyEntry::new(Box::new(btc_only)).with_symbols(vec!["BTC/USDT".to_string()]),
+        ],
+    );
+    *sol_script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    *btc_script.lock().unwrap() = Some((OrderSide::Buy, 0.01));
+    for _ in 0..3 {
+        bot.tick("SOL/USDT", 100.0).await;
+        bot.tick("BTC/USDT", 40_000.0).await;
+    }
+
+    let orders = bot.submitted();
+    assert_eq!(orders.len(), 6);
+    for order in &orders {
+        let expected = match order.symbol.as_str() {
+            "SOL/USDT" => "sol_only",
+            "BTC/USDT" => "btc_only",
+            other => panic!("order on unexpected symbol {other}"),
+        };
+        assert_eq!(order.strategy.as_deref(), Some(expected));
+    }
+    let info = bot.bot.list_strategies().await;
+    let sol = info.iter().find(|s| s.name == "sol_only").unwrap();
+    assert_eq!(sol.signals_by_symbol.get("SOL/USDT"), Some(&3));
+    assert_eq!(sol.signals_by_symbol.get("BTC/USDT"), None);
+}
+
+#[tokio::test]
+async fn conflicting_signals_trade_nothing_under_the_net_policy() {
+    let mut config = config(&["SOL/USDT"]);
+    config.aggregation = AggregationPolicy::ConfidenceWeightedNet { threshold: 0.5 };
+    let (buyer, buy_script) = Scripted::new("buyer");
+    let (seller, sell_script) = Scripted::new("seller");
+    let mut bot = TestBot::with_strategies(
+        &config,
+        vec![
+            StrategyEntry::new(Box::new(buyer)),
+            StrategyEntry::new(Box::new(seller)),
+        ],
+    );
+    *buy_script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    *sell_script.lock().unwrap() = Some((OrderSide::Sell, 1.0));
+    for _ in 0..3 {
+        bot.tick("SOL/USDT", 100.0).await;
+    }
+    assert!(bot.submitted().is_empty());
+    assert!(bot.fills.is_empty());
+}
+
+#[tokio::test]
+async fn kill_switch_leaves_the_account_flat_with_nothing_working() {
+    let (buyer, script) = Scripted::new("buyer");
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT", "BTC/USDT"]),
+        vec![StrategyEntry::new(Box::new(buyer))],
+    );
+    *script.lock().unwrap() = Some((OrderSide::Buy, 2.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    *script.lock().unwrap() = Some((OrderSide::Buy, 0.05));
+    bot.tick("BTC/USDT", 40_000.0).await;
+    *script.lock().unwrap() = None;
+    let executor = bot.bot.order_executor.clone();
+    let resting = [
+        ("SOL/USDT", 90.0),
+        ("SOL/USDT", 91.0),
+        ("BTC/USDT", 39_000.0),
+    ];
+    for (i, (symbol, price)) in resting.into_iter().enumerate() {
+        let mut order = limit_order(symbol, OrderSide::Buy, 0.01, price);
+        order.id = format!("resting-{i}");
+        order.client_order_id = order.id.clone();
+        executor.submit_order(order).await.unwrap();
+    }
+    assert_eq!(bot.bot.positions().await.len(), 2);
+    assert_eq!(bot.bot.open_orders().await.len(), 3);
+
+    let report = bot.bot.kill_switch().await;
+    bot.settle().await;
+    assert_eq!(report.cancelled_orders, 3);
+    assert_eq!(report.flattened_positions.len(), 2);
+    assert!(report.failed_positions.is_empty());
+    assert!(bot.bot.is_halted());
+    assert!(bot.bot.open_orders().await.is_empty());
+    for position in bot.bot.positions().await {
+        assert_eq!(position.quantity, 0.0, "{} not flat", position.symbol);
+    }
+
+    bot.bot.resume().await;
+    assert!(!bot.bot.is_halted());
+}
+
+#[tokio::test]
+async fn drawdown_from_the_peak_halts_before_the_daily_loss_limit() {
+    let mut config = config(&["SOL/USDT"]);
+    config.risk.max_drawdown_pct = 0.05;
+    config.risk.max_daily_loss = 5_000.0;
+    let (buyer, script) = Scripted::new("buyer");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(buyer))]);
+    *script.lock().unwrap() = Some((OrderSide::Buy, 50.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    *script.lock().unwrap() = None;
+    assert_eq!(bot.bot.positions().await[0].quantity, 50.0);
+
+    // Up 10% to set the peak, then a slide
+    let mut prices: Vec<f64> = (1..=40).map(|i| 100.0 + i as f64 * 0.25).collect();
+    prices.extend((1..=60).map(|i| 110.0 - i as f64 * 0.25));
+    let mut halted_at = None;
+    for price in prices {
+        bot.tick("SOL/USDT", price).await;
+        bot.bot.check_drawdown().await;
+        bot.settle().await;
+        if bot.bot.is_halted() {
+            halted_at = Some(price);
+            break;
+        }
+    }
+
+    // 5% below a peak of about 10,500 is about 9,975, which 50 SOL reach
+    // near 99.5; the daily loss limit is nowhere near
+    let halted_at = halted_at.expect("the drawdown breaker never tripped");
+    assert!((99.0..100.0).contains(&halted_at), "halted at {halted_at}");
+    let events = bot.events();
+    assert!(
+        events.iter().any(
+            |e| matches!(e, BotEvent::DrawdownBreached { limit_pct, .. } if *limit_pct == 0.05)
+        )
+    );
+    assert!(
+        !events
+            .iter()
+            .any(|e| matches!(e, BotEvent::DailyLossLimit { .. }))
+    );
+    assert!(bot.bot.risk_manager.daily_pnl().await > -config.risk.max_daily_loss);
+    assert_eq!(bot.bot.positions().await[0].quantity, 0.0);
+}
+
+#[tokio::test]
+async fn kill_switch_cancels_past_an_exhausted_cancel_budget() {
+    let mut config = config(&["SOL/USDT"]);
+    config.rate_limits = RateLimitConfig {
+        cancel_burst: 1,
+        on_exhausted: ExhaustedPolicy::Reject,
+        ..RateLimitConfig::default()
+    };
+    let mut bot = TestBot::with_strategies(&config, Vec::new());
+    bot.tick("SOL/USDT", 100.0).await;
+    let executor = bot.bot.order_executor.clone();
+    for i in 0..3 {
+        let mut order = limit_order("SOL/USDT", OrderSide::Buy, 0.01, 90.0 + i as f64);
+        order.id = format!("resting-{i}");
+        order.client_order_id = order.id.clone();
+        executor.submit_order(order).await.unwrap();
+    }
+    executor.cancel_order("resting-0").await.unwrap();
+    assert!(matches!(
+        executor.cancel_order("resting-1").await,
+        Err(ExecutionError::RateLimited { .. })
+    ));
+
+    let report = bot.bot.kill_switch().await;
+    assert_eq!(report.cancelled_orders, 2);
+    assert!(bot.bot.open_orders().await.is_empty());
+}
+
+#[tokio::test]
+async fn buys_stop_when_cash_runs_out_but_sells_still_pass() {
+    let (trader, script) = Scripted::new("trader");
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT"]),
+        vec![StrategyEntry::new(Box::new(trader))],
+    );
+    // 2,000 USDT a buy against 10,000 of cash
+    *script.lock().unwrap() = Some((OrderSide::Buy, 20.0));
+    for _ in 0..4 {
+        bot.tick("SOL/USDT", 100.0).await;
+    }
+    assert_eq!(bot.submitted().len(), 4);
+    let cash = bot.bot.risk_manager.balances().await["USDT"];
+    assert!(cash < 2_000.0, "cash {cash}");
+
+    bot.tick("SOL/USDT", 100.0).await;
+    let events = bot.events();
+    assert!(events.iter().any(|e| matches!(
+        e,
+        BotEvent::RiskRejection { side: OrderSide::Buy, reason, .. }
+            if reason.starts_with("Insufficient buying power in USDT")
+    )));
+    assert!(
+        !events
+            .iter()
+            .any(|e| matches!(e, BotEvent::OrderSubmitted(_)))
+    );
+
+    *script.lock().unwrap() = Some((OrderSide::Sell, 20.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    let sells = bot.submitted();
+    assert_eq!(sells.len(), 1);
+    assert_eq!(sells[0].side, OrderSide::Sell);
+    assert!(bot.bot.risk_manager.balances().await["USDT"] > cash + 1_900.0);
+}
+
+#[tokio::test]
+async fn unfilled_order_leaves_the_position_unchanged() {
+    let mut bot = TestBot::with_strategies(&config(&["SOL/USDT"]), Vec::new());
+    bot.tick("SOL/USDT", 100.0).await;
+    let mut order = limit_order("SOL/USDT", OrderSide::Buy, 5.0, 95.0);
+    order.id = "never-fills".to_string();
+    order.client_order_id = order.id.clone();
+    bot.bot.order_executor.submit_order(order).await.unwrap();
+    for _ in 0..5 {
+        bot.tick("SOL/USDT", 100.0).await;
+    }
+
+    assert!(bot.fills.is_empty());
+    assert!(bot.bot.positions().await.is_empty());
+    assert_eq!(bot.bot.risk_manager.balances().await["USDT"], 10_000.0);
+    assert_eq!(bot.bot.open_orders().await.len(), 1);
+}
+
+#[tokio::test]
+async fn each_partial_fill_moves_the_position() {
+    let mut bot = TestBot::with_strategies(&config(&["SOL/USDT"]), Vec::new());
+    let book = crate::OrderBook::new(
+        "SOL/USDT".to_string(),
+        vec![(99.9, 10.0)],
+        vec![(100.1, 2.0), (100.2, 3.0)],
+        bot.clock.now_secs(),
+    );
+    bot.evaluate(book).await;
+    let mut order = crate::fixtures::market_order("SOL/USDT", OrderSide::Buy, 4.0);
+    order.id = "sweep".to_string();
+    order.client_order_id = order.id.clone();
+    bot.bot.order_executor.submit_order(order).await.unwrap();
+    bot.settle().await;
+
+    let quantities: Vec<f64> = bot.fills.iter().map(|f| f.quantity).collect();
+    assert_eq!(quantities, [2.0, 2.0]);
+    let position = &bot.bot.positions().await[0];
+    assert_eq!(position.quantity, 4.0);
+    assert!((position.avg_price - 100.15).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn round_trip_at_one_price_loses_exactly_the_fees() {
+    let (trader, script) = Scripted::new("trader");
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT"]),
+        vec![StrategyEntry::new(Box::new(trader))],
+    );
+    // A locked book, so buying and selling both trade at 100
+    let locked = |timestamp| {
+        crate::OrderBook::new(
+            "SOL/USDT".to_string(),
+            vec![(100.0, 100.0)],
+            vec![(100.0, 100.0)],
+            timestamp,
+        )
+    };
+    for side in [OrderSide::Buy, OrderSide::Sell] {
+        *script.lock().unwrap() = Some((side, 10.0));
+        bot.record(crate::Price {
+            symbol: "SOL/USDT".to_string(),
+            price: 100.0,
+            timestamp: bot.clock.now_secs(),
+            volume: 1_000.0,
+        })
+        .await;
+        bot.evaluate(locked(bot.clock.now_secs())).await;
+    }
+
+    assert_eq!(bot.fills.len(), 2);
+    let fees: f64 = bot.fills.iter().map(|f| f.fee).sum();
+    // 10 bps taker on 1,000 each way
+    assert!((fees - 2.0).abs() < 1e-9);
+    let position = &bot.bot.positions().await[0];
+    assert_eq!(position.quantity, 0.0);
+    assert!((position.realized_pnl + fees).abs() < 1e-9);
+    let cash = bot.bot.risk_manager.balances().await["USDT"];
+    assert!((cash - (10_000.0 - fees)).abs() < 1e-9);
+}
+
+// Working orders as (symbol, side, type, quantity, price), sorted
+fn working(orders: &[OrderState]) -> Vec<String> {
+    let mut working: Vec<String> = orders
+        .iter()
+        .map(|state| {
+            let order = &state.order;
+            format!(
+                "{} {:?} {:?} {} {:?} reduce_only={}",
+                order.symbol,
+                order.side,
+                order.order_type,
+                order.quantity,
+                order.price,
+                order.reduce_only
+            )
+        })
+        .collect();
+    working.sort();
+    working
+}
+
+#[tokio::test]
+async fn a_dropped_bot_comes_back_with_its_positions_and_stops() {
+    let mut config = config(&["SOL/USDT"]);
+    config.risk.place_stop_orders = true;
+    config.state.enabled = true;
+    config.state.path = scratch_dir("recover").join("state.json");
+
+    let (buyer, script) = Scripted::new("buyer");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(buyer))]);
+    *script.lock().unwrap() = Some((OrderSide::Buy, 2.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    *script.lock().unwrap() = None;
+    let order = limit_order("SOL/USDT", OrderSide::Buy, 0.5, 90.0);
+    bot.bot
+        .order_executor
+        .submit_order(with_id(order, "resting"))
+        .await
+        .unwrap();
+    let positions = bot.bot.positions().await;
+    let orders = working(&bot.bot.open_orders().await);
+    // The position's stop and the resting buy
+    assert_eq!(orders.len(), 2);
+    bot.bot.save_state().await.unwrap();
+    drop(bot);
+
+    let restarted = TestBot::with_strategies(&config, Vec::new());
+    let report = restarted.bot.recover().await.unwrap().unwrap();
+    assert_eq!(report.positions, ["SOL/USDT"]);
+    assert_eq!(report.working_orders, 1);
+    assert_eq!(report.protected, ["SOL/USDT"]);
+
+    let recovered = restarted.bot.positions().await;
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(recovered[0].quantity, 2.0);
+    assert_eq!(recovered[0].avg_price, positions[0].avg_price);
+    assert_eq!(working(&restarted.bot.open_orders().await), orders);
+}
+
+#[tokio::test]
+async fn every_submitted_order_is_timed_through_each_stage() {
+    let (buyer, script) = Scripted::new("buyer");
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT"]),
+        vec![StrategyEntry::new(Box::new(buyer))],
+    );
+    *script.lock().unwrap() = Some((OrderSide::Buy, 0.1));
+    for i in 0..5 {
+        bot.tick("SOL/USDT", 100.0 + i as f64 * 0.1).await;
+    }
+    let orders = bot.submitted().len();
+    assert!(orders > 0);
+
+    let summary = bot.bot.latency_summary();
+    let stages: Vec<Stage> = summary.stages.iter().map(|s| s.stage).collect();
+    assert_eq!(stages, Stage::ALL);
+    assert!(summary.stages.iter().all(|s| s.count == orders));
+    // Every order's total covers each of its stages
+    let total = summary.stages[4];
+    for stage in &summary.stages[..4] {
+        assert!(stage.p50_us <= total.p50_us && stage.p99_us <= total.p99_us);
+    }
+}
+
+// A live venue that keeps every order it is sent and accepts it as is
+struct Recording {
+    id: VenueId,
+    feed: MockFeed,
+    fees: FeeModel,
+    orders: Arc<Mutex<Vec<Order>>>,
+}
+
+impl Exchange for Recording {
+    fn id(&self) -> &VenueId {
+        &self.id
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        Arc::new(self.feed.clone())
+    }
+
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(async move {
+            let id = order.id.clone();
+            self.orders.lock().unwrap().push(order);
+            Ok(id)
+        })
+    }
+
+    fn cancel_order<'a>(&'a self, _order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn symbol_meta(&self, _symbol: &str) -> Option<SymbolMeta> {
+        None
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        Box::pin(async { Ok(START_MS) })
+    }
+
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        Ok(order.id.clone())
+    }
+}
+
+// One evaluation cycle: a tick and a book from the bot's feed, then a pass
+async fn evaluate_once(bot: &TradingBot, symbol: &str) {
+    let interned = Symbol::intern(symbol);
+    let price = bot.market_feed.get_price(symbol).await.unwrap();
+    bot.record_price(&interned, price).await;
+    let book = bot.market_feed.get_orderbook(symbol).await.unwrap();
+    bot.record_book(&interned, book.clone()).await;
+    let strategies = bot.strategies.read().await;
+    bot.evaluate_symbol(&interned, book, &strategies, Due::ALL)
+        .await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_built_bot_trades_a_mock_feed_through_a_recording_venue() {
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let feed = MockFeed::new(clock.clone(), &[("SOL/USDT", 100.0)]);
+    let orders = Arc::new(Mutex::new(Vec::new()));
+    let venue = Recording {
+        id: VenueId::new("recording"),
+        feed: feed.clone(),
+        fees: FeeModel::default(),
+        orders: orders.clone(),
+    };
+    let (buyer, script) = Scripted::new("buyer");
+    let mut config = config(&["SOL/USDT"]);
+    config.mode = ExecutionMode::Live;
+    config.venues.primary = VenueId::new("recording");
+
+    // No strategy, then no confirmation, is refused
+    let unconfirmed = || {
+        TradingBotBuilder::from_config(&config)
+            .with_clock(clock.clone())
+            .with_venue(Arc::new(Recording {
+                id: VenueId::new("recording"),
+                feed: feed.clone(),
+                fees: FeeModel::default(),
+                orders: Arc::default(),
+            }))
+    };
+    let mut strategyless = config.clone();
+    strategyless.strategies.clear();
+    assert!(
+        TradingBotBuilder::from_config(&strategyless)
+            .build()
+            .is_err()
+    );
+    assert!(
+        unconfirmed()
+            .with_strategy(Box::new(Panicking))
+            .build()
+            .is_err()
+    );
+
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_venue(Arc::new(venue))
+        .with_strategy(Box::new(buyer))
+        .confirm_live()
+        .build()
+        .unwrap();
+    assert_eq!(bot.primary_venue(), &VenueId::new("recording"));
+
+    *script.lock().unwrap() = Some((OrderSide::Buy, 2.0));
+    evaluate_once(&bot, "SOL/USDT").await;
+
+    let orders = orders.lock().unwrap();
+    assert_eq!(orders.len(), 1);
+    let order = &orders[0];
+    assert_eq!(
+        (order.symbol.as_str(), order.side, order.quantity),
+        ("SOL/USDT", OrderSide::Buy, 2.0)
+    );
+    assert_eq!(order.strategy.as_deref(), Some("buyer"));
+    assert_eq!(order.venue, VenueId::new("recording"));
+}
+
+// A venue whose order entry must never be reached
+struct NoOrderEntry {
+    id: VenueId,
+    feed: MockFeed,
+    fees: FeeModel,
+}
+
+impl Exchange for NoOrderEntry {
+    fn id(&self) -> &VenueId {
+        &self.id
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        Arc::new(self.feed.clone())
+    }
+
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        panic!("order {} sent to the venue", order.id);
+    }
+
+    fn cancel_order<'a>(&'a self, order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        panic!("cancel of {} sent to the venue", order.id);
+    }
+
+    fn symbol_meta(&self, _symbol: &str) -> Option<SymbolMeta> {
+        None
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        Box::pin(async { Ok(START_MS) })
+    }
+
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        Ok(format!("POST /order {}", order.id))
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn dry_run_fills_on_paper_and_never_calls_the_venues_order_entry() {
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let venue = NoOrderEntry {
+        id: VenueId::new("venue"),
+        feed: MockFeed::new(clock.clone(), &[("SOL/USDT", 100.0)]),
+        fees: FeeModel::default(),
+    };
+    let (buyer, script) = Scripted::new("buyer");
+    let mut config = config(&["SOL/USDT"]);
+    config.mode = ExecutionMode::DryRun;
+    config.venues.primary = VenueId::new("venue");
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_venue(Arc::new(venue))
+        .with_strategy(Box::new(buyer))
+        .build()
+        .unwrap();
+    assert_eq!(bot.execution_mode(), ExecutionMode::DryRun);
+    let mut fills = bot.order_executor.take_fills().unwrap();
+
+    // A market buy fills on paper; a limit far below the book rests there
+    *script.lock().unwrap() = Some((OrderSide::Buy, 2.0));
+    evaluate_once(&bot, "SOL/USDT").await;
+    let fill = fills.try_recv().unwrap();
+    assert_eq!((fill.quantity, fill.venue), (2.0, VenueId::new("venue")));
+    *script.lock().unwrap() = None;
+    let resting = with_id(
+        limit_order("SOL/USDT", OrderSide::Buy, 1.0, 50.0),
+        "resting",
+    );
+    let resting = Order {
+        venue: VenueId::new("venue"),
+        ..resting
+    };
+    bot.submit_to_venue(resting).await.unwrap();
+    assert_eq!(bot.open_orders().await.len(), 1);
+
+    // The kill switch cancels on paper too
+    bot.kill_switch().await;
+    assert!(bot.open_orders().await.is_empty());
+}
+
+#[tokio::test]
+async fn a_shadow_strategy_has_stats_but_never_reaches_the_executor() {
+    let (live, live_script) = Scripted::new("live");
+    let (shadow, shadow_script) = Scripted::new("shadow");
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT"]),
+        vec![
+            StrategyEntry::new(Box::new(live)),
+            StrategyEntry::new(Box::new(shadow)).with_mode(StrategyMode::Shadow),
+        ],
+    );
+    *live_script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    *shadow_script.lock().unwrap() = Some((OrderSide::Sell, 3.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    *live_script.lock().unwrap() = None;
+    *shadow_script.lock().unwrap() = Some((OrderSide::Buy, 3.0));
+    bot.tick("SOL/USDT", 99.0).await;
+
+    let submitted = bot.submitted();
+    assert_eq!(submitted.len(), 1);
+    assert_eq!(submitted[0].strategy.as_deref(), Some("live"));
+    assert!(
+        bot.fills
+            .iter()
+            .all(|f| f.strategy.as_deref() == Some("live"))
+    );
+    let positions = bot.bot.positions().await;
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0].quantity, 1.0);
+
+    let live = bot.bot.strategy_pnl().await;
+    assert_eq!(live["live"].fills, 1);
+    assert!(!live.contains_key("shadow"));
+    let shadow = bot.bot.shadow_strategy_pnl().await;
+    assert_eq!((shadow["shadow"].fills, shadow["shadow"].trades), (2, 1));
+    // Sold at the bid around 100, bought back at the ask around 99
+    assert!(shadow["shadow"].realized_pnl > 0.0);
+    assert!(!shadow.contains_key("live"));
+}
+
+// Buys on its script and keeps what on_position tells it
+struct Holder {
+    scripted: Scripted,
+    exits: Arc<Mutex<Vec<ExitReason>>>,
+}
+
+impl TradingStrategy for Holder {
+    fn analyze(&self, prices: &[Price], orderbook: &OrderBook) -> Option<TradingSignal> {
+        self.scripted.analyze(prices, orderbook)
+    }
+
+    fn name(&self) -> &str {
+        self.scripted.name()
+    }
+
+    fn on_position(&self, _position: &Position, exit: ExitReason) {
+        self.exits.lock().unwrap().push(exit);
+    }
+}
+
+#[tokio::test]
+async fn a_flat_position_is_closed_past_its_holding_period() {
+    for reset_on_extend in [false, true] {
+        let mut config = config(&["SOL/USDT"]);
+        config.holding.max_holding_secs = Some(60);
+        config.holding.reset_on_extend = reset_on_extend;
+        let (scripted, script) = Scripted::new("holder");
+        let exits = Arc::new(Mutex::new(Vec::new()));
+        let holder = Holder {
+            scripted,
+            exits: exits.clone(),
+        };
+        let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(holder))]);
+        *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+        bot.tick("SOL/USDT", 100.0).await;
+        *script.lock().unwrap() = None;
+        bot.clock.advance(Duration::from_secs(30));
+        // Adding to it restarts the clock only with reset_on_extend
+        *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+        bot.tick("SOL/USDT", 100.0).await;
+        *script.lock().unwrap() = None;
+        bot.submitted();
+
+        // 62 s after the opening fill, 31 s after the extension
+        bot.clock.advance(Duration::from_secs(30));
+        bot.tick("SOL/USDT", 100.0).await;
+        bot.bot.check_exits().await;
+        bot.settle().await;
+        let mut events = bot.events();
+        let closing = |events: &[BotEvent]| -> Vec<Fill> {
+            events
+                .iter()
+                .filter_map(|event| match event {
+                    BotEvent::Fill(fill) => Some(fill.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        if reset_on_extend {
+            assert!(closing(&events).is_empty());
+            bot.clock.advance(Duration::from_secs(30));
+            bot.tick("SOL/USDT", 100.0).await;
+            bot.bot.check_exits().await;
+            bot.settle().await;
+            events = bot.events();
+        }
+        let closed = closing(&events);
+        assert_eq!(closed.len(), 1, "reset_on_extend {reset_on_extend}");
+        assert_eq!((closed[0].side, closed[0].quantity), (OrderSide::Sell, 2.0));
+        let exit = events.iter().find_map(|event| match event {
+            BotEvent::PositionExit {
+                reason, strategy, ..
+            } => Some((*reason, strategy.clone())),
+            _ => None,
+        });
+        assert_eq!(
+            exit,
+            Some((ExitReason::TimeStop, Some("holder".to_string())))
+        );
+        assert_eq!(*exits.lock().unwrap(), [ExitReason::TimeStop]);
+        assert_eq!(bot.bot.positions().await[0].quantity, 0.0);
+    }
+}
+
+#[tokio::test]
+async fn atr_stops_sit_further_out_on_the_more_volatile_symbol() {
+    let mut config = config(&["SOL/USDT", "BTC/USDT"]);
+    config.risk.stop_mode = StopMode::AtrMultiple { multiple: 2.0 };
+    config.risk.place_stop_orders = true;
+    let (buyer, script) = Scripted::new("buyer");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(buyer))]);
+    bot.bot.risk_manager.update_atr("SOL/USDT", 0.5).await;
+    bot.bot.risk_manager.update_atr("BTC/USDT", 3.0).await;
+    *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    bot.tick("BTC/USDT", 100.0).await;
+
+    let positions = bot.bot.positions().await;
+    let orders = bot.bot.open_orders().await;
+    let distance = |symbol: &str| {
+        let position = positions.iter().find(|p| p.symbol == symbol).unwrap();
+        let stop = orders
+            .iter()
+            .find_map(|state| match state.order.order_type {
+                OrderType::StopMarket { stop_price } if state.order.symbol == symbol => {
+                    Some(stop_price)
+                }
+                _ => None,
+            })
+            .unwrap();
+        position.avg_price - stop
+    };
+    assert!((distance("SOL/USDT") - 1.0).abs() < 1e-9);
+    assert!((distance("BTC/USDT") - 6.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn one_position_after_another_leaves_by_each_exit_type() {
+    let mut config = config(&["SOL/USDT"]);
+    config.risk.monitor_exits = true;
+    config.risk.stop_loss_pct = 0.02;
+    config.risk.take_profit_pct = 0.04;
+    config.risk.trailing_stop_pct = Some(0.01);
+    config.holding.max_holding_secs = Some(60);
+    let (buyer, script) = Scripted::new("buyer");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(buyer))]);
+
+    // Opens 1 at 100 (filled at the 100.01 ask), walks the price through
+    // `path`, then runs the monitor once and returns why it closed
+    let mut round = async |path: &[f64], wait_secs: u64| {
+        *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+        bot.tick("SOL/USDT", 100.0).await;
+        *script.lock().unwrap() = None;
+        bot.clock.advance(Duration::from_secs(wait_secs));
+        for &price in path {
+            bot.tick("SOL/USDT", price).await;
+        }
+        let levels = bot.bot.protective_levels().await;
+        bot.bot.check_exits().await;
+        bot.settle().await;
+        assert_eq!(bot.bot.positions().await[0].quantity, 0.0);
+        let reasons: Vec<ExitReason> = bot
+            .events()
+            .into_iter()
+            .filter_map(|event| match event {
+                BotEvent::PositionExit { reason, .. } => Some(reason),
+                _ => None,
+            })
+            .collect();
+        (reasons, levels)
+    };
+
+    // 97 is through the trailing stop too; the stop-loss comes first
+    let (reasons, levels) = round(&[97.0], 0).await;
+    assert_eq!(reasons, [ExitReason::StopLoss]);
+    let stop = levels[0].stop_loss.unwrap();
+    assert!((stop.price - 100.01 * 0.98).abs() < 1e-9 && !stop.resting);
+
+    let (reasons, levels) = round(&[103.0, 101.5], 0).await;
+    assert_eq!(reasons, [ExitReason::TrailingStop]);
+    assert_eq!(levels[0].best_price, 103.0);
+    assert!((levels[0].trailing_stop.unwrap() - 101.97).abs() < 1e-9);
+
+    let (reasons, levels) = round(&[105.0], 0).await;
+    assert_eq!(reasons, [ExitReason::TakeProfit]);
+    assert!((levels[0].take_profit.unwrap().price - 100.01 * 1.04).abs() < 1e-9);
+
+    let (reasons, levels) = round(&[100.0], 60).await;
+    assert_eq!(reasons, [ExitReason::TimeStop]);
+    assert_eq!(levels[0].time_stop_at, Some(levels[0].opened_at + 60));
+}
+
+// A live venue reporting whatever open orders and balances it is given,
+// and remembering what it was asked to cancel
+struct Drifted {
+    id: VenueId,
+    feed: MockFeed,
+    fees: FeeModel,
+    open: Vec<Order>,
+    balances: Mutex<std::collections::HashMap<String, f64>>,
+    cancelled: Arc<Mutex<Vec<String>>>,
+}
+
+impl Exchange for Drifted {
+    fn id(&self) -> &VenueId {
+        &self.id
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        Arc::new(self.feed.clone())
+    }
+
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(async move { Ok(order.id) })
+    }
+
+    fn cancel_order<'a>(&'a self, order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        self.cancelled.lock().unwrap().push(order.id.clone());
+        Box::pin(async { Ok(()) })
+    }
+
+    fn symbol_meta(&self, _symbol: &str) -> Option<SymbolMeta> {
+        None
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        Box::pin(async { Ok(START_MS) })
+    }
+
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        Ok(order.id.clone())
+    }
+
+    fn open_orders<'a>(
+        &'a self,
+        _symbols: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<crate::venue::OpenOrder>, ExecutionError>> {
+        // Cancelled orders stop being reported
+        let cancelled = self.cancelled.lock().unwrap().clone();
+        let open = self
+            .open
+            .iter()
+            .filter(|order| !cancelled.contains(&order.id))
+            .map(|order| crate::venue::OpenOrder {
+                state: OrderState::new(order.clone()),
+                known: false,
+            })
+            .collect();
+        Box::pin(async move { Ok(open) })
+    }
+
+    fn balances(
+        &self,
+    ) -> BoxFuture<'_, Result<std::collections::HashMap<String, f64>, ExecutionError>> {
+        let balances = self.balances.lock().unwrap().clone();
+        Box::pin(async move { Ok(balances) })
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn an_unknown_order_and_a_position_gap_are_resolved_by_each_policy() {
+    for policy in [
+        ReconcilePolicy::Adopt,
+        ReconcilePolicy::CancelUnknown,
+        ReconcilePolicy::Halt,
+    ] {
+        let clock = Arc::new(PausedClock::new(START_MS));
+        let cancelled = Arc::new(Mutex::new(Vec::new()));
+        // Placed by hand at the venue, and 2 SOL the bot never bought
+        let stray = limit_order("SOL/USDT", OrderSide::Buy, 1.0, 95.0);
+        let venue = Arc::new(Drifted {
+            id: VenueId::new("venue"),
+            feed: MockFeed::new(clock.clone(), &[("SOL/USDT", 100.0)]),
+            fees: FeeModel::default(),
+            open: vec![with_id(stray, "stray")],
+            balances: Mutex::default(),
+            cancelled: cancelled.clone(),
+        });
+        let (strategy, _) = Scripted::new("idle");
+        let mut config = config(&["SOL/USDT"]);
+        config.mode = ExecutionMode::Live;
+        config.venues.primary = VenueId::new("venue");
+        config.reconcile.policy = policy;
+        config.journal.enabled = true;
+        config.journal.path =
+            scratch_dir(&format!("reconcile-{}", policy.as_str())).join("journal.sqlite");
+        let bot = TradingBotBuilder::from_config(&config)
+            .with_clock(clock)
+            .with_venue(venue.clone())
+            .with_strategy(Box::new(strategy))
+            .confirm_live()
+            .build()
+            .unwrap();
+        // Cash agrees; only the base asset is off
+        let mut balances = bot.risk_manager.balances().await;
+        balances.insert("SOL".to_string(), 2.0);
+        *venue.balances.lock().unwrap() = balances;
+
+        let report = bot.reconcile_symbols(vec!["SOL/USDT".to_string()]).await;
+        let found: Vec<(&str, &str, &Resolution)> = report
+            .discrepancies
+            .iter()
+            .map(|(_, discrepancy, resolution)| {
+                (discrepancy.kind(), discrepancy.subject(), resolution)
+            })
+            .collect();
+        let (on_order, on_position) = match policy {
+            ReconcilePolicy::Adopt => (Resolution::Adopted, Resolution::Adopted),
+            ReconcilePolicy::CancelUnknown => (Resolution::Cancelled, Resolution::Adopted),
+            ReconcilePolicy::Halt => (Resolution::Halted, Resolution::Halted),
+        };
+        assert_eq!(
+            found,
+            [
+                ("unknown_order", "stray", &on_order),
+                ("position", "SOL/USDT", &on_position)
+            ]
+        );
+        let position = |bot: &TradingBot| {
+            let risk_manager = bot.risk_manager.clone();
+            async move {
+                risk_manager
+                    .positions()
+                    .await
+                    .iter()
+                    .find(|p| p.symbol == "SOL/USDT")
+                    .map_or(0.0, |p| p.quantity)
+            }
+        };
+
+        let again = bot.reconcile_symbols(vec!["SOL/USDT".to_string()]).await;
+        if policy == ReconcilePolicy::Halt {
+            // Nothing changed, so the next pass finds the same again
+            assert!(report.halted && bot.is_halted());
+            assert!(cancelled.lock().unwrap().is_empty());
+            assert_eq!(position(&bot).await, 0.0);
+            assert_eq!(again.discrepancies.len(), 2);
+        } else {
+            // The bot's books now agree with the venue
+            assert!(!report.halted && !bot.is_halted());
+            let expected: &[&str] = match policy {
+                ReconcilePolicy::CancelUnknown => &["stray"],
+                _ => &[],
+            };
+            assert_eq!(*cancelled.lock().unwrap(), expected);
+            assert_eq!(position(&bot).await, 2.0);
+            assert!(again.discrepancies.is_empty());
+        }
+
+        // Every adjustment is journaled with what was done
+        let path = config.journal.path.clone();
+        drop(bot);
+        let journaled = || -> Vec<(String, String, String)> {
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            let mut statement = conn
+                .prepare("SELECT discrepancy, subject, action FROM reconciliations ORDER BY id")
+                .unwrap();
+            statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        let passes = if policy == ReconcilePolicy::Halt {
+            2
+        } else {
+            1
+        };
+        let mut rows = journaled();
+        for _ in 0..100 {
+            if rows.len() >= 2 * passes {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+            rows = journaled();
+        }
+        let expected: Vec<(String, String, String)> = [
+            ("unknown_order", "stray", on_order.to_string()),
+            ("position", "SOL/USDT", on_position.to_string()),
+        ]
+        .iter()
+        .cycle()
+        .take(2 * passes)
+        .map(|(kind, subject, action)| (kind.to_string(), subject.to_string(), action.clone()))
+        .collect();
+        assert_eq!(rows, expected);
+    }
+}
+
+#[tokio::test]
+async fn the_daily_report_ties_out_with_the_fills_and_the_risk_managers_pnl() {
+    let (alpha, alpha_script) = Scripted::new("alpha");
+    let (beta, beta_script) = Scripted::new("beta");
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT", "ETH/USDT"]),
+        vec![
+            StrategyEntry::new(Box::new(alpha)),
+            StrategyEntry::new(Box::new(beta)),
+        ],
+    );
+    // alpha wins on SOL; beta loses on ETH, then keeps one open
+    let trades = [
+        (&alpha_script, "SOL/USDT", OrderSide::Buy, 2.0, 100.0),
+        (&alpha_script, "SOL/USDT", OrderSide::Sell, 2.0, 110.0),
+        (&beta_script, "ETH/USDT", OrderSide::Buy, 1.0, 2_000.0),
+        (&beta_script, "ETH/USDT", OrderSide::Sell, 1.0, 1_950.0),
+        (&beta_script, "ETH/USDT", OrderSide::Buy, 1.0, 1_960.0),
+    ];
+    for (script, symbol, side, quantity, price) in trades {
+        *script.lock().unwrap() = Some((side, quantity));
+        bot.tick(symbol, price).await;
+        *script.lock().unwrap() = None;
+    }
+    assert_eq!(bot.fills.len(), 5);
+
+    // By hand from the fills: each round trip's price difference, less
+    // every fee paid
+    let fills = &bot.fills;
+    let fees: f64 = fills.iter().map(|f| f.fee).sum();
+    let sol = 2.0 * (fills[1].price - fills[0].price);
+    let eth = fills[3].price - fills[2].price;
+    let fee_of = |i: usize| fills[i].fee;
+    let alpha_pnl = sol - fee_of(0) - fee_of(1);
+    let beta_pnl = eth - fee_of(2) - fee_of(3) - fee_of(4);
+    assert!(sol > 0.0 && eth < 0.0 && fees > 0.0);
+
+    let report = bot.bot.daily_report().await;
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    assert_eq!((report.total.fills, report.total.trades), (5, 2));
+    assert!(close(report.total.fees, fees));
+    assert!(close(report.total.realized_pnl, alpha_pnl + beta_pnl));
+    // ...which is the risk manager's own daily PnL
+    let daily_pnl = bot.bot.risk_manager.daily_pnl().await;
+    assert!(close(report.daily_pnl.unwrap(), daily_pnl));
+    assert!(close(
+        report.total.realized_pnl + report.funding_pnl,
+        daily_pnl
+    ));
+
+    let rows = |rows: &[crate::report::PnlRow]| -> Vec<(String, usize, usize)> {
+        rows.iter()
+            .map(|row| (row.name.clone(), row.fills, row.trades))
+            .collect()
+    };
+    assert_eq!(
+        rows(&report.strategies),
+        [("alpha".to_string(), 2, 1), ("beta".to_string(), 3, 1)]
+    );
+    assert_eq!(
+        rows(&report.symbols),
+        [
+            ("ETH/USDT".to_string(), 3, 1),
+            ("SOL/USDT".to_string(), 2, 1)
+        ]
+    );
+    let (alpha_row, beta_row) = (&report.strategies[0], &report.strategies[1]);
+    assert!(close(alpha_row.realized_pnl, alpha_pnl));
+    assert!(close(beta_row.realized_pnl, beta_pnl));
+    assert!(close(alpha_row.largest_win.unwrap(), sol - fee_of(1)));
+    assert!(close(beta_row.largest_loss.unwrap(), eth - fee_of(3)));
+
+    // The ETH bought last is still open
+    let open: Vec<(&str, f64)> = report
+        .positions
+        .iter()
+        .map(|p| (p.symbol.as_str(), p.quantity))
+        .collect();
+    assert_eq!(open, [("ETH/USDT", 1.0)]);
+
+    // The written CSV carries the same total
+    let dir = scratch_dir("daily-report");
+    let paths = report
+        .write(&dir, &[crate::report::ReportFormat::Csv])
+        .unwrap();
+    let csv = std::fs::read_to_string(&paths[0]).unwrap();
+    let total: Vec<&str> = csv
+        .lines()
+        .find(|line| line.starts_with("total,"))
+        .unwrap()
+        .split(',')
+        .collect();
+    assert_eq!(total[2..4], ["5", "2"]);
+    let realized: f64 = total[7].parse().unwrap();
+    assert!(close(realized, daily_pnl));
+}
+
+#[tokio::test]
+async fn a_run_of_losing_trades_benches_the_strategy_until_re_enabled() {
+    let mut config = config(&["SOL/USDT"]);
+    config.streaks.enabled = true;
+    config.streaks.max_losing_trades = 3;
+    config.streaks.bench_mode = StrategyMode::Disabled;
+    config.streaks.cooldown_secs = 60;
+    config.journal.enabled = true;
+    config.journal.path = scratch_dir("streaks").join("journal.sqlite");
+    let (loser, script) = Scripted::new("loser");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(loser))]);
+    let mode = async |bot: &TradingBot| {
+        bot.strategies
+            .read()
+            .await
+            .iter()
+            .find(|entry| entry.strategy.name() == "loser")
+            .unwrap()
+            .mode()
+    };
+
+    // Two losses, a win that resets the streak, then three losses
+    let trades = [
+        (99.0, 1),
+        (99.0, 2),
+        (101.0, 0),
+        (99.0, 1),
+        (99.0, 2),
+        (99.0, 3),
+    ];
+    for (exit, losing) in trades {
+        *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+        bot.tick("SOL/USDT", 100.0).await;
+        *script.lock().unwrap() = Some((OrderSide::Sell, 1.0));
+        bot.tick("SOL/USDT", exit).await;
+        *script.lock().unwrap() = None;
+        let streak = bot.bot.streaks.lock().unwrap().streak("loser");
+        assert_eq!(streak.losing_trades, losing);
+    }
+    assert_eq!(bot.fills.len(), 12);
+    let benched: Vec<(StreakKind, u32, StrategyMode)> = bot
+        .events()
+        .into_iter()
+        .filter_map(|event| match event {
+            BotEvent::StrategyBenched {
+                strategy,
+                kind,
+                count,
+                mode,
+                ..
+            } if strategy == "loser" => Some((kind, count, mode)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        benched,
+        [(StreakKind::LosingTrades, 3, StrategyMode::Disabled)]
+    );
+    assert_eq!(mode(&bot.bot).await, StrategyMode::Disabled);
+
+    // Benched, it places nothing
+    *script.lock().unwrap() = Some((OrderSide::Buy, 1.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    assert!(bot.submitted().is_empty());
+
+    // The bench survives a restart through the journal
+    let path = config.journal.path.clone();
+    let journaled = || {
+        crate::journal::JournalStore::open(&path)
+            .and_then(|store| store.streaks())
+            .unwrap()
+            .into_iter()
+            .any(|(name, streak)| name == "loser" && streak.benched.is_some())
+    };
+    for _ in 0..100 {
+        if journaled() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(journaled());
+    let (loser, _) = Scripted::new("loser");
+    let restarted = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(loser))]);
+    restarted.bot.restore_benched().await;
+    assert_eq!(mode(&restarted.bot).await, StrategyMode::Disabled);
+
+    // Re-enabling it by hand overrides the cooldown
+    assert!(bot.bot.set_strategy_enabled("loser", true).await);
+    assert_eq!(mode(&bot.bot).await, StrategyMode::Live);
+    assert_eq!(
+        bot.bot.streaks.lock().unwrap().streak("loser").benched,
+        None
+    );
+    bot.tick("SOL/USDT", 100.0).await;
+    assert_eq!(bot.submitted().len(), 1);
+}
+
+// Bids for one SOL at whatever limit price it's given
+struct Bidder {
+    price: Arc<Mutex<f64>>,
+}
+
+impl TradingStrategy for Bidder {
+    fn analyze(&self, _prices: &[Price], orderbook: &OrderBook) -> Option<TradingSignal> {
+        let price = *self.price.lock().unwrap();
+        Some(TradingSignal {
+            symbol: orderbook.symbol.as_str().into(),
+            action: OrderSide::Buy,
+            confidence: 1.0,
+            target_price: orderbook.mid_price()?,
+            quantity: 1.0,
+            time_in_force: TimeInForce::Gtc,
+            limit_price: Some(price),
+        })
+    }
+
+    fn name(&self) -> &str {
+        "bidder"
+    }
+}
+
+#[tokio::test]
+async fn repeated_signals_leave_the_working_orders_the_guard_allows() {
+    for policy in [
+        DuplicatePolicy::Skip,
+        DuplicatePolicy::Replace,
+        DuplicatePolicy::Stack,
+    ] {
+        let mut config = config(&["SOL/USDT"]);
+        config.order_guard.policy = policy;
+        config.order_guard.max_stacked = 3;
+        let price = Arc::new(Mutex::new(97.0));
+        let bidder = Bidder {
+            price: price.clone(),
+        };
+        let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(bidder))]);
+
+        // The same bid, well below the book, five times over; the last one
+        // a hair higher but within the tolerance
+        for _ in 0..4 {
+            bot.tick("SOL/USDT", 100.0).await;
+        }
+        *price.lock().unwrap() = 97.05;
+        bot.tick("SOL/USDT", 100.0).await;
+        assert!(bot.fills.is_empty());
+
+        let mut working: Vec<f64> = bot
+            .bot
+            .order_executor
+            .open_orders()
+            .await
+            .iter()
+            .map(|state| state.order.price.unwrap())
+            .collect();
+        working.sort_by(f64::total_cmp);
+        let expected: &[f64] = match policy {
+            DuplicatePolicy::Skip => &[97.0],
+            DuplicatePolicy::Replace => &[97.05],
+            DuplicatePolicy::Stack => &[97.0, 97.0, 97.0],
+        };
+        assert_eq!(working, expected, "{policy:?}");
+    }
+}
+
+#[tokio::test]
+async fn a_short_stops_out_on_a_rise_covers_on_a_fall_and_hedges_beside_a_long() {
+    let mut config = config(&["SOL/USDT"]);
+    config.risk.monitor_exits = true;
+    config.risk.stop_loss_pct = 0.02;
+    config.risk.take_profit_pct = 0.04;
+    let (trader, script) = Scripted::new("trader");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(trader))]);
+
+    // Sells 1 at 100 (filled at the bid), walks the price through `path`,
+    // runs the monitor and returns why it closed and the day's PnL since
+    let mut round = async |path: &[f64]| {
+        let before = bot.bot.risk_manager.daily_pnl().await;
+        *script.lock().unwrap() = Some((OrderSide::Sell, 1.0));
+        bot.tick("SOL/USDT", 100.0).await;
+        *script.lock().unwrap() = None;
+        let entry = bot.fills.last().unwrap().price;
+        let position = bot.bot.positions().await[0].clone();
+        assert_eq!(position.quantity, -1.0);
+        let levels = bot.bot.protective_levels().await[0].clone();
+        // Both on the far side from a long's
+        assert!((levels.stop_loss.unwrap().price - entry * 1.02).abs() < 1e-9);
+        assert!((levels.take_profit.unwrap().price - entry * 0.96).abs() < 1e-9);
+        for &price in path {
+            bot.tick("SOL/USDT", price).await;
+        }
+        let unrealized = bot.bot.positions().await[0].unrealized_pnl;
+        bot.bot.check_exits().await;
+        bot.settle().await;
+        assert_eq!(bot.bot.positions().await[0].quantity, 0.0);
+        let reasons: Vec<ExitReason> = bot
+            .events()
+            .into_iter()
+            .filter_map(|event| match event {
+                BotEvent::PositionExit { reason, .. } => Some(reason),
+                _ => None,
+            })
+            .collect();
+        let realized = bot.bot.risk_manager.daily_pnl().await - before;
+        (reasons, unrealized, realized)
+    };
+
+    // A rise is a loss to a short, and stops it out
+    let (reasons, unrealized, realized) = round(&[101.0, 103.0]).await;
+    assert_eq!(reasons, [ExitReason::StopLoss]);
+    assert!(unrealized < 0.0 && realized < 0.0);
+    // A fall is its profit, taken at the target
+    let (reasons, unrealized, realized) = round(&[99.0, 95.0]).await;
+    assert_eq!(reasons, [ExitReason::TakeProfit]);
+    assert!(unrealized > 0.0 && realized > 0.0);
+
+    // In hedge mode a buy and a sell open a long and a short side by side
+    config.account.hedge_mode = true;
+    let (trader, script) = Scripted::new("trader");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(trader))]);
+    for side in [OrderSide::Buy, OrderSide::Sell] {
+        *script.lock().unwrap() = Some((side, 1.0));
+        bot.tick("SOL/USDT", 100.0).await;
+    }
+    *script.lock().unwrap() = None;
+    let legs = |positions: Vec<Position>| -> Vec<(PositionSide, f64)> {
+        let mut legs: Vec<(PositionSide, f64)> =
+            positions.iter().map(|p| (p.side, p.quantity)).collect();
+        legs.sort_by_key(|(side, _)| *side);
+        legs
+    };
+    assert_eq!(
+        legs(bot.bot.positions().await),
+        [(PositionSide::Long, 1.0), (PositionSide::Short, -1.0)]
+    );
+    // A drop stops out the long leg and leaves the short
+    bot.tick("SOL/USDT", 97.0).await;
+    bot.bot.check_exits().await;
+    bot.settle().await;
+    assert_eq!(
+        legs(bot.bot.positions().await),
+        [(PositionSide::Long, 0.0), (PositionSide::Short, -1.0)]
+    );
+}
+
+#[tokio::test]
+async fn funding_eight_hours_on_moves_pnl_by_rate_times_notional() {
+    let mut config = config(&["SOL/USDT"]);
+    config.symbol_meta.insert(
+        "SOL/USDT".to_string(),
+        SymbolMeta {
+            instrument: InstrumentType::Perp,
+            ..SymbolMeta::default()
+        },
+    );
+    config.funding.enabled = true;
+    config.funding.rates.insert("SOL/USDT".to_string(), 0.0001);
+    let (buyer, script) = Scripted::new("buyer");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(buyer))]);
+    // The funding time the day opened on
+    bot.bot.settle_funding().await;
+
+    *script.lock().unwrap() = Some((OrderSide::Buy, 10.0));
+    bot.tick("SOL/USDT", 100.0).await;
+    *script.lock().unwrap() = None;
+    bot.tick("SOL/USDT", 102.0).await;
+    let before = bot.bot.risk_manager.daily_pnl().await;
+    let held = bot.bot.positions().await[0].clone();
+
+    // Nothing until the next funding time, then one payment
+    bot.clock.advance(Duration::from_secs(8 * 3_600 - 10));
+    bot.bot.settle_funding().await;
+    assert_eq!(bot.bot.risk_manager.daily_pnl().await, before);
+    bot.clock.advance(Duration::from_secs(10));
+    bot.bot.settle_funding().await;
+    bot.bot.settle_funding().await;
+
+    // The long pays 0.01% of 10 at the 102 mark
+    let paid = -0.0001 * 10.0 * 102.0;
+    let after = bot.bot.positions().await[0].clone();
+    assert!((after.realized_pnl - held.realized_pnl - paid).abs() < 1e-9);
+    assert!((bot.bot.risk_manager.daily_pnl().await - before - paid).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn a_market_order_is_cut_to_its_share_of_a_shallow_book() {
+    for remainder in [DepthRemainder::Drop, DepthRemainder::Limit] {
+        let mut config = config(&["SOL/USDT"]);
+        config.depth_sizing.enabled = true;
+        config.depth_sizing.band_bps = 10.0;
+        config.depth_sizing.max_participation = 0.2;
+        config.depth_sizing.remainder = remainder;
+        let (buyer, script) = Scripted::new("buyer");
+        let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(buyer))]);
+
+        // 5 offered within 10 bps of the 100 mid, the rest a long way off
+        let book = OrderBook::new(
+            "SOL/USDT".to_string(),
+            vec![(99.95, 2.0), (99.92, 3.0), (99.0, 100.0)],
+            vec![(100.05, 2.0), (100.08, 3.0), (101.0, 100.0)],
+            START_MS / 1_000,
+        );
+        bot.record(Price {
+            symbol: "SOL/USDT".to_string(),
+            price: 100.0,
+            timestamp: START_MS / 1_000,
+            volume: 1_000.0,
+        })
+        .await;
+        *script.lock().unwrap() = Some((OrderSide::Buy, 3.0));
+        bot.evaluate(book).await;
+
+        // A fifth of the 5 goes at market, off the first level alone
+        let orders: Vec<(OrderType, f64, Option<f64>)> = bot
+            .submitted()
+            .iter()
+            .map(|order| (order.order_type, order.quantity, order.price))
+            .collect();
+        assert_eq!(orders[0], (OrderType::Market, 1.0, None));
+        let fills: Vec<(f64, f64)> = bot.fills.iter().map(|f| (f.quantity, f.price)).collect();
+        assert_eq!(fills, [(1.0, 100.05)]);
+        match remainder {
+            DepthRemainder::Drop => assert_eq!(orders.len(), 1),
+            // The other 2 rest at the bid
+            DepthRemainder::Limit => {
+                assert_eq!(orders[1..], [(OrderType::Limit, 2.0, Some(99.95))])
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn a_staircase_gets_one_donchian_entry_per_breakout() {
+    let strategy = DonchianBreakoutStrategy::new(3, Timeframe::S5, DonchianExit::None, 1.0);
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT"]),
+        vec![StrategyEntry::new(Box::new(strategy))],
+    );
+    // Five 5s bars on each step: up two steps from 100, then down two
+    for level in [100.0, 101.0, 102.0, 101.0, 100.0] {
+        for _ in 0..25 {
+            bot.tick("SOL/USDT", level).await;
+        }
+    }
+    // Each step's first bar breaks out; the four held after it don't
+    let entries: Vec<OrderSide> = bot.submitted().iter().map(|order| order.side).collect();
+    assert_eq!(
+        entries,
+        [
+            OrderSide::Buy,
+            OrderSide::Buy,
+            OrderSide::Sell,
+            OrderSide::Sell
+        ]
+    );
+}
+
+#[tokio::test]
+async fn macd_stays_quiet_on_a_flat_series_and_signals_each_cross_once() {
+    let strategy = MacdStrategy::new(3, 6, 3, Timeframe::S5, 1.0);
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT"]),
+        vec![StrategyEntry::new(Box::new(strategy))],
+    );
+    // Warm-up and a long flat stretch: no signal, and nothing NaN to act on
+    for _ in 0..100 {
+        bot.tick("SOL/USDT", 100.0).await;
+    }
+    assert!(bot.submitted().is_empty());
+
+    // A rise crosses the MACD line up once, a fall crosses it down once
+    let path = [
+        101.0, 102.0, 103.0, 104.0, 105.0, 104.0, 102.0, 100.0, 98.0, 96.0,
+    ];
+    for price in path {
+        for _ in 0..5 {
+            bot.tick("SOL/USDT", price).await;
+        }
+    }
+    let signals: Vec<OrderSide> = bot.submitted().iter().map(|order| order.side).collect();
+    assert_eq!(signals, [OrderSide::Buy, OrderSide::Sell]);
+}
+
+#[tokio::test]
+async fn the_scalper_captures_a_tick_then_bails_when_the_book_flips() {
+    let scalper = SpreadScalperStrategy::new(ScalperParams {
+        tick_size: 0.01,
+        min_spread_ticks: 3.0,
+        min_imbalance: 0.3,
+        imbalance_levels: 1,
+        profit_ticks: 1,
+        timeout_secs: 30,
+        base_quantity: 1.0,
+        max_inventory: 1.0,
+    });
+    let mut bot = TestBot::with_strategies(
+        &config(&["SOL/USDT"]),
+        vec![StrategyEntry::new(Box::new(scalper))],
+    );
+    // A second later, the book as given and an evaluation pass; returns
+    // the orders it sent and the fills it got
+    type Sent = Vec<(OrderSide, OrderType, TimeInForce, Option<f64>)>;
+    let mut step = async |bid: (f64, f64), ask: (f64, f64)| -> (Sent, Vec<(OrderSide, f64)>) {
+        bot.clock.advance(Duration::from_secs(1));
+        let now = bot.clock.now_secs();
+        bot.record(Price {
+            symbol: "SOL/USDT".to_string(),
+            price: (bid.0 + ask.0) / 2.0,
+            timestamp: now,
+            volume: 1_000.0,
+        })
+        .await;
+        let seen = bot.fills.len();
+        let book = OrderBook::new("SOL/USDT".to_string(), vec![bid], vec![ask], now);
+        bot.evaluate(book).await;
+        let sent = bot
+            .submitted()
+            .iter()
+            .map(|order| {
+                let price = order.price.map(|p| (p * 100.0).round() / 100.0);
+                (order.side, order.order_type, order.time_in_force, price)
+            })
+            .collect();
+        let fills = bot.fills[seen..]
+            .iter()
+            .map(|fill| (fill.side, (fill.price * 100.0).round() / 100.0))
+            .collect();
+        (sent, fills)
+    };
+    let (leaning_bid, thin_ask) = ((99.95, 10.0), (100.00, 2.0));
+    let entry = (
+        OrderSide::Buy,
+        OrderType::Limit,
+        TimeInForce::PostOnly,
+        Some(99.95),
+    );
+
+    // Five ticks wide with the bids heavy: join the bid, post-only
+    assert_eq!(step(leaning_bid, thin_ask).await, (vec![entry], vec![]));
+    // A seller comes down to it
+    let filled = step((99.94, 10.0), (99.95, 2.0)).await;
+    assert_eq!(filled, (vec![], vec![(OrderSide::Buy, 99.95)]));
+    // The target rests a tick up, and the bid lifting to it takes it
+    let target = (
+        OrderSide::Sell,
+        OrderType::Limit,
+        TimeInForce::Gtc,
+        Some(99.96),
+    );
+    assert_eq!(step(leaning_bid, thin_ask).await, (vec![target], vec![]));
+    let captured = step((99.97, 10.0), thin_ask).await;
+    assert_eq!(captured, (vec![], vec![(OrderSide::Sell, 99.96)]));
+
+    // Again, but the book turns to the sellers with the target resting:
+    // it's pulled and the position sold at market
+    assert_eq!(step(leaning_bid, thin_ask).await, (vec![entry], vec![]));
+    step((99.94, 10.0), (99.95, 2.0)).await;
+    assert_eq!(step(leaning_bid, thin_ask).await.0, [target]);
+    let bailed = step((99.95, 3.0), (100.00, 10.0)).await;
+    let exit = (OrderSide::Sell, OrderType::Market, TimeInForce::Gtc, None);
+    assert_eq!(bailed, (vec![exit], vec![(OrderSide::Sell, 99.95)]));
+    assert!(bot.bot.order_executor.open_orders().await.is_empty());
+    assert_eq!(bot.bot.positions().await[0].quantity, 0.0);
+}
+
+// A venue that turns every order down, and under a dry run can't even
+// build the request
+struct Refusing {
+    id: VenueId,
+    feed: MockFeed,
+    fees: FeeModel,
+}
+
+impl Exchange for Refusing {
+    fn id(&self) -> &VenueId {
+        &self.id
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        Arc::new(self.feed.clone())
+    }
+
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(async move { self.order_request(&order) })
+    }
+
+    fn cancel_order<'a>(&'a self, _order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn symbol_meta(&self, _symbol: &str) -> Option<SymbolMeta> {
+        None
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        Box::pin(async { Ok(START_MS) })
+    }
+
+    fn order_request(&self, _order: &Order) -> Result<String, ExecutionError> {
+        Err(ExecutionError::VenueRejected {
+            code: -2010,
+            message: "Account has insufficient balance".to_string(),
+        })
+    }
+}
+
+// Long SOL spot against the SOL perp, 1,000 a leg, in at 10% annualized
+// and out at 2%
+fn basis_config() -> crate::config::BotConfig {
+    let mut config = config(&["SOL/USDT", "SOL-PERP/USDT"]);
+    config.symbol_meta.insert(
+        "SOL-PERP/USDT".to_string(),
+        SymbolMeta {
+            instrument: InstrumentType::Perp,
+            ..SymbolMeta::default()
+        },
+    );
+    config.basis.enabled = true;
+    config.basis.spot_symbol = "SOL/USDT".to_string();
+    config.basis.perp_symbol = "SOL-PERP/USDT".to_string();
+    config.basis.target_notional = 1_000.0;
+    config
+}
+
+// Each leg's position: spot then perp
+async fn basis_legs(bot: &TradingBot) -> (f64, f64) {
+    let held = |symbol: &str, positions: &[Position]| {
+        positions
+            .iter()
+            .filter(|p| p.symbol == symbol)
+            .map(|p| p.quantity)
+            .sum::<f64>()
+    };
+    let positions = bot.positions().await;
+    (
+        held("SOL/USDT", &positions),
+        held("SOL-PERP/USDT", &positions),
+    )
+}
+
+#[tokio::test]
+async fn the_basis_pair_opens_as_the_basis_widens_and_closes_as_it_narrows() {
+    let (quiet, _) = Scripted::new("quiet");
+    let mut bot =
+        TestBot::with_strategies(&basis_config(), vec![StrategyEntry::new(Box::new(quiet))]);
+    let mut quote = async |perp: f64| {
+        bot.tick("SOL/USDT", 100.0).await;
+        bot.tick("SOL-PERP/USDT", perp).await;
+        bot.bot.check_basis().await;
+        bot.settle().await;
+        let legs = bot
+            .submitted()
+            .iter()
+            .map(|order| (order.symbol, order.side))
+            .collect::<Vec<_>>();
+        (legs, basis_legs(&bot.bot).await)
+    };
+
+    // 0.001% a funding interval is about 1% a year: not enough
+    let (legs, held) = quote(100.001).await;
+    assert!(legs.is_empty() && held == (0.0, 0.0));
+    // 0.05% is about 55% a year: both legs go on, 10 each
+    let (legs, (spot, perp)) = quote(100.05).await;
+    let entry = [
+        (SymbolId::intern("SOL/USDT"), OrderSide::Buy),
+        (SymbolId::intern("SOL-PERP/USDT"), OrderSide::Sell),
+    ];
+    assert_eq!(legs, entry);
+    assert!((spot - 10.0).abs() < 1e-9 && (perp + 10.0).abs() < 1e-9);
+    // Still wide, or narrowing but above the exit: held as it is
+    let (legs, _) = quote(100.03).await;
+    assert!(legs.is_empty());
+    // Converged: both legs come off together
+    let (legs, held) = quote(100.0).await;
+    let exit = [
+        (SymbolId::intern("SOL/USDT"), OrderSide::Sell),
+        (SymbolId::intern("SOL-PERP/USDT"), OrderSide::Buy),
+    ];
+    assert_eq!(legs, exit);
+    assert_eq!(held, (0.0, 0.0));
+    assert_eq!(bot.bot.basis.lock().unwrap().open_quantity(), None);
+}
+
+#[tokio::test]
+async fn a_refused_perp_leg_takes_the_spot_leg_back_out() {
+    let mut config = basis_config();
+    config.basis.perp_venue = Some(VenueId::new("refusing"));
+    let refusing = Refusing {
+        id: VenueId::new("refusing"),
+        feed: MockFeed::new(Arc::new(PausedClock::new(START_MS)), &[]),
+        fees: FeeModel::default(),
+    };
+    let (quiet, _) = Scripted::new("quiet");
+    let mut bot = TestBot::with_venues(
+        &config,
+        vec![StrategyEntry::new(Box::new(quiet))],
+        vec![Arc::new(refusing)],
+    );
+    bot.tick("SOL/USDT", 100.0).await;
+    bot.tick("SOL-PERP/USDT", 100.05).await;
+    bot.bot.check_basis().await;
+    bot.settle().await;
+
+    // The spot leg filled, then was sold straight back
+    let fills: Vec<(&str, OrderSide, f64)> = bot
+        .fills
+        .iter()
+        .map(|fill| (fill.symbol.as_str(), fill.side, fill.quantity))
+        .collect();
+    assert_eq!(
+        fills,
+        [
+            ("SOL/USDT", OrderSide::Buy, 10.0),
+            ("SOL/USDT", OrderSide::Sell, 10.0)
+        ]
+    );
+    assert_eq!(basis_legs(&bot.bot).await, (0.0, 0.0));
+    assert_eq!(bot.bot.basis.lock().unwrap().open_quantity(), None);
+}
+
+#[tokio::test]
+async fn a_strategy_past_its_budget_is_rejected_while_another_trades_on() {
+    let (alpha, alpha_script) = Scripted::new("alpha");
+    let (beta, beta_script) = Scripted::new("beta");
+    let mut config = config(&["SOL/USDT", "ETH/USDT"]);
+    config.allocation.budgets.insert("alpha".to_string(), 500.0);
+    let mut bot = TestBot::with_strategies(
+        &config,
+        vec![
+            StrategyEntry::new(Box::new(alpha)),
+            StrategyEntry::new(Box::new(beta)),
+        ],
+    );
+    let trade = async |bot: &mut TestBot, script: &Script, symbol: &str, side, quantity| {
+        *script.lock().unwrap() = Some((side, quantity));
+        bot.tick(symbol, 100.0).await;
+        *script.lock().unwrap() = None;
+        let rejections: Vec<String> = bot
+            .events()
+            .into_iter()
+            .filter_map(|event| match event {
+                BotEvent::RiskRejection {
+                    strategy, reason, ..
+                } => {
+                    assert_eq!(strategy.as_deref(), Some("alpha"));
+                    Some(reason)
+                }
+                _ => None,
+            })
+            .collect();
+        let filled = bot.fills.drain(..).map(|f| f.quantity).sum::<f64>();
+        (filled, rejections)
+    };
+
+    // alpha puts 300 of its 500 to work; another 300 would go past it
+    let (filled, rejections) =
+        trade(&mut bot, &alpha_script, "SOL/USDT", OrderSide::Buy, 3.0).await;
+    assert!(filled == 3.0 && rejections.is_empty());
+    let (filled, rejections) =
+        trade(&mut bot, &alpha_script, "SOL/USDT", OrderSide::Buy, 3.0).await;
+    assert_eq!(filled, 0.0);
+    assert_eq!(rejections.len(), 1);
+    assert!(
+        rejections[0].contains("alpha budget exceeded"),
+        "{rejections:?}"
+    );
+    // beta has no budget and trades on
+    let (filled, rejections) = trade(&mut bot, &beta_script, "ETH/USDT", OrderSide::Buy, 3.0).await;
+    assert!(filled == 3.0 && rejections.is_empty());
+
+    let budgets = bot.bot.strategy_budgets();
+    assert_eq!(budgets.keys().collect::<Vec<_>>(), ["alpha"]);
+    let alpha = &budgets["alpha"];
+    assert!((alpha.deployed - 300.0).abs() < 0.1, "{alpha:?}");
+    assert_eq!(alpha.reserved, 0.0);
+    assert!((alpha.utilization - 0.6).abs() < 1e-3);
+    // Closing lots frees budget for the next order
+    let (filled, _) = trade(&mut bot, &alpha_script, "SOL/USDT", OrderSide::Sell, 2.0).await;
+    assert_eq!(filled, 2.0);
+    let (filled, rejections) =
+        trade(&mut bot, &alpha_script, "SOL/USDT", OrderSide::Buy, 3.0).await;
+    assert!(filled == 3.0 && rejections.is_empty());
+}
+
+#[tokio::test]
+async fn the_daily_loss_limit_lifts_at_the_session_rollover_with_a_summary() {
+    let (trader, script) = Scripted::new("trader");
+    let mut config = config(&["SOL/USDT"]);
+    config.session.utc_offset = "+02:00".to_string();
+    config.risk.max_daily_loss = 10.0;
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(trader))]);
+    let trade = async |bot: &mut TestBot, side, price| {
+        *script.lock().unwrap() = Some((side, 5.0));
+        bot.tick("SOL/USDT", price).await;
+        *script.lock().unwrap() = None;
+        bot.fills.drain(..).count()
+    };
+
+    // Buy 5 at 100 and sell at 97: 15 lost, past the limit of 10
+    assert_eq!(trade(&mut bot, OrderSide::Buy, 100.0).await, 1);
+    assert_eq!(trade(&mut bot, OrderSide::Sell, 97.0).await, 1);
+    let lost = bot.bot.risk_manager.daily_pnl().await;
+    assert!(lost < -15.0, "{lost}");
+    assert_eq!(trade(&mut bot, OrderSide::Buy, 97.0).await, 0);
+    assert!(
+        bot.events()
+            .iter()
+            .any(|e| matches!(e, BotEvent::DailyLossLimit { .. }))
+    );
+
+    // Midnight at +02:00 is 22:00 UTC; a second short of it, nothing rolls
+    let midnight = START_MS / 1_000 + 22 * 3_600;
+    bot.clock.advance_to((midnight - 1) * 1_000);
+    bot.bot.check_drawdown().await;
+    assert!(
+        !bot.events()
+            .iter()
+            .any(|e| matches!(e, BotEvent::SessionSummary(_)))
+    );
+    assert_eq!(bot.bot.risk_manager.daily_pnl().await, lost);
+
+    bot.clock.advance_to(midnight * 1_000);
+    bot.bot.check_drawdown().await;
+    let summaries: Vec<_> = bot
+        .events()
+        .into_iter()
+        .filter_map(|e| match e {
+            BotEvent::SessionSummary(summary) => Some(summary),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(summaries.len(), 1);
+    let summary = &summaries[0];
+    assert_eq!(summary.ended_at, midnight);
+    assert_eq!(summary.stats.realized_pnl, lost);
+    assert_eq!((summary.stats.fills, summary.stats.trades), (2, 1));
+    // A new day: the limit has lifted
+    assert_eq!(bot.bot.risk_manager.daily_pnl().await, 0.0);
+    assert_eq!(trade(&mut bot, OrderSide::Buy, 97.0).await, 1);
+}
+
+#[tokio::test]
+async fn the_equity_csv_ends_on_the_equity_the_bot_stopped_with() {
+    let (trader, script) = Scripted::new("trader");
+    let mut config = config(&["SOL/USDT"]);
+    let dir = scratch_dir("equity-csv");
+    config.journal.enabled = true;
+    config.journal.path = dir.join("journal.sqlite");
+    config.equity.sample_secs = 10;
+    config.equity.flush_secs = 30;
+    config.equity.csv_path = Some(dir.join("equity.csv"));
+    let path = dir.join("equity.csv");
+    let mut bot = TestBot::with_strategies(&config, vec![StrategyEntry::new(Box::new(trader))]);
+
+    // Buy 2, sell 1 halfway, and mark the rest up a dollar every 10s
+    for i in 0..9 {
+        let signal = match i {
+            0 => Some((OrderSide::Buy, 2.0)),
+            5 => Some((OrderSide::Sell, 1.0)),
+            _ => None,
+        };
+        *script.lock().unwrap() = signal;
+        bot.tick("SOL/USDT", 100.0 + i as f64).await;
+        bot.bot.check_drawdown().await;
+        bot.clock.advance(Duration::from_secs(9));
+    }
+    assert_eq!(bot.fills.len(), 2);
+    bot.bot.stop().await;
+    let equity = bot.bot.risk_manager.equity().await;
+    let now = bot.clock.now_secs();
+
+    // Stopping waits for the journal's writer to append the rows
+    let rows: Vec<String> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    assert_eq!(
+        rows.first().map(String::as_str),
+        Some("timestamp,equity,realized_pnl,unrealized_pnl,gross_exposure")
+    );
+    // A sample every 10s over the 90s, then the one taken on stopping
+    assert_eq!(rows.len(), 1 + 9 + 1, "{rows:#?}");
+    let last: Vec<f64> = rows
+        .last()
+        .unwrap()
+        .split(',')
+        .map(|field| field.parse().unwrap())
+        .collect();
+    assert_eq!(last[0], now as f64);
+    assert_eq!(last[1], equity);
+    let positions = bot.bot.positions().await;
+    assert_eq!(last[2], positions[0].realized_pnl);
+    assert_eq!(last[3], positions[0].unrealized_pnl);
+}
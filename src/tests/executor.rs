@@ -0,0 +1,837 @@
+use super::{PausedClock, START_MS, with_id};
+use crate::clock::{Clock, VirtualClock};
+use crate::fixtures::{self, limit_order, market_order, stop_order};
+use crate::paper::{LatencyConfig, PaperConfig, RemainderPolicy, SelfMatchPolicy};
+use crate::rate_limit::{ExhaustedPolicy, RateLimitConfig};
+use crate::{
+    BookCache, ExecutionError, Fill, OcoOrder, Order, OrderBook, OrderExecutor, OrderSide,
+    OrderStatus, OrderType, RiskManager, RiskParams, RiskRejection, TimeInForce,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+fn unlimited() -> RateLimitConfig {
+    RateLimitConfig {
+        orders_per_second: 1e9,
+        order_burst: usize::MAX,
+        cancels_per_second: 1e9,
+        cancel_burst: usize::MAX,
+        on_exhausted: ExhaustedPolicy::Reject,
+    }
+}
+
+// A paper executor on a virtual clock, filling against books the test sets
+struct Venue {
+    executor: Arc<OrderExecutor>,
+    books: BookCache,
+    fill_stream: mpsc::UnboundedReceiver<Fill>,
+}
+
+impl Venue {
+    fn new(paper: PaperConfig) -> Self {
+        Self::with_rate_limits(paper, unlimited())
+    }
+
+    fn with_rate_limits(paper: PaperConfig, rate_limits: RateLimitConfig) -> Self {
+        Self::on_clock(paper, rate_limits, Arc::new(VirtualClock::new(START_MS)))
+    }
+
+    // On tokio's clock, for tests of real delays on a paused runtime
+    fn paused(paper: PaperConfig) -> Self {
+        Self::on_clock(paper, unlimited(), Arc::new(PausedClock::new(START_MS)))
+    }
+
+    fn on_clock(paper: PaperConfig, rate_limits: RateLimitConfig, clock: Arc<dyn Clock>) -> Self {
+        let books = BookCache::default();
+        let executor = OrderExecutor::with_rate_limits(&rate_limits)
+            .with_clock(clock)
+            .with_order_books(books.clone())
+            .with_paper_config(paper);
+        let fill_stream = executor.take_fills().unwrap();
+        Self {
+            executor: Arc::new(executor),
+            books,
+            fill_stream,
+        }
+    }
+
+    async fn set_book(&self, bids: &[(f64, f64)], asks: &[(f64, f64)]) {
+        self.set_book_on("SOL/USDT", bids, asks).await;
+    }
+
+    async fn set_book_on(&self, symbol: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) {
+        let book = OrderBook::new(symbol.to_string(), bids.to_vec(), asks.to_vec(), 0);
+        self.books.write().await.insert(book.symbol.clone(), book);
+    }
+
+    fn fills(&mut self) -> Vec<Fill> {
+        std::iter::from_fn(|| self.fill_stream.try_recv().ok()).collect()
+    }
+}
+
+fn vwap(fills: &[Fill]) -> f64 {
+    let quantity: f64 = fills.iter().map(|f| f.quantity).sum();
+    fills.iter().map(|f| f.quantity * f.price).sum::<f64>() / quantity
+}
+
+#[tokio::test]
+async fn market_orders_walk_the_book_at_its_vwap() {
+    let mut venue = Venue::new(PaperConfig::default());
+    venue
+        .set_book(
+            &[(99.9, 4.0), (99.5, 4.0)],
+            &[(100.1, 2.0), (100.2, 3.0), (100.5, 5.0)],
+        )
+        .await;
+
+    let buy = with_id(market_order("SOL/USDT", OrderSide::Buy, 6.0), "buy");
+    venue.executor.submit_order(buy).await.unwrap();
+    let fills = venue.fills();
+    let levels: Vec<(f64, f64)> = fills.iter().map(|f| (f.price, f.quantity)).collect();
+    assert_eq!(levels, [(100.1, 2.0), (100.2, 3.0), (100.5, 1.0)]);
+    // (2 * 100.1 + 3 * 100.2 + 100.5) / 6
+    assert!((vwap(&fills) - 601.3 / 6.0).abs() < 1e-9);
+
+    let sell = with_id(market_order("SOL/USDT", OrderSide::Sell, 5.0), "sell");
+    venue.executor.submit_order(sell).await.unwrap();
+    let fills = venue.fills();
+    // (4 * 99.9 + 99.5) / 5
+    assert!((vwap(&fills) - 99.82).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn remainder_past_the_book_is_cancelled_or_left_working() {
+    for (remainder, working) in [
+        (RemainderPolicy::Cancel, 0),
+        (RemainderPolicy::KeepWorking, 1),
+    ] {
+        let mut venue = Venue::new(PaperConfig {
+            remainder,
+            ..PaperConfig::default()
+        });
+        venue
+            .set_book(&[(99.9, 1.0)], &[(100.1, 2.0), (100.2, 3.0)])
+            .await;
+        let buy = with_id(market_order("SOL/USDT", OrderSide::Buy, 8.0), "buy");
+        venue.executor.submit_order(buy).await.unwrap();
+
+        let filled: f64 = venue.fills().iter().map(|f| f.quantity).sum();
+        assert_eq!(filled, 5.0);
+        let open = venue.executor.open_orders().await;
+        assert_eq!(open.len(), working, "{remainder:?}");
+        if let Some(state) = open.first() {
+            assert_eq!(state.remaining_quantity(), 3.0);
+        }
+    }
+}
+
+#[tokio::test]
+async fn an_order_that_times_out_then_goes_through_works_once() {
+    // Seed 6 draws 0.335 then 0.683: the first attempt times out, the retry
+    // gets through
+    let mut venue = Venue::new(PaperConfig {
+        latency: LatencyConfig {
+            seed: 6,
+            timeout_probability: 0.5,
+            ..LatencyConfig::default()
+        },
+        ..PaperConfig::default()
+    });
+    venue.set_book(&[(99.9, 1.0)], &[(100.1, 1.0)]).await;
+
+    let bid = with_id(limit_order("SOL/USDT", OrderSide::Buy, 1.0, 99.0), "bid");
+    assert_eq!(
+        venue.executor.submit_order(bid.clone()).await.unwrap(),
+        "bid"
+    );
+    assert_eq!(venue.executor.simulated_latency().timeouts, 1);
+    // Resubmitting what was accepted places nothing new
+    assert_eq!(venue.executor.submit_order(bid).await.unwrap(), "bid");
+    assert_eq!(venue.executor.open_orders().await.len(), 1);
+    assert!(venue.fills().is_empty());
+}
+
+#[tokio::test]
+async fn only_a_duplicate_waits_on_a_submission_in_flight() {
+    let venue = Venue::new(PaperConfig {
+        latency: LatencyConfig {
+            fill_ms: 50,
+            ..LatencyConfig::default()
+        },
+        ..PaperConfig::default()
+    });
+    venue.set_book(&[(99.9, 1.0)], &[(100.1, 1.0)]).await;
+    venue
+        .set_book_on("BTC/USDT", &[(39_990.0, 1.0)], &[(40_010.0, 1.0)])
+        .await;
+    let first = with_id(limit_order("SOL/USDT", OrderSide::Buy, 1.0, 99.0), "first");
+    let other = with_id(
+        limit_order("BTC/USDT", OrderSide::Buy, 1.0, 39_000.0),
+        "other",
+    );
+
+    // The first is still on its way to the book...
+    let mut placing = Box::pin(venue.executor.submit_order(first.clone()));
+    assert!(futures::poll!(placing.as_mut()).is_pending());
+    // ...which holds up neither another symbol's order...
+    assert_eq!(venue.executor.submit_order(other).await.unwrap(), "other");
+    // ...nor, once it lands, its duplicate, which gets the same id back
+    let mut duplicate = Box::pin(venue.executor.submit_order(first));
+    assert!(futures::poll!(duplicate.as_mut()).is_pending());
+    assert_eq!(placing.await.unwrap(), "first");
+    assert_eq!(duplicate.await.unwrap(), "first");
+    assert_eq!(venue.executor.open_orders().await.len(), 2);
+}
+
+#[tokio::test]
+async fn cancel_all_for_symbol_leaves_the_other_symbol_working() {
+    let venue = Venue::new(PaperConfig::default());
+    venue.set_book(&[(99.9, 1.0)], &[(100.1, 1.0)]).await;
+    venue
+        .set_book_on("BTC/USDT", &[(49_990.0, 1.0)], &[(50_010.0, 1.0)])
+        .await;
+    for (i, (symbol, price)) in [
+        ("SOL/USDT", 99.0),
+        ("SOL/USDT", 98.0),
+        ("SOL/USDT", 97.0),
+        ("BTC/USDT", 49_000.0),
+        ("BTC/USDT", 48_000.0),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let bid = with_id(
+            limit_order(symbol, OrderSide::Buy, 0.1, price),
+            &format!("bid-{i}"),
+        );
+        venue.executor.submit_order(bid).await.unwrap();
+    }
+    assert_eq!(venue.executor.open_orders().await.len(), 5);
+
+    let mut report = venue.executor.cancel_all_for_symbol("SOL/USDT").await;
+    report.cancelled.sort();
+    assert_eq!(report.cancelled, ["bid-0", "bid-1", "bid-2"]);
+    assert!(report.failed.is_empty());
+    assert!(
+        venue
+            .executor
+            .open_orders_for_symbol("SOL/USDT")
+            .await
+            .is_empty()
+    );
+    assert_eq!(
+        venue
+            .executor
+            .open_orders_for_symbol("BTC/USDT")
+            .await
+            .len(),
+        2
+    );
+    assert!(matches!(
+        venue.executor.cancel_order("bid-0").await,
+        Err(ExecutionError::UnknownOrder { .. })
+    ));
+
+    // The barrier is the call: what comes after it stays working
+    assert_eq!(venue.executor.cancel_all().await.cancelled.len(), 2);
+    let late = with_id(limit_order("SOL/USDT", OrderSide::Buy, 0.1, 99.0), "late");
+    venue.executor.submit_order(late).await.unwrap();
+    let open = venue.executor.open_orders().await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].order.id, "late");
+}
+
+#[tokio::test]
+async fn replacing_a_partial_fill_carries_what_already_filled() {
+    let mut venue = Venue::new(PaperConfig::default());
+    venue.set_book(&[(99.9, 1.0)], &[(100.1, 2.0)]).await;
+    let bid = with_id(limit_order("SOL/USDT", OrderSide::Buy, 5.0, 100.1), "bid");
+    venue.executor.submit_order(bid).await.unwrap();
+    assert_eq!(venue.fills().len(), 1);
+
+    let new_id = venue
+        .executor
+        .replace_order("bid", Some(99.5), Some(6.0))
+        .await
+        .unwrap();
+    assert_ne!(new_id, "bid");
+    let open = venue.executor.open_orders().await;
+    assert_eq!(open.len(), 1);
+    let state = &open[0];
+    assert_eq!(state.order.id, new_id);
+    assert_eq!(state.order.price, Some(99.5));
+    assert_eq!(state.filled_quantity, 2.0);
+    assert_eq!(state.remaining_quantity(), 4.0);
+
+    // The new total has to be more than what filled
+    assert!(matches!(
+        venue.executor.replace_order(&new_id, None, Some(2.0)).await,
+        Err(ExecutionError::ReplaceRejected { .. })
+    ));
+    // The old id is gone
+    assert!(matches!(
+        venue.executor.replace_order("bid", Some(99.0), None).await,
+        Err(ExecutionError::UnknownOrder { .. })
+    ));
+}
+
+#[tokio::test]
+async fn replacing_an_order_that_never_existed_is_unknown() {
+    let venue = Venue::new(PaperConfig::default());
+    assert!(matches!(
+        venue.executor.replace_order("missing", Some(99.0), Some(1.0)).await,
+        Err(ExecutionError::UnknownOrder { order_id }) if order_id == "missing"
+    ));
+    assert!(venue.executor.open_orders().await.is_empty());
+}
+
+// A limit buy with the given time in force, its name for an id
+async fn submit_with(
+    venue: &Venue,
+    tif: TimeInForce,
+    quantity: f64,
+    price: f64,
+) -> Result<String, ExecutionError> {
+    let order = Order {
+        time_in_force: tif,
+        ..with_id(
+            limit_order("SOL/USDT", OrderSide::Buy, quantity, price),
+            &format!("{tif:?}"),
+        )
+    };
+    venue.executor.submit_order(order).await
+}
+
+// 2 at 100.1 and 3 at 100.2 within reach of a buy limit at 100.2, and more
+// beyond it
+async fn stepped_venue() -> Venue {
+    let venue = Venue::new(PaperConfig::default());
+    venue
+        .set_book(&[(99.9, 1.0)], &[(100.1, 2.0), (100.2, 3.0), (100.5, 5.0)])
+        .await;
+    venue
+}
+
+#[tokio::test]
+async fn each_time_in_force_treats_the_remainder_its_way() {
+    let filled = |venue: &mut Venue| venue.fills().iter().map(|f| f.quantity).sum::<f64>();
+
+    // GTC fills what crosses and works the rest
+    let mut venue = stepped_venue().await;
+    submit_with(&venue, TimeInForce::Gtc, 8.0, 100.2)
+        .await
+        .unwrap();
+    assert_eq!(filled(&mut venue), 5.0);
+    let open = venue.executor.open_orders().await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].remaining_quantity(), 3.0);
+
+    // IOC fills the same and cancels the rest
+    let mut venue = stepped_venue().await;
+    submit_with(&venue, TimeInForce::Ioc, 8.0, 100.2)
+        .await
+        .unwrap();
+    assert_eq!(filled(&mut venue), 5.0);
+    assert!(venue.executor.open_orders().await.is_empty());
+
+    // FOK fills in full when the book holds enough within the limit...
+    let mut venue = stepped_venue().await;
+    submit_with(&venue, TimeInForce::Fok, 5.0, 100.2)
+        .await
+        .unwrap();
+    assert_eq!(filled(&mut venue), 5.0);
+
+    // ...and is rejected with nothing filled when it doesn't
+    let mut venue = stepped_venue().await;
+    let rejected = submit_with(&venue, TimeInForce::Fok, 8.0, 100.2).await;
+    assert!(matches!(
+        rejected,
+        Err(ExecutionError::FillOrKillUnfilled { quantity, available })
+            if quantity == 8.0 && available == 5.0
+    ));
+    assert_eq!(filled(&mut venue), 0.0);
+    assert!(venue.executor.open_orders().await.is_empty());
+
+    // Post-only is rejected if it would cross, and rests if it wouldn't
+    let mut venue = stepped_venue().await;
+    assert!(matches!(
+        submit_with(&venue, TimeInForce::PostOnly, 1.0, 100.1).await,
+        Err(ExecutionError::PostOnlyWouldCross { best, .. }) if best == 100.1
+    ));
+    submit_with(&venue, TimeInForce::PostOnly, 1.0, 100.0)
+        .await
+        .unwrap();
+    assert_eq!(filled(&mut venue), 0.0);
+    assert_eq!(venue.executor.open_orders().await.len(), 1);
+}
+
+#[tokio::test]
+async fn stops_trigger_on_their_own_side_of_the_book() {
+    let mut venue = Venue::new(PaperConfig::default());
+    venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+    // A sell stop watches the bid falling, a buy stop the ask rising
+    let sell = with_id(stop_order("SOL/USDT", OrderSide::Sell, 1.0, 99.0), "sell");
+    let buy = with_id(stop_order("SOL/USDT", OrderSide::Buy, 1.0, 101.0), "buy");
+    venue.executor.submit_order(sell).await.unwrap();
+    venue.executor.submit_order(buy).await.unwrap();
+    let open = venue.executor.open_orders().await;
+    assert_eq!(open.len(), 2);
+    assert!(open.iter().all(|s| s.status == OrderStatus::Untriggered));
+
+    // Up through the buy stop only
+    venue.set_book(&[(101.0, 5.0)], &[(101.2, 5.0)]).await;
+    assert_eq!(venue.executor.match_resting_orders("SOL/USDT").await, 1);
+    let fills = venue.fills();
+    assert_eq!(fills.len(), 1);
+    assert_eq!((fills[0].order_id.as_str(), fills[0].price), ("buy", 101.2));
+
+    // Then down through the sell stop
+    venue.set_book(&[(98.9, 5.0)], &[(99.1, 5.0)]).await;
+    assert_eq!(venue.executor.match_resting_orders("SOL/USDT").await, 1);
+    let fills = venue.fills();
+    assert_eq!((fills[0].order_id.as_str(), fills[0].price), ("sell", 98.9));
+    assert!(venue.executor.open_orders().await.is_empty());
+}
+
+#[tokio::test]
+async fn a_stop_limit_becomes_a_working_limit() {
+    let mut venue = Venue::new(PaperConfig::default());
+    venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+    let stop = Order {
+        order_type: OrderType::StopLimit {
+            stop_price: 99.0,
+            limit_price: 98.95,
+        },
+        ..with_id(market_order("SOL/USDT", OrderSide::Sell, 1.0), "stop")
+    };
+    venue.executor.submit_order(stop).await.unwrap();
+
+    // Triggered, but the bid is already below its limit
+    venue.set_book(&[(98.9, 5.0)], &[(99.1, 5.0)]).await;
+    venue.executor.match_resting_orders("SOL/USDT").await;
+    assert!(venue.fills().is_empty());
+    let open = venue.executor.open_orders().await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].order.order_type, OrderType::Limit);
+    assert_eq!(open[0].order.price, Some(98.95));
+}
+
+#[tokio::test]
+async fn a_cancelled_stop_never_triggers() {
+    let mut venue = Venue::new(PaperConfig::default());
+    venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+    let stop = with_id(stop_order("SOL/USDT", OrderSide::Sell, 1.0, 99.0), "stop");
+    venue.executor.submit_order(stop).await.unwrap();
+    venue.executor.cancel_order("stop").await.unwrap();
+
+    venue.set_book(&[(98.0, 5.0)], &[(98.2, 5.0)]).await;
+    assert_eq!(venue.executor.match_resting_orders("SOL/USDT").await, 0);
+    assert!(venue.fills().is_empty());
+    assert!(venue.executor.open_orders().await.is_empty());
+}
+
+// A bracket around a position: a stop at `stop` and a take-profit limit at
+// `take_profit`, both on `side`
+async fn bracket(venue: &Venue, side: OrderSide, stop: f64, take_profit: f64) -> String {
+    venue
+        .executor
+        .submit_oco(OcoOrder {
+            stop_loss: with_id(stop_order("SOL/USDT", side, 1.0, stop), "stop"),
+            take_profit: with_id(
+                limit_order("SOL/USDT", side, 1.0, take_profit),
+                "take-profit",
+            ),
+            cancel_threshold: 1.0,
+        })
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn either_leg_of_a_bracket_cancels_the_other() {
+    // Long, exits are sells: (stop, take-profit, where the market goes, the
+    // leg that fills)
+    for (side, stop, take_profit, (bid, ask), leg) in [
+        (OrderSide::Sell, 99.0, 101.0, (101.1, 101.3), "take-profit"),
+        (OrderSide::Sell, 99.0, 101.0, (98.8, 99.0), "stop"),
+        // Short, exits are buys
+        (OrderSide::Buy, 101.0, 99.0, (98.8, 98.9), "take-profit"),
+        (OrderSide::Buy, 101.0, 99.0, (101.0, 101.2), "stop"),
+    ] {
+        let mut venue = Venue::new(PaperConfig::default());
+        venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+        bracket(&venue, side, stop, take_profit).await;
+        assert_eq!(venue.executor.open_orders().await.len(), 2);
+
+        venue.set_book(&[(bid, 5.0)], &[(ask, 5.0)]).await;
+        assert_eq!(venue.executor.match_resting_orders("SOL/USDT").await, 1);
+        let fills = venue.fills();
+        assert_eq!(fills.len(), 1, "{side:?} {leg}");
+        assert_eq!(fills[0].order_id, leg);
+        assert!(
+            venue.executor.open_orders().await.is_empty(),
+            "{side:?} {leg}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn a_book_through_both_legs_fills_only_the_stop() {
+    let mut venue = Venue::new(PaperConfig::default());
+    venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+    // The bid is both at or below the stop and above the take-profit
+    bracket(&venue, OrderSide::Sell, 100.0, 99.5).await;
+    let fills = venue.fills();
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].order_id, "stop");
+    assert!(venue.executor.open_orders().await.is_empty());
+
+    // Cancelling a group cancels both legs
+    let venue = Venue::new(PaperConfig::default());
+    venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+    let group = bracket(&venue, OrderSide::Sell, 99.0, 101.0).await;
+    let mut report = venue.executor.cancel_group(&group).await;
+    report.cancelled.sort();
+    assert_eq!(report.cancelled, ["stop", "take-profit"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn orders_reach_the_book_in_turn_per_symbol_but_not_across_symbols() {
+    let mut venue = Venue::paused(PaperConfig {
+        latency: LatencyConfig {
+            fill_ms: 100,
+            ack_ms: 0,
+            ..LatencyConfig::default()
+        },
+        ..PaperConfig::default()
+    });
+    venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+    venue
+        .set_book_on("BTC/USDT", &[(39_990.0, 5.0)], &[(40_010.0, 5.0)])
+        .await;
+    let started = tokio::time::Instant::now();
+    let submit = |order: Order| {
+        let executor = venue.executor.clone();
+        tokio::spawn(async move {
+            executor.submit_order(order).await.unwrap();
+            started.elapsed().as_millis()
+        })
+    };
+    let first = submit(with_id(
+        market_order("SOL/USDT", OrderSide::Buy, 1.0),
+        "sol-1",
+    ));
+    let second = submit(with_id(
+        market_order("SOL/USDT", OrderSide::Buy, 2.0),
+        "sol-2",
+    ));
+    let btc = submit(with_id(
+        market_order("BTC/USDT", OrderSide::Buy, 1.0),
+        "btc",
+    ));
+
+    // The second SOL order sets off behind the first; BTC doesn't wait
+    assert_eq!(first.await.unwrap(), 100);
+    assert_eq!(second.await.unwrap(), 200);
+    assert_eq!(btc.await.unwrap(), 100);
+    let sol: Vec<String> = venue
+        .fills()
+        .into_iter()
+        .filter(|fill| fill.symbol == "SOL/USDT")
+        .map(|fill| fill.order_id)
+        .collect();
+    assert_eq!(sol, ["sol-1", "sol-2"]);
+}
+
+#[tokio::test]
+async fn a_market_sell_into_our_own_bid_follows_the_self_match_policy() {
+    for policy in [
+        SelfMatchPolicy::Allow,
+        SelfMatchPolicy::CancelResting,
+        SelfMatchPolicy::Reprice,
+        SelfMatchPolicy::Reject,
+    ] {
+        let mut venue = Venue::new(PaperConfig {
+            self_match: policy,
+            ..PaperConfig::default()
+        });
+        venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+        // Resting inside the spread, ahead of the book's bid
+        let bid = limit_order("SOL/USDT", OrderSide::Buy, 1.0, 99.95);
+        venue
+            .executor
+            .submit_order(with_id(bid, "bid"))
+            .await
+            .unwrap();
+        let sell = market_order("SOL/USDT", OrderSide::Sell, 1.0);
+        let result = venue.executor.submit_order(with_id(sell, "sell")).await;
+
+        let working: Vec<String> = venue
+            .executor
+            .open_orders_for_symbol("SOL/USDT")
+            .await
+            .into_iter()
+            .map(|state| state.order.id)
+            .collect();
+        let fills: Vec<(String, f64)> = venue
+            .fills()
+            .into_iter()
+            .map(|fill| (fill.order_id, fill.price))
+            .collect();
+        let sold = vec![("sell".to_string(), 99.9)];
+        match policy {
+            // Against the book, past our bid
+            SelfMatchPolicy::Allow => {
+                assert_eq!(result, Ok("sell".to_string()));
+                assert_eq!((working, fills), (vec!["bid".to_string()], sold));
+            }
+            SelfMatchPolicy::CancelResting => {
+                assert_eq!(result, Ok("sell".to_string()));
+                assert_eq!((working, fills), (Vec::new(), sold));
+            }
+            // An IOC limit just above our bid, with nothing there to take
+            SelfMatchPolicy::Reprice => {
+                assert_eq!(result, Ok("sell".to_string()));
+                assert_eq!((working, fills), (vec!["bid".to_string()], Vec::new()));
+            }
+            SelfMatchPolicy::Reject => {
+                assert_eq!(
+                    result,
+                    Err(ExecutionError::SelfMatch {
+                        resting_order_id: "bid".to_string()
+                    })
+                );
+                assert_eq!((working, fills), (vec!["bid".to_string()], Vec::new()));
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn a_full_symbol_quota_rejects_the_next_order_but_not_a_replace() {
+    let venue = Venue::new(PaperConfig::default());
+    venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+    venue
+        .set_book_on("ETH/USDT", &[(1_999.0, 5.0)], &[(2_001.0, 5.0)])
+        .await;
+    let risk = RiskManager::new(RiskParams {
+        max_open_orders_total: 4,
+        max_open_orders_per_symbol: 3,
+        ..RiskParams::default()
+    })
+    .with_open_orders(venue.executor.open_order_tracker());
+    let book = fixtures::order_book("SOL/USDT", 100.0, 10, 1);
+
+    // Three bids resting below the book fill SOL's quota
+    for (i, price) in [99.0, 98.0, 97.0].into_iter().enumerate() {
+        let bid = limit_order("SOL/USDT", OrderSide::Buy, 1.0, price);
+        assert_eq!(risk.validate_order(&bid, 100.0, &book).await, Ok(()));
+        let id = format!("bid-{i}");
+        venue
+            .executor
+            .submit_order(with_id(bid, &id))
+            .await
+            .unwrap();
+    }
+    let fourth = limit_order("SOL/USDT", OrderSide::Buy, 1.0, 96.0);
+    assert_eq!(
+        risk.validate_order(&fourth, 100.0, &book).await,
+        Err(RiskRejection::SymbolOpenOrders {
+            symbol: "SOL/USDT".to_string(),
+            open: 3,
+            limit: 3,
+        })
+    );
+    // A market order doesn't rest, so it doesn't count
+    let market = market_order("SOL/USDT", OrderSide::Buy, 1.0);
+    assert_eq!(risk.validate_order(&market, 100.0, &book).await, Ok(()));
+
+    // Moving a quote takes the slot of the one it replaces
+    let moved = limit_order("SOL/USDT", OrderSide::Buy, 1.0, 99.5);
+    assert_eq!(
+        risk.validate_replacement(&moved, "bid-0", 100.0, &book)
+            .await,
+        Ok(())
+    );
+    venue
+        .executor
+        .replace_order("bid-0", Some(99.5), None)
+        .await
+        .unwrap();
+    assert_eq!(venue.executor.open_order_tracker().count_for("SOL/USDT"), 3);
+
+    // Another symbol has its own quota, up to the total
+    let eth_book = fixtures::order_book("ETH/USDT", 2_000.0, 10, 1);
+    let eth = limit_order("ETH/USDT", OrderSide::Buy, 1.0, 1_990.0);
+    assert_eq!(risk.validate_order(&eth, 2_000.0, &eth_book).await, Ok(()));
+    venue
+        .executor
+        .submit_order(with_id(eth, "eth-0"))
+        .await
+        .unwrap();
+    let eth = limit_order("ETH/USDT", OrderSide::Buy, 1.0, 1_980.0);
+    assert_eq!(
+        risk.validate_order(&eth, 2_000.0, &eth_book).await,
+        Err(RiskRejection::OpenOrders { open: 4, limit: 4 })
+    );
+}
+
+#[tokio::test]
+async fn a_reduce_only_sell_fills_no_more_than_the_long_it_reduces() {
+    let risk = RiskManager::new(RiskParams::default());
+    let books = BookCache::default();
+    let executor = OrderExecutor::with_rate_limits(&unlimited())
+        .with_clock(Arc::new(VirtualClock::new(START_MS)))
+        .with_order_books(books.clone())
+        .with_positions(risk.position_book());
+    let fill_stream = executor.take_fills().unwrap();
+    let mut venue = Venue {
+        executor: Arc::new(executor),
+        books,
+        fill_stream,
+    };
+    venue.set_book(&[(99.9, 500.0)], &[(100.1, 500.0)]).await;
+    risk.apply_fill(&fixtures::fill("SOL/USDT", OrderSide::Buy, 60.0, 100.0))
+        .await;
+
+    // 100 against a 60 long sells only the 60
+    let mut exit = market_order("SOL/USDT", OrderSide::Sell, 100.0);
+    exit.reduce_only = true;
+    venue
+        .executor
+        .submit_order(with_id(exit.clone(), "exit-1"))
+        .await
+        .unwrap();
+    let fills = venue.fills();
+    let sold: f64 = fills.iter().map(|fill| fill.quantity).sum();
+    assert_eq!(sold, 60.0);
+    for fill in &fills {
+        risk.apply_fill(fill).await;
+    }
+    let held = risk
+        .position(&exit.venue, "SOL/USDT", exit.position_side)
+        .await
+        .map_or(0.0, |p| p.quantity);
+    assert_eq!(held, 0.0);
+
+    // Flat, the same exit is cancelled unfilled rather than going short
+    venue
+        .executor
+        .submit_order(with_id(exit, "exit-2"))
+        .await
+        .unwrap();
+    assert!(venue.fills().is_empty());
+    assert!(venue.executor.open_orders().await.is_empty());
+}
+
+#[tokio::test]
+async fn a_resting_limit_fills_only_past_the_volume_queued_ahead_of_it() {
+    let mut venue = Venue::new(PaperConfig {
+        queue_position: true,
+        ..PaperConfig::default()
+    });
+    venue.set_book(&[(100.0, 10.0)], &[(100.5, 5.0)]).await;
+    let buy = with_id(limit_order("SOL/USDT", OrderSide::Buy, 3.0, 100.0), "buy");
+    venue.executor.submit_order(buy).await.unwrap();
+    venue.executor.match_resting_orders("SOL/USDT").await;
+    let ahead = async |venue: &Venue| venue.executor.open_orders().await[0].queue_ahead;
+    assert_eq!(ahead(&venue).await, Some(10.0));
+
+    // Printing the 10 ahead of it fills nothing; 2 more fill 2
+    assert_eq!(venue.executor.match_trade("SOL/USDT", 100.0, 10.0).await, 0);
+    assert!(venue.fills().is_empty());
+    assert_eq!(ahead(&venue).await, Some(0.0));
+    venue.executor.match_trade("SOL/USDT", 100.0, 2.0).await;
+    let filled: Vec<f64> = venue.fills().iter().map(|f| f.quantity).collect();
+    assert_eq!(filled, [2.0]);
+    assert_eq!(
+        venue.executor.open_orders().await[0].remaining_quantity(),
+        1.0
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn simulated_latency_stays_within_its_jitter_for_each_order() {
+    let venue = Venue::paused(PaperConfig {
+        latency: LatencyConfig {
+            // 90 ± 10 to the book and 10 ± 10 back: 100 ± 20 in all
+            fill_ms: 90,
+            ack_ms: 10,
+            jitter_ms: 10,
+            seed: 42,
+            ..LatencyConfig::default()
+        },
+        ..PaperConfig::default()
+    });
+    let symbols: Vec<String> = (0..20).map(|i| format!("S{i}/USDT")).collect();
+    for symbol in &symbols {
+        venue
+            .set_book_on(symbol, &[(99.9, 5.0)], &[(100.1, 5.0)])
+            .await;
+    }
+
+    // One at a time, each takes 100ms ± 20ms
+    let mut taken = Vec::new();
+    for (i, symbol) in symbols.iter().enumerate() {
+        let bid = with_id(
+            limit_order(symbol, OrderSide::Buy, 1.0, 99.0),
+            &format!("bid-{i}"),
+        );
+        let started = tokio::time::Instant::now();
+        venue.executor.submit_order(bid).await.unwrap();
+        taken.push(started.elapsed().as_millis());
+    }
+    assert!(taken.iter().all(|ms| (80..=120).contains(ms)), "{taken:?}");
+    // ...and not all alike
+    assert!(taken.iter().any(|ms| *ms != taken[0]), "{taken:?}");
+    let stats = venue.executor.simulated_latency();
+    assert_eq!((stats.fills.count, stats.acks.count), (20, 20));
+    assert!(
+        stats.fills.min_ms >= 80 && stats.fills.max_ms <= 100,
+        "{stats:?}"
+    );
+    assert!(stats.acks.max_ms <= 20, "{stats:?}");
+
+    // All at once, across symbols, the delays overlap rather than add up
+    let started = tokio::time::Instant::now();
+    let asks = symbols.iter().enumerate().map(|(i, symbol)| {
+        let ask = with_id(
+            limit_order(symbol, OrderSide::Sell, 1.0, 101.0),
+            &format!("ask-{i}"),
+        );
+        venue.executor.submit_order(ask)
+    });
+    for placed in futures::future::join_all(asks).await {
+        placed.unwrap();
+    }
+    assert!(started.elapsed().as_millis() <= 120);
+}
+
+#[tokio::test]
+async fn a_lost_acknowledgement_is_retried_onto_the_order_already_placed() {
+    // Seed 6 draws 0.335 then 0.683: the first attempt is placed but its
+    // acknowledgement lost, the retry gets through
+    let mut venue = Venue::new(PaperConfig {
+        latency: LatencyConfig {
+            seed: 6,
+            ack_loss_probability: 0.5,
+            ..LatencyConfig::default()
+        },
+        ..PaperConfig::default()
+    });
+    venue.set_book(&[(99.9, 5.0)], &[(100.1, 5.0)]).await;
+
+    let buy = with_id(limit_order("SOL/USDT", OrderSide::Buy, 1.0, 100.1), "buy");
+    assert_eq!(venue.executor.submit_order(buy).await.unwrap(), "buy");
+    let stats = venue.executor.simulated_latency();
+    assert_eq!((stats.acks_lost, stats.timeouts), (1, 0));
+    // The retry found the client order id accepted and went no further:
+    // one trip to the book, one fill
+    assert_eq!(stats.fills.count, 1);
+    let fills = venue.fills();
+    assert_eq!(fills.len(), 1);
+    assert_eq!(
+        (fills[0].order_id.as_str(), fills[0].quantity),
+        ("buy", 1.0)
+    );
+}
@@ -0,0 +1,495 @@
+// Tests that run the live loop on a paused runtime: tokio's clock only
+// moves when every task waits, so seconds of trading take no real time
+use super::{MockFeed, PausedClock, START_MS, Scripted, config, with_id};
+use crate::builder::TradingBotBuilder;
+use crate::calendar::TradingWindow;
+use crate::events::BotEvent;
+use crate::fixtures::limit_order;
+use crate::rate_limit::RateLimitConfig;
+use crate::{OrderBook, OrderSide, Price, StrategyEntry, TradingSignal, TradingStrategy};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+fn drain(events: &mut broadcast::Receiver<BotEvent>) -> Vec<BotEvent> {
+    let mut drained = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        drained.push(event);
+    }
+    drained
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_paused_feed_halts_only_its_symbol() {
+    let mut config = config(&["SOL/USDT", "BTC/USDT"]);
+    // A tick a second, as the history takes one per timestamp
+    config.schedule.poll_interval_ms = 1_000;
+    config.schedule.evaluation_interval_ms = 1_000;
+    config.staleness.max_age_ms = 5_000;
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let feed = MockFeed::new(clock.clone(), &[("SOL/USDT", 10.0), ("BTC/USDT", 20.0)]);
+    let (buyer, script) = Scripted::new("buyer");
+    *script.lock().unwrap() = Some((OrderSide::Buy, 0.01));
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_feed(feed.clone())
+        .with_strategy_entry(StrategyEntry::new(Box::new(buyer)))
+        .assemble();
+    let mut events = bot.subscribe_events();
+    let running = tokio::spawn({
+        let (bot, symbols) = (bot.clone(), config.symbols.clone());
+        async move { bot.start(symbols).await }
+    });
+
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    let resting = limit_order("BTC/USDT", OrderSide::Buy, 0.01, 1.0);
+    bot.order_executor
+        .submit_order(with_id(resting, "resting"))
+        .await
+        .unwrap();
+    feed.pause("BTC/USDT");
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    assert!(
+        bot.order_executor
+            .open_orders_for_symbol("BTC/USDT")
+            .await
+            .is_empty()
+    );
+    feed.resume("BTC/USDT");
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    bot.stop().await;
+    running.await.unwrap();
+
+    // Orders per symbol before BTC went stale, while it was, and after
+    let mut orders = [[0; 2]; 3];
+    let mut phase = 0;
+    for event in drain(&mut events) {
+        match event {
+            BotEvent::DataStale {
+                symbol,
+                cancelled_orders,
+                ..
+            } => {
+                assert_eq!((phase, symbol.as_str()), (0, "BTC/USDT"));
+                assert_eq!(cancelled_orders, 1);
+                phase = 1;
+            }
+            BotEvent::DataFresh { symbol } => {
+                assert_eq!((phase, symbol.as_str()), (1, "BTC/USDT"));
+                phase = 2;
+            }
+            BotEvent::OrderSubmitted(order) if order.strategy.is_some() => {
+                orders[phase][usize::from(order.symbol == "BTC/USDT")] += 1;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(phase, 2);
+    let [before, stale, after] = orders;
+    assert!(before[0] > 0 && before[1] > 0, "{before:?}");
+    assert!(stale[0] > 10 && stale[1] == 0, "{stale:?}");
+    assert!(after[0] > 0 && after[1] > 0, "{after:?}");
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_symbol_subscribed_mid_run_trades_until_unsubscribed() {
+    let mut config = config(&["SOL/USDT"]);
+    config.schedule.poll_interval_ms = 1_000;
+    config.schedule.evaluation_interval_ms = 1_000;
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let feed = MockFeed::new(clock.clone(), &[("SOL/USDT", 10.0), ("ETH/USDT", 20.0)]);
+    let (buyer, script) = Scripted::new("buyer");
+    *script.lock().unwrap() = Some((OrderSide::Buy, 0.01));
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_feed(feed)
+        .with_strategy_entry(StrategyEntry::new(Box::new(buyer)))
+        .assemble();
+    let mut events = bot.subscribe_events();
+    let running = tokio::spawn({
+        let (bot, symbols) = (bot.clone(), config.symbols.clone());
+        async move { bot.start(symbols).await }
+    });
+
+    tokio::time::sleep(Duration::from_secs(20)).await;
+    let before = drain(&mut events);
+    bot.subscribe("ETH/USDT").await.unwrap();
+    assert!(bot.subscribe("ETH/USDT").await.is_err());
+    assert_eq!(bot.symbols().await.len(), 2);
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    let subscribed = drain(&mut events);
+    let report = bot.unsubscribe("ETH/USDT", true).await.unwrap();
+    assert!(report.flattened);
+    tokio::time::sleep(Duration::from_secs(20)).await;
+    bot.stop().await;
+    running.await.unwrap();
+    let after = drain(&mut events);
+
+    // Ticks and strategy orders for ETH in a batch of events
+    let eth = |events: &[BotEvent]| {
+        let ticks = events
+            .iter()
+            .filter(|event| matches!(event, BotEvent::Price(price) if price.symbol == "ETH/USDT"))
+            .count();
+        let orders = events
+            .iter()
+            .filter(|event| {
+                matches!(event, BotEvent::OrderSubmitted(order)
+                    if order.symbol == "ETH/USDT" && order.strategy.is_some())
+            })
+            .count();
+        (ticks, orders)
+    };
+    assert_eq!(eth(&before), (0, 0));
+    let (ticks, orders) = eth(&subscribed);
+    assert!(ticks >= 25, "{ticks} ticks");
+    assert!(orders > 0);
+    assert_eq!(eth(&after), (0, 0));
+    // SOL traded throughout
+    assert!(after.iter().any(|event| {
+        matches!(event, BotEvent::OrderSubmitted(order) if order.symbol == "SOL/USDT")
+    }));
+}
+
+// Counts its evaluations and never trades
+struct Counting(Arc<AtomicUsize>);
+
+impl TradingStrategy for Counting {
+    fn analyze(&self, _prices: &[Price], _orderbook: &OrderBook) -> Option<TradingSignal> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    fn name(&self) -> &str {
+        "counting"
+    }
+}
+
+// Evaluations over ten simulated seconds, after the history has warmed up
+async fn evaluations(evaluation_interval_ms: u64) -> usize {
+    let mut config = config(&["SOL/USDT"]);
+    config.schedule.poll_interval_ms = 1_000;
+    config.schedule.evaluation_interval_ms = evaluation_interval_ms;
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let count = Arc::new(AtomicUsize::new(0));
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock.clone())
+        .with_feed(MockFeed::new(clock, &[("SOL/USDT", 10.0)]))
+        .with_strategy_entry(StrategyEntry::new(Box::new(Counting(count.clone()))))
+        .assemble();
+    let running = tokio::spawn({
+        let (bot, symbols) = (bot.clone(), config.symbols.clone());
+        async move { bot.start(symbols).await }
+    });
+    tokio::time::sleep(Duration::from_millis(15_250)).await;
+    let before = count.load(Ordering::Relaxed);
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    let counted = count.load(Ordering::Relaxed) - before;
+    bot.stop().await;
+    running.await.unwrap();
+    counted
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_evaluation_interval_sets_the_evaluation_rate() {
+    // Two a second at 500 ms, four at 250 ms
+    assert_eq!(evaluations(500).await, 20);
+    assert_eq!(evaluations(250).await, 40);
+    // At zero, one per new price: a tick a second
+    assert_eq!(evaluations(0).await, 10);
+}
+
+// Strategy orders per symbol over a minute of SOL and BTC, with SOL's book
+// taking `sol_book` to fetch
+async fn orders_with_slow_sol(sol_book: Duration) -> (usize, usize) {
+    let mut config = config(&["SOL/USDT", "BTC/USDT"]);
+    config.schedule.poll_interval_ms = 1_000;
+    config.schedule.evaluation_interval_ms = 1_000;
+    config.staleness.max_age_ms = 60_000;
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let feed = MockFeed::new(clock.clone(), &[("SOL/USDT", 10.0), ("BTC/USDT", 20.0)]);
+    feed.slow_down("SOL/USDT", sol_book);
+    let (buyer, script) = Scripted::new("buyer");
+    *script.lock().unwrap() = Some((OrderSide::Buy, 0.01));
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_feed(feed)
+        .with_strategy_entry(StrategyEntry::new(Box::new(buyer)))
+        .assemble();
+    let mut events = bot.subscribe_events();
+    let running = tokio::spawn({
+        let (bot, symbols) = (bot.clone(), config.symbols.clone());
+        async move { bot.start(symbols).await }
+    });
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    bot.stop().await;
+    running.await.unwrap();
+
+    let mut orders = (0, 0);
+    for event in drain(&mut events) {
+        if let BotEvent::OrderSubmitted(order) = event
+            && order.strategy.is_some()
+        {
+            match order.symbol.as_str() {
+                "SOL/USDT" => orders.0 += 1,
+                _ => orders.1 += 1,
+            }
+        }
+    }
+    orders
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_slow_book_fetch_delays_only_its_symbol() {
+    let (sol, btc) = orders_with_slow_sol(Duration::ZERO).await;
+    assert!(sol > 0 && btc > 0);
+    // Five seconds a book: SOL's history fills a fifth as fast
+    let (slow_sol, slow_btc) = orders_with_slow_sol(Duration::from_secs(5)).await;
+    assert!(slow_sol < sol / 2, "{slow_sol} of {sol}");
+    assert_eq!(slow_btc, btc);
+}
+
+#[tokio::test(start_paused = true)]
+async fn entries_stop_at_the_window_close_but_a_stop_still_fires() {
+    let mut config = config(&["SOL/USDT"]);
+    config.schedule.poll_interval_ms = 1_000;
+    config.schedule.evaluation_interval_ms = 1_000;
+    config.risk.place_stop_orders = true;
+    // Open for the first minute of every day only
+    config.calendar.enabled = true;
+    config.calendar.windows = vec![TradingWindow {
+        days: Vec::new(),
+        start: "00:00".to_string(),
+        end: "00:01".to_string(),
+        symbols: None,
+    }];
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let feed = MockFeed::new(clock.clone(), &[("SOL/USDT", 100.0)]);
+    let (buyer, script) = Scripted::new("buyer");
+    *script.lock().unwrap() = Some((OrderSide::Buy, 0.1));
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_feed(feed.clone())
+        .with_strategy_entry(StrategyEntry::new(Box::new(buyer)))
+        .assemble();
+    let mut events = bot.subscribe_events();
+    let running = tokio::spawn({
+        let (bot, symbols) = (bot.clone(), config.symbols.clone());
+        async move { bot.start(symbols).await }
+    });
+
+    tokio::time::sleep(Duration::from_secs(90)).await;
+    let open = drain(&mut events);
+    assert!(
+        open.iter()
+            .any(|e| matches!(e, BotEvent::SessionClosed { .. }))
+    );
+    let entries = |events: &[BotEvent]| {
+        events
+            .iter()
+            .filter(|e| matches!(e, BotEvent::OrderSubmitted(o) if o.strategy.is_some()))
+            .count()
+    };
+    assert!(entries(&open) > 0);
+    let held = bot.positions().await[0].quantity;
+
+    // Well through the 2% stop while closed
+    feed.set_price("SOL/USDT", 90.0);
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    bot.stop().await;
+    running.await.unwrap();
+
+    let closed = drain(&mut events);
+    assert_eq!(entries(&closed), 0);
+    let stopped: f64 = closed
+        .iter()
+        .filter_map(|e| match e {
+            BotEvent::Fill(fill) if fill.side == OrderSide::Sell => Some(fill.quantity),
+            _ => None,
+        })
+        .sum();
+    assert!((stopped - held).abs() < 1e-9, "{stopped} of {held}");
+    assert_eq!(bot.positions().await[0].quantity, 0.0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_20_pct_jump_pauses_entries_until_the_cooldown_runs_out() {
+    let mut config = config(&["SOL/USDT"]);
+    config.schedule.poll_interval_ms = 1_000;
+    config.schedule.evaluation_interval_ms = 1_000;
+    config.breaker.enabled = true;
+    config.breaker.max_move_pct = Some(0.1);
+    config.breaker.horizon_secs = 10;
+    config.breaker.cooldown_secs = 30;
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let feed = MockFeed::new(clock.clone(), &[("SOL/USDT", 100.0)]);
+    let (buyer, script) = Scripted::new("buyer");
+    *script.lock().unwrap() = Some((OrderSide::Buy, 0.01));
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_feed(feed.clone())
+        .with_strategy_entry(StrategyEntry::new(Box::new(buyer)))
+        .assemble();
+    let mut events = bot.subscribe_events();
+    let running = tokio::spawn({
+        let (bot, symbols) = (bot.clone(), config.symbols.clone());
+        async move { bot.start(symbols).await }
+    });
+    let entries = |events: &[BotEvent]| {
+        events
+            .iter()
+            .filter(|e| matches!(e, BotEvent::OrderSubmitted(o) if o.strategy.is_some()))
+            .count()
+    };
+
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    assert!(entries(&drain(&mut events)) > 0);
+    let resting = limit_order("SOL/USDT", OrderSide::Buy, 0.01, 90.0);
+    bot.order_executor
+        .submit_order(with_id(resting, "resting"))
+        .await
+        .unwrap();
+
+    // One tick 20% up, held there: every tick within the horizon of the
+    // jump extends the cooldown, so it lasts until 40 s after it
+    feed.set_price("SOL/USDT", 120.0);
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    let tripped = drain(&mut events);
+    let halt = tripped.iter().find_map(|e| match e {
+        BotEvent::VolatilityHalt {
+            move_pct,
+            cancelled_orders,
+            ..
+        } => Some((*move_pct, *cancelled_orders)),
+        _ => None,
+    });
+    let (move_pct, cancelled_orders) = halt.expect("no VolatilityHalt");
+    assert!((move_pct - 0.2).abs() < 1e-9);
+    assert_eq!(cancelled_orders, 1);
+    assert_eq!(bot.tripped_symbols()[0].symbol, "SOL/USDT");
+    // An evaluation may have caught the last price before the jump
+    let halted_at = tripped
+        .iter()
+        .position(|e| matches!(e, BotEvent::VolatilityHalt { .. }))
+        .unwrap();
+    assert_eq!(entries(&tripped[halted_at..]), 0);
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    let paused = drain(&mut events);
+    assert_eq!(entries(&paused), 0);
+    assert!(
+        !paused
+            .iter()
+            .any(|e| matches!(e, BotEvent::VolatilityResumed { .. }))
+    );
+
+    tokio::time::sleep(Duration::from_secs(20)).await;
+    bot.stop().await;
+    running.await.unwrap();
+    let resumed = drain(&mut events);
+    assert!(
+        resumed
+            .iter()
+            .any(|e| matches!(e, BotEvent::VolatilityResumed { .. }))
+    );
+    assert!(entries(&resumed) > 0);
+    assert!(bot.tripped_symbols().is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn positions_are_marked_on_a_timer_as_the_price_moves() {
+    let mut config = config(&["SOL/USDT"]);
+    config.schedule.poll_interval_ms = 1_000;
+    config.schedule.evaluation_interval_ms = 1_000;
+    config.marks.refresh_interval_ms = 500;
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let feed = MockFeed::new(clock.clone(), &[("SOL/USDT", 100.0)]);
+    let (buyer, script) = Scripted::new("buyer");
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_feed(feed.clone())
+        .with_strategy_entry(StrategyEntry::new(Box::new(buyer)))
+        .assemble();
+    let running = tokio::spawn({
+        let (bot, symbols) = (bot.clone(), config.symbols.clone());
+        async move { bot.start(symbols).await }
+    });
+
+    tokio::time::sleep(Duration::from_secs(15)).await;
+    *script.lock().unwrap() = Some((OrderSide::Buy, 2.0));
+    tokio::time::sleep(Duration::from_millis(1_500)).await;
+    *script.lock().unwrap() = None;
+    let position = bot.positions().await[0].clone();
+    assert!(position.quantity > 0.0);
+
+    for price in [110.0, 95.0] {
+        feed.set_price("SOL/USDT", price);
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        let marked = bot.positions().await[0].clone();
+        assert_eq!(marked.quantity, position.quantity);
+        let expected = position.quantity * (price - position.avg_price);
+        assert!(
+            (marked.unrealized_pnl - expected).abs() < 1e-9,
+            "at {price}"
+        );
+    }
+    bot.stop().await;
+    running.await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_trip_that_waits_on_the_cancel_budget_reports_the_cooldown_it_began_with() {
+    let mut config = config(&["SOL/USDT"]);
+    config.schedule.poll_interval_ms = 1_000;
+    config.schedule.evaluation_interval_ms = 1_000;
+    config.breaker.enabled = true;
+    config.breaker.max_move_pct = Some(0.1);
+    config.breaker.horizon_secs = 10;
+    config.breaker.cooldown_secs = 5;
+    // One cancel every 20 s, so the trip's cancel outlasts its cooldown
+    config.rate_limits = RateLimitConfig {
+        cancels_per_second: 0.05,
+        cancel_burst: 1,
+        ..RateLimitConfig::default()
+    };
+    let clock = Arc::new(PausedClock::new(START_MS));
+    let feed = MockFeed::new(clock.clone(), &[("SOL/USDT", 100.0)]);
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_clock(clock)
+        .with_feed(feed.clone())
+        .assemble();
+    let mut events = bot.subscribe_events();
+    let running = tokio::spawn({
+        let (bot, symbols) = (bot.clone(), config.symbols.clone());
+        async move { bot.start(symbols).await }
+    });
+
+    tokio::time::sleep(Duration::from_secs(15)).await;
+    for i in 0..2 {
+        let resting = limit_order("SOL/USDT", OrderSide::Buy, 0.01, 90.0 + i as f64);
+        bot.order_executor
+            .submit_order(with_id(resting, &format!("resting-{i}")))
+            .await
+            .unwrap();
+    }
+    bot.order_executor.cancel_order("resting-0").await.unwrap();
+
+    feed.set_price("SOL/USDT", 120.0);
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    bot.stop().await;
+    running.await.unwrap();
+    let (cooldown_secs, cancelled_orders) = drain(&mut events)
+        .into_iter()
+        .find_map(|e| match e {
+            BotEvent::VolatilityHalt {
+                cooldown_secs,
+                cancelled_orders,
+                ..
+            } => Some((cooldown_secs, cancelled_orders)),
+            _ => None,
+        })
+        .expect("no VolatilityHalt");
+    assert_eq!(cooldown_secs, 5);
+    assert_eq!(cancelled_orders, 1);
+    assert!(bot.open_orders().await.is_empty());
+}
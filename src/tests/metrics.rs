@@ -0,0 +1,67 @@
+use super::{Scripted, config};
+use crate::builder::TradingBotBuilder;
+use crate::metrics;
+use crate::{OrderSide, StrategyEntry};
+use std::time::Duration;
+
+// The value of the sample `series` (name and labels, as rendered)
+fn sample(scrape: &str, series: &str) -> f64 {
+    scrape
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("no {series} in\n{scrape}"))
+        .parse()
+        .unwrap()
+}
+
+// A paper bot trading the simulated market every 10 ms, scraped twice
+#[tokio::test]
+async fn a_running_bot_serves_its_metrics() {
+    let mut config = config(&["SOL/USDT"]);
+    config.metrics.enabled = true;
+    config.metrics.bind = "127.0.0.1:0".to_string();
+    config.simulation.seed = Some(1);
+    config.schedule.poll_interval_ms = 10;
+    config.schedule.evaluation_interval_ms = 10;
+    // Its minute-long sleep would hold up the shutdown
+    config.attribution.log_interval_secs = 0;
+    let (buyer, script) = Scripted::new("buyer");
+    *script.lock().unwrap() = Some((OrderSide::Buy, 0.01));
+    let bot = TradingBotBuilder::from_config(&config)
+        .with_strategy_entry(StrategyEntry::new(Box::new(buyer)))
+        .assemble();
+    let addr = metrics::serve(bot.metrics(), &config.metrics.bind)
+        .await
+        .unwrap();
+    let url = format!("http://{addr}/metrics");
+    let scrape = || async { reqwest::get(&url).await.unwrap().text().await.unwrap() };
+
+    let running = tokio::spawn({
+        let bot = bot.clone();
+        async move { bot.start(vec!["SOL/USDT".to_string()]).await }
+    });
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let first = scrape().await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let second = scrape().await;
+    bot.stop().await;
+    running.await.unwrap();
+
+    let counters = [
+        r#"hft_price_updates_total{symbol="SOL/USDT"}"#,
+        r#"hft_signals_total{strategy="buyer"}"#,
+        r#"hft_orders_submitted_total{symbol="SOL/USDT"}"#,
+        r#"hft_fills_total{symbol="SOL/USDT"}"#,
+        "hft_loop_iteration_seconds_count",
+        "hft_order_submit_seconds_count",
+    ];
+    for series in counters {
+        let (before, after) = (sample(&first, series), sample(&second, series));
+        assert!(before > 0.0, "{series} is {before}");
+        assert!(after > before, "{series} went from {before} to {after}");
+    }
+    // Long the whole time, so its gauges are set
+    assert!(sample(&second, r#"hft_position_quantity{symbol="SOL/USDT"}"#) > 0.0);
+    sample(&second, r#"hft_unrealized_pnl{symbol="SOL/USDT"}"#);
+    sample(&second, "hft_daily_pnl");
+}
@@ -0,0 +1,337 @@
+// Tests that drive a whole bot. Each one builds a bot on a virtual clock and
+// steps it the way the backtester does: record a tick, install its book, run
+// one evaluation pass, book the fills. Strategies are scripted so a test
+// says exactly which signals go in.
+
+mod api;
+mod backtest;
+mod bot;
+mod executor;
+mod live;
+mod metrics;
+mod order_book;
+mod recording;
+mod risk;
+mod simulation;
+mod strategies;
+
+use crate::backtest::BacktestConfig;
+use crate::builder::TradingBotBuilder;
+use crate::clock::{Clock, VirtualClock};
+use crate::config::BotConfig;
+use crate::events::BotEvent;
+use crate::rate_limit::{ExhaustedPolicy, RateLimitConfig};
+use crate::schedule::Due;
+use crate::symbols::Symbol;
+use crate::venue::Exchange;
+use crate::{
+    Fill, MarketData, Order, OrderBook, OrderSide, Price, StrategyEntry, TimeInForce, TradingBot,
+    TradingSignal, TradingStrategy,
+};
+use futures::future::BoxFuture;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+// Tokio's clock from `start_ms` on. On a paused runtime (start_paused) time
+// only moves when every task is waiting, so the live loop runs its tasks in
+// a fixed order and never really sleeps.
+pub(crate) struct PausedClock {
+    start_ms: u64,
+    started: tokio::time::Instant,
+}
+
+impl PausedClock {
+    pub(crate) fn new(start_ms: u64) -> Self {
+        Self {
+            start_ms,
+            started: tokio::time::Instant::now(),
+        }
+    }
+}
+
+impl Clock for PausedClock {
+    fn now_ms(&self) -> u64 {
+        self.start_ms + self.started.elapsed().as_millis() as u64
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+// A feed quoting each symbol at a fixed price, stamped with the clock's
+// time, around the backtester's synthetic book. Clones share their state,
+// so a test keeps one to move, pause or slow down symbols. Ticks are stamped to the second
+// and the price history keeps one per timestamp, so poll it once a second.
+#[derive(Clone)]
+pub(crate) struct MockFeed {
+    clock: Arc<dyn Clock>,
+    books: BacktestConfig,
+    prices: Arc<Mutex<HashMap<String, f64>>>,
+    paused: Arc<Mutex<HashSet<String>>>,
+    // How long a symbol's book takes to fetch
+    slow: Arc<Mutex<HashMap<String, Duration>>>,
+}
+
+impl MockFeed {
+    pub(crate) fn new(clock: Arc<dyn Clock>, prices: &[(&str, f64)]) -> Self {
+        Self {
+            clock,
+            books: BacktestConfig::default(),
+            prices: Arc::new(Mutex::new(
+                prices.iter().map(|(s, p)| (s.to_string(), *p)).collect(),
+            )),
+            paused: Arc::default(),
+            slow: Arc::default(),
+        }
+    }
+
+    pub(crate) fn set_price(&self, symbol: &str, price: f64) {
+        self.prices
+            .lock()
+            .unwrap()
+            .insert(symbol.to_string(), price);
+    }
+
+    // A paused symbol reports nothing, as if its feed had died
+    pub(crate) fn pause(&self, symbol: &str) {
+        self.paused.lock().unwrap().insert(symbol.to_string());
+    }
+
+    pub(crate) fn resume(&self, symbol: &str) {
+        self.paused.lock().unwrap().remove(symbol);
+    }
+
+    pub(crate) fn slow_down(&self, symbol: &str, delay: Duration) {
+        self.slow.lock().unwrap().insert(symbol.to_string(), delay);
+    }
+
+    fn price(&self, symbol: &str) -> Option<Price> {
+        if self.paused.lock().unwrap().contains(symbol) {
+            return None;
+        }
+        Some(Price {
+            symbol: symbol.to_string(),
+            price: *self.prices.lock().unwrap().get(symbol)?,
+            timestamp: self.clock.now_secs(),
+            volume: 1_000.0,
+        })
+    }
+}
+
+impl MarketData for MockFeed {
+    fn get_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Price>> {
+        Box::pin(async move { self.price(symbol) })
+    }
+
+    fn get_orderbook<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<OrderBook>> {
+        Box::pin(async move {
+            let delay = self.slow.lock().unwrap().get(symbol).copied();
+            if let Some(delay) = delay {
+                self.clock.sleep(delay).await;
+            }
+            Some(self.books.synthetic_book(&self.price(symbol)?))
+        })
+    }
+}
+
+// 2024-01-01T00:00:00Z, where every test bot's clock starts
+pub(crate) const START_MS: u64 = 1_704_067_200_000;
+
+// The default config on `symbols`, unpaced as a simulated venue needs no
+// rate limiting
+pub(crate) fn config(symbols: &[&str]) -> BotConfig {
+    let mut config = BotConfig {
+        symbols: symbols.iter().map(|s| s.to_string()).collect(),
+        ..BotConfig::default()
+    };
+    config.rate_limits = RateLimitConfig {
+        orders_per_second: 1e9,
+        order_burst: usize::MAX,
+        cancels_per_second: 1e9,
+        cancel_burst: usize::MAX,
+        on_exhausted: ExhaustedPolicy::Reject,
+    };
+    config
+}
+
+// `order` with its own id, which is also its client order id, so the
+// executor doesn't take it for a resubmission
+pub(crate) fn with_id(mut order: Order, id: &str) -> Order {
+    order.id = id.to_string();
+    order.client_order_id = id.to_string();
+    order
+}
+
+// What a scripted strategy signals next; None keeps it quiet
+pub(crate) type Script = Arc<Mutex<Option<(OrderSide, f64)>>>;
+
+// Signals whatever its script says, at market, on every pass
+pub(crate) struct Scripted {
+    name: String,
+    script: Script,
+}
+
+impl Scripted {
+    pub(crate) fn new(name: &str) -> (Self, Script) {
+        let script = Script::default();
+        let strategy = Self {
+            name: name.to_string(),
+            script: script.clone(),
+        };
+        (strategy, script)
+    }
+}
+
+impl TradingStrategy for Scripted {
+    fn analyze(&self, _prices: &[Price], orderbook: &OrderBook) -> Option<TradingSignal> {
+        let (action, quantity) = (*self.script.lock().unwrap())?;
+        Some(TradingSignal {
+            symbol: orderbook.symbol.as_str().into(),
+            action,
+            confidence: 1.0,
+            target_price: orderbook.mid_price()?,
+            quantity,
+            time_in_force: TimeInForce::Gtc,
+            limit_price: None,
+        })
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+// A bot on a virtual clock plus what it has put out so far
+pub(crate) struct TestBot {
+    pub bot: TradingBot,
+    pub clock: Arc<VirtualClock>,
+    pub books: BacktestConfig,
+    fill_stream: mpsc::UnboundedReceiver<Fill>,
+    events: broadcast::Receiver<BotEvent>,
+    pub fills: Vec<Fill>,
+}
+
+impl TestBot {
+    // `entries` in place of the config's strategies, if any are given
+    pub(crate) fn with_strategies(config: &BotConfig, entries: Vec<StrategyEntry>) -> Self {
+        Self::with_venues(config, entries, Vec::new())
+    }
+
+    // ...and with `venues` alongside the config's
+    pub(crate) fn with_venues(
+        config: &BotConfig,
+        entries: Vec<StrategyEntry>,
+        venues: Vec<Arc<dyn Exchange>>,
+    ) -> Self {
+        let clock = Arc::new(VirtualClock::new(START_MS));
+        let mut builder = TradingBotBuilder::from_config(config).with_clock(clock.clone());
+        for venue in venues {
+            builder = builder.with_venue(venue);
+        }
+        for entry in entries {
+            builder = builder.with_strategy_entry(entry);
+        }
+        let bot = builder.assemble();
+        let fill_stream = bot
+            .order_executor
+            .take_fills()
+            .expect("a fresh executor still has its fill stream");
+        let events = bot.subscribe_events();
+        Self {
+            bot,
+            clock,
+            books: config.backtest.clone(),
+            fill_stream,
+            events,
+            fills: Vec::new(),
+        }
+    }
+
+    // A second later, a tick at `price` on `symbol` and an evaluation pass
+    // against a book around it
+    pub(crate) async fn tick(&mut self, symbol: &str, price: f64) {
+        self.tick_with_volume(symbol, price, 1_000.0).await;
+    }
+
+    pub(crate) async fn tick_with_volume(&mut self, symbol: &str, price: f64, volume: f64) {
+        self.clock.advance(Duration::from_secs(1));
+        let price = Price {
+            symbol: symbol.to_string(),
+            price,
+            timestamp: self.clock.now_secs(),
+            volume,
+        };
+        let book = self.books.synthetic_book(&price);
+        self.record(price).await;
+        self.evaluate(book).await;
+    }
+
+    // Records a tick without evaluating
+    pub(crate) async fn record(&self, price: Price) {
+        let symbol = Symbol::intern(&price.symbol);
+        self.bot.record_price(&symbol, price).await;
+    }
+
+    // One evaluation pass on the book's symbol, then the fills it caused
+    pub(crate) async fn evaluate(&mut self, book: OrderBook) {
+        let symbol = Symbol::intern(&book.symbol);
+        self.bot.record_book(&symbol, book.clone()).await;
+        let strategies = self.bot.strategies.read().await;
+        self.bot
+            .evaluate_symbol(&symbol, book, &strategies, Due::ALL)
+            .await;
+        drop(strategies);
+        self.settle().await;
+    }
+
+    // Books every fill so far; booking one can place orders that fill too
+    pub(crate) async fn settle(&mut self) {
+        while let Ok(fill) = self.fill_stream.try_recv() {
+            self.bot.on_fill(&fill).await;
+            self.fills.push(fill);
+        }
+    }
+
+    // Every event since the last call
+    pub(crate) fn events(&mut self) -> Vec<BotEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => events.push(event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => return events,
+            }
+        }
+    }
+
+    // Orders submitted since the last call to this or `events`
+    pub(crate) fn submitted(&mut self) -> Vec<Order> {
+        self.events()
+            .into_iter()
+            .filter_map(|event| match event {
+                BotEvent::OrderSubmitted(order) => Some(order),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// Serves `app` on a free local port until the test's runtime ends; returns
+// its base URL
+pub(crate) async fn serve(app: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}")
+}
+
+// A fresh directory under the system temp dir for a test's files
+pub(crate) fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("hft-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,57 @@
+use crate::OrderSide;
+use crate::fixtures;
+
+#[test]
+fn empty_book_has_no_prices() {
+    let empty = fixtures::book("SOL/USDT", &[], &[]);
+    assert_eq!(empty.best_bid(), None);
+    assert_eq!(empty.best_ask(), None);
+    assert_eq!(empty.mid_price(), None);
+    assert_eq!(empty.spread(), None);
+    assert_eq!(empty.spread_bps(), None);
+    assert_eq!(empty.total_depth(5), None);
+    assert_eq!(empty.imbalance(5), None);
+    assert_eq!(empty.microprice(), None);
+    assert_eq!(empty.depth_within_bps(OrderSide::Buy, 10.0), None);
+    assert!(!empty.is_crossed());
+
+    // One side only still has depth but no mid
+    let bids_only = fixtures::book("SOL/USDT", &[(99.0, 2.0)], &[]);
+    assert_eq!(bids_only.mid_price(), None);
+    assert_eq!(bids_only.total_depth(5), Some(2.0));
+    assert_eq!(bids_only.imbalance(5), Some(1.0));
+}
+
+#[test]
+fn single_level_book() {
+    let single = fixtures::book("SOL/USDT", &[(99.0, 3.0)], &[(101.0, 1.0)]);
+    assert_eq!(single.mid_price(), Some(100.0));
+    assert_eq!(single.spread(), Some(2.0));
+    assert_eq!(single.spread_bps(), Some(200.0));
+    assert_eq!(single.total_depth(10), Some(4.0));
+    assert_eq!(single.imbalance(1), Some(0.5));
+    // Leans toward the ask, the thinner side
+    assert_eq!(single.microprice(), Some(100.5));
+}
+
+#[test]
+fn crossed_book_has_a_negative_spread() {
+    let crossed = fixtures::book("SOL/USDT", &[(101.0, 1.0)], &[(100.0, 1.0)]);
+    assert!(crossed.is_crossed());
+    assert_eq!(crossed.spread(), Some(-1.0));
+    assert_eq!(crossed.mid_price(), Some(100.5));
+}
+
+#[test]
+fn levels_are_sorted_on_construction() {
+    let unsorted = fixtures::book(
+        "SOL/USDT",
+        &[(98.0, 1.0), (99.0, 2.0), (97.0, 3.0)],
+        &[(103.0, 1.0), (101.0, 2.0), (102.0, 3.0)],
+    );
+    unsorted.debug_validate();
+    assert_eq!(unsorted.best_bid(), Some((99.0, 2.0)));
+    assert_eq!(unsorted.best_ask(), Some((101.0, 2.0)));
+    assert_eq!(unsorted.bid_depth(2), 3.0);
+    assert_eq!(unsorted.ask_depth(2), 5.0);
+}
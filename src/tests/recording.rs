@@ -0,0 +1,75 @@
+use super::backtest::sample_config;
+use super::{PausedClock, scratch_dir};
+use crate::TradingBot;
+use crate::backtest::Backtester;
+use crate::events::BotEvent;
+use crate::recording::{
+    MarketRecorder, RecordingConfig, RecordingFeed, ReplayFeed, ReplaySpeed, load_recording,
+};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+fn signals(events: &mut broadcast::Receiver<BotEvent>) -> Vec<String> {
+    let mut signals = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let BotEvent::Signal { .. } = event {
+            signals.push(format!("{event:?}"));
+        }
+    }
+    signals
+}
+
+// A live run of the bundled capture: the live loop polls it (through a
+// recorder, as with [recording] enabled) and evaluates on every price. The
+// recording it leaves must replay to the signals it raised. The drawdown
+// halt is off: the live loop checks it on a timer, a replay on every book,
+// so on data this wild the two would halt at different points.
+#[tokio::test(start_paused = true)]
+async fn replaying_a_live_recording_repeats_its_signals() {
+    let capture = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/sample_recording");
+    let capture = load_recording(&capture).unwrap();
+    let mut config = sample_config();
+    config.symbols = vec!["SOL/USDT".to_string()];
+    config.schedule.evaluation_interval_ms = 0;
+    config.risk.max_drawdown_pct = 1.0;
+
+    let clock = Arc::new(PausedClock::new(capture[0].received_at_ms));
+    let source = Arc::new(ReplayFeed::new(capture, ReplaySpeed::AsFastAsPossible));
+    let directory = scratch_dir("recording");
+    let recorder = MarketRecorder::new(&RecordingConfig {
+        enabled: true,
+        directory: directory.clone(),
+        ..RecordingConfig::default()
+    })
+    .unwrap()
+    .with_clock(clock.clone());
+    let bot = TradingBot::from_config_with_clock(&config, clock)
+        .with_market_data(Arc::new(RecordingFeed::new(source.clone(), recorder)));
+    let mut events = bot.subscribe_events();
+
+    let (live, symbols) = (bot.clone(), config.symbols.clone());
+    let running = tokio::spawn(async move { live.start(symbols).await });
+    while !source.is_finished() {
+        tokio::time::sleep(Duration::from_millis(config.schedule.poll_interval_ms)).await;
+    }
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    bot.stop().await;
+    tokio::time::timeout(Duration::from_secs(60), running)
+        .await
+        .unwrap()
+        .unwrap();
+    let live = signals(&mut events);
+
+    let recording = load_recording(&directory).unwrap();
+    let replayed: Vec<String> = Backtester::new(&config)
+        .replay(&recording)
+        .await
+        .signals
+        .iter()
+        .map(|event| format!("{event:?}"))
+        .collect();
+    assert!(!live.is_empty());
+    assert_eq!(replayed, live);
+}
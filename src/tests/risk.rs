@@ -0,0 +1,539 @@
+use crate::account::{Account, AccountConfig};
+use crate::correlation::{CorrelationConfig, CorrelationTracker};
+use crate::currency::{CurrencyConfig, CurrencyConverter};
+use crate::fixtures::{self, fill, limit_order, market_order};
+use crate::marks::{MarkPriceCache, MarkSource};
+use crate::venue::VenueId;
+use crate::{Fill, OrderSide, PositionSide, RiskManager, RiskParams, RiskRejection, StopMode};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn limit_price_band_is_inclusive_of_its_edge() {
+    let risk = RiskManager::new(RiskParams {
+        max_price_deviation_pct: 0.05,
+        ..RiskParams::default()
+    });
+    let book = fixtures::order_book("SOL/USDT", 100.0, 10, 1);
+
+    let inside = limit_order("SOL/USDT", OrderSide::Buy, 1.0, 104.99);
+    assert_eq!(risk.validate_order(&inside, 100.0, &book).await, Ok(()));
+
+    let outside = limit_order("SOL/USDT", OrderSide::Buy, 1.0, 105.01);
+    assert!(matches!(
+        risk.validate_order(&outside, 100.0, &book).await,
+        Err(RiskRejection::PriceDeviation { .. })
+    ));
+    let below = limit_order("SOL/USDT", OrderSide::Sell, 1.0, 94.99);
+    assert!(matches!(
+        risk.validate_order(&below, 100.0, &book).await,
+        Err(RiskRejection::PriceDeviation { .. })
+    ));
+}
+
+#[tokio::test]
+async fn market_order_may_take_only_its_share_of_depth() {
+    let risk = RiskManager::new(RiskParams {
+        max_depth_fraction: 0.5,
+        ..RiskParams::default()
+    });
+    let book = fixtures::book("SOL/USDT", &[(99.9, 4.0)], &[(100.1, 4.0)]);
+    let half = market_order("SOL/USDT", OrderSide::Buy, 2.0);
+    assert_eq!(risk.validate_order(&half, 100.0, &book).await, Ok(()));
+    let more = market_order("SOL/USDT", OrderSide::Buy, 2.5);
+    assert!(matches!(
+        risk.validate_order(&more, 100.0, &book).await,
+        Err(RiskRejection::InsufficientDepth { .. })
+    ));
+}
+
+#[tokio::test]
+async fn deep_levels_behind_a_thin_touch_dont_count_as_depth() {
+    let risk = RiskManager::new(RiskParams {
+        max_depth_fraction: 0.5,
+        ..RiskParams::default()
+    });
+    // 1 SOL at the touch over 500 behind it
+    let book = fixtures::book(
+        "SOL/USDT",
+        &[(99.9, 1.0), (99.8, 500.0)],
+        &[(100.1, 1.0), (100.2, 500.0)],
+    );
+    let sweep = market_order("SOL/USDT", OrderSide::Sell, 10.0);
+    assert_eq!(
+        risk.validate_order(&sweep, 100.0, &book).await,
+        Err(RiskRejection::InsufficientDepth {
+            notional: 999.0,
+            top_of_book_notional: 99.9,
+            max_fraction: 0.5,
+        })
+    );
+    let within = market_order("SOL/USDT", OrderSide::Sell, 0.5);
+    assert_eq!(risk.validate_order(&within, 100.0, &book).await, Ok(()));
+}
+
+#[tokio::test]
+async fn first_order_over_the_notional_limits_is_rejected() {
+    let risk = RiskManager::new(RiskParams {
+        max_position_notional: 1_000.0,
+        max_order_notional: 1_500.0,
+        ..RiskParams::default()
+    });
+    let book = fixtures::order_book("SOL/USDT", 100.0, 10, 1);
+
+    // No position in the symbol yet, so only the order itself counts
+    let over_position = market_order("SOL/USDT", OrderSide::Buy, 12.0);
+    assert!(matches!(
+        risk.validate_order(&over_position, 100.0, &book).await,
+        Err(RiskRejection::PositionNotional { .. })
+    ));
+    let over_order = market_order("SOL/USDT", OrderSide::Buy, 16.0);
+    assert!(matches!(
+        risk.validate_order(&over_order, 100.0, &book).await,
+        Err(RiskRejection::OrderNotional { .. })
+    ));
+    let within = market_order("SOL/USDT", OrderSide::Buy, 9.0);
+    assert_eq!(risk.validate_order(&within, 100.0, &book).await, Ok(()));
+}
+
+#[tokio::test]
+async fn third_position_is_rejected_for_gross_exposure() {
+    let risk = RiskManager::new(RiskParams {
+        max_gross_exposure: 2_500.0,
+        ..RiskParams::default()
+    });
+    risk.apply_fill(&fill("SOL/USDT", OrderSide::Buy, 10.0, 100.0))
+        .await;
+    risk.apply_fill(&fill("BTC/USDT", OrderSide::Sell, 0.03, 40_000.0))
+        .await;
+    let book = fixtures::order_book("ETH/USDT", 100.0, 10, 1);
+
+    let third = market_order("ETH/USDT", OrderSide::Buy, 5.0);
+    match risk.validate_order(&third, 100.0, &book).await {
+        Err(RiskRejection::GrossExposure { exposure, limit }) => {
+            assert!((exposure - 2_700.0).abs() < 1e-6);
+            assert_eq!(limit, 2_500.0);
+        }
+        other => panic!("expected a gross exposure rejection, got {other:?}"),
+    }
+    let smaller = market_order("ETH/USDT", OrderSide::Buy, 2.0);
+    assert_eq!(risk.validate_order(&smaller, 100.0, &book).await, Ok(()));
+}
+
+#[tokio::test]
+async fn net_exposure_counts_shorts_against_longs() {
+    let risk = RiskManager::new(RiskParams {
+        max_net_exposure: 1_500.0,
+        ..RiskParams::default()
+    });
+    risk.apply_fill(&fill("SOL/USDT", OrderSide::Buy, 10.0, 100.0))
+        .await;
+    let book = fixtures::order_book("ETH/USDT", 100.0, 10, 1);
+
+    let long = market_order("ETH/USDT", OrderSide::Buy, 6.0);
+    assert!(matches!(
+        risk.validate_order(&long, 100.0, &book).await,
+        Err(RiskRejection::NetExposure { .. })
+    ));
+}
+
+#[tokio::test]
+async fn each_symbol_is_held_to_its_own_limits() {
+    let sol = RiskParams {
+        max_position_size: 200.0,
+        ..RiskParams::default()
+    };
+    let btc = RiskParams {
+        max_position_size: 0.5,
+        ..RiskParams::default()
+    };
+    let risk = RiskManager::with_symbol_params(
+        RiskParams::default(),
+        HashMap::from([("SOL/USDT".to_string(), sol), ("BTC/USDT".to_string(), btc)]),
+    );
+    let sol_book = fixtures::order_book("SOL/USDT", 10.0, 10, 1);
+    let btc_book = fixtures::order_book("BTC/USDT", 10_000.0, 10, 1);
+
+    let sol_ok = market_order("SOL/USDT", OrderSide::Buy, 150.0);
+    assert_eq!(risk.validate_order(&sol_ok, 10.0, &sol_book).await, Ok(()));
+    let sol_over = market_order("SOL/USDT", OrderSide::Buy, 250.0);
+    assert!(matches!(
+        risk.validate_order(&sol_over, 10.0, &sol_book).await,
+        Err(RiskRejection::PositionLimit { limit: 200.0, .. })
+    ));
+
+    let btc_ok = market_order("BTC/USDT", OrderSide::Buy, 0.4);
+    assert_eq!(
+        risk.validate_order(&btc_ok, 10_000.0, &btc_book).await,
+        Ok(())
+    );
+    let btc_over = market_order("BTC/USDT", OrderSide::Buy, 0.6);
+    assert!(matches!(
+        risk.validate_order(&btc_over, 10_000.0, &btc_book).await,
+        Err(RiskRejection::PositionLimit { limit: 0.5, .. })
+    ));
+
+    // Raised at runtime, and cleared back to the defaults
+    let raised = RiskParams {
+        max_position_size: 1.0,
+        max_loss_per_trade: 200.0,
+        ..RiskParams::default()
+    };
+    risk.set_symbol_params("BTC/USDT", raised).await;
+    assert_eq!(
+        risk.validate_order(&btc_over, 10_000.0, &btc_book).await,
+        Ok(())
+    );
+    risk.clear_symbol_params("SOL/USDT").await;
+    assert_eq!(risk.params_for("SOL/USDT").await.max_position_size, 1_000.0);
+}
+
+#[tokio::test]
+async fn gross_exposure_counts_every_open_position() {
+    let held = fixtures::positions(100, 3);
+    let gross: f64 = held.iter().map(|p| p.quantity.abs() * p.avg_price).sum();
+    let risk = RiskManager::new(RiskParams {
+        max_gross_exposure: gross + 150.0,
+        max_net_exposure: f64::MAX,
+        ..RiskParams::default()
+    });
+    let cash = HashMap::from([("USDT".to_string(), 1_000_000.0)]);
+    risk.restore(held, cash, 0.0, 0).await;
+    let book = fixtures::order_book("SOL/USDT", 100.0, 10, 1);
+
+    // Unmarked positions count at cost, so 100 more of exposure fits
+    let fits = market_order("SOL/USDT", OrderSide::Buy, 1.0);
+    assert_eq!(risk.validate_order(&fits, 100.0, &book).await, Ok(()));
+    let over = market_order("SOL/USDT", OrderSide::Buy, 2.0);
+    match risk.validate_order(&over, 100.0, &book).await {
+        Err(RiskRejection::GrossExposure { exposure, .. }) => {
+            assert!((exposure - (gross + 200.0)).abs() < 1e-6)
+        }
+        other => panic!("expected a gross exposure rejection, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn the_same_symbol_on_two_venues_is_two_positions() {
+    let risk = RiskManager::new(RiskParams::default());
+    let binance = VenueId::new("binance");
+    risk.apply_fill(&fill("SOL/USDT", OrderSide::Buy, 2.0, 100.0))
+        .await;
+    risk.apply_fill(&Fill {
+        venue: binance.clone(),
+        ..fill("SOL/USDT", OrderSide::Sell, 0.5, 101.0)
+    })
+    .await;
+
+    let paper = risk
+        .position(&VenueId::paper(), "SOL/USDT", PositionSide::Both)
+        .await
+        .unwrap();
+    assert_eq!((paper.quantity, paper.avg_price), (2.0, 100.0));
+    let short = risk
+        .position(&binance, "SOL/USDT", PositionSide::Both)
+        .await
+        .unwrap();
+    assert_eq!((short.quantity, short.avg_price), (-0.5, 101.0));
+    assert_eq!(risk.positions().await.len(), 2);
+}
+
+#[tokio::test]
+async fn the_loss_check_uses_the_same_atr_stop_as_the_position() {
+    let risk = RiskManager::new(RiskParams {
+        stop_mode: StopMode::AtrMultiple { multiple: 2.0 },
+        max_loss_per_trade: 50.0,
+        ..RiskParams::default()
+    });
+    risk.update_atr("SOL/USDT", 1.0).await;
+    risk.update_atr("BTC/USDT", 5.0).await;
+    assert_eq!(risk.stop_distance("SOL/USDT", 100.0).await, 2.0);
+    assert_eq!(risk.stop_distance("BTC/USDT", 100.0).await, 10.0);
+    // No ATR yet: stop_loss_pct of the price
+    assert_eq!(risk.stop_distance("ETH/USDT", 100.0).await, 2.0);
+
+    // 10 at risk 2 apiece passes, 10 at risk 10 apiece doesn't
+    let sol = market_order("SOL/USDT", OrderSide::Buy, 10.0);
+    let book = fixtures::order_book("SOL/USDT", 100.0, 10, 1);
+    assert_eq!(risk.validate_order(&sol, 100.0, &book).await, Ok(()));
+    let btc = market_order("BTC/USDT", OrderSide::Buy, 10.0);
+    let book = fixtures::order_book("BTC/USDT", 100.0, 10, 1);
+    assert!(matches!(
+        risk.validate_order(&btc, 100.0, &book).await,
+        Err(RiskRejection::PotentialLoss { .. })
+    ));
+}
+
+#[tokio::test]
+async fn unrealized_pnl_follows_the_mark_cache_without_fills() {
+    let tick = |symbol: &str, price: f64| crate::Price {
+        symbol: symbol.to_string(),
+        price,
+        timestamp: 0,
+        volume: 1.0,
+    };
+    let unrealized = |positions: Vec<crate::Position>, symbol: &str| {
+        positions
+            .into_iter()
+            .find(|p| p.symbol == symbol)
+            .unwrap()
+            .unrealized_pnl
+    };
+
+    let marks = Arc::new(MarkPriceCache::new(MarkSource::LastTrade));
+    let risk = RiskManager::new(RiskParams::default()).with_marks(marks.clone());
+    risk.apply_fill(&fill("SOL/USDT", OrderSide::Buy, 10.0, 100.0))
+        .await;
+    risk.apply_fill(&fill("BTC/USDT", OrderSide::Sell, 0.03, 40_000.0))
+        .await;
+    marks.record_price(&tick("SOL/USDT", 105.0));
+    risk.update_unrealized_pnl().await;
+    assert_eq!(unrealized(risk.positions().await, "SOL/USDT"), 50.0);
+    // No mark yet: left as it was
+    assert_eq!(unrealized(risk.positions().await, "BTC/USDT"), 0.0);
+    marks.record_price(&tick("SOL/USDT", 98.0));
+    marks.record_price(&tick("BTC/USDT", 39_000.0));
+    risk.update_unrealized_pnl().await;
+    assert_eq!(unrealized(risk.positions().await, "SOL/USDT"), -20.0);
+    assert_eq!(unrealized(risk.positions().await, "BTC/USDT"), 30.0);
+
+    let marks = Arc::new(MarkPriceCache::new(MarkSource::Mid));
+    let risk = RiskManager::new(RiskParams::default()).with_marks(marks.clone());
+    risk.apply_fill(&fill("SOL/USDT", OrderSide::Buy, 10.0, 100.0))
+        .await;
+    marks.record_price(&tick("SOL/USDT", 101.0));
+    risk.update_unrealized_pnl().await;
+    assert_eq!(unrealized(risk.positions().await, "SOL/USDT"), 10.0);
+    // Once there's a book, its mid wins over trades
+    marks.record_book(&crate::OrderBook::new(
+        "SOL/USDT".to_string(),
+        vec![(98.9, 1.0)],
+        vec![(99.1, 1.0)],
+        0,
+    ));
+    marks.record_price(&tick("SOL/USDT", 120.0));
+    risk.update_unrealized_pnl().await;
+    assert!((unrealized(risk.positions().await, "SOL/USDT") + 10.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn concentration_passes_at_the_limit_and_always_lets_reduce_only_out() {
+    let sol = RiskParams {
+        max_symbol_concentration_pct: Some(0.2),
+        max_loss_per_trade: 1_000.0,
+        ..RiskParams::default()
+    };
+    let risk = RiskManager::with_symbol_params(
+        RiskParams {
+            max_symbol_concentration_pct: Some(0.5),
+            ..RiskParams::default()
+        },
+        HashMap::from([("SOL/USDT".to_string(), sol)]),
+    );
+    let book = fixtures::order_book("ETH/USDT", 100.0, 10, 1);
+
+    // Half of the 10,000 of equity is exactly the limit; a lot more isn't
+    let at_limit = limit_order("ETH/USDT", OrderSide::Buy, 50.0, 100.0);
+    assert_eq!(risk.validate_order(&at_limit, 100.0, &book).await, Ok(()));
+    let over = limit_order("ETH/USDT", OrderSide::Buy, 50.01, 100.0);
+    match risk.validate_order(&over, 100.0, &book).await {
+        Err(RiskRejection::Concentration {
+            concentration,
+            limit,
+            ..
+        }) => {
+            assert!((concentration - 0.5001).abs() < 1e-9);
+            assert_eq!(limit, 0.5);
+        }
+        other => panic!("expected a concentration rejection, got {other:?}"),
+    }
+
+    // Half of equity in SOL is over its own 20%: nothing more goes in,
+    // but a reduce-only order comes out whatever its size
+    risk.apply_fill(&fill("SOL/USDT", OrderSide::Buy, 50.0, 100.0))
+        .await;
+    let book = fixtures::order_book("SOL/USDT", 100.0, 10, 1);
+    let more = limit_order("SOL/USDT", OrderSide::Buy, 1.0, 100.0);
+    assert!(matches!(
+        risk.validate_order(&more, 100.0, &book).await,
+        Err(RiskRejection::Concentration { limit: 0.2, .. })
+    ));
+    let mut flips = limit_order("SOL/USDT", OrderSide::Sell, 80.0, 100.0);
+    assert!(matches!(
+        risk.validate_order(&flips, 100.0, &book).await,
+        Err(RiskRejection::Concentration { .. })
+    ));
+    flips.reduce_only = true;
+    assert_eq!(risk.validate_order(&flips, 100.0, &book).await, Ok(()));
+}
+
+#[tokio::test]
+async fn the_position_limit_holds_on_the_short_side_too() {
+    let risk = RiskManager::new(RiskParams {
+        max_position_size: 10.0,
+        ..RiskParams::default()
+    });
+    let book = fixtures::order_book("SOL/USDT", 100.0, 10, 5);
+    let short = limit_order("SOL/USDT", OrderSide::Sell, 10.0, 100.0);
+    assert_eq!(risk.validate_order(&short, 100.0, &book).await, Ok(()));
+    let over = limit_order("SOL/USDT", OrderSide::Sell, 10.5, 100.0);
+    assert_eq!(
+        risk.validate_order(&over, 100.0, &book).await,
+        Err(RiskRejection::PositionLimit {
+            resulting_quantity: -10.5,
+            limit: 10.0,
+        })
+    );
+}
+
+#[tokio::test]
+async fn the_margin_check_rejects_an_over_leveraged_perp_order() {
+    let account = AccountConfig::default();
+    let risk = RiskManager::new(RiskParams {
+        max_order_notional: f64::MAX,
+        max_position_size: f64::MAX,
+        max_loss_per_trade: f64::MAX,
+        ..RiskParams::default()
+    })
+    .with_account(Account::new(&account).with_perps(["SOL/USDT".to_string()]));
+    let book = fixtures::order_book("SOL/USDT", 100.0, 10, 1);
+
+    // 5x on 10,000 with 80% of it usable carries 40,000 of notional
+    let full = limit_order("SOL/USDT", OrderSide::Buy, 400.0, 100.0);
+    assert_eq!(risk.validate_order(&full, 100.0, &book).await, Ok(()));
+    let over = limit_order("SOL/USDT", OrderSide::Buy, 401.0, 100.0);
+    match risk.validate_order(&over, 100.0, &book).await {
+        Err(RiskRejection::InsufficientMargin {
+            required,
+            available,
+        }) => {
+            assert!((required - 8_020.0).abs() < 1e-9);
+            assert!((available - 8_000.0).abs() < 1e-9);
+        }
+        other => panic!("expected a margin rejection, got {other:?}"),
+    }
+
+    // Held, it would be liquidated 19.5% below its entry
+    risk.apply_fill(&fill("SOL/USDT", OrderSide::Buy, 100.0, 100.0))
+        .await;
+    let position = risk
+        .position(&VenueId::paper(), "SOL/USDT", PositionSide::Both)
+        .await
+        .unwrap();
+    let liquidation = risk.liquidation_price(&position).await.unwrap();
+    assert!((liquidation - 80.5).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn correlated_longs_are_held_to_one_exposure_limit() {
+    let correlations = Arc::new(CorrelationTracker::new(CorrelationConfig {
+        enabled: true,
+        sample_secs: 1,
+        window: 100,
+        metrics: false,
+    }));
+    // JTO moves exactly with SOL; ETH walks its own way
+    let sol = fixtures::price_history("SOL/USDT", 102, 1);
+    let eth = fixtures::price_history("ETH/USDT", 102, 2);
+    for (sol, eth) in sol.iter().zip(&eth) {
+        let jto = crate::Price {
+            symbol: "JTO/USDT".to_string(),
+            ..sol.clone()
+        };
+        for tick in [sol, &jto, eth] {
+            correlations.record_price(tick);
+        }
+    }
+    assert!(correlations.correlation("SOL/USDT", "JTO/USDT").unwrap() > 0.99);
+    assert!(correlations.correlation("SOL/USDT", "ETH/USDT").unwrap() < 0.8);
+
+    let risk = RiskManager::new(RiskParams {
+        max_correlated_exposure: Some(2_000.0),
+        ..RiskParams::default()
+    })
+    .with_correlations(correlations);
+    risk.apply_fill(&fill("SOL/USDT", OrderSide::Buy, 15.0, 100.0))
+        .await;
+
+    // 1,500 of SOL and 600 of JTO is one 2,100 bet
+    let book = fixtures::order_book("JTO/USDT", 100.0, 10, 1);
+    let jto = limit_order("JTO/USDT", OrderSide::Buy, 6.0, 100.0);
+    match risk.validate_order(&jto, 100.0, &book).await {
+        Err(RiskRejection::CorrelatedExposure {
+            correlated,
+            exposure,
+            limit,
+            ..
+        }) => {
+            assert_eq!(correlated, ["SOL/USDT"]);
+            assert!((exposure - 2_100.0).abs() < 1e-9);
+            assert_eq!(limit, 2_000.0);
+        }
+        other => panic!("expected a correlated exposure rejection, got {other:?}"),
+    }
+    // Shorting JTO against the SOL long shrinks the bet
+    let hedge = limit_order("JTO/USDT", OrderSide::Sell, 6.0, 100.0);
+    assert_eq!(risk.validate_order(&hedge, 100.0, &book).await, Ok(()));
+    // The same 600 of ETH stands on its own
+    let book = fixtures::order_book("ETH/USDT", 100.0, 10, 1);
+    let eth = limit_order("ETH/USDT", OrderSide::Buy, 6.0, 100.0);
+    assert_eq!(risk.validate_order(&eth, 100.0, &book).await, Ok(()));
+}
+
+#[tokio::test]
+async fn a_btc_quoted_position_is_exposure_at_the_btc_rate() {
+    let tick = |symbol: &str, price: f64| crate::Price {
+        symbol: symbol.to_string(),
+        price,
+        timestamp: 0,
+        volume: 1.0,
+    };
+    let symbols = ["ETH/BTC".to_string(), "BTC/USDT".to_string()];
+    let currency = CurrencyConverter::new(&CurrencyConfig::default()).with_symbols(&symbols);
+    let marks = Arc::new(MarkPriceCache::new(MarkSource::LastTrade));
+    let risk = RiskManager::new(RiskParams {
+        max_order_notional: f64::MAX,
+        max_position_size: f64::MAX,
+        max_position_notional: f64::MAX,
+        max_loss_per_trade: f64::MAX,
+        max_net_exposure: f64::MAX,
+        max_gross_exposure: 27_000.0,
+        ..RiskParams::default()
+    })
+    .with_account(Account::new(&AccountConfig {
+        balances: HashMap::from([("USDT".to_string(), 10_000.0), ("BTC".to_string(), 1.0)]),
+        ..AccountConfig::default()
+    }))
+    .with_marks(marks.clone())
+    .with_currency(Arc::new(currency));
+    risk.apply_fill(&fill("ETH/BTC", OrderSide::Buy, 10.0, 0.05))
+        .await;
+    marks.record_price(&tick("ETH/BTC", 0.05));
+    let book = fixtures::order_book("ETH/BTC", 0.05, 10, 1);
+    // Another 0.5 ETH is 0.025 BTC
+    let more = limit_order("ETH/BTC", OrderSide::Buy, 0.5, 0.05);
+
+    // 0.5 BTC of ETH is 25,000, 30,000 then 20,000 USDT as BTC moves
+    for (btc, exposure, fits) in [
+        (50_000.0, 25_000.0, true),
+        (60_000.0, 30_000.0, false),
+        (40_000.0, 20_000.0, true),
+    ] {
+        marks.record_price(&tick("BTC/USDT", btc));
+        let point = risk.equity_point().await;
+        assert!((point.gross_exposure - exposure).abs() < 1e-6, "{point:?}");
+        let checked = risk.validate_order(&more, 0.05, &book).await;
+        if fits {
+            assert_eq!(checked, Ok(()), "at {btc}");
+        } else {
+            match checked {
+                Err(RiskRejection::GrossExposure { exposure, limit }) => {
+                    assert!((exposure - 31_500.0).abs() < 1e-6);
+                    assert_eq!(limit, 27_000.0);
+                }
+                other => panic!("expected a gross exposure rejection, got {other:?}"),
+            }
+        }
+    }
+    assert!(risk.currency().unconverted().is_empty());
+}
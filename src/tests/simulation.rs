@@ -0,0 +1,66 @@
+use super::backtest::sample_config;
+use crate::simulation::{SimulationResult, Simulator};
+use crate::{Fill, Price};
+
+// Five minutes of the example config's market on `seed`, cheap and wild
+// enough that the strategies trade
+async fn simulate(seed: u64) -> SimulationResult {
+    let mut config = sample_config();
+    config.simulation.seed = Some(seed);
+    config.simulation.start_price = 10.0;
+    config.simulation.volatility = 50.0;
+    config.simulation.duration_secs = 300;
+    Simulator::new(&config).run().await
+}
+
+fn prices(result: &SimulationResult) -> Vec<(String, u64, f64, f64)> {
+    result
+        .prices
+        .iter()
+        .map(|price: &Price| {
+            (
+                price.symbol.clone(),
+                price.timestamp,
+                price.price,
+                price.volume,
+            )
+        })
+        .collect()
+}
+
+// Fills without their random order ids
+fn fills(result: &SimulationResult) -> Vec<String> {
+    result
+        .result
+        .fills
+        .iter()
+        .map(|fill| {
+            let fill = Fill {
+                order_id: String::new(),
+                ..fill.clone()
+            };
+            format!("{fill:?}")
+        })
+        .collect()
+}
+
+fn signals(result: &SimulationResult) -> Vec<String> {
+    let signals = &result.result.signals;
+    signals.iter().map(|event| format!("{event:?}")).collect()
+}
+
+#[tokio::test]
+async fn a_seed_repeats_its_run_exactly() {
+    let (first, second) = (simulate(7).await, simulate(7).await);
+    assert!(!first.result.fills.is_empty());
+    assert_eq!(prices(&first), prices(&second));
+    assert_eq!(signals(&first), signals(&second));
+    assert_eq!(fills(&first), fills(&second));
+    assert_eq!(first.result.final_equity, second.result.final_equity);
+    assert_eq!(first.result.realized_pnl, second.result.realized_pnl);
+    assert_eq!(first.result.equity_curve, second.result.equity_curve);
+
+    let other = simulate(8).await;
+    assert_eq!(other.prices.len(), first.prices.len());
+    assert_ne!(prices(&other), prices(&first));
+}
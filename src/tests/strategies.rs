@@ -0,0 +1,134 @@
+use crate::fixtures;
+use crate::indicators::IndicatorHub;
+use crate::symbols::Symbol;
+use crate::{MeanReversionStrategy, MomentumStrategy, OrderSide, Price, TradingStrategy};
+use std::sync::Arc;
+
+// `prices` a second apart, every tick trading `volume`
+fn series(prices: &[f64], volume: f64) -> Vec<Price> {
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| Price {
+            symbol: "SOL/USDT".to_string(),
+            price,
+            timestamp: i as u64,
+            volume,
+        })
+        .collect()
+}
+
+// Runs `strategy` over a fixture walk both ways and checks every tick's
+// signal agrees; returns how many ticks signalled
+fn assert_incremental_matches_slice(strategy: &dyn TradingStrategy) -> usize {
+    let history = fixtures::price_history("SOL/USDT", 1_000, 11);
+    let book = fixtures::order_book("SOL/USDT", 100.0, 5, 11);
+    let sol = Symbol::intern("SOL/USDT");
+    let mut hub = IndicatorHub::new();
+    for window in strategy.indicator_windows() {
+        hub.register_window(window);
+    }
+    let mut signalled = 0;
+    for i in 0..history.len() {
+        let tick = &history[i];
+        hub.update(&sol, tick.price, tick.volume);
+        let prices = &history[..=i];
+        let indicators = hub.get(sol.id()).unwrap();
+        let slice = strategy.analyze(prices, &book);
+        let incremental = strategy.analyze_incremental(prices, indicators, &book);
+        match (slice, incremental) {
+            (None, None) => {}
+            (Some(a), Some(b)) => {
+                assert_eq!(a.action, b.action, "tick {i}");
+                assert!((a.confidence - b.confidence).abs() < 1e-9, "tick {i}");
+                assert!((a.target_price - b.target_price).abs() < 1e-9, "tick {i}");
+                signalled += 1;
+            }
+            (a, b) => panic!("tick {i}: slice {a:?}, incremental {b:?}"),
+        }
+    }
+    signalled
+}
+
+#[test]
+fn momentum_reads_the_same_signals_incrementally() {
+    let strategy = MomentumStrategy::new(10, 0.002).unwrap();
+    assert!(assert_incremental_matches_slice(&strategy) > 0);
+}
+
+#[test]
+fn mean_reversion_reads_the_same_signals_incrementally() {
+    let strategy = MeanReversionStrategy::new(20, 0.002);
+    assert!(assert_incremental_matches_slice(&strategy) > 0);
+}
+
+#[test]
+fn momentum_needs_volume_to_signal() {
+    let strategy = MomentumStrategy::with_params(5, 0.02, 500.0, 10.0).unwrap();
+    let book = fixtures::order_book("SOL/USDT", 110.0, 5, 1);
+    let rising = [100.0, 102.0, 104.0, 106.0, 108.0, 110.0];
+
+    assert!(strategy.analyze(&series(&rising, 100.0), &book).is_none());
+
+    let signal = strategy.analyze(&series(&rising, 1_000.0), &book).unwrap();
+    assert_eq!(signal.action, OrderSide::Buy);
+    assert_eq!(signal.quantity, 10.0);
+    assert!(signal.confidence.is_finite());
+}
+
+#[test]
+fn momentum_ignores_a_zero_reference_price() {
+    let strategy = MomentumStrategy::with_params(3, 0.02, 0.0, 10.0).unwrap();
+    let book = fixtures::order_book("SOL/USDT", 1.0, 5, 1);
+    assert!(
+        strategy
+            .analyze(&series(&[0.0, 0.5, 1.0], 1_000.0), &book)
+            .is_none()
+    );
+}
+
+#[test]
+fn momentum_rejects_a_lookback_under_two() {
+    assert!(MomentumStrategy::new(1, 0.02).is_err());
+    assert!(MomentumStrategy::with_params(2, 0.02, 0.0, 1.0).is_ok());
+}
+
+#[test]
+fn only_the_newest_window_is_read() {
+    let strategies: [Box<dyn TradingStrategy>; 2] = [
+        Box::new(MomentumStrategy::new(10, 0.002).unwrap()),
+        Box::new(MeanReversionStrategy::new(20, 0.002)),
+    ];
+    let history = fixtures::price_history("SOL/USDT", 1_000, 3);
+    let book = fixtures::order_book("SOL/USDT", 100.0, 5, 3);
+    let mut signalled = 0;
+    for strategy in &strategies {
+        for end in (100..=1_000).step_by(50) {
+            let whole = strategy.analyze(&history[..end], &book);
+            signalled += usize::from(whole.is_some());
+            let tail = strategy.analyze(&history[end - 20..end], &book);
+            assert_eq!(format!("{whole:?}"), format!("{tail:?}"), "{end}");
+        }
+    }
+    assert!(signalled > 0);
+}
+
+#[test]
+fn incremental_signals_share_the_symbol_name() {
+    let strategy = MomentumStrategy::with_params(5, 0.02, 500.0, 10.0).unwrap();
+    let mut hub = IndicatorHub::new();
+    for window in strategy.indicator_windows() {
+        hub.register_window(window);
+    }
+    let prices = series(&[100.0, 102.0, 104.0, 106.0, 108.0, 110.0], 1_000.0);
+    let sol = Symbol::intern("SOL/USDT");
+    for tick in &prices {
+        hub.update(&sol, tick.price, tick.volume);
+    }
+    let indicators = hub.get(sol.id()).unwrap();
+    let book = fixtures::order_book("SOL/USDT", 110.0, 5, 1);
+    let signal = strategy
+        .analyze_incremental(&prices, indicators, &book)
+        .unwrap();
+    assert!(Arc::ptr_eq(&signal.symbol, indicators.symbol()));
+}
@@ -0,0 +1,299 @@
+use crate::Price;
+use crate::indicators::RealizedVolatility;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+// What happens to a tick the filter catches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BadTickAction {
+    // Dropped before it reaches prices, indicators or candles
+    #[default]
+    Reject,
+    // Counted and quarantined, but let through
+    Flag,
+}
+
+// Screens incoming prices for bad prints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TickFilterConfig {
+    pub enabled: bool,
+    pub action: BadTickAction,
+    // A tick this far from the last accepted price is bad, as a fraction
+    pub max_deviation_pct: Option<f64>,
+    // Or this many standard deviations of recent tick returns away
+    pub max_sigma: Option<f64>,
+    // Accepted tick returns the standard deviation is taken over
+    pub volatility_window: usize,
+    // Bad ticks that agree with each other are a real move: once this many
+    // follow the first, the latest is accepted
+    pub confirmations: usize,
+    // Bad ticks are appended here as JSON lines
+    pub quarantine_path: Option<PathBuf>,
+}
+
+impl Default for TickFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            action: BadTickAction::Reject,
+            max_deviation_pct: Some(0.10),
+            max_sigma: Some(10.0),
+            volatility_window: 100,
+            confirmations: 3,
+            quarantine_path: None,
+        }
+    }
+}
+
+impl TickFilterConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.max_deviation_pct.is_none() && self.max_sigma.is_none() {
+            return Err("Tick filter needs max_deviation_pct or max_sigma".to_string());
+        }
+        if self
+            .max_deviation_pct
+            .is_some_and(|pct| pct.is_nan() || pct <= 0.0)
+        {
+            return Err("Tick filter max_deviation_pct must be positive".to_string());
+        }
+        if self.max_sigma.is_some_and(|k| k.is_nan() || k <= 0.0) {
+            return Err("Tick filter max_sigma must be positive".to_string());
+        }
+        if self.volatility_window < 2 {
+            return Err("Tick filter volatility_window must be at least 2 returns".to_string());
+        }
+        if self.confirmations == 0 {
+            return Err("Tick filter confirmations must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+// The filter's ruling on a tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Screening {
+    Accepted,
+    // A bad tick; passed on only when flagging
+    Bad { deviation_pct: f64 },
+    // The confirmation that makes a run of bad ticks a real move
+    Confirmed,
+}
+
+#[derive(Debug, Clone)]
+struct SymbolFilter {
+    last_accepted: f64,
+    volatility: RealizedVolatility,
+    // Consecutive bad ticks that agree with each other
+    pending: usize,
+    pending_price: f64,
+}
+
+// One quarantine log line
+#[derive(Serialize)]
+struct QuarantinedTick<'a> {
+    #[serde(flatten)]
+    price: &'a Price,
+    last_accepted: f64,
+    deviation_pct: f64,
+}
+
+/// Catches ticks far from the symbol's last accepted price, in absolute
+/// terms or against its recent volatility. One print out of line is a bad
+/// tick; a run of them agreeing with each other is the market moving, and
+/// is let through after `confirmations` ticks.
+#[derive(Debug)]
+pub struct TickFilter {
+    config: TickFilterConfig,
+    symbols: HashMap<String, SymbolFilter>,
+    quarantine: Option<File>,
+}
+
+impl TickFilter {
+    pub fn new(config: &TickFilterConfig) -> Self {
+        Self {
+            config: config.clone(),
+            symbols: HashMap::new(),
+            quarantine: None,
+        }
+    }
+
+    pub fn action(&self) -> BadTickAction {
+        self.config.action
+    }
+
+    pub fn screen(&mut self, price: &Price) -> Screening {
+        if !self.config.enabled {
+            return Screening::Accepted;
+        }
+        let config = &self.config;
+        let Some(filter) = self.symbols.get_mut(&price.symbol) else {
+            let mut volatility = RealizedVolatility::new(config.volatility_window, None);
+            volatility.update(price.price);
+            self.symbols.insert(
+                price.symbol.clone(),
+                SymbolFilter {
+                    last_accepted: price.price,
+                    volatility,
+                    pending: 0,
+                    pending_price: price.price,
+                },
+            );
+            return Screening::Accepted;
+        };
+
+        let out_of_line = |reference: f64, volatility: Option<f64>| {
+            let deviation_pct = price.price / reference - 1.0;
+            let too_far = config
+                .max_deviation_pct
+                .is_some_and(|max| deviation_pct.abs() > max);
+            let too_many_sigmas = config.max_sigma.zip(volatility).is_some_and(|(k, sigma)| {
+                sigma > 0.0 && (price.price / reference).ln().abs() > k * sigma
+            });
+            (too_far || too_many_sigmas).then_some(deviation_pct)
+        };
+        let volatility = filter.volatility.value();
+        let Some(deviation_pct) = out_of_line(filter.last_accepted, volatility) else {
+            filter.last_accepted = price.price;
+            filter.volatility.update(price.price);
+            filter.pending = 0;
+            return Screening::Accepted;
+        };
+
+        // A bad tick in line with the last one continues its run
+        if filter.pending > 0 && out_of_line(filter.pending_price, volatility).is_none() {
+            filter.pending += 1;
+        } else {
+            filter.pending = 1;
+        }
+        filter.pending_price = price.price;
+        if filter.pending > config.confirmations {
+            // A new level, not volatility: the jump isn't counted as a return
+            filter.last_accepted = price.price;
+            filter.volatility.rebase(price.price);
+            filter.pending = 0;
+            return Screening::Confirmed;
+        }
+        let last_accepted = filter.last_accepted;
+        self.quarantine(price, last_accepted, deviation_pct);
+        Screening::Bad { deviation_pct }
+    }
+
+    fn quarantine(&mut self, price: &Price, last_accepted: f64, deviation_pct: f64) {
+        let Some(path) = &self.config.quarantine_path else {
+            return;
+        };
+        if self.quarantine.is_none() {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => self.quarantine = Some(file),
+                Err(e) => {
+                    println!("Failed to open quarantine log {}: {}", path.display(), e);
+                    return;
+                }
+            }
+        }
+        let line = serde_json::to_string(&QuarantinedTick {
+            price,
+            last_accepted,
+            deviation_pct,
+        })
+        .expect("a price serializes");
+        if let Some(file) = &mut self.quarantine
+            && let Err(e) = writeln!(file, "{}", line)
+        {
+            println!("Failed to write quarantine log: {}", e);
+        }
+    }
+
+    pub fn remove(&mut self, symbol: &str) {
+        self.symbols.remove(symbol);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_dir;
+
+    fn tick(price: f64) -> Price {
+        Price {
+            symbol: "SOL/USDT".to_string(),
+            price,
+            timestamp: 1_704_067_200,
+            volume: 1.0,
+        }
+    }
+
+    fn filter(config: TickFilterConfig) -> TickFilter {
+        TickFilter::new(&TickFilterConfig {
+            enabled: true,
+            ..config
+        })
+    }
+
+    #[test]
+    fn a_single_spike_is_rejected_and_quarantined() {
+        let path = scratch_dir("tick-filter").join("quarantine.jsonl");
+        let mut filter = filter(TickFilterConfig {
+            quarantine_path: Some(path.clone()),
+            ..TickFilterConfig::default()
+        });
+        let screened: Vec<Screening> = [100.0, 100.1, 150.0, 100.2]
+            .into_iter()
+            .map(|price| filter.screen(&tick(price)))
+            .collect();
+        assert_eq!(screened[..2], [Screening::Accepted, Screening::Accepted]);
+        assert!(matches!(
+            screened[2],
+            Screening::Bad { deviation_pct } if (deviation_pct - 0.4985).abs() < 1e-3
+        ));
+        assert_eq!(screened[3], Screening::Accepted);
+
+        let quarantined = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = quarantined.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(
+            (line["price"].as_f64(), line["last_accepted"].as_f64()),
+            (Some(150.0), Some(100.1))
+        );
+    }
+
+    #[test]
+    fn a_sustained_move_is_accepted_after_its_confirmations() {
+        let mut filter = filter(TickFilterConfig {
+            confirmations: 3,
+            ..TickFilterConfig::default()
+        });
+        filter.screen(&tick(100.0));
+        for price in [130.0, 130.1, 129.9] {
+            assert!(matches!(filter.screen(&tick(price)), Screening::Bad { .. }));
+        }
+        assert_eq!(filter.screen(&tick(130.2)), Screening::Confirmed);
+        assert_eq!(filter.screen(&tick(130.3)), Screening::Accepted);
+    }
+
+    #[test]
+    fn a_tick_many_sigmas_out_is_bad_however_small_the_move() {
+        let mut filter = filter(TickFilterConfig {
+            max_deviation_pct: None,
+            max_sigma: Some(10.0),
+            volatility_window: 10,
+            ..TickFilterConfig::default()
+        });
+        // Ticks alternating a basis point apart
+        for i in 0..12 {
+            let price = if i % 2 == 0 { 100.0 } else { 100.01 };
+            assert_eq!(filter.screen(&tick(price)), Screening::Accepted);
+        }
+        assert!(matches!(filter.screen(&tick(101.0)), Screening::Bad { .. }));
+        assert_eq!(filter.screen(&tick(100.0)), Screening::Accepted);
+    }
+}
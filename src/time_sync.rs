@@ -0,0 +1,259 @@
+use crate::ExecutionError;
+use crate::clock::Clock;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Keeping signed request timestamps on the venue's clock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeSyncConfig {
+    // How often the venue's time is sampled
+    pub interval_ms: u64,
+    // Weight of each new sample in the smoothed offset; 1 takes it as is
+    pub smoothing: f64,
+    // Offsets beyond this (either way) are warned about
+    pub max_skew_ms: u64,
+    // Also refuse new orders while the offset is beyond max_skew_ms
+    pub halt_on_skew: bool,
+}
+
+impl Default for TimeSyncConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 60_000,
+            smoothing: 0.2,
+            max_skew_ms: 1_000,
+            halt_on_skew: false,
+        }
+    }
+}
+
+impl TimeSyncConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_ms == 0 {
+            return Err("Time sync interval_ms must be positive".to_string());
+        }
+        if !(self.smoothing > 0.0 && self.smoothing <= 1.0) {
+            return Err("Time sync smoothing must be in (0, 1]".to_string());
+        }
+        Ok(())
+    }
+}
+
+// A venue that reports its own time
+pub trait ServerTime: Send + Sync {
+    // Milliseconds since the Unix epoch by the venue's clock
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>>;
+}
+
+// One round trip to the venue's time endpoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSample {
+    pub rtt_ms: u64,
+    // Venue time minus local time, taking the reply as stamped half a
+    // round trip before it arrived
+    pub offset_ms: f64,
+}
+
+impl TimeSample {
+    pub fn new(sent_ms: u64, server_ms: u64, received_ms: u64) -> Self {
+        let rtt_ms = received_ms.saturating_sub(sent_ms);
+        Self {
+            rtt_ms,
+            offset_ms: server_ms as f64 + rtt_ms as f64 / 2.0 - received_ms as f64,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SyncState {
+    // None until the first sample
+    offset_ms: Option<f64>,
+    skewed: bool,
+}
+
+/// Tracks how far the venue's clock is from ours. Each sample's offset
+/// assumes the venue stamped its reply half a round trip before it
+/// arrived; samples are smoothed with an exponential moving average, the
+/// first taken as is. `exchange_now_ms` is local time moved by the
+/// smoothed offset, for stamping signed requests.
+pub struct TimeSync {
+    config: TimeSyncConfig,
+    clock: Arc<dyn Clock>,
+    state: Mutex<SyncState>,
+}
+
+impl TimeSync {
+    pub fn new(config: TimeSyncConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            config,
+            clock,
+            state: Mutex::new(SyncState::default()),
+        }
+    }
+
+    pub fn config(&self) -> &TimeSyncConfig {
+        &self.config
+    }
+
+    // Local time on the venue's clock, as far as we know it
+    pub fn exchange_now_ms(&self) -> u64 {
+        let offset = self.offset_ms().unwrap_or_default().round() as i64;
+        self.clock.now_ms().saturating_add_signed(offset)
+    }
+
+    // The smoothed venue-minus-local offset, once there is a sample
+    pub fn offset_ms(&self) -> Option<f64> {
+        self.state.lock().unwrap().offset_ms
+    }
+
+    // Whether new orders should be held back: halt_on_skew is set and the
+    // offset is past max_skew_ms
+    pub fn halted(&self) -> bool {
+        self.config.halt_on_skew && self.state.lock().unwrap().skewed
+    }
+
+    /// Folds a sample into the smoothed offset and returns the new offset.
+    /// Warns when the offset crosses max_skew_ms, and again when it comes
+    /// back within it.
+    pub fn record(&self, sample: TimeSample) -> f64 {
+        let mut state = self.state.lock().unwrap();
+        let offset = match state.offset_ms {
+            Some(offset) => offset + self.config.smoothing * (sample.offset_ms - offset),
+            None => sample.offset_ms,
+        };
+        state.offset_ms = Some(offset);
+
+        let skewed = offset.abs() > self.config.max_skew_ms as f64;
+        if skewed && !state.skewed {
+            println!(
+                "Warning: local clock is {:.0}ms {} the venue's (over {}ms, round trip {}ms){}",
+                offset.abs(),
+                if offset > 0.0 { "behind" } else { "ahead of" },
+                self.config.max_skew_ms,
+                sample.rtt_ms,
+                if self.config.halt_on_skew {
+                    "; new orders halted"
+                } else {
+                    ""
+                }
+            );
+        } else if !skewed && state.skewed {
+            println!(
+                "Local clock back within {}ms of the venue's ({:.0}ms off)",
+                self.config.max_skew_ms,
+                offset.abs()
+            );
+        }
+        state.skewed = skewed;
+        offset
+    }
+
+    // Samples the venue's time once and returns the new smoothed offset
+    pub async fn sync(&self, venue: &dyn ServerTime) -> Result<f64, ExecutionError> {
+        let sent_ms = self.clock.now_ms();
+        let server_ms = venue.server_time().await?;
+        let received_ms = self.clock.now_ms();
+        Ok(self.record(TimeSample::new(sent_ms, server_ms, received_ms)))
+    }
+
+    /// Samples the venue every interval_ms until the task is aborted. A
+    /// failed sample is logged and leaves the offset as it was.
+    pub fn spawn(self: Arc<Self>, venue: Arc<dyn ServerTime>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.sync(venue.as_ref()).await {
+                    println!("Time sync failed: {}", e);
+                }
+                self.clock
+                    .sleep(Duration::from_millis(self.config.interval_ms))
+                    .await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::VirtualClock;
+
+    // A time endpoint `offset_ms` ahead of the local clock, answering in
+    // `rtt_ms` and stamping its reply halfway through
+    struct MockVenue {
+        clock: Arc<VirtualClock>,
+        offset_ms: Mutex<i64>,
+        rtt_ms: u64,
+    }
+
+    impl ServerTime for MockVenue {
+        fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+            Box::pin(async move {
+                self.clock.advance(Duration::from_millis(self.rtt_ms / 2));
+                let stamped = self
+                    .clock
+                    .now_ms()
+                    .saturating_add_signed(*self.offset_ms.lock().unwrap());
+                self.clock.advance(Duration::from_millis(self.rtt_ms / 2));
+                Ok(stamped)
+            })
+        }
+    }
+
+    fn setup(config: TimeSyncConfig, offset_ms: i64) -> (TimeSync, MockVenue) {
+        let clock = Arc::new(VirtualClock::new(1_704_067_200_000));
+        let venue = MockVenue {
+            clock: clock.clone(),
+            offset_ms: Mutex::new(offset_ms),
+            rtt_ms: 80,
+        };
+        (TimeSync::new(config, clock), venue)
+    }
+
+    #[test]
+    fn a_sample_takes_the_reply_as_stamped_half_a_round_trip_back() {
+        let sample = TimeSample::new(1_000, 1_540, 1_080);
+        assert_eq!(sample.rtt_ms, 80);
+        assert_eq!(sample.offset_ms, 500.0);
+    }
+
+    #[tokio::test]
+    async fn the_offset_is_smoothed_from_the_first_sample_on() {
+        let (sync, venue) = setup(TimeSyncConfig::default(), 500);
+        assert_eq!(sync.offset_ms(), None);
+        assert_eq!(sync.exchange_now_ms(), 1_704_067_200_000);
+
+        assert_eq!(sync.sync(&venue).await.unwrap(), 500.0);
+        assert_eq!(sync.exchange_now_ms(), 1_704_067_200_080 + 500);
+        // The venue jumps to 1s ahead; a fifth of the change comes through
+        *venue.offset_ms.lock().unwrap() = 1_000;
+        assert_eq!(sync.sync(&venue).await.unwrap(), 600.0);
+        assert_eq!(sync.exchange_now_ms(), 1_704_067_200_160 + 600);
+    }
+
+    #[tokio::test]
+    async fn skew_halts_orders_only_when_asked_and_only_while_it_lasts() {
+        let config = TimeSyncConfig {
+            smoothing: 1.0,
+            max_skew_ms: 1_000,
+            halt_on_skew: true,
+            ..TimeSyncConfig::default()
+        };
+        let (sync, venue) = setup(config.clone(), -1_500);
+        sync.sync(&venue).await.unwrap();
+        assert!(sync.halted());
+        *venue.offset_ms.lock().unwrap() = 900;
+        sync.sync(&venue).await.unwrap();
+        assert!(!sync.halted());
+
+        let warn_only = TimeSyncConfig {
+            halt_on_skew: false,
+            ..config
+        };
+        let (sync, venue) = setup(warn_only, -1_500);
+        assert_eq!(sync.sync(&venue).await.unwrap(), -1_500.0);
+        assert!(!sync.halted());
+    }
+}
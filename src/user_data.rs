@@ -0,0 +1,490 @@
+use crate::binance::{BinanceConfig, BinanceExecutor, VenueStatus};
+use crate::fees::Liquidity;
+use crate::symbols::{SymbolId, assets};
+use crate::venue::VenueId;
+use crate::ws::{WsProtocol, WsSupervisor};
+use crate::{ExecutionError, Fill, OrderSide, PositionSide};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Notify, mpsc};
+
+// One of our orders executing, as a user data stream reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionReport {
+    // The venue's symbol, e.g. SOLUSDT
+    pub symbol: String,
+    pub client_order_id: String,
+    pub side: OrderSide,
+    // NEW, TRADE, CANCELED, EXPIRED...; only TRADE carries a fill
+    pub execution_type: String,
+    pub status: VenueStatus,
+    // Unique per symbol; -1 when nothing traded
+    pub trade_id: i64,
+    pub last_quantity: f64,
+    pub last_price: f64,
+    pub commission: f64,
+    pub commission_asset: Option<String>,
+    pub maker: bool,
+    // Milliseconds since the Unix epoch
+    pub transaction_time: u64,
+}
+
+// What the user data stream carries that the bot takes in
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserDataEvent {
+    Execution(ExecutionReport),
+    // Free plus locked, for each asset that changed
+    Balances(HashMap<String, f64>),
+    // The listen key lapsed; the stream carries nothing more
+    ListenKeyExpired,
+}
+
+/// Parses one Binance user data stream payload, spot or futures. Events
+/// the bot has no use for (balanceUpdate, which an outboundAccountPosition
+/// always follows, listStatus and futures margin calls) are Ok(None).
+pub fn parse_binance_event(text: &str) -> Result<Option<UserDataEvent>, String> {
+    let event: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| format!("unparseable user data: {}", e))?;
+    let unparseable = || format!("unparseable user data: {}", text);
+    match event["e"].as_str() {
+        // A spot cancel's own id is in c; the order's is in C
+        Some("executionReport") => execution_report(&event, "C", text)
+            .map(UserDataEvent::Execution)
+            .map(Some),
+        // Futures reports nest the order under o and have no cancel ids
+        Some("ORDER_TRADE_UPDATE") => execution_report(&event["o"], "c", text)
+            .map(UserDataEvent::Execution)
+            .map(Some),
+        // Futures wallet balances; positions are booked from fills
+        Some("ACCOUNT_UPDATE") => {
+            let mut balances = HashMap::new();
+            for balance in event["a"]["B"].as_array().ok_or_else(unparseable)? {
+                let asset = balance["a"].as_str().ok_or_else(unparseable)?;
+                let wallet = balance["wb"]
+                    .as_str()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .ok_or_else(unparseable)?;
+                balances.insert(asset.to_string(), wallet);
+            }
+            Ok(Some(UserDataEvent::Balances(balances)))
+        }
+        Some("outboundAccountPosition") => {
+            let mut balances = HashMap::new();
+            for balance in event["B"].as_array().ok_or_else(unparseable)? {
+                let amount = |field: &str| {
+                    balance[field]
+                        .as_str()
+                        .and_then(|value| value.parse::<f64>().ok())
+                        .ok_or_else(unparseable)
+                };
+                let asset = balance["a"].as_str().ok_or_else(unparseable)?;
+                balances.insert(asset.to_string(), amount("f")? + amount("l")?);
+            }
+            Ok(Some(UserDataEvent::Balances(balances)))
+        }
+        Some("listenKeyExpired") => Ok(Some(UserDataEvent::ListenKeyExpired)),
+        Some(_) => Ok(None),
+        None => Err(unparseable()),
+    }
+}
+
+// An execution report's fields, which spot and futures share; a cancel
+// names the order in `cancelled_id`
+fn execution_report(
+    report: &serde_json::Value,
+    cancelled_id: &str,
+    text: &str,
+) -> Result<ExecutionReport, String> {
+    let unparseable = || format!("unparseable user data: {}", text);
+    let string = |field: &str| report[field].as_str().ok_or_else(unparseable);
+    let decimal = |field: &str| string(field)?.parse::<f64>().map_err(|_| unparseable());
+    let execution_type = string("x")?.to_string();
+    let client_order_id = match execution_type.as_str() {
+        "CANCELED" => string(cancelled_id)?,
+        _ => string("c")?,
+    };
+    Ok(ExecutionReport {
+        symbol: string("s")?.to_string(),
+        client_order_id: client_order_id.to_string(),
+        side: match string("S")? {
+            "BUY" => OrderSide::Buy,
+            "SELL" => OrderSide::Sell,
+            _ => return Err(unparseable()),
+        },
+        execution_type,
+        status: VenueStatus::parse(string("X")?),
+        trade_id: report["t"].as_i64().ok_or_else(unparseable)?,
+        last_quantity: decimal("l")?,
+        last_price: decimal("L")?,
+        // Futures leave it out when there is none
+        commission: match report["n"].as_str() {
+            Some(_) => decimal("n")?,
+            None => 0.0,
+        },
+        commission_asset: report["N"].as_str().map(str::to_string),
+        maker: report["m"].as_bool().unwrap_or(false),
+        transaction_time: report["T"].as_u64().ok_or_else(unparseable)?,
+    })
+}
+
+// Executions already booked, by (symbol, trade id); the oldest are
+// forgotten
+#[derive(Debug, Default)]
+struct SeenExecutions {
+    seen: HashSet<(String, i64)>,
+    arrival: VecDeque<(String, i64)>,
+}
+
+impl SeenExecutions {
+    const CAPACITY: usize = 10_000;
+
+    // Whether this is the first report of the execution
+    fn insert(&mut self, symbol: &str, trade_id: i64) -> bool {
+        let key = (symbol.to_string(), trade_id);
+        if !self.seen.insert(key.clone()) {
+            return false;
+        }
+        if self.arrival.len() == Self::CAPACITY
+            && let Some(oldest) = self.arrival.pop_front()
+        {
+            self.seen.remove(&oldest);
+        }
+        self.arrival.push_back(key);
+        true
+    }
+}
+
+/// Turns Binance execution reports into Fills on the bot's fill stream
+/// (TradingBot::fill_sender), where they are booked as paper fills are,
+/// and account updates into balances. Each execution is booked once,
+/// however many times it is reported; reports are booked in the order they
+/// arrive, which for positions is the same as in order of execution.
+pub struct BinanceUserData {
+    executor: Arc<BinanceExecutor>,
+    // What the fills are booked against
+    venue: VenueId,
+    fills: mpsc::UnboundedSender<Fill>,
+    balances: Option<mpsc::UnboundedSender<HashMap<String, f64>>>,
+    seen: Mutex<SeenExecutions>,
+    // Raised when the venue says the listen key lapsed
+    expired: Arc<Notify>,
+}
+
+impl BinanceUserData {
+    pub fn new(executor: Arc<BinanceExecutor>, fills: mpsc::UnboundedSender<Fill>) -> Self {
+        Self {
+            executor,
+            venue: VenueId::new("binance"),
+            fills,
+            balances: None,
+            seen: Mutex::new(SeenExecutions::default()),
+            expired: Arc::new(Notify::new()),
+        }
+    }
+
+    // The venue id the account is configured under, when not "binance"
+    pub fn with_venue(mut self, venue: VenueId) -> Self {
+        self.venue = venue;
+        self
+    }
+
+    // Where balance updates go, for TradingBot::update_balances
+    pub fn with_balances(mut self, balances: mpsc::UnboundedSender<HashMap<String, f64>>) -> Self {
+        self.balances = Some(balances);
+        self
+    }
+
+    /// The fill an execution report carries, if it is a trade not seen
+    /// before. Orders placed through the executor keep their id, symbol
+    /// and strategy; others get our name for the venue's symbol and keep
+    /// their client order id.
+    /// The commission is converted to the quote asset when it was charged
+    /// in the base asset; commission in any other asset (BNB) isn't counted
+    /// here and shows in the balances instead.
+    pub fn fill(&self, report: &ExecutionReport) -> Option<Fill> {
+        if report.execution_type != "TRADE"
+            || !self
+                .seen
+                .lock()
+                .unwrap()
+                .insert(&report.symbol, report.trade_id)
+        {
+            return None;
+        }
+        let order = self.executor.order_ref(&report.client_order_id);
+        let symbol = order.as_ref().map_or_else(
+            || self.executor.mapper().to_canonical(&report.symbol),
+            |order| order.symbol.clone(),
+        );
+        let quote = assets(&symbol).map(|(_, quote)| quote);
+        let fee = match report.commission_asset.as_deref() {
+            Some(asset) if Some(asset) == quote => report.commission,
+            Some(asset) if quote.is_none() && report.symbol.ends_with(asset) => report.commission,
+            Some(asset) if report.symbol.starts_with(asset) => {
+                report.commission * report.last_price
+            }
+            _ => 0.0,
+        };
+        Some(Fill {
+            order_id: order
+                .as_ref()
+                .map_or(report.client_order_id.clone(), |order| {
+                    order.order_id.clone()
+                }),
+            symbol: SymbolId::intern(&symbol),
+            side: report.side,
+            quantity: report.last_quantity,
+            price: report.last_price,
+            fee,
+            liquidity: if report.maker {
+                Liquidity::Maker
+            } else {
+                Liquidity::Taker
+            },
+            timestamp: report.transaction_time / 1_000,
+            position_side: order
+                .as_ref()
+                .map_or(PositionSide::Both, |order| order.position_side),
+            strategy: order.and_then(|order| order.strategy),
+            venue: self.venue.clone(),
+        })
+    }
+
+    fn handle(&self, event: UserDataEvent) {
+        match event {
+            UserDataEvent::Execution(report) => {
+                if let Some(fill) = self.fill(&report) {
+                    // The receiver only goes away at shutdown
+                    let _ = self.fills.send(fill);
+                }
+            }
+            UserDataEvent::Balances(balances) => {
+                if let Some(sender) = &self.balances {
+                    let _ = sender.send(balances);
+                }
+            }
+            UserDataEvent::ListenKeyExpired => {
+                println!("Binance listen key expired; renewing it");
+                self.expired.notify_one();
+            }
+        }
+    }
+}
+
+impl WsProtocol for BinanceUserData {
+    // The listen key in the URL is the subscription
+    fn subscribe(&self, _streams: &[String]) -> String {
+        String::new()
+    }
+
+    fn unsubscribe(&self, _streams: &[String]) -> String {
+        String::new()
+    }
+
+    fn on_message(&self, text: &str) {
+        match parse_binance_event(text) {
+            Ok(Some(event)) => self.handle(event),
+            Ok(None) => {}
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+/// A running Binance user data stream: the connection, kept up by a
+/// WsSupervisor, and a task keeping its listen key alive. A listen key the
+/// venue no longer knows is replaced and the stream moved onto the new one.
+pub struct BinanceUserStream {
+    executor: Arc<BinanceExecutor>,
+    supervisor: Arc<WsSupervisor>,
+    listen_key: Arc<Mutex<String>>,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl BinanceUserStream {
+    pub async fn start(
+        config: &BinanceConfig,
+        executor: Arc<BinanceExecutor>,
+        protocol: BinanceUserData,
+    ) -> Result<Self, ExecutionError> {
+        let stream_url = config.stream_url().to_string();
+        let expired = protocol.expired.clone();
+        let key = executor.create_listen_key().await?;
+        let supervisor = Arc::new(WsSupervisor::new(
+            "binance-user-data",
+            format!("{}/ws/{}", stream_url, key),
+            Arc::new(protocol),
+            config.reconnect.clone(),
+        ));
+        let listen_key = Arc::new(Mutex::new(key));
+
+        let keepalive = {
+            let (executor, supervisor, listen_key) =
+                (executor.clone(), supervisor.clone(), listen_key.clone());
+            let interval = Duration::from_millis(config.keepalive_ms);
+            tokio::spawn(async move {
+                loop {
+                    let key = listen_key.lock().unwrap().clone();
+                    tokio::select! {
+                        _ = tokio::time::sleep(interval) => {
+                            let Err(e) = executor.keep_alive_listen_key(&key).await else {
+                                continue;
+                            };
+                            println!("Binance listen key keepalive failed: {}", e);
+                        }
+                        _ = expired.notified() => {}
+                    }
+                    // Opening one returns the live key if there still is one
+                    match executor.create_listen_key().await {
+                        Ok(renewed) if renewed != key => {
+                            supervisor.set_url(format!("{}/ws/{}", stream_url, renewed));
+                            *listen_key.lock().unwrap() = renewed;
+                        }
+                        Ok(_) => {}
+                        Err(e) => println!("Binance listen key renewal failed: {}", e),
+                    }
+                }
+            })
+        };
+        let connection = supervisor.clone().spawn();
+        Ok(Self {
+            executor,
+            supervisor,
+            listen_key,
+            tasks: vec![keepalive, connection],
+        })
+    }
+
+    pub fn supervisor(&self) -> &Arc<WsSupervisor> {
+        &self.supervisor
+    }
+
+    // Disconnects and closes the listen key
+    pub async fn stop(self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        let key = self.listen_key.lock().unwrap().clone();
+        if let Err(e) = self.executor.close_listen_key(&key).await {
+            println!("Failed to close the Binance listen key: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A spot order placed outside the bot filling in two trades, the first
+    // reported twice, then the account update that follows; shaped as
+    // Binance's user data stream sends them
+    const SESSION: [&str; 5] = [
+        r#"{"e":"executionReport","E":1704067200100,"s":"SOLUSDT","c":"web-1","S":"BUY","o":"LIMIT","f":"GTC","q":"1.00000000","p":"100.50000000","P":"0.00000000","F":"0.00000000","g":-1,"C":"","x":"NEW","X":"NEW","r":"NONE","i":4293153,"l":"0.00000000","z":"0.00000000","L":"0.00000000","n":"0","N":null,"T":1704067200099,"t":-1,"I":8641984,"w":true,"m":false,"M":false,"O":1704067200099,"Z":"0.00000000","Y":"0.00000000","Q":"0.00000000"}"#,
+        r#"{"e":"executionReport","E":1704067201200,"s":"SOLUSDT","c":"web-1","S":"BUY","o":"LIMIT","f":"GTC","q":"1.00000000","p":"100.50000000","P":"0.00000000","F":"0.00000000","g":-1,"C":"","x":"TRADE","X":"PARTIALLY_FILLED","r":"NONE","i":4293153,"l":"0.40000000","z":"0.40000000","L":"100.50000000","n":"0.04020000","N":"USDT","T":1704067201199,"t":11,"I":8641990,"w":false,"m":false,"M":true,"O":1704067200099,"Z":"40.20000000","Y":"40.20000000","Q":"0.00000000"}"#,
+        r#"{"e":"executionReport","E":1704067201200,"s":"SOLUSDT","c":"web-1","S":"BUY","o":"LIMIT","f":"GTC","q":"1.00000000","p":"100.50000000","P":"0.00000000","F":"0.00000000","g":-1,"C":"","x":"TRADE","X":"PARTIALLY_FILLED","r":"NONE","i":4293153,"l":"0.40000000","z":"0.40000000","L":"100.50000000","n":"0.04020000","N":"USDT","T":1704067201199,"t":11,"I":8641990,"w":false,"m":false,"M":true,"O":1704067200099,"Z":"40.20000000","Y":"40.20000000","Q":"0.00000000"}"#,
+        r#"{"e":"executionReport","E":1704067203300,"s":"SOLUSDT","c":"web-1","S":"BUY","o":"LIMIT","f":"GTC","q":"1.00000000","p":"100.50000000","P":"0.00000000","F":"0.00000000","g":-1,"C":"","x":"TRADE","X":"FILLED","r":"NONE","i":4293153,"l":"0.60000000","z":"1.00000000","L":"100.40000000","n":"0.00060000","N":"SOL","T":1704067203299,"t":12,"I":8641995,"w":false,"m":true,"M":true,"O":1704067200099,"Z":"100.44000000","Y":"60.24000000","Q":"0.00000000"}"#,
+        r#"{"e":"outboundAccountPosition","E":1704067203301,"u":1704067203299,"B":[{"a":"SOL","f":"0.99940000","l":"0.00000000"},{"a":"USDT","f":"899.50000000","l":"0.50000000"}]}"#,
+    ];
+
+    fn user_data() -> (
+        BinanceUserData,
+        mpsc::UnboundedReceiver<Fill>,
+        mpsc::UnboundedReceiver<HashMap<String, f64>>,
+    ) {
+        let config = BinanceConfig {
+            api_key: Some("key".to_string()),
+            api_secret: Some("secret".to_string()),
+            ..BinanceConfig::default()
+        };
+        let executor = Arc::new(BinanceExecutor::new(&config).unwrap());
+        let (fills, fill_stream) = mpsc::unbounded_channel();
+        let (balances, balance_stream) = mpsc::unbounded_channel();
+        let user_data = BinanceUserData::new(executor, fills).with_balances(balances);
+        (user_data, fill_stream, balance_stream)
+    }
+
+    #[test]
+    fn a_captured_session_books_each_trade_once() {
+        let (user_data, mut fill_stream, mut balance_stream) = user_data();
+        for payload in SESSION {
+            user_data.on_message(payload);
+        }
+
+        let mut fills = Vec::new();
+        while let Ok(fill) = fill_stream.try_recv() {
+            fills.push(fill);
+        }
+        let booked: Vec<_> = fills
+            .iter()
+            .map(|f| {
+                let fee = (f.fee * 1e8).round() / 1e8;
+                (
+                    f.order_id.as_str(),
+                    f.symbol.as_str(),
+                    f.side,
+                    f.quantity,
+                    f.price,
+                    fee,
+                )
+            })
+            .collect();
+        // The fee charged in SOL is booked in USDT at the trade's price
+        assert_eq!(
+            booked,
+            [
+                ("web-1", "SOL/USDT", OrderSide::Buy, 0.4, 100.5, 0.0402),
+                ("web-1", "SOL/USDT", OrderSide::Buy, 0.6, 100.4, 0.06024),
+            ]
+        );
+        assert_eq!(
+            fills
+                .iter()
+                .map(|f| (f.liquidity, f.timestamp))
+                .collect::<Vec<_>>(),
+            [
+                (Liquidity::Taker, 1_704_067_201),
+                (Liquidity::Maker, 1_704_067_203)
+            ]
+        );
+        assert!(fills.iter().all(|f| f.venue == VenueId::new("binance")));
+
+        let balances = balance_stream.try_recv().unwrap();
+        assert_eq!(balances["SOL"], 0.9994);
+        assert_eq!(balances["USDT"], 900.0);
+        assert!(balance_stream.try_recv().is_err());
+    }
+
+    #[test]
+    fn futures_updates_and_stream_notices_parse() {
+        let trade = r#"{"e":"ORDER_TRADE_UPDATE","E":1704067201200,"T":1704067201199,"o":{"s":"SOLUSDT","c":"bot-2","S":"SELL","o":"MARKET","f":"GTC","q":"2","p":"0","ap":"99.9","sp":"0","x":"TRADE","X":"FILLED","i":8886774,"l":"2","z":"2","L":"99.9","N":"USDT","n":"0.07992","T":1704067201199,"t":42,"b":"0","a":"0","m":false,"R":false,"wt":"CONTRACT_PRICE","ot":"MARKET","ps":"BOTH","cp":false,"rp":"0"}}"#;
+        let Some(UserDataEvent::Execution(report)) = parse_binance_event(trade).unwrap() else {
+            panic!("not an execution");
+        };
+        assert_eq!(
+            (
+                report.client_order_id.as_str(),
+                report.side,
+                report.trade_id
+            ),
+            ("bot-2", OrderSide::Sell, 42)
+        );
+        assert_eq!((report.last_quantity, report.last_price), (2.0, 99.9));
+
+        let account = r#"{"e":"ACCOUNT_UPDATE","E":1704067201200,"T":1704067201199,"a":{"m":"ORDER","B":[{"a":"USDT","wb":"1000.5","cw":"1000.5","bc":"0"}],"P":[]}}"#;
+        let Some(UserDataEvent::Balances(balances)) = parse_binance_event(account).unwrap() else {
+            panic!("not balances");
+        };
+        assert_eq!(balances["USDT"], 1000.5);
+
+        let expired = r#"{"e":"listenKeyExpired","E":1704067201200,"listenKey":"abc"}"#;
+        assert_eq!(
+            parse_binance_event(expired).unwrap(),
+            Some(UserDataEvent::ListenKeyExpired)
+        );
+        assert_eq!(
+            parse_binance_event(r#"{"e":"balanceUpdate","a":"USDT","d":"1"}"#).unwrap(),
+            None
+        );
+        assert!(parse_binance_event("not json").is_err());
+    }
+}
@@ -0,0 +1,662 @@
+use crate::binance::{BinanceConfig, BinanceExecutor, BinanceMarketData};
+use crate::clock::Clock;
+use crate::coinbase::{CoinbaseConfig, CoinbaseExecutor, CoinbaseMarketData};
+use crate::fees::FeeModel;
+use crate::fix::{FixConfig, FixExecutor, NoMarketData};
+use crate::orders::OrderState;
+use crate::symbols::{SymbolMapper, SymbolMeta, SymbolRegistry};
+use crate::time_sync::ServerTime;
+use crate::{ExecutionError, MarketData, Order, OrderExecutor};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+// Names a venue: "paper", "binance"... Orders, fills and positions carry
+// the venue they belong to; the bot's own paper venue is the default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VenueId(String);
+
+impl VenueId {
+    pub const PAPER: &'static str = "paper";
+
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn paper() -> Self {
+        Self::new(Self::PAPER)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for VenueId {
+    fn default() -> Self {
+        Self::paper()
+    }
+}
+
+impl fmt::Display for VenueId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Everything the bot uses a venue for: its market data, order entry,
+/// trading rules, fees and clock. One per venue, held by the bot by id.
+///
+/// ```
+/// use hft_trading_bot::MarketData;
+/// use hft_trading_bot::builder::TradingBotBuilder;
+/// use hft_trading_bot::venue::{Exchange, VenueId};
+///
+/// let bot = TradingBotBuilder::new().build().unwrap();
+/// // Every bot has its own paper venue, trading the simulated market
+/// let paper: &dyn Exchange = bot.venue(&VenueId::paper()).unwrap().as_ref();
+/// assert_eq!(paper.id(), bot.primary_venue());
+/// let runtime = tokio::runtime::Runtime::new().unwrap();
+/// let price = runtime.block_on(paper.market_data().get_price("SOL/USDT"));
+/// assert!(price.is_some_and(|p| p.price > 0.0));
+/// ```
+pub trait Exchange: Send + Sync {
+    fn id(&self) -> &VenueId;
+    // Prices and books for the venue's symbols
+    fn market_data(&self) -> Arc<dyn MarketData>;
+    // Returns the id the order is known by
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>>;
+    fn cancel_order<'a>(&'a self, order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>>;
+    // Tick/lot grids and minimums, where the venue's rules are known
+    fn symbol_meta(&self, symbol: &str) -> Option<SymbolMeta>;
+    fn fee_model(&self) -> &FeeModel;
+    // Milliseconds since the Unix epoch by the venue's clock
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>>;
+    // The request submit_order would send for the order, without
+    // credentials or signatures, for logging
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError>;
+    // Orders working at the venue in these symbols, as it reports them
+    fn open_orders<'a>(
+        &'a self,
+        _symbols: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<OpenOrder>, ExecutionError>> {
+        Box::pin(async {
+            Err(ExecutionError::Unsupported(
+                "open order queries".to_string(),
+            ))
+        })
+    }
+    // Everything the account holds at the venue, free and locked, by asset
+    fn balances(&self) -> BoxFuture<'_, Result<HashMap<String, f64>, ExecutionError>> {
+        Box::pin(async { Err(ExecutionError::Unsupported("balance queries".to_string())) })
+    }
+    // A perpetual future's current funding rate per funding interval
+    fn funding_rate<'a>(&'a self, _symbol: &'a str) -> BoxFuture<'a, Result<f64, ExecutionError>> {
+        Box::pin(async { Err(ExecutionError::Unsupported("funding rates".to_string())) })
+    }
+}
+
+// A working order as a venue reports it
+#[derive(Debug, Clone)]
+pub struct OpenOrder {
+    // Our order where it was placed through this connection, or else one
+    // rebuilt from the report under the venue's order id
+    pub state: OrderState,
+    // Placed through this connection, and still remembered
+    pub known: bool,
+}
+
+/// Where the bot's orders go. Paper trades the simulated market on paper;
+/// DryRun takes prices, books and symbol rules from the primary venue but
+/// fills orders on paper; only Live sends orders to a venue, and only
+/// once confirmed at startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    #[default]
+    Paper,
+    DryRun,
+    Live,
+}
+
+impl ExecutionMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExecutionMode::Paper => "paper",
+            ExecutionMode::DryRun => "dry_run",
+            ExecutionMode::Live => "live",
+        }
+    }
+}
+
+impl fmt::Display for ExecutionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// One venue in the config's [venues] section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VenueConfig {
+    Binance(BinanceConfig),
+    Coinbase(CoinbaseConfig),
+    Fix(FixConfig),
+}
+
+impl VenueConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            VenueConfig::Binance(config) => config.validate(),
+            VenueConfig::Coinbase(config) => config.validate(),
+            VenueConfig::Fix(config) => config.validate(),
+        }
+    }
+
+    // How the venue names our symbols
+    pub fn symbol_mapper(&self) -> SymbolMapper {
+        match self {
+            VenueConfig::Binance(config) => config.symbol_mapper(),
+            VenueConfig::Coinbase(config) => config.symbol_mapper(),
+            VenueConfig::Fix(config) => config.symbol_mapper(),
+        }
+    }
+}
+
+/// The venues the bot connects to besides its own paper venue, and which
+/// one its orders go to:
+///
+/// ```toml
+/// [venues]
+/// primary = "binance"
+///
+/// [venues.binance]
+/// kind = "binance"
+/// testnet = true
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VenuesConfig {
+    // Where the bot trades
+    pub primary: VenueId,
+    #[serde(flatten)]
+    pub venues: BTreeMap<VenueId, VenueConfig>,
+}
+
+impl VenuesConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.venues.contains_key(&VenueId::paper()) {
+            return Err(format!(
+                "Venue id {} is the bot's own paper venue",
+                VenueId::PAPER
+            ));
+        }
+        if self.primary != VenueId::paper() && !self.venues.contains_key(&self.primary) {
+            return Err(format!("Primary venue {} isn't configured", self.primary));
+        }
+        for (id, venue) in &self.venues {
+            venue
+                .validate()
+                .map_err(|e| format!("Venue {}: {}", id, e))?;
+        }
+        Ok(())
+    }
+
+    // Every venue must have a name for every symbol the bot trades
+    pub fn validate_symbols(&self, symbols: &[String]) -> Result<(), String> {
+        for (id, venue) in &self.venues {
+            let mapper = venue.symbol_mapper();
+            for symbol in symbols {
+                mapper
+                    .to_venue(symbol)
+                    .map_err(|e| format!("Venue {}: {}", id, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Connects the configured venues. Binance and Coinbase venues need
+    // their credentials; their symbol rules load with load_exchange_info
+    // and load_products. FIX sessions connect on their first order.
+    pub fn build(&self, clock: Arc<dyn Clock>) -> Result<Vec<Arc<dyn Exchange>>, String> {
+        self.venues
+            .iter()
+            .map(|(id, venue)| match venue {
+                VenueConfig::Binance(config) => {
+                    BinanceExchange::new(id.clone(), config, clock.clone())
+                        .map(|exchange| Arc::new(exchange) as Arc<dyn Exchange>)
+                }
+                VenueConfig::Coinbase(config) => {
+                    CoinbaseExchange::new(id.clone(), config, clock.clone())
+                        .map(|exchange| Arc::new(exchange) as Arc<dyn Exchange>)
+                }
+                VenueConfig::Fix(config) => FixExchange::new(id.clone(), config, clock.clone())
+                    .map(|exchange| Arc::new(exchange) as Arc<dyn Exchange>),
+            })
+            .collect()
+    }
+}
+
+/// The bot's own simulated venue: its market data feed and the paper
+/// OrderExecutor, with the executor's fee model and symbol rules
+pub struct PaperExchange {
+    id: VenueId,
+    market_data: Arc<dyn MarketData>,
+    executor: Arc<OrderExecutor>,
+    clock: Arc<dyn Clock>,
+}
+
+impl PaperExchange {
+    pub fn new(
+        market_data: Arc<dyn MarketData>,
+        executor: Arc<OrderExecutor>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            id: VenueId::paper(),
+            market_data,
+            executor,
+            clock,
+        }
+    }
+
+    pub fn executor(&self) -> &Arc<OrderExecutor> {
+        &self.executor
+    }
+}
+
+impl Exchange for PaperExchange {
+    fn id(&self) -> &VenueId {
+        &self.id
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        self.market_data.clone()
+    }
+
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(self.executor.submit_order(order))
+    }
+
+    fn cancel_order<'a>(&'a self, order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        Box::pin(self.executor.cancel_order(&order.id))
+    }
+
+    fn symbol_meta(&self, symbol: &str) -> Option<SymbolMeta> {
+        self.executor.symbol_meta(symbol).cloned()
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        self.executor.fee_model()
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        Box::pin(async move { Ok(self.clock.now_ms()) })
+    }
+
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        Ok(format!("{:?}", order))
+    }
+
+    fn open_orders<'a>(
+        &'a self,
+        symbols: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<OpenOrder>, ExecutionError>> {
+        Box::pin(paper_open_orders(&self.executor, &self.id, symbols))
+    }
+}
+
+// The paper executor's working orders on the venue, every one of them known
+async fn paper_open_orders(
+    executor: &OrderExecutor,
+    venue: &VenueId,
+    symbols: &[String],
+) -> Result<Vec<OpenOrder>, ExecutionError> {
+    Ok(executor
+        .open_orders()
+        .await
+        .into_iter()
+        .filter(|state| {
+            state.order.venue == *venue
+                && symbols.iter().any(|symbol| state.order.symbol == *symbol)
+        })
+        .map(|state| OpenOrder { state, known: true })
+        .collect())
+}
+
+/// Binance spot or USDⓈ-M futures: REST market data and signed order
+/// entry sharing one request weight budget, and the venue's symbol rules
+/// once load_exchange_info has fetched them
+pub struct BinanceExchange {
+    id: VenueId,
+    executor: Arc<BinanceExecutor>,
+    market_data: Arc<BinanceMarketData>,
+    symbols: RwLock<SymbolRegistry>,
+    fees: FeeModel,
+}
+
+impl BinanceExchange {
+    pub fn new(id: VenueId, config: &BinanceConfig, clock: Arc<dyn Clock>) -> Result<Self, String> {
+        let executor = Arc::new(BinanceExecutor::new(config)?.with_clock(clock));
+        Ok(Self {
+            id,
+            market_data: Arc::new(BinanceMarketData::new(executor.clone())),
+            executor,
+            symbols: RwLock::new(SymbolRegistry::default()),
+            fees: config.fees.clone(),
+        })
+    }
+
+    pub fn executor(&self) -> &Arc<BinanceExecutor> {
+        &self.executor
+    }
+
+    // Fetches the venue's tick/lot sizes and minimums for every symbol
+    pub async fn load_exchange_info(&self) -> Result<(), ExecutionError> {
+        let symbols = self.executor.exchange_info().await?;
+        *self.symbols.write().unwrap() = symbols;
+        Ok(())
+    }
+}
+
+impl Exchange for BinanceExchange {
+    fn id(&self) -> &VenueId {
+        &self.id
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        self.market_data.clone()
+    }
+
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(async move { self.executor.submit_order(&order).await })
+    }
+
+    fn cancel_order<'a>(&'a self, order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        Box::pin(self.executor.cancel_order(order))
+    }
+
+    fn symbol_meta(&self, symbol: &str) -> Option<SymbolMeta> {
+        self.symbols.read().unwrap().get(symbol).cloned()
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        self.executor.server_time()
+    }
+
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        self.executor.order_request(order)
+    }
+
+    fn open_orders<'a>(
+        &'a self,
+        symbols: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<OpenOrder>, ExecutionError>> {
+        Box::pin(async move {
+            let mut orders = Vec::new();
+            for symbol in symbols {
+                for mut open in self.executor.open_orders(symbol).await? {
+                    open.state.order.venue = self.id.clone();
+                    orders.push(open);
+                }
+            }
+            Ok(orders)
+        })
+    }
+
+    // On futures, each symbol's net position is added under its base
+    // asset, the way spot holdings show, so it reconciles the same way
+    fn balances(&self) -> BoxFuture<'_, Result<HashMap<String, f64>, ExecutionError>> {
+        Box::pin(async move {
+            let mut balances = self.executor.balances().await?;
+            let positions = self.executor.futures_positions().await?;
+            for (symbol, meta) in self.symbols.read().unwrap().iter() {
+                if let Some(quantity) = positions.get(symbol) {
+                    *balances.entry(meta.base_asset.clone()).or_insert(0.0) += quantity;
+                }
+            }
+            Ok(balances)
+        })
+    }
+
+    fn funding_rate<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<f64, ExecutionError>> {
+        Box::pin(self.executor.funding_rate(symbol))
+    }
+}
+
+/// Coinbase Advanced Trade: WebSocket market data, authenticated REST
+/// order entry, and the venue's symbol rules once load_products has
+/// fetched them
+pub struct CoinbaseExchange {
+    id: VenueId,
+    executor: Arc<CoinbaseExecutor>,
+    market_data: Arc<CoinbaseMarketData>,
+    symbols: RwLock<SymbolRegistry>,
+    fees: FeeModel,
+}
+
+impl CoinbaseExchange {
+    pub fn new(
+        id: VenueId,
+        config: &CoinbaseConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            id,
+            executor: Arc::new(CoinbaseExecutor::new(config)?.with_clock(clock)),
+            market_data: Arc::new(CoinbaseMarketData::new(config)),
+            symbols: RwLock::new(SymbolRegistry::default()),
+            fees: config.fees.clone(),
+        })
+    }
+
+    pub fn executor(&self) -> &Arc<CoinbaseExecutor> {
+        &self.executor
+    }
+
+    pub fn market_data_feed(&self) -> &Arc<CoinbaseMarketData> {
+        &self.market_data
+    }
+
+    // Fetches the venue's tick/lot sizes and minimums for every product
+    pub async fn load_products(&self) -> Result<(), ExecutionError> {
+        let symbols = self.executor.products().await?;
+        *self.symbols.write().unwrap() = symbols;
+        Ok(())
+    }
+}
+
+impl Exchange for CoinbaseExchange {
+    fn id(&self) -> &VenueId {
+        &self.id
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        self.market_data.clone()
+    }
+
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(async move { self.executor.submit_order(&order).await })
+    }
+
+    fn cancel_order<'a>(&'a self, order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        Box::pin(self.executor.cancel_order(order))
+    }
+
+    fn symbol_meta(&self, symbol: &str) -> Option<SymbolMeta> {
+        self.symbols.read().unwrap().get(symbol).cloned()
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        self.executor.server_time()
+    }
+
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        self.executor.order_request(order)
+    }
+}
+
+/// Order entry over a FIX 4.4 session. FIX here carries orders only: the
+/// venue serves no market data unless a feed is given, so a FIX primary
+/// venue wants one from TradingBotBuilder::with_feed. Symbol rules aren't
+/// known; the config's symbol_meta applies.
+pub struct FixExchange {
+    id: VenueId,
+    executor: Arc<FixExecutor>,
+    market_data: Arc<dyn MarketData>,
+    fees: FeeModel,
+}
+
+impl FixExchange {
+    pub fn new(id: VenueId, config: &FixConfig, clock: Arc<dyn Clock>) -> Result<Self, String> {
+        Ok(Self {
+            executor: Arc::new(
+                FixExecutor::new(config)?
+                    .with_venue(id.clone())
+                    .with_clock(clock),
+            ),
+            id,
+            market_data: Arc::new(NoMarketData),
+            fees: config.fees.clone(),
+        })
+    }
+
+    // Prices and books for the venue's symbols, from elsewhere
+    pub fn with_market_data(mut self, market_data: Arc<dyn MarketData>) -> Self {
+        self.market_data = market_data;
+        self
+    }
+
+    pub fn executor(&self) -> &Arc<FixExecutor> {
+        &self.executor
+    }
+}
+
+impl Drop for FixExchange {
+    fn drop(&mut self) {
+        self.executor.stop();
+    }
+}
+
+impl Exchange for FixExchange {
+    fn id(&self) -> &VenueId {
+        &self.id
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        self.market_data.clone()
+    }
+
+    fn submit_order(&self, order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        self.executor.start();
+        Box::pin(async move { self.executor.submit_order(&order).await })
+    }
+
+    fn cancel_order<'a>(&'a self, order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        self.executor.start();
+        Box::pin(self.executor.cancel_order(order))
+    }
+
+    fn symbol_meta(&self, _symbol: &str) -> Option<SymbolMeta> {
+        None
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        &self.fees
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        self.executor.server_time()
+    }
+
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        self.executor.order_request(order)
+    }
+}
+
+/// A venue with its order entry swapped for the paper executor: market
+/// data, symbol rules, fees and clock are the venue's, but orders are
+/// logged as the request the venue would have been sent and filled on
+/// paper against its books. Fills keep the venue's id, so positions are
+/// booked as they would be live.
+pub struct DryRunExchange {
+    venue: Arc<dyn Exchange>,
+    paper: Arc<OrderExecutor>,
+}
+
+impl DryRunExchange {
+    pub fn new(venue: Arc<dyn Exchange>, paper: Arc<OrderExecutor>) -> Self {
+        Self { venue, paper }
+    }
+
+    // The venue orders would otherwise have gone to
+    pub fn venue(&self) -> &Arc<dyn Exchange> {
+        &self.venue
+    }
+}
+
+impl Exchange for DryRunExchange {
+    fn id(&self) -> &VenueId {
+        self.venue.id()
+    }
+
+    fn market_data(&self) -> Arc<dyn MarketData> {
+        self.venue.market_data()
+    }
+
+    // Rounded to the venue's rules, where known, before the paper
+    // executor applies the configured ones
+    fn submit_order(&self, mut order: Order) -> BoxFuture<'_, Result<String, ExecutionError>> {
+        Box::pin(async move {
+            if let Some(meta) = self.venue.symbol_meta(order.symbol.as_str()) {
+                meta.normalize(&mut order, None)?;
+            }
+            let request = self.venue.order_request(&order)?;
+            println!("Dry run on {}; would have sent: {}", self.id(), request);
+            self.paper.submit_order(order).await
+        })
+    }
+
+    fn cancel_order<'a>(&'a self, order: &'a Order) -> BoxFuture<'a, Result<(), ExecutionError>> {
+        Box::pin(self.paper.cancel_order(&order.id))
+    }
+
+    fn symbol_meta(&self, symbol: &str) -> Option<SymbolMeta> {
+        self.venue.symbol_meta(symbol)
+    }
+
+    fn fee_model(&self) -> &FeeModel {
+        self.venue.fee_model()
+    }
+
+    fn server_time(&self) -> BoxFuture<'_, Result<u64, ExecutionError>> {
+        self.venue.server_time()
+    }
+
+    fn order_request(&self, order: &Order) -> Result<String, ExecutionError> {
+        self.venue.order_request(order)
+    }
+
+    fn funding_rate<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<f64, ExecutionError>> {
+        self.venue.funding_rate(symbol)
+    }
+
+    // The orders on paper, not the venue's
+    fn open_orders<'a>(
+        &'a self,
+        symbols: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<OpenOrder>, ExecutionError>> {
+        Box::pin(paper_open_orders(&self.paper, self.id(), symbols))
+    }
+}
@@ -0,0 +1,477 @@
+use crate::events::BotEvent;
+use crate::metrics::Metrics;
+use futures::future::BoxFuture;
+use futures::{Sink, SinkExt, StreamExt};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{Notify, broadcast, watch};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+
+// How a dropped WebSocket connection is retried
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectConfig {
+    // Delay before the first retry; each failure in a row multiplies it
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+    // Each delay is moved by up to this fraction either way, so many
+    // connections dropped together don't retry in lockstep
+    pub jitter: f64,
+    // Failed connection attempts in a row that raise a FeedDown event
+    pub max_failures: u32,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_failures: 5,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.initial_backoff_ms == 0 || self.max_backoff_ms < self.initial_backoff_ms {
+            return Err(
+                "Reconnect initial_backoff_ms must be positive and at most max_backoff_ms"
+                    .to_string(),
+            );
+        }
+        if self.multiplier < 1.0 {
+            return Err("Reconnect multiplier must be at least 1".to_string());
+        }
+        if !(0.0..1.0).contains(&self.jitter) {
+            return Err("Reconnect jitter must be in [0, 1)".to_string());
+        }
+        if self.max_failures == 0 {
+            return Err("Reconnect max_failures must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Exponential backoff with jitter, capped at max_backoff_ms
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    config: ReconnectConfig,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(config: ReconnectConfig) -> Self {
+        Self { config, attempt: 0 }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let max = self.config.max_backoff_ms as f64;
+        let base = (self.config.initial_backoff_ms as f64
+            * self.config.multiplier.powi(self.attempt as i32))
+        .min(max);
+        self.attempt = self.attempt.saturating_add(1);
+        let jitter = if self.config.jitter > 0.0 {
+            rand::thread_rng().gen_range(-self.config.jitter..=self.config.jitter)
+        } else {
+            0.0
+        };
+        Duration::from_millis((base * (1.0 + jitter)).min(max) as u64)
+    }
+
+    // Back to initial_backoff_ms, once a connection is up
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// What a venue's WebSocket stream speaks: how streams are (un)subscribed
+/// and what is done with what arrives. The supervisor owns the connection
+/// and calls these hooks around it.
+pub trait WsProtocol: Send + Sync {
+    // The frame subscribing to the streams
+    fn subscribe(&self, streams: &[String]) -> String;
+    fn unsubscribe(&self, streams: &[String]) -> String;
+    // A text frame from the venue
+    fn on_message(&self, text: &str);
+    // The connection dropped; anything kept current from the stream (order
+    // books) no longer is
+    fn on_disconnect(&self) {}
+    // The streams were resubscribed after a reconnect. Updates were missed
+    // in between, so books need a fresh snapshot.
+    fn on_reconnect<'a>(&'a self, _streams: &'a [String]) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+}
+
+/// Keeps one WebSocket connection up. A dropped or failed connection is
+/// retried after an exponential backoff; once back, every active stream is
+/// subscribed again and the protocol's on_reconnect runs. Streams can be
+/// added and removed while it runs, and each is subscribed once per
+/// connection. Connection state goes to the feed_connected gauge and, as
+/// FeedState events, to the event channel; after max_failures failed
+/// attempts in a row a FeedDown event is raised and retrying goes on.
+pub struct WsSupervisor {
+    name: String,
+    // Changing it moves the connection there
+    url: watch::Sender<String>,
+    protocol: Arc<dyn WsProtocol>,
+    config: ReconnectConfig,
+    // Streams that should be subscribed
+    streams: Mutex<BTreeSet<String>>,
+    streams_changed: Notify,
+    connected: AtomicBool,
+    events: Option<broadcast::Sender<BotEvent>>,
+    connected_gauge: Option<prometheus::Gauge>,
+    reconnects: Option<prometheus::IntCounter>,
+}
+
+impl WsSupervisor {
+    pub fn new(
+        name: impl Into<String>,
+        url: impl Into<String>,
+        protocol: Arc<dyn WsProtocol>,
+        config: ReconnectConfig,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            url: watch::Sender::new(url.into()),
+            protocol,
+            config,
+            streams: Mutex::new(BTreeSet::new()),
+            streams_changed: Notify::new(),
+            connected: AtomicBool::new(false),
+            events: None,
+            connected_gauge: None,
+            reconnects: None,
+        }
+    }
+
+    // Where FeedState and FeedDown events go, typically the bot's channel
+    pub fn with_events(mut self, events: broadcast::Sender<BotEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    // Reports connection state and reconnects under the feed's name
+    pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+        self.connected_gauge = Some(metrics.feed_connected.with_label_values(&[&self.name]));
+        self.reconnects = Some(metrics.feed_reconnects.with_label_values(&[&self.name]));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Reconnects to `url`, as when a stream's credentials in the URL are
+    // replaced
+    pub fn set_url(&self, url: impl Into<String>) {
+        self.url.send_replace(url.into());
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    pub fn streams(&self) -> Vec<String> {
+        self.streams.lock().unwrap().iter().cloned().collect()
+    }
+
+    // Adds the stream; it is subscribed now if connected, else on connect
+    pub fn subscribe(&self, stream: impl Into<String>) {
+        if self.streams.lock().unwrap().insert(stream.into()) {
+            self.streams_changed.notify_one();
+        }
+    }
+
+    pub fn unsubscribe(&self, stream: &str) {
+        if self.streams.lock().unwrap().remove(stream) {
+            self.streams_changed.notify_one();
+        }
+    }
+
+    /// Connects and keeps reconnecting until the task is aborted
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(&self) {
+        let mut backoff = Backoff::new(self.config.clone());
+        let mut failures = 0u32;
+        let mut reconnecting = false;
+        let mut url = self.url.subscribe();
+        loop {
+            let target = url.borrow_and_update().clone();
+            match tokio_tungstenite::connect_async(target.as_str()).await {
+                Ok((socket, _)) => {
+                    failures = 0;
+                    backoff.reset();
+                    if reconnecting && let Some(reconnects) = &self.reconnects {
+                        reconnects.inc();
+                    }
+                    self.set_connected(true);
+                    let result = self.pump(socket, reconnecting, &mut url).await;
+                    self.set_connected(false);
+                    self.protocol.on_disconnect();
+                    match result {
+                        Ok(()) => println!("Feed {} disconnected; reconnecting", self.name),
+                        Err(e) => println!("Feed {} dropped: {}; reconnecting", self.name, e),
+                    }
+                    reconnecting = true;
+                }
+                Err(e) => {
+                    failures += 1;
+                    println!(
+                        "Feed {} failed to connect ({} in a row): {}",
+                        self.name, failures, e
+                    );
+                    if failures == self.config.max_failures {
+                        self.publish(BotEvent::FeedDown {
+                            feed: self.name.clone(),
+                            failures,
+                        });
+                    }
+                }
+            }
+            tokio::time::sleep(backoff.next_delay()).await;
+        }
+    }
+
+    // Subscribes, then hands frames to the protocol until the connection
+    // closes or the URL changes, keeping subscriptions in step with the
+    // active streams
+    async fn pump<S>(
+        &self,
+        socket: S,
+        reconnected: bool,
+        url: &mut watch::Receiver<String>,
+    ) -> Result<(), WsError>
+    where
+        S: futures::Stream<Item = Result<Message, WsError>>
+            + Sink<Message, Error = WsError>
+            + Unpin,
+    {
+        let (mut sink, mut incoming) = socket.split();
+        let mut subscribed = BTreeSet::new();
+        self.sync_streams(&mut sink, &mut subscribed).await?;
+        if reconnected {
+            let streams: Vec<String> = subscribed.iter().cloned().collect();
+            self.protocol.on_reconnect(&streams).await;
+        }
+        loop {
+            tokio::select! {
+                message = incoming.next() => match message {
+                    Some(Ok(Message::Text(text))) => self.protocol.on_message(text.as_str()),
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    // Pings are answered by tungstenite itself
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e),
+                },
+                _ = self.streams_changed.notified() => {
+                    self.sync_streams(&mut sink, &mut subscribed).await?;
+                }
+                _ = url.changed() => return Ok(()),
+            }
+        }
+    }
+
+    // Sends whatever (un)subscriptions take the connection's streams to
+    // the active ones
+    async fn sync_streams(
+        &self,
+        sink: &mut (impl Sink<Message, Error = WsError> + Unpin),
+        subscribed: &mut BTreeSet<String>,
+    ) -> Result<(), WsError> {
+        let wanted = self.streams.lock().unwrap().clone();
+        let removed: Vec<String> = subscribed.difference(&wanted).cloned().collect();
+        let added: Vec<String> = wanted.difference(subscribed).cloned().collect();
+        if !removed.is_empty() {
+            sink.send(Message::text(self.protocol.unsubscribe(&removed)))
+                .await?;
+        }
+        if !added.is_empty() {
+            sink.send(Message::text(self.protocol.subscribe(&added)))
+                .await?;
+        }
+        *subscribed = wanted;
+        Ok(())
+    }
+
+    fn set_connected(&self, connected: bool) {
+        if self.connected.swap(connected, Ordering::AcqRel) == connected {
+            return;
+        }
+        if let Some(gauge) = &self.connected_gauge {
+            gauge.set(if connected { 1.0 } else { 0.0 });
+        }
+        self.publish(BotEvent::FeedState {
+            feed: self.name.clone(),
+            connected,
+        });
+    }
+
+    fn publish(&self, event: BotEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        messages: Mutex<Vec<String>>,
+        reconnects: Mutex<Vec<Vec<String>>>,
+        disconnects: Mutex<u32>,
+    }
+
+    impl WsProtocol for Recorder {
+        fn subscribe(&self, streams: &[String]) -> String {
+            format!("SUB {}", streams.join(","))
+        }
+
+        fn unsubscribe(&self, streams: &[String]) -> String {
+            format!("UNSUB {}", streams.join(","))
+        }
+
+        fn on_message(&self, text: &str) {
+            self.messages.lock().unwrap().push(text.to_string());
+        }
+
+        fn on_disconnect(&self) {
+            *self.disconnects.lock().unwrap() += 1;
+        }
+
+        fn on_reconnect<'a>(&'a self, streams: &'a [String]) -> BoxFuture<'a, ()> {
+            self.reconnects.lock().unwrap().push(streams.to_vec());
+            Box::pin(async {})
+        }
+    }
+
+    // Waits up to a few seconds for `done`
+    async fn until(done: impl Fn() -> bool) {
+        for _ in 0..500 {
+            if done() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out");
+    }
+
+    // Serves three connections: each gets two numbered updates after its
+    // subscription, and the first two are then dropped. Returns the URL and
+    // every frame the server was sent.
+    async fn dropping_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let received = frames.clone();
+        tokio::spawn(async move {
+            for connection in 0..3 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let Some(Ok(Message::Text(frame))) = ws.next().await else {
+                    panic!("no subscription on connection {connection}");
+                };
+                received.lock().unwrap().push(frame.to_string());
+                for update in 1..=2 {
+                    let text = format!("update {}", connection * 2 + update);
+                    ws.send(Message::text(text)).await.unwrap();
+                }
+                if connection < 2 {
+                    ws.close(None).await.unwrap();
+                    continue;
+                }
+                while let Some(Ok(message)) = ws.next().await {
+                    if let Message::Text(frame) = message {
+                        received.lock().unwrap().push(frame.to_string());
+                    }
+                }
+            }
+        });
+        (url, frames)
+    }
+
+    #[tokio::test]
+    async fn two_drops_resubscribe_everything_and_deliver_each_update_once() {
+        let (url, frames) = dropping_server().await;
+        let recorder = Arc::new(Recorder::default());
+        let (events, mut feed_events) = broadcast::channel(64);
+        let config = ReconnectConfig {
+            initial_backoff_ms: 1,
+            max_backoff_ms: 5,
+            jitter: 0.0,
+            ..ReconnectConfig::default()
+        };
+        let supervisor =
+            Arc::new(WsSupervisor::new("mock", url, recorder.clone(), config).with_events(events));
+        supervisor.subscribe("solusdt@depth");
+        supervisor.subscribe("btcusdt@depth");
+        let task = supervisor.clone().spawn();
+
+        until(|| recorder.messages.lock().unwrap().len() == 6 && supervisor.is_connected()).await;
+        // Added while connected: subscribed on its own
+        supervisor.subscribe("ethusdt@depth");
+        until(|| frames.lock().unwrap().len() == 4).await;
+        task.abort();
+
+        let both = "SUB btcusdt@depth,solusdt@depth";
+        assert_eq!(
+            *frames.lock().unwrap(),
+            [both, both, both, "SUB ethusdt@depth"]
+        );
+        let updates: Vec<String> = (1..=6).map(|i| format!("update {i}")).collect();
+        assert_eq!(*recorder.messages.lock().unwrap(), updates);
+        let resubscribed = vec!["btcusdt@depth".to_string(), "solusdt@depth".to_string()];
+        assert_eq!(
+            *recorder.reconnects.lock().unwrap(),
+            [resubscribed.clone(), resubscribed]
+        );
+        assert_eq!(*recorder.disconnects.lock().unwrap(), 2);
+        let mut states = Vec::new();
+        while let Ok(event) = feed_events.try_recv() {
+            if let BotEvent::FeedState { connected, .. } = event {
+                states.push(connected);
+            }
+        }
+        assert_eq!(states, [true, false, true, false, true]);
+    }
+
+    #[tokio::test]
+    async fn failing_to_connect_max_failures_times_raises_feed_down() {
+        // A port nothing listens on any more
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let (events, mut feed_events) = broadcast::channel(64);
+        let config = ReconnectConfig {
+            initial_backoff_ms: 1,
+            max_backoff_ms: 5,
+            max_failures: 3,
+            ..ReconnectConfig::default()
+        };
+        let supervisor = Arc::new(
+            WsSupervisor::new("mock", url, Arc::new(Recorder::default()), config)
+                .with_events(events),
+        );
+        let task = supervisor.clone().spawn();
+        let down = tokio::time::timeout(Duration::from_secs(5), feed_events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        task.abort();
+        assert!(matches!(down, BotEvent::FeedDown { failures: 3, .. }));
+    }
+}